@@ -0,0 +1,392 @@
+use crate::block::BlockType;
+use crate::events::{EventListener, GameEvent};
+
+/// Sample rate of the synthesized sound effects
+pub const SAMPLE_RATE: u32 = 22050;
+/// Blocks walked on the ground between two footsteps
+const STRIDE_LENGTH: f32 = 1.7;
+/// Footstep variations cycled through so repeated steps don't sound identical
+const STEP_VARIANTS: u32 = 4;
+/// Length of one ambience loop in seconds
+const AMBIENCE_LOOP: f32 = 8.0;
+/// Seconds per note of a music track
+const MUSIC_NOTE: f32 = 0.75;
+const MUSIC_NOTES: usize = 24;
+
+/// What a block sounds like when it is broken or placed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundMaterial {
+    Stone,
+    Wood,
+    Dirt,
+    Grass,
+    Sand,
+    Glass,
+    Foliage,
+}
+
+impl SoundMaterial {
+    pub fn of(block: BlockType) -> Self {
+        match block {
+            BlockType::Stone | BlockType::Furnace | BlockType::Obsidian | BlockType::EnchantingTable => SoundMaterial::Stone,
+            BlockType::Lever | BlockType::LeverOn | BlockType::PressurePlate | BlockType::PressedPlate => SoundMaterial::Stone,
+            BlockType::Button | BlockType::PressedButton => SoundMaterial::Stone,
+            BlockType::Wood | BlockType::Planks | BlockType::Chest | BlockType::Door | BlockType::OpenDoor => SoundMaterial::Wood,
+            BlockType::Bed => SoundMaterial::Wood,
+            BlockType::Dirt | BlockType::Clay => SoundMaterial::Dirt,
+            BlockType::Grass => SoundMaterial::Grass,
+            BlockType::Sand | BlockType::Snow | BlockType::Gravel => SoundMaterial::Sand,
+            BlockType::Glass | BlockType::Lamp | BlockType::LitLamp => SoundMaterial::Glass,
+            BlockType::Wire | BlockType::PoweredWire => SoundMaterial::Sand,
+            BlockType::Leaves | BlockType::Mushroom | BlockType::GlowingFungus => SoundMaterial::Foliage,
+            BlockType::Water | BlockType::Puddle | BlockType::Fire | BlockType::Air => SoundMaterial::Foliage,
+        }
+    }
+
+    /// Length in seconds, noise brightness (one-pole lowpass coefficient in (0, 1]),
+    /// and an optional resonant tone in Hz with its mix
+    fn timbre(self) -> (f32, f32, f32, f32) {
+        match self {
+            SoundMaterial::Stone => (0.14, 0.55, 0.0, 0.0),
+            SoundMaterial::Wood => (0.14, 0.25, 180.0, 0.5),
+            SoundMaterial::Dirt => (0.16, 0.15, 0.0, 0.0),
+            SoundMaterial::Grass => (0.18, 0.35, 0.0, 0.0),
+            SoundMaterial::Sand => (0.2, 0.7, 0.0, 0.0),
+            SoundMaterial::Glass => (0.25, 0.9, 2400.0, 0.6),
+            SoundMaterial::Foliage => (0.14, 0.8, 0.0, 0.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sound {
+    BlockBreak(SoundMaterial),
+    BlockPlace(SoundMaterial),
+    /// Footstep on a block; `variant` picks one of the material's step sounds
+    Step { material: SoundMaterial, variant: u32, muffled: bool },
+    UiClick,
+    /// An item going into the inventory off the ground
+    Pickup,
+    Jump,
+    Land,
+    Hurt,
+    /// Something biting at a fishing bobber
+    Splash,
+}
+
+impl Sound {
+    pub fn block_break(block: BlockType) -> Self {
+        Sound::BlockBreak(SoundMaterial::of(block))
+    }
+
+    pub fn block_place(block: BlockType) -> Self {
+        Sound::BlockPlace(SoundMaterial::of(block))
+    }
+}
+
+/// Deterministic white noise in [-1, 1]
+struct Noise(u32);
+
+impl Noise {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0 as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+}
+
+/// Render a sound effect as mono samples in [-1, 1] at `SAMPLE_RATE`.
+///
+/// There are no sound assets; every effect is a short burst of filtered noise
+/// and/or a decaying tone.
+#[cfg_attr(not(any(feature = "audio", test)), allow(dead_code))]
+pub fn synthesize(sound: Sound) -> Vec<f32> {
+    // (seconds, lowpass coefficient, tone Hz, tone mix, gain)
+    let mut variant = 0;
+    let (duration, brightness, tone, tone_mix, gain) = match sound {
+        Sound::BlockBreak(material) => {
+            let (duration, brightness, tone, tone_mix) = material.timbre();
+            (duration, brightness, tone, tone_mix, 0.6)
+        }
+        Sound::BlockPlace(material) => {
+            // Placing is a shorter, duller knock
+            let (duration, brightness, tone, tone_mix) = material.timbre();
+            (duration * 0.6, brightness * 0.7, tone * 0.8, tone_mix, 0.45)
+        }
+        Sound::Step { material, variant: v, muffled } => {
+            // Steps reuse the material's sound, shorter and quieter; sneaking also dulls it
+            variant = v % STEP_VARIANTS;
+            let (duration, brightness, tone, tone_mix) = material.timbre();
+            let pitch = 0.9 + 0.07 * variant as f32;
+            if muffled {
+                (duration * 0.5, brightness * 0.3, tone * pitch, tone_mix, 0.08)
+            } else {
+                (duration * 0.6, brightness, tone * pitch, tone_mix, 0.25)
+            }
+        }
+        Sound::UiClick => (0.03, 1.0, 1800.0, 0.8, 0.3),
+        Sound::Pickup => (0.07, 0.8, 1100.0, 0.9, 0.3),
+        Sound::Jump => (0.08, 0.3, 0.0, 0.0, 0.25),
+        Sound::Land => (0.1, 0.1, 110.0, 0.6, 0.5),
+        Sound::Hurt => (0.2, 0.4, 180.0, 0.5, 0.5),
+        Sound::Splash => (0.3, 0.6, 0.0, 0.0, 0.5),
+    };
+
+    let len = (duration * SAMPLE_RATE as f32) as usize;
+    // Fixed seed so the same sound always renders the same samples
+    let mut noise = Noise(0x9E37_79B9 ^ variant.wrapping_mul(0x85EB_CA6B));
+    let mut filtered = 0.0;
+    (0..len)
+        .map(|i| {
+            filtered += brightness * (noise.next() - filtered);
+
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let tone = (t * tone * std::f32::consts::TAU).sin();
+            let envelope = (-5.0 * t / duration).exp();
+            (filtered * (1.0 - tone_mix) + tone * tone_mix) * envelope * gain
+        })
+        .collect()
+}
+
+/// Looping background sound picked from the player's surroundings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Ambience {
+    /// Low rumble underground, out of the daylight
+    Cave,
+    /// Gusts on mountaintops
+    Wind,
+    /// Waves near the ocean
+    Ocean,
+    /// Rainfall, layered over the others while it rains
+    Rain,
+}
+
+impl Ambience {
+    pub const ALL: [Ambience; 4] = [Ambience::Cave, Ambience::Wind, Ambience::Ocean, Ambience::Rain];
+}
+
+/// Render one seamless loop of an ambience bed
+#[cfg_attr(not(any(feature = "audio", test)), allow(dead_code))]
+pub fn synthesize_ambience(ambience: Ambience) -> Vec<f32> {
+    use std::f32::consts::TAU;
+
+    // Lowpass coefficient, gain, and the slow swell (period in seconds, depth)
+    let (brightness, gain, swell_period, swell_depth) = match ambience {
+        Ambience::Cave => (0.01, 0.5, AMBIENCE_LOOP, 0.3),
+        Ambience::Wind => (0.04, 0.35, AMBIENCE_LOOP / 2.0, 0.8),
+        Ambience::Ocean => (0.15, 0.2, AMBIENCE_LOOP / 2.0, 0.9),
+        Ambience::Rain => (0.5, 0.15, AMBIENCE_LOOP / 4.0, 0.2),
+    };
+
+    let len = (AMBIENCE_LOOP * SAMPLE_RATE as f32) as usize;
+    // The tail is blended over the head so the loop has no seam
+    let blend = SAMPLE_RATE as usize / 20;
+    let mut noise = Noise(0x2545_F491);
+    let mut filtered = 0.0;
+    let samples: Vec<f32> = (0..len + blend)
+        .map(|i| {
+            filtered += brightness * (noise.next() - filtered);
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let swell = 1.0 - swell_depth * (0.5 + 0.5 * (t / swell_period * TAU).cos());
+            // Lowpassed noise is quiet; normalize roughly by the filter bandwidth
+            (filtered / brightness.sqrt() * 0.3 * swell * gain).clamp(-1.0, 1.0)
+        })
+        .collect();
+
+    let mut looped = samples[..len].to_vec();
+    for i in 0..blend {
+        let t = i as f32 / blend as f32;
+        looped[i] = looped[i] * t + samples[len + i] * (1.0 - t);
+    }
+    looped
+}
+
+/// Render a short, calm music track; `track` picks the melody
+#[cfg_attr(not(any(feature = "audio", test)), allow(dead_code))]
+pub fn synthesize_music(track: u32) -> Vec<f32> {
+    use std::f32::consts::TAU;
+
+    // Major pentatonic scale over two octaves, starting at A3
+    const SCALE: [f32; 10] = [220.0, 247.5, 277.2, 330.0, 370.0, 440.0, 495.0, 554.4, 660.0, 740.0];
+    let mut noise = Noise(0x68E3_1DA4 ^ track.wrapping_mul(0x9E37_79B9));
+    let mut degree = 5_i32;
+
+    let note_len = (MUSIC_NOTE * SAMPLE_RATE as f32) as usize;
+    let mut samples = Vec::with_capacity(note_len * MUSIC_NOTES);
+    for note in 0..MUSIC_NOTES {
+        // Wander by small steps; every fourth note rests
+        degree = (degree + (noise.next() * 2.5).round() as i32).clamp(0, SCALE.len() as i32 - 1);
+        let freq = SCALE[degree as usize];
+        let rest = note % 4 == 3;
+        samples.extend((0..note_len).map(|i| {
+            if rest {
+                return 0.0;
+            }
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let envelope = (t / 0.02).min(1.0) * (-3.0 * t / MUSIC_NOTE).exp();
+            let voice = (t * freq * TAU).sin() + 0.3 * (t * freq * 2.0 * TAU).sin();
+            voice * envelope * 0.15
+        }));
+    }
+    samples
+}
+
+/// Times footsteps to the distance walked, so faster movement steps more often
+pub struct Footsteps {
+    distance: f32,
+    steps: u32,
+}
+
+impl Footsteps {
+    pub fn new() -> Self {
+        Self { distance: 0.0, steps: 0 }
+    }
+
+    /// Add horizontal distance walked on the ground. Returns the footstep sound
+    /// when a step falls due, or None while airborne or between steps.
+    pub fn update(&mut self, distance: f32, underfoot: Option<BlockType>, sneaking: bool) -> Option<Sound> {
+        let Some(block) = underfoot else {
+            // Start the next walk with a fresh stride
+            self.distance = 0.0;
+            return None;
+        };
+        self.distance += distance;
+        if self.distance < STRIDE_LENGTH {
+            return None;
+        }
+        self.distance -= STRIDE_LENGTH;
+        self.steps = self.steps.wrapping_add(1);
+        Some(Sound::Step {
+            material: SoundMaterial::of(block),
+            variant: self.steps % STEP_VARIANTS,
+            muffled: sneaking,
+        })
+    }
+
+    /// How far into the current pair of footsteps the player is, from 0 to 1;
+    /// each footstep falls on 0 or 0.5
+    pub fn walk_cycle(&self) -> f32 {
+        ((self.steps % 2) as f32 + self.distance / STRIDE_LENGTH) / 2.0
+    }
+}
+
+impl Default for Footsteps {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Open output stream with one looping sink per ambience and a sink for music
+#[cfg(feature = "audio")]
+struct Output {
+    _stream: rodio::OutputStream,
+    handle: rodio::OutputStreamHandle,
+    ambience: Vec<(Ambience, rodio::Sink)>,
+    music: rodio::Sink,
+}
+
+#[cfg(feature = "audio")]
+impl Output {
+    fn open() -> Result<Self, Box<dyn std::error::Error>> {
+        use rodio::Source;
+
+        let (stream, handle) = rodio::OutputStream::try_default()?;
+        let mut ambience = Vec::new();
+        for kind in Ambience::ALL {
+            let sink = rodio::Sink::try_new(&handle)?;
+            sink.set_volume(0.0);
+            sink.append(rodio::buffer::SamplesBuffer::new(1, SAMPLE_RATE, synthesize_ambience(kind)).repeat_infinite());
+            ambience.push((kind, sink));
+        }
+        let music = rodio::Sink::try_new(&handle)?;
+        Ok(Self {
+            _stream: stream,
+            handle,
+            ambience,
+            music,
+        })
+    }
+}
+
+/// Plays sound effects, ambience and music at the master volume. Without the
+/// `audio` feature, or when no output device is available, all sound is dropped.
+pub struct Audio {
+    volume: f32,
+    #[cfg(feature = "audio")]
+    output: Option<Output>,
+}
+
+impl Audio {
+    pub fn new(volume: f32) -> Self {
+        Self {
+            volume: volume.clamp(0.0, 1.0),
+            #[cfg(feature = "audio")]
+            output: Output::open().map_err(|e| log::warn!("Audio disabled: {}", e)).ok(),
+        }
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+
+    pub fn play(&self, sound: Sound) {
+        if self.volume <= 0.0 {
+            return;
+        }
+        #[cfg(feature = "audio")]
+        if let Some(output) = &self.output {
+            use rodio::Source;
+            let source = rodio::buffer::SamplesBuffer::new(1, SAMPLE_RATE, synthesize(sound)).amplify(self.volume);
+            output.handle.play_raw(source).ok();
+        }
+        #[cfg(not(feature = "audio"))]
+        let _ = sound;
+    }
+
+    /// Set the loudness of an ambience loop in [0, 1], before the master volume
+    pub fn set_ambience_level(&self, ambience: Ambience, level: f32) {
+        #[cfg(feature = "audio")]
+        if let Some(output) = &self.output {
+            for (kind, sink) in &output.ambience {
+                if *kind == ambience {
+                    sink.set_volume(level * self.volume);
+                }
+            }
+        }
+        #[cfg(not(feature = "audio"))]
+        let _ = (ambience, level);
+    }
+
+    /// Start a music track unless one is already playing
+    pub fn play_music(&self, track: u32) {
+        #[cfg(feature = "audio")]
+        if let Some(output) = &self.output {
+            if output.music.empty() {
+                output.music.set_volume(self.volume);
+                output.music.append(rodio::buffer::SamplesBuffer::new(1, SAMPLE_RATE, synthesize_music(track)));
+            }
+        }
+        #[cfg(not(feature = "audio"))]
+        let _ = track;
+    }
+
+    pub fn is_music_playing(&self) -> bool {
+        #[cfg(feature = "audio")]
+        if let Some(output) = &self.output {
+            return !output.music.empty();
+        }
+        false
+    }
+}
+
+impl EventListener for Audio {
+    fn on_event(&mut self, event: &GameEvent) {
+        match *event {
+            GameEvent::BlockBroken { block, .. } => self.play(Sound::block_break(block)),
+            GameEvent::BlockPlaced { block, .. } => self.play(Sound::block_place(block)),
+            GameEvent::PlayerDamaged { .. } => self.play(Sound::Hurt),
+            GameEvent::ChunkLoaded { .. } | GameEvent::ItemPickedUp { .. } => {}
+        }
+    }
+}
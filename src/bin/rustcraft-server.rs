@@ -0,0 +1,12 @@
+//! Dedicated server: the world simulation without a window, taking console
+//! commands on stdin. Same options as the game; `--headless` is implied.
+
+use clap::Parser;
+use rustcraft::cli::Args;
+use rustcraft::{crash, headless, logging, storage};
+
+fn main() {
+    logging::init();
+    let crash = crash::install();
+    headless::run(&Args::parse(), storage::platform(), &crash);
+}
@@ -1,26 +1,401 @@
+use crate::item::{ToolKind, ToolTier};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// Brightest block light, that of fire
+pub const MAX_LIGHT: u8 = 14;
+
+/// Blocks are saved and sent by their number, which never changes; the
+/// numbers missing were items before `Item` took them over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "u32", try_from = "u32")]
 pub enum BlockType {
-    Air,
-    Dirt,
-    Sand,
-    Grass,
-    Wood,
-    Leaves,
-    Planks,
-    Glass,
-    Water,
-    Stone,
+    Air = 0,
+    Dirt = 1,
+    Sand = 2,
+    Grass = 3,
+    Wood = 4,
+    Leaves = 5,
+    Planks = 6,
+    Glass = 7,
+    Water = 8,
+    Stone = 9,
+    Chest = 10,
+    Furnace = 11,
+    /// Thin layer left by snowfall in cold places
+    Snow = 12,
+    /// Shallow water left by rain, dries up in clear weather
+    Puddle = 13,
+    /// Burns flammable blocks next to it away, spreading as it goes
+    Fire = 17,
+    /// Loose stones patching ocean floors; falls like sand
+    Gravel = 19,
+    /// Soft grey earth patching ocean floors
+    Clay = 20,
+    /// Grows on the ground in dark places
+    Mushroom = 21,
+    /// Rarer dark-loving fungus giving off a faint light
+    GlowingFungus = 22,
+    /// Switched on and off with right click; see `circuit`
+    Lever = 23,
+    /// A switched on lever, powering the blocks next to it
+    LeverOn = 24,
+    /// Carries power from a lever, a level less every block
+    Wire = 25,
+    PoweredWire = 26,
+    /// Lights up while powered
+    Lamp = 27,
+    LitLamp = 28,
+    /// Opens while powered, or with right click, together with the doors
+    /// stacked on it
+    Door = 29,
+    OpenDoor = 30,
+    /// Very hard, and stands up to explosions
+    Obsidian = 31,
+    /// Powers the blocks next to it while a player, item or boat is on it
+    PressurePlate = 32,
+    PressedPlate = 33,
+    /// Pressed with right click, powering the blocks next to it for a second
+    Button = 34,
+    PressedButton = 35,
+    /// Spends experience levels on enchanting the tool put into it; see
+    /// `enchanting`
+    EnchantingTable = 36,
+    /// Right click at night to sleep in; see `sleep`
+    Bed = 37,
+}
+
+impl From<BlockType> for u32 {
+    fn from(block: BlockType) -> Self {
+        block as u32
+    }
+}
+
+impl TryFrom<u32> for BlockType {
+    type Error = String;
+
+    fn try_from(id: u32) -> Result<Self, Self::Error> {
+        BlockType::ALL.into_iter().find(|block| *block as u32 == id).ok_or_else(|| format!("no block numbered {}", id))
+    }
 }
 
 impl BlockType {
+    pub const ALL: [BlockType; 34] = [
+        BlockType::Air,
+        BlockType::Dirt,
+        BlockType::Sand,
+        BlockType::Grass,
+        BlockType::Wood,
+        BlockType::Leaves,
+        BlockType::Planks,
+        BlockType::Glass,
+        BlockType::Water,
+        BlockType::Stone,
+        BlockType::Chest,
+        BlockType::Furnace,
+        BlockType::Snow,
+        BlockType::Puddle,
+        BlockType::Fire,
+        BlockType::Gravel,
+        BlockType::Clay,
+        BlockType::Mushroom,
+        BlockType::GlowingFungus,
+        BlockType::Lever,
+        BlockType::LeverOn,
+        BlockType::Wire,
+        BlockType::PoweredWire,
+        BlockType::Lamp,
+        BlockType::LitLamp,
+        BlockType::Door,
+        BlockType::OpenDoor,
+        BlockType::Obsidian,
+        BlockType::PressurePlate,
+        BlockType::PressedPlate,
+        BlockType::Button,
+        BlockType::PressedButton,
+        BlockType::EnchantingTable,
+        BlockType::Bed,
+    ];
+
+    /// Lowercase identifier used in commands and configs
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlockType::Air => "air",
+            BlockType::Dirt => "dirt",
+            BlockType::Sand => "sand",
+            BlockType::Grass => "grass",
+            BlockType::Wood => "wood",
+            BlockType::Leaves => "leaves",
+            BlockType::Planks => "planks",
+            BlockType::Glass => "glass",
+            BlockType::Water => "water",
+            BlockType::Stone => "stone",
+            BlockType::Chest => "chest",
+            BlockType::Furnace => "furnace",
+            BlockType::Snow => "snow",
+            BlockType::Puddle => "puddle",
+            BlockType::Fire => "fire",
+            BlockType::Gravel => "gravel",
+            BlockType::Clay => "clay",
+            BlockType::Mushroom => "mushroom",
+            BlockType::GlowingFungus => "glowing_fungus",
+            BlockType::Lever => "lever",
+            BlockType::LeverOn => "lever_on",
+            BlockType::Wire => "wire",
+            BlockType::PoweredWire => "powered_wire",
+            BlockType::Lamp => "lamp",
+            BlockType::LitLamp => "lit_lamp",
+            BlockType::Door => "door",
+            BlockType::OpenDoor => "open_door",
+            BlockType::Obsidian => "obsidian",
+            BlockType::PressurePlate => "pressure_plate",
+            BlockType::PressedPlate => "pressed_plate",
+            BlockType::Button => "button",
+            BlockType::PressedButton => "pressed_button",
+            BlockType::EnchantingTable => "enchanting_table",
+            BlockType::Bed => "bed",
+        }
+    }
+
+    /// Look up a block by its identifier (case-insensitive)
+    pub fn from_name(name: &str) -> Option<BlockType> {
+        match name.to_ascii_lowercase().as_str() {
+            "air" => Some(BlockType::Air),
+            "dirt" => Some(BlockType::Dirt),
+            "sand" => Some(BlockType::Sand),
+            "grass" => Some(BlockType::Grass),
+            "wood" => Some(BlockType::Wood),
+            "leaves" => Some(BlockType::Leaves),
+            "planks" => Some(BlockType::Planks),
+            "glass" => Some(BlockType::Glass),
+            "water" => Some(BlockType::Water),
+            "stone" => Some(BlockType::Stone),
+            "chest" => Some(BlockType::Chest),
+            "furnace" => Some(BlockType::Furnace),
+            "snow" => Some(BlockType::Snow),
+            "puddle" => Some(BlockType::Puddle),
+            "fire" => Some(BlockType::Fire),
+            "gravel" => Some(BlockType::Gravel),
+            "clay" => Some(BlockType::Clay),
+            "mushroom" => Some(BlockType::Mushroom),
+            "glowing_fungus" => Some(BlockType::GlowingFungus),
+            "lever" => Some(BlockType::Lever),
+            "lever_on" => Some(BlockType::LeverOn),
+            "wire" => Some(BlockType::Wire),
+            "powered_wire" => Some(BlockType::PoweredWire),
+            "lamp" => Some(BlockType::Lamp),
+            "lit_lamp" => Some(BlockType::LitLamp),
+            "door" => Some(BlockType::Door),
+            "open_door" => Some(BlockType::OpenDoor),
+            "obsidian" => Some(BlockType::Obsidian),
+            "pressure_plate" => Some(BlockType::PressurePlate),
+            "pressed_plate" => Some(BlockType::PressedPlate),
+            "button" => Some(BlockType::Button),
+            "pressed_button" => Some(BlockType::PressedButton),
+            "enchanting_table" => Some(BlockType::EnchantingTable),
+            "bed" => Some(BlockType::Bed),
+            _ => None,
+        }
+    }
+
     pub fn is_solid(&self) -> bool {
         !matches!(self, BlockType::Air)
     }
 
+    pub fn is_liquid(&self) -> bool {
+        matches!(self, BlockType::Water | BlockType::Puddle)
+    }
+
+    /// Thin layers lying on the block below, which nothing collides with
+    pub fn is_layer(&self) -> bool {
+        matches!(
+            self,
+            BlockType::Snow
+                | BlockType::Puddle
+                | BlockType::Wire
+                | BlockType::PoweredWire
+                | BlockType::PressurePlate
+                | BlockType::PressedPlate
+                | BlockType::Button
+                | BlockType::PressedButton
+        )
+    }
+
+    /// Height of the block within its cell, as drawn; water under more water
+    /// fills its cell
+    pub fn height(&self) -> f32 {
+        match self {
+            BlockType::Snow | BlockType::Button => 0.125,
+            BlockType::Puddle | BlockType::Wire | BlockType::PoweredWire => 0.0625,
+            BlockType::PressurePlate | BlockType::PressedButton => 0.0625,
+            BlockType::PressedPlate => 0.03125,
+            BlockType::Water => 0.875,
+            BlockType::Mushroom => 0.5,
+            BlockType::GlowingFungus => 0.625,
+            _ => 1.0,
+        }
+    }
+
+    /// Blocks that fill their whole cell; everything but air, the layers,
+    /// plants, fire and open doors
+    pub fn is_full_cube(&self) -> bool {
+        self.is_solid() && !self.is_layer() && !self.is_plant() && !matches!(self, BlockType::Fire | BlockType::OpenDoor)
+    }
+
+    /// Small plants, drawn as two crossed quads and popping off when the
+    /// ground under them goes
+    pub fn is_plant(&self) -> bool {
+        matches!(self, BlockType::Mushroom | BlockType::GlowingFungus)
+    }
+
+    /// Blocks that only go on top of a full block and not into water: the
+    /// plants, thin layers, levers and doors
+    pub fn needs_floor(&self) -> bool {
+        self.is_plant()
+            || self.is_layer()
+            || matches!(self, BlockType::Lever | BlockType::LeverOn | BlockType::Door | BlockType::OpenDoor)
+    }
+
+    /// Blocks that fire spreads to and burns away
+    pub fn is_flammable(&self) -> bool {
+        matches!(self, BlockType::Wood | BlockType::Leaves | BlockType::Planks)
+    }
+
+    /// Block light the block gives off, from 0 (none) to `MAX_LIGHT`
+    pub fn light_emission(&self) -> u8 {
+        match self {
+            BlockType::Fire => MAX_LIGHT,
+            BlockType::GlowingFungus => 5,
+            BlockType::LitLamp => MAX_LIGHT,
+            _ => 0,
+        }
+    }
+
+    /// What breaking the block gives the player; fire gives nothing, and
+    /// switched on or powered blocks give their plain selves
+    pub fn drop(&self) -> Option<BlockType> {
+        match self {
+            BlockType::Fire => None,
+            BlockType::LeverOn => Some(BlockType::Lever),
+            BlockType::PoweredWire => Some(BlockType::Wire),
+            BlockType::LitLamp => Some(BlockType::Lamp),
+            BlockType::OpenDoor => Some(BlockType::Door),
+            BlockType::PressedPlate => Some(BlockType::PressurePlate),
+            BlockType::PressedButton => Some(BlockType::Button),
+            block => Some(*block),
+        }
+    }
+
+    /// Seconds a bare hand takes to break the block, before the penalty for
+    /// blocks that need a tool; 0 breaks at once
+    pub fn hardness(&self) -> f32 {
+        match self {
+            BlockType::Air | BlockType::Water | BlockType::Puddle | BlockType::Fire => 0.0,
+            BlockType::Mushroom | BlockType::GlowingFungus => 0.0,
+            BlockType::Wire | BlockType::PoweredWire => 0.0,
+            BlockType::Snow => 0.1,
+            BlockType::Leaves | BlockType::Bed => 0.2,
+            BlockType::Glass | BlockType::Lamp | BlockType::LitLamp => 0.3,
+            BlockType::Lever | BlockType::LeverOn => 0.5,
+            BlockType::PressurePlate | BlockType::PressedPlate | BlockType::Button | BlockType::PressedButton => 0.5,
+            BlockType::Dirt | BlockType::Sand => 0.5,
+            BlockType::Grass | BlockType::Gravel | BlockType::Clay => 0.6,
+            BlockType::Stone => 1.5,
+            BlockType::Wood | BlockType::Planks | BlockType::Door | BlockType::OpenDoor => 2.0,
+            BlockType::Chest => 2.5,
+            BlockType::Furnace => 3.5,
+            BlockType::EnchantingTable => 5.0,
+            BlockType::Obsidian => 25.0,
+        }
+    }
+
+    /// How much the block holds back an explosion, see `explosion`: a ray
+    /// through it loses `(resistance + 0.3) * 0.3` power per step
+    pub fn blast_resistance(&self) -> f32 {
+        match self {
+            BlockType::Air | BlockType::Fire => 0.0,
+            BlockType::Mushroom | BlockType::GlowingFungus | BlockType::Wire | BlockType::PoweredWire => 0.0,
+            BlockType::Snow => 0.1,
+            BlockType::Leaves | BlockType::Bed => 0.2,
+            BlockType::Glass | BlockType::Lamp | BlockType::LitLamp => 0.3,
+            BlockType::Lever | BlockType::LeverOn => 0.5,
+            BlockType::PressurePlate | BlockType::PressedPlate | BlockType::Button | BlockType::PressedButton => 0.5,
+            BlockType::Dirt | BlockType::Sand => 0.5,
+            BlockType::Grass | BlockType::Gravel | BlockType::Clay => 0.6,
+            BlockType::Wood => 2.0,
+            BlockType::Chest => 2.5,
+            BlockType::Planks | BlockType::Door | BlockType::OpenDoor => 3.0,
+            BlockType::Furnace => 3.5,
+            BlockType::Stone => 6.0,
+            BlockType::Water | BlockType::Puddle => 100.0,
+            BlockType::Obsidian | BlockType::EnchantingTable => 1200.0,
+        }
+    }
+
+    /// The kind of tool that breaks the block faster, if any
+    pub fn preferred_tool(&self) -> Option<ToolKind> {
+        match self {
+            BlockType::Stone | BlockType::Furnace | BlockType::Obsidian | BlockType::EnchantingTable => Some(ToolKind::Pickaxe),
+            BlockType::Dirt | BlockType::Grass | BlockType::Sand | BlockType::Gravel | BlockType::Clay | BlockType::Snow => {
+                Some(ToolKind::Shovel)
+            }
+            BlockType::Wood | BlockType::Planks | BlockType::Chest | BlockType::Door | BlockType::OpenDoor => Some(ToolKind::Axe),
+            _ => None,
+        }
+    }
+
+    /// Lowest tier of the preferred tool the block needs to drop anything;
+    /// None drops it whatever breaks it
+    pub fn required_tier(&self) -> Option<ToolTier> {
+        match self {
+            BlockType::Stone | BlockType::Furnace | BlockType::EnchantingTable => Some(ToolTier::Wood),
+            BlockType::Obsidian => Some(ToolTier::Stone),
+            _ => None,
+        }
+    }
+
+    /// Blocks that fall when nothing is below them
+    pub fn has_gravity(&self) -> bool {
+        matches!(self, BlockType::Sand | BlockType::Gravel)
+    }
+
+    /// Blocks with a block entity holding items, opened with right click
+    pub fn is_container(&self) -> bool {
+        matches!(self, BlockType::Chest | BlockType::Furnace | BlockType::EnchantingTable)
+    }
+
+    /// Blocks right click switches over: levers, doors and buttons
+    pub fn is_switch(&self) -> bool {
+        matches!(
+            self,
+            BlockType::Lever
+                | BlockType::LeverOn
+                | BlockType::Door
+                | BlockType::OpenDoor
+                | BlockType::Button
+                | BlockType::PressedButton
+        )
+    }
+
     pub fn is_transparent(&self) -> bool {
-        matches!(self, BlockType::Air | BlockType::Glass | BlockType::Leaves | BlockType::Water)
+        matches!(
+            self,
+            BlockType::Air
+                | BlockType::Glass
+                | BlockType::Leaves
+                | BlockType::Water
+                | BlockType::Snow
+                | BlockType::Puddle
+                | BlockType::Fire
+                | BlockType::Mushroom
+                | BlockType::GlowingFungus
+                | BlockType::Wire
+                | BlockType::PoweredWire
+                | BlockType::OpenDoor
+                | BlockType::PressurePlate
+                | BlockType::PressedPlate
+                | BlockType::Button
+                | BlockType::PressedButton
+        )
     }
 
     pub fn get_color(&self) -> [f32; 3] {
@@ -35,9 +410,31 @@ impl BlockType {
             BlockType::Glass => [0.8, 0.9, 1.0],
             BlockType::Water => [0.0, 0.4, 0.8],
             BlockType::Stone => [0.5, 0.5, 0.5],
+            BlockType::Chest => [0.85, 0.6, 0.3],
+            BlockType::Furnace => [0.45, 0.42, 0.4],
+            BlockType::Snow => [0.95, 0.97, 1.0],
+            BlockType::Puddle => [0.2, 0.45, 0.75],
+            BlockType::Fire => [1.0, 0.55, 0.1],
+            BlockType::Gravel => [0.52, 0.49, 0.47],
+            BlockType::Clay => [0.62, 0.65, 0.72],
+            BlockType::Mushroom => [0.72, 0.22, 0.16],
+            BlockType::GlowingFungus => [0.35, 0.95, 0.75],
+            BlockType::Lever => [0.45, 0.38, 0.3],
+            BlockType::LeverOn => [0.85, 0.7, 0.35],
+            BlockType::Wire => [0.4, 0.05, 0.05],
+            BlockType::PoweredWire => [0.95, 0.15, 0.1],
+            BlockType::Lamp => [0.45, 0.35, 0.2],
+            BlockType::LitLamp => [1.0, 0.85, 0.5],
+            BlockType::Door | BlockType::OpenDoor => [0.6, 0.42, 0.2],
+            BlockType::Obsidian => [0.16, 0.1, 0.24],
+            BlockType::PressurePlate | BlockType::PressedPlate => [0.58, 0.58, 0.56],
+            BlockType::Button | BlockType::PressedButton => [0.42, 0.4, 0.38],
+            BlockType::EnchantingTable => [0.45, 0.12, 0.2],
+            BlockType::Bed => [0.7, 0.13, 0.13],
         }
     }
 
+    #[allow(dead_code)]
     pub fn get_texture_path(&self) -> Option<&'static str> {
         match self {
             BlockType::Air => None,
@@ -50,12 +447,24 @@ impl BlockType {
             BlockType::Glass => Some("textures/glass.png"),
             BlockType::Water => Some("textures/water.png"),
             BlockType::Stone => Some("textures/stone.png"),
+            BlockType::Chest => Some("textures/planks.png"),
+            BlockType::Furnace => Some("textures/stone.png"),
+            BlockType::Snow => None,
+            BlockType::Puddle => Some("textures/water.png"),
+            BlockType::Fire => None,
+            BlockType::Gravel | BlockType::Clay => None,
+            BlockType::Mushroom | BlockType::GlowingFungus => None,
+            BlockType::Lever | BlockType::LeverOn | BlockType::Wire | BlockType::PoweredWire => None,
+            BlockType::Lamp | BlockType::LitLamp => None,
+            BlockType::Door | BlockType::OpenDoor => Some("textures/planks.png"),
+            BlockType::Obsidian | BlockType::EnchantingTable => Some("textures/stone.png"),
+            BlockType::PressurePlate | BlockType::PressedPlate | BlockType::Button | BlockType::PressedButton => None,
+            BlockType::Bed => None,
         }
     }
 
-    /// Return (col, row) coordinates of the block's tile inside the atlas.
-    /// (0,0) is the left-bottom tile (adjust to your atlas orientation).
-    /// Update these indices to match your atlas layout.
+    /// (col, row) of the block's tile in the built-in `textures/atlas.png`.
+    /// Resource packs are repacked into an atlas of their own, see `AtlasLayout`.
     pub fn atlas_coords(&self) -> Option<(u32, u32)> {
         match self {
             BlockType::Air => None,
@@ -68,6 +477,17 @@ impl BlockType {
             BlockType::Glass => Some((6, 0)),
             BlockType::Water => Some((7, 0)),
             BlockType::Stone => Some((8, 0)),
+            // No dedicated tiles yet: tinted planks and stone
+            BlockType::Chest | BlockType::Door | BlockType::OpenDoor => Some((5, 0)),
+            BlockType::Furnace | BlockType::Obsidian | BlockType::EnchantingTable => Some((8, 0)),
+            // Plain white tile tinted by the block color; puddles reuse the water
+            BlockType::Snow | BlockType::Fire | BlockType::Gravel | BlockType::Clay => None,
+            BlockType::Mushroom | BlockType::GlowingFungus => None,
+            BlockType::Lever | BlockType::LeverOn | BlockType::Wire | BlockType::PoweredWire => None,
+            BlockType::Lamp | BlockType::LitLamp => None,
+            BlockType::PressurePlate | BlockType::PressedPlate | BlockType::Button | BlockType::PressedButton => None,
+            BlockType::Bed => None,
+            BlockType::Puddle => Some((7, 0)),
         }
     }
 }
@@ -0,0 +1,295 @@
+use crate::block::BlockType;
+use crate::item::Item;
+use crate::inventory::{click_stack, insert_stack, right_click_stack, Inventory, ItemStack, SlotClick, SlotId};
+use serde::{Deserialize, Serialize};
+
+pub const CHEST_SLOTS: usize = 27;
+
+// Furnace slot indices
+pub const FURNACE_INPUT: usize = 0;
+pub const FURNACE_FUEL: usize = 1;
+pub const FURNACE_OUTPUT: usize = 2;
+
+/// Seconds to smelt one item
+const COOK_TIME: f32 = 10.0;
+
+/// Item the furnace turns an input item into
+pub fn smelting_result(item: Item) -> Option<Item> {
+    match item {
+        Item::Block(BlockType::Sand) => Some(Item::Block(BlockType::Glass)),
+        _ => None,
+    }
+}
+
+/// Seconds one fuel item keeps the furnace burning
+pub fn fuel_time(item: Item) -> Option<f32> {
+    match item {
+        Item::Block(BlockType::Wood | BlockType::Planks) => Some(15.0),
+        Item::Stick => Some(5.0),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerKind {
+    Chest,
+    Furnace,
+    EnchantingTable,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chest {
+    pub slots: [Option<ItemStack>; CHEST_SLOTS],
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Furnace {
+    /// Input, fuel and output, indexed by FURNACE_INPUT / FURNACE_FUEL / FURNACE_OUTPUT
+    pub slots: [Option<ItemStack>; 3],
+    /// Seconds of burning left from the current fuel item
+    pub burn_time: f32,
+    /// Total burn time of the current fuel item, for the flame indicator
+    pub burn_duration: f32,
+    /// Seconds the current input item has been cooking
+    pub cook_time: f32,
+}
+
+impl Furnace {
+    pub fn new() -> Self {
+        Self {
+            slots: [None; 3],
+            burn_time: 0.0,
+            burn_duration: 0.0,
+            cook_time: 0.0,
+        }
+    }
+
+    pub fn is_burning(&self) -> bool {
+        self.burn_time > 0.0
+    }
+
+    /// Remaining fuel of the current item in [0, 1]
+    pub fn burn_fraction(&self) -> f32 {
+        if self.burn_duration > 0.0 {
+            (self.burn_time / self.burn_duration).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Smelting progress of the current item in [0, 1]
+    pub fn cook_fraction(&self) -> f32 {
+        (self.cook_time / COOK_TIME).clamp(0.0, 1.0)
+    }
+
+    /// Whether the input can be smelted and the result fits into the output slot
+    fn can_smelt(&self) -> bool {
+        let Some(result) = self.slots[FURNACE_INPUT].and_then(|s| smelting_result(s.item)) else {
+            return false;
+        };
+        match self.slots[FURNACE_OUTPUT] {
+            None => true,
+            Some(out) => out.item == result && out.can_add(1),
+        }
+    }
+
+    /// Burn fuel and smelt for `delta_time` seconds. Returns true if anything changed.
+    pub fn update(&mut self, delta_time: f32) -> bool {
+        let was_burning = self.is_burning();
+        let can_smelt = self.can_smelt();
+
+        if self.is_burning() {
+            self.burn_time = (self.burn_time - delta_time).max(0.0);
+        }
+
+        // Light the next fuel item only when there is something to smelt
+        if !self.is_burning() && can_smelt {
+            if let Some(fuel) = self.slots[FURNACE_FUEL].as_mut() {
+                if let Some(time) = fuel_time(fuel.item) {
+                    fuel.count -= 1;
+                    if fuel.count == 0 {
+                        self.slots[FURNACE_FUEL] = None;
+                    }
+                    self.burn_time = time;
+                    self.burn_duration = time;
+                }
+            }
+        }
+
+        if self.is_burning() && can_smelt {
+            self.cook_time += delta_time;
+            if self.cook_time >= COOK_TIME {
+                self.cook_time = 0.0;
+                self.finish_item();
+            }
+        } else {
+            self.cook_time = 0.0;
+        }
+
+        was_burning || self.is_burning()
+    }
+
+    /// Move one smelted item from the input to the output
+    fn finish_item(&mut self) {
+        let Some(input) = self.slots[FURNACE_INPUT].as_mut() else {
+            return;
+        };
+        let Some(result) = smelting_result(input.item) else {
+            return;
+        };
+        input.count -= 1;
+        if input.count == 0 {
+            self.slots[FURNACE_INPUT] = None;
+        }
+        match self.slots[FURNACE_OUTPUT].as_mut() {
+            Some(out) => out.count += 1,
+            None => self.slots[FURNACE_OUTPUT] = Some(ItemStack::new(result, 1)),
+        }
+    }
+}
+
+impl Default for Furnace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Holds the tool being enchanted; see `enchanting`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnchantingTable {
+    pub slots: [Option<ItemStack>; 1],
+}
+
+/// Extra per-block state stored by the world, keyed by block position
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BlockEntity {
+    /// Boxed, as its slots make it many times the size of the others
+    Chest(Box<Chest>),
+    Furnace(Furnace),
+    EnchantingTable(EnchantingTable),
+}
+
+impl BlockEntity {
+    /// Fresh block entity for a newly placed block, if the block has one
+    pub fn for_block(block: BlockType) -> Option<Self> {
+        match block {
+            BlockType::Chest => Some(BlockEntity::Chest(Box::new(Chest { slots: [None; CHEST_SLOTS] }))),
+            BlockType::Furnace => Some(BlockEntity::Furnace(Furnace::new())),
+            BlockType::EnchantingTable => Some(BlockEntity::EnchantingTable(EnchantingTable { slots: [None] })),
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> ContainerKind {
+        match self {
+            BlockEntity::Chest(_) => ContainerKind::Chest,
+            BlockEntity::Furnace(_) => ContainerKind::Furnace,
+            BlockEntity::EnchantingTable(_) => ContainerKind::EnchantingTable,
+        }
+    }
+
+    pub fn slots(&self) -> &[Option<ItemStack>] {
+        match self {
+            BlockEntity::Chest(chest) => &chest.slots,
+            BlockEntity::Furnace(furnace) => &furnace.slots,
+            BlockEntity::EnchantingTable(table) => &table.slots,
+        }
+    }
+
+    pub fn slots_mut(&mut self) -> &mut [Option<ItemStack>] {
+        match self {
+            BlockEntity::Chest(chest) => &mut chest.slots,
+            BlockEntity::Furnace(furnace) => &mut furnace.slots,
+            BlockEntity::EnchantingTable(table) => &mut table.slots,
+        }
+    }
+
+    /// Whether `block` may be put into slot `idx` by the player
+    fn accepts(&self, idx: usize, item: Item) -> bool {
+        match self {
+            BlockEntity::Chest(_) => true,
+            BlockEntity::Furnace(_) => match idx {
+                FURNACE_FUEL => fuel_time(item).is_some(),
+                FURNACE_OUTPUT => false,
+                _ => true,
+            },
+            // Only tools take enchantments
+            BlockEntity::EnchantingTable(_) => item.durability().is_some(),
+        }
+    }
+
+    /// Handle a click on one of the container's slots, using the inventory's held stack
+    pub fn click_slot(&mut self, idx: usize, click: SlotClick, inventory: &mut Inventory) {
+        if idx >= self.slots().len() {
+            return;
+        }
+
+        if click == SlotClick::ShiftLeft {
+            // Move the whole stack into the player's inventory; what doesn't fit stays
+            if let Some(stack) = self.slots_mut()[idx].take() {
+                let left = inventory.insert(stack);
+                if left > 0 {
+                    self.slots_mut()[idx] = Some(stack.with_count(left));
+                }
+            }
+            return;
+        }
+
+        let mut held = inventory.held.take();
+        let accepts = held.is_none_or(|h| self.accepts(idx, h.item));
+        let slot = &mut self.slots_mut()[idx];
+
+        if accepts {
+            match click {
+                SlotClick::Right => right_click_stack(slot, &mut held),
+                _ => click_stack(slot, &mut held),
+            }
+        } else if let (Some(stack), Some(h)) = (slot.as_mut(), held.as_mut()) {
+            // Slots that refuse the held item (e.g. furnace output) can still be emptied
+            // into a matching held stack
+            if stack.item == h.item && h.count + stack.count <= h.max_stack_size() {
+                h.count += stack.count;
+                *slot = None;
+            }
+        }
+        inventory.held = held;
+    }
+
+    /// Shift-click on a player slot while this container is open: move the stack in
+    pub fn quick_move_from(&mut self, inventory: &mut Inventory, id: SlotId) {
+        let Some(stack) = inventory.take_slot(id) else {
+            return;
+        };
+
+        let left = match self {
+            BlockEntity::Chest(chest) => insert_stack(&mut chest.slots, stack),
+            BlockEntity::Furnace(furnace) => {
+                let target = if smelting_result(stack.item).is_some() {
+                    Some(FURNACE_INPUT)
+                } else if fuel_time(stack.item).is_some() {
+                    Some(FURNACE_FUEL)
+                } else {
+                    None
+                };
+                match target {
+                    Some(target) => insert_stack(&mut furnace.slots[target..=target], stack),
+                    None => stack.count,
+                }
+            }
+            BlockEntity::EnchantingTable(table) if stack.item.durability().is_some() => insert_stack(&mut table.slots, stack),
+            BlockEntity::EnchantingTable(_) => stack.count,
+        };
+
+        if left > 0 {
+            inventory.put_slot(id, Some(stack.with_count(left)));
+        }
+    }
+
+    /// Advance furnaces; returns true if the entity's visible state changed
+    pub fn update(&mut self, delta_time: f32) -> bool {
+        match self {
+            BlockEntity::Chest(_) | BlockEntity::EnchantingTable(_) => false,
+            BlockEntity::Furnace(furnace) => furnace.update(delta_time),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Boats, placed on water (or land) with the boat item and ridden with a
+//! right click. The rider paddles with W/S and turns with A/D; the boat keeps
+//! its momentum on water, slowly losing it, and grinds to a halt on land. The
+//! camera turns with the boat, and sneaking gets off. Boats float when left
+//! alone, and a left click breaks one back into the item. They are saved with
+//! the world, the one being ridden too, which stays in `World::boats`.
+
+use crate::block::BlockType;
+use crate::debug_render::DebugVertex;
+use crate::physics::Aabb;
+use crate::remote_players::add_cuboid;
+use crate::world::World;
+use glam::{Mat3, Vec3};
+use serde::{Deserialize, Serialize};
+
+/// Half the side of the square a boat collides as
+const HALF_WIDTH: f32 = 0.7;
+/// Height of the hull
+pub const HEIGHT: f32 = 0.55;
+/// How deep a floating boat sits in the water
+const DRAFT: f32 = 0.25;
+/// Paddling speed gained per second, in blocks per second
+const ACCELERATION: f32 = 6.0;
+/// Turning speed, in radians per second
+const TURN_SPEED: f32 = 2.2;
+/// Share of horizontal speed kept per second on water; with the acceleration
+/// above, a boat tops out at about 6.5 blocks per second
+const WATER_DRAG: f32 = 0.4;
+/// Share of horizontal speed kept per second on land
+const LAND_DRAG: f32 = 0.0005;
+/// How much of the paddling still moves a beached boat
+const LAND_PADDLING: f32 = 0.1;
+/// Height of a bank a moving boat runs up onto, beaching it
+const STEP_HEIGHT: f32 = 0.5;
+const GRAVITY: f32 = -20.0;
+/// Upwards acceleration of a boat under the surface
+const BUOYANCY: f32 = 15.0;
+/// Share of vertical speed kept per second in water, so the boat settles
+const WATER_DAMPING: f32 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Boat {
+    /// Middle of the bottom of the hull
+    pub position: Vec3,
+    pub velocity: Vec3,
+    /// Heading, in the camera's yaw convention
+    pub yaw: f32,
+    /// Someone sits in it and paddles it, so it doesn't drift on its own;
+    /// not saved, a loaded boat is empty
+    #[serde(skip)]
+    pub ridden: bool,
+}
+
+impl Boat {
+    /// A boat put down at `position`, facing along `yaw`
+    pub fn placed(position: Vec3, yaw: f32) -> Self {
+        Self { position, velocity: Vec3::ZERO, yaw, ridden: false }
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        Aabb::from_position(self.position, HALF_WIDTH, HEIGHT)
+    }
+
+    /// Where the rider's feet go: on the bottom of the hull, where the
+    /// server sees them clear of the ground even on land
+    pub fn seat(&self) -> Vec3 {
+        self.position
+    }
+
+    /// Where the rider stands after getting off: on top of the boat
+    pub fn dismount_position(&self) -> Vec3 {
+        self.position + Vec3::Y * HEIGHT
+    }
+
+    /// Unit vector the boat points along
+    pub fn heading(&self) -> Vec3 {
+        Vec3::new(self.yaw.cos(), 0.0, self.yaw.sin())
+    }
+
+    /// Whether the water line of the boat is under water
+    pub fn in_water(&self, world: &World) -> bool {
+        let p = (self.position + Vec3::Y * DRAFT).floor().as_ivec3();
+        world.get_block_at(p.x, p.y, p.z) == Some(BlockType::Water)
+    }
+
+    /// Whether the boat rests on the ground rather than floating
+    fn beached(&self, world: &World) -> bool {
+        let p = (self.position - Vec3::Y * 0.05).floor().as_ivec3();
+        !self.in_water(world) && blocks_boat(world, p.x, p.y, p.z)
+    }
+
+    /// Paddle for `delta_time` seconds: `forward` and `turn` are in [-1, 1],
+    /// positive ahead and to the right. Paddling barely moves a boat on land.
+    pub fn paddle(&mut self, world: &World, forward: f32, turn: f32, delta_time: f32) {
+        self.yaw += turn.clamp(-1.0, 1.0) * TURN_SPEED * delta_time;
+        let strength = if self.beached(world) { LAND_PADDLING } else { 1.0 };
+        self.velocity += self.heading() * forward.clamp(-1.0, 1.0) * ACCELERATION * strength * delta_time;
+    }
+
+    /// Float, fall and drift for `delta_time` seconds, stopping against
+    /// solid blocks one axis at a time and running up low banks
+    pub fn update(&mut self, world: &World, delta_time: f32) {
+        if self.in_water(world) {
+            self.velocity.y += BUOYANCY * delta_time;
+            self.velocity.y *= WATER_DAMPING.powf(delta_time);
+        } else {
+            self.velocity.y += GRAVITY * delta_time;
+        }
+        let kept = if self.beached(world) { LAND_DRAG } else { WATER_DRAG }.powf(delta_time);
+        self.velocity.x *= kept;
+        self.velocity.z *= kept;
+
+        for axis in 0..3 {
+            let mut moved = *self;
+            moved.position[axis] += self.velocity[axis] * delta_time;
+            if moved.collides(world) && axis != 1 {
+                moved.position.y += STEP_HEIGHT;
+            }
+            if moved.collides(world) {
+                self.velocity[axis] = 0.0;
+            } else {
+                self.position = moved.position;
+            }
+        }
+        world.push_inside_border(&mut self.position, &mut self.velocity, HALF_WIDTH);
+    }
+
+    /// Whether the boat overlaps a solid block
+    pub fn collides(&self, world: &World) -> bool {
+        let aabb = self.bounding_box();
+        let (min, max) = (aabb.min.floor().as_ivec3(), (aabb.max - 1e-4).floor().as_ivec3());
+        (min.x..=max.x).any(|x| (min.y..=max.y).any(|y| (min.z..=max.z).any(|z| blocks_boat(world, x, y, z))))
+    }
+}
+
+/// Whether the block at (x, y, z) stops a boat: full blocks other than water
+fn blocks_boat(world: &World, x: i32, y: i32, z: i32) -> bool {
+    world.get_block_at(x, y, z).is_some_and(|block| block.is_full_cube() && !block.is_liquid())
+}
+
+/// Index in `World::boats` of the boat being ridden
+pub fn ridden(world: &World) -> Option<usize> {
+    world.boats.iter().position(|boat| boat.ridden)
+}
+
+/// Let the boats in the world nobody rides float and drift for `delta_time`
+/// seconds
+pub fn update_boats(world: &mut World, delta_time: f32) {
+    let mut boats = std::mem::take(&mut world.boats);
+    for boat in boats.iter_mut().filter(|boat| !boat.ridden) {
+        boat.update(world, delta_time);
+    }
+    boats.retain(|boat| boat.position.y > -64.0);
+    world.boats = boats;
+}
+
+/// Triangles of the boats: a wooden hull, open at the top
+pub fn build_mesh<'a>(boats: impl IntoIterator<Item = &'a Boat>, vertices: &mut Vec<DebugVertex>, indices: &mut Vec<u32>) {
+    const PLANK: f32 = 0.1;
+    const COLOR: [f32; 3] = [0.55, 0.4, 0.22];
+    let (w, h) = (HALF_WIDTH, HEIGHT);
+    // Along the boat's heading, x is the length and z the width
+    let parts = [
+        (Vec3::new(-w, 0.0, -w * 0.7), Vec3::new(w, PLANK, w * 0.7)),
+        (Vec3::new(-w, 0.0, -w * 0.7), Vec3::new(w, h, -w * 0.7 + PLANK)),
+        (Vec3::new(-w, 0.0, w * 0.7 - PLANK), Vec3::new(w, h, w * 0.7)),
+        (Vec3::new(-w, 0.0, -w * 0.7), Vec3::new(-w + PLANK, h, w * 0.7)),
+        (Vec3::new(w - PLANK, 0.0, -w * 0.7), Vec3::new(w, h, w * 0.7)),
+    ];
+    for boat in boats {
+        let turn = Mat3::from_rotation_y(-boat.yaw);
+        let transform = |p: Vec3| boat.position + turn * p;
+        for (min, max) in parts {
+            add_cuboid(vertices, indices, min, max, transform, COLOR);
+        }
+    }
+}
@@ -0,0 +1,49 @@
+struct Uniforms {
+    view_proj: mat4x4<f32>,
+    camera_pos: vec4<f32>,
+    fog_color: vec4<f32>,
+    fog_params: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+// Horizontal distance from the camera at which the wall is fully faded out
+const FADE_DISTANCE: f32 = 24.0;
+// Width of one stripe, in blocks along the wall and up it
+const STRIPE_WIDTH: f32 = 2.0;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) world_position: vec3<f32>,
+};
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = uniforms.view_proj * vec4<f32>(input.position, 1.0);
+    out.color = input.color;
+    out.world_position = input.position;
+    return out;
+}
+
+// Diagonal stripes that fade in as the camera comes near; the walls run along
+// x or z, so their sum measures the position along either
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let offset = input.world_position.xz - uniforms.camera_pos.xz;
+    let fade = 1.0 - smoothstep(0.0, FADE_DISTANCE, length(offset));
+    if fade <= 0.0 {
+        discard;
+    }
+    let along = input.world_position.x + input.world_position.z + input.world_position.y;
+    let stripe = step(0.5, fract(along / (2.0 * STRIPE_WIDTH)));
+    let alpha = input.color.a * fade * mix(0.35, 1.0, stripe);
+    return vec4<f32>(input.color.rgb, alpha);
+}
@@ -0,0 +1,112 @@
+//! Reuse of the GPU buffers holding the terrain mesh. The whole visible
+//! terrain is uploaded again whenever a chunk changes; instead of creating
+//! new buffers each time, buffers are sized in powers of two and the ones
+//! replaced are kept for the next upload of a similar size.
+
+/// Smallest buffer created, in bytes
+const MIN_BUFFER_SIZE: u64 = 64 * 1024;
+/// Freed buffers kept for reuse; more are released to the driver
+const MAX_FREE_BUFFERS: usize = 4;
+
+/// A buffer the pool can hand out again
+pub trait PooledBuffer {
+    fn size(&self) -> u64;
+}
+
+impl PooledBuffer for wgpu::Buffer {
+    fn size(&self) -> u64 {
+        wgpu::Buffer::size(self)
+    }
+}
+
+/// Size of a new buffer for `bytes` of data
+pub fn buffer_size(bytes: u64) -> u64 {
+    bytes.next_power_of_two().max(MIN_BUFFER_SIZE)
+}
+
+/// Freed buffers of one kind (vertex or index) waiting to be reused
+pub struct BufferPool<B> {
+    free: Vec<B>,
+    /// Uploads that got a freed buffer, and those that needed a new one
+    pub reused: u64,
+    pub allocated: u64,
+}
+
+impl<B: PooledBuffer> BufferPool<B> {
+    pub fn new() -> Self {
+        Self {
+            free: Vec::new(),
+            reused: 0,
+            allocated: 0,
+        }
+    }
+
+    /// The smallest free buffer that holds `bytes` without being more than
+    /// twice the size a new one would get, or `create`d with `buffer_size`
+    pub fn take(&mut self, bytes: u64, create: impl FnOnce(u64) -> B) -> B {
+        let limit = 2 * buffer_size(bytes);
+        let best = (0..self.free.len())
+            .filter(|&i| (bytes..=limit).contains(&self.free[i].size()))
+            .min_by_key(|&i| self.free[i].size());
+        match best {
+            Some(i) => {
+                self.reused += 1;
+                self.free.swap_remove(i)
+            }
+            None => {
+                self.allocated += 1;
+                create(buffer_size(bytes))
+            }
+        }
+    }
+
+    /// Keep `buffer` for reuse, releasing the oldest one if the pool is full
+    pub fn give_back(&mut self, buffer: B) {
+        if self.free.len() == MAX_FREE_BUFFERS {
+            self.free.remove(0);
+        }
+        self.free.push(buffer);
+    }
+
+    /// Bytes held by the free buffers
+    pub fn free_bytes(&self) -> u64 {
+        self.free.iter().map(PooledBuffer::size).sum()
+    }
+}
+
+impl<B: PooledBuffer> Default for BufferPool<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Memory used by the terrain meshes, for the debug overlay
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct MeshMemory {
+    /// Chunk meshes kept on the CPU, and the bytes they take
+    pub cached_chunks: usize,
+    pub cached_bytes: u64,
+    /// Bytes of the terrain buffers being drawn, of those kept for reuse,
+    /// and of the mesh data in the drawn ones
+    pub gpu_bytes: u64,
+    pub pooled_bytes: u64,
+    pub used_bytes: u64,
+    pub reused: u64,
+    pub allocated: u64,
+}
+
+impl MeshMemory {
+    pub fn format_display(&self) -> String {
+        const MB: f64 = 1024.0 * 1024.0;
+        format!(
+            "Terrain meshes: {} chunks, {:.1} MB cached | GPU {:.1} MB ({:.1} MB used, {:.1} MB pooled) | buffers {} reused, {} new",
+            self.cached_chunks,
+            self.cached_bytes as f64 / MB,
+            self.gpu_bytes as f64 / MB,
+            self.used_bytes as f64 / MB,
+            self.pooled_bytes as f64 / MB,
+            self.reused,
+            self.allocated
+        )
+    }
+}
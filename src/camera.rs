@@ -1,10 +1,20 @@
 use glam::{Mat4, Vec3};
 
+/// Field of view while the zoom key is held
+pub const ZOOM_FOV_DEGREES: f32 = 20.0;
+/// FOV multiplier while sprinting
+pub const SPRINT_FOV_FACTOR: f32 = 1.15;
+/// How quickly the FOV approaches its target (higher is snappier)
+const FOV_SPEED: f32 = 12.0;
+
 pub struct Camera {
     pub position: Vec3,
     pub yaw: f32,
     pub pitch: f32,
+    /// Current (possibly zoomed or widened by sprinting) field of view in radians
     pub fov: f32,
+    /// Unzoomed field of view in radians, from the config
+    pub base_fov: f32,
     pub aspect: f32,
     pub near: f32,
     pub far: f32,
@@ -17,6 +27,7 @@ impl Camera {
             yaw: 0.0,
             pitch: 0.0,
             fov: 70.0_f32.to_radians(),
+            base_fov: 70.0_f32.to_radians(),
             aspect,
             near: 0.1,
             far: 1000.0,
@@ -52,6 +63,34 @@ impl Camera {
         self.get_forward().cross(Vec3::Y).normalize()
     }
 
+    /// Set the unzoomed FOV (degrees), snapping the current FOV to it
+    pub fn set_base_fov(&mut self, degrees: f32) {
+        self.base_fov = degrees.to_radians();
+        self.fov = self.base_fov;
+    }
+
+    /// Move the FOV towards the zoomed, sprinting or normal value; eased
+    /// unless `smooth` is off, then it snaps
+    pub fn update_fov(&mut self, zoomed: bool, sprinting: bool, delta_time: f32, smooth: bool) {
+        let target = if zoomed {
+            ZOOM_FOV_DEGREES.to_radians().min(self.base_fov)
+        } else if sprinting {
+            self.base_fov * SPRINT_FOV_FACTOR
+        } else {
+            self.base_fov
+        };
+        if smooth {
+            self.fov += (target - self.fov) * (1.0 - (-FOV_SPEED * delta_time).exp());
+        } else {
+            self.fov = target;
+        }
+    }
+
+    /// Mouse sensitivity multiplier so aiming feels the same while zoomed
+    pub fn look_sensitivity_scale(&self) -> f32 {
+        (self.fov / self.base_fov).min(1.0)
+    }
+
     pub fn update_aspect(&mut self, aspect: f32) {
         self.aspect = aspect;
     }
@@ -0,0 +1,161 @@
+//! What the camera shows between the player's input and the physics steps.
+//! Player movement runs in fixed steps so it behaves the same at any frame
+//! rate; the eye is interpolated between the last two steps so it still moves
+//! every frame. Mouse look can optionally ease towards where the mouse points
+//! instead of following it exactly, and the eye bobs as the player walks and
+//! dips on landing. Each can be turned off in the config.
+
+use crate::camera::Camera;
+use glam::Vec3;
+use std::f32::consts::TAU;
+
+/// Seconds of player movement per physics step
+pub const PHYSICS_STEP: f32 = 1.0 / 60.0;
+/// Most physics steps run in one frame; after a long stall the player lags
+/// behind instead of the game freezing while it catches up
+const MAX_STEPS_PER_FRAME: u32 = 10;
+/// How far the eye rises between footsteps, in blocks
+const BOB_HEIGHT: f32 = 0.05;
+/// How far the eye sways sideways towards each footstep, in blocks
+const BOB_SWAY: f32 = 0.03;
+/// How quickly the bob fades in and out as walking starts and stops
+const BOB_FADE_SPEED: f32 = 8.0;
+/// Landing dip in blocks per block per second of fall speed, and at most
+const DIP_PER_FALL_SPEED: f32 = 0.01;
+const MAX_DIP: f32 = 0.25;
+/// How quickly the eye comes back up after a landing
+const DIP_RECOVERY_SPEED: f32 = 8.0;
+
+pub struct CameraSmoothing {
+    /// Where mouse look points; with look smoothing the camera eases towards it
+    target_yaw: f32,
+    target_pitch: f32,
+    /// Seconds not yet consumed by a physics step
+    accumulator: f32,
+    /// Eye position before and after the last physics step
+    previous_eye: Vec3,
+    current_eye: Vec3,
+}
+
+impl CameraSmoothing {
+    pub fn new(camera: &Camera) -> Self {
+        Self {
+            target_yaw: camera.yaw,
+            target_pitch: camera.pitch,
+            accumulator: 0.0,
+            previous_eye: camera.position,
+            current_eye: camera.position,
+        }
+    }
+
+    /// Turn the camera by `input`. With a `strength` above zero (roughly the
+    /// seconds it takes to catch up) the camera eases towards the new look
+    /// direction; at zero it follows the input exactly.
+    pub fn look(&mut self, camera: &mut Camera, strength: f32, delta_time: f32, input: impl FnOnce(&mut Camera)) {
+        if strength <= 0.0 {
+            input(camera);
+            self.target_yaw = camera.yaw;
+            self.target_pitch = camera.pitch;
+            return;
+        }
+        let (shown_yaw, shown_pitch) = (camera.yaw, camera.pitch);
+        camera.yaw = self.target_yaw;
+        camera.pitch = self.target_pitch;
+        input(camera);
+        self.target_yaw = camera.yaw;
+        self.target_pitch = camera.pitch;
+
+        let t = 1.0 - (-delta_time / strength).exp();
+        camera.yaw = shown_yaw + (self.target_yaw - shown_yaw) * t;
+        camera.pitch = shown_pitch + (self.target_pitch - shown_pitch) * t;
+    }
+
+    /// Turn the camera, and where it is easing towards, by `yaw` radians, as
+    /// when the boat under it turns
+    pub fn turn(&mut self, camera: &mut Camera, yaw: f32) {
+        camera.yaw += yaw;
+        self.target_yaw += yaw;
+    }
+
+    /// Add `delta_time` seconds. Returns the number of physics steps to run now.
+    pub fn advance(&mut self, delta_time: f32) -> u32 {
+        self.accumulator += delta_time;
+        let steps = (self.accumulator / PHYSICS_STEP).floor() as u32;
+        if steps > MAX_STEPS_PER_FRAME {
+            self.accumulator = 0.0;
+            return MAX_STEPS_PER_FRAME;
+        }
+        self.accumulator -= steps as f32 * PHYSICS_STEP;
+        steps
+    }
+
+    /// Record the player's eye position before and after a physics step
+    pub fn record_step(&mut self, before: Vec3, after: Vec3) {
+        self.previous_eye = before;
+        self.current_eye = after;
+    }
+
+    /// Where to draw the camera for the player's eye at `eye`. Interpolated
+    /// between the last two steps by how far into the next step the frame is;
+    /// an eye that moved outside a step (a teleport) is shown where it is.
+    pub fn eye_position(&self, eye: Vec3, interpolate: bool) -> Vec3 {
+        if !interpolate || eye != self.current_eye {
+            return eye;
+        }
+        self.previous_eye.lerp(self.current_eye, self.accumulator / PHYSICS_STEP)
+    }
+}
+
+/// Walking bob and landing dip, added to the eye position
+pub struct ViewBob {
+    /// Position in the walk cycle (see `Footsteps::walk_cycle`)
+    cycle: f32,
+    /// How much of the bob shows: eases in while walking and out when stopped
+    amount: f32,
+    /// The player walked on the ground in the last physics step
+    walking: bool,
+    /// How far a landing has pushed the eye down, in blocks
+    dip: f32,
+}
+
+impl ViewBob {
+    pub fn new() -> Self {
+        Self { cycle: 0.0, amount: 0.0, walking: false, dip: 0.0 }
+    }
+
+    /// After each physics step: the walk cycle position if the player walked
+    /// on the ground, None if they stood still or were in the air
+    pub fn step(&mut self, walk_cycle: Option<f32>) {
+        self.walking = walk_cycle.is_some();
+        if let Some(cycle) = walk_cycle {
+            self.cycle = cycle;
+        }
+    }
+
+    /// The player landed, falling at `fall_speed` blocks per second
+    pub fn land(&mut self, fall_speed: f32) {
+        self.dip = self.dip.max((fall_speed * DIP_PER_FALL_SPEED).min(MAX_DIP));
+    }
+
+    /// Fade the bob in or out and recover from a dip
+    pub fn update(&mut self, delta_time: f32) {
+        let target = if self.walking { 1.0 } else { 0.0 };
+        self.amount += (target - self.amount) * (1.0 - (-BOB_FADE_SPEED * delta_time).exp());
+        self.dip *= (-DIP_RECOVERY_SPEED * delta_time).exp();
+    }
+
+    /// Offset from the eye position; `right` is the camera's right vector.
+    /// The eye is lowest and furthest to the side at each footstep.
+    pub fn offset(&self, right: Vec3) -> Vec3 {
+        let angle = self.cycle * TAU;
+        let rise = angle.sin().abs() * BOB_HEIGHT * self.amount - self.dip;
+        let sway = angle.cos() * BOB_SWAY * self.amount;
+        Vec3::Y * rise + right * sway
+    }
+}
+
+impl Default for ViewBob {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,103 @@
+//! The creative catalog: every block and item, to take as many of as
+//! wanted. It is a tab of the inventory screen in creative mode, filtered
+//! by a search box and scrolled a row at a time.
+
+use crate::block::BlockType;
+use crate::inventory::{Inventory, ItemStack, SlotClick};
+use crate::item::Item;
+
+/// Slots per catalog row
+pub const CATALOG_COLUMNS: usize = 9;
+/// Rows of the catalog on screen at once
+pub const CATALOG_ROWS: usize = 3;
+/// Most characters the search box takes
+const MAX_SEARCH_LEN: usize = 16;
+
+/// Everything the catalog lists: the blocks players can place, then the
+/// items. Water, puddles and fire only come from buckets, rain and flint,
+/// and switched on levers, powered wire, lit lamps and open doors from
+/// using the plain ones.
+pub fn catalog_items() -> impl Iterator<Item = Item> {
+    BlockType::ALL
+        .into_iter()
+        .filter(|block| !matches!(block, BlockType::Air | BlockType::Water | BlockType::Puddle) && block.drop() == Some(*block))
+        .map(Item::Block)
+        .chain(Item::ITEMS)
+}
+
+/// Search and scroll position of the catalog tab
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Catalog {
+    search: String,
+    /// First row on screen
+    scroll: usize,
+    /// Keys typed go to the search box
+    pub search_focused: bool,
+}
+
+impl Catalog {
+    pub fn search(&self) -> &str {
+        &self.search
+    }
+
+    /// Add typed text to the search, back at the top of the results
+    pub fn type_text(&mut self, text: &str) {
+        for c in text.chars() {
+            if !c.is_control() && self.search.chars().count() < MAX_SEARCH_LEN {
+                self.search.push(c);
+            }
+        }
+        self.scroll = 0;
+    }
+
+    pub fn backspace(&mut self) {
+        self.search.pop();
+        self.scroll = 0;
+    }
+
+    /// The items whose name contains the search, ignoring case; spaces
+    /// match the underscores in names
+    pub fn results(&self) -> Vec<Item> {
+        let search = self.search.trim().to_ascii_lowercase().replace(' ', "_");
+        catalog_items().filter(|item| item.name().contains(&search)).collect()
+    }
+
+    /// Rows the results fill
+    pub fn rows(&self) -> usize {
+        self.results().len().div_ceil(CATALOG_COLUMNS)
+    }
+
+    pub fn scroll(&self) -> usize {
+        self.scroll
+    }
+
+    /// Scroll down by `rows` (up if negative), keeping the last row on screen
+    pub fn scroll_by(&mut self, rows: i32) {
+        let last = self.rows().saturating_sub(CATALOG_ROWS);
+        self.scroll = (self.scroll as i64 + rows as i64).clamp(0, last as i64) as usize;
+    }
+
+    /// The item in slot `idx` of the rows on screen
+    pub fn item_at(&self, idx: usize) -> Option<Item> {
+        self.results().get(self.scroll * CATALOG_COLUMNS + idx).copied()
+    }
+}
+
+/// Click on a catalog slot holding `item`: a stack on the cursor goes away;
+/// otherwise left click takes a full stack, right click a single item and
+/// shift-click puts a full stack into the inventory.
+pub fn click_catalog(item: Option<Item>, click: SlotClick, inventory: &mut Inventory) {
+    if inventory.held.take().is_some() {
+        return;
+    }
+    let Some(item) = item else {
+        return;
+    };
+    match click {
+        SlotClick::Left => inventory.held = Some(ItemStack::new(item, item.max_stack_size())),
+        SlotClick::Right => inventory.held = Some(ItemStack::new(item, 1)),
+        SlotClick::ShiftLeft => {
+            inventory.add_stack(item, item.max_stack_size());
+        }
+    }
+}
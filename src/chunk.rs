@@ -4,13 +4,44 @@ use serde::{Deserialize, Serialize};
 pub const CHUNK_SIZE: usize = 16;
 pub const CHUNK_HEIGHT: usize = 64;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "SavedChunk")]
 pub struct Chunk {
+    /// Blocks x fastest, then z, then y; changed through `set_block`, which
+    /// keeps the heightmap up to date
     pub blocks: Vec<BlockType>,
     pub x: i32,
     pub z: i32,
     #[serde(skip)]
     pub dirty: bool,
+    /// Bumped on every block change, so caches (e.g. the minimap) can detect edits
+    /// independently of the mesh `dirty` flag
+    #[serde(skip)]
+    pub revision: u32,
+    /// Changed since the world was last saved; these are what an emergency
+    /// save after a crash writes
+    #[serde(skip)]
+    pub unsaved: bool,
+    /// Per column, indexed by x + z * CHUNK_SIZE: one above its topmost
+    /// non-air block, 0 if it is empty. Not saved, but worked out on load.
+    #[serde(skip)]
+    heights: Vec<u8>,
+}
+
+/// What is saved of a chunk
+#[derive(Deserialize)]
+struct SavedChunk {
+    blocks: Vec<BlockType>,
+    x: i32,
+    z: i32,
+}
+
+impl From<SavedChunk> for Chunk {
+    fn from(saved: SavedChunk) -> Self {
+        let mut chunk = Self::with_blocks(saved.x, saved.z, saved.blocks);
+        chunk.dirty = false;
+        chunk
+    }
 }
 
 impl Chunk {
@@ -20,9 +51,23 @@ impl Chunk {
             x,
             z,
             dirty: true,
+            revision: 0,
+            unsaved: false,
+            heights: vec![0; CHUNK_SIZE * CHUNK_SIZE],
         }
     }
 
+    /// A chunk of the given blocks, x fastest, then z, then y
+    pub fn with_blocks(x: i32, z: i32, blocks: Vec<BlockType>) -> Self {
+        let mut chunk = Self { blocks, ..Self::new(x, z) };
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                chunk.heights[x + z * CHUNK_SIZE] = chunk.column_height(x, z, CHUNK_HEIGHT);
+            }
+        }
+        chunk
+    }
+
     fn get_index(&self, x: usize, y: usize, z: usize) -> usize {
         x + z * CHUNK_SIZE + y * CHUNK_SIZE * CHUNK_SIZE
     }
@@ -38,10 +83,32 @@ impl Chunk {
         if x < CHUNK_SIZE && y < CHUNK_HEIGHT && z < CHUNK_SIZE {
             let index = self.get_index(x, y, z);
             self.blocks[index] = block;
+            let height = &mut self.heights[x + z * CHUNK_SIZE];
+            if block != BlockType::Air {
+                *height = (*height).max(y as u8 + 1);
+            } else if *height as usize == y + 1 {
+                self.heights[x + z * CHUNK_SIZE] = self.column_height(x, z, y);
+            }
             self.dirty = true;
+            self.revision = self.revision.wrapping_add(1);
+            self.unsaved = true;
         }
     }
 
+    /// One above the topmost non-air block of the column at (x, z), 0 if it
+    /// is empty
+    pub fn height(&self, x: usize, z: usize) -> usize {
+        self.heights[x + z * CHUNK_SIZE] as usize
+    }
+
+    /// One above the topmost non-air block of the column at (x, z) below `top`
+    fn column_height(&self, x: usize, z: usize, top: usize) -> u8 {
+        (0..top)
+            .rev()
+            .find(|&y| self.blocks.get(self.get_index(x, y, z)).is_some_and(|block| *block != BlockType::Air))
+            .map_or(0, |y| y as u8 + 1)
+    }
+
     pub fn mark_clean(&mut self) {
         self.dirty = false;
     }
@@ -0,0 +1,217 @@
+use crate::chunk::{Chunk, CHUNK_SIZE};
+use crate::world::World;
+use crate::world_gen::WorldGenerator;
+use glam::Vec2;
+use std::collections::HashSet;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use web_time::Instant;
+
+/// Upper bound on terrain worker threads
+const MAX_WORKERS: usize = 4;
+/// How much further away, in blocks, a chunk straight behind the viewer
+/// counts than one straight ahead
+const BEHIND_PENALTY: f32 = 32.0;
+
+/// How much chunk work a frame may do: at most `chunks` chunks, and none
+/// past the first once `time` has gone by, however cheap or heavy they are
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameBudget {
+    pub chunks: usize,
+    pub time: Duration,
+}
+
+impl FrameBudget {
+    /// Everything that is ready, for when no frames need to stay smooth
+    pub const UNLIMITED: FrameBudget = FrameBudget { chunks: usize::MAX, time: Duration::MAX };
+
+    /// Whether `done` chunks, worked on since `start`, use the budget up
+    pub fn spent(&self, done: usize, start: Instant) -> bool {
+        done >= self.chunks || (done > 0 && start.elapsed() >= self.time)
+    }
+}
+
+/// Generates chunk terrain on background threads.
+///
+/// Terrain is generated off the main thread; finished chunks are handed back
+/// through `integrate`, which inserts them and places trees (those need the
+/// whole world, so they run on the main thread). The web has no threads;
+/// there `integrate` generates the requested chunks itself, within its budget.
+pub struct ChunkLoader {
+    request_tx: Option<Sender<(i32, i32)>>,
+    result_rx: Receiver<Chunk>,
+    /// Requests `integrate` generates, when there are no workers
+    own_requests: Option<Receiver<(i32, i32)>>,
+    pending: HashSet<(i32, i32)>,
+    /// Tells workers to abandon queued requests on shutdown
+    stop: Arc<AtomicBool>,
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+impl ChunkLoader {
+    /// Start the workers, each with its own copy of `generator`
+    pub fn new(generator: &WorldGenerator) -> Self {
+        let (request_tx, request_rx) = mpsc::channel::<(i32, i32)>();
+        let (result_tx, result_rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        if cfg!(target_arch = "wasm32") {
+            return Self {
+                request_tx: Some(request_tx),
+                result_rx,
+                own_requests: Some(request_rx),
+                pending: HashSet::new(),
+                stop,
+                workers: Vec::new(),
+            };
+        }
+        let request_rx = Arc::new(Mutex::new(request_rx));
+
+        let worker_count = thread::available_parallelism()
+            .map(|n| n.get().saturating_sub(1))
+            .unwrap_or(1)
+            .clamp(1, MAX_WORKERS);
+
+        let workers = (0..worker_count)
+            .map(|i| {
+                let request_rx = Arc::clone(&request_rx);
+                let result_tx = result_tx.clone();
+                let stop = Arc::clone(&stop);
+                let generator = generator.clone();
+                thread::Builder::new()
+                    .name(format!("chunk-gen-{}", i))
+                    .spawn(move || {
+                        loop {
+                            // Hold the lock only while waiting for the next request
+                            let request = request_rx.lock().unwrap().recv();
+                            let Ok((x, z)) = request else {
+                                break; // loader dropped
+                            };
+                            if stop.load(Ordering::Relaxed) {
+                                break;
+                            }
+                            if result_tx.send(generator.generate_chunk(x, z)).is_err() {
+                                break;
+                            }
+                        }
+                    })
+                    .expect("failed to spawn chunk generation thread")
+            })
+            .collect();
+
+        Self {
+            request_tx: Some(request_tx),
+            result_rx,
+            own_requests: None,
+            pending: HashSet::new(),
+            stop,
+            workers,
+        }
+    }
+
+    /// Queue every missing chunk within `radius` chunks of a viewer at
+    /// `position` (x and z, in blocks) looking along `facing`, in
+    /// `load_priority` order, leaving out those beyond the world border
+    pub fn request_area(&mut self, world: &World, position: Vec2, facing: Vec2, radius: i32) {
+        let center = chunk_at(position);
+        let mut missing: Vec<(i32, i32)> = area(center, radius)
+            .filter(|key| !world.chunks.contains_key(key) && !self.pending.contains(key))
+            .filter(|&(x, z)| world.chunk_inside_border(x, z))
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+
+        missing.sort_by(|&a, &b| load_priority(a, position, facing).total_cmp(&load_priority(b, position, facing)));
+        for key in missing {
+            if let Some(tx) = &self.request_tx {
+                if tx.send(key).is_ok() {
+                    self.pending.insert(key);
+                }
+            }
+        }
+    }
+
+    /// Move finished chunks into the world within `budget`, in the order they
+    /// were requested; the rest wait for the next call. Returns how many were
+    /// added.
+    pub fn integrate(&mut self, world: &mut World, generator: &WorldGenerator, budget: FrameBudget) -> usize {
+        let start = Instant::now();
+        let mut added = 0;
+        while !budget.spent(added, start) {
+            let finished = self.result_rx.try_recv().ok().or_else(|| {
+                let (x, z) = self.own_requests.as_ref()?.try_recv().ok()?;
+                Some(generator.generate_chunk(x, z))
+            });
+            let Some(chunk) = finished else {
+                break;
+            };
+            let key = (chunk.x, chunk.z);
+            self.pending.remove(&key);
+            // A chunk may have been generated synchronously in the meantime
+            if !world.chunks.contains_key(&key) {
+                world.insert_generated_chunk(chunk, generator);
+                added += 1;
+            }
+        }
+        added
+    }
+}
+
+impl Drop for ChunkLoader {
+    fn drop(&mut self) {
+        // Closing the request channel stops the workers once they finish their current chunk
+        self.stop.store(true, Ordering::Relaxed);
+        self.request_tx = None;
+        for worker in self.workers.drain(..) {
+            worker.join().ok();
+        }
+    }
+}
+
+/// Coordinates of the chunk holding the point (x, z) = `position`
+pub fn chunk_at(position: Vec2) -> (i32, i32) {
+    let chunk = (position / CHUNK_SIZE as f32).floor();
+    (chunk.x as i32, chunk.y as i32)
+}
+
+/// How soon the chunk at `key` should be loaded or sent for a viewer at
+/// `position` (x and z, in blocks) looking along `facing`; lower comes
+/// first. Nearer chunks come first, and of those equally near the ones in
+/// view; a zero `facing` orders by distance alone.
+pub fn load_priority((x, z): (i32, i32), position: Vec2, facing: Vec2) -> f32 {
+    let offset = (Vec2::new(x as f32, z as f32) + 0.5) * CHUNK_SIZE as f32 - position;
+    let distance = offset.length();
+    if distance == 0.0 {
+        return 0.0;
+    }
+    let away = (1.0 - offset.dot(facing) / distance) / 2.0;
+    distance + away * BEHIND_PENALTY
+}
+
+/// Chunk coordinates of the square area within `radius` of `center`
+pub fn area(center: (i32, i32), radius: i32) -> impl Iterator<Item = (i32, i32)> {
+    (-radius..=radius).flat_map(move |dx| (-radius..=radius).map(move |dz| (center.0 + dx, center.1 + dz)))
+}
+
+/// Chunk coordinates of the ring `distance` chunks from `center` (the
+/// outline of the square area of that radius)
+pub fn ring(center: (i32, i32), distance: i32) -> impl Iterator<Item = (i32, i32)> {
+    let edges = (-distance..=distance).flat_map(move |d| [(d, -distance), (d, distance)]);
+    let sides = (1 - distance..distance).flat_map(move |d| [(-distance, d), (distance, d)]);
+    edges
+        .chain(sides)
+        .take(if distance == 0 { 1 } else { usize::MAX })
+        .map(move |(dx, dz)| (center.0 + dx, center.1 + dz))
+}
+
+/// Fraction of the area around `center` that is loaded, in [0, 1]. Chunks
+/// beyond the world border never load, so they don't count.
+pub fn area_progress(world: &World, center: (i32, i32), radius: i32) -> f32 {
+    let (loaded, total) = area(center, radius)
+        .filter(|&(x, z)| world.chunk_inside_border(x, z))
+        .fold((0, 0), |(loaded, total), key| (loaded + world.chunks.contains_key(&key) as u32, total + 1));
+    if total == 0 { 1.0 } else { loaded as f32 / total as f32 }
+}
@@ -0,0 +1,175 @@
+//! The loaded chunks of a world, readable from other threads. The world owns
+//! the `ChunkMap` and is the only one to change it; a `ChunkReader` from
+//! `reader` can be sent to a worker, which then sees the same chunks without
+//! a `&World`. The map is split into shards, each behind its own lock, so
+//! threads looking at different chunks rarely wait on each other.
+//!
+//! Chunks are kept in `Arc`s: a reader can hold on to one as long as it
+//! likes, and changing the chunk while it does copies it instead of waiting
+//! for the reader.
+
+use crate::chunk::Chunk;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError};
+
+/// Locks the chunks are split over
+const SHARDS: usize = 16;
+
+type Shard = HashMap<(i32, i32), Arc<Chunk>>;
+
+/// Read access to the chunks of a `ChunkMap`, for any thread
+#[derive(Clone)]
+pub struct ChunkReader {
+    shards: Arc<[RwLock<Shard>]>,
+}
+
+impl ChunkReader {
+    pub fn len(&self) -> usize {
+        (0..SHARDS).map(|shard| self.read(shard).len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains_key(&self, key: &(i32, i32)) -> bool {
+        self.read(shard(key)).contains_key(key)
+    }
+
+    /// The chunk at `key`, as it is now; later changes don't reach it
+    pub fn get(&self, key: &(i32, i32)) -> Option<Arc<Chunk>> {
+        self.read(shard(key)).get(key).cloned()
+    }
+
+    /// Look at the chunk at `key` without holding on to it, which is cheaper
+    /// than `get` for a single block
+    pub fn with<R>(&self, key: &(i32, i32), f: impl FnOnce(&Chunk) -> R) -> Option<R> {
+        self.read(shard(key)).get(key).map(|chunk| f(chunk))
+    }
+
+    /// Coordinates of the loaded chunks, in no particular order
+    pub fn keys(&self) -> impl Iterator<Item = (i32, i32)> {
+        let keys: Vec<_> = (0..SHARDS).flat_map(|shard| self.read(shard).keys().copied().collect::<Vec<_>>()).collect();
+        keys.into_iter()
+    }
+
+    /// The loaded chunks, in no particular order
+    pub fn values(&self) -> impl Iterator<Item = Arc<Chunk>> {
+        let values: Vec<_> = (0..SHARDS).flat_map(|shard| self.read(shard).values().cloned().collect::<Vec<_>>()).collect();
+        values.into_iter()
+    }
+
+    /// The loaded chunks, leaving out the shards locked for a change right
+    /// now instead of waiting for them; for the crash hook, which may run on
+    /// the thread holding such a lock
+    pub fn try_values(&self) -> Vec<Arc<Chunk>> {
+        let mut values = Vec::new();
+        for shard in self.shards.iter() {
+            let guard = match shard.try_read() {
+                Ok(guard) => guard,
+                Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+                Err(TryLockError::WouldBlock) => continue,
+            };
+            values.extend(guard.values().cloned());
+        }
+        values
+    }
+
+    // A panic while a shard was locked can't leave a chunk half changed, so a
+    // poisoned lock is used as it is
+    fn read(&self, shard: usize) -> RwLockReadGuard<'_, Shard> {
+        self.shards[shard].read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self, shard: usize) -> RwLockWriteGuard<'_, Shard> {
+        self.shards[shard].write().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// The chunks of a world; reading goes through `ChunkReader`
+pub struct ChunkMap {
+    chunks: ChunkReader,
+}
+
+impl ChunkMap {
+    pub fn new() -> Self {
+        Self { chunks: ChunkReader { shards: (0..SHARDS).map(|_| RwLock::new(HashMap::new())).collect() } }
+    }
+
+    /// A handle to read the chunks from another thread
+    pub fn reader(&self) -> ChunkReader {
+        self.chunks.clone()
+    }
+
+    /// Change the chunk at `key`; a copy is made first if a reader still
+    /// holds the old one. None if the chunk isn't loaded.
+    pub fn update<R>(&mut self, key: &(i32, i32), f: impl FnOnce(&mut Chunk) -> R) -> Option<R> {
+        self.chunks.write(shard(key)).get_mut(key).map(|chunk| f(Arc::make_mut(chunk)))
+    }
+
+    /// Change every chunk, as `update` does
+    pub fn update_all(&mut self, mut f: impl FnMut(&mut Chunk)) {
+        for shard in 0..SHARDS {
+            self.chunks.write(shard).values_mut().for_each(|chunk| f(Arc::make_mut(chunk)));
+        }
+    }
+
+    pub fn insert(&mut self, key: (i32, i32), chunk: Chunk) -> Option<Arc<Chunk>> {
+        self.chunks.write(shard(&key)).insert(key, Arc::new(chunk))
+    }
+
+    pub fn remove(&mut self, key: &(i32, i32)) -> Option<Arc<Chunk>> {
+        self.chunks.write(shard(key)).remove(key)
+    }
+
+    pub fn clear(&mut self) {
+        for shard in 0..SHARDS {
+            self.chunks.write(shard).clear();
+        }
+    }
+}
+
+impl Default for ChunkMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for ChunkMap {
+    type Target = ChunkReader;
+
+    fn deref(&self) -> &ChunkReader {
+        &self.chunks
+    }
+}
+
+impl From<HashMap<(i32, i32), Chunk>> for ChunkMap {
+    fn from(chunks: HashMap<(i32, i32), Chunk>) -> Self {
+        let mut map = Self::new();
+        for (key, chunk) in chunks {
+            map.insert(key, chunk);
+        }
+        map
+    }
+}
+
+/// Which shard the chunk at `key` is in
+fn shard(&(x, z): &(i32, i32)) -> usize {
+    (x.wrapping_mul(31).wrapping_add(z)).rem_euclid(SHARDS as i32) as usize
+}
+
+// Saved as a plain map of chunks, the same as before the map was shared
+impl Serialize for ChunkMap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let chunks: Vec<_> = self.keys().filter_map(|key| Some((key, self.get(&key)?))).collect();
+        serializer.collect_map(chunks.iter().map(|(key, chunk)| (key, &**chunk)))
+    }
+}
+
+impl<'de> Deserialize<'de> for ChunkMap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        HashMap::<(i32, i32), Chunk>::deserialize(deserializer).map(Self::from)
+    }
+}
@@ -0,0 +1,101 @@
+//! Read-only copies of a chunk for work done away from the world, such as
+//! building its mesh or lighting it on another thread. A snapshot holds the
+//! chunk's blocks with a one block border from the chunks around it, and the
+//! light sources close enough to reach it, so nothing has to look at the
+//! chunks again. Cloning one only copies two `Arc`s.
+
+use crate::block::BlockType;
+use crate::chunk::{Chunk, CHUNK_HEIGHT, CHUNK_SIZE};
+use crate::chunk_map::ChunkReader;
+use std::sync::Arc;
+
+/// Blocks along x and z, the chunk and its border on both sides
+const PADDED: usize = CHUNK_SIZE + 2;
+
+/// A light source: world position and emission
+pub type Light = ([i32; 3], u8);
+
+#[derive(Debug, Clone)]
+pub struct ChunkSnapshot {
+    pub x: i32,
+    pub z: i32,
+    /// Blocks x fastest, then z, then y, from -1 to `CHUNK_SIZE` along x and z
+    blocks: Arc<[BlockType]>,
+    lights: Arc<[Light]>,
+}
+
+impl ChunkSnapshot {
+    /// Copy the chunk at (chunk_x, chunk_z) with its border, or None if it
+    /// isn't loaded. The border is air where a neighbor isn't loaded.
+    pub fn capture(chunks: &ChunkReader, chunk_x: i32, chunk_z: i32) -> Option<Self> {
+        chunks.get(&(chunk_x, chunk_z))?;
+        // Each chunk is taken once, so the blocks and lights agree even while
+        // the world goes on changing
+        let around: Vec<(i32, i32, Arc<Chunk>)> = (-1..=1)
+            .flat_map(|dx| (-1..=1).map(move |dz| (dx, dz)))
+            .filter_map(|(dx, dz)| Some((dx, dz, chunks.get(&(chunk_x + dx, chunk_z + dz))?)))
+            .collect();
+        let mut blocks = vec![BlockType::Air; PADDED * CHUNK_HEIGHT * PADDED];
+        for (dx, dz, source) in &around {
+            // The columns of this chunk that fall inside the snapshot, in its local coordinates
+            let columns = |d: i32| match d {
+                -1 => CHUNK_SIZE as i32 - 1..=CHUNK_SIZE as i32 - 1,
+                0 => 0..=CHUNK_SIZE as i32 - 1,
+                _ => 0..=0,
+            };
+            for y in 0..CHUNK_HEIGHT {
+                for z in columns(*dz) {
+                    for x in columns(*dx) {
+                        let block = source.get_block(x as usize, y, z as usize);
+                        let (px, pz) = (x + dx * CHUNK_SIZE as i32, z + dz * CHUNK_SIZE as i32);
+                        blocks[index(px, y as i32, pz)] = block;
+                    }
+                }
+            }
+        }
+        Some(Self {
+            x: chunk_x,
+            z: chunk_z,
+            blocks: blocks.into(),
+            lights: nearby_lights(around.iter().map(|(_, _, chunk)| &**chunk)).into(),
+        })
+    }
+
+    /// Block at local (x, y, z), with x and z from -1 to `CHUNK_SIZE`; air
+    /// anywhere else
+    pub fn get_block(&self, x: i32, y: i32, z: i32) -> BlockType {
+        let inside = |c: i32, size: usize| (-1..=size as i32).contains(&c);
+        if !inside(x, CHUNK_SIZE) || !inside(z, CHUNK_SIZE) || !(0..CHUNK_HEIGHT as i32).contains(&y) {
+            return BlockType::Air;
+        }
+        self.blocks[index(x, y, z)]
+    }
+
+    /// Light sources in the chunk and the chunks around it, whose light may
+    /// reach its blocks
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+}
+
+fn index(x: i32, y: i32, z: i32) -> usize {
+    (x + 1) as usize + (z + 1) as usize * PADDED + y as usize * PADDED * PADDED
+}
+
+fn nearby_lights<'a>(chunks: impl Iterator<Item = &'a Chunk>) -> Vec<Light> {
+    let mut lights = Vec::new();
+    for source in chunks.filter(|source| has_lights(source)) {
+        for (index, block) in source.blocks.iter().enumerate() {
+            if block.light_emission() > 0 {
+                let (x, z, y) = (index % CHUNK_SIZE, index / CHUNK_SIZE % CHUNK_SIZE, index / (CHUNK_SIZE * CHUNK_SIZE));
+                let position = [source.x * CHUNK_SIZE as i32 + x as i32, y as i32, source.z * CHUNK_SIZE as i32 + z as i32];
+                lights.push((position, block.light_emission()));
+            }
+        }
+    }
+    lights
+}
+
+fn has_lights(chunk: &Chunk) -> bool {
+    chunk.blocks.iter().any(|block| block.light_emission() > 0)
+}
@@ -0,0 +1,239 @@
+//! Circuits. A switched on lever powers the blocks next to it, and so do a
+//! pressure plate while a player, item or boat is on it and a button for a
+//! second after it is pressed. Wire carries the power on along the blocks it
+//! connects to, a level less every block, so a lever reaches 15 blocks of
+//! wire. Lamps light up and doors open while a source or powered wire is next
+//! to them.
+//!
+//! Flipping a lever, pressing or releasing a plate or button, laying or
+//! cutting wire and putting down a lamp or door schedule a block update,
+//! which works out the power of the wire around and sets the wire, lamps and
+//! doors there accordingly. Power levels aren't saved: they follow from the
+//! levers and wire, and the blocks are saved in the state they were left in.
+
+use crate::block::BlockType;
+use crate::physics::Aabb;
+use crate::world::World;
+use glam::Vec3;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+type Pos = (i32, i32, i32);
+
+/// Power of a lever and of the wire next to it
+pub const MAX_POWER: u8 = 15;
+/// Ticks from a change to a circuit until the blocks on it follow
+pub const CIRCUIT_DELAY: u64 = 1;
+/// Ticks a pressed button stays down
+pub const BUTTON_DELAY: u64 = 20;
+/// Ticks between checks whether a pressed plate is still stood on
+const PLATE_DELAY: u64 = 10;
+/// Most wire blocks worked out in one update
+const MAX_NETWORK: usize = 4096;
+const NEIGHBORS: [(i32, i32, i32); 6] = [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)];
+
+/// What a block does in a circuit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    None,
+    Source,
+    Wire,
+    Device,
+}
+
+fn role(block: BlockType) -> Role {
+    match block {
+        BlockType::LeverOn | BlockType::PressedPlate | BlockType::PressedButton => Role::Source,
+        BlockType::Wire | BlockType::PoweredWire => Role::Wire,
+        BlockType::Lamp | BlockType::LitLamp | BlockType::Door | BlockType::OpenDoor => Role::Device,
+        _ => Role::None,
+    }
+}
+
+/// Whether replacing `previous` with `block` changes a circuit: a lever
+/// flipped, a plate or button pressed or released, wire laid or cut, or a
+/// lamp or door put down. Wire, lamps and doors following the power don't.
+pub fn changes_circuit(previous: BlockType, block: BlockType) -> bool {
+    role(previous) != role(block)
+}
+
+fn neighbors((x, y, z): Pos) -> impl Iterator<Item = Pos> {
+    NEIGHBORS.into_iter().map(move |(dx, dy, dz)| (x + dx, y + dy, z + dz))
+}
+
+fn block_at(world: &World, (x, y, z): Pos) -> BlockType {
+    world.get_block_at(x, y, z).unwrap_or(BlockType::Air)
+}
+
+/// Whether a switched on lever, a pressed plate or button or powered wire is
+/// next to `pos`
+pub fn is_powered(world: &World, pos: Pos) -> bool {
+    neighbors(pos).any(|n| {
+        let block = block_at(world, n);
+        role(block) == Role::Source || block == BlockType::PoweredWire
+    })
+}
+
+/// Power level of each block of the wire connected to the wire at `start`:
+/// `MAX_POWER` next to a source, a level less every block further
+/// along, 0 out of reach
+pub fn wire_levels(world: &World, start: Pos) -> HashMap<Pos, u8> {
+    let mut network = vec![start];
+    let mut seen = HashSet::from([start]);
+    let mut i = 0;
+    while i < network.len() && network.len() < MAX_NETWORK {
+        for n in neighbors(network[i]) {
+            if seen.insert(n) && role(block_at(world, n)) == Role::Wire {
+                network.push(n);
+            }
+        }
+        i += 1;
+    }
+
+    let mut levels: HashMap<Pos, u8> = network.iter().map(|&wire| (wire, 0)).collect();
+    let mut queue: VecDeque<Pos> =
+        network.into_iter().filter(|&wire| neighbors(wire).any(|n| role(block_at(world, n)) == Role::Source)).collect();
+    for wire in &queue {
+        levels.insert(*wire, MAX_POWER);
+    }
+    while let Some(wire) = queue.pop_front() {
+        let next = levels[&wire] - 1;
+        for n in neighbors(wire) {
+            if let Some(level) = levels.get_mut(&n).filter(|level| **level < next) {
+                *level = next;
+                queue.push_back(n);
+            }
+        }
+    }
+    levels
+}
+
+/// Scheduled update at `pos`, where a circuit changed: power the wire
+/// connected to it and the blocks next to it, then switch the lamps and
+/// doors next to those. Returns true if any block changed.
+pub fn update(world: &mut World, pos: Pos) -> bool {
+    let around: Vec<Pos> = std::iter::once(pos).chain(neighbors(pos)).collect();
+    if around.iter().all(|&p| role(block_at(world, p)) == Role::None) {
+        return false;
+    }
+    let mut changed = false;
+    let mut devices: HashSet<Pos> = around.iter().copied().collect();
+    let mut done = HashSet::new();
+    for &start in &around {
+        if done.contains(&start) || role(block_at(world, start)) != Role::Wire {
+            continue;
+        }
+        for (wire, level) in wire_levels(world, start) {
+            done.insert(wire);
+            devices.extend(neighbors(wire));
+            let block = if level > 0 { BlockType::PoweredWire } else { BlockType::Wire };
+            if block_at(world, wire) != block {
+                changed |= world.set_block_at(wire.0, wire.1, wire.2, block);
+            }
+        }
+    }
+    let mut devices: Vec<Pos> = devices.into_iter().collect();
+    devices.sort_unstable();
+    for device in devices {
+        changed |= update_device(world, device);
+    }
+    changed
+}
+
+/// Light the lamp or open the door at `pos` if it is powered, or put it out
+/// or close it if not
+fn update_device(world: &mut World, pos: Pos) -> bool {
+    match block_at(world, pos) {
+        BlockType::Lamp | BlockType::LitLamp => {
+            let lamp = if is_powered(world, pos) { BlockType::LitLamp } else { BlockType::Lamp };
+            block_at(world, pos) != lamp && world.set_block_at(pos.0, pos.1, pos.2, lamp)
+        }
+        BlockType::Door | BlockType::OpenDoor => {
+            let column = door_column(world, pos);
+            let open = column.iter().any(|&door| is_powered(world, door));
+            set_doors(world, &column, open)
+        }
+        _ => false,
+    }
+}
+
+/// The door at `pos` and the doors stacked above and below it
+fn door_column(world: &World, (x, y, z): Pos) -> Vec<Pos> {
+    let is_door = |y| matches!(block_at(world, (x, y, z)), BlockType::Door | BlockType::OpenDoor);
+    let bottom = (0..=y).rev().take_while(|&y| is_door(y)).last().unwrap_or(y);
+    (bottom..).take_while(|&y| is_door(y)).map(|y| (x, y, z)).collect()
+}
+
+fn set_doors(world: &mut World, doors: &[Pos], open: bool) -> bool {
+    let door = if open { BlockType::OpenDoor } else { BlockType::Door };
+    let mut changed = false;
+    for &(x, y, z) in doors {
+        if block_at(world, (x, y, z)) != door {
+            changed |= world.set_block_at(x, y, z, door);
+        }
+    }
+    changed
+}
+
+/// Right click on the block at `pos`: flip a lever, press a button, or open
+/// or close a door with the doors stacked on it. Pressing a pressed button
+/// keeps it down longer. Returns true if anything changed.
+pub fn switch(world: &mut World, pos: Pos) -> bool {
+    match block_at(world, pos) {
+        BlockType::Lever => world.set_block_at(pos.0, pos.1, pos.2, BlockType::LeverOn),
+        BlockType::LeverOn => world.set_block_at(pos.0, pos.1, pos.2, BlockType::Lever),
+        BlockType::Button | BlockType::PressedButton => {
+            world.block_ticks.schedule(pos, BUTTON_DELAY);
+            world.set_block_at(pos.0, pos.1, pos.2, BlockType::PressedButton)
+        }
+        BlockType::Door => set_doors(world, &door_column(world, pos), true),
+        BlockType::OpenDoor => set_doors(world, &door_column(world, pos), false),
+        _ => false,
+    }
+}
+
+/// Press the pressure plates under the players, items and boats of
+/// `World::bodies`. Returns true if any was pressed.
+pub fn press_plates(world: &mut World) -> bool {
+    let mut pressed = Vec::new();
+    for body in &world.bodies {
+        let (min, max) = (body.min.floor().as_ivec3(), body.max.floor().as_ivec3());
+        for x in min.x..=max.x {
+            for z in min.z..=max.z {
+                let pos = (x, min.y, z);
+                if block_at(world, pos) == BlockType::PressurePlate && plate_box(pos).intersects(body) {
+                    pressed.push(pos);
+                }
+            }
+        }
+    }
+    let mut changed = false;
+    for (x, y, z) in pressed {
+        changed |= world.set_block_at(x, y, z, BlockType::PressedPlate);
+    }
+    changed
+}
+
+/// Where a plate at `pos` reaches up to when pressed: a little above the
+/// floor, so a body only just standing on it still counts
+fn plate_box((x, y, z): Pos) -> Aabb {
+    let min = Vec3::new(x as f32, y as f32, z as f32);
+    Aabb::new(min, min + Vec3::new(1.0, BlockType::PressurePlate.height(), 1.0))
+}
+
+/// Scheduled update of a pressed plate or button: pop it back up, unless a
+/// later update of it is still to come or something is still on the plate
+pub fn release(world: &mut World, pos: Pos) -> bool {
+    if world.block_ticks.is_scheduled(pos) {
+        return false;
+    }
+    let released = match block_at(world, pos) {
+        BlockType::PressedPlate if world.bodies.iter().any(|body| plate_box(pos).intersects(body)) => {
+            world.block_ticks.schedule(pos, PLATE_DELAY);
+            return false;
+        }
+        BlockType::PressedPlate => BlockType::PressurePlate,
+        BlockType::PressedButton => BlockType::Button,
+        _ => return false,
+    };
+    world.set_block_at(pos.0, pos.1, pos.2, released)
+}
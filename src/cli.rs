@@ -0,0 +1,119 @@
+//! Command-line launch options. They take precedence over `config.json`.
+
+use crate::config::GameConfig;
+use crate::net;
+use crate::storage::Storage;
+use crate::world::World;
+use crate::world_border::{MAX_BORDER, MIN_BORDER};
+use crate::world_gen::TerrainMode;
+use clap::Parser;
+use std::path::Path;
+
+/// Seed of worlds created without `--seed`
+const DEFAULT_SEED: u32 = 12345;
+
+#[derive(Parser, Debug, Clone)]
+#[command(version, about = "Rustcraft - Voxel Game")]
+pub struct Args {
+    /// World to play, saved as <WORLD>.dat
+    #[arg(long, default_value = "world")]
+    pub world: String,
+    /// Seed of a newly created world; existing worlds keep theirs
+    #[arg(long)]
+    pub seed: Option<u32>,
+    /// Terrain of a newly created world; density terrain has cliffs,
+    /// overhangs and caves open to the sky. Existing worlds keep theirs
+    #[arg(long, value_enum)]
+    pub terrain: Option<TerrainMode>,
+    /// Start in borderless fullscreen
+    #[arg(long)]
+    pub fullscreen: bool,
+    /// View distance in chunks
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(i32).range(2..=16))]
+    pub view_distance: Option<i32>,
+    /// Chunks around each player in which the world is simulated
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(i32).range(2..=16))]
+    pub simulation_distance: Option<i32>,
+    /// Configuration file
+    #[arg(long, value_name = "PATH", default_value = "config.json")]
+    pub config: String,
+    /// Run the world simulation without a window, reading commands from stdin
+    #[arg(long)]
+    pub headless: bool,
+    /// Record the frame profiler's phases and write them as a Chrome trace on exit
+    #[arg(long, value_name = "PATH")]
+    pub trace: Option<String>,
+    /// Join a LAN server instead of playing a local world
+    #[arg(long, value_name = "HOST[:PORT]", conflicts_with = "headless")]
+    pub connect: Option<String>,
+    /// Player name shown to the other players
+    #[arg(long, default_value = "Player")]
+    pub name: String,
+    /// Port the dedicated server accepts players on
+    #[arg(long, default_value_t = net::DEFAULT_PORT)]
+    pub port: u16,
+    /// Server operators, one player name per line; /op and /deop edit it
+    #[arg(long, value_name = "PATH", default_value = "ops.txt")]
+    pub ops: String,
+    /// Only let the players listed in this file (one name per line) join
+    #[arg(long, value_name = "PATH")]
+    pub whitelist: Option<String>,
+    /// Limit the world to RADIUS blocks either side of the origin; saved
+    /// with the world, `/worldborder off` removes it
+    #[arg(long, value_name = "RADIUS", value_parser = clap::value_parser!(i32).range(MIN_BORDER as i64..=MAX_BORDER as i64))]
+    pub world_border: Option<i32>,
+}
+
+impl Args {
+    pub fn world_path(&self) -> String {
+        format!("{}.dat", self.world)
+    }
+
+    /// Load the config file with the command-line overrides applied, writing
+    /// the defaults if there is no file yet
+    pub fn load_config(&self, storage: &dyn Storage) -> GameConfig {
+        let mut config = GameConfig::load(storage, &self.config);
+        if !storage.exists(Path::new(&self.config)) {
+            config.save(storage, &self.config).ok();
+        }
+        self.apply_overrides(&mut config);
+        config
+    }
+
+    /// Put the options given on the command line into `config`
+    pub fn apply_overrides(&self, config: &mut GameConfig) {
+        if let Some(view_distance) = self.view_distance {
+            config.view_distance = view_distance;
+        }
+        if let Some(simulation_distance) = self.simulation_distance {
+            config.simulation_distance = simulation_distance;
+        }
+    }
+
+    /// Load the world, or create it with the requested seed and terrain
+    pub fn open_world(&self, storage: &dyn Storage) -> World {
+        let path = self.world_path();
+        if !storage.exists(Path::new(&path)) {
+            log::info!("Creating new world {}...", self.world);
+            return self.new_world();
+        }
+        if self.seed.is_some() {
+            log::warn!("World {} already exists, ignoring --seed", self.world);
+        }
+        if self.terrain.is_some() {
+            log::warn!("World {} already exists, ignoring --terrain", self.world);
+        }
+        World::load(storage, &path).unwrap_or_else(|e| {
+            log::error!("Failed to load {}: {}", path, e);
+            log::info!("Creating new world...");
+            self.new_world()
+        })
+    }
+
+    fn new_world(&self) -> World {
+        World {
+            terrain: self.terrain.unwrap_or_default(),
+            ..World::new(self.seed.unwrap_or(DEFAULT_SEED))
+        }
+    }
+}
@@ -0,0 +1,535 @@
+use crate::block::{BlockType, MAX_LIGHT};
+use crate::block_entity::BlockEntity;
+use crate::chunk::CHUNK_SIZE;
+use crate::chunk_snapshot::ChunkSnapshot;
+use crate::experience::Experience;
+use crate::item::Item;
+use crate::free_cam::FreeCam;
+use crate::gamerules::GameRules;
+use crate::mesh;
+use crate::physics::{GameMode, Player};
+use crate::region::{self, Bounds, Clipboard, Selection, MAX_REGION_BLOCKS};
+use crate::resource_pack::{self, RESOURCE_PACKS_DIR};
+use crate::schematic::{self, SCHEMATICS_DIR};
+use crate::structures::StructureKind;
+use crate::time::{self, Weather};
+use crate::world::World;
+use crate::world_border;
+use crate::world_gen::WorldGenerator;
+use glam::Vec3;
+
+/// Mutable game state a command may act on
+pub struct CommandContext<'a> {
+    pub world: &'a mut World,
+    pub player: &'a mut Player,
+    /// The player's region for the building commands
+    pub selection: &'a mut Selection,
+}
+
+/// Client state a command the client handles itself may act on
+pub struct ClientContext<'a> {
+    /// The selected resource pack; the game switches textures when it changes
+    pub resource_pack: &'a mut Option<String>,
+    /// The spectator camera, while it is detached from the player
+    pub free_cam: &'a mut Option<FreeCam>,
+    /// Where a new spectator camera starts, and the way the player faces
+    pub eye: Vec3,
+    pub yaw: f32,
+}
+
+/// Result of running a command: feedback line on success, error message on failure
+pub type CommandResult = Result<String, String>;
+
+const HELP: &str = "Commands: /tp x y z, /give <item> [count], /xp <points>[L], /time set|add|query|timelapse, /weather [clear|rain|thunder], /setworldspawn [x y z], /worldborder [radius|off], /gamerule [rule] [value], /locate <structure>, /seed, /stats, /blockinfo [x y z], /gamemode <mode>, //pos1 [x y z], //pos2 [x y z], //set <block>, //fill <block>, //copy, //paste [x y z], //schem save|load <name>, //schem list, /resourcepack [name], /spectate [speed], /kick <player> [reason], /op <player>, /deop <player>, /stop, /help";
+
+/// Split a command line (leading '/' optional) into its name and arguments
+pub fn parse(line: &str) -> Result<(&str, Vec<&str>), String> {
+    let line = line.trim().trim_start_matches('/');
+    let mut parts = line.split_whitespace();
+    let name = parts.next().ok_or_else(|| "Empty command".to_string())?;
+    Ok((name, parts.collect()))
+}
+
+/// Parse and run a console command line on the server
+pub fn execute(line: &str, ctx: &mut CommandContext) -> CommandResult {
+    let (name, args) = parse(line)?;
+    match name {
+        "tp" => cmd_tp(&args, ctx),
+        "give" => cmd_give(&args, ctx),
+        "xp" => cmd_xp(&args, ctx),
+        "time" => cmd_time(&args, ctx),
+        "weather" => cmd_weather(&args, ctx),
+        "setworldspawn" => cmd_setworldspawn(&args, ctx),
+        "worldborder" => cmd_worldborder(&args, ctx),
+        "gamerule" => cmd_gamerule(&args, ctx),
+        "locate" => cmd_locate(&args, ctx),
+        "seed" => Ok(format!("Seed: {}", ctx.world.seed)),
+        "stats" => Ok(world_stats(ctx.world)),
+        "blockinfo" => cmd_blockinfo(&args, ctx),
+        "gamemode" => cmd_gamemode(&args, ctx),
+        "pos1" | "pos2" => cmd_pos(name, &args, ctx),
+        "set" | "fill" => cmd_set(name, &args, ctx),
+        "copy" => cmd_copy(ctx),
+        "paste" => cmd_paste(&args, ctx),
+        "schem" => cmd_schem(&args, ctx),
+        "resourcepack" | "spectate" => Err(format!("/{} is only available in the game client", name)),
+        "help" => Ok(HELP.to_string()),
+        _ => Err(format!("Unknown command: /{} (try /help)", name)),
+    }
+}
+
+/// Parse a coordinate, supporting `~` / `~offset` relative to `current`
+fn parse_coord(arg: &str, current: f32) -> Result<f32, String> {
+    if let Some(rest) = arg.strip_prefix('~') {
+        if rest.is_empty() {
+            return Ok(current);
+        }
+        return rest
+            .parse::<f32>()
+            .map(|offset| current + offset)
+            .map_err(|_| format!("Invalid coordinate: {}", arg));
+    }
+    arg.parse::<f32>().map_err(|_| format!("Invalid coordinate: {}", arg))
+}
+
+fn cmd_tp(args: &[&str], ctx: &mut CommandContext) -> CommandResult {
+    if args.len() != 3 {
+        return Err("Usage: /tp <x> <y> <z>".to_string());
+    }
+    let pos = ctx.player.position;
+    let target = Vec3::new(
+        parse_coord(args[0], pos.x)?,
+        parse_coord(args[1], pos.y)?,
+        parse_coord(args[2], pos.z)?,
+    );
+    // Landing outside the border would leave the player stuck against it
+    let target = ctx.world.clamp_to_border(target, Player::COLLISION_HALF_WIDTH);
+    ctx.player.teleport(target);
+    Ok(format!("Teleported to ({:.1}, {:.1}, {:.1})", target.x, target.y, target.z))
+}
+
+fn cmd_give(args: &[&str], ctx: &mut CommandContext) -> CommandResult {
+    let (item, count) = parse_give(args)?;
+    if ctx.world.inventory.add_item(item, count) {
+        Ok(format!("Gave {} x {}", count, item.name()))
+    } else {
+        Err("Inventory full".to_string())
+    }
+}
+
+/// The item and count of `/give`; a network client's inventory is its own,
+/// so the server only checks them and sends them on
+pub fn parse_give(args: &[&str]) -> Result<(Item, u32), String> {
+    let name = args.first().ok_or_else(|| "Usage: /give <item> [count]".to_string())?;
+    let item = Item::from_name(name)
+        .filter(|item| *item != Item::Block(BlockType::Air))
+        .ok_or_else(|| format!("Unknown item: {}", name))?;
+    let count = match args.get(1) {
+        Some(c) => c.parse::<u32>().map_err(|_| format!("Invalid count: {}", c))?,
+        None => 1,
+    };
+    if count == 0 {
+        return Err("Count must be at least 1".to_string());
+    }
+    Ok((item, count))
+}
+
+/// Add experience points, or whole levels with an `L` after the amount
+fn cmd_xp(args: &[&str], ctx: &mut CommandContext) -> CommandResult {
+    let (amount, levels) = parse_xp(args)?;
+    let experience = &mut ctx.world.experience;
+    add_experience(experience, amount, levels);
+    let unit = if levels { "levels" } else { "points" };
+    Ok(format!("Gave {} {}, now at level {}", amount, unit, experience.level))
+}
+
+/// The amount of `/xp` and whether it is in levels; checked by the server
+/// like `parse_give`
+pub fn parse_xp(args: &[&str]) -> Result<(u32, bool), String> {
+    let arg = args.first().ok_or_else(|| "Usage: /xp <points>[L]".to_string())?;
+    let (amount, levels) = match arg.strip_suffix(['L', 'l']) {
+        Some(amount) => (amount, true),
+        None => (*arg, false),
+    };
+    let amount = amount.parse::<u32>().map_err(|_| format!("Invalid amount: {}", arg))?;
+    Ok((amount, levels))
+}
+
+/// What `/xp` does: `amount` points, or whole levels
+pub fn add_experience(experience: &mut Experience, amount: u32, levels: bool) {
+    if levels {
+        experience.level += amount;
+    } else {
+        experience.add(amount);
+    }
+}
+
+fn cmd_time(args: &[&str], ctx: &mut CommandContext) -> CommandResult {
+    match args {
+        ["set", value] => {
+            let ticks = time::parse_time(value).ok_or_else(|| format!("Invalid time: {}", value))?;
+            ctx.world.time.set_time_of_day(ticks);
+            Ok(format!("Set the time to {}", ticks))
+        }
+        ["add", value] => {
+            let ticks = value.parse::<u64>().map_err(|_| format!("Invalid tick count: {}", value))?;
+            if ticks > time::MAX_ADD_TICKS {
+                return Err(format!("Can't add more than {} ticks at once", time::MAX_ADD_TICKS));
+            }
+            ctx.world.time.add_ticks(ticks);
+            Ok(format!("Added {} ticks, the time is now {} (day {})", ticks, ctx.world.time.time_of_day(), ctx.world.time.day()))
+        }
+        ["query"] | [] => Ok(format!("Time: {} (day {})", ctx.world.time.time_of_day(), ctx.world.time.day())),
+        ["timelapse"] => Ok(match ctx.world.time.timelapse() {
+            Some(factor) => format!("Timelapse: {}x", factor),
+            None => "Timelapse is off".to_string(),
+        }),
+        ["timelapse", "off"] => {
+            ctx.world.time.set_timelapse(None);
+            Ok("Timelapse off, time runs at normal speed".to_string())
+        }
+        ["timelapse", value] => {
+            let factor = value
+                .parse::<u32>()
+                .ok()
+                .filter(|factor| (2..=time::MAX_TIMELAPSE).contains(factor))
+                .ok_or_else(|| format!("Timelapse factor must be 2 to {}, or off", time::MAX_TIMELAPSE))?;
+            ctx.world.time.set_timelapse(Some(factor));
+            Ok(format!("Timelapse on, time runs {}x faster", factor))
+        }
+        _ => Err("Usage: /time set <day|noon|night|midnight|ticks> | /time add <ticks> | /time query | /time timelapse [factor|off]".to_string()),
+    }
+}
+
+fn cmd_weather(args: &[&str], ctx: &mut CommandContext) -> CommandResult {
+    match args {
+        [] => Ok(format!("Weather: {}", ctx.world.time.weather().name())),
+        [name] => {
+            let weather = Weather::from_name(name).ok_or_else(|| format!("Unknown weather: {}", name))?;
+            ctx.world.time.set_weather(weather);
+            Ok(format!("Set the weather to {}", weather.name()))
+        }
+        _ => Err("Usage: /weather [clear|rain|thunder]".to_string()),
+    }
+}
+
+fn cmd_setworldspawn(args: &[&str], ctx: &mut CommandContext) -> CommandResult {
+    let pos = ctx.player.position;
+    let target = match args {
+        [] => pos,
+        [x, y, z] => Vec3::new(parse_coord(x, pos.x)?, parse_coord(y, pos.y)?, parse_coord(z, pos.z)?),
+        _ => return Err("Usage: /setworldspawn [x y z]".to_string()),
+    };
+    let spawn = (target.x.floor() as i32, target.y.floor() as i32, target.z.floor() as i32);
+    ctx.world.spawn = Some(spawn);
+    Ok(format!("Set the world spawn to ({}, {}, {})", spawn.0, spawn.1, spawn.2))
+}
+
+fn cmd_worldborder(args: &[&str], ctx: &mut CommandContext) -> CommandResult {
+    match args {
+        [] => Ok(match ctx.world.border {
+            Some(radius) => format!("World border: {} blocks from the origin", radius),
+            None => "There is no world border".to_string(),
+        }),
+        [radius] => {
+            ctx.world.border = world_border::parse_radius(radius)?;
+            Ok(match ctx.world.border {
+                Some(radius) => format!("Set the world border to {} blocks from the origin", radius),
+                None => "Removed the world border".to_string(),
+            })
+        }
+        _ => Err("Usage: /worldborder [radius|off]".to_string()),
+    }
+}
+
+fn cmd_gamerule(args: &[&str], ctx: &mut CommandContext) -> CommandResult {
+    let rules = &mut ctx.world.rules;
+    match args {
+        [] => {
+            let values: Vec<String> = GameRules::names().map(|name| format!("{} = {}", name, rules.get(name).unwrap())).collect();
+            Ok(format!("Game rules: {}", values.join(", ")))
+        }
+        [name] => {
+            let value = rules.get(name).ok_or_else(|| format!("Unknown game rule: {}", name))?;
+            Ok(format!("{} = {}", name, value))
+        }
+        [name, value] => {
+            let (name, value) = rules.set(name, value)?;
+            Ok(format!("Set {} to {}", name, value))
+        }
+        _ => Err("Usage: /gamerule [rule] [value]".to_string()),
+    }
+}
+
+fn cmd_locate(args: &[&str], ctx: &mut CommandContext) -> CommandResult {
+    let [name] = args else {
+        return Err("Usage: /locate <dungeon|village>".to_string());
+    };
+    let kind = StructureKind::from_name(name).ok_or_else(|| format!("Unknown structure: {}", name))?;
+    let structure = ctx
+        .world
+        .nearest_structure(kind, ctx.player.position)
+        .ok_or_else(|| format!("No {} generated yet; explore to find one", kind.name()))?;
+    let center = structure.center();
+    Ok(format!(
+        "Nearest {} at ({}, {}, {}), {:.0} blocks away",
+        kind.name(),
+        center.x.floor(),
+        center.y.floor(),
+        center.z.floor(),
+        center.distance(ctx.player.position)
+    ))
+}
+
+/// How much the world holds and how much has changed since it was loaded
+fn world_stats(world: &World) -> String {
+    let dirty = world.chunks.values().filter(|chunk| chunk.dirty).count();
+    format!(
+        "Loaded chunks: {} ({} to redraw), generated: {}; entities: {} items; block entities: {}; blocks changed: {}; memory: about {:.1} MiB",
+        world.chunks.len(),
+        dirty,
+        world.stats.chunks_generated,
+        world.items.len(),
+        world.block_entities.len(),
+        world.stats.blocks_changed,
+        world.memory_estimate() as f64 / (1024.0 * 1024.0)
+    )
+}
+
+/// Describe a block: what it is, what it holds, its light and biome. The
+/// client fills in the block the player looks at; without coordinates the
+/// block under the player's feet is described.
+fn cmd_blockinfo(args: &[&str], ctx: &mut CommandContext) -> CommandResult {
+    let pos = ctx.player.position;
+    let (x, y, z) = match args {
+        [] => {
+            let (x, y, z) = feet_block(ctx.player);
+            (x, y - 1, z)
+        }
+        [x, y, z] => {
+            let target = Vec3::new(parse_coord(x, pos.x)?, parse_coord(y, pos.y)?, parse_coord(z, pos.z)?);
+            (target.x.floor() as i32, target.y.floor() as i32, target.z.floor() as i32)
+        }
+        _ => return Err("Usage: /blockinfo [x y z]".to_string()),
+    };
+    let world = &*ctx.world;
+    let block = world.get_block_at(x, y, z).ok_or_else(|| format!("The chunk at ({}, {}, {}) isn't loaded", x, y, z))?;
+
+    let mut properties = vec![if block.is_full_cube() { "full block" } else if block.is_solid() { "partial block" } else { "not solid" }];
+    if block.is_liquid() {
+        properties.push("liquid");
+    }
+    if block.has_gravity() {
+        properties.push("falls");
+    }
+    let contents = match world.block_entities.get(&(x, y, z)) {
+        Some(BlockEntity::Furnace(furnace)) if furnace.is_burning() => ", burning".to_string(),
+        Some(entity) => format!(", holding {} items", entity.slots().iter().flatten().map(|stack| stack.count).sum::<u32>()),
+        None => String::new(),
+    };
+
+    let (chunk_x, chunk_z) = (x.div_euclid(CHUNK_SIZE as i32), z.div_euclid(CHUNK_SIZE as i32));
+    let lights = ChunkSnapshot::capture(&world.chunks, chunk_x, chunk_z).map(|snapshot| snapshot.lights().to_vec()).unwrap_or_default();
+    let light = (mesh::block_light(&lights, [x, y, z]) * MAX_LIGHT as f32).round();
+    let sky = if world.highest_block(x, z).is_none_or(|(top, _)| top <= y) { "open to the sky" } else { "under cover" };
+
+    let generator = WorldGenerator::new(world.seed);
+    let biome = if generator.is_mountain(x, z) { "mountains" } else { "plains" };
+    Ok(format!(
+        "Block at ({}, {}, {}): {}, {}{}; block light {}/{}, {}; biome {}, temperature {:.2}",
+        x,
+        y,
+        z,
+        block.name(),
+        properties.join(", "),
+        contents,
+        light,
+        MAX_LIGHT,
+        sky,
+        biome,
+        generator.temperature(x, y, z)
+    ))
+}
+
+fn cmd_gamemode(args: &[&str], ctx: &mut CommandContext) -> CommandResult {
+    let mode_name = args.first().ok_or_else(|| "Usage: /gamemode <survival|creative>".to_string())?;
+    let mode = GameMode::from_name(mode_name).ok_or_else(|| format!("Unknown game mode: {}", mode_name))?;
+    ctx.player.game_mode = mode;
+    Ok(format!("Game mode set to {}", mode.name()))
+}
+
+/// The block the player's feet are in
+fn feet_block(player: &Player) -> (i32, i32, i32) {
+    let feet = player.position.floor().as_ivec3();
+    (feet.x, feet.y, feet.z)
+}
+
+/// The selected cuboid, if it is small enough for one command
+fn selected_region(selection: &Selection) -> Result<Bounds, String> {
+    let (min, max) = selection.bounds().ok_or_else(|| "Select a region with //pos1 and //pos2 first".to_string())?;
+    let volume = region::volume(min, max);
+    if volume > MAX_REGION_BLOCKS {
+        return Err(format!("The region has {} blocks, more than the {} allowed", volume, MAX_REGION_BLOCKS));
+    }
+    Ok((min, max))
+}
+
+/// Set a corner of the region, at the player's feet without coordinates
+fn cmd_pos(name: &str, args: &[&str], ctx: &mut CommandContext) -> CommandResult {
+    let pos = ctx.player.position;
+    let corner = match args {
+        [] => feet_block(ctx.player),
+        [x, y, z] => {
+            let target = Vec3::new(parse_coord(x, pos.x)?, parse_coord(y, pos.y)?, parse_coord(z, pos.z)?);
+            (target.x.floor() as i32, target.y.floor() as i32, target.z.floor() as i32)
+        }
+        _ => return Err(format!("Usage: //{} [x y z]", name)),
+    };
+    let (which, slot) = match name {
+        "pos1" => ("First", &mut ctx.selection.pos1),
+        _ => ("Second", &mut ctx.selection.pos2),
+    };
+    *slot = Some(corner);
+    let size = match ctx.selection.bounds() {
+        Some((min, max)) => format!(" ({} blocks)", region::volume(min, max)),
+        None => String::new(),
+    };
+    Ok(format!("{} position set to ({}, {}, {}){}", which, corner.0, corner.1, corner.2, size))
+}
+
+/// `//set` changes every block of the region, `//fill` only the air in it
+fn cmd_set(name: &str, args: &[&str], ctx: &mut CommandContext) -> CommandResult {
+    let [block_name] = args else {
+        return Err(format!("Usage: //{} <block>", name));
+    };
+    let block = BlockType::from_name(block_name).ok_or_else(|| format!("Unknown block: {}", block_name))?;
+    let (min, max) = selected_region(ctx.selection)?;
+    let changed = if name == "set" {
+        ctx.world.fill_region(min, max, block)
+    } else {
+        let air: Vec<_> = region::cuboid(min, max)
+            .filter(|&(x, y, z)| ctx.world.get_block_at(x, y, z) == Some(BlockType::Air))
+            .map(|pos| (pos, block))
+            .collect();
+        ctx.world.set_blocks(air)
+    };
+    Ok(format!("Changed {} blocks to {}", changed, block.name()))
+}
+
+/// Copy the region, relative to the player's feet
+fn cmd_copy(ctx: &mut CommandContext) -> CommandResult {
+    let (min, max) = selected_region(ctx.selection)?;
+    let clipboard = Clipboard::copy(ctx.world, min, max, feet_block(ctx.player));
+    let count = clipboard.blocks.len();
+    ctx.selection.clipboard = Some(clipboard);
+    Ok(format!("Copied {} blocks", count))
+}
+
+/// Paste the copied blocks, placed the same way from the player's feet, or
+/// the given coordinates, as they were when copied
+fn cmd_paste(args: &[&str], ctx: &mut CommandContext) -> CommandResult {
+    let pos = ctx.player.position;
+    let origin = match args {
+        [] => feet_block(ctx.player),
+        [x, y, z] => {
+            let target = Vec3::new(parse_coord(x, pos.x)?, parse_coord(y, pos.y)?, parse_coord(z, pos.z)?);
+            (target.x.floor() as i32, target.y.floor() as i32, target.z.floor() as i32)
+        }
+        _ => return Err("Usage: //paste [x y z]".to_string()),
+    };
+    let clipboard = ctx.selection.clipboard.as_ref().ok_or_else(|| "Copy a region with //copy first".to_string())?;
+    let changed = ctx.world.set_blocks(clipboard.placed_at(origin));
+    Ok(format!("Pasted, changing {} blocks", changed))
+}
+
+/// Save the region to a schematic file, load one into the clipboard, or list them
+fn cmd_schem(args: &[&str], ctx: &mut CommandContext) -> CommandResult {
+    let dir = std::path::Path::new(SCHEMATICS_DIR);
+    match args {
+        ["save", name] => {
+            let (min, max) = selected_region(ctx.selection)?;
+            let clipboard = Clipboard::copy(ctx.world, min, max, min);
+            let path = schematic::save(dir, name, &clipboard)?;
+            Ok(format!("Saved {} blocks to {}", clipboard.blocks.len(), path.display()))
+        }
+        ["load", name] => {
+            let loaded = schematic::load(dir, name)?;
+            let (x, y, z) = loaded.clipboard.size;
+            let unknown = match loaded.unknown {
+                0 => String::new(),
+                count => format!("; {} blocks unknown to this game became air", count),
+            };
+            ctx.selection.clipboard = Some(loaded.clipboard);
+            Ok(format!("Loaded {} ({}x{}x{}) into the clipboard, place it with //paste{}", name, x, y, z, unknown))
+        }
+        ["list"] => {
+            let names = schematic::list(dir);
+            Ok(if names.is_empty() {
+                format!("No schematics in {}/", SCHEMATICS_DIR)
+            } else {
+                format!("Schematics: {}", names.join(", "))
+            })
+        }
+        _ => Err("Usage: //schem save <name> | //schem load <name> | //schem list".to_string()),
+    }
+}
+
+/// The command line to send to the server: a `/blockinfo` without
+/// coordinates gets those of the block the player looks at, if any
+pub fn with_target(line: String, target: Option<(i32, i32, i32)>) -> String {
+    match (parse(&line), target) {
+        (Ok(("blockinfo", args)), Some((x, y, z))) if args.is_empty() => format!("/blockinfo {} {} {}", x, y, z),
+        _ => line,
+    }
+}
+
+/// Run the command if it is one the client handles itself, without the server
+pub fn execute_client(line: &str, ctx: &mut ClientContext) -> Option<CommandResult> {
+    match parse(line) {
+        Ok(("resourcepack", args)) => Some(cmd_resourcepack(&args, ctx.resource_pack)),
+        Ok(("spectate", args)) => Some(cmd_spectate(&args, ctx)),
+        _ => None,
+    }
+}
+
+/// Toggle the spectator camera; with a speed, start it or change its speed
+fn cmd_spectate(args: &[&str], ctx: &mut ClientContext) -> CommandResult {
+    match args {
+        [] if ctx.free_cam.is_some() => {
+            *ctx.free_cam = None;
+            Ok("Spectator camera off".to_string())
+        }
+        [] => {
+            *ctx.free_cam = Some(FreeCam::new(ctx.eye, ctx.yaw));
+            Ok("Spectator camera on (F6 or /spectate to return)".to_string())
+        }
+        [speed] => {
+            let speed = speed.parse::<f32>().map_err(|_| format!("Invalid speed: {}", speed))?;
+            let free_cam = ctx.free_cam.get_or_insert_with(|| FreeCam::new(ctx.eye, ctx.yaw));
+            free_cam.set_speed(speed);
+            Ok(format!("Spectator camera speed: {:.1} blocks/s", free_cam.speed()))
+        }
+        _ => Err("Usage: /spectate [speed]".to_string()),
+    }
+}
+
+fn cmd_resourcepack(args: &[&str], resource_pack: &mut Option<String>) -> CommandResult {
+    let packs = resource_pack::available(std::path::Path::new(RESOURCE_PACKS_DIR));
+    match args {
+        [] => {
+            let current = resource_pack.as_deref().unwrap_or("default");
+            let available = if packs.is_empty() { "none".to_string() } else { packs.join(", ") };
+            Ok(format!("Resource pack: {} (available: {})", current, available))
+        }
+        ["default"] => {
+            *resource_pack = None;
+            Ok("Using the default textures".to_string())
+        }
+        [name] if packs.iter().any(|pack| pack == name) => {
+            *resource_pack = Some(name.to_string());
+            Ok(format!("Using resource pack {}", name))
+        }
+        [name] => Err(format!("No resource pack named {} in {}/", name, RESOURCE_PACKS_DIR)),
+        _ => Err("Usage: /resourcepack [name|default]".to_string()),
+    }
+}
@@ -0,0 +1,32 @@
+//! Directions for the compass item and the F3 overlay. North is -Z and east
+//! is +X, as on the minimap.
+
+use glam::Vec3;
+
+/// Compass heading of a camera yaw in degrees: 0 north, 90 east, 180 south, 270 west
+pub fn heading(yaw: f32) -> f32 {
+    let (forward_x, forward_z) = (yaw.cos(), yaw.sin());
+    forward_x.atan2(-forward_z).to_degrees().rem_euclid(360.0)
+}
+
+/// Nearest cardinal direction of a heading, with the axis it points along
+pub fn cardinal(heading: f32) -> (&'static str, &'static str) {
+    match ((heading / 90.0).round() as i32).rem_euclid(4) {
+        0 => ("north", "-Z"),
+        1 => ("east", "+X"),
+        2 => ("south", "+Z"),
+        _ => ("west", "-X"),
+    }
+}
+
+/// Angle of a compass needle pointing from `position` to `target` in radians,
+/// clockwise from straight ahead for a camera facing `yaw`. Standing on the
+/// target the needle points ahead.
+pub fn needle_angle(position: Vec3, yaw: f32, target: Vec3) -> f32 {
+    let offset = target - position;
+    if offset.x == 0.0 && offset.z == 0.0 {
+        return 0.0;
+    }
+    let angle = offset.z.atan2(offset.x) - yaw;
+    (angle + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI
+}
@@ -1,14 +1,84 @@
+use crate::chunk_loader::FrameBudget;
+use crate::minimap::MinimapMode;
+use crate::renderer::GraphicsBackend;
+use crate::storage::Storage;
+use crate::weather::Particles;
 use serde::{Deserialize, Serialize};
-use std::fs;
 use std::path::Path;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::{self, Receiver};
 
-#[derive(Serialize, Deserialize, Clone)]
+// Missing fields fall back to their defaults so older config files keep loading
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
 pub struct GameConfig {
     pub sensitivity: f32,
     pub walk_speed: f32,
     pub view_distance: i32,
+    /// Most chunks added to the world and most chunk meshes built per frame,
+    /// so a burst of new chunks is spread over several frames
+    pub chunks_per_frame: usize,
+    /// Milliseconds a frame may spend adding chunks, and as many building
+    /// chunk meshes; a heavy chunk ends the frame's share before the count does
+    pub chunk_budget_ms: f32,
+    /// Chunks around the player in which blocks, weather and furnaces
+    /// update; farther chunks are only drawn
+    pub simulation_distance: i32,
     pub fov: f32,
     pub show_debug: bool,
+    /// Multiplier on `sensitivity` for horizontal mouse look
+    pub sensitivity_x_scale: f32,
+    /// Multiplier on `sensitivity` for vertical mouse look
+    pub sensitivity_y_scale: f32,
+    pub invert_y: bool,
+    /// Mouse acceleration strength; 0 gives a linear response
+    pub mouse_acceleration: f32,
+    /// Use raw (unaccelerated) device motion instead of OS cursor movement
+    pub raw_input: bool,
+    /// Seconds mouse look takes to catch up with the mouse; 0 follows it exactly
+    pub camera_smoothing: f32,
+    /// Ease zoom and sprint FOV changes instead of snapping
+    pub smooth_fov: bool,
+    /// Draw the camera between physics steps instead of at the last one
+    pub interpolate_movement: bool,
+    /// Bob the camera while walking and dip it on landing
+    pub view_bobbing: bool,
+    /// Sync presentation to the display refresh rate
+    pub vsync: bool,
+    /// Fade distant terrain into the sky color
+    pub fog: bool,
+    /// Blend block light across faces instead of lighting each face evenly
+    pub smooth_lighting: bool,
+    /// Draw distant blocks with smaller copies of their textures, which keeps
+    /// them from shimmering
+    pub mipmapping: bool,
+    /// How much rain and snow is drawn: all, decreased or minimal
+    pub particles: Particles,
+    /// Corner minimap: off, north_up or rotating
+    pub minimap_mode: MinimapMode,
+    /// UI pixel scale; 0 picks the largest scale that fits the window
+    pub gui_scale: u32,
+    /// Sound effect volume in [0, 1]
+    pub master_volume: f32,
+    /// Folder or zip in `resourcepacks/` to take block textures from; none
+    /// uses the built-in textures
+    pub resource_pack: Option<String>,
+    /// Path of a 64x64 (or old 64x32) PNG skin the player wears for the
+    /// others and the spectator camera; none wears the default skin
+    pub skin: Option<String>,
+    /// Graphics API to try first: auto, vulkan, dx12, metal or gl. Applies on
+    /// the next start.
+    pub graphics_backend: GraphicsBackend,
+    /// Start in fullscreen; F11 toggles it
+    pub fullscreen: bool,
+    /// Name of the monitor to go fullscreen on; none uses the one the window
+    /// is on. The names are in the log at startup.
+    pub monitor: Option<String>,
+    /// Window size in pixels, also used in fullscreen if the monitor has a
+    /// video mode that size; none opens a 1280x720 window and goes
+    /// fullscreen at the desktop resolution
+    pub resolution: Option<[u32; 2]>,
 }
 
 impl Default for GameConfig {
@@ -17,27 +87,183 @@ impl Default for GameConfig {
             sensitivity: 0.005,
             walk_speed: 4.3,
             view_distance: 6,
+            chunks_per_frame: 4,
+            chunk_budget_ms: 4.0,
+            simulation_distance: 4,
             fov: 70.0,
             show_debug: false,
+            sensitivity_x_scale: 1.0,
+            sensitivity_y_scale: 1.0,
+            invert_y: false,
+            mouse_acceleration: 0.0,
+            raw_input: true,
+            camera_smoothing: 0.0,
+            smooth_fov: true,
+            interpolate_movement: true,
+            view_bobbing: true,
+            vsync: true,
+            fog: true,
+            smooth_lighting: true,
+            mipmapping: false,
+            particles: Particles::All,
+            minimap_mode: MinimapMode::NorthUp,
+            gui_scale: 0,
+            master_volume: 1.0,
+            resource_pack: None,
+            skin: None,
+            graphics_backend: GraphicsBackend::Auto,
+            fullscreen: false,
+            monitor: None,
+            resolution: None,
         }
     }
 }
 
-impl GameConfig {
-    pub fn load(path: &str) -> Self {
-        if Path::new(path).exists() {
-            if let Ok(data) = fs::read_to_string(path) {
-                if let Ok(config) = serde_json::from_str(&data) {
-                    return config;
-                }
-            }
+/// Bundles of the settings that trade looks for frame rate, set at once
+/// with the Graphics button of the Options screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphicsPreset {
+    Low,
+    Medium,
+    High,
+    Ultra,
+    /// The settings match none of the presets
+    Custom,
+}
+
+/// What a preset sets
+#[derive(PartialEq)]
+struct GraphicsSettings {
+    view_distance: i32,
+    fog: bool,
+    smooth_lighting: bool,
+    mipmapping: bool,
+    particles: Particles,
+}
+
+impl GraphicsPreset {
+    pub const PRESETS: [GraphicsPreset; 4] = [GraphicsPreset::Low, GraphicsPreset::Medium, GraphicsPreset::High, GraphicsPreset::Ultra];
+
+    /// Next preset for the Graphics button; Custom starts over at Low
+    pub fn next(self) -> Self {
+        match self {
+            GraphicsPreset::Low => GraphicsPreset::Medium,
+            GraphicsPreset::Medium => GraphicsPreset::High,
+            GraphicsPreset::High => GraphicsPreset::Ultra,
+            GraphicsPreset::Ultra | GraphicsPreset::Custom => GraphicsPreset::Low,
         }
-        Self::default()
     }
 
-    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn name(self) -> &'static str {
+        match self {
+            GraphicsPreset::Low => "Low",
+            GraphicsPreset::Medium => "Medium",
+            GraphicsPreset::High => "High",
+            GraphicsPreset::Ultra => "Ultra",
+            GraphicsPreset::Custom => "Custom",
+        }
+    }
+
+    /// Medium is what a new config starts with
+    fn settings(self) -> Option<GraphicsSettings> {
+        let (view_distance, fog, smooth_lighting, mipmapping, particles) = match self {
+            GraphicsPreset::Low => (4, false, false, false, Particles::Minimal),
+            GraphicsPreset::Medium => (6, true, true, false, Particles::All),
+            GraphicsPreset::High => (10, true, true, true, Particles::All),
+            GraphicsPreset::Ultra => (16, true, true, true, Particles::All),
+            GraphicsPreset::Custom => return None,
+        };
+        Some(GraphicsSettings { view_distance, fog, smooth_lighting, mipmapping, particles })
+    }
+}
+
+impl GameConfig {
+    /// The chunk work one frame may do, from `chunks_per_frame` and
+    /// `chunk_budget_ms`
+    pub fn chunk_budget(&self) -> FrameBudget {
+        let time = Duration::try_from_secs_f32(self.chunk_budget_ms.max(0.0) / 1000.0).unwrap_or(Duration::MAX);
+        FrameBudget { chunks: self.chunks_per_frame.max(1), time }
+    }
+
+    /// The preset the graphics settings match, Custom if none
+    pub fn graphics_preset(&self) -> GraphicsPreset {
+        let current = GraphicsSettings {
+            view_distance: self.view_distance,
+            fog: self.fog,
+            smooth_lighting: self.smooth_lighting,
+            mipmapping: self.mipmapping,
+            particles: self.particles,
+        };
+        GraphicsPreset::PRESETS.into_iter().find(|preset| preset.settings().as_ref() == Some(&current)).unwrap_or(GraphicsPreset::Custom)
+    }
+
+    /// Change the settings `preset` bundles; Custom changes nothing
+    pub fn set_graphics_preset(&mut self, preset: GraphicsPreset) {
+        let Some(settings) = preset.settings() else {
+            return;
+        };
+        self.view_distance = settings.view_distance;
+        self.fog = settings.fog;
+        self.smooth_lighting = settings.smooth_lighting;
+        self.mipmapping = settings.mipmapping;
+        self.particles = settings.particles;
+    }
+
+    /// The config saved at `path`; the defaults if there is none or it can't
+    /// be read
+    pub fn load(storage: &dyn Storage, path: &str) -> Self {
+        Self::try_load(storage, path).unwrap_or_default()
+    }
+
+    pub fn try_load(storage: &dyn Storage, path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let data = storage.read_to_string(Path::new(path))?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    pub fn save(&self, storage: &dyn Storage, path: &str) -> Result<(), Box<dyn std::error::Error>> {
         let data = serde_json::to_string_pretty(self)?;
-        fs::write(path, data)?;
+        storage.write(Path::new(path), data.as_bytes())?;
         Ok(())
     }
 }
+
+/// Notices changes to the config file made outside the game, in a text
+/// editor say, so they can be applied without a restart. Not available in
+/// the browser, whose config is in local storage.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct ConfigWatcher {
+    /// Watches until dropped
+    _watcher: notify::RecommendedWatcher,
+    changes: Receiver<()>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ConfigWatcher {
+    /// Watch the config file at `path`. Its directory is watched, as editors
+    /// often save by replacing the file.
+    pub fn new(path: &str) -> notify::Result<Self> {
+        use notify::{Event, RecursiveMode, Watcher};
+
+        let path = Path::new(path);
+        let file_name = path.file_name().map(|name| name.to_os_string());
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let (tx, changes) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            let written = event.kind.is_create() || event.kind.is_modify();
+            if written && event.paths.iter().any(|path| path.file_name() == file_name.as_deref()) {
+                tx.send(()).ok();
+            }
+        })?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(Self { _watcher: watcher, changes })
+    }
+
+    /// Whether the file was written since the last call. The game's own
+    /// saves count too.
+    pub fn changed(&self) -> bool {
+        self.changes.try_iter().count() > 0
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::VecDeque;
+use winit::event::{ElementState, KeyEvent};
+use winit::keyboard::{Key, NamedKey};
+
+/// Maximum number of lines kept in the scrollback
+const MAX_SCROLLBACK: usize = 100;
+/// Maximum number of submitted lines remembered for Up/Down recall
+const MAX_HISTORY: usize = 50;
+/// Maximum length of the input line in characters
+const MAX_INPUT_LEN: usize = 100;
+/// Number of scrollback lines shown while the console is open
+pub const VISIBLE_LINES: usize = 10;
+/// Number of recent lines shown as the chat log while the console is closed
+pub const CHAT_LOG_LINES: usize = 5;
+/// Seconds a line stays in the chat log, including the fade-out
+const CHAT_LOG_DURATION: f32 = 10.0;
+/// Seconds spent fading out at the end
+const CHAT_LOG_FADE: f32 = 1.0;
+
+/// What the caller should do after the console consumed a key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsoleAction {
+    None,
+    /// Input line was edited; UI needs a rebuild
+    Changed,
+    /// Enter was pressed with this line; console has closed
+    Submit(String),
+    /// Console was closed without submitting
+    Close,
+}
+
+/// A scrollback line and how long ago it was printed
+struct Line {
+    text: String,
+    age: f32,
+}
+
+/// Chat/command console overlay state: input line, scrollback, and input history.
+/// While closed, the newest lines show as a chat log that fades out.
+pub struct Console {
+    open: bool,
+    input: String,
+    scrollback: VecDeque<Line>,
+    /// A line was printed since the last update
+    printed: bool,
+    history: Vec<String>,
+    history_cursor: Option<usize>,
+    scroll_offset: usize,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            input: String::new(),
+            scrollback: VecDeque::new(),
+            printed: false,
+            history: Vec::new(),
+            history_cursor: None,
+            scroll_offset: 0,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Open the console with some pre-filled input (e.g. "/" for commands)
+    pub fn open(&mut self, initial: &str) {
+        self.open = true;
+        self.input = initial.to_string();
+        self.history_cursor = None;
+        self.scroll_offset = 0;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+        self.input.clear();
+        self.history_cursor = None;
+    }
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// Append a line to the scrollback
+    pub fn print(&mut self, line: impl Into<String>) {
+        self.scrollback.push_back(Line { text: line.into(), age: 0.0 });
+        self.printed = true;
+        while self.scrollback.len() > MAX_SCROLLBACK {
+            self.scrollback.pop_front();
+        }
+    }
+
+    /// The scrollback lines currently in view, oldest first
+    pub fn visible_lines(&self) -> impl Iterator<Item = &String> {
+        let end = self.scrollback.len().saturating_sub(self.scroll_offset);
+        let start = end.saturating_sub(VISIBLE_LINES);
+        self.scrollback.range(start..end).map(|line| &line.text)
+    }
+
+    /// Age the lines by `delta_time` seconds. Returns true if the chat log
+    /// changed (new line, one fading or expired) and the UI needs rebuilding.
+    pub fn update(&mut self, delta_time: f32) -> bool {
+        let printed = std::mem::take(&mut self.printed);
+        let mut fading = false;
+        for line in self.scrollback.iter_mut().rev().take(CHAT_LOG_LINES) {
+            if line.age < CHAT_LOG_DURATION {
+                line.age += delta_time;
+                fading |= line.age > CHAT_LOG_DURATION - CHAT_LOG_FADE;
+            }
+        }
+        printed || fading
+    }
+
+    /// The chat log: recent lines, oldest first, with their opacity in [0, 1]
+    pub fn chat_log(&self) -> impl Iterator<Item = (&str, f32)> {
+        let start = self.scrollback.len().saturating_sub(CHAT_LOG_LINES);
+        self.scrollback.range(start..).filter(|line| line.age < CHAT_LOG_DURATION).map(|line| {
+            let alpha = ((CHAT_LOG_DURATION - line.age) / CHAT_LOG_FADE).clamp(0.0, 1.0);
+            (line.text.as_str(), alpha)
+        })
+    }
+
+    pub fn push_str(&mut self, text: &str) {
+        for c in text.chars() {
+            if !c.is_control() && self.input.chars().count() < MAX_INPUT_LEN {
+                self.input.push(c);
+            }
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        self.input.pop();
+    }
+
+    /// Take the current input, record it in the history, and close the console
+    pub fn submit(&mut self) -> Option<String> {
+        let line = self.input.trim().to_string();
+        self.close();
+        if line.is_empty() {
+            return None;
+        }
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+            if self.history.len() > MAX_HISTORY {
+                self.history.remove(0);
+            }
+        }
+        Some(line)
+    }
+
+    /// Recall an older history entry into the input line
+    pub fn history_prev(&mut self) {
+        if self.history.is_empty() {
+            return;
+        }
+        let idx = match self.history_cursor {
+            Some(0) => 0,
+            Some(i) => i - 1,
+            None => self.history.len() - 1,
+        };
+        self.history_cursor = Some(idx);
+        self.input = self.history[idx].clone();
+    }
+
+    /// Step towards newer history entries, ending at an empty line
+    pub fn history_next(&mut self) {
+        match self.history_cursor {
+            Some(i) if i + 1 < self.history.len() => {
+                self.history_cursor = Some(i + 1);
+                self.input = self.history[i + 1].clone();
+            }
+            Some(_) => {
+                self.history_cursor = None;
+                self.input.clear();
+            }
+            None => {}
+        }
+    }
+
+    pub fn scroll_up(&mut self) {
+        let max_offset = self.scrollback.len().saturating_sub(VISIBLE_LINES);
+        self.scroll_offset = (self.scroll_offset + 1).min(max_offset);
+    }
+
+    pub fn scroll_down(&mut self) {
+        self.scroll_offset = self.scroll_offset.saturating_sub(1);
+    }
+
+    /// Feed a keyboard event to the open console
+    pub fn handle_key(&mut self, event: &KeyEvent) -> ConsoleAction {
+        if !self.open || event.state != ElementState::Pressed {
+            return ConsoleAction::None;
+        }
+
+        match &event.logical_key {
+            Key::Named(NamedKey::Escape) => {
+                self.close();
+                ConsoleAction::Close
+            }
+            Key::Named(NamedKey::Enter) => match self.submit() {
+                Some(line) => ConsoleAction::Submit(line),
+                None => ConsoleAction::Close,
+            },
+            Key::Named(NamedKey::Backspace) => {
+                self.backspace();
+                ConsoleAction::Changed
+            }
+            Key::Named(NamedKey::ArrowUp) => {
+                self.history_prev();
+                ConsoleAction::Changed
+            }
+            Key::Named(NamedKey::ArrowDown) => {
+                self.history_next();
+                ConsoleAction::Changed
+            }
+            Key::Named(NamedKey::PageUp) => {
+                self.scroll_up();
+                ConsoleAction::Changed
+            }
+            Key::Named(NamedKey::PageDown) => {
+                self.scroll_down();
+                ConsoleAction::Changed
+            }
+            _ => match &event.text {
+                Some(text) => {
+                    self.push_str(text);
+                    ConsoleAction::Changed
+                }
+                None => ConsoleAction::None,
+            },
+        }
+    }
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new()
+    }
+}
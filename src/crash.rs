@@ -0,0 +1,258 @@
+//! What happens when the game panics. The hook installed by `install` writes
+//! a crash report to `crash-reports/` with the panic, a backtrace, the system
+//! and the last frame timings, saves the chunks changed since the last save
+//! and the player's state to an emergency file next to the world, and aborts.
+//! The next start of the world loads the emergency file back in, and the host
+//! carries on where and as the player was.
+//!
+//! The hook can't reach the game, which is stuck mid-frame, so the game keeps
+//! a `CrashGuard` up to date with what the hook needs: a `ChunkReader` of the
+//! world, and now and then the player and the frame timings.
+
+use crate::chunk::Chunk;
+use crate::chunk_map::ChunkReader;
+use crate::inventory::Inventory;
+use crate::physics::{GameMode, Player};
+use crate::storage::Storage;
+use crate::world::World;
+use serde::{Deserialize, Serialize};
+use std::backtrace::Backtrace;
+use std::fs;
+use std::panic::{self, PanicHookInfo};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+pub const CRASH_REPORTS_DIR: &str = "crash-reports";
+
+/// The emergency save of the world saved at `world_path`
+pub fn emergency_path(world_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.emergency", world_path))
+}
+
+/// What the crash hook knows about the game; cheap to clone
+#[derive(Clone, Default)]
+pub struct CrashGuard {
+    context: Arc<Mutex<CrashContext>>,
+}
+
+#[derive(Default)]
+struct CrashContext {
+    /// None when connected to a server, which saves its own world
+    world: Option<WatchedWorld>,
+    player: Option<PlayerState>,
+    frame_timings: Vec<String>,
+    adapter: Option<String>,
+}
+
+struct WatchedWorld {
+    chunks: ChunkReader,
+    seed: u32,
+    storage: Arc<dyn Storage>,
+    path: PathBuf,
+}
+
+/// The player as written to an emergency save
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerState {
+    pub position: [f32; 3],
+    pub game_mode: GameMode,
+    pub health: u32,
+    pub hunger: u32,
+    pub inventory: Inventory,
+}
+
+impl PlayerState {
+    pub fn new(player: &Player, inventory: &Inventory) -> Self {
+        Self {
+            position: player.position.to_array(),
+            game_mode: player.game_mode,
+            health: player.stats.health,
+            hunger: player.stats.hunger,
+            inventory: inventory.clone(),
+        }
+    }
+
+    /// Put `player` where and as it was; the inventory is the world's
+    pub fn restore(&self, player: &mut Player) {
+        player.teleport(self.position.into());
+        player.game_mode = self.game_mode;
+        player.stats.health = self.health;
+        player.stats.hunger = self.hunger;
+    }
+}
+
+/// Contents of an emergency save
+#[derive(Serialize, Deserialize)]
+pub struct EmergencySave {
+    /// Seed of the world the chunks belong to
+    pub seed: u32,
+    pub chunks: Vec<Chunk>,
+    pub player: Option<PlayerState>,
+}
+
+impl CrashGuard {
+    /// Save the unsaved chunks of `world` to `path` in `storage` in a crash
+    pub fn watch_world(&self, world: &World, storage: Arc<dyn Storage>, path: PathBuf) {
+        self.lock().world = Some(WatchedWorld { chunks: world.chunks.reader(), seed: world.seed, storage, path });
+    }
+
+    /// The player to write in a crash; the game updates it about once a
+    /// second, from `report_fps`, so a crash loses at most that much of it
+    pub fn set_player(&self, player: PlayerState) {
+        self.lock().player = Some(player);
+    }
+
+    /// Frame timings for the report, as shown by F3
+    pub fn set_frame_timings(&self, lines: Vec<String>) {
+        self.lock().frame_timings = lines;
+    }
+
+    /// Name and backend of the graphics adapter, for the report
+    pub fn set_adapter(&self, adapter: String) {
+        self.lock().adapter = Some(adapter);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CrashContext> {
+        self.context.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Install the crash hook. Panics are still printed as before.
+pub fn install() -> CrashGuard {
+    let guard = CrashGuard::default();
+    let context = Arc::clone(&guard.context);
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        // Another thread panicking meanwhile leaves the report to the first
+        static HANDLING: AtomicBool = AtomicBool::new(false);
+        if HANDLING.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        // The panicking thread may be the one holding the lock
+        let context = match context.try_lock() {
+            Ok(context) => Some(context),
+            Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        };
+        handle_panic(info, context.as_deref());
+        std::process::abort();
+    }));
+    guard
+}
+
+fn handle_panic(info: &PanicHookInfo, context: Option<&CrashContext>) {
+    let message = panic_message(info);
+    log::error!("The game crashed: {}", message);
+    let emergency = match context.map(|context| (context.world.as_ref(), context.player.clone())) {
+        Some((Some(world), player)) => match write_emergency_save(&*world.storage, &world.chunks, world.seed, player, &world.path) {
+            Ok(chunks) => format!("{} unsaved chunks and the player written to {}", chunks, world.path.display()),
+            Err(e) => format!("failed: {}", e),
+        },
+        Some((None, _)) => "not needed, the world is on a server".to_string(),
+        None => "skipped, the crash came while the game was updating the crash hook".to_string(),
+    };
+    log::error!("Emergency save: {}", emergency);
+
+    let report = crash_report(&message, context, &emergency, &Backtrace::force_capture().to_string());
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let path = Path::new(CRASH_REPORTS_DIR).join(format!("crash-{}.txt", seconds));
+    match fs::create_dir_all(CRASH_REPORTS_DIR).and_then(|_| fs::write(&path, report)) {
+        Ok(()) => log::error!("Crash report written to {}", path.display()),
+        Err(e) => log::error!("Could not write the crash report: {}", e),
+    }
+}
+
+/// The panic's message with where it happened
+fn panic_message(info: &PanicHookInfo) -> String {
+    let payload = info.payload();
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("(no message)");
+    let thread = std::thread::current().name().unwrap_or("unnamed").to_string();
+    match info.location() {
+        Some(location) => format!("{} (thread {}, at {})", message, thread, location),
+        None => format!("{} (thread {})", message, thread),
+    }
+}
+
+/// Write the unsaved chunks of the world and the player to `path`. Chunks
+/// being changed right now are left out, as their lock may be held by the
+/// thread that crashed. Returns how many chunks were written.
+pub fn write_emergency_save(
+    storage: &dyn Storage,
+    chunks: &ChunkReader,
+    seed: u32,
+    player: Option<PlayerState>,
+    path: &Path,
+) -> Result<usize, String> {
+    let chunks: Vec<Chunk> = chunks.try_values().into_iter().filter(|chunk| chunk.unsaved).map(|chunk| (*chunk).clone()).collect();
+    let count = chunks.len();
+    let data = bincode::serialize(&EmergencySave { seed, chunks, player }).map_err(|e| e.to_string())?;
+    storage.write(path, &data).map_err(|e| e.to_string())?;
+    Ok(count)
+}
+
+/// Put the chunks and inventory of the emergency save at `path` back into
+/// `world`. Returns how many chunks it had and the rest of the player, for
+/// the server to restore, or None if there is no usable emergency save. The
+/// file stays until the world is saved again.
+pub fn recover(storage: &dyn Storage, world: &mut World, path: &Path) -> Option<(usize, Option<PlayerState>)> {
+    let data = storage.read(path).ok()?;
+    let save: EmergencySave = match bincode::deserialize(&data) {
+        Ok(save) => save,
+        Err(e) => {
+            log::error!("Could not read the emergency save {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    if save.seed != world.seed {
+        log::warn!("Ignoring the emergency save {}, which is of another world", path.display());
+        return None;
+    }
+    let count = save.chunks.len();
+    for mut chunk in save.chunks {
+        // Still not in the world file; a second crash saves them again
+        chunk.unsaved = true;
+        world.chunks.insert((chunk.x, chunk.z), chunk);
+    }
+    if let Some(player) = &save.player {
+        let [x, y, z] = player.position;
+        log::info!("The player was at ({:.1}, {:.1}, {:.1}) with {} health when the game crashed", x, y, z, player.health);
+        world.inventory = player.inventory.clone();
+    }
+    log::info!("Recovered {} chunks from the emergency save {}", count, path.display());
+    Some((count, save.player))
+}
+
+/// Text of a crash report
+fn crash_report(message: &str, context: Option<&CrashContext>, emergency: &str, backtrace: &str) -> String {
+    let mut lines = vec![
+        format!("Rustcraft crashed: {}", message),
+        String::new(),
+        format!("Version: {}", env!("CARGO_PKG_VERSION")),
+        format!("System: {} {}", std::env::consts::OS, std::env::consts::ARCH),
+        format!("Threads: {}", std::thread::available_parallelism().map_or(1, |n| n.get())),
+    ];
+    if let Some(adapter) = context.and_then(|context| context.adapter.as_deref()) {
+        lines.push(format!("Graphics adapter: {}", adapter));
+    }
+    if let Some(player) = context.and_then(|context| context.player.as_ref()) {
+        let [x, y, z] = player.position;
+        lines.push(format!("Player: ({:.1}, {:.1}, {:.1}), {:?}, health {}", x, y, z, player.game_mode, player.health));
+    }
+    lines.push(format!("Emergency save: {}", emergency));
+    if let Some(context) = context.filter(|context| !context.frame_timings.is_empty()) {
+        lines.push(String::new());
+        lines.push("Last frame timings:".to_string());
+        lines.extend(context.frame_timings.iter().cloned());
+    }
+    lines.push(String::new());
+    lines.push("Backtrace:".to_string());
+    lines.push(backtrace.to_string());
+    lines.join("\n")
+}
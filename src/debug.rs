@@ -1,5 +1,7 @@
+use crate::buffer_pool::MeshMemory;
+use crate::compass;
 use crate::physics::Player;
-use crate::raycast::raycast;
+use crate::raycast::{raycast, RaycastFilter};
 use crate::camera::Camera;
 use crate::world::World;
 use glam::Vec3;
@@ -11,7 +13,12 @@ pub struct DebugInfo {
     pub on_ground: bool,
     pub chunk_x: i32,
     pub chunk_z: i32,
+    /// Camera angles in radians
+    pub yaw: f32,
+    pub pitch: f32,
     pub looking_at_block: Option<(i32, i32, i32)>,
+    /// Terrain mesh memory, filled in by the game from the renderer
+    pub mesh_memory: MeshMemory,
 }
 
 impl DebugInfo {
@@ -23,7 +30,10 @@ impl DebugInfo {
             on_ground: false,
             chunk_x: 0,
             chunk_z: 0,
+            yaw: 0.0,
+            pitch: 0.0,
             looking_at_block: None,
+            mesh_memory: MeshMemory::default(),
         }
     }
 
@@ -34,17 +44,17 @@ impl DebugInfo {
         self.on_ground = player.on_ground;
         self.chunk_x = (player.position.x / 16.0).floor() as i32;
         self.chunk_z = (player.position.z / 16.0).floor() as i32;
+        self.yaw = camera.yaw;
+        self.pitch = camera.pitch;
         
-        // Update looking at block with raycast
-        let result = raycast(camera.position, camera.get_direction(), 10.0, world);
-        self.looking_at_block = if result.hit {
-            result.position
-        } else {
-            None
-        };
+        // Update looking at block with raycast, water included
+        let direction = camera.get_direction();
+        self.looking_at_block = raycast(camera.position, direction, 10.0, world, RaycastFilter::ALL_BLOCKS).map(|hit| hit.position);
     }
 
     pub fn format_display(&self) -> Vec<String> {
+        let heading = compass::heading(self.yaw);
+        let (direction, axis) = compass::cardinal(heading);
         vec![
             format!("=== DEBUG INFO (F3 to toggle) ==="),
             format!("FPS: {}", self.fps),
@@ -52,11 +62,20 @@ impl DebugInfo {
             format!("Velocity: ({:.2}, {:.2}, {:.2})", self.velocity.x, self.velocity.y, self.velocity.z),
             format!("On Ground: {}", self.on_ground),
             format!("Chunk: ({}, {})", self.chunk_x, self.chunk_z),
+            format!(
+                "Facing: {} (towards {}) heading {:.1} | Yaw: {:.1} Pitch: {:.1}",
+                direction,
+                axis,
+                heading,
+                self.yaw.to_degrees().rem_euclid(360.0),
+                self.pitch.to_degrees()
+            ),
             if let Some((x, y, z)) = self.looking_at_block {
                 format!("Looking at: ({}, {}, {})", x, y, z)
             } else {
                 "Looking at: None".to_string()
             },
+            self.mesh_memory.format_display(),
         ]
     }
 }
@@ -0,0 +1,209 @@
+//! Debug geometry drawn into the world: chunk borders (F3+G), hitboxes
+//! (F3+B) for the players, block entities and the block targeting ray, and
+//! the bounds of generated structures (F3+L).
+
+use crate::camera::Camera;
+use crate::chunk::{CHUNK_HEIGHT, CHUNK_SIZE};
+use crate::input::REACH;
+use crate::physics::{Aabb, Player};
+use crate::raycast::{raycast, HitTarget, RaycastFilter};
+use crate::world::World;
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+
+const CHUNK_PLANE_COLOR: [f32; 4] = [0.2, 0.4, 1.0, 0.15];
+const CHUNK_EDGE_COLOR: [f32; 4] = [0.2, 0.4, 1.0, 1.0];
+/// Vertical edges of the neighboring chunks
+const NEIGHBOR_EDGE_COLOR: [f32; 4] = [1.0, 0.2, 0.2, 1.0];
+const PLAYER_BOX_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const ENTITY_BOX_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 1.0];
+const RAY_COLOR: [f32; 4] = [1.0, 0.2, 0.2, 1.0];
+const TARGET_BOX_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 1.0];
+const OTHER_PLAYER_COLOR: [f32; 4] = [0.3, 1.0, 0.4, 1.0];
+const STRUCTURE_BOX_COLOR: [f32; 4] = [1.0, 0.3, 1.0, 1.0];
+/// Block entities further than this from the player get no hitbox
+const ENTITY_BOX_RANGE: f32 = 32.0;
+/// Structures further than this from the player are not outlined
+const STRUCTURE_BOX_RANGE: f32 = 128.0;
+
+/// Which debug layers are drawn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DebugLayers {
+    pub chunk_borders: bool,
+    pub hitboxes: bool,
+    pub structures: bool,
+}
+
+impl DebugLayers {
+    pub fn any(self) -> bool {
+        self.chunk_borders || self.hitboxes || self.structures
+    }
+
+    /// Layer states and their keys for the F3 debug output
+    pub fn format_display(self) -> String {
+        let state = |on: bool| if on { "on" } else { "off" };
+        format!(
+            "Chunk borders (F3+G): {} | Hitboxes (F3+B): {} | Structures (F3+L): {}",
+            state(self.chunk_borders),
+            state(self.hitboxes),
+            state(self.structures)
+        )
+    }
+}
+
+/// World-space vertex of the debug pipelines
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct DebugVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl DebugVertex {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<DebugVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// Line list plus translucent triangles, rebuilt every frame a layer is on
+#[derive(Default)]
+pub struct DebugMesh {
+    pub line_vertices: Vec<DebugVertex>,
+    pub line_indices: Vec<u32>,
+    pub plane_vertices: Vec<DebugVertex>,
+    pub plane_indices: Vec<u32>,
+}
+
+impl DebugMesh {
+    /// Geometry of the enabled layers around the player. `others` are the
+    /// hitboxes of the other players, with their ids.
+    pub fn build(layers: DebugLayers, world: &World, player: &Player, camera: &Camera, others: &[(u32, Aabb)]) -> Self {
+        let mut mesh = Self::default();
+        if layers.chunk_borders {
+            let chunk_x = (camera.position.x / CHUNK_SIZE as f32).floor() as i32;
+            let chunk_z = (camera.position.z / CHUNK_SIZE as f32).floor() as i32;
+            mesh.add_chunk_borders(chunk_x, chunk_z);
+        }
+        if layers.hitboxes {
+            mesh.add_box(&player.bounding_box, PLAYER_BOX_COLOR);
+            for &(x, y, z) in world.block_entities.keys() {
+                let min = Vec3::new(x as f32, y as f32, z as f32);
+                if min.distance(player.position) <= ENTITY_BOX_RANGE {
+                    mesh.add_box(&Aabb::new(min, min + Vec3::ONE), ENTITY_BOX_COLOR);
+                }
+            }
+
+            for (_, aabb) in others {
+                mesh.add_box(aabb, OTHER_PLAYER_COLOR);
+            }
+
+            // The ray used for breaking and placing blocks, up to the block or
+            // player it hits
+            let direction = camera.get_direction();
+            let filter = RaycastFilter { entities: others, ..RaycastFilter::TARGET_BLOCKS };
+            let end = match raycast(camera.position, direction, REACH, world, filter) {
+                Some(hit) => {
+                    if let HitTarget::Block(_) = hit.target {
+                        let (x, y, z) = hit.position;
+                        let min = Vec3::new(x as f32, y as f32, z as f32);
+                        mesh.add_box(&Aabb::new(min, min + Vec3::ONE), TARGET_BOX_COLOR);
+                    }
+                    hit.distance
+                }
+                None => REACH,
+            };
+            mesh.add_line(camera.position, camera.position + direction * end, RAY_COLOR);
+        }
+        if layers.structures {
+            for structure in &world.structures {
+                if structure.center().distance(player.position) <= STRUCTURE_BOX_RANGE {
+                    mesh.add_box(&structure.bounds(), STRUCTURE_BOX_COLOR);
+                }
+            }
+        }
+        mesh
+    }
+
+    pub fn add_line(&mut self, a: Vec3, b: Vec3, color: [f32; 4]) {
+        let base = self.line_vertices.len() as u32;
+        self.line_vertices.push(DebugVertex { position: a.to_array(), color });
+        self.line_vertices.push(DebugVertex { position: b.to_array(), color });
+        self.line_indices.extend([base, base + 1]);
+    }
+
+    /// The twelve edges of a box
+    pub fn add_box(&mut self, aabb: &Aabb, color: [f32; 4]) {
+        let corner = |i: usize| {
+            Vec3::new(
+                if i & 1 == 0 { aabb.min.x } else { aabb.max.x },
+                if i & 2 == 0 { aabb.min.y } else { aabb.max.y },
+                if i & 4 == 0 { aabb.min.z } else { aabb.max.z },
+            )
+        };
+        // Corners differing in exactly one axis bit share an edge
+        for i in 0..8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.add_line(corner(i), corner(i | bit), color);
+                }
+            }
+        }
+    }
+
+    /// Translucent quad, visible from both sides
+    fn add_quad(&mut self, corners: [Vec3; 4], color: [f32; 4]) {
+        let base = self.plane_vertices.len() as u32;
+        for corner in corners {
+            self.plane_vertices.push(DebugVertex { position: corner.to_array(), color });
+        }
+        self.plane_indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    /// Planes along the four sides of a chunk, and the corner edges of its neighbors
+    fn add_chunk_borders(&mut self, chunk_x: i32, chunk_z: i32) {
+        let size = CHUNK_SIZE as f32;
+        let height = CHUNK_HEIGHT as f32;
+        let (x0, z0) = (chunk_x as f32 * size, chunk_z as f32 * size);
+        let (x1, z1) = (x0 + size, z0 + size);
+
+        let corners = [(x0, z0), (x1, z0), (x1, z1), (x0, z1)];
+        for i in 0..4 {
+            let (ax, az) = corners[i];
+            let (bx, bz) = corners[(i + 1) % 4];
+            self.add_quad(
+                [
+                    Vec3::new(ax, 0.0, az),
+                    Vec3::new(bx, 0.0, bz),
+                    Vec3::new(bx, height, bz),
+                    Vec3::new(ax, height, az),
+                ],
+                CHUNK_PLANE_COLOR,
+            );
+            self.add_line(Vec3::new(ax, 0.0, az), Vec3::new(ax, height, az), CHUNK_EDGE_COLOR);
+        }
+
+        for dx in -1..=2 {
+            for dz in -1..=2 {
+                let (x, z) = (x0 + dx as f32 * size, z0 + dz as f32 * size);
+                if !(0..=1).contains(&dx) || !(0..=1).contains(&dz) {
+                    self.add_line(Vec3::new(x, 0.0, z), Vec3::new(x, height, z), NEIGHBOR_EDGE_COLOR);
+                }
+            }
+        }
+    }
+}
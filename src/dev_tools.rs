@@ -0,0 +1,276 @@
+//! egui developer overlay (built with `--features debug-ui`), toggled with F4.
+//!
+//! Tweaks worldgen, physics and daylight at runtime and shows the chunk cache
+//! and block entities, without hand-building widgets in the UI vertex code.
+
+use crate::block_entity::BlockEntity;
+use crate::physics::{PhysicsConstants, Player};
+use crate::profiler::Profiler;
+use crate::time::{Weather, WorldTime, TICKS_PER_DAY, TIME_DAY, TIME_MIDNIGHT, TIME_NIGHT, TIME_NOON};
+use crate::world::World;
+use crate::world_gen::{TerrainMode, TerrainParams, WorldGenerator};
+use egui_wgpu::ScreenDescriptor;
+use winit::event::WindowEvent;
+use winit::window::Window;
+
+/// What the overlay asks the game loop to do after a frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DevAction {
+    None,
+    /// Drop all loaded chunks and regenerate them with new terrain parameters
+    RegenerateTerrain(TerrainParams),
+}
+
+/// Game state the overlay can inspect and edit for one frame
+pub struct DevContext<'a> {
+    pub world: &'a mut World,
+    pub player: &'a mut Player,
+    pub generator: &'a WorldGenerator,
+    /// Cached chunk meshes, vertices and indices, from `Renderer::chunk_cache_stats`
+    pub chunk_cache: (usize, usize, usize),
+    pub profiler: &'a Profiler,
+}
+
+/// Tessellated egui output waiting to be painted
+struct PendingFrame {
+    primitives: Vec<egui::ClippedPrimitive>,
+    textures: egui::TexturesDelta,
+    pixels_per_point: f32,
+}
+
+pub struct DevTools {
+    open: bool,
+    ctx: egui::Context,
+    state: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+    frame: Option<PendingFrame>,
+    /// Terrain parameters being edited; applied with "Regenerate"
+    terrain: TerrainParams,
+}
+
+impl DevTools {
+    pub fn new(window: &Window, device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let ctx = egui::Context::default();
+        let state = egui_winit::State::new(
+            ctx.clone(),
+            ctx.viewport_id(),
+            window,
+            Some(window.scale_factor() as f32),
+            None,
+        );
+        Self {
+            open: false,
+            ctx,
+            state,
+            renderer: egui_wgpu::Renderer::new(device, format, None, 1),
+            frame: None,
+            terrain: TerrainParams::default(),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.frame = None;
+    }
+
+    /// Feed a window event to egui while the overlay is open. Returns true if
+    /// egui consumed it and the game should ignore it.
+    pub fn on_window_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        if !self.open {
+            return false;
+        }
+        self.state.on_window_event(window, event).consumed
+    }
+
+    /// Build the panels for this frame; the result is drawn by `paint`
+    pub fn run(&mut self, window: &Window, game: DevContext) -> DevAction {
+        if !self.open {
+            return DevAction::None;
+        }
+
+        let input = self.state.take_egui_input(window);
+        let mut action = DevAction::None;
+        let terrain = &mut self.terrain;
+        let output = self.ctx.run(input, |ctx| {
+            egui::Window::new("Developer Tools")
+                .default_pos([10.0, 10.0])
+                .default_width(320.0)
+                .show(ctx, |ui| {
+                    egui::CollapsingHeader::new("Frame profiler").show(ui, |ui| profiler_ui(ui, game.profiler));
+                    egui::CollapsingHeader::new("World generation").default_open(true).show(ui, |ui| {
+                        if terrain_ui(ui, terrain, &game.generator.params) {
+                            action = DevAction::RegenerateTerrain(*terrain);
+                        }
+                    });
+                    egui::CollapsingHeader::new("Physics").show(ui, |ui| physics_ui(ui, &mut game.player.physics));
+                    egui::CollapsingHeader::new("Light").show(ui, |ui| light_ui(ui, &mut game.world.time));
+                    egui::CollapsingHeader::new("Chunk cache").show(ui, |ui| {
+                        let dirty = game.world.chunks.values().filter(|chunk| chunk.dirty).count();
+                        let (meshes, vertices, indices) = game.chunk_cache;
+                        ui.label(format!("Loaded chunks: {} ({} dirty)", game.world.chunks.len(), dirty));
+                        ui.label(format!("Cached meshes: {}", meshes));
+                        ui.label(format!("Vertices: {}  Indices: {}", vertices, indices));
+                    });
+                    egui::CollapsingHeader::new("Entities").show(ui, |ui| entities_ui(ui, game.world, game.player));
+                });
+        });
+
+        self.state.handle_platform_output(window, output.platform_output);
+        self.frame = Some(PendingFrame {
+            primitives: self.ctx.tessellate(output.shapes, output.pixels_per_point),
+            textures: output.textures_delta,
+            pixels_per_point: output.pixels_per_point,
+        });
+        action
+    }
+
+    /// Draw the last built frame on top of `view`
+    pub fn paint(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) {
+        let Some(frame) = self.frame.take() else {
+            return;
+        };
+        let screen = ScreenDescriptor {
+            size_in_pixels: [size.width, size.height],
+            pixels_per_point: frame.pixels_per_point,
+        };
+
+        for (id, delta) in &frame.textures.set {
+            self.renderer.update_texture(device, queue, *id, delta);
+        }
+        let callbacks = self.renderer.update_buffers(device, queue, encoder, &frame.primitives, &screen);
+        queue.submit(callbacks);
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Debug UI Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            self.renderer.render(&mut render_pass, &frame.primitives, &screen);
+        }
+
+        for id in &frame.textures.free {
+            self.renderer.free_texture(id);
+        }
+    }
+}
+
+/// Terrain sliders. Returns true when "Regenerate" was clicked.
+fn terrain_ui(ui: &mut egui::Ui, terrain: &mut TerrainParams, current: &TerrainParams) -> bool {
+    ui.add(egui::Slider::new(&mut terrain.octaves, 1..=8).text("Octaves"));
+    ui.add(egui::Slider::new(&mut terrain.base_frequency, 0.001..=0.05).logarithmic(true).text("Base frequency"));
+    ui.add(egui::Slider::new(&mut terrain.persistence, 0.1..=1.0).text("Persistence"));
+    ui.add(egui::Slider::new(&mut terrain.lacunarity, 1.0..=4.0).text("Lacunarity"));
+    ui.add(egui::Slider::new(&mut terrain.height_scale, 0.0..=40.0).text("Height scale"));
+    ui.add(egui::Slider::new(&mut terrain.base_height, -20.0..=20.0).text("Base height"));
+    ui.add(egui::Slider::new(&mut terrain.tree_threshold, 0.0..=1.0).text("Tree threshold"));
+    let mut density = terrain.mode == TerrainMode::Density;
+    if ui.checkbox(&mut density, "Overhangs (3D density)").changed() {
+        terrain.mode = if density { TerrainMode::Density } else { TerrainMode::Heightmap };
+    }
+
+    let mut regenerate = false;
+    ui.horizontal(|ui| {
+        // Regenerating discards every loaded chunk, including edits
+        regenerate = ui.add_enabled(terrain != current, egui::Button::new("Regenerate")).clicked();
+        if ui.button("Defaults").clicked() {
+            *terrain = TerrainParams::default();
+        }
+    });
+    regenerate
+}
+
+/// Rolling average of each phase, as a bar relative to the whole frame
+fn profiler_ui(ui: &mut egui::Ui, profiler: &Profiler) {
+    let frame = profiler.average_frame().as_secs_f32();
+    ui.label(format!("Frame: {:.2} ms ({:.0} FPS)", frame * 1000.0, 1.0 / frame.max(1e-6)));
+    for (phase, time) in profiler.averages() {
+        let time = time.as_secs_f32();
+        ui.add(
+            egui::ProgressBar::new(time / frame.max(1e-6))
+                .text(format!("{}: {:.2} ms", phase.name(), time * 1000.0)),
+        );
+    }
+}
+
+fn physics_ui(ui: &mut egui::Ui, physics: &mut PhysicsConstants) {
+    ui.add(egui::Slider::new(&mut physics.gravity, -60.0..=0.0).text("Gravity"));
+    ui.add(egui::Slider::new(&mut physics.terminal_velocity, -100.0..=-1.0).text("Terminal velocity"));
+    ui.add(egui::Slider::new(&mut physics.jump_velocity, 0.0..=20.0).text("Jump velocity"));
+    ui.add(egui::Slider::new(&mut physics.step_height, 0.0..=1.0).text("Step height"));
+    if ui.button("Defaults").clicked() {
+        *physics = PhysicsConstants::default();
+    }
+}
+
+/// Daylight is the only light source so far: it drives the sky and fog color
+fn light_ui(ui: &mut egui::Ui, time: &mut WorldTime) {
+    let mut ticks = time.time_of_day();
+    if ui.add(egui::Slider::new(&mut ticks, 0..=TICKS_PER_DAY - 1).text("Time of day")).changed() {
+        time.set_time_of_day(ticks);
+    }
+    ui.horizontal(|ui| {
+        for (name, ticks) in [("Day", TIME_DAY), ("Noon", TIME_NOON), ("Night", TIME_NIGHT), ("Midnight", TIME_MIDNIGHT)] {
+            if ui.button(name).clicked() {
+                time.set_time_of_day(ticks);
+            }
+        }
+    });
+    ui.label(format!("Day {}, daylight: {:.2}", time.day(), time.daylight()));
+    ui.horizontal(|ui| {
+        ui.label(format!("Weather: {}", time.weather().name()));
+        for weather in [Weather::Clear, Weather::Rain, Weather::Thunder] {
+            if ui.button(weather.name()).clicked() {
+                time.set_weather(weather);
+            }
+        }
+    });
+    ui.label(format!("Sky light: {:.2}", time.sky_light()));
+}
+
+fn entities_ui(ui: &mut egui::Ui, world: &World, player: &Player) {
+    ui.label(format!(
+        "Player at ({:.1}, {:.1}, {:.1}), velocity ({:.1}, {:.1}, {:.1}), {}",
+        player.position.x,
+        player.position.y,
+        player.position.z,
+        player.velocity.x,
+        player.velocity.y,
+        player.velocity.z,
+        if player.on_ground { "on ground" } else { "airborne" },
+    ));
+
+    let mut entities: Vec<_> = world.block_entities.iter().collect();
+    entities.sort_by_key(|(pos, _)| **pos);
+    ui.label(format!("Block entities: {}", entities.len()));
+    egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+        for ((x, y, z), entity) in entities {
+            let items: u32 = entity.slots().iter().flatten().map(|stack| stack.count).sum();
+            let state = match entity {
+                BlockEntity::Furnace(furnace) if furnace.is_burning() => ", burning",
+                _ => "",
+            };
+            ui.label(format!("{:?} at ({}, {}, {}): {} items{}", entity.kind(), x, y, z, items, state));
+        }
+    });
+}
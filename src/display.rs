@@ -0,0 +1,82 @@
+//! Window size, fullscreen and the monitor the game shows on. F11 toggles
+//! fullscreen; `config.json` picks the monitor by name and a resolution,
+//! which sizes the window and, if the monitor has a video mode that size,
+//! is used in fullscreen too. Otherwise fullscreen is borderless at the
+//! desktop resolution.
+
+use crate::config::GameConfig;
+use winit::dpi::{LogicalSize, PhysicalSize, Size};
+use winit::monitor::MonitorHandle;
+use winit::window::{Fullscreen, Window};
+
+/// Window size without a configured resolution, in logical pixels
+pub const DEFAULT_WINDOW_SIZE: (u32, u32) = (1280, 720);
+
+/// Size the window opens with
+pub fn window_size(config: &GameConfig) -> Size {
+    match config.resolution {
+        Some([width, height]) => PhysicalSize::new(width, height).into(),
+        None => LogicalSize::new(DEFAULT_WINDOW_SIZE.0, DEFAULT_WINDOW_SIZE.1).into(),
+    }
+}
+
+/// How to go fullscreen: on the configured monitor, or `current` if there is
+/// no such monitor, in the configured resolution if it has a video mode of
+/// that size
+pub fn fullscreen(monitors: impl Iterator<Item = MonitorHandle>, current: Option<MonitorHandle>, config: &GameConfig) -> Fullscreen {
+    let monitors: Vec<MonitorHandle> = monitors.collect();
+    let names: Vec<Option<String>> = monitors.iter().map(|monitor| monitor.name()).collect();
+    let preferred = config.monitor.as_deref().and_then(|name| pick_monitor(&names, name));
+    if config.monitor.is_some() && preferred.is_none() {
+        log::warn!("No monitor named {:?}; using the current one", config.monitor.as_deref().unwrap_or_default());
+    }
+    let Some(monitor) = preferred.map(|index| monitors[index].clone()).or(current) else {
+        return Fullscreen::Borderless(None);
+    };
+    if let Some(resolution) = config.resolution {
+        let modes: Vec<_> = monitor.video_modes().collect();
+        let sizes: Vec<([u32; 2], u32)> = modes
+            .iter()
+            .map(|mode| ([mode.size().width, mode.size().height], mode.refresh_rate_millihertz()))
+            .collect();
+        match pick_mode(&sizes, resolution) {
+            Some(index) => return Fullscreen::Exclusive(modes[index].clone()),
+            None => log::warn!("The monitor has no {}x{} video mode; going borderless", resolution[0], resolution[1]),
+        }
+    }
+    Fullscreen::Borderless(Some(monitor))
+}
+
+/// Go fullscreen as configured, or back to a window
+pub fn set_fullscreen(window: &Window, config: &GameConfig, on: bool) {
+    let mode = on.then(|| fullscreen(window.available_monitors(), window.current_monitor(), config));
+    window.set_fullscreen(mode);
+}
+
+/// Log the monitors and their video modes, for picking `monitor` and
+/// `resolution` in the config
+pub fn log_monitors(monitors: impl Iterator<Item = MonitorHandle>) {
+    for monitor in monitors {
+        let mut sizes: Vec<(u32, u32)> = monitor.video_modes().map(|mode| (mode.size().width, mode.size().height)).collect();
+        sizes.sort_unstable_by(|a, b| b.cmp(a));
+        sizes.dedup();
+        let sizes: Vec<String> = sizes.iter().map(|(width, height)| format!("{}x{}", width, height)).collect();
+        log::info!("Monitor {:?}: {}", monitor.name().unwrap_or_default(), sizes.join(", "));
+    }
+}
+
+/// Index of the monitor called `name`
+pub fn pick_monitor(names: &[Option<String>], name: &str) -> Option<usize> {
+    names.iter().position(|candidate| candidate.as_deref() == Some(name))
+}
+
+/// Index of the video mode of `resolution` with the highest refresh rate, of
+/// modes given as size and refresh rate
+pub fn pick_mode(modes: &[([u32; 2], u32)], resolution: [u32; 2]) -> Option<usize> {
+    modes
+        .iter()
+        .enumerate()
+        .filter(|(_, (size, _))| *size == resolution)
+        .max_by_key(|(_, (_, refresh))| *refresh)
+        .map(|(index, _)| index)
+}
@@ -0,0 +1,85 @@
+//! Undo and redo of the blocks changed in creative mode, with Ctrl+Z and
+//! Ctrl+Y. Each click is one step, however many blocks it changed (a broken
+//! block takes the plant on it along). The oldest steps are forgotten once
+//! the history holds `MAX_EDITS` block changes.
+
+use crate::block::BlockType;
+use crate::world::World;
+use std::collections::VecDeque;
+
+/// Most block changes remembered, across all steps
+pub const MAX_EDITS: usize = 4096;
+
+/// One block changing from `old` to `new`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockEdit {
+    pub pos: (i32, i32, i32),
+    pub old: BlockType,
+    pub new: BlockType,
+}
+
+#[derive(Debug, Default)]
+pub struct EditHistory {
+    /// Steps to undo, the latest at the back
+    undo: VecDeque<Vec<BlockEdit>>,
+    /// Steps undone, the latest undone at the back
+    redo: Vec<Vec<BlockEdit>>,
+    /// Block changes over both lists
+    len: usize,
+}
+
+impl EditHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember the block changes of one step; a new step can no longer be
+    /// redone over
+    pub fn record(&mut self, edits: Vec<BlockEdit>) {
+        if edits.is_empty() {
+            return;
+        }
+        self.len -= self.redo.drain(..).map(|step| step.len()).sum::<usize>();
+        self.len += edits.len();
+        self.undo.push_back(edits);
+        while self.len > MAX_EDITS {
+            let Some(oldest) = self.undo.pop_front() else {
+                break;
+            };
+            self.len -= oldest.len();
+        }
+    }
+
+    /// Put back the blocks of the latest step. Blocks changed again since
+    /// then are left alone. Returns false if there was nothing to undo.
+    pub fn undo(&mut self, world: &mut World) -> bool {
+        let Some(step) = self.undo.pop_back() else {
+            return false;
+        };
+        for edit in step.iter().rev() {
+            apply(world, edit.pos, edit.new, edit.old);
+        }
+        self.redo.push(step);
+        true
+    }
+
+    /// Make the latest undone step again. Returns false if there was nothing
+    /// to redo.
+    pub fn redo(&mut self, world: &mut World) -> bool {
+        let Some(step) = self.redo.pop() else {
+            return false;
+        };
+        for edit in &step {
+            apply(world, edit.pos, edit.old, edit.new);
+        }
+        self.undo.push_back(step);
+        true
+    }
+}
+
+/// Change the block at `pos` from `from` to `to`, if it is still `from`
+fn apply(world: &mut World, pos: (i32, i32, i32), from: BlockType, to: BlockType) {
+    if world.get_block_at(pos.0, pos.1, pos.2) == Some(from) {
+        world.set_block_at(pos.0, pos.1, pos.2, to);
+    }
+}
@@ -0,0 +1,138 @@
+//! Enchanting: at an enchanting table, experience levels (see `experience`)
+//! buy modifiers for the tool put into its slot. Each modifier goes up to
+//! level 3, a level at a time, and the next level costs more experience the
+//! higher it is.
+//!
+//! - Efficiency: the tool breaks the blocks it is made for faster, adding
+//!   `level² + 1` to its speed as in Minecraft
+//! - Unbreaking: a use wears the tool only one time in `level + 1`
+//!
+//! Enchantments stay with the stack wherever it goes, and are saved with it.
+
+use crate::experience::Experience;
+use crate::inventory::ItemStack;
+
+/// Highest level of every enchantment
+pub const MAX_LEVEL: u8 = 3;
+/// Bits each level takes in `Enchantments::to_bits`
+const LEVEL_BITS: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Enchantment {
+    Efficiency,
+    Unbreaking,
+}
+
+impl Enchantment {
+    pub const ALL: [Enchantment; 2] = [Enchantment::Efficiency, Enchantment::Unbreaking];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Enchantment::Efficiency => "Efficiency",
+            Enchantment::Unbreaking => "Unbreaking",
+        }
+    }
+}
+
+/// Levels of each enchantment on a stack; 0 for the ones it doesn't have
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Enchantments([u8; 2]);
+
+impl Enchantments {
+    pub const NONE: Enchantments = Enchantments([0; 2]);
+
+    pub fn level(&self, enchantment: Enchantment) -> u8 {
+        self.0[enchantment as usize]
+    }
+
+    pub fn set(&mut self, enchantment: Enchantment, level: u8) {
+        self.0[enchantment as usize] = level.min(MAX_LEVEL);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::NONE
+    }
+
+    /// The levels packed into a number, `LEVEL_BITS` bits each, for saving
+    pub fn to_bits(self) -> u32 {
+        self.0.iter().enumerate().map(|(i, &level)| (level as u32) << (i as u32 * LEVEL_BITS)).sum()
+    }
+
+    pub fn from_bits(bits: u32) -> Self {
+        let mut enchantments = Self::NONE;
+        for enchantment in Enchantment::ALL {
+            let level = (bits >> (enchantment as u32 * LEVEL_BITS)) & ((1 << LEVEL_BITS) - 1);
+            enchantments.set(enchantment, level as u8);
+        }
+        enchantments
+    }
+}
+
+/// A level of an enchantment the table offers for the tool in it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Offer {
+    pub enchantment: Enchantment,
+    pub level: u8,
+    /// Experience levels it costs
+    pub cost: u32,
+}
+
+impl Offer {
+    /// Name and level, as in "Efficiency II"
+    pub fn title(&self) -> String {
+        format!("{} {}", self.enchantment.name(), roman(self.level))
+    }
+}
+
+fn roman(level: u8) -> &'static str {
+    match level {
+        1 => "I",
+        2 => "II",
+        3 => "III",
+        _ => "",
+    }
+}
+
+/// Experience levels the given level of an enchantment costs
+pub fn cost(level: u8) -> u32 {
+    2 * level as u32 - 1
+}
+
+/// The next level of every enchantment `stack` doesn't have at its highest;
+/// nothing if it isn't a tool
+pub fn offers(stack: &ItemStack) -> Vec<Offer> {
+    if stack.item.durability().is_none() {
+        return Vec::new();
+    }
+    Enchantment::ALL
+        .into_iter()
+        .map(|enchantment| (enchantment, stack.enchantments.level(enchantment) + 1))
+        .filter(|&(_, level)| level <= MAX_LEVEL)
+        .map(|(enchantment, level)| Offer { enchantment, level, cost: cost(level) })
+        .collect()
+}
+
+/// Put the offered level on the tool, paying for it out of `experience`.
+/// Returns false, changing nothing, if the levels aren't there or the offer
+/// no longer fits the tool.
+pub fn enchant(tool: &mut ItemStack, offer: Offer, experience: &mut Experience) -> bool {
+    if !offers(tool).contains(&offer) || !experience.spend(offer.cost) {
+        return false;
+    }
+    tool.enchantments.set(offer.enchantment, offer.level);
+    true
+}
+
+/// Speed a tool gains on the blocks it is made for from its efficiency
+pub fn efficiency_bonus(level: u8) -> f32 {
+    match level {
+        0 => 0.0,
+        level => (level as u32 * level as u32 + 1) as f32,
+    }
+}
+
+/// Whether a tool's unbreaking spares it the wear of a use; `roll` is random
+/// bits deciding it
+pub fn spares_wear(level: u8, roll: u32) -> bool {
+    !roll.is_multiple_of(level as u32 + 1)
+}
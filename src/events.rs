@@ -0,0 +1,50 @@
+//! Gameplay events. Whatever causes something to happen (input, chunk loading,
+//! the survival tick) emits an event; systems such as audio react to it in
+//! `EventListener::on_event` instead of being wired into the code that caused it.
+
+use crate::block::BlockType;
+use crate::item::Item;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameEvent {
+    BlockBroken { pos: (i32, i32, i32), block: BlockType },
+    BlockPlaced { pos: (i32, i32, i32), block: BlockType },
+    /// A generated chunk was added to the world
+    ChunkLoaded { x: i32, z: i32 },
+    /// The player lost `amount` health, leaving `health`
+    PlayerDamaged { amount: u32, health: u32 },
+    /// Items went into the player's inventory
+    ItemPickedUp { item: Item, count: u32 },
+}
+
+/// A system that reacts to gameplay events
+pub trait EventListener {
+    fn on_event(&mut self, event: &GameEvent);
+}
+
+/// Queue of events emitted since they were last dispatched
+#[derive(Default)]
+pub struct EventBus {
+    queue: Vec<GameEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn emit(&mut self, event: GameEvent) {
+        self.queue.push(event);
+    }
+
+    /// The queued events, oldest first, left in the queue
+    pub fn pending(&self) -> &[GameEvent] {
+        &self.queue
+    }
+
+    /// Take the queued events, oldest first
+    pub fn drain(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.queue)
+    }
+}
@@ -0,0 +1,59 @@
+//! Experience: points earned by trading with villagers and by fishing, which
+//! add up to levels that the enchanting table spends (see `enchanting`).
+//! As in Minecraft's first levels, getting from level `n` to the next takes
+//! `2n + 7` points. Experience is saved with the world, and lost on death
+//! unless `keepInventory` is on.
+
+use serde::{Deserialize, Serialize};
+
+/// Points from a trade with a villager
+pub const TRADE_POINTS: u32 = 3;
+/// Points from a fishing catch
+pub const CATCH_POINTS: u32 = 2;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Experience {
+    pub level: u32,
+    /// Points towards the next level
+    pub points: u32,
+}
+
+impl Experience {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Points it takes to get from `level` to the next
+    pub fn points_to_next(level: u32) -> u32 {
+        2 * level + 7
+    }
+
+    /// Add points, going up as many levels as they fill. Returns the levels
+    /// gained.
+    pub fn add(&mut self, points: u32) -> u32 {
+        let start = self.level;
+        self.points += points;
+        while self.points >= Self::points_to_next(self.level) {
+            self.points -= Self::points_to_next(self.level);
+            self.level += 1;
+        }
+        self.level - start
+    }
+
+    /// Go down `levels` levels, keeping the points towards the next as far
+    /// as the lower level holds them. Returns false, changing nothing, if
+    /// there aren't as many.
+    pub fn spend(&mut self, levels: u32) -> bool {
+        if self.level < levels {
+            return false;
+        }
+        self.level -= levels;
+        self.points = self.points.min(Self::points_to_next(self.level) - 1);
+        true
+    }
+
+    /// Share of the way to the next level, 0 to 1
+    pub fn progress(&self) -> f32 {
+        self.points as f32 / Self::points_to_next(self.level) as f32
+    }
+}
@@ -0,0 +1,44 @@
+//! Fire, lit with flint and steel. Every so often a fire may spread to each
+//! flammable block next to it, burning the block away; once nothing next to
+//! it burns any more it dies out. Rain puts out fires open to the sky.
+
+use crate::block::BlockType;
+use crate::time::Weather;
+use crate::world::World;
+
+/// Fewest ticks between two updates of a fire; the next one comes up to
+/// twice as late
+pub const FIRE_DELAY: u64 = 20;
+/// One in this many updates a flammable neighbor catches fire
+const SPREAD_CHANCE: u32 = 3;
+/// One in this many updates a fire with nothing left to burn goes out
+const BURN_OUT_CHANCE: u32 = 2;
+const NEIGHBORS: [(i32, i32, i32); 6] = [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)];
+
+/// Scheduled update of the fire at `pos`: go out in the rain, spread, or
+/// burn out. Returns true if any block changed.
+pub fn fire_tick(world: &mut World, (x, y, z): (i32, i32, i32)) -> bool {
+    let open_sky = world.highest_block(x, z).is_some_and(|(top, _)| top == y);
+    if open_sky && world.time.weather() != Weather::Clear {
+        return world.set_block_at(x, y, z, BlockType::Air);
+    }
+
+    let mut changed = false;
+    let mut fuel = false;
+    for (dx, dy, dz) in NEIGHBORS {
+        let (nx, ny, nz) = (x + dx, y + dy, z + dz);
+        if world.get_block_at(nx, ny, nz).is_some_and(|block| block.is_flammable()) {
+            fuel = true;
+            if world.block_ticks.next_random().is_multiple_of(SPREAD_CHANCE) {
+                changed |= world.set_block_at(nx, ny, nz, BlockType::Fire);
+            }
+        }
+    }
+
+    let r = world.block_ticks.next_random();
+    if !fuel && r.is_multiple_of(BURN_OUT_CHANCE) {
+        return world.set_block_at(x, y, z, BlockType::Air) || changed;
+    }
+    world.block_ticks.schedule((x, y, z), FIRE_DELAY + (r >> 8) as u64 % FIRE_DELAY);
+    changed
+}
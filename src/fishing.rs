@@ -0,0 +1,180 @@
+//! Fishing. A right click with a fishing rod casts a bobber, which floats
+//! once it lands on water. After a random wait something bites: the bobber
+//! is pulled under with a splash, and reeling in with another right click
+//! while it is down catches a roll of the `fishing` loot table (see `loot`),
+//! flung out of the water towards the player. Reeling in too late or too
+//! early catches nothing, and a missed bite is followed by another wait.
+//! The line breaks when the rod is put away or the bobber ends up too far.
+
+use crate::block::BlockType;
+use crate::debug_render::DebugVertex;
+use crate::item_entity::{ItemEntity, PICKUP_DELAY};
+use crate::loot::LootTable;
+use crate::physics::Aabb;
+use crate::remote_players::add_cuboid;
+use crate::world::World;
+use glam::Vec3;
+
+/// Bobbers further than this from the player break the line
+pub const MAX_LINE_LENGTH: f32 = 32.0;
+/// Speed a bobber leaves the rod with, in blocks per second
+const CAST_SPEED: f32 = 12.0;
+/// Shortest and longest wait for a bite, in seconds
+const BITE_WAIT: (f32, f32) = (5.0, 30.0);
+/// Seconds a bite lasts, the time there is to reel in
+pub const BITE_TIME: f32 = 1.0;
+/// Speed a bite pulls the bobber under with, in blocks per second
+const BITE_PULL: f32 = 3.0;
+/// Seconds a catch flies for before reaching the player
+const CATCH_FLIGHT: f32 = 0.8;
+const GRAVITY: f32 = -20.0;
+/// Upwards acceleration of a bobber under the surface
+const BUOYANCY: f32 = 25.0;
+/// Share of vertical speed kept per second in water, so the bobber settles
+const WATER_DAMPING: f32 = 0.02;
+/// Share of horizontal speed kept per second in water
+const WATER_DRAG: f32 = 0.001;
+/// How deep a floating bobber sits in the water
+const DRAFT: f32 = 0.1;
+/// Side of the cube a bobber is drawn and collides as
+const SIZE: f32 = 0.2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BobberState {
+    /// In the air or on land
+    Flying,
+    /// Floating, with the seconds left until a bite
+    Waiting(f32),
+    /// Pulled under, with the seconds left to reel in
+    Biting(f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bobber {
+    /// Middle of the bottom face
+    pub position: Vec3,
+    pub velocity: Vec3,
+    state: BobberState,
+    /// Xorshift state drawing the waits
+    rng: u32,
+}
+
+impl Bobber {
+    /// A bobber cast from `eye` towards `direction`; `seed` varies the waits
+    /// for a bite
+    pub fn cast(eye: Vec3, direction: Vec3, seed: u32) -> Self {
+        let direction = direction.normalize_or_zero();
+        Self {
+            position: eye - Vec3::Y * 0.2 + direction * 0.5,
+            velocity: direction * CAST_SPEED + Vec3::Y * 3.0,
+            state: BobberState::Flying,
+            // Xorshift never leaves zero
+            rng: seed.wrapping_mul(0x9E37_79B9) | 1,
+        }
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        Aabb::from_position(self.position, SIZE / 2.0, SIZE)
+    }
+
+    /// Whether the point `height` above the bottom of the bobber is in water
+    fn water_at(&self, world: &World, height: f32) -> bool {
+        let p = (self.position + Vec3::Y * height).floor().as_ivec3();
+        world.get_block_at(p.x, p.y, p.z) == Some(BlockType::Water)
+    }
+
+    /// Whether the bobber is on or in water, where fish bite; a floating
+    /// bobber bobs around its water line, but its bottom stays under
+    pub fn in_water(&self, world: &World) -> bool {
+        self.water_at(world, 0.0)
+    }
+
+    /// Whether something is biting, so reeling in now catches it
+    pub fn is_biting(&self) -> bool {
+        matches!(self.state, BobberState::Biting(_))
+    }
+
+    /// Seconds from a random wait for a bite
+    fn bite_wait(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        BITE_WAIT.0 + (BITE_WAIT.1 - BITE_WAIT.0) * (self.rng as f32 / u32::MAX as f32)
+    }
+
+    /// Fly, float and wait for `delta_time` seconds, stopping against solid
+    /// blocks one axis at a time. Returns true when a bite starts.
+    pub fn update(&mut self, world: &World, delta_time: f32) -> bool {
+        let in_water = self.in_water(world);
+        if self.water_at(world, DRAFT) {
+            self.velocity.y += BUOYANCY * delta_time;
+            self.velocity.y *= WATER_DAMPING.powf(delta_time);
+            let kept = WATER_DRAG.powf(delta_time);
+            self.velocity.x *= kept;
+            self.velocity.z *= kept;
+        } else {
+            self.velocity.y += GRAVITY * delta_time;
+        }
+        for axis in 0..3 {
+            let mut moved = *self;
+            moved.position[axis] += self.velocity[axis] * delta_time;
+            if moved.collides(world) {
+                self.velocity[axis] = 0.0;
+            } else {
+                self.position = moved.position;
+            }
+        }
+        world.push_inside_border(&mut self.position, &mut self.velocity, SIZE / 2.0);
+
+        let mut bit = false;
+        self.state = match self.state {
+            // Fish only bite on water
+            _ if !in_water => BobberState::Flying,
+            BobberState::Flying => BobberState::Waiting(self.bite_wait()),
+            BobberState::Waiting(left) if left > delta_time => BobberState::Waiting(left - delta_time),
+            BobberState::Waiting(_) => {
+                bit = true;
+                BobberState::Biting(BITE_TIME)
+            }
+            BobberState::Biting(left) if left > delta_time => BobberState::Biting(left - delta_time),
+            BobberState::Biting(_) => BobberState::Waiting(self.bite_wait()),
+        };
+        if self.is_biting() {
+            // Held under for as long as the bite lasts
+            self.velocity.y = -BITE_PULL;
+        }
+        bit
+    }
+
+    /// Whether the bobber overlaps a full block other than water
+    fn collides(&self, world: &World) -> bool {
+        let aabb = self.bounding_box();
+        let (min, max) = (aabb.min.floor().as_ivec3(), (aabb.max - 1e-4).floor().as_ivec3());
+        let blocks = |x, y, z| world.get_block_at(x, y, z).is_some_and(|block: BlockType| block.is_full_cube() && !block.is_liquid());
+        (min.x..=max.x).any(|x| (min.y..=max.y).any(|y| (min.z..=max.z).any(|z| blocks(x, y, z))))
+    }
+
+    /// Reel the bobber in to a player whose eyes are at `eye`. During a bite
+    /// this catches a roll of `loot` with `seed`: the stacks leap out of the
+    /// water in an arc that comes down on the player, ready to be picked up.
+    /// Otherwise it catches nothing.
+    pub fn reel_in(self, loot: &LootTable, seed: u32, eye: Vec3) -> Vec<ItemEntity> {
+        if !self.is_biting() {
+            return Vec::new();
+        }
+        let offset = eye - self.position;
+        let velocity = offset / CATCH_FLIGHT - Vec3::Y * 0.5 * GRAVITY * CATCH_FLIGHT;
+        loot.roll(seed)
+            .into_iter()
+            .map(|stack| ItemEntity { stack, position: self.position, velocity, age: PICKUP_DELAY })
+            .collect()
+    }
+}
+
+/// Triangles of a bobber: a red float with a white top
+pub fn build_mesh(bobber: &Bobber, vertices: &mut Vec<DebugVertex>, indices: &mut Vec<u32>) {
+    let half = SIZE / 2.0;
+    let transform = |p: Vec3| bobber.position + p;
+    add_cuboid(vertices, indices, Vec3::new(-half, 0.0, -half), Vec3::new(half, half, half), transform, [0.8, 0.1, 0.08]);
+    add_cuboid(vertices, indices, Vec3::new(-half, half, -half), Vec3::new(half, SIZE, half), transform, [0.9, 0.9, 0.9]);
+}
@@ -0,0 +1,180 @@
+// Tiny built-in 5x7 bitmap font for UI text (console, HUD labels).
+// Each glyph is 5 columns; bit 0 of a column is the top row, bit 6 the bottom row.
+// Covers printable ASCII (0x20..=0x7E); anything else renders as '?'.
+// `atlas` packs the glyphs into a texture for text drawn in the world.
+
+pub const GLYPH_WIDTH: usize = 5;
+pub const GLYPH_HEIGHT: usize = 7;
+/// Horizontal advance per character in font pixels (glyph + 1px spacing)
+pub const GLYPH_ADVANCE: usize = GLYPH_WIDTH + 1;
+
+const FIRST_CHAR: u8 = 0x20;
+
+const GLYPHS: [[u8; GLYPH_WIDTH]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5F, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], // #
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x55, 0x22, 0x50], // &
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '
+    [0x00, 0x1C, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1C, 0x00], // )
+    [0x08, 0x2A, 0x1C, 0x2A, 0x08], // *
+    [0x08, 0x08, 0x3E, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], // 0
+    [0x00, 0x42, 0x7F, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4B, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7F, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3C, 0x4A, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1E], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x08, 0x14, 0x22, 0x41, 0x00], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x00, 0x41, 0x22, 0x14, 0x08], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3E], // @
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], // A
+    [0x7F, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], // D
+    [0x7F, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7F, 0x09, 0x09, 0x01, 0x01], // F
+    [0x3E, 0x41, 0x41, 0x51, 0x32], // G
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], // H
+    [0x00, 0x41, 0x7F, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3F, 0x01], // J
+    [0x7F, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7F, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7F, 0x02, 0x04, 0x02, 0x7F], // M
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], // N
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // O
+    [0x7F, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3E, 0x41, 0x51, 0x21, 0x5E], // Q
+    [0x7F, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7F, 0x01, 0x01], // T
+    [0x3F, 0x40, 0x40, 0x40, 0x3F], // U
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], // V
+    [0x7F, 0x20, 0x18, 0x20, 0x7F], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x03, 0x04, 0x78, 0x04, 0x03], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x7F, 0x41, 0x41, 0x00], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // backslash
+    [0x00, 0x41, 0x41, 0x7F, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7F, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7F], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7E, 0x09, 0x01, 0x02], // f
+    [0x08, 0x54, 0x54, 0x54, 0x3C], // g
+    [0x7F, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7D, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3D, 0x00], // j
+    [0x00, 0x7F, 0x10, 0x28, 0x44], // k
+    [0x00, 0x41, 0x7F, 0x40, 0x00], // l
+    [0x7C, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7C, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7C, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x18, 0x7C], // q
+    [0x7C, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3F, 0x44, 0x40, 0x20], // t
+    [0x3C, 0x40, 0x40, 0x20, 0x7C], // u
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], // v
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0C, 0x50, 0x50, 0x50, 0x3C], // y
+    [0x44, 0x64, 0x54, 0x4C, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7F, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x02, 0x01, 0x02, 0x04, 0x02], // ~
+];
+
+/// Index of a character's glyph; characters without one get the '?' glyph
+fn glyph_index(c: char) -> usize {
+    let code = c as u32;
+    if (FIRST_CHAR as u32..=0x7E).contains(&code) {
+        (code - FIRST_CHAR as u32) as usize
+    } else {
+        (b'?' - FIRST_CHAR) as usize
+    }
+}
+
+/// Column bitmaps for a character
+pub fn glyph(c: char) -> &'static [u8; GLYPH_WIDTH] {
+    &GLYPHS[glyph_index(c)]
+}
+
+/// Whether the pixel at (col, row) of a glyph is set, row 0 being the top
+pub fn glyph_pixel(c: char, col: usize, row: usize) -> bool {
+    col < GLYPH_WIDTH && row < GLYPH_HEIGHT && glyph(c)[col] & (1 << row) != 0
+}
+
+
+/// Characters per row of the font atlas
+const ATLAS_COLUMNS: usize = 16;
+/// Cells of the font atlas: the glyphs, then one solid cell for backgrounds
+const ATLAS_CELLS: usize = GLYPHS.len() + 1;
+/// Size of an atlas cell; the glyph sits in its top left corner, with a
+/// pixel of gap to the next so neighbors don't bleed in
+const CELL_WIDTH: usize = GLYPH_ADVANCE;
+const CELL_HEIGHT: usize = GLYPH_HEIGHT + 1;
+
+/// Width and height of the font atlas in pixels
+pub fn atlas_size() -> (u32, u32) {
+    let rows = ATLAS_CELLS.div_ceil(ATLAS_COLUMNS);
+    ((ATLAS_COLUMNS * CELL_WIDTH) as u32, (rows * CELL_HEIGHT) as u32)
+}
+
+/// Top left pixel of the atlas cell at `index`
+fn cell_origin(index: usize) -> (u32, u32) {
+    ((index % ATLAS_COLUMNS * CELL_WIDTH) as u32, (index / ATLAS_COLUMNS * CELL_HEIGHT) as u32)
+}
+
+/// Top left pixel of the character's glyph in the font atlas
+pub fn atlas_cell(c: char) -> (u32, u32) {
+    cell_origin(glyph_index(c))
+}
+
+/// Top left pixel of the atlas cell that is set all over, `GLYPH_WIDTH` x
+/// `GLYPH_HEIGHT` pixels like the glyphs
+pub fn solid_cell() -> (u32, u32) {
+    cell_origin(GLYPHS.len())
+}
+
+/// The font as a texture for text drawn in the world: white where glyph
+/// pixels are set, transparent elsewhere
+pub fn atlas() -> image::RgbaImage {
+    let (width, height) = atlas_size();
+    let mut atlas = image::RgbaImage::new(width, height);
+    let cells = GLYPHS.iter().map(Some).chain([None]);
+    for (index, glyph) in cells.enumerate() {
+        let (x, y) = cell_origin(index);
+        for col in 0..GLYPH_WIDTH {
+            for row in 0..GLYPH_HEIGHT {
+                if glyph.is_none_or(|glyph| glyph[col] & (1 << row) != 0) {
+                    atlas.put_pixel(x + col as u32, y + row as u32, image::Rgba([255, 255, 255, 255]));
+                }
+            }
+        }
+    }
+    atlas
+}
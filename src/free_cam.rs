@@ -0,0 +1,45 @@
+//! Spectator camera: a noclip camera detached from the player's body that
+//! flies through blocks, for looking at terrain generation and culling from
+//! anywhere. The body stays where it was, and chunks keep loading around it.
+
+use glam::Vec3;
+
+/// Flying speed of a new spectator camera, in blocks per second
+pub const DEFAULT_SPEED: f32 = 10.0;
+pub const MIN_SPEED: f32 = 1.0;
+pub const MAX_SPEED: f32 = 100.0;
+/// Speed multiplier per mouse wheel step
+const SPEED_STEP: f32 = 1.25;
+
+pub struct FreeCam {
+    pub position: Vec3,
+    /// Blocks per second
+    speed: f32,
+    /// Where the body left behind faces while the camera looks around
+    pub body_yaw: f32,
+}
+
+impl FreeCam {
+    /// A camera starting at the player's eye `position`, leaving the body facing `body_yaw`
+    pub fn new(position: Vec3, body_yaw: f32) -> Self {
+        Self { position, speed: DEFAULT_SPEED, body_yaw }
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    /// Speed up (positive) or slow down by mouse wheel steps
+    pub fn scroll_speed(&mut self, steps: f32) {
+        self.set_speed(self.speed * SPEED_STEP.powf(steps));
+    }
+
+    /// Move along `direction` at the flying speed; blocks don't get in the way
+    pub fn fly(&mut self, direction: Vec3, delta_time: f32) {
+        self.position += direction.normalize_or_zero() * self.speed * delta_time;
+    }
+}
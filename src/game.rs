@@ -0,0 +1,1783 @@
+use crate::audio::{Audio, Footsteps, Sound};
+use crate::block_entity::BlockEntity;
+use crate::boat;
+use crate::camera::Camera;
+use crate::camera_smoothing::{CameraSmoothing, ViewBob, PHYSICS_STEP};
+use crate::catalog;
+use crate::cli::Args;
+use crate::chunk_loader::{self, FrameBudget};
+use crate::commands::{self, ClientContext, CommandResult};
+use crate::compass;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::config::ConfigWatcher;
+use crate::config::GameConfig;
+use crate::console::{Console, ConsoleAction};
+use crate::crash::{CrashGuard, PlayerState};
+use crate::debug::DebugInfo;
+use crate::debug_render::{DebugLayers, DebugMesh, DebugVertex};
+use crate::display;
+use crate::edit_history::EditHistory;
+use crate::enchanting;
+use crate::events::{EventListener, GameEvent};
+use crate::experience::{Experience, CATCH_POINTS, TRADE_POINTS};
+use crate::fishing::{self, Bobber, MAX_LINE_LENGTH};
+use crate::free_cam::FreeCam;
+use crate::game_state::{GameState, StateStack};
+use crate::input::{InputHandler, REACH};
+use crate::inventory::{SlotClick, SlotId};
+use crate::item::Item;
+use crate::item_entity::{self, ItemEntity};
+use crate::labels;
+use crate::loot::FISHING;
+use crate::menu::{Menu, MenuAction, MenuScreen};
+use crate::minimap::{Minimap, MinimapMode};
+use crate::physics::{GameMode, Player};
+use crate::player_stats::{PlayerStats, STATS_FILE};
+use crate::profiler::{Phase, Profiler};
+use crate::raycast::{raycast, RaycastFilter};
+use crate::protocol::{ClientConnection, ClientMessage, ServerMessage};
+use crate::remote_players::RemotePlayers;
+use crate::renderer::Renderer;
+use crate::resource_pack::{self, ResourcePack, TextureAtlas, RESOURCE_PACKS_DIR};
+use crate::session::Session;
+use crate::skin::{Skin, MAX_SKIN_FILE_SIZE};
+use crate::sleep;
+use crate::soundscape::{self, Soundscape};
+use crate::storage::Storage;
+use crate::survival::SurvivalStats;
+use crate::tick::TICK_LENGTH;
+use crate::time;
+use crate::toast::Toasts;
+use crate::ui::{ScreenSlot, UiRenderer, UiScale};
+use crate::villager;
+use crate::weather::Precipitation;
+use crate::world::World;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use web_time::Instant;
+use winit::event::*;
+use winit::keyboard::{KeyCode, PhysicalKey};
+use winit::window::Window;
+
+/// Everything the running game owns. Window events are routed to the handler
+/// of the current `GameState`; entering and leaving a state opens and closes
+/// its screen.
+pub struct Game {
+    window: Arc<Window>,
+    renderer: Renderer,
+    config: GameConfig,
+    /// The command line; its options take precedence over the config file
+    args: Args,
+    /// Changes to the config file made while the game runs
+    #[cfg(not(target_arch = "wasm32"))]
+    config_watcher: Option<ConfigWatcher>,
+    /// This player's statistics, kept next to the config
+    player_stats: PlayerStats,
+    stats_path: PathBuf,
+    /// Where the config, the statistics and a singleplayer world are kept
+    storage: Arc<dyn Storage>,
+    /// Kept up to date with what the crash hook saves and reports
+    crash: CrashGuard,
+    /// The in-process server's world, or a copy of a LAN server's; read
+    /// directly for rendering and physics
+    session: Session,
+    connection: ClientConnection,
+    players: RemotePlayers,
+    /// Built-in textures, filling in whatever the selected resource pack lacks
+    builtin_pack: ResourcePack,
+    camera: Camera,
+    smoothing: CameraSmoothing,
+    view_bob: ViewBob,
+    /// Spectator camera flying on its own while the player's body stays put
+    free_cam: Option<FreeCam>,
+    /// Predicted locally; the server may send it back to a valid position
+    player: Player,
+    /// The bobber of the player's fishing rod, while it is cast
+    bobber: Option<Bobber>,
+    input_handler: InputHandler,
+    ui_renderer: UiRenderer,
+    console: Console,
+    menu: Menu,
+    minimap: Minimap,
+    toasts: Toasts,
+    audio: Audio,
+    footsteps: Footsteps,
+    soundscape: Soundscape,
+    precipitation: Precipitation,
+    /// Streaks of the falling rain and snow, rebuilt every frame while it falls
+    weather_vertices: Vec<DebugVertex>,
+    weather_indices: Vec<u32>,
+    /// Distance the player walked since the last tick, for hunger
+    tick_distance: f32,
+    debug_info: DebugInfo,
+    profiler: Profiler,
+    /// Where to write the Chrome trace on exit, if one is being recorded
+    trace_path: Option<String>,
+    debug_layers: DebugLayers,
+    /// Set while F3 is held; true once it was combined with another key
+    f3_chord: Option<bool>,
+    #[cfg(feature = "debug-ui")]
+    dev_tools: crate::dev_tools::DevTools,
+    states: StateStack,
+    world_needs_update: bool,
+    last_camera_chunk: (i32, i32),
+    /// HUD values last drawn, to rebuild the HUD only when they change
+    last_hud: (u32, u32, u32, GameMode, Experience),
+    /// Compass needle last drawn in whole degrees, None while no compass is held
+    last_compass: Option<i32>,
+    /// Breaking progress last drawn under the crosshair in percent, None while
+    /// no block is being broken
+    last_mining: Option<u32>,
+    /// Thrown items or boats were part of the last entity mesh, which needs
+    /// clearing once they are gone
+    drew_items: bool,
+    /// Blocks changed in creative mode, for Ctrl+Z and Ctrl+Y
+    edit_history: EditHistory,
+    /// World border the walls were last built for
+    last_border: Option<i32>,
+    last_frame: Instant,
+    frame_count: u32,
+    last_fps_update: Instant,
+    /// Set once the game wants the event loop to stop
+    exit: bool,
+}
+
+impl Game {
+    /// Fails if no graphics adapter could be set up
+    pub async fn new(window: Arc<Window>, args: &Args, storage: Arc<dyn Storage>, crash: CrashGuard) -> Result<Self, String> {
+        let config = args.load_config(&*storage);
+
+        let mut toasts = Toasts::new();
+        let builtin_pack = ResourcePack::builtin();
+        let atlas = load_atlas(&builtin_pack, config.resource_pack.as_deref(), &mut toasts);
+        let mut renderer = Renderer::new(window.clone(), &atlas, config.graphics_backend).await?;
+        renderer.set_vsync(config.vsync);
+        renderer.set_fog(config.fog, config.view_distance);
+        renderer.set_smooth_lighting(config.smooth_lighting);
+        renderer.set_mipmapping(config.mipmapping);
+        crash.set_adapter(renderer.adapter().to_string());
+
+        let (session, connection, load_errors) = match &args.connect {
+            Some(address) => Session::connect(address, &args.name)?,
+            None => Session::open(args, Arc::clone(&storage), config.view_distance, config.simulation_distance),
+        };
+        if !load_errors.is_empty() {
+            toasts.push("Some block scripts or loot tables failed to load");
+        }
+        if let Session::Local(server) = &session {
+            server.watch_for_crash(&crash);
+        }
+        connection.send(ClientMessage::ViewDistance(config.view_distance));
+        let spawn_position = session.spawn_position();
+
+        let aspect = renderer.size.width as f32 / renderer.size.height as f32;
+        let mut camera = Camera::new(aspect);
+        let player = Player::new(spawn_position); // Spieler mit korrigierter Höhe starten
+        camera.position = spawn_position; // Kamera-Position synchronisieren
+        camera.set_base_fov(config.fov);
+        let mut input_handler = InputHandler::new();
+        input_handler.apply_config(&config);
+
+        let mut ui_renderer = UiRenderer::new();
+        ui_renderer.set_atlas(atlas.layout);
+        let ui_scale = UiScale::new(renderer.size.width, renderer.size.height, config.gui_scale);
+        ui_renderer.set_scale(ui_scale);
+        renderer.set_ui_scale(&ui_scale);
+        ui_renderer.build_toolbar(&session.world().inventory);
+        ui_renderer.sync_selected_block(&session.world().inventory);
+        ui_renderer.build_hud(&player.stats, &session.world().experience, player.game_mode);
+        let last_hud = hud_state(&player, &session.world().experience);
+
+        let music_seed = web_time::SystemTime::now()
+            .duration_since(web_time::UNIX_EPOCH)
+            .map_or(1, |d| d.subsec_nanos());
+        let last_camera_chunk = (
+            (camera.position.x / 16.0).floor() as i32,
+            (camera.position.z / 16.0).floor() as i32,
+        );
+
+        let stats_path = Path::new(&args.config).with_file_name(STATS_FILE);
+        let mut game = Self {
+            #[cfg(feature = "debug-ui")]
+            dev_tools: crate::dev_tools::DevTools::new(&window, renderer.device(), renderer.surface_format()),
+            window,
+            builtin_pack,
+            audio: Audio::new(config.master_volume),
+            renderer,
+            config,
+            args: args.clone(),
+            #[cfg(not(target_arch = "wasm32"))]
+            config_watcher: ConfigWatcher::new(&args.config)
+                .inspect_err(|e| log::warn!("Not watching {} for changes: {}", args.config, e))
+                .ok(),
+            player_stats: PlayerStats::load(&*storage, &stats_path),
+            stats_path,
+            storage,
+            crash,
+            session,
+            connection,
+            players: RemotePlayers::new(),
+            smoothing: CameraSmoothing::new(&camera),
+            view_bob: ViewBob::new(),
+            free_cam: None,
+            bobber: None,
+            camera,
+            last_hud,
+            last_compass: None,
+            last_mining: None,
+            drew_items: false,
+            edit_history: EditHistory::new(),
+            last_border: None,
+            player,
+            input_handler,
+            ui_renderer,
+            console: Console::new(),
+            menu: Menu::new(),
+            minimap: Minimap::new(),
+            toasts,
+            footsteps: Footsteps::new(),
+            soundscape: Soundscape::new(music_seed),
+            precipitation: Precipitation::new(),
+            weather_vertices: Vec::new(),
+            weather_indices: Vec::new(),
+            tick_distance: 0.0,
+            debug_info: DebugInfo::new(),
+            profiler: Profiler::new(),
+            trace_path: args.trace.clone(),
+            debug_layers: DebugLayers::default(),
+            f3_chord: None,
+            states: StateStack::new(GameState::MainMenu),
+            world_needs_update: false,
+            last_camera_chunk,
+            last_frame: Instant::now(),
+            frame_count: 0,
+            last_fps_update: Instant::now(),
+            exit: false,
+        };
+        if game.trace_path.is_some() {
+            game.profiler.start_trace();
+        }
+        game.precipitation.set_particles(game.config.particles);
+        game.apply_skin();
+        game.enter(GameState::MainMenu);
+        game.state_changed();
+        Ok(game)
+    }
+
+    /// Whether the event loop should stop
+    pub fn should_exit(&self) -> bool {
+        self.exit
+    }
+
+    pub fn window_event(&mut self, event: &WindowEvent) {
+        // The debug overlay gets first pick of every event while it is open
+        #[cfg(feature = "debug-ui")]
+        if self.dev_tools.on_window_event(&self.window, event) {
+            return;
+        }
+
+        match event {
+            WindowEvent::CloseRequested => {
+                // Nothing in the world changed before it was entered
+                self.exit(self.states.current() != GameState::MainMenu);
+                return;
+            }
+            // A minimized window has no size; keep everything as it was
+            WindowEvent::Resized(physical_size) if physical_size.width == 0 || physical_size.height == 0 => return,
+            WindowEvent::Resized(physical_size) => {
+                self.renderer.resize(*physical_size);
+                self.camera.update_aspect(physical_size.width as f32 / physical_size.height as f32);
+                self.rescale_ui();
+                self.minimap.invalidate();
+                return;
+            }
+            WindowEvent::RedrawRequested => {
+                self.frame();
+                return;
+            }
+            // F11 works everywhere, typing in the console included
+            WindowEvent::KeyboardInput { event, .. } if event.physical_key == PhysicalKey::Code(KeyCode::F11) => {
+                if event.state == ElementState::Pressed && !event.repeat {
+                    self.toggle_fullscreen();
+                }
+                return;
+            }
+            // Pause when the player switches to another window, which also
+            // frees the cursor
+            WindowEvent::Focused(focused) => {
+                self.input_handler.set_focused(*focused);
+                if !focused && self.states.current() == GameState::Playing {
+                    self.push_state(GameState::Paused);
+                }
+                return;
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.input_handler.process_modifiers(modifiers.state());
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.input_handler.process_cursor_moved(*position);
+            }
+            _ => {}
+        }
+
+        let start = Instant::now();
+        match self.states.current() {
+            GameState::MainMenu | GameState::Paused | GameState::Dead | GameState::Sleeping => self.menu_input(event),
+            // No input while the loading screen is up
+            GameState::Loading => {}
+            GameState::Playing => self.playing_input(event),
+            GameState::Inventory => self.inventory_input(event),
+            GameState::Console => self.console_input(event),
+        }
+        self.profiler.record(Phase::Input, start);
+    }
+
+    pub fn mouse_motion(&mut self, delta: (f64, f64)) {
+        self.input_handler.process_mouse_motion(delta);
+    }
+
+    fn push_state(&mut self, state: GameState) {
+        self.states.push(state);
+        self.enter(state);
+        self.state_changed();
+    }
+
+    /// Leave the current state for the one below it. Returns false if it was the last one.
+    fn pop_state(&mut self) -> bool {
+        let Some(state) = self.states.pop() else {
+            return false;
+        };
+        self.exit_state(state);
+        self.state_changed();
+        true
+    }
+
+    fn replace_state(&mut self, state: GameState) {
+        let old = self.states.replace(state);
+        self.exit_state(old);
+        self.enter(state);
+        self.state_changed();
+    }
+
+    /// Open the screen of a state that was just entered
+    fn enter(&mut self, state: GameState) {
+        match state {
+            GameState::MainMenu => self.menu.open(MenuScreen::Main),
+            GameState::Loading => {
+                // The server generates the spawn area in the background; frames
+                // keep presenting the loading screen until it is complete
+                self.ui_renderer.build_loading_screen(Some(0.0));
+            }
+            GameState::Paused => self.menu.open(MenuScreen::Pause),
+            GameState::Dead => self.menu.open(MenuScreen::Death),
+            GameState::Sleeping => self.menu.open(MenuScreen::Sleeping),
+            GameState::Inventory => {
+                self.ui_renderer.build_toolbar(&self.session.world().inventory);
+                build_inventory_screen(&mut self.ui_renderer, self.session.world());
+            }
+            GameState::Playing | GameState::Console => {}
+        }
+    }
+
+    /// Close the screen of a state that was just left
+    fn exit_state(&mut self, state: GameState) {
+        match state {
+            GameState::MainMenu | GameState::Paused | GameState::Dead => self.menu.close(),
+            GameState::Sleeping => {
+                self.menu.close();
+                // Harmless when the server woke everyone up itself
+                self.connection.send(ClientMessage::WakeUp);
+            }
+            GameState::Loading => self.ui_renderer.build_loading_screen(None),
+            GameState::Inventory => {
+                // Return any held stack to the inventory, throwing what doesn't fit
+                let world = self.session.world_mut();
+                if let Some(stack) = world.inventory.return_held() {
+                    world.items.push(ItemEntity::thrown(stack, self.player.eye_position(), self.camera.get_direction()));
+                    self.toasts.push("Inventory full, items dropped");
+                }
+                if self.ui_renderer.is_inventory_open() {
+                    self.ui_renderer.toggle_inventory();
+                }
+                self.ui_renderer.build_toolbar(&self.session.world().inventory);
+                build_inventory_screen(&mut self.ui_renderer, self.session.world());
+                self.ui_renderer.sync_selected_block(&self.session.world().inventory);
+            }
+            GameState::Console => self.console.close(),
+            GameState::Playing => {}
+        }
+    }
+
+    /// Free or grab the cursor for the new state and redraw the screens that depend on it
+    fn state_changed(&mut self) {
+        let state = self.states.current();
+        let gui_mode = state.gui_mode();
+        // The developer overlay needs a free cursor
+        #[cfg(feature = "debug-ui")]
+        let gui_mode = gui_mode || self.dev_tools.is_open();
+        self.input_handler.set_gui_mode(&self.window, gui_mode);
+        if gui_mode {
+            let (x, y) = self.input_handler.cursor_ui(self.renderer.size, &self.ui_renderer.scale());
+            self.ui_renderer.set_cursor(x, y);
+        }
+        self.renderer.set_world_visible(state.shows_world());
+        self.ui_renderer.build_menu(&self.menu, &self.config, &self.player_stats);
+        self.ui_renderer.build_console(&self.console);
+        self.renderer.update_ui(&self.ui_renderer);
+    }
+
+    /// Title, pause, death and sleeping screens: everything goes to the menu
+    fn menu_input(&mut self, event: &WindowEvent) {
+        let action = match event {
+            // Escape goes back a screen
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        physical_key: PhysicalKey::Code(KeyCode::Escape),
+                        ..
+                    },
+                ..
+            } => self.menu.back(),
+            WindowEvent::CursorMoved { .. } => {
+                let (x, y) = self.input_handler.cursor_ui(self.renderer.size, &self.ui_renderer.scale());
+                self.ui_renderer.set_cursor(x, y);
+                match self.menu.drag(x, &mut self.config) {
+                    MenuAction::None => MenuAction::Redraw, // hover highlight
+                    action => action,
+                }
+            }
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => {
+                let (x, y) = self.input_handler.cursor_ui(self.renderer.size, &self.ui_renderer.scale());
+                match state {
+                    ElementState::Pressed => {
+                        let action = self.menu.press(x, y, &mut self.config);
+                        if action != MenuAction::None {
+                            self.audio.play(Sound::UiClick);
+                        }
+                        action
+                    }
+                    ElementState::Released => self.menu.release(),
+                }
+            }
+            _ => MenuAction::None,
+        };
+
+        match action {
+            MenuAction::None => return,
+            MenuAction::Redraw => {}
+            MenuAction::Play => {
+                self.replace_state(GameState::Loading);
+                return;
+            }
+            MenuAction::Resume | MenuAction::LeaveBed => {
+                self.pop_state();
+                return;
+            }
+            MenuAction::Respawn => {
+                self.player.teleport(self.session.spawn_position());
+                self.player.stats = SurvivalStats::new();
+                self.connection.send(ClientMessage::Respawn);
+                self.pop_state();
+                return;
+            }
+            MenuAction::SaveAndQuit => {
+                self.exit(true);
+                return;
+            }
+            MenuAction::Quit => {
+                self.exit(false);
+                return;
+            }
+            MenuAction::ConfigChanged { save } => {
+                // Apply immediately; write config.json once the change is final
+                self.apply_config();
+                if save {
+                    if let Err(e) = self.config.save(&*self.storage, &self.args.config) {
+                        log::error!("Failed to save config: {}", e);
+                        self.toasts.push("Failed to save options");
+                    }
+                }
+            }
+        }
+        self.ui_renderer.build_menu(&self.menu, &self.config, &self.player_stats);
+        self.renderer.update_ui(&self.ui_renderer);
+    }
+
+    /// FPS controls: movement, block interaction and the keys that open screens
+    fn playing_input(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                self.input_handler.process_keyboard(event);
+                self.toggle_keys(event);
+                if event.state != ElementState::Pressed {
+                    return;
+                }
+
+                match event.physical_key {
+                    // Escape opens the pause menu
+                    PhysicalKey::Code(KeyCode::Escape) => self.push_state(GameState::Paused),
+                    // Open chat with T, or the command line with /
+                    PhysicalKey::Code(KeyCode::KeyT) => {
+                        self.console.open("");
+                        self.push_state(GameState::Console);
+                    }
+                    PhysicalKey::Code(KeyCode::Slash) => {
+                        self.console.open("/");
+                        self.push_state(GameState::Console);
+                    }
+                    // Cycle minimap mode (off / north-up / rotating) with M
+                    PhysicalKey::Code(KeyCode::KeyM) => {
+                        self.config.minimap_mode = self.config.minimap_mode.next();
+                        self.toasts.push(match self.config.minimap_mode {
+                            MinimapMode::Off => "Minimap: Off",
+                            MinimapMode::NorthUp => "Minimap: North Up",
+                            MinimapMode::Rotating => "Minimap: Rotating",
+                        });
+                    }
+                    // Undo the last block edits of creative mode with Ctrl+Z, redo them with Ctrl+Y
+                    PhysicalKey::Code(code @ (KeyCode::KeyZ | KeyCode::KeyY))
+                        if self.input_handler.is_ctrl_held() && self.player.game_mode == GameMode::Creative =>
+                    {
+                        let world = self.session.world_mut();
+                        let done = if code == KeyCode::KeyZ {
+                            self.edit_history.undo(world)
+                        } else {
+                            self.edit_history.redo(world)
+                        };
+                        if !done {
+                            self.toasts.push(if code == KeyCode::KeyZ { "Nothing to undo" } else { "Nothing to redo" });
+                        }
+                    }
+                    // Throw one item of the selected stack with Q, or all of it with Ctrl+Q
+                    PhysicalKey::Code(KeyCode::KeyQ) => self.drop_selected(self.input_handler.is_ctrl_held()),
+                    // Swap the selected stack into the off hand with F
+                    PhysicalKey::Code(KeyCode::KeyF) if !event.repeat => {
+                        self.session.world_mut().inventory.swap_offhand();
+                        self.ui_renderer.build_toolbar(&self.session.world().inventory);
+                        self.ui_renderer.sync_selected_block(&self.session.world().inventory);
+                        self.renderer.update_ui(&self.ui_renderer);
+                    }
+                    // Open the inventory with E, on the catalog in creative mode
+                    PhysicalKey::Code(KeyCode::KeyE) => {
+                        if self.player.game_mode == GameMode::Creative {
+                            self.ui_renderer.open_creative_inventory();
+                        } else {
+                            self.ui_renderer.toggle_inventory();
+                        }
+                        self.push_state(GameState::Inventory);
+                    }
+                    // Detach the camera from the player with F6
+                    PhysicalKey::Code(KeyCode::F6) if !event.repeat => {
+                        let free_cam = match self.free_cam {
+                            Some(_) => None,
+                            None => Some(FreeCam::new(self.camera.position, self.camera.yaw)),
+                        };
+                        self.set_free_cam(free_cam);
+                    }
+                    _ => {}
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                // The developer overlay may hold the cursor
+                if self.input_handler.is_gui_mode() {
+                    return;
+                }
+                self.input_handler.process_mouse_button(*state, *button);
+
+                // Handle block interactions on mouse click; the spectator camera only looks
+                if *state == ElementState::Pressed && self.free_cam.is_none() {
+                    self.interact(0.0);
+                }
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let scroll = match delta {
+                    MouseScrollDelta::LineDelta(_x, y) => *y as f64,
+                    MouseScrollDelta::PixelDelta(pos) => pos.y,
+                };
+                // The wheel sets the spectator camera's speed instead of the toolbar slot
+                if let Some(free_cam) = &mut self.free_cam {
+                    if scroll != 0.0 {
+                        free_cam.scroll_speed(scroll.signum() as f32);
+                        self.toasts.push(format!("Flying speed: {:.1} blocks/s", free_cam.speed()));
+                    }
+                    return;
+                }
+                if scroll > 0.0 {
+                    self.session.world_mut().inventory.prev_slot();
+                } else if scroll < 0.0 {
+                    self.session.world_mut().inventory.next_slot();
+                } else {
+                    return;
+                }
+                self.ui_renderer.sync_selected_block(&self.session.world().inventory);
+                self.ui_renderer.build_toolbar(&self.session.world().inventory);
+                self.renderer.update_ui(&self.ui_renderer);
+            }
+            _ => {}
+        }
+    }
+
+    /// Scatter the whole inventory around the player, who died, and lose
+    /// their experience
+    fn drop_inventory(&mut self) {
+        let eye = self.player.eye_position();
+        let world = self.session.world_mut();
+        world.experience = Experience::new();
+        for (i, stack) in world.inventory.take_all().into_iter().enumerate() {
+            // Spread out by the golden angle, so no two go the same way
+            let angle = i as f32 * 2.4;
+            world.items.push(ItemEntity::thrown(stack, eye, glam::Vec3::new(angle.cos(), 0.0, angle.sin())));
+        }
+        self.ui_renderer.build_toolbar(&self.session.world().inventory);
+        self.ui_renderer.sync_selected_block(&self.session.world().inventory);
+        self.renderer.update_ui(&self.ui_renderer);
+    }
+
+    /// Throw one item of the selected stack, or all of it, where the camera looks
+    fn drop_selected(&mut self, whole_stack: bool) {
+        let world = self.session.world_mut();
+        let Some(stack) = world.inventory.take_selected(whole_stack) else {
+            return;
+        };
+        world.items.push(ItemEntity::thrown(stack, self.player.eye_position(), self.camera.get_direction()));
+        self.ui_renderer.build_toolbar(&self.session.world().inventory);
+        self.ui_renderer.sync_selected_block(&self.session.world().inventory);
+        self.renderer.update_ui(&self.ui_renderer);
+    }
+
+    /// Break, place or open the block under the crosshair, or hit the player
+    /// there; `delta_time` is how long the left button was held since the last call
+    fn interact(&mut self, delta_time: f32) {
+        // Pass current player feet position to interaction handler so it can detect support removal.
+        let others = self.players.hitboxes();
+        let (world, scripts) = self.session.world_and_scripts();
+        // Creative edits are recorded for undo
+        let creative = self.player.game_mode == GameMode::Creative;
+        if creative {
+            world.recorded_edits = Some(Vec::new());
+        }
+        let queued = world.events.pending().len();
+        let interaction =
+            self.input_handler
+                .handle_block_interaction(&self.camera, world, &self.ui_renderer, &self.player, scripts, &others, delta_time);
+        // Only this player's own edits count towards their statistics
+        for event in &world.events.pending()[queued..] {
+            self.player_stats.on_event(event);
+        }
+        if let Some(edits) = world.recorded_edits.take() {
+            self.edit_history.record(edits);
+        }
+        if interaction.inventory_full {
+            self.toasts.push("Inventory full");
+        }
+        if interaction.tool_broke {
+            self.toasts.push("Your tool broke");
+        }
+        if interaction.removed_under_feet {
+            // Lost support -> start falling immediately
+            self.player.on_ground = false;
+            // optionally ensure some small downward velocity so we don't "stick" due to EPSILON checks:
+            // player.velocity.y = player.velocity.y.min(-0.01);
+        }
+        if let Some((pos, _)) = interaction.used_block {
+            self.connection.send(ClientMessage::UseBlock { pos });
+        }
+        if let Some(target) = interaction.attacked {
+            // The server checks the hit and swings the arm for the others
+            self.connection.send(ClientMessage::Attack { target });
+        } else if interaction.changed_block || interaction.used_block.is_some() {
+            self.connection.send(ClientMessage::Swing);
+        }
+        if let Some(index) = interaction.boarded {
+            self.board(index);
+        }
+        if interaction.used_rod {
+            self.use_rod();
+        }
+        if let Some(index) = interaction.trading {
+            let trades = self.session.world().villagers[index].trades.clone();
+            self.ui_renderer.open_trades(trades);
+            self.push_state(GameState::Inventory);
+        }
+        if let Some(pos) = interaction.opened_container {
+            if let Some(entity) = self.session.world().block_entity(pos) {
+                self.ui_renderer.open_container(pos, entity.kind());
+                self.push_state(GameState::Inventory);
+            }
+        }
+        if let Some(pos) = interaction.bed {
+            self.go_to_bed(pos);
+        }
+    }
+
+    /// Lie down in the bed at `pos`, if it is time to sleep; the server has
+    /// the final say and sends a wake-up if it disagrees
+    fn go_to_bed(&mut self, pos: (i32, i32, i32)) {
+        if !sleep::can_sleep(&self.session.world().time) {
+            self.toasts.push("You can only sleep at night or during thunderstorms");
+            return;
+        }
+        self.connection.send(ClientMessage::Sleep { pos });
+        self.push_state(GameState::Sleeping);
+    }
+
+    /// Cast the fishing rod, or reel the bobber in, catching the loot of a
+    /// bite in time
+    fn use_rod(&mut self) {
+        let eye = self.player.eye_position();
+        let seed = self.session.world().time.ticks() as u32;
+        let Some(bobber) = self.bobber.take() else {
+            self.bobber = Some(Bobber::cast(eye, self.camera.get_direction(), seed));
+            return;
+        };
+        let loot = self.session.generator().loot.clone();
+        if let Some(table) = loot.get(FISHING) {
+            let catch = bobber.reel_in(table, seed, eye);
+            let world = self.session.world_mut();
+            if !catch.is_empty() {
+                world.experience.add(CATCH_POINTS);
+            }
+            world.items.extend(catch);
+        }
+    }
+
+    /// Get into the boat with this index in `World::boats`
+    fn board(&mut self, index: usize) {
+        let world = self.session.world_mut();
+        if boat::ridden(world).is_some() || self.free_cam.is_some() {
+            return;
+        }
+        let boat = &mut world.boats[index];
+        boat.ridden = true;
+        self.player.teleport(boat.seat());
+        self.toasts.push("Sneak to get off the boat");
+    }
+
+    /// Get out of the boat, onto it
+    fn dismount(&mut self) {
+        let world = self.session.world_mut();
+        if let Some(index) = boat::ridden(world) {
+            let boat = &mut world.boats[index];
+            boat.ridden = false;
+            self.player.teleport(boat.dismount_position());
+        }
+    }
+
+    /// Row the boat the player sits in for `delta_time` seconds, keeping them
+    /// in it and turning the camera with it. Sneaking gets off, and so does
+    /// anything else moving the player, such as a teleport or a respawn.
+    fn paddle(&mut self, delta_time: f32) {
+        let world = self.session.world_mut();
+        let Some(index) = boat::ridden(world) else {
+            return;
+        };
+        let controlled = self.free_cam.is_none();
+        if self.player.position != world.boats[index].seat() {
+            world.boats[index].ridden = false;
+            return;
+        }
+        if controlled && self.input_handler.is_shift_held() {
+            self.dismount();
+            return;
+        }
+        let (forward, turn) = if controlled { self.input_handler.paddle() } else { (0.0, 0.0) };
+        // Moved on a copy, as it looks at the world it is part of
+        let mut boat = world.boats[index];
+        let yaw = boat.yaw;
+        boat.paddle(world, forward, turn, delta_time);
+        boat.update(world, delta_time);
+        world.boats[index] = boat;
+        self.smoothing.turn(&mut self.camera, boat.yaw - yaw);
+        self.player.position = boat.seat();
+        self.player.velocity = boat.velocity;
+        self.player.update_bounding_box();
+        self.player.sneaking = false;
+        self.player.sprinting = false;
+    }
+
+    /// Inventory, chest and furnace screens
+    fn inventory_input(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                // While the catalog's search box has focus it takes the keys, Escape still closing
+                if let Some(catalog) = self.ui_renderer.catalog_mut().filter(|catalog| catalog.search_focused) {
+                    if event.state != ElementState::Pressed {
+                        return;
+                    }
+                    match (&event.physical_key, &event.text) {
+                        (PhysicalKey::Code(KeyCode::Escape), _) => {
+                            self.pop_state();
+                            return;
+                        }
+                        (PhysicalKey::Code(KeyCode::Backspace), _) => catalog.backspace(),
+                        (PhysicalKey::Code(KeyCode::Enter), _) => catalog.search_focused = false,
+                        (_, Some(text)) => catalog.type_text(text),
+                        _ => return,
+                    }
+                    build_inventory_screen(&mut self.ui_renderer, self.session.world());
+                    self.renderer.update_ui(&self.ui_renderer);
+                    return;
+                }
+                self.toggle_keys(event);
+                // Escape or E closes the screen and returns to the game
+                if event.state == ElementState::Pressed
+                    && matches!(event.physical_key, PhysicalKey::Code(KeyCode::Escape | KeyCode::KeyE))
+                {
+                    self.pop_state();
+                } else if event.state == ElementState::Pressed
+                    && !event.repeat
+                    && event.physical_key == PhysicalKey::Code(KeyCode::KeyR)
+                {
+                    self.sort_inventory();
+                }
+            }
+            WindowEvent::CursorMoved { .. } => {
+                // Keep hover highlight and the held stack following the cursor
+                let (x, y) = self.input_handler.cursor_ui(self.renderer.size, &self.ui_renderer.scale());
+                self.ui_renderer.set_cursor(x, y);
+                build_inventory_screen(&mut self.ui_renderer, self.session.world());
+                self.renderer.update_ui(&self.ui_renderer);
+            }
+            WindowEvent::MouseInput { state: ElementState::Pressed, button, .. } => {
+                let (x, y) = self.input_handler.cursor_ui(self.renderer.size, &self.ui_renderer.scale());
+                if !self.ui_renderer.inventory_panel_contains(x, y) {
+                    // Clicking outside the open screen closes it and returns to the game
+                    self.pop_state();
+                    return;
+                }
+
+                if self.ui_renderer.sort_button_contains(x, y) {
+                    self.sort_inventory();
+                    return;
+                }
+                // Creative tabs and the search box; clicking anywhere else takes the focus off it
+                if let Some(tab) = self.ui_renderer.tab_at(x, y) {
+                    self.ui_renderer.set_tab(tab);
+                } else {
+                    let on_search = self.ui_renderer.search_box_contains(x, y);
+                    if let Some(catalog) = self.ui_renderer.catalog_mut() {
+                        catalog.search_focused = on_search;
+                    }
+                }
+
+                let click = match button {
+                    MouseButton::Left if self.input_handler.is_shift_held() => SlotClick::ShiftLeft,
+                    MouseButton::Left => SlotClick::Left,
+                    MouseButton::Right => SlotClick::Right,
+                    _ => return,
+                };
+                match self.ui_renderer.slot_at(x, y) {
+                    Some(ScreenSlot::Catalog(idx)) => {
+                        let item = self.ui_renderer.catalog().and_then(|catalog| catalog.item_at(idx));
+                        catalog::click_catalog(item, click, &mut self.session.world_mut().inventory);
+                    }
+                    Some(ScreenSlot::Slot(slot)) => {
+                        click_screen_slot(self.session.world_mut(), self.ui_renderer.container_pos(), slot, click)
+                    }
+                    // A click on an offer's result trades once, a shift-click as often as it can
+                    Some(ScreenSlot::Trade(idx)) => {
+                        let Some(trade) = self.ui_renderer.trade(idx).copied() else {
+                            return;
+                        };
+                        let inventory = &mut self.session.world_mut().inventory;
+                        // Each trade pays its price, so repeating runs out
+                        let mut made = 0;
+                        while (made == 0 || click == SlotClick::ShiftLeft) && trade.make(inventory) {
+                            made += 1;
+                        }
+                        if made == 0 {
+                            self.toasts.push(if trade.affordable(inventory) { "Inventory full" } else { "Can't afford that" });
+                        }
+                        self.session.world_mut().experience.add(made * TRADE_POINTS);
+                    }
+                    // A click on an offer of the enchanting table buys it for the tool in the table
+                    Some(ScreenSlot::Enchantment(idx)) => {
+                        let Some(pos) = self.ui_renderer.container_pos() else {
+                            return;
+                        };
+                        let world = self.session.world_mut();
+                        let Some(BlockEntity::EnchantingTable(table)) = world.block_entities.get_mut(&pos) else {
+                            return;
+                        };
+                        let Some(tool) = &mut table.slots[0] else {
+                            return;
+                        };
+                        let Some(offer) = enchanting::offers(tool).get(idx).copied() else {
+                            return;
+                        };
+                        if !enchanting::enchant(tool, offer, &mut world.experience) {
+                            self.toasts.push("Not enough levels");
+                        }
+                    }
+                    None => {
+                        build_inventory_screen(&mut self.ui_renderer, self.session.world());
+                        self.renderer.update_ui(&self.ui_renderer);
+                        return;
+                    }
+                }
+                self.audio.play(Sound::UiClick);
+                self.ui_renderer.build_toolbar(&self.session.world().inventory);
+                build_inventory_screen(&mut self.ui_renderer, self.session.world());
+                self.ui_renderer.sync_selected_block(&self.session.world().inventory);
+                self.renderer.update_ui(&self.ui_renderer);
+            }
+            // The wheel scrolls the catalog a row at a time
+            WindowEvent::MouseWheel { delta, .. } => {
+                let scroll = match delta {
+                    MouseScrollDelta::LineDelta(_x, y) => *y as f64,
+                    MouseScrollDelta::PixelDelta(pos) => pos.y,
+                };
+                if let Some(catalog) = self.ui_renderer.catalog_mut() {
+                    if scroll != 0.0 {
+                        catalog.scroll_by(-scroll.signum() as i32);
+                        build_inventory_screen(&mut self.ui_renderer, self.session.world());
+                        self.renderer.update_ui(&self.ui_renderer);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Merge and order the storage of the inventory screen (R or the Sort button)
+    fn sort_inventory(&mut self) {
+        self.session.world_mut().inventory.compact();
+        self.audio.play(Sound::UiClick);
+        build_inventory_screen(&mut self.ui_renderer, self.session.world());
+        self.renderer.update_ui(&self.ui_renderer);
+    }
+
+    /// Chat and command line: it receives all keyboard input
+    fn console_input(&mut self, event: &WindowEvent) {
+        let WindowEvent::KeyboardInput { event, .. } = event else {
+            return;
+        };
+        match self.console.handle_key(event) {
+            ConsoleAction::None => return,
+            ConsoleAction::Changed => {}
+            ConsoleAction::Close => {
+                self.pop_state();
+                return;
+            }
+            ConsoleAction::Submit(line) => {
+                if line.starts_with('/') {
+                    let previous_pack = self.config.resource_pack.clone();
+                    let mut free_cam = self.free_cam.take();
+                    let was_spectating = free_cam.is_some();
+                    let mut ctx = ClientContext {
+                        resource_pack: &mut self.config.resource_pack,
+                        free_cam: &mut free_cam,
+                        eye: self.camera.position,
+                        yaw: self.camera.yaw,
+                    };
+                    match commands::execute_client(&line, &mut ctx) {
+                        Some(result) => self.print_command_output(result),
+                        // Everything else runs on the server, which answers with the output
+                        None => {
+                            let direction = self.camera.get_direction();
+                            let target = raycast(self.camera.position, direction, REACH, self.session.world(), RaycastFilter::TARGET_BLOCKS)
+                                .filter(|hit| hit.block().is_some())
+                                .map(|hit| hit.position);
+                            self.connection.send(ClientMessage::Command(commands::with_target(line, target)));
+                        }
+                    }
+                    if free_cam.is_some() != was_spectating {
+                        self.set_free_cam(free_cam);
+                    } else {
+                        self.free_cam = free_cam;
+                    }
+                    if self.config.resource_pack != previous_pack {
+                        self.apply_resource_pack();
+                        self.config.save(&*self.storage, &self.args.config).ok();
+                    }
+                } else {
+                    // The server sends chat back to everyone, this player included
+                    self.connection.send(ClientMessage::Chat(line));
+                }
+                self.pop_state();
+                return;
+            }
+        }
+        self.ui_renderer.build_console(&self.console);
+        self.renderer.update_ui(&self.ui_renderer);
+    }
+
+    /// Bring everything the options affect in line with `config`
+    fn apply_config(&mut self) {
+        let scale = UiScale::new(self.renderer.size.width, self.renderer.size.height, self.config.gui_scale);
+        if self.ui_renderer.scale() != scale {
+            self.rescale_ui();
+            self.minimap.invalidate();
+        }
+        self.input_handler.apply_config(&self.config);
+        self.audio.set_volume(self.config.master_volume);
+        self.camera.set_base_fov(self.config.fov);
+        self.renderer.set_vsync(self.config.vsync);
+        self.renderer.set_fog(self.config.fog, self.config.view_distance);
+        self.renderer.set_smooth_lighting(self.config.smooth_lighting);
+        self.renderer.set_mipmapping(self.config.mipmapping);
+        self.precipitation.set_particles(self.config.particles);
+        self.connection.send(ClientMessage::ViewDistance(self.config.view_distance));
+        self.world_needs_update = true;
+    }
+
+    /// Apply the config file again if it was changed outside the game. A
+    /// file that doesn't parse, likely half written, is left for the next
+    /// change.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reload_config(&mut self) {
+        if !self.config_watcher.as_ref().is_some_and(ConfigWatcher::changed) {
+            return;
+        }
+        let mut config = match GameConfig::try_load(&*self.storage, &self.args.config) {
+            Ok(config) => config,
+            Err(e) => {
+                log::warn!("Not reloading {}: {}", self.args.config, e);
+                return;
+            }
+        };
+        self.args.apply_overrides(&mut config);
+        // Most likely the game's own save
+        if config == self.config {
+            return;
+        }
+        log::info!("Reloading {}", self.args.config);
+        let previous = std::mem::replace(&mut self.config, config);
+        self.apply_config();
+        if self.config.resource_pack != previous.resource_pack {
+            self.apply_resource_pack();
+        }
+        if self.config.skin != previous.skin {
+            self.apply_skin();
+        }
+        if self.config.fullscreen != previous.fullscreen {
+            display::set_fullscreen(&self.window, &self.config, self.config.fullscreen);
+        }
+        self.ui_renderer.build_menu(&self.menu, &self.config, &self.player_stats);
+        self.renderer.update_ui(&self.ui_renderer);
+        self.toasts.push("Options reloaded");
+    }
+
+    fn print_command_output(&mut self, result: CommandResult) {
+        match result {
+            Ok(msg) => self.console.print(msg),
+            Err(err) => self.console.print(format!("Error: {}", err)),
+        }
+    }
+
+    /// Swap the block textures for those of the configured resource pack
+    fn apply_resource_pack(&mut self) {
+        let atlas = load_atlas(&self.builtin_pack, self.config.resource_pack.as_deref(), &mut self.toasts);
+        self.renderer.set_texture_atlas(&atlas);
+        self.ui_renderer.set_atlas(atlas.layout);
+        self.ui_renderer.build_toolbar(&self.session.world().inventory);
+        self.world_needs_update = true;
+    }
+
+    /// Wear the configured skin and show it to the other players. A skin that
+    /// fails to load is reported and the default one worn instead.
+    fn apply_skin(&mut self) {
+        let skin = self.config.skin.as_deref().and_then(|path| match load_skin(&*self.storage, path) {
+            Ok(skin) => Some(skin),
+            Err(e) => {
+                log::error!("Failed to load skin {}: {}", path, e);
+                self.toasts.push(format!("Failed to load skin {}", path));
+                None
+            }
+        });
+        let data = skin.as_ref().map_or_else(Vec::new, |(_, data)| data.clone());
+        self.players.set_own_skin(skin.map(|(skin, _)| skin));
+        self.connection.send(ClientMessage::Skin(data));
+    }
+
+    /// Detach the camera from the player's body, or put it back
+    fn set_free_cam(&mut self, free_cam: Option<FreeCam>) {
+        self.toasts.push(if free_cam.is_some() { "Spectator camera: On" } else { "Spectator camera: Off" });
+        self.free_cam = free_cam;
+        self.player.sprinting = false;
+        // `update_world` draws the body left behind; stop drawing it on return
+        if self.free_cam.is_none() && self.players.is_empty() {
+            self.renderer.update_entities(&[], &[]);
+        }
+    }
+
+    /// Switch between fullscreen and a window, and remember it for the next start
+    fn toggle_fullscreen(&mut self) {
+        self.config.fullscreen = self.window.fullscreen().is_none();
+        display::set_fullscreen(&self.window, &self.config, self.config.fullscreen);
+        if let Err(e) = self.config.save(&*self.storage, &self.args.config) {
+            log::error!("Failed to save config: {}", e);
+        }
+    }
+
+    /// Debug toggles available in the game view and on the inventory screen.
+    /// F3 on its own toggles the debug view when released; F3+G, F3+B and
+    /// F3+L toggle the chunk border, hitbox and structure layers instead.
+    fn toggle_keys(&mut self, event: &KeyEvent) {
+        let pressed = event.state == ElementState::Pressed;
+        match event.physical_key {
+            PhysicalKey::Code(KeyCode::F3) if pressed && !event.repeat => self.f3_chord = Some(false),
+            PhysicalKey::Code(KeyCode::F3) if !pressed => {
+                self.config.show_debug ^= self.f3_chord.take() == Some(false);
+            }
+            PhysicalKey::Code(KeyCode::KeyG) if pressed && self.f3_chord.is_some() => {
+                self.f3_chord = Some(true);
+                self.debug_layers.chunk_borders = !self.debug_layers.chunk_borders;
+                self.toasts.push(if self.debug_layers.chunk_borders {
+                    "Chunk borders: On"
+                } else {
+                    "Chunk borders: Off"
+                });
+                self.debug_layers_changed();
+            }
+            PhysicalKey::Code(KeyCode::KeyB) if pressed && self.f3_chord.is_some() => {
+                self.f3_chord = Some(true);
+                self.debug_layers.hitboxes = !self.debug_layers.hitboxes;
+                self.toasts.push(if self.debug_layers.hitboxes { "Hitboxes: On" } else { "Hitboxes: Off" });
+                self.debug_layers_changed();
+            }
+            PhysicalKey::Code(KeyCode::KeyL) if pressed && self.f3_chord.is_some() => {
+                self.f3_chord = Some(true);
+                self.debug_layers.structures = !self.debug_layers.structures;
+                self.toasts.push(if self.debug_layers.structures { "Structures: On" } else { "Structures: Off" });
+                self.debug_layers_changed();
+            }
+            // Toggle the developer overlay with F4
+            #[cfg(feature = "debug-ui")]
+            PhysicalKey::Code(KeyCode::F4) if pressed => {
+                self.dev_tools.toggle();
+                self.state_changed();
+            }
+            _ => {}
+        }
+    }
+
+    /// Clear the debug geometry once its last layer is turned off; while any
+    /// layer is on it is rebuilt every frame in `update_world`
+    fn debug_layers_changed(&mut self) {
+        if !self.debug_layers.any() {
+            self.renderer.update_debug(&DebugMesh::default());
+        }
+    }
+
+    fn frame(&mut self) {
+        let now = Instant::now();
+        let delta_time = now.duration_since(self.last_frame).as_secs_f32();
+        self.last_frame = now;
+        #[cfg(not(target_arch = "wasm32"))]
+        self.reload_config();
+
+        let state = self.states.current();
+        match state {
+            GameState::MainMenu => {}
+            GameState::Loading => self.update_loading(),
+            _ => self.update_world(state, delta_time),
+        }
+        self.render();
+        self.profiler.end_frame();
+
+        if state.shows_world() {
+            self.report_fps(now);
+        }
+    }
+
+    /// Apply what the server sent since the last frame. Gameplay events go to
+    /// every system that reacts to them.
+    fn receive_messages(&mut self) {
+        let mut inventory_changed = false;
+        // A network client's own edits raise their events in its copy of the world
+        let own_events = self.session.take_events().into_iter().map(ServerMessage::Event);
+        for message in own_events.chain(self.connection.receive()) {
+            if self.session.apply(&message) {
+                self.world_needs_update = true;
+            }
+            match message {
+                ServerMessage::Teleport { position } => self.player.teleport(position.into()),
+                ServerMessage::GameMode(mode) => self.player.game_mode = mode,
+                ServerMessage::Vitals { health, hunger } => {
+                    self.player.stats.health = health;
+                    self.player.stats.hunger = hunger;
+                }
+                // The session added them to the network client's world
+                ServerMessage::Give { .. } | ServerMessage::Experience { .. } => inventory_changed = true,
+                ServerMessage::CommandOutput(result) => {
+                    self.print_command_output(result);
+                    // Commands may have changed the inventory
+                    inventory_changed = true;
+                }
+                ServerMessage::Tick => self.tick(),
+                ServerMessage::WorldChanged => self.world_needs_update = true,
+                ServerMessage::BlockEntitiesChanged => {
+                    // Furnaces keep smelting; refresh an open furnace screen
+                    if self.ui_renderer.container_pos().is_some() {
+                        build_inventory_screen(&mut self.ui_renderer, self.session.world());
+                        self.renderer.update_ui(&self.ui_renderer);
+                    }
+                }
+                ServerMessage::Event(event) => {
+                    self.audio.on_event(&event);
+                    inventory_changed |= matches!(
+                        event,
+                        GameEvent::BlockBroken { .. } | GameEvent::BlockPlaced { .. } | GameEvent::ItemPickedUp { .. }
+                    );
+                }
+                ServerMessage::EntitySpawn { id, name, position, yaw } => {
+                    self.toasts.push(format!("{} joined the game", name));
+                    self.players.spawn(id, name, position.into(), yaw);
+                }
+                ServerMessage::EntityMove { id, position, yaw } => self.players.move_to(id, position.into(), yaw),
+                ServerMessage::EntitySwing { id } => self.players.swing(id),
+                ServerMessage::EntitySkin { id, data } => {
+                    let skin = (!data.is_empty()).then(|| Skin::decode(&data)).transpose();
+                    let skin = skin.unwrap_or_else(|e| {
+                        log::warn!("Ignoring the skin of player {}: {}", id, e);
+                        None
+                    });
+                    self.players.set_skin(id, skin);
+                }
+                ServerMessage::EntityDespawn { id } => {
+                    if let Some(name) = self.players.despawn(id) {
+                        self.toasts.push(format!("{} left the game", name));
+                    }
+                    if self.players.is_empty() {
+                        self.renderer.update_entities(&[], &[]);
+                        self.renderer.update_labels(&[], &[]);
+                    }
+                }
+                ServerMessage::Chat { from, text } => self.console.print(format!("<{}> {}", from, text)),
+                ServerMessage::SleepStatus(status) => {
+                    self.ui_renderer.build_action_bar(status.message().as_deref());
+                    self.renderer.update_ui(&self.ui_renderer);
+                }
+                ServerMessage::WakeUp => {
+                    if self.states.current() == GameState::Sleeping {
+                        self.pop_state();
+                    }
+                }
+                ServerMessage::KeepAlive(id) => self.connection.send(ClientMessage::KeepAlive(id)),
+                ServerMessage::Hurt { amount, knockback } => self.hurt(amount, knockback.into()),
+                ServerMessage::Disconnect(reason) => {
+                    log::error!("Disconnected by the server: {}", reason);
+                    self.exit(false);
+                }
+                // Chunks, block changes and the clock went to the session above
+                ServerMessage::Saved(_)
+                | ServerMessage::LoginAccepted(_)
+                | ServerMessage::Time(_)
+                | ServerMessage::Weather(_)
+                | ServerMessage::WorldBorder(_)
+                | ServerMessage::GameRules(_)
+                | ServerMessage::ChunkData { .. }
+                | ServerMessage::ChunkUnload { .. }
+                | ServerMessage::BlockChange { .. }
+                | ServerMessage::MultiBlockChange { .. } => {}
+            }
+        }
+        if self.session.is_remote() && !self.connection.is_open() && !self.exit {
+            log::error!("Lost the connection to the server");
+            self.exit(false);
+        }
+        if inventory_changed {
+            // Update UI to reflect inventory changes
+            self.ui_renderer.build_toolbar(&self.session.world().inventory);
+            self.ui_renderer.sync_selected_block(&self.session.world().inventory);
+            self.renderer.update_ui(&self.ui_renderer);
+        }
+    }
+
+    fn update_loading(&mut self) {
+        let start = Instant::now();
+        // Nothing moves on the loading screen, so there are no hitches to avoid
+        self.session.load_chunks(FrameBudget::UNLIMITED);
+        // A LAN server sends the chunks around spawn
+        self.receive_messages();
+        self.profiler.record(Phase::ChunkGen, start);
+        let progress = chunk_loader::area_progress(self.session.world(), self.last_camera_chunk, self.config.view_distance);
+        if progress >= 1.0 {
+            self.renderer.update_mesh(self.session.world_mut(), &self.camera, self.config.view_distance, FrameBudget::UNLIMITED, &mut self.profiler);
+            self.replace_state(GameState::Playing);
+        } else {
+            self.ui_renderer.build_loading_screen(Some(progress));
+            self.renderer.update_ui(&self.ui_renderer);
+        }
+    }
+
+    /// Advance the world by one frame. The pause menu freezes it; the scene keeps
+    /// rendering behind every screen.
+    fn update_world(&mut self, state: GameState, delta_time: f32) {
+        // Update camera look direction and FOV
+        let start = Instant::now();
+        let sprinting = self.player.sprinting && state.simulates_player();
+        self.camera.update_fov(self.input_handler.is_zoom_held(), sprinting, delta_time, self.config.smooth_fov);
+        let input_handler = &mut self.input_handler;
+        self.smoothing.look(&mut self.camera, self.config.camera_smoothing, delta_time, |camera| {
+            input_handler.update_camera(camera)
+        });
+        self.input_handler.recenter_cursor(&self.window);
+        let start = self.profiler.record(Phase::Input, start);
+
+        // Predict the player's movement in fixed steps and report it to the server
+        if state.simulates_player() {
+            self.player_stats.play_time += delta_time as f64;
+            for _ in 0..self.smoothing.advance(delta_time) {
+                let before = self.player.eye_position();
+                self.update_player(PHYSICS_STEP);
+                self.smoothing.record_step(before, self.player.eye_position());
+            }
+            if let Some(free_cam) = &mut self.free_cam {
+                free_cam.fly(self.input_handler.fly_direction(&self.camera), delta_time);
+            }
+            self.connection.send(ClientMessage::Move {
+                position: self.player.position.to_array(),
+                velocity: self.player.velocity.to_array(),
+                on_ground: self.player.on_ground,
+                yaw: self.free_cam.as_ref().map_or(self.camera.yaw, |free_cam| free_cam.body_yaw),
+            });
+        }
+        let start = self.profiler.record(Phase::Physics, start);
+
+        // Holding the left button keeps breaking the block under the crosshair
+        if self.input_handler.left_mouse_held && state.simulates_player() && self.free_cam.is_none() {
+            self.interact(delta_time);
+        }
+        let progress = self.input_handler.mining.progress();
+        let shown = progress.map(|progress| (progress * 100.0) as u32);
+        if shown != self.last_mining {
+            self.last_mining = shown;
+            self.ui_renderer.build_mining_progress(progress);
+            self.renderer.update_ui(&self.ui_renderer);
+        }
+
+        // Thrown items fall and are picked up again, with a pop and a flash of
+        // the toolbar slots they went to
+        if state.simulates_world() && !self.session.world().items.is_empty() {
+            let before = self.session.world().inventory.toolbar;
+            let body = (state != GameState::Dead).then_some(&self.player.bounding_box);
+            if item_entity::update_items(self.session.world_mut(), body, delta_time) {
+                self.audio.play(Sound::Pickup);
+                let after = self.session.world().inventory.toolbar;
+                for slot in (0..after.len()).filter(|&i| after[i].map(|s| s.count) > before[i].map(|s| s.count)) {
+                    self.ui_renderer.flash_slot(slot);
+                }
+                self.ui_renderer.build_toolbar(&self.session.world().inventory);
+                self.ui_renderer.sync_selected_block(&self.session.world().inventory);
+                self.renderer.update_ui(&self.ui_renderer);
+            }
+        }
+        // The bobber floats until something bites, and the line breaks when
+        // the rod is put away or the bobber is out of reach
+        if let Some(bobber) = &mut self.bobber {
+            let world = self.session.world();
+            let holding = world.inventory.hand_item(world.inventory.active_hand()) == Some(Item::FishingRod);
+            if !holding || state == GameState::Dead || bobber.position.distance(self.player.eye_position()) > MAX_LINE_LENGTH {
+                self.bobber = None;
+            } else if state.simulates_world() && bobber.update(world, delta_time) {
+                self.audio.play(Sound::Splash);
+            }
+        }
+        // Boats left alone float and drift
+        if state.simulates_world() && !self.session.world().boats.is_empty() {
+            boat::update_boats(self.session.world_mut(), delta_time);
+        }
+        // Villagers stroll about and watch the players nearby
+        if state.simulates_world() && !self.session.world().villagers.is_empty() {
+            let others = self.players.iter().map(|player| Player::new(player.position).eye_position());
+            let players: Vec<_> = std::iter::once(self.player.eye_position()).chain(others).collect();
+            villager::update_villagers(self.session.world_mut(), &players, delta_time);
+        }
+        if self.ui_renderer.update_slot_flashes(delta_time) {
+            self.ui_renderer.build_toolbar(&self.session.world().inventory);
+            self.renderer.update_ui(&self.ui_renderer);
+        }
+
+        self.session.load_chunks(self.config.chunk_budget());
+        let start = self.profiler.record(Phase::ChunkGen, start);
+
+        // The rest of the simulation runs in fixed ticks on the server
+        self.session.update(&self.connection, delta_time, state.simulates_world());
+        self.receive_messages();
+        self.profiler.record(Phase::Physics, start);
+
+        // Rain and snow fade in and out with the weather, darkening the sky and the world
+        let time = &self.session.world().time;
+        self.precipitation.update(time.weather(), delta_time);
+        self.renderer.set_sky_color(time::sky_color(time.daylight() * self.precipitation.light()));
+        self.renderer.set_light(self.precipitation.light());
+
+        if self.toasts.update(delta_time) {
+            self.ui_renderer.build_toasts(&self.toasts);
+            self.renderer.update_ui(&self.ui_renderer);
+        }
+        if self.console.update(delta_time) {
+            self.ui_renderer.build_console(&self.console);
+            self.renderer.update_ui(&self.ui_renderer);
+        }
+
+        let hud = hud_state(&self.player, &self.session.world().experience);
+        if hud != self.last_hud {
+            self.last_hud = hud;
+            self.ui_renderer.build_hud(&self.player.stats, &self.session.world().experience, self.player.game_mode);
+            self.renderer.update_ui(&self.ui_renderer);
+        }
+
+        // Sync camera position with player, unless it is flying on its own
+        self.view_bob.update(delta_time);
+        self.camera.position = match &self.free_cam {
+            Some(free_cam) => free_cam.position,
+            None => {
+                let eye = self.smoothing.eye_position(self.player.eye_position(), self.config.interpolate_movement);
+                if self.config.view_bobbing {
+                    eye + self.view_bob.offset(self.camera.get_right())
+                } else {
+                    eye
+                }
+            }
+        };
+
+        // Ambience follows the player's surroundings; music plays now and then
+        let ambience = soundscape::detect(self.session.world(), self.camera.position);
+        let eye = self.camera.position.floor().as_ivec3();
+        let rain = if self.session.generator().is_cold(eye.x, eye.y, eye.z) {
+            0.0
+        } else {
+            self.precipitation.strength() * soundscape::rain_exposure(self.session.world(), self.camera.position)
+        };
+        if let Some(track) = self.soundscape.update(delta_time, ambience, rain, self.audio.is_music_playing()) {
+            self.audio.play_music(track);
+        }
+        for (ambience, level) in self.soundscape.levels() {
+            self.audio.set_ambience_level(ambience, level);
+        }
+
+        if self.minimap.update(self.session.world(), self.player.position, self.camera.yaw, self.config.minimap_mode) {
+            self.ui_renderer.build_minimap(&self.minimap, self.config.minimap_mode, self.player.position, self.camera.yaw);
+            self.renderer.update_ui(&self.ui_renderer);
+        }
+
+        // A held compass points to the world spawn
+        let needle = (self.session.world().inventory.selected_item() == Some(Item::Compass))
+            .then(|| compass::needle_angle(self.player.position, self.camera.yaw, self.session.spawn_position()));
+        let shown = needle.map(|angle| angle.to_degrees().round() as i32);
+        if shown != self.last_compass {
+            self.last_compass = shown;
+            self.ui_renderer.build_compass(needle);
+            self.renderer.update_ui(&self.ui_renderer);
+        }
+
+        let cam_chunk_x = (self.camera.position.x / 16.0).floor() as i32;
+        let cam_chunk_z = (self.camera.position.z / 16.0).floor() as i32;
+
+        // Check if camera moved to a different chunk
+        let current_chunk = (cam_chunk_x, cam_chunk_z);
+        let camera_moved_chunk = current_chunk != self.last_camera_chunk;
+        if camera_moved_chunk {
+            self.last_camera_chunk = current_chunk;
+        }
+
+        // Update mesh if world changed or camera moved to different chunk,
+        // a few chunks a frame until all are built
+        if self.world_needs_update || camera_moved_chunk {
+            let budget = self.config.chunk_budget();
+            let done = self.renderer.update_mesh(self.session.world_mut(), &self.camera, self.config.view_distance, budget, &mut self.profiler);
+            self.world_needs_update = !done;
+        }
+
+        let start = Instant::now();
+        self.renderer.update_camera(&self.camera);
+        let border = self.session.world().border;
+        if border != self.last_border {
+            self.last_border = border;
+            self.renderer.update_border(border);
+        }
+        if self.debug_layers.any() {
+            let others = self.players.hitboxes();
+            let mesh = DebugMesh::build(self.debug_layers, self.session.world(), &self.player, &self.camera, &others);
+            self.renderer.update_debug(&mesh);
+        }
+        if self.precipitation.strength() > 0.0 || !self.weather_indices.is_empty() {
+            let (world, generator) = (self.session.world(), self.session.generator());
+            self.precipitation.build_mesh(world, generator, self.camera.position, &mut self.weather_vertices, &mut self.weather_indices);
+            self.renderer.update_weather(&self.weather_vertices, &self.weather_indices);
+        }
+        let (items, boats, villagers) = (&self.session.world().items, &self.session.world().boats, &self.session.world().villagers);
+        let entities = !items.is_empty() || !boats.is_empty() || !villagers.is_empty() || self.bobber.is_some();
+        if !self.players.is_empty() || self.free_cam.is_some() || entities || self.drew_items {
+            // The spectator camera sees the player's own body too
+            let body = self.free_cam.as_ref().map(|free_cam| (self.player.position, free_cam.body_yaw));
+            self.players.set_body(body);
+            self.players.update(delta_time);
+            let (mut vertices, mut indices) = self.players.build_mesh();
+            item_entity::build_mesh(items, &mut vertices, &mut indices);
+            boat::build_mesh(boats, &mut vertices, &mut indices);
+            if let Some(bobber) = &self.bobber {
+                fishing::build_mesh(bobber, &mut vertices, &mut indices);
+            }
+            villager::build_mesh(villagers, &mut vertices, &mut indices);
+            self.drew_items = entities;
+            self.renderer.update_entities(&vertices, &indices);
+            let (mut label_vertices, mut label_indices) = (Vec::new(), Vec::new());
+            labels::build_mesh(&self.players.labels(), &self.camera, &mut label_vertices, &mut label_indices);
+            self.renderer.update_labels(&label_vertices, &label_indices);
+        }
+        self.profiler.record(Phase::Upload, start);
+    }
+
+    fn update_player(&mut self, delta_time: f32) {
+        if boat::ridden(self.session.world()).is_some() {
+            self.paddle(delta_time);
+            return;
+        }
+        let player = &mut self.player;
+        let prev_position = player.position;
+        let was_on_ground = player.on_ground;
+        if self.free_cam.is_none() {
+            self.input_handler.update_player(player, &self.camera, delta_time);
+        } else {
+            // The body stands still while the spectator camera flies
+            player.velocity.x = 0.0;
+            player.velocity.z = 0.0;
+            player.sneaking = false;
+            player.sprinting = false;
+        }
+        // Only a jump leaves the ground before physics runs
+        let jumped = was_on_ground && !player.on_ground;
+        let fall_speed = -player.velocity.y;
+        player.apply_physics(delta_time, self.session.world());
+        if jumped {
+            self.audio.play(Sound::Jump);
+            self.player_stats.jumps += 1;
+        } else if !was_on_ground && player.on_ground {
+            self.audio.play(Sound::Land);
+            self.view_bob.land(fall_speed);
+        }
+
+        let moved_by = player.position - prev_position;
+        let walked = glam::Vec2::new(moved_by.x, moved_by.z).length();
+        let underfoot = if player.on_ground { player.block_underfoot(self.session.world()) } else { None };
+        if let Some(step) = self.footsteps.update(walked, underfoot, player.sneaking) {
+            self.audio.play(step);
+        }
+        let walking = underfoot.is_some() && walked > 0.0;
+        if walking {
+            self.player_stats.distance_walked += walked as f64;
+        }
+        self.view_bob.step(walking.then(|| self.footsteps.walk_cycle()));
+        self.tick_distance += moved_by.length();
+    }
+
+    /// The client's part of a server tick: survival stats
+    fn tick(&mut self) {
+        let moved = std::mem::take(&mut self.tick_distance);
+        // A death earlier in this frame already switched to the death screen
+        if !self.states.current().simulates_player() || self.player.game_mode != GameMode::Survival {
+            return;
+        }
+        let submerged = self.player.is_head_submerged(self.session.world());
+        let health = self.player.stats.health;
+        self.player.stats.update(TICK_LENGTH, moved, submerged);
+        self.after_damage(health);
+    }
+
+    /// Another player hit this one
+    fn hurt(&mut self, amount: u32, knockback: glam::Vec3) {
+        if !self.states.current().simulates_player() || self.player.game_mode != GameMode::Survival {
+            return;
+        }
+        let health = self.player.stats.health;
+        self.player.stats.damage(amount);
+        self.player.velocity += knockback;
+        self.player.on_ground = false;
+        self.after_damage(health);
+    }
+
+    /// Sound for health lost since it was `health`, and the death screen if
+    /// that was the last of it
+    fn after_damage(&mut self, health: u32) {
+        if self.player.stats.health < health {
+            self.audio.on_event(&GameEvent::PlayerDamaged {
+                amount: health - self.player.stats.health,
+                health: self.player.stats.health,
+            });
+        }
+
+        if self.player.stats.is_dead() {
+            log::info!("You died!");
+            self.player_stats.deaths += 1;
+            if !self.session.world().rules.keep_inventory() {
+                self.drop_inventory();
+            }
+            // Close whatever screen was open and wait on the death screen
+            while self.states.current() != GameState::Playing && self.pop_state() {}
+            self.push_state(GameState::Dead);
+        }
+    }
+
+    fn render(&mut self) {
+        #[cfg(feature = "debug-ui")]
+        let result = {
+            let (world, generator) = self.session.dev_parts();
+            let action = self.dev_tools.run(&self.window, crate::dev_tools::DevContext {
+                world,
+                player: &mut self.player,
+                generator,
+                chunk_cache: self.renderer.chunk_cache_stats(),
+                profiler: &self.profiler,
+            });
+            if let crate::dev_tools::DevAction::RegenerateTerrain(params) = action {
+                // Start over with fresh chunks; they stream back in like on startup
+                if let Session::Local(server) = &mut self.session {
+                    server.regenerate(crate::world_gen::WorldGenerator::with_params(server.world.seed, params));
+                    self.renderer.clear_chunk_cache();
+                    self.minimap = Minimap::new();
+                    self.world_needs_update = true;
+                } else {
+                    self.toasts.push("Terrain is generated by the server");
+                }
+            }
+            let size = self.renderer.size;
+            let dev_tools = &mut self.dev_tools;
+            self.renderer.render_with(&mut self.profiler, |device, queue, encoder, view| {
+                dev_tools.paint(device, queue, encoder, view, size)
+            })
+        };
+        #[cfg(not(feature = "debug-ui"))]
+        let result = self.renderer.render(&mut self.profiler);
+
+        match result {
+            Ok(_) => {}
+            Err(wgpu::SurfaceError::Lost) => self.renderer.resize(self.renderer.size),
+            Err(wgpu::SurfaceError::OutOfMemory) => self.exit = true,
+            Err(e) => log::warn!("Surface error: {:?}", e),
+        }
+    }
+
+    fn report_fps(&mut self, now: Instant) {
+        self.frame_count += 1;
+        if now.duration_since(self.last_fps_update).as_secs() < 1 {
+            return;
+        }
+        let player = &self.player;
+        self.debug_info.update(player, self.frame_count, &self.camera, self.session.world());
+        self.debug_info.mesh_memory = self.renderer.mesh_memory();
+        self.crash.set_player(PlayerState::new(player, &self.session.world().inventory));
+        self.crash.set_frame_timings(self.profiler.format_display());
+
+        if self.config.show_debug {
+            let debug_lines = self.debug_info.format_display();
+            let layers = self.debug_layers.format_display();
+            for line in debug_lines.into_iter().chain(self.profiler.format_display()).chain([layers]) {
+                log::info!("{}", line);
+            }
+            log::info!("---");
+        } else {
+            log::info!(
+                "FPS: {} | Pos: ({:.1}, {:.1}, {:.1}) | Vel: ({:.1}, {:.1}, {:.1}) | Ground: {}",
+                self.frame_count, player.position.x, player.position.y, player.position.z,
+                player.velocity.x, player.velocity.y, player.velocity.z,
+                player.on_ground
+            );
+        }
+        self.frame_count = 0;
+        self.last_fps_update = now;
+    }
+
+    /// Recompute the UI virtual screen for the window size and GUI scale, then
+    /// rebuild every mesh anchored to the screen edges
+    fn rescale_ui(&mut self) {
+        let scale = UiScale::new(self.renderer.size.width, self.renderer.size.height, self.config.gui_scale);
+        self.ui_renderer.set_scale(scale);
+        self.renderer.set_ui_scale(&scale);
+        self.ui_renderer.build_toolbar(&self.session.world().inventory);
+        self.ui_renderer.build_hud(&self.player.stats, &self.session.world().experience, self.player.game_mode);
+        self.ui_renderer.build_console(&self.console);
+        self.ui_renderer.build_menu(&self.menu, &self.config, &self.player_stats);
+        self.renderer.update_ui(&self.ui_renderer);
+        // The compass dial follows on the next frame
+        self.last_compass = None;
+    }
+
+    /// Write the config, and the world if it was entered, then stop the game
+    fn exit(&mut self, save_world: bool) {
+        if let (true, Session::Local(server)) = (save_world, &mut self.session) {
+            log::info!("Saving world...");
+            self.connection.send(ClientMessage::Save);
+            server.update(0.0, false);
+            let saved = self.connection.receive().into_iter().find_map(|message| match message {
+                ServerMessage::Saved(result) => Some(result),
+                _ => None,
+            });
+            match saved {
+                Some(Ok(())) => log::info!("World saved successfully!"),
+                Some(Err(e)) => log::error!("Failed to save world: {}", e),
+                None => log::error!("Failed to save world: no answer from the server"),
+            }
+        }
+        if let (Some(path), Some(trace)) = (&self.trace_path, self.profiler.finish_trace()) {
+            match std::fs::write(path, trace) {
+                Ok(()) => log::info!("Frame trace written to {}", path),
+                Err(e) => log::error!("Failed to write frame trace: {}", e),
+            }
+        }
+        if let Err(e) = self.player_stats.save(&*self.storage, &self.stats_path) {
+            log::error!("Failed to save statistics: {}", e);
+        }
+        log::info!("Saving config...");
+        if let Err(e) = self.config.save(&*self.storage, &self.args.config) {
+            log::error!("Failed to save config: {}", e);
+        } else {
+            log::info!("Config saved successfully!");
+        }
+        self.exit = true;
+    }
+}
+
+/// Rebuild the inventory screen, including the open chest or furnace
+/// Texture atlas of the named resource pack over the built-in textures. A pack
+/// that fails to load is reported and skipped.
+fn load_atlas(builtin: &ResourcePack, pack_name: Option<&str>, toasts: &mut Toasts) -> TextureAtlas {
+    let Some(pack_name) = pack_name else {
+        return resource_pack::build_atlas(&[builtin]);
+    };
+    match ResourcePack::load(&std::path::Path::new(RESOURCE_PACKS_DIR).join(pack_name)) {
+        Ok(pack) => resource_pack::build_atlas(&[&pack, builtin]),
+        Err(e) => {
+            log::error!("Failed to load resource pack {}: {}", pack_name, e);
+            toasts.push(format!("Failed to load resource pack {}", pack_name));
+            resource_pack::build_atlas(&[builtin])
+        }
+    }
+}
+
+/// The skin PNG at `path`, decoded and as sent to the server
+fn load_skin(storage: &dyn Storage, path: &str) -> Result<(Skin, Vec<u8>), String> {
+    let data = storage.read(Path::new(path)).map_err(|e| e.to_string())?;
+    if data.len() > MAX_SKIN_FILE_SIZE {
+        return Err(format!("larger than {} bytes", MAX_SKIN_FILE_SIZE));
+    }
+    Ok((Skin::decode(&data)?, data))
+}
+
+fn build_inventory_screen(ui_renderer: &mut UiRenderer, world: &World) {
+    let container = ui_renderer.container_pos().and_then(|pos| world.block_entity(pos));
+    ui_renderer.build_inventory(&world.inventory, container, &world.experience);
+}
+
+/// Apply a click on an inventory screen slot, moving items between the player's
+/// inventory and the open container
+fn click_screen_slot(world: &mut World, container_pos: Option<(i32, i32, i32)>, slot: SlotId, click: SlotClick) {
+    let container = container_pos.and_then(|pos| world.block_entities.get_mut(&pos));
+    match (slot, click, container) {
+        (SlotId::Container(idx), _, Some(container)) => container.click_slot(idx, click, &mut world.inventory),
+        (SlotId::Container(_), _, None) => {}
+        (id, SlotClick::ShiftLeft, Some(container)) => container.quick_move_from(&mut world.inventory, id),
+        (id, SlotClick::ShiftLeft, None) => world.inventory.quick_move(id),
+        (id, SlotClick::Left, _) => world.inventory.click_slot(id),
+        (id, SlotClick::Right, _) => world.inventory.right_click_slot(id),
+    }
+}
+
+/// The values the HUD displays: health, hunger, air, game mode and experience
+fn hud_state(player: &Player, experience: &Experience) -> (u32, u32, u32, GameMode, Experience) {
+    (player.stats.health, player.stats.hunger, player.stats.air, player.game_mode, *experience)
+}
@@ -0,0 +1,79 @@
+/// Top-level screens of the game, kept on a `StateStack` so an overlay (pause
+/// menu, inventory, console) returns to whatever state it was opened from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameState {
+    /// Title screen shown at startup
+    MainMenu,
+    /// Spawn area generating in the background
+    Loading,
+    /// In the world with FPS controls
+    Playing,
+    /// Pause menu (and its Options screen); the world is frozen
+    Paused,
+    /// Inventory, chest or furnace screen
+    Inventory,
+    /// Chat / command line
+    Console,
+    /// Death screen waiting for respawn
+    Dead,
+    /// In bed, waiting for the night to be skipped
+    Sleeping,
+}
+
+impl GameState {
+    /// Whether the world keeps running (time, furnaces, survival) in this state
+    pub fn simulates_world(self) -> bool {
+        matches!(
+            self,
+            GameState::Playing | GameState::Inventory | GameState::Console | GameState::Dead | GameState::Sleeping
+        )
+    }
+
+    /// Whether the player moves and is affected by physics in this state
+    pub fn simulates_player(self) -> bool {
+        matches!(self, GameState::Playing | GameState::Inventory | GameState::Console)
+    }
+
+    /// Whether the cursor is free for the UI instead of grabbed for mouse look
+    pub fn gui_mode(self) -> bool {
+        self != GameState::Playing
+    }
+
+    /// Whether the world, HUD and toolbar are drawn behind the state's screen
+    pub fn shows_world(self) -> bool {
+        !matches!(self, GameState::MainMenu | GameState::Loading)
+    }
+}
+
+/// Stack of game states; the top one receives input. Never empty.
+pub struct StateStack {
+    states: Vec<GameState>,
+}
+
+impl StateStack {
+    pub fn new(initial: GameState) -> Self {
+        Self { states: vec![initial] }
+    }
+
+    pub fn current(&self) -> GameState {
+        *self.states.last().expect("state stack is never empty")
+    }
+
+    pub fn push(&mut self, state: GameState) {
+        self.states.push(state);
+    }
+
+    /// Remove the top state, returning it; the bottom state is never popped
+    pub fn pop(&mut self) -> Option<GameState> {
+        if self.states.len() > 1 {
+            self.states.pop()
+        } else {
+            None
+        }
+    }
+
+    /// Replace the top state
+    pub fn replace(&mut self, state: GameState) -> GameState {
+        std::mem::replace(self.states.last_mut().expect("state stack is never empty"), state)
+    }
+}
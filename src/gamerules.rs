@@ -0,0 +1,131 @@
+//! Game rules: settings of a world changed with `/gamerule <name> [value]`
+//! and saved with it. They are kept as a map from name to value, so saves
+//! stay readable as rules come and go: a rule missing from a save takes its
+//! default.
+//!
+//! - `doDaytimeCycle`: whether the clock runs (default true)
+//! - `dayLengthSeconds`: real seconds a whole day and night take (default 1200)
+//! - `keepInventory`: whether players keep their items when they die rather
+//!   than dropping them (default false)
+//! - `doMobSpawning`: whether mobs spawn (default true); there are no mobs yet
+//! - `playersSleepingPercentage`: share of the players online, in percent,
+//!   that have to be in bed to skip the night (default 100); see `sleep`
+
+use crate::time::{TICKS_PER_DAY, TICKS_PER_SECOND};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Length of a day at the normal clock speed, in seconds
+pub const DEFAULT_DAY_LENGTH: u32 = (TICKS_PER_DAY as f32 / TICKS_PER_SECOND) as u32;
+/// Longest day `dayLengthSeconds` allows, a real day
+const MAX_DAY_LENGTH: u32 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleValue {
+    Bool(bool),
+    Int(u32),
+}
+
+impl fmt::Display for RuleValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuleValue::Bool(value) => write!(f, "{}", value),
+            RuleValue::Int(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Every rule with its default, which also tells the kind of value it takes
+const RULES: [(&str, RuleValue); 5] = [
+    ("doDaytimeCycle", RuleValue::Bool(true)),
+    ("dayLengthSeconds", RuleValue::Int(DEFAULT_DAY_LENGTH)),
+    ("keepInventory", RuleValue::Bool(false)),
+    ("doMobSpawning", RuleValue::Bool(true)),
+    ("playersSleepingPercentage", RuleValue::Int(100)),
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GameRules {
+    /// Rules set to something, by name; the others are at their default
+    values: BTreeMap<String, RuleValue>,
+}
+
+impl GameRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Names of all rules
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        RULES.into_iter().map(|(name, _)| name)
+    }
+
+    /// The rule's name as written in `RULES` and its default, for any case
+    fn rule(name: &str) -> Option<(&'static str, RuleValue)> {
+        RULES.into_iter().find(|(rule, _)| rule.eq_ignore_ascii_case(name))
+    }
+
+    /// Value of the rule called `name`, None if there is no such rule
+    pub fn get(&self, name: &str) -> Option<RuleValue> {
+        let (name, default) = Self::rule(name)?;
+        Some(self.values.get(name).copied().unwrap_or(default))
+    }
+
+    /// Set the rule called `name` from the text of its value. Returns the
+    /// rule's name and the value it now has.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(&'static str, RuleValue), String> {
+        let (name, default) = Self::rule(name).ok_or_else(|| format!("Unknown game rule: {}", name))?;
+        let value = match default {
+            RuleValue::Bool(_) => match value {
+                "true" => RuleValue::Bool(true),
+                "false" => RuleValue::Bool(false),
+                _ => return Err(format!("{} is true or false", name)),
+            },
+            RuleValue::Int(_) => match value.parse() {
+                Ok(seconds) if name == "dayLengthSeconds" && !(1..=MAX_DAY_LENGTH).contains(&seconds) => {
+                    return Err(format!("{} is from 1 to {}", name, MAX_DAY_LENGTH));
+                }
+                Ok(percentage) if name == "playersSleepingPercentage" && percentage > 100 => {
+                    return Err(format!("{} is from 0 to 100", name));
+                }
+                Ok(number) => RuleValue::Int(number),
+                Err(_) => return Err(format!("{} is a whole number", name)),
+            },
+        };
+        if value == default {
+            self.values.remove(name);
+        } else {
+            self.values.insert(name.to_string(), value);
+        }
+        Ok((name, value))
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.get(name) == Some(RuleValue::Bool(true))
+    }
+
+    pub fn do_daytime_cycle(&self) -> bool {
+        self.flag("doDaytimeCycle")
+    }
+
+    pub fn keep_inventory(&self) -> bool {
+        self.flag("keepInventory")
+    }
+
+    /// Real seconds a whole day takes, at least 1
+    pub fn day_length_seconds(&self) -> u32 {
+        match self.get("dayLengthSeconds") {
+            Some(RuleValue::Int(seconds)) => seconds.max(1),
+            _ => DEFAULT_DAY_LENGTH,
+        }
+    }
+
+    /// Percentage of the players online that have to sleep to skip the night
+    pub fn players_sleeping_percentage(&self) -> u32 {
+        match self.get("playersSleepingPercentage") {
+            Some(RuleValue::Int(percentage)) => percentage.min(100),
+            _ => 100,
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! The dedicated server (`rustcraft-server` or `rustcraft --headless`): the
+//! world simulation without a window or GPU. It generates the area around
+//! spawn, accepts LAN players on `--port`, then ticks the world and runs
+//! console commands read from stdin until `stop` or the end of input.
+
+use crate::chunk_loader::FrameBudget;
+use crate::cli::Args;
+use crate::crash::CrashGuard;
+use crate::server::Server;
+use crate::storage::Storage;
+use std::io::BufRead;
+use std::sync::mpsc::{self, TryRecvError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Sleep between polls of the tick clock and stdin
+const IDLE_SLEEP: Duration = Duration::from_millis(5);
+
+pub fn run(args: &Args, storage: Arc<dyn Storage>, crash: &CrashGuard) {
+    let config = args.load_config(&*storage);
+    let (mut server, _) = Server::open(args, storage, config.view_distance, config.simulation_distance);
+    server.watch_for_crash(crash);
+
+    let started = Instant::now();
+    server.generate_spawn_area();
+    log::info!(
+        "Generated {} chunks in {:.2}s",
+        server.world.chunks.len(),
+        started.elapsed().as_secs_f32()
+    );
+    if let Err(e) = server.listen(args.port) {
+        log::error!("Could not listen on port {}, running without players: {}", args.port, e);
+    }
+
+    // Read stdin on its own thread so a quiet console doesn't stall the ticks;
+    // the channel disconnects at the end of input
+    let (lines, input) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+            if lines.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut ticks = 0u32;
+    let mut tick_time = Duration::ZERO;
+    let mut last_frame = Instant::now();
+    log::info!("Running; type a command or 'stop' to save and exit");
+
+    loop {
+        match input.try_recv() {
+            Ok(line) if !line.trim().is_empty() => match server.execute_command(&line) {
+                Ok(msg) => log::info!("{}", msg),
+                Err(err) => log::warn!("{}", err),
+            },
+            Ok(_) | Err(TryRecvError::Empty) => {}
+            Err(TryRecvError::Disconnected) => break,
+        }
+        if server.is_stopping() {
+            break;
+        }
+
+        let now = Instant::now();
+        let delta_time = now.duration_since(last_frame).as_secs_f32();
+        last_frame = now;
+        // Without frames to keep smooth, take every chunk that is ready
+        server.load_chunks(FrameBudget::UNLIMITED);
+        let ran = server.update(delta_time, true);
+        if ran > 0 {
+            tick_time += now.elapsed();
+            ticks += ran;
+        }
+        std::thread::sleep(IDLE_SLEEP);
+    }
+
+    if ticks > 0 {
+        log::info!(
+            "Ran {} ticks, {:.3} ms per tick on average",
+            ticks,
+            tick_time.as_secs_f64() * 1000.0 / ticks as f64
+        );
+    }
+    log::info!("Saving world...");
+    match server.save() {
+        Ok(()) => log::info!("World saved successfully!"),
+        Err(e) => log::error!("Failed to save world: {}", e),
+    }
+}
@@ -0,0 +1,60 @@
+// Built-in 9x9 pixel-art HUD icons, drawn as UI quads like the bitmap font.
+// Rows are listed top to bottom: 'o' is the outline, '#' the fill, '+' a highlight,
+// '.' transparent.
+
+pub const ICON_SIZE: usize = 9;
+
+pub type Icon = [&'static str; ICON_SIZE];
+
+pub const HEART: Icon = [
+    ".oo...oo.",
+    "o++o.o##o",
+    "o+###o##o",
+    "o#######o",
+    ".o#####o.",
+    "..o###o..",
+    "...o#o...",
+    "....o....",
+    ".........",
+];
+
+pub const HUNGER: Icon = [
+    ".....ooo.",
+    "....o###o",
+    "...o#+##o",
+    "...o####o",
+    "..oo###o.",
+    ".o#oooo..",
+    "o+o......",
+    "oo.......",
+    ".........",
+];
+
+pub const BUBBLE: Icon = [
+    "..ooooo..",
+    ".o.....o.",
+    "o.++....o",
+    "o.+.....o",
+    "o.......o",
+    "o.......o",
+    ".o.....o.",
+    "..ooooo..",
+    ".........",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconPixel {
+    Outline,
+    Fill,
+    Highlight,
+}
+
+/// Pixel at (col, row) of an icon, row 0 being the top; None if transparent
+pub fn icon_pixel(icon: &Icon, col: usize, row: usize) -> Option<IconPixel> {
+    match icon.get(row)?.as_bytes().get(col)? {
+        b'o' => Some(IconPixel::Outline),
+        b'#' => Some(IconPixel::Fill),
+        b'+' => Some(IconPixel::Highlight),
+        _ => None,
+    }
+}
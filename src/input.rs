@@ -1,19 +1,76 @@
+use crate::boat::Boat;
 use crate::camera::Camera;
-use crate::physics::Player;
-use crate::raycast::raycast;
+use crate::chunk::CHUNK_HEIGHT;
+use crate::config::GameConfig;
+use crate::events::GameEvent;
+use crate::inventory::{Hand, ItemStack};
+use crate::item::Item;
+use crate::mining::{self, Mining};
+use crate::scripting::{BlockHook, BlockScripts};
+use crate::physics::{Aabb, GameMode, Player};
+use crate::raycast::{raycast, HitTarget, RaycastFilter, RaycastHit};
+use crate::ui::UiScale;
 use crate::world::World;
 use crate::block::BlockType;
 use std::collections::HashSet;
 use winit::event::*;
 use winit::keyboard::{KeyCode, PhysicalKey};
+use winit::window::{CursorGrabMode, Window};
+
+/// How far away blocks can be broken and placed
+pub const REACH: f32 = 5.0;
+
+/// Outcome of a mouse click on the world
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Interaction {
+    /// The block under the player's feet was removed
+    pub removed_under_feet: bool,
+    /// Right click on a chest or furnace at this position
+    pub opened_container: Option<(i32, i32, i32)>,
+    /// Right click on a lever, door, button or block with an `on_interact` script
+    pub used_block: Option<((i32, i32, i32), BlockType)>,
+    /// A broken block (or container contents) didn't fit into the inventory
+    pub inventory_full: bool,
+    /// A block was broken or placed
+    pub changed_block: bool,
+    /// Left click on the entity with this id
+    pub attacked: Option<u32>,
+    /// Right click on the boat with this index in `World::boats`
+    pub boarded: Option<usize>,
+    /// Right click with a fishing rod, to cast or reel in
+    pub used_rod: bool,
+    /// Right click on the villager with this index in `World::villagers`
+    pub trading: Option<usize>,
+    /// Right click on a bed at this position, to sleep in it
+    pub bed: Option<(i32, i32, i32)>,
+    /// The tool in hand wore out breaking a block
+    pub tool_broke: bool,
+}
 
 pub struct InputHandler {
     keys_pressed: HashSet<KeyCode>,
     pub mouse_delta: (f64, f64),
     pub left_mouse_pressed: bool,
+    /// Left button held down, breaking the block under the crosshair
+    pub left_mouse_held: bool,
     pub right_mouse_pressed: bool,
+    pub mining: Mining,
     sensitivity: f32,
+    sensitivity_x_scale: f32,
+    sensitivity_y_scale: f32,
+    invert_y: bool,
+    mouse_acceleration: f32,
+    /// Use DeviceEvent motion; otherwise deltas come from cursor movement
+    raw_input: bool,
     walk_speed: f32,
+    /// True while a screen (inventory, chest, menu) owns the mouse
+    gui_mode: bool,
+    /// Whether the window has keyboard focus; raw mouse motion keeps coming
+    /// without it and is ignored
+    focused: bool,
+    /// Last known cursor position in physical window pixels
+    cursor_position: (f64, f64),
+    modifiers: winit::keyboard::ModifiersState,
 }
 
 impl InputHandler {
@@ -22,21 +79,120 @@ impl InputHandler {
             keys_pressed: HashSet::new(),
             mouse_delta: (0.0, 0.0),
             left_mouse_pressed: false,
+            left_mouse_held: false,
             right_mouse_pressed: false,
+            mining: Mining::default(),
             sensitivity: 0.005,
+            sensitivity_x_scale: 1.0,
+            sensitivity_y_scale: 1.0,
+            invert_y: false,
+            mouse_acceleration: 0.0,
+            raw_input: true,
             walk_speed: 4.3,
+            gui_mode: false,
+            focused: true,
+            cursor_position: (0.0, 0.0),
+            modifiers: winit::keyboard::ModifiersState::empty(),
+        }
+    }
+
+    pub fn is_gui_mode(&self) -> bool {
+        self.gui_mode
+    }
+
+    /// Switch between FPS controls (cursor grabbed, mouse look) and GUI mode
+    /// (cursor free and visible, clicks go to the UI).
+    pub fn set_gui_mode(&mut self, window: &Window, enabled: bool) {
+        self.gui_mode = enabled;
+        self.mouse_delta = (0.0, 0.0);
+        self.left_mouse_pressed = false;
+        self.left_mouse_held = false;
+        self.right_mouse_pressed = false;
+        self.mining.reset();
+        // Drop held movement keys so the player doesn't keep walking behind the screen
+        self.keys_pressed.clear();
+        set_cursor_grabbed(window, !enabled);
+    }
+
+    /// The window gained or lost focus. Anything held or moved in the
+    /// meantime went to another window, so it is dropped.
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+        self.mouse_delta = (0.0, 0.0);
+        self.left_mouse_pressed = false;
+        self.left_mouse_held = false;
+        self.right_mouse_pressed = false;
+        self.mining.reset();
+        self.keys_pressed.clear();
+    }
+
+    pub fn process_modifiers(&mut self, modifiers: winit::keyboard::ModifiersState) {
+        self.modifiers = modifiers;
+    }
+
+    /// Whether Shift is held (tracked even in GUI mode, for shift-click)
+    pub fn is_shift_held(&self) -> bool {
+        self.modifiers.shift_key()
+    }
+
+    /// Whether Ctrl is held
+    pub fn is_ctrl_held(&self) -> bool {
+        self.modifiers.control_key()
+    }
+
+    /// Whether the zoom key (C) is held
+    pub fn is_zoom_held(&self) -> bool {
+        self.keys_pressed.contains(&KeyCode::KeyC)
+    }
+
+    pub fn process_cursor_moved(&mut self, position: winit::dpi::PhysicalPosition<f64>) {
+        // Without raw input, mouse look follows the (OS-accelerated) cursor movement
+        if !self.raw_input && !self.gui_mode {
+            self.mouse_delta.0 += position.x - self.cursor_position.0;
+            self.mouse_delta.1 += position.y - self.cursor_position.1;
         }
+        self.cursor_position = (position.x, position.y);
     }
 
-    pub fn set_sensitivity(&mut self, sensitivity: f32) {
-        self.sensitivity = sensitivity;
+    /// Warp the cursor back to the window center so cursor-based look never hits the edge
+    pub fn recenter_cursor(&mut self, window: &Window) {
+        if self.raw_input || self.gui_mode {
+            return;
+        }
+        let size = window.inner_size();
+        let center = winit::dpi::PhysicalPosition::new(size.width as f64 / 2.0, size.height as f64 / 2.0);
+        if window.set_cursor_position(center).is_ok() {
+            // The warp itself must not count as movement
+            self.cursor_position = (center.x, center.y);
+        }
     }
 
-    pub fn set_walk_speed(&mut self, speed: f32) {
-        self.walk_speed = speed;
+    /// Cursor position converted to normalized device coordinates (-1..1, +Y up),
+    /// the same space the UI geometry is built in.
+    pub fn cursor_ndc(&self, window_size: winit::dpi::PhysicalSize<u32>) -> (f32, f32) {
+        screen_to_ndc(self.cursor_position, window_size)
+    }
+
+    /// Cursor position in UI virtual pixels
+    pub fn cursor_ui(&self, window_size: winit::dpi::PhysicalSize<u32>, scale: &UiScale) -> (f32, f32) {
+        scale.ndc_to_ui(self.cursor_ndc(window_size))
+    }
+
+    /// Apply all mouse and movement settings from the config
+    pub fn apply_config(&mut self, config: &GameConfig) {
+        self.sensitivity = config.sensitivity;
+        self.sensitivity_x_scale = config.sensitivity_x_scale;
+        self.sensitivity_y_scale = config.sensitivity_y_scale;
+        self.invert_y = config.invert_y;
+        self.mouse_acceleration = config.mouse_acceleration.max(0.0);
+        self.raw_input = config.raw_input;
+        self.walk_speed = config.walk_speed;
     }
 
     pub fn process_keyboard(&mut self, event: &KeyEvent) {
+        if self.gui_mode {
+            return;
+        }
         if let PhysicalKey::Code(keycode) = event.physical_key {
             match event.state {
                 ElementState::Pressed => {
@@ -50,13 +206,25 @@ impl InputHandler {
     }
 
     pub fn process_mouse_motion(&mut self, delta: (f64, f64)) {
-        self.mouse_delta = delta;
+        if self.gui_mode || !self.raw_input || !self.focused {
+            return;
+        }
+        // Several motion events can arrive per frame; accumulate them
+        self.mouse_delta.0 += delta.0;
+        self.mouse_delta.1 += delta.1;
     }
 
     pub fn process_mouse_button(&mut self, state: ElementState, button: MouseButton) {
+        if self.gui_mode {
+            return;
+        }
         match button {
             MouseButton::Left => {
                 self.left_mouse_pressed = state == ElementState::Pressed;
+                self.left_mouse_held = state == ElementState::Pressed;
+                if state == ElementState::Released {
+                    self.mining.reset();
+                }
             }
             MouseButton::Right => {
                 self.right_mouse_pressed = state == ElementState::Pressed;
@@ -66,14 +234,29 @@ impl InputHandler {
     }
 
     pub fn update_camera(&mut self, camera: &mut Camera) {
-        // Mouse look (no button hold required now)
-        camera.yaw += self.mouse_delta.0 as f32 * self.sensitivity;
-        camera.pitch -= self.mouse_delta.1 as f32 * self.sensitivity;
+        if self.gui_mode {
+            self.mouse_delta = (0.0, 0.0);
+            return;
+        }
+
+        // Mouse look (no button hold required now), slowed down while zoomed in
+        let (dx, dy) = self.look_delta();
+        let sensitivity = self.sensitivity * camera.look_sensitivity_scale();
+        let y_sign = if self.invert_y { -1.0 } else { 1.0 };
+        camera.yaw += dx * sensitivity * self.sensitivity_x_scale;
+        camera.pitch -= dy * sensitivity * self.sensitivity_y_scale * y_sign;
         camera.pitch = camera.pitch.clamp(-1.5, 1.5);
 
         self.mouse_delta = (0.0, 0.0);
     }
 
+    /// This frame's mouse delta with the acceleration curve applied
+    pub fn look_delta(&self) -> (f32, f32) {
+        let (dx, dy) = (self.mouse_delta.0 as f32, self.mouse_delta.1 as f32);
+        let factor = acceleration_factor((dx * dx + dy * dy).sqrt(), self.mouse_acceleration);
+        (dx * factor, dy * factor)
+    }
+
     pub fn update_player(&mut self, player: &mut Player, camera: &Camera, _delta_time: f32) {
         let mut movement = glam::Vec3::ZERO;
 
@@ -91,9 +274,24 @@ impl InputHandler {
             movement += camera.get_right();
         }
 
+        // Holding Shift sneaks: slower and with quiet footsteps. Holding Ctrl
+        // while walking forward sprints, unless too hungry.
+        player.sneaking = self.is_shift_held();
+        player.sprinting = self.keys_pressed.contains(&KeyCode::ControlLeft)
+            && self.keys_pressed.contains(&KeyCode::KeyW)
+            && !player.sneaking
+            && player.stats.hunger >= SPRINT_MIN_HUNGER;
+        let speed = if player.sneaking {
+            self.walk_speed * SNEAK_SPEED_FACTOR
+        } else if player.sprinting {
+            self.walk_speed * SPRINT_SPEED_FACTOR
+        } else {
+            self.walk_speed
+        };
+
         // Normalize horizontal movement to prevent faster diagonal movement
         if movement.length_squared() > 0.0 {
-            movement = movement.normalize() * self.walk_speed;
+            movement = movement.normalize() * speed;
         }
 
         player.velocity.x = movement.x;
@@ -105,35 +303,124 @@ impl InputHandler {
         }
     }
 
-    pub fn handle_block_interaction(&mut self, camera: &Camera, world: &mut World, _ui: &crate::ui::UiRenderer, player_pos: glam::Vec3) -> (bool, bool) {
-        let mut world_changed = false;
+    /// Flying direction of the spectator camera: W/A/S/D along the view,
+    /// Space up and Shift down. Not normalized.
+    pub fn fly_direction(&self, camera: &Camera) -> glam::Vec3 {
+        let mut direction = glam::Vec3::ZERO;
+        for (key, towards) in [
+            (KeyCode::KeyW, camera.get_direction()),
+            (KeyCode::KeyS, -camera.get_direction()),
+            (KeyCode::KeyA, -camera.get_right()),
+            (KeyCode::KeyD, camera.get_right()),
+            (KeyCode::Space, glam::Vec3::Y),
+        ] {
+            if self.keys_pressed.contains(&key) {
+                direction += towards;
+            }
+        }
+        if self.is_shift_held() {
+            direction -= glam::Vec3::Y;
+        }
+        direction
+    }
+
+    /// Paddling of a boat: W/S ahead and back and A/D turning left and
+    /// right, each in [-1, 1]
+    pub fn paddle(&self) -> (f32, f32) {
+        let axis = |positive, negative| self.keys_pressed.contains(&positive) as i32 as f32 - self.keys_pressed.contains(&negative) as i32 as f32;
+        (axis(KeyCode::KeyW, KeyCode::KeyS), axis(KeyCode::KeyD, KeyCode::KeyA))
+    }
+
+    /// Act on the block or entity under the crosshair for the clicks since the
+    /// last call, and keep breaking the block while the left button is held
+    /// for `delta_time` seconds. `entities` are the hitboxes of the other
+    /// players, which block the view of what is behind them.
+    #[allow(clippy::too_many_arguments)]
+    pub fn handle_block_interaction(
+        &mut self,
+        camera: &Camera,
+        world: &mut World,
+        _ui: &crate::ui::UiRenderer,
+        player: &Player,
+        scripts: &BlockScripts,
+        entities: &[(u32, Aabb)],
+        delta_time: f32,
+    ) -> Interaction {
+        let player_pos = player.position;
+        let creative = player.game_mode == GameMode::Creative;
         let mut removed_under_feet = false;
+        let mut opened_container = None;
+        let mut used_block = None;
+        let mut inventory_full = false;
+        let mut changed_block = false;
+        let mut attacked = None;
+        let mut boarded = None;
+        let mut used_rod = false;
+        let mut trading = None;
+        let mut bed = None;
+        let mut tool_broke = false;
+        // Water is looked through, to get at the blocks under it
+        let filter = RaycastFilter { entities, ..RaycastFilter::TARGET_BLOCKS };
+
+        // Left click - attack an entity; held - break the block and add it to the
+        // inventory, at once in creative mode
+        let clicked = std::mem::take(&mut self.left_mouse_pressed);
+        if clicked || self.left_mouse_held {
+            let hit = raycast(camera.position, camera.get_direction(), REACH, world, filter);
+            let boat = boat_under_crosshair(camera, world, hit);
+            // Each click, or looking away from the block, starts over
+            if clicked || boat.is_some() || hit.is_none_or(|hit| hit.block().is_none()) {
+                self.mining.reset();
+            }
+            // A click breaks a boat back into the item
+            if let Some(index) = boat.filter(|_| clicked) {
+                world.boats.remove(index);
+                if !creative {
+                    inventory_full |= !pick_up(world, Item::Boat, 1);
+                }
+            } else if let Some(hit) = hit.filter(|_| boat.is_none()) {
+                let (x, y, z) = hit.position;
+                let held = world.inventory.get_selected_item().copied();
+                if let HitTarget::Entity(id) = hit.target {
+                    if clicked {
+                        attacked = Some(id);
+                    }
+                } else if let Some(block_type) = world.get_block_at(x, y, z) {
+                    let broken = if creative { clicked } else { self.mining.advance((x, y, z), block_type, held, delta_time) };
+                    if block_type != BlockType::Air && broken {
+                        // A broken container's contents go to the player
+                        let contents: Vec<_> = world
+                            .block_entity((x, y, z))
+                            .map(|entity| entity.slots().iter().flatten().copied().collect())
+                            .unwrap_or_default();
+                        for stack in contents {
+                            inventory_full |= !pick_up_stack(world, stack);
+                        }
+
+                        let success = world.set_block_at(x, y, z, BlockType::Air);
+                        if success {
+                            // Add destroyed block to inventory (creative mode has no drops,
+                            // and some blocks need the right tool)
+                            let harvested = !creative && mining::can_harvest(block_type, held.map(|stack| stack.item));
+                            if let Some(drop) = block_type.drop().filter(|_| harvested) {
+                                inventory_full |= !pick_up(world, Item::Block(drop), 1);
+                            }
+                            // Tools wear on everything but what breaks at once
+                            if !creative && block_type.hardness() > 0.0 {
+                                tool_broke = world.inventory.wear_selected(wear_roll(world, (x, y, z)));
+                            }
+                            world.events.emit(GameEvent::BlockBroken { pos: (x, y, z), block: block_type });
+                            changed_block = true;
 
-        // Left click - destroy block and add to inventory
-        if self.left_mouse_pressed {
-            self.left_mouse_pressed = false; // Treat as single click
-            let result = raycast(camera.position, camera.get_direction(), 5.0, world);
-            if result.hit {
-                if let Some((x, y, z)) = result.position {
-                    // Get the block type before destroying it
-                    if let Some(block_type) = world.get_block_at(x, y, z) {
-                        if block_type != BlockType::Air {
-                            let success = world.set_block_at(x, y, z, BlockType::Air);
-                            if success {
-                                // Add destroyed block to inventory
-                                world.inventory.add_item(block_type, 1);
-                                world_changed = true;
-
-                                // Check whether the removed block was directly under the player's feet.
-                                // Player's feet world coordinate is player_pos.y, block occupies [y, y+1).
-                                let foot_block_x = player_pos.x.floor() as i32;
-                                let foot_block_z = player_pos.z.floor() as i32;
-                                let feet_floor_y = player_pos.y.floor() as i32;
-                                // block is directly under feet if it's the block whose top is at feet_floor_y
-                                // i.e. block y == feet_floor_y - 1 and x/z cell matches footprint.
-                                if x == foot_block_x && z == foot_block_z && y == feet_floor_y - 1 {
-                                    removed_under_feet = true;
-                                }
+                            // Check whether the removed block was directly under the player's feet.
+                            // Player's feet world coordinate is player_pos.y, block occupies [y, y+1).
+                            let foot_block_x = player_pos.x.floor() as i32;
+                            let foot_block_z = player_pos.z.floor() as i32;
+                            let feet_floor_y = player_pos.y.floor() as i32;
+                            // block is directly under feet if it's the block whose top is at feet_floor_y
+                            // i.e. block y == feet_floor_y - 1 and x/z cell matches footprint.
+                            if x == foot_block_x && z == foot_block_z && y == feet_floor_y - 1 {
+                                removed_under_feet = true;
                             }
                         }
                     }
@@ -141,33 +428,279 @@ impl InputHandler {
             }
         }
 
-        // Right click - place block from inventory
+        // Right click - trade with a villager, board a boat, open a container,
+        // go to bed, use a bucket, flint and steel, a boat or a fishing rod, or place
+        // block from inventory
         if self.right_mouse_pressed {
             self.right_mouse_pressed = false; // Treat as single click
-            
-            // Check if player has the selected block in inventory
-            if world.inventory.has_selected_item() {
-                let result = raycast(camera.position, camera.get_direction(), 5.0, world);
-                if result.hit {
-                    if let (Some((x, y, z)), Some((nx, ny, nz))) = (result.position, result.normal) {
-                        // Place block at the adjacent position
-                        let place_x = x + nx;
-                        let place_y = y + ny;
-                        let place_z = z + nz;
-                        
-                        // Get the block type from inventory
-                        if let Some(block_type) = world.inventory.get_selected_block() {
-                            if world.set_block_at(place_x, place_y, place_z, block_type) {
-                                // Remove one block from inventory
-                                world.inventory.remove_selected_item(1);
-                                world_changed = true;
+
+            // Placing while looking through water puts the block on the floor
+            // below; nothing is placed through an entity
+            let hit = raycast(camera.position, camera.get_direction(), REACH, world, filter);
+            // An empty main hand uses the off hand's item
+            let hand = world.inventory.active_hand();
+            let target = hit
+                .and_then(|hit| Some((hit.position, hit.block()?)))
+                .filter(|(_, block)| {
+                    block.is_container() || block.is_switch() || *block == BlockType::Bed || scripts.handles(*block, BlockHook::Interact)
+                });
+
+            // Shift still places blocks against a container or bed
+            if let Some(index) = villager_under_crosshair(camera, world, hit) {
+                trading = Some(index);
+            } else if let Some(index) = boat_under_crosshair(camera, world, hit) {
+                boarded = Some(index);
+            } else if let Some((pos, block)) = target.filter(|_| !self.is_shift_held()) {
+                if block.is_container() {
+                    opened_container = Some(pos);
+                } else if block == BlockType::Bed {
+                    bed = Some(pos);
+                } else {
+                    used_block = Some((pos, block));
+                }
+            } else if let Some(bucket @ (Item::Bucket | Item::WaterBucket)) = world.inventory.hand_item(hand) {
+                let (changed, full) = use_bucket(world, camera, hand, bucket, creative, entities);
+                changed_block |= changed;
+                inventory_full |= full;
+            } else if world.inventory.hand_item(hand) == Some(Item::FlintAndSteel) {
+                changed_block |= light_fire(world, hit);
+            } else if world.inventory.hand_item(hand) == Some(Item::Boat) {
+                place_boat(world, camera, hand, creative);
+            } else if world.inventory.hand_item(hand) == Some(Item::FishingRod) {
+                used_rod = true;
+            } else if world.inventory.hand_item(hand).is_some() {
+                // Check if player has the selected block in inventory
+                if let Some(hit) = hit.filter(|hit| hit.block().is_some()) {
+                    let ((x, y, z), (nx, ny, nz)) = (hit.position, hit.normal);
+                    // Place block at the adjacent position, against the face the ray hit
+                    let place_x = x + nx;
+                    let place_y = y + ny;
+                    let place_z = z + nz;
+
+                    // Get the block type from inventory; other items aren't placed, nor
+                    // blocks without the support they need, and solid blocks not where
+                    // they would trap the player, another one or any other entity
+                    let cell = Aabb::block((place_x, place_y, place_z));
+                    let blocked = |block: BlockType| {
+                        let traps = block.is_full_cube()
+                            && (player.bounding_box.intersects(&cell)
+                                || entities.iter().any(|(_, aabb)| aabb.intersects(&cell))
+                                || world.entity_in(&cell));
+                        traps || !world.can_place((place_x, place_y, place_z), block)
+                    };
+                    if let Some(block_type) = world.inventory.hand_item(hand).and_then(|item| item.block()).filter(|&block| !blocked(block)) {
+                        if world.set_block_at(place_x, place_y, place_z, block_type) {
+                            // Remove one block from inventory (creative mode never runs out)
+                            if !creative {
+                                world.inventory.remove_hand_item(hand, 1);
                             }
+                            world.events.emit(GameEvent::BlockPlaced {
+                                pos: (place_x, place_y, place_z),
+                                block: block_type,
+                            });
+                            changed_block = true;
                         }
                     }
                 }
             }
         }
 
-        (world_changed, removed_under_feet)
+        Interaction {
+            removed_under_feet,
+            opened_container,
+            used_block,
+            inventory_full,
+            changed_block,
+            attacked,
+            boarded,
+            used_rod,
+            trading,
+            bed,
+            tool_broke,
+        }
+    }
+}
+
+/// Index in `World::boats` of the boat under the crosshair, unless `hit`,
+/// what the ray stops at otherwise, comes first. The one being ridden, which
+/// the camera is inside, doesn't count.
+fn boat_under_crosshair(camera: &Camera, world: &World, hit: Option<RaycastHit>) -> Option<usize> {
+    let boats: Vec<(u32, Aabb)> =
+        world.boats.iter().enumerate().filter(|(_, boat)| !boat.ridden).map(|(i, boat)| (i as u32, boat.bounding_box())).collect();
+    box_under_crosshair(camera, world, &boats, hit)
+}
+
+/// Index in `World::villagers` of the villager under the crosshair, unless
+/// `hit` comes first
+fn villager_under_crosshair(camera: &Camera, world: &World, hit: Option<RaycastHit>) -> Option<usize> {
+    let villagers: Vec<(u32, Aabb)> = world.villagers.iter().enumerate().map(|(i, villager)| (i as u32, villager.bounding_box())).collect();
+    box_under_crosshair(camera, world, &villagers, hit)
+}
+
+/// Index of the box of `boxes`, given with their indices, under the
+/// crosshair, unless `hit` comes first
+fn box_under_crosshair(camera: &Camera, world: &World, boxes: &[(u32, Aabb)], hit: Option<RaycastHit>) -> Option<usize> {
+    if boxes.is_empty() {
+        return None;
+    }
+    let filter = RaycastFilter { entities: boxes, ..RaycastFilter::TARGET_BLOCKS };
+    let entity = raycast(camera.position, camera.get_direction(), REACH, world, filter)?;
+    let HitTarget::Entity(index) = entity.target else {
+        return None;
+    };
+    hit.is_none_or(|hit| entity.distance < hit.distance).then_some(index as usize)
+}
+
+/// Put the boat in `hand` down on the water or the block face under the
+/// crosshair, facing the way the camera does. Returns true if it was placed.
+fn place_boat(world: &mut World, camera: &Camera, hand: Hand, creative: bool) -> bool {
+    let Some(hit) = raycast(camera.position, camera.get_direction(), REACH, world, RaycastFilter::ALL_BLOCKS) else {
+        return false;
+    };
+    let (x, y, z) = hit.position;
+    let position = if hit.block() == Some(BlockType::Water) {
+        glam::Vec3::new(x as f32 + 0.5, y as f32 + 1.0, z as f32 + 0.5)
+    } else {
+        let (nx, ny, nz) = hit.normal;
+        glam::Vec3::new((x + nx) as f32 + 0.5, (y + ny) as f32, (z + nz) as f32 + 0.5)
+    };
+    let boat = Boat::placed(position, camera.yaw);
+    if boat.collides(world) {
+        return false;
+    }
+    world.boats.push(boat);
+    if !creative {
+        world.inventory.remove_hand_item(hand, 1);
     }
+    true
+}
+
+/// Scoop up the water under the crosshair with an empty bucket in `hand`, or
+/// pour a water source out of a full one against the block face under it.
+/// Creative mode keeps the bucket as it is. Returns whether a block changed
+/// and whether the filled bucket didn't fit into the inventory.
+fn use_bucket(
+    world: &mut World,
+    camera: &Camera,
+    hand: Hand,
+    bucket: Item,
+    creative: bool,
+    entities: &[(u32, Aabb)],
+) -> (bool, bool) {
+    let (filter, swapped) = match bucket {
+        Item::Bucket => (RaycastFilter { entities, ..RaycastFilter::ALL_BLOCKS }, Item::WaterBucket),
+        _ => (RaycastFilter { entities, ..RaycastFilter::TARGET_BLOCKS }, Item::Bucket),
+    };
+    let Some(hit) = raycast(camera.position, camera.get_direction(), REACH, world, filter).filter(|hit| hit.block().is_some())
+    else {
+        return (false, false);
+    };
+    let (pos, block, event) = if bucket == Item::Bucket {
+        if hit.block() != Some(BlockType::Water) {
+            return (false, false);
+        }
+        (hit.position, BlockType::Air, GameEvent::BlockBroken { pos: hit.position, block: BlockType::Water })
+    } else {
+        let ((x, y, z), (nx, ny, nz)) = (hit.position, hit.normal);
+        let pos = (x + nx, y + ny, z + nz);
+        // Water washes away layers of snow and puddles, but nothing else
+        let in_world = (0..CHUNK_HEIGHT as i32).contains(&pos.1);
+        if !in_world || world.get_block_at(pos.0, pos.1, pos.2).is_none_or(|current| current.is_full_cube()) {
+            return (false, false);
+        }
+        (pos, BlockType::Water, GameEvent::BlockPlaced { pos, block: BlockType::Water })
+    };
+
+    // The block is loaded and in the world, so setting it can't fail
+    if !creative && !world.inventory.replace_hand_item(hand, swapped) {
+        return (false, true);
+    }
+    world.set_block_at(pos.0, pos.1, pos.2, block);
+    world.events.emit(event);
+    (true, false)
+}
+
+/// Set fire to the air in front of the block face the ray hit. Returns true
+/// if a fire was lit.
+fn light_fire(world: &mut World, hit: Option<RaycastHit>) -> bool {
+    let Some(hit) = hit.filter(|hit| hit.block().is_some()) else {
+        return false;
+    };
+    let ((x, y, z), (nx, ny, nz)) = (hit.position, hit.normal);
+    let pos = (x + nx, y + ny, z + nz);
+    let in_world = (0..CHUNK_HEIGHT as i32).contains(&pos.1);
+    if !in_world || world.get_block_at(pos.0, pos.1, pos.2) != Some(BlockType::Air) {
+        return false;
+    }
+    world.set_block_at(pos.0, pos.1, pos.2, BlockType::Fire);
+    world.events.emit(GameEvent::BlockPlaced { pos, block: BlockType::Fire });
+    true
+}
+
+/// Random bits deciding whether breaking the block at `pos` now wears the
+/// tool, see `ItemStack::wear`
+fn wear_roll(world: &World, (x, y, z): (i32, i32, i32)) -> u32 {
+    let mut h = (x as u32).wrapping_mul(0x8DA6_B343) ^ (y as u32).wrapping_mul(0xCB1A_B31F) ^ (z as u32).wrapping_mul(0xD816_3841);
+    h ^= world.time.ticks() as u32;
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2C1B_3C6D);
+    h ^ (h >> 12)
+}
+
+/// Add items to the player's inventory, announcing what fit. Returns false if
+/// not everything fit.
+fn pick_up(world: &mut World, item: Item, count: u32) -> bool {
+    pick_up_stack(world, ItemStack::new(item, count))
+}
+
+/// Add a stack to the player's inventory, wear and enchantments and all, as
+/// `pick_up` does
+fn pick_up_stack(world: &mut World, stack: ItemStack) -> bool {
+    let left_over = world.inventory.insert(stack);
+    if left_over < stack.count {
+        world.events.emit(GameEvent::ItemPickedUp { item: stack.item, count: stack.count - left_over });
+    }
+    left_over == 0
+}
+
+/// Walk speed multiplier while sneaking
+const SNEAK_SPEED_FACTOR: f32 = 0.3;
+/// Walk speed multiplier while sprinting
+const SPRINT_SPEED_FACTOR: f32 = 1.3;
+/// Hunger needed to start or keep sprinting
+const SPRINT_MIN_HUNGER: u32 = 7;
+
+/// Maximum multiplier the acceleration curve can apply
+const MAX_ACCELERATION_FACTOR: f32 = 4.0;
+
+/// Multiplier applied to a mouse movement of `speed` pixels this frame.
+/// Slow movements stay at 1x for precise aim; fast flicks turn further.
+pub fn acceleration_factor(speed: f32, acceleration: f32) -> f32 {
+    if acceleration <= 0.0 {
+        return 1.0;
+    }
+    (1.0 + acceleration * speed / 100.0).min(MAX_ACCELERATION_FACTOR)
+}
+
+/// Grab and hide the cursor for FPS-style controls, or release and show it for GUI screens.
+pub fn set_cursor_grabbed(window: &Window, grabbed: bool) {
+    if grabbed {
+        window.set_cursor_grab(CursorGrabMode::Confined)
+            .or_else(|_e| window.set_cursor_grab(CursorGrabMode::Locked))
+            .unwrap_or_else(|e| log::warn!("Failed to grab cursor: {}", e));
+    } else {
+        window.set_cursor_grab(CursorGrabMode::None)
+            .unwrap_or_else(|e| log::warn!("Failed to release cursor: {}", e));
+    }
+    window.set_cursor_visible(!grabbed);
+}
+
+/// Convert a position in physical window pixels (origin top-left, +Y down)
+/// to normalized device coordinates (origin center, +Y up).
+pub fn screen_to_ndc(position: (f64, f64), window_size: winit::dpi::PhysicalSize<u32>) -> (f32, f32) {
+    let width = window_size.width.max(1) as f64;
+    let height = window_size.height.max(1) as f64;
+    let x = (position.0 / width) * 2.0 - 1.0;
+    let y = 1.0 - (position.1 / height) * 2.0;
+    (x as f32, y as f32)
 }
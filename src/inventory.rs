@@ -1,16 +1,79 @@
 use crate::block::BlockType;
+use crate::enchanting::{self, Enchantment, Enchantments};
+use crate::item::Item;
 use serde::{Deserialize, Serialize};
 
 /// Represents a stack of items in the inventory
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "SavedStack", from = "SavedStack")]
 pub struct ItemStack {
-    pub block_type: BlockType,
+    pub item: Item,
     pub count: u32,
+    /// Uses a tool has worn off its durability; see `Item::durability`
+    pub wear: u32,
+    /// Modifiers from an enchanting table; see `enchanting`
+    pub enchantments: Enchantments,
+}
+
+/// How a stack is saved and sent. A tool's wear and enchantments share the
+/// number of its count, above the bits the count takes, so the layout is
+/// that of older saves, whose stacks have neither.
+#[derive(Serialize, Deserialize)]
+struct SavedStack {
+    item: Item,
+    count: u32,
+}
+
+/// Bits of `SavedStack::count` below the wear, and below the enchantments
+const COUNT_BITS: u32 = 8;
+const WEAR_BITS: u32 = 12;
+
+impl From<ItemStack> for SavedStack {
+    fn from(stack: ItemStack) -> Self {
+        let wear = stack.wear.min((1 << WEAR_BITS) - 1);
+        let count = stack.count | wear << COUNT_BITS | stack.enchantments.to_bits() << (COUNT_BITS + WEAR_BITS);
+        Self { item: stack.item, count }
+    }
+}
+
+impl From<SavedStack> for ItemStack {
+    fn from(saved: SavedStack) -> Self {
+        Self {
+            item: saved.item,
+            count: saved.count & ((1 << COUNT_BITS) - 1),
+            wear: (saved.count >> COUNT_BITS) & ((1 << WEAR_BITS) - 1),
+            enchantments: Enchantments::from_bits(saved.count >> (COUNT_BITS + WEAR_BITS)),
+        }
+    }
 }
 
 impl ItemStack {
-    pub fn new(block_type: BlockType, count: u32) -> Self {
-        Self { block_type, count }
+    pub fn new(item: impl Into<Item>, count: u32) -> Self {
+        Self { item: item.into(), count, wear: 0, enchantments: Enchantments::NONE }
+    }
+
+    /// The same stack, wear and enchantments and all, with another count
+    pub fn with_count(self, count: u32) -> Self {
+        Self { count, ..self }
+    }
+
+    /// Wear a tool by one use, unless its unbreaking spares it; `roll` is
+    /// random bits deciding that. Returns true once the tool is worn out.
+    /// Items without durability never wear.
+    pub fn wear(&mut self, roll: u32) -> bool {
+        let Some(durability) = self.item.durability() else {
+            return false;
+        };
+        if !enchanting::spares_wear(self.enchantments.level(Enchantment::Unbreaking), roll) {
+            self.wear += 1;
+        }
+        self.wear >= durability
+    }
+
+    /// Share of a tool's uses left, 0 to 1; None for items without durability
+    pub fn durability_left(&self) -> Option<f32> {
+        let durability = self.item.durability()?;
+        Some(1.0 - self.wear.min(durability) as f32 / durability as f32)
     }
 
     pub fn is_empty(&self) -> bool {
@@ -18,7 +81,7 @@ impl ItemStack {
     }
 
     pub fn max_stack_size(&self) -> u32 {
-        64 // Standard Minecraft stack size
+        self.item.max_stack_size()
     }
 
     pub fn can_add(&self, amount: u32) -> bool {
@@ -26,6 +89,110 @@ impl ItemStack {
     }
 }
 
+/// How a slot on an inventory screen was clicked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotClick {
+    Left,
+    Right,
+    /// Shift + left click: move the stack to the other section
+    ShiftLeft,
+}
+
+/// Left click on a slot: pick up, put down, merge, or swap with the held stack
+pub fn click_stack(slot: &mut Option<ItemStack>, held: &mut Option<ItemStack>) {
+    match (slot.as_mut(), held.as_mut()) {
+        // Merge held items into a matching stack; any remainder stays held
+        (Some(stack), Some(h)) if stack.item == h.item => {
+            let to_add = h.count.min(stack.max_stack_size() - stack.count);
+            stack.count += to_add;
+            h.count -= to_add;
+            if h.count == 0 {
+                *held = None;
+            }
+        }
+        // Pick up, put down, or swap
+        _ => std::mem::swap(slot, held),
+    }
+}
+
+/// Right click on a slot: pick up half the stack, or put down a single held item
+pub fn right_click_stack(slot: &mut Option<ItemStack>, held: &mut Option<ItemStack>) {
+    match (slot.as_mut(), held.as_mut()) {
+        (Some(stack), None) => {
+            let take = stack.count.div_ceil(2);
+            stack.count -= take;
+            *held = Some(stack.with_count(take));
+            if stack.count == 0 {
+                *slot = None;
+            }
+        }
+        (None, Some(h)) => {
+            *slot = Some(h.with_count(1));
+            h.count -= 1;
+        }
+        (Some(stack), Some(h)) if stack.item == h.item && stack.can_add(1) => {
+            stack.count += 1;
+            h.count -= 1;
+        }
+        _ => {}
+    }
+    if held.is_some_and(|h| h.is_empty()) {
+        *held = None;
+    }
+}
+
+/// Put a stack into a row of slots, merging into matching stacks first and then
+/// filling the first empty slot. Returns how many items did not fit.
+pub fn insert_stack(slots: &mut [Option<ItemStack>], stack: ItemStack) -> u32 {
+    let mut remaining = stack.count;
+    for slot in slots.iter_mut().flatten() {
+        if slot.item == stack.item {
+            let to_add = remaining.min(slot.max_stack_size() - slot.count);
+            slot.count += to_add;
+            remaining -= to_add;
+            if remaining == 0 {
+                return 0;
+            }
+        }
+    }
+    if let Some(empty) = slots.iter_mut().find(|slot| slot.is_none()) {
+        *empty = Some(stack.with_count(remaining));
+        return 0;
+    }
+    remaining
+}
+
+/// Address of a slot on the inventory screen: one of the player's, or one of
+/// the open chest's or furnace's
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SlotId {
+    Toolbar(usize),
+    Storage(usize),
+    Offhand,
+    Container(usize),
+}
+
+/// Where an item is used from: the selected toolbar slot or the off-hand slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hand {
+    Main,
+    Off,
+}
+
+/// Inventory layout from before the off-hand slot, in older saves
+#[derive(Deserialize)]
+pub struct InventoryWithoutOffhand {
+    toolbar: [Option<ItemStack>; 9],
+    storage: [Option<ItemStack>; 27],
+    selected_slot: usize,
+}
+
+impl From<InventoryWithoutOffhand> for Inventory {
+    fn from(old: InventoryWithoutOffhand) -> Self {
+        Self { toolbar: old.toolbar, storage: old.storage, selected_slot: old.selected_slot, ..Self::new() }
+    }
+}
+
 /// Main inventory structure with toolbar (9 slots) and storage
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Inventory {
@@ -35,6 +202,11 @@ pub struct Inventory {
     pub storage: [Option<ItemStack>; 27],
     /// Currently selected toolbar slot (0-8)
     pub selected_slot: usize,
+    /// Second hand, used by right click while the selected slot is empty
+    pub offhand: Option<ItemStack>,
+    /// Stack picked up with the mouse while the inventory screen is open
+    #[serde(skip)]
+    pub held: Option<ItemStack>,
 }
 
 impl Inventory {
@@ -43,6 +215,8 @@ impl Inventory {
             toolbar: [None; 9],
             storage: [None; 27],
             selected_slot: 0,
+            offhand: None,
+            held: None,
         }
     }
 
@@ -58,6 +232,14 @@ impl Inventory {
         inv.toolbar[5] = Some(ItemStack::new(BlockType::Leaves, 64));
         inv.toolbar[6] = Some(ItemStack::new(BlockType::Glass, 64));
         inv.toolbar[7] = Some(ItemStack::new(BlockType::Stone, 64));
+        inv.toolbar[8] = Some(ItemStack::new(Item::WoodenPickaxe, 1));
+        inv.storage[0] = Some(ItemStack::new(BlockType::Chest, 4));
+        inv.storage[1] = Some(ItemStack::new(BlockType::Furnace, 4));
+        inv.storage[2] = Some(ItemStack::new(Item::Compass, 1));
+        inv.storage[3] = Some(ItemStack::new(Item::Bucket, 1));
+        inv.storage[4] = Some(ItemStack::new(Item::FlintAndSteel, 1));
+        inv.storage[5] = Some(ItemStack::new(Item::WoodenShovel, 1));
+        inv.storage[6] = Some(ItemStack::new(Item::WoodenAxe, 1));
         inv
     }
 
@@ -67,13 +249,19 @@ impl Inventory {
     }
 
     /// Get the currently selected item stack mutably
+    #[allow(dead_code)]
     pub fn get_selected_item_mut(&mut self) -> &mut Option<ItemStack> {
         &mut self.toolbar[self.selected_slot]
     }
 
-    /// Get the block type in the selected slot
+    /// Get the item in the selected slot
+    pub fn selected_item(&self) -> Option<Item> {
+        self.toolbar[self.selected_slot].as_ref().map(|s| s.item)
+    }
+
+    /// Get the block the selected slot places, if it holds a block
     pub fn get_selected_block(&self) -> Option<BlockType> {
-        self.toolbar[self.selected_slot].as_ref().map(|s| s.block_type)
+        self.selected_item().and_then(|item| item.block())
     }
 
     /// Select next toolbar slot
@@ -88,72 +276,91 @@ impl Inventory {
 
     /// Try to add an item to the inventory
     /// Returns true if item was added, false if inventory is full
-    pub fn add_item(&mut self, block_type: BlockType, amount: u32) -> bool {
+    pub fn add_item(&mut self, item: impl Into<Item>, amount: u32) -> bool {
+        self.add_stack(item, amount) == 0
+    }
+
+    /// Add as many items as fit, returning how many were left over
+    pub fn add_stack(&mut self, item: impl Into<Item>, amount: u32) -> u32 {
+        self.insert(ItemStack::new(item, amount))
+    }
+
+    /// Add as much of a stack as fits, keeping its wear and enchantments,
+    /// returning how many items were left over
+    pub fn insert(&mut self, stack: ItemStack) -> u32 {
+        let (item, amount) = (stack.item, stack.count);
         if amount == 0 {
-            return true;
+            return 0;
         }
 
         let mut remaining = amount;
 
         // First, try to add to existing stacks in toolbar
         for stack in self.toolbar.iter_mut().flatten() {
-            if stack.block_type == block_type && !stack.is_empty() {
+            if stack.item == item && !stack.is_empty() {
                 let can_add = stack.max_stack_size() - stack.count;
                 let to_add = remaining.min(can_add);
                 stack.count += to_add;
                 remaining -= to_add;
                 if remaining == 0 {
-                    return true;
+                    return 0;
                 }
             }
         }
 
         // Then try existing stacks in storage
         for stack in self.storage.iter_mut().flatten() {
-            if stack.block_type == block_type && !stack.is_empty() {
+            if stack.item == item && !stack.is_empty() {
                 let can_add = stack.max_stack_size() - stack.count;
                 let to_add = remaining.min(can_add);
                 stack.count += to_add;
                 remaining -= to_add;
                 if remaining == 0 {
-                    return true;
+                    return 0;
                 }
             }
         }
 
         // Create new stacks in empty slots
         while remaining > 0 {
-            let stack_size = remaining.min(64);
+            let stack_size = remaining.min(item.max_stack_size());
             
             // Try toolbar first
             if let Some(empty_slot) = self.toolbar.iter_mut().find(|slot| slot.is_none()) {
-                *empty_slot = Some(ItemStack::new(block_type, stack_size));
+                *empty_slot = Some(stack.with_count(stack_size));
                 remaining -= stack_size;
                 continue;
             }
 
             // Then try storage
             if let Some(empty_slot) = self.storage.iter_mut().find(|slot| slot.is_none()) {
-                *empty_slot = Some(ItemStack::new(block_type, stack_size));
+                *empty_slot = Some(stack.with_count(stack_size));
                 remaining -= stack_size;
                 continue;
             }
 
             // No space left
-            return false;
+            return remaining;
         }
 
-        true
+        0
     }
 
     /// Try to remove an item from the selected slot
     /// Returns true if item was removed, false if slot is empty
     pub fn remove_selected_item(&mut self, amount: u32) -> bool {
-        if let Some(stack) = &mut self.toolbar[self.selected_slot] {
+        self.remove_hand_item(Hand::Main, amount)
+    }
+
+    /// Try to remove items from the stack in a hand
+    /// Returns true if they were removed, false if there weren't enough
+    pub fn remove_hand_item(&mut self, hand: Hand, amount: u32) -> bool {
+        let slot = self.hand_slot_mut(hand);
+        if let Some(stack) = slot {
             if stack.count >= amount {
                 stack.count -= amount;
                 if stack.count == 0 {
-                    self.toolbar[self.selected_slot] = None;
+                    *slot = None;
                 }
                 return true;
             }
@@ -161,78 +368,243 @@ impl Inventory {
         false
     }
 
+    /// Trade one item of the stack in a hand for `item`: in its place if it
+    /// was the last one, otherwise wherever it fits. Returns false, changing
+    /// nothing, if it doesn't fit.
+    pub fn replace_hand_item(&mut self, hand: Hand, item: impl Into<Item>) -> bool {
+        let item = item.into();
+        let Some(stack) = self.hand_slot_mut(hand) else {
+            return false;
+        };
+        if stack.count == 1 {
+            *stack = ItemStack::new(item, 1);
+            return true;
+        }
+        self.add_stack(item, 1) == 0 && self.remove_hand_item(hand, 1)
+    }
+
+    /// The item in a hand
+    pub fn hand_item(&self, hand: Hand) -> Option<Item> {
+        match hand {
+            Hand::Main => self.selected_item(),
+            Hand::Off => self.offhand.map(|stack| stack.item),
+        }
+    }
+
+    /// The hand right click uses: the main hand, or the off hand while the
+    /// selected slot is empty
+    pub fn active_hand(&self) -> Hand {
+        if self.toolbar[self.selected_slot].is_none() && self.offhand.is_some() {
+            Hand::Off
+        } else {
+            Hand::Main
+        }
+    }
+
+    fn hand_slot_mut(&mut self, hand: Hand) -> &mut Option<ItemStack> {
+        match hand {
+            Hand::Main => &mut self.toolbar[self.selected_slot],
+            Hand::Off => &mut self.offhand,
+        }
+    }
+
+    /// Take one item out of the selected slot, or the whole stack
+    pub fn take_selected(&mut self, whole_stack: bool) -> Option<ItemStack> {
+        let stack = self.toolbar[self.selected_slot]?;
+        let count = if whole_stack { stack.count } else { 1 };
+        self.remove_selected_item(count).then(|| stack.with_count(count))
+    }
+
+    /// Empty every slot, the off hand and the held stack, returning what was
+    /// in them
+    pub fn take_all(&mut self) -> Vec<ItemStack> {
+        let slots = self.toolbar.iter_mut().chain(self.storage.iter_mut());
+        slots.chain([&mut self.offhand, &mut self.held]).filter_map(Option::take).collect()
+    }
+
+    /// Wear the tool in the selected slot by one use (see `ItemStack::wear`),
+    /// taking it out once it is worn out. Returns true if it broke.
+    pub fn wear_selected(&mut self, roll: u32) -> bool {
+        let slot = &mut self.toolbar[self.selected_slot];
+        if slot.as_mut().is_some_and(|stack| stack.wear(roll)) {
+            *slot = None;
+            return true;
+        }
+        false
+    }
+
+    /// Swap the selected slot's stack with the off hand's
+    pub fn swap_offhand(&mut self) {
+        std::mem::swap(&mut self.toolbar[self.selected_slot], &mut self.offhand);
+    }
+
     /// Check if the selected slot has at least one item
     pub fn has_selected_item(&self) -> bool {
         self.toolbar[self.selected_slot].as_ref().is_some_and(|s| s.count > 0)
     }
 
-    /// Move item from one slot to another
-    /// Returns true if successful
-    pub fn move_item(&mut self, from_toolbar: bool, from_idx: usize, to_toolbar: bool, to_idx: usize) -> bool {
-        // Get source and destination arrays
-        let (from_slot, to_slot) = if from_toolbar && to_toolbar {
-            if from_idx >= 9 || to_idx >= 9 {
-                return false;
-            }
-            let ptr = self.toolbar.as_mut_ptr();
-            unsafe {
-                (ptr.add(from_idx), ptr.add(to_idx))
-            }
-        } else if from_toolbar && !to_toolbar {
-            if from_idx >= 9 || to_idx >= 27 {
-                return false;
-            }
-            // Can't use split_at_mut because they're different arrays
-            // We'll handle this differently
-            let from_item = self.toolbar[from_idx].take();
-            let to_item = self.storage[to_idx].take();
-            
-            self.toolbar[from_idx] = to_item;
-            self.storage[to_idx] = from_item;
-            return true;
-        } else if !from_toolbar && to_toolbar {
-            if from_idx >= 27 || to_idx >= 9 {
-                return false;
+    /// Swap the contents of two of the inventory's slots
+    /// Returns false, changing nothing, if either isn't one of its slots
+    pub fn move_item(&mut self, from: SlotId, to: SlotId) -> bool {
+        let (Some(&moving), Some(&replaced)) = (self.slot(from), self.slot(to)) else {
+            return false;
+        };
+        self.put_slot(to, moving);
+        self.put_slot(from, replaced);
+        true
+    }
+
+    /// Get one of the inventory's slots; container slots aren't
+    pub fn slot(&self, id: SlotId) -> Option<&Option<ItemStack>> {
+        match id {
+            SlotId::Toolbar(idx) => self.toolbar.get(idx),
+            SlotId::Storage(idx) => self.storage.get(idx),
+            SlotId::Offhand => Some(&self.offhand),
+            SlotId::Container(_) => None,
+        }
+    }
+
+    fn slot_mut(&mut self, id: SlotId) -> Option<&mut Option<ItemStack>> {
+        match id {
+            SlotId::Toolbar(idx) => self.toolbar.get_mut(idx),
+            SlotId::Storage(idx) => self.storage.get_mut(idx),
+            SlotId::Offhand => Some(&mut self.offhand),
+            SlotId::Container(_) => None,
+        }
+    }
+
+    /// Left click on a slot: pick up, put down, merge, or swap with the held stack
+    pub fn click_slot(&mut self, id: SlotId) {
+        let mut held = self.held.take();
+        if let Some(slot) = self.slot_mut(id) {
+            click_stack(slot, &mut held);
+        }
+        self.held = held;
+    }
+
+    /// Right click on a slot: pick up half the stack, or put down a single held item
+    pub fn right_click_slot(&mut self, id: SlotId) {
+        let mut held = self.held.take();
+        if let Some(slot) = self.slot_mut(id) {
+            right_click_stack(slot, &mut held);
+        }
+        self.held = held;
+    }
+
+    /// Take the stack out of a slot (e.g. to move it into an open container)
+    pub fn take_slot(&mut self, id: SlotId) -> Option<ItemStack> {
+        self.slot_mut(id)?.take()
+    }
+
+    /// Put a stack back into a slot, replacing its contents
+    pub fn put_slot(&mut self, id: SlotId, stack: Option<ItemStack>) {
+        if let Some(slot) = self.slot_mut(id) {
+            *slot = stack;
+        }
+    }
+
+    /// Shift-click: move a stack from the toolbar to storage, or from storage
+    /// or the off hand to the toolbar, merging into matching stacks first and
+    /// then into the first empty slot
+    pub fn quick_move(&mut self, id: SlotId) {
+        let Some(Some(mut moving)) = self.slot(id).copied() else {
+            return;
+        };
+
+        let to_storage = matches!(id, SlotId::Toolbar(_));
+        let targets: &mut [Option<ItemStack>] = if to_storage { &mut self.storage } else { &mut self.toolbar };
+        for stack in targets.iter_mut().flatten() {
+            if stack.item == moving.item {
+                let to_add = moving.count.min(stack.max_stack_size() - stack.count);
+                stack.count += to_add;
+                moving.count -= to_add;
+                if moving.count == 0 {
+                    break;
+                }
             }
-            let from_item = self.storage[from_idx].take();
-            let to_item = self.toolbar[to_idx].take();
-            
-            self.storage[from_idx] = to_item;
-            self.toolbar[to_idx] = from_item;
-            return true;
-        } else {
-            if from_idx >= 27 || to_idx >= 27 {
-                return false;
+        }
+        let empty_target = targets.iter().position(|slot| slot.is_none());
+
+        if moving.count == 0 {
+            self.put_slot(id, None);
+            return;
+        }
+
+        self.put_slot(id, Some(moving));
+        if let Some(target_idx) = empty_target {
+            let target = if to_storage { SlotId::Storage(target_idx) } else { SlotId::Toolbar(target_idx) };
+            self.move_item(id, target);
+        }
+    }
+
+    /// Sort the storage: partial stacks of the same item merge into full ones,
+    /// then stacks are ordered by item id, larger first, with the empty slots
+    /// at the end. The toolbar is left as the player arranged it.
+    pub fn compact(&mut self) {
+        let mut stacks: Vec<ItemStack> = Vec::new();
+        for stack in self.storage.iter_mut().filter_map(Option::take) {
+            let mut remaining = stack.count;
+            for merged in stacks.iter_mut().filter(|merged| merged.item == stack.item) {
+                let to_add = remaining.min(merged.max_stack_size() - merged.count);
+                merged.count += to_add;
+                remaining -= to_add;
             }
-            let ptr = self.storage.as_mut_ptr();
-            unsafe {
-                (ptr.add(from_idx), ptr.add(to_idx))
+            if remaining > 0 {
+                stacks.push(stack.with_count(remaining));
             }
-        };
-
-        unsafe {
-            // Swap the two slots
-            std::ptr::swap(from_slot, to_slot);
         }
-        
-        true
+        stacks.sort_by_key(|stack| (u32::from(stack.item), std::cmp::Reverse(stack.count)));
+        for (slot, stack) in self.storage.iter_mut().zip(stacks) {
+            *slot = Some(stack);
+        }
+    }
+
+    /// Put the held stack back into the inventory (e.g. when the screen closes).
+    /// Returns what didn't fit, for the caller to drop.
+    pub fn return_held(&mut self) -> Option<ItemStack> {
+        let stack = self.held.take()?;
+        let left = self.insert(stack);
+        (left > 0).then_some(ItemStack { count: left, ..stack })
     }
 
-    /// Get total number of a specific block type in inventory
-    pub fn count_block_type(&self, block_type: BlockType) -> u32 {
+    /// Get total number of a specific item in inventory
+    pub fn count_item(&self, item: impl Into<Item>) -> u32 {
+        let item = item.into();
         let mut total = 0;
         for stack in self.toolbar.iter().flatten() {
-            if stack.block_type == block_type {
+            if stack.item == item {
                 total += stack.count;
             }
         }
-        for stack in self.storage.iter().flatten() {
-            if stack.block_type == block_type {
+        for stack in self.storage.iter().chain([&self.offhand]).flatten() {
+            if stack.item == item {
                 total += stack.count;
             }
         }
         total
     }
+
+    /// Take `amount` of an item out of the toolbar, storage and off hand,
+    /// in that order. Returns false, taking nothing, if there aren't as many.
+    pub fn remove_item(&mut self, item: impl Into<Item>, amount: u32) -> bool {
+        let item = item.into();
+        if self.count_item(item) < amount {
+            return false;
+        }
+        let mut remaining = amount;
+        for slot in self.toolbar.iter_mut().chain(self.storage.iter_mut()).chain([&mut self.offhand]) {
+            let Some(stack) = slot.as_mut().filter(|stack| stack.item == item) else {
+                continue;
+            };
+            let taken = remaining.min(stack.count);
+            stack.count -= taken;
+            remaining -= taken;
+            if stack.count == 0 {
+                *slot = None;
+            }
+        }
+        true
+    }
 }
 
 impl Default for Inventory {
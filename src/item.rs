@@ -0,0 +1,225 @@
+//! What an inventory slot holds: a block to place, or an item that is only
+//! held and used (tools, buckets, food, ...). Items are saved by a number
+//! of their own; blocks keep the number of their `BlockType`, and the items
+//! that used to be block types keep theirs too, so older saves still load.
+
+use crate::block::BlockType;
+use serde::{Deserialize, Serialize};
+
+/// Number of the first item that never was a block type; block types stay below it
+const FIRST_ITEM_ID: u32 = 256;
+
+/// Kind of tool, each breaking its own blocks faster
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ToolKind {
+    Pickaxe,
+    Shovel,
+    Axe,
+}
+
+/// What a tool is made of, from worst to best
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ToolTier {
+    Wood,
+    Stone,
+}
+
+impl ToolTier {
+    /// How many times faster than a bare hand the tool breaks its blocks
+    pub fn speed(&self) -> f32 {
+        match self {
+            ToolTier::Wood => 2.0,
+            ToolTier::Stone => 4.0,
+        }
+    }
+
+    /// Uses a tool of this tier lasts before it breaks, as in Minecraft
+    pub fn durability(&self) -> u32 {
+        match self {
+            ToolTier::Wood => 59,
+            ToolTier::Stone => 131,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "u32", try_from = "u32")]
+pub enum Item {
+    /// A block, placed with right click
+    Block(BlockType),
+    /// Points to the world spawn while held
+    Compass,
+    /// Scoops up water, turning into a water bucket
+    Bucket,
+    /// Pours out a water source, turning back into a bucket
+    WaterBucket,
+    /// Sets fire to the block face it is used on
+    FlintAndSteel,
+    /// Tools, held to break blocks faster; see `Item::tool`
+    WoodenPickaxe,
+    StonePickaxe,
+    WoodenShovel,
+    WoodenAxe,
+    Stick,
+    Apple,
+    Seeds,
+    /// Put down on water and ridden; see `boat`
+    Boat,
+    /// Casts a bobber into water and reels in catches; see `fishing`
+    FishingRod,
+    Fish,
+    /// What villagers trade in; see `villager`
+    Emerald,
+}
+
+impl Item {
+    /// Every item that isn't a block
+    pub const ITEMS: [Item; 15] = [
+        Item::Compass,
+        Item::Bucket,
+        Item::WaterBucket,
+        Item::FlintAndSteel,
+        Item::WoodenPickaxe,
+        Item::StonePickaxe,
+        Item::WoodenShovel,
+        Item::WoodenAxe,
+        Item::Stick,
+        Item::Apple,
+        Item::Seeds,
+        Item::Boat,
+        Item::FishingRod,
+        Item::Fish,
+        Item::Emerald,
+    ];
+
+    /// Lowercase identifier used in commands and configs; blocks go by
+    /// their block name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Item::Block(block) => block.name(),
+            Item::Compass => "compass",
+            Item::Bucket => "bucket",
+            Item::WaterBucket => "water_bucket",
+            Item::FlintAndSteel => "flint_and_steel",
+            Item::WoodenPickaxe => "wooden_pickaxe",
+            Item::StonePickaxe => "stone_pickaxe",
+            Item::WoodenShovel => "wooden_shovel",
+            Item::WoodenAxe => "wooden_axe",
+            Item::Stick => "stick",
+            Item::Apple => "apple",
+            Item::Seeds => "seeds",
+            Item::Boat => "boat",
+            Item::FishingRod => "fishing_rod",
+            Item::Fish => "fish",
+            Item::Emerald => "emerald",
+        }
+    }
+
+    /// Look up an item or block by its identifier (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Item> {
+        BlockType::from_name(name)
+            .map(Item::Block)
+            .or_else(|| Item::ITEMS.into_iter().find(|item| item.name().eq_ignore_ascii_case(name)))
+    }
+
+    /// The block this item places, if it is one
+    pub fn block(&self) -> Option<BlockType> {
+        match self {
+            Item::Block(block) => Some(*block),
+            _ => None,
+        }
+    }
+
+    /// Most items of this kind one inventory slot holds
+    pub fn max_stack_size(&self) -> u32 {
+        match self {
+            Item::WaterBucket | Item::FlintAndSteel | Item::Boat | Item::FishingRod => 1,
+            Item::Bucket => 16,
+            _ if self.tool().is_some() => 1,
+            _ => 64,
+        }
+    }
+
+    /// Kind and tier of a tool
+    pub fn tool(&self) -> Option<(ToolKind, ToolTier)> {
+        match self {
+            Item::WoodenPickaxe => Some((ToolKind::Pickaxe, ToolTier::Wood)),
+            Item::StonePickaxe => Some((ToolKind::Pickaxe, ToolTier::Stone)),
+            Item::WoodenShovel => Some((ToolKind::Shovel, ToolTier::Wood)),
+            Item::WoodenAxe => Some((ToolKind::Axe, ToolTier::Wood)),
+            _ => None,
+        }
+    }
+
+    /// Uses the item lasts before it breaks; None for items that don't
+    /// wear, everything but the tools. See `ItemStack::wear`.
+    pub fn durability(&self) -> Option<u32> {
+        self.tool().map(|(_, tier)| tier.durability())
+    }
+
+    /// Color of the item's icon; blocks take their block color
+    pub fn get_color(&self) -> [f32; 3] {
+        match self {
+            Item::Block(block) => block.get_color(),
+            Item::Compass => [0.8, 0.25, 0.2],
+            Item::Bucket => [0.7, 0.7, 0.72],
+            Item::WaterBucket => [0.35, 0.5, 0.8],
+            Item::FlintAndSteel => [0.3, 0.3, 0.32],
+            Item::WoodenPickaxe | Item::WoodenShovel | Item::WoodenAxe => [0.6, 0.45, 0.25],
+            Item::StonePickaxe => [0.55, 0.55, 0.55],
+            Item::Stick => [0.5, 0.36, 0.2],
+            Item::Apple => [0.8, 0.12, 0.1],
+            Item::Seeds => [0.45, 0.6, 0.2],
+            Item::Boat => [0.55, 0.4, 0.22],
+            Item::FishingRod => [0.45, 0.32, 0.18],
+            Item::Fish => [0.45, 0.6, 0.7],
+            Item::Emerald => [0.1, 0.75, 0.35],
+        }
+    }
+
+    /// Number the item is saved as
+    fn id(&self) -> u32 {
+        match self {
+            Item::Block(block) => u32::from(*block),
+            // Block type numbers from before items had a type of their own
+            Item::Compass => 14,
+            Item::Bucket => 15,
+            Item::WaterBucket => 16,
+            Item::FlintAndSteel => 18,
+            Item::WoodenPickaxe => FIRST_ITEM_ID,
+            Item::StonePickaxe => FIRST_ITEM_ID + 1,
+            Item::WoodenShovel => FIRST_ITEM_ID + 2,
+            Item::WoodenAxe => FIRST_ITEM_ID + 3,
+            Item::Stick => FIRST_ITEM_ID + 4,
+            Item::Apple => FIRST_ITEM_ID + 5,
+            Item::Seeds => FIRST_ITEM_ID + 6,
+            Item::Boat => FIRST_ITEM_ID + 7,
+            Item::FishingRod => FIRST_ITEM_ID + 8,
+            Item::Fish => FIRST_ITEM_ID + 9,
+            Item::Emerald => FIRST_ITEM_ID + 10,
+        }
+    }
+}
+
+impl From<BlockType> for Item {
+    fn from(block: BlockType) -> Self {
+        Item::Block(block)
+    }
+}
+
+impl From<Item> for u32 {
+    fn from(item: Item) -> Self {
+        item.id()
+    }
+}
+
+impl TryFrom<u32> for Item {
+    type Error = String;
+
+    fn try_from(id: u32) -> Result<Self, Self::Error> {
+        Item::ITEMS
+            .into_iter()
+            .find(|item| item.id() == id)
+            .map_or_else(|| BlockType::try_from(id).map(Item::Block), Ok)
+    }
+}
@@ -0,0 +1,136 @@
+//! Items lying in the world, thrown out of the inventory with Q. They fall
+//! and slide to a stop on the ground. Once they have been out a moment they
+//! drift towards a player who comes close and go back into the inventory
+//! on touching them. They are not saved, and vanish after five minutes.
+
+use crate::debug_render::DebugVertex;
+use crate::events::GameEvent;
+use crate::inventory::ItemStack;
+use crate::physics::Aabb;
+use crate::remote_players::add_cuboid;
+use crate::world::World;
+use glam::{Mat3, Vec3};
+
+/// Seconds after being thrown before an item can be picked up again
+pub const PICKUP_DELAY: f32 = 1.5;
+/// Seconds an item lies around before it vanishes
+const DESPAWN_TIME: f32 = 300.0;
+/// Distance from the player's body within which items drift towards it
+const MAGNET_RANGE: f32 = 1.5;
+/// Speed items drift at, in blocks per second
+const MAGNET_SPEED: f32 = 5.0;
+/// Distance from the player's body within which items are picked up
+const PICKUP_RANGE: f32 = 0.25;
+/// Speed an item leaves the hand with, in blocks per second
+const THROW_SPEED: f32 = 6.0;
+const GRAVITY: f32 = -20.0;
+/// Share of horizontal speed kept per second while on the ground
+const GROUND_FRICTION: f32 = 0.02;
+/// Side of the cube an item is drawn and collides as
+const SIZE: f32 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ItemEntity {
+    pub stack: ItemStack,
+    /// Middle of the bottom face
+    pub position: Vec3,
+    pub velocity: Vec3,
+    /// Seconds since it was thrown
+    pub age: f32,
+}
+
+impl ItemEntity {
+    /// A stack thrown from `eye` towards `direction`, a little upwards
+    pub fn thrown(stack: ItemStack, eye: Vec3, direction: Vec3) -> Self {
+        let direction = direction.normalize_or_zero();
+        Self {
+            stack,
+            position: eye - Vec3::Y * 0.3 + direction * 0.3,
+            velocity: direction * THROW_SPEED + Vec3::Y * 2.0,
+            age: 0.0,
+        }
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        Aabb::from_position(self.position, SIZE / 2.0, SIZE)
+    }
+
+    /// Fall and slide for `delta_time` seconds, stopping against solid blocks
+    /// one axis at a time
+    fn update(&mut self, world: &World, delta_time: f32) {
+        self.age += delta_time;
+        self.velocity.y += GRAVITY * delta_time;
+        for axis in 0..3 {
+            let mut moved = *self;
+            moved.position[axis] += self.velocity[axis] * delta_time;
+            if moved.collides(world) {
+                self.velocity[axis] = 0.0;
+            } else {
+                self.position = moved.position;
+            }
+        }
+        world.push_inside_border(&mut self.position, &mut self.velocity, SIZE / 2.0);
+        if self.velocity.y == 0.0 {
+            let kept = GROUND_FRICTION.powf(delta_time);
+            self.velocity.x *= kept;
+            self.velocity.z *= kept;
+        }
+    }
+
+    fn collides(&self, world: &World) -> bool {
+        let aabb = self.bounding_box();
+        let (min, max) = (aabb.min.floor().as_ivec3(), (aabb.max - 1e-4).floor().as_ivec3());
+        (min.x..=max.x).any(|x| {
+            (min.y..=max.y).any(|y| {
+                (min.z..=max.z).any(|z| world.get_block_at(x, y, z).is_some_and(|block| block.is_full_cube()))
+            })
+        })
+    }
+}
+
+/// Move the items lying in the world on by `delta_time` seconds. Those near
+/// the player's `body` drift towards it, and those touching it go into the
+/// inventory as far as they fit; a dead player has no body and picks nothing
+/// up. Returns true if any were picked up.
+pub fn update_items(world: &mut World, body: Option<&Aabb>, delta_time: f32) -> bool {
+    let mut items = std::mem::take(&mut world.items);
+    let mut picked_up = false;
+    items.retain_mut(|item| {
+        let body = body.filter(|_| item.age >= PICKUP_DELAY);
+        let centre = item.position + Vec3::Y * SIZE / 2.0;
+        if let Some(body) = body.filter(|body| distance_to(body, centre) <= MAGNET_RANGE) {
+            item.velocity = ((body.min + body.max) / 2.0 - centre).normalize_or_zero() * MAGNET_SPEED;
+        }
+        item.update(world, delta_time);
+        let centre = item.position + Vec3::Y * SIZE / 2.0;
+        if body.is_some_and(|body| distance_to(body, centre) <= PICKUP_RANGE) {
+            let left_over = world.inventory.insert(item.stack);
+            if left_over < item.stack.count {
+                world.events.emit(GameEvent::ItemPickedUp { item: item.stack.item, count: item.stack.count - left_over });
+                picked_up = true;
+            }
+            item.stack.count = left_over;
+        }
+        item.stack.count > 0 && item.age < DESPAWN_TIME && item.position.y > -64.0
+    });
+    world.items = items;
+    picked_up
+}
+
+/// Distance from a point to the nearest point of a box, 0 inside it
+fn distance_to(aabb: &Aabb, point: Vec3) -> f32 {
+    point.distance(point.clamp(aabb.min, aabb.max))
+}
+
+/// Triangles of the items, small cubes in their item's color that turn and
+/// bob over time
+pub fn build_mesh(items: &[ItemEntity], vertices: &mut Vec<DebugVertex>, indices: &mut Vec<u32>) {
+    let half = SIZE / 2.0;
+    for item in items {
+        let turn = Mat3::from_rotation_y(item.age * 1.5);
+        let bob = Vec3::Y * (half + (item.age * 2.5).sin() * 0.05);
+        let transform = |p: Vec3| item.position + bob + turn * p;
+        let color = item.stack.item.get_color();
+        add_cuboid(vertices, indices, Vec3::splat(-half), Vec3::splat(half), transform, color);
+    }
+}
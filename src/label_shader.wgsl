@@ -0,0 +1,49 @@
+// World-space text labels: glyphs sampled from the font atlas, tinted by the
+// vertex color, depth tested against the world but neither lit nor fogged
+
+struct Uniforms {
+    view_proj: mat4x4<f32>,
+    camera_pos: vec4<f32>,
+    fog_color: vec4<f32>,
+    fog_params: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> uniforms: Uniforms;
+
+@group(1) @binding(0)
+var t_font: texture_2d<f32>;
+@group(1) @binding(1)
+var s_font: sampler;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    // Pixel position in the font atlas
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = uniforms.view_proj * vec4<f32>(input.position, 1.0);
+    out.tex_coords = input.tex_coords;
+    out.color = input.color;
+    return out;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(t_font));
+    let coverage = textureSample(t_font, s_font, input.tex_coords / size).a;
+    if coverage < 0.5 {
+        discard;
+    }
+    return input.color;
+}
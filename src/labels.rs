@@ -0,0 +1,109 @@
+//! Text labels in the world, such as the names above the other players. A
+//! label is a strip of glyphs from the font atlas (see `font::atlas`) on a
+//! dim background, turned to face the camera every frame and drawn depth
+//! tested like the figures. Labels shrink with distance like everything else
+//! in the world and fade out towards the edge of their range.
+
+use crate::camera::Camera;
+use crate::font::{self, GLYPH_ADVANCE, GLYPH_HEIGHT, GLYPH_WIDTH};
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+
+/// Size of a font pixel of a label, in blocks
+const LABEL_PIXEL: f32 = 0.025;
+/// Font pixels of background around the text
+const PADDING: f32 = 1.0;
+const BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 0.25];
+const TEXT_COLOR: [f32; 3] = [1.0, 1.0, 1.0];
+/// Labels further from the camera than this aren't drawn
+pub const LABEL_RANGE: f32 = 48.0;
+/// Distance at which labels start fading out, reaching nothing at `LABEL_RANGE`
+const FADE_START: f32 = 32.0;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct LabelVertex {
+    pub position: [f32; 3],
+    /// Pixel position in the font atlas
+    pub tex_coords: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl LabelVertex {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<LabelVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// A line of text centered above `position`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub text: String,
+    pub position: Vec3,
+}
+
+/// How much of a label `distance` blocks away shows, from 1 up close to 0
+/// at the edge of the range
+pub fn fade(distance: f32) -> f32 {
+    ((LABEL_RANGE - distance) / (LABEL_RANGE - FADE_START)).clamp(0.0, 1.0)
+}
+
+/// Triangles of the labels in range and in front of the camera, facing it
+pub fn build_mesh(labels: &[Label], camera: &Camera, vertices: &mut Vec<LabelVertex>, indices: &mut Vec<u32>) {
+    let direction = camera.get_direction();
+    let right = camera.get_right();
+    let up = right.cross(direction);
+    for label in labels {
+        let offset = label.position - camera.position;
+        let alpha = fade(offset.length());
+        if alpha <= 0.0 || offset.dot(direction) <= 0.0 {
+            continue;
+        }
+        // In font pixels from the bottom middle of the background
+        let width = (label.text.chars().count() * GLYPH_ADVANCE).saturating_sub(1) as f32 + 2.0 * PADDING;
+        let height = GLYPH_HEIGHT as f32 + 2.0 * PADDING;
+        let point = |x: f32, y: f32| (label.position + (right * (x - width / 2.0) + up * y) * LABEL_PIXEL).to_array();
+        // A quad `size` font pixels large at (x, y), showing the glyph-sized
+        // atlas cell at `cell`; atlas rows run down, the label's up
+        let mut quad = |(x, y): (f32, f32), (w, h): (f32, f32), cell: (u32, u32), color: [f32; 4]| {
+            let base = vertices.len() as u32;
+            let (u, v) = (cell.0 as f32, cell.1 as f32);
+            let (cell_w, cell_h) = (GLYPH_WIDTH as f32, GLYPH_HEIGHT as f32);
+            let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+            for (dx, dy) in corners {
+                let tex_coords = [u + dx * cell_w, v + (1.0 - dy) * cell_h];
+                vertices.push(LabelVertex { position: point(x + dx * w, y + dy * h), tex_coords, color });
+            }
+            indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+        };
+
+        // The solid cell stretched behind the text, then the glyphs on it
+        let background = [BACKGROUND[0], BACKGROUND[1], BACKGROUND[2], BACKGROUND[3] * alpha];
+        quad((0.0, 0.0), (width, height), font::solid_cell(), background);
+        let color = [TEXT_COLOR[0], TEXT_COLOR[1], TEXT_COLOR[2], alpha];
+        for (i, c) in label.text.chars().enumerate() {
+            let x = PADDING + (i * GLYPH_ADVANCE) as f32;
+            quad((x, PADDING), (GLYPH_WIDTH as f32, GLYPH_HEIGHT as f32), font::atlas_cell(c), color);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Rustcraft, a voxel game. The `rustcraft` binary is the game client, which
+//! runs its own server in-process for singleplayer; `rustcraft-server` is the
+//! dedicated server.
+
+mod audio;
+mod block;
+mod block_entity;
+mod boat;
+mod buffer_pool;
+mod camera;
+mod camera_smoothing;
+mod catalog;
+mod chunk;
+mod chunk_loader;
+mod chunk_map;
+mod chunk_snapshot;
+mod circuit;
+pub mod cli;
+mod commands;
+mod compass;
+mod config;
+mod console;
+pub mod crash;
+mod debug;
+mod debug_render;
+#[cfg(feature = "debug-ui")]
+mod dev_tools;
+pub mod display;
+mod edit_history;
+mod enchanting;
+mod events;
+mod experience;
+mod explosion;
+mod fire;
+mod fishing;
+mod font;
+mod free_cam;
+pub mod game;
+mod game_state;
+mod gamerules;
+pub mod headless;
+mod input;
+mod icons;
+mod inventory;
+mod item;
+mod item_entity;
+mod labels;
+mod loot;
+pub mod logging;
+mod menu;
+mod mining;
+mod minimap;
+mod mesh;
+mod net;
+mod permissions;
+mod physics;
+mod player_stats;
+mod profiler;
+mod protocol;
+mod raycast;
+mod region;
+mod remote_players;
+mod renderer;
+mod resource_pack;
+mod schematic;
+mod scripting;
+mod server;
+mod session;
+mod skin;
+mod sleep;
+mod soundscape;
+pub mod storage;
+mod structures;
+mod survival;
+mod tick;
+mod time;
+mod toast;
+mod ui;
+mod vertex;
+mod villager;
+mod weather;
+mod world;
+mod world_border;
+mod world_gen;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests;
@@ -0,0 +1,121 @@
+//! Logging through the `log` crate to stderr and to `logs/latest.log`. The
+//! previous logs are kept as `latest.1.log` (newest) to `latest.5.log`, and a
+//! log growing past `MAX_LOG_SIZE` is rotated while the game runs.
+//!
+//! The level defaults to `info` for the game and `warn` for its dependencies;
+//! `RUST_LOG` overrides it as usual for `env_logger`.
+//!
+//! In a browser there are no files; the log goes to the developer console.
+
+// The log files are left unused in the browser
+#![cfg_attr(target_arch = "wasm32", allow(dead_code, unused_imports))]
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const LOGS_DIR: &str = "logs";
+const LATEST_LOG: &str = "latest.log";
+/// Where the current session's log is written, for error messages
+pub const LATEST_LOG_PATH: &str = "logs/latest.log";
+/// Rotated logs kept besides the current one
+const MAX_OLD_LOGS: usize = 5;
+/// Size at which the current log is rotated, 10 MB
+const MAX_LOG_SIZE: u64 = 10 * 1024 * 1024;
+const DEFAULT_FILTER: &str = "warn,rustcraft=info";
+
+/// Install the logger. A log file that can't be created leaves stderr only.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn init() {
+    let sink = LogSink::open(Path::new(LOGS_DIR));
+    let file_error = sink.file.is_none();
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(DEFAULT_FILTER))
+        .target(env_logger::Target::Pipe(Box::new(sink)))
+        .init();
+    if file_error {
+        log::warn!("Could not create {}/{}, logging to stderr only", LOGS_DIR, LATEST_LOG);
+    }
+
+    log::info!(
+        "Rustcraft {} on {} {} ({} threads)",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        std::thread::available_parallelism().map_or(1, |n| n.get())
+    );
+}
+
+/// Install the logger, which also shows panics in the developer console
+#[cfg(target_arch = "wasm32")]
+pub fn init() {
+    console_error_panic_hook::set_once();
+    console_log::init_with_level(log::Level::Info).ok();
+    log::info!("Rustcraft {} in the browser", env!("CARGO_PKG_VERSION"));
+}
+
+/// Shift `latest.log` to `latest.1.log`, `latest.1.log` to `latest.2.log` and
+/// so on, dropping the oldest beyond `keep`
+pub fn rotate(dir: &Path, keep: usize) {
+    let numbered = |n: usize| dir.join(format!("latest.{}.log", n));
+    if keep == 0 {
+        std::fs::remove_file(dir.join(LATEST_LOG)).ok();
+        return;
+    }
+    std::fs::remove_file(numbered(keep)).ok();
+    for n in (1..keep).rev() {
+        std::fs::rename(numbered(n), numbered(n + 1)).ok();
+    }
+    std::fs::rename(dir.join(LATEST_LOG), numbered(1)).ok();
+}
+
+/// Writes every record to stderr and the current log file
+struct LogSink {
+    dir: PathBuf,
+    file: Option<File>,
+    written: u64,
+}
+
+impl LogSink {
+    fn open(dir: &Path) -> Self {
+        let mut sink = Self {
+            dir: dir.to_path_buf(),
+            file: None,
+            written: 0,
+        };
+        sink.start_file();
+        sink
+    }
+
+    /// Rotate the old logs away and start a fresh `latest.log`
+    fn start_file(&mut self) {
+        self.file = std::fs::create_dir_all(&self.dir).ok().and_then(|_| {
+            rotate(&self.dir, MAX_OLD_LOGS);
+            File::create(self.dir.join(LATEST_LOG)).ok()
+        });
+        self.written = 0;
+    }
+}
+
+impl Write for LogSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::stderr().write_all(buf)?;
+        if self.written >= MAX_LOG_SIZE {
+            self.start_file();
+        }
+        if let Some(file) = &mut self.file {
+            // Losing the file must not take the console output with it
+            if file.write_all(buf).is_err() {
+                self.file = None;
+            }
+            self.written += buf.len() as u64;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(file) = &mut self.file {
+            file.flush().ok();
+        }
+        io::stderr().flush()
+    }
+}
@@ -0,0 +1,231 @@
+//! Loot tables: what the containers the generator fills (dungeon chests for
+//! now) hold, and what fishing catches. The built-in tables can be replaced, and new ones added, by
+//! `loot_tables/<name>.json` files loaded at startup:
+//!
+//! ```text
+//! {
+//!     "rolls": [2, 4],
+//!     "entries": [
+//!         { "item": "planks", "weight": 5, "count": [4, 16] },
+//!         { "item": "compass" }
+//!     ]
+//! }
+//! ```
+//!
+//! Each roll draws one entry, more likely the higher its weight (default 1),
+//! with a count between the two bounds (default 1). Rolls are seeded, so the
+//! same chest of the same world always gets the same loot.
+
+use crate::block::BlockType;
+use crate::inventory::ItemStack;
+use crate::item::Item;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Folder scanned for loot tables at startup
+pub const LOOT_TABLES_DIR: &str = "loot_tables";
+/// Table filling the chest of each dungeon
+pub const DUNGEON_CHEST: &str = "dungeon_chest";
+/// Table of a catch reeled in while a fish bites
+pub const FISHING: &str = "fishing";
+
+/// One item a roll may draw
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LootEntry {
+    pub item: Item,
+    pub weight: u32,
+    /// Fewest and most items of a draw
+    pub count: (u32, u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LootTable {
+    /// Fewest and most draws
+    pub rolls: (u32, u32),
+    pub entries: Vec<LootEntry>,
+}
+
+/// A loot table as written in JSON, with item names
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LootTableFile {
+    rolls: (u32, u32),
+    entries: Vec<LootEntryFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LootEntryFile {
+    item: String,
+    #[serde(default = "one")]
+    weight: u32,
+    #[serde(default = "one_each")]
+    count: (u32, u32),
+}
+
+fn one() -> u32 {
+    1
+}
+
+fn one_each() -> (u32, u32) {
+    (1, 1)
+}
+
+impl LootTable {
+    /// Parse a table from JSON, checking the item names and ranges
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let file: LootTableFile = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let ordered = |(min, max): (u32, u32)| min <= max;
+        if !ordered(file.rolls) {
+            return Err(format!("rolls {:?} run backwards", file.rolls));
+        }
+        let entries = file
+            .entries
+            .into_iter()
+            .map(|entry| {
+                let item = Item::from_name(&entry.item).ok_or_else(|| format!("no item named '{}'", entry.item))?;
+                if !ordered(entry.count) {
+                    return Err(format!("count {:?} of {} runs backwards", entry.count, entry.item));
+                }
+                Ok(LootEntry { item, weight: entry.weight, count: entry.count })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        if file.rolls.1 > 0 && entries.iter().all(|entry| entry.weight == 0) {
+            return Err("no entry has any weight".to_string());
+        }
+        Ok(Self { rolls: file.rolls, entries })
+    }
+
+    /// The stacks drawn with this seed, each at most a full stack
+    pub fn roll(&self, seed: u32) -> Vec<ItemStack> {
+        let mut rng = LootRng::new(seed);
+        let total: u32 = self.entries.iter().map(|entry| entry.weight).sum();
+        let rolls = rng.between(self.rolls);
+        let mut stacks = Vec::new();
+        if total == 0 {
+            return stacks;
+        }
+        for _ in 0..rolls {
+            let mut pick = rng.next() % total;
+            let Some(entry) = self.entries.iter().find(|entry| {
+                let hit = pick < entry.weight;
+                pick = pick.saturating_sub(entry.weight);
+                hit
+            }) else {
+                continue;
+            };
+            let count = rng.between(entry.count).min(entry.item.max_stack_size());
+            if count > 0 {
+                stacks.push(ItemStack::new(entry.item, count));
+            }
+        }
+        stacks
+    }
+
+    /// Roll the loot into empty slots picked with the same seed, so it is
+    /// spread over the container; stacks beyond the free slots are dropped
+    pub fn fill(&self, slots: &mut [Option<ItemStack>], seed: u32) {
+        let mut rng = LootRng::new(seed ^ 0x5107_5EED);
+        for stack in self.roll(seed) {
+            let free: Vec<usize> = (0..slots.len()).filter(|&i| slots[i].is_none()).collect();
+            if free.is_empty() {
+                return;
+            }
+            slots[free[rng.next() as usize % free.len()]] = Some(stack);
+        }
+    }
+}
+
+/// The loot tables by name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LootTables {
+    tables: HashMap<String, LootTable>,
+}
+
+impl LootTables {
+    /// The tables the game ships with
+    pub fn builtin() -> Self {
+        let dungeon = LootTable {
+            rolls: (2, 5),
+            entries: vec![
+                LootEntry { item: Item::Block(BlockType::Planks), weight: 6, count: (4, 16) },
+                LootEntry { item: Item::Block(BlockType::Glass), weight: 4, count: (2, 8) },
+                LootEntry { item: Item::Bucket, weight: 3, count: (1, 1) },
+                LootEntry { item: Item::FlintAndSteel, weight: 2, count: (1, 1) },
+                LootEntry { item: Item::Compass, weight: 1, count: (1, 1) },
+            ],
+        };
+        // Mostly fish, sometimes junk, rarely something useful
+        let fishing = LootTable {
+            rolls: (1, 1),
+            entries: vec![
+                LootEntry { item: Item::Fish, weight: 12, count: (1, 1) },
+                LootEntry { item: Item::Stick, weight: 3, count: (1, 3) },
+                LootEntry { item: Item::Seeds, weight: 2, count: (1, 2) },
+                LootEntry { item: Item::Bucket, weight: 1, count: (1, 1) },
+                LootEntry { item: Item::Compass, weight: 1, count: (1, 1) },
+            ],
+        };
+        Self { tables: HashMap::from([(DUNGEON_CHEST.to_string(), dungeon), (FISHING.to_string(), fishing)]) }
+    }
+
+    /// The built-in tables, replaced or joined by every `<name>.json` in
+    /// `dir` (a missing folder means none). Returns the tables and one
+    /// message per file that failed.
+    pub fn load(dir: &Path) -> (Self, Vec<String>) {
+        let mut tables = Self::builtin();
+        let mut errors = Vec::new();
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return (tables, errors);
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let name = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default().to_string();
+            let result = std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|json| LootTable::from_json(&json));
+            match result {
+                Ok(table) => {
+                    tables.tables.insert(name, table);
+                }
+                Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+        (tables, errors)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&LootTable> {
+        self.tables.get(name)
+    }
+}
+
+impl Default for LootTables {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// Xorshift generator behind the rolls
+struct LootRng(u32);
+
+impl LootRng {
+    fn new(seed: u32) -> Self {
+        // Xorshift never leaves zero
+        Self(seed.wrapping_mul(0x9E37_79B9) | 1)
+    }
+
+    fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    /// Uniform in `min..=max`
+    fn between(&mut self, (min, max): (u32, u32)) -> u32 {
+        min + (self.next() as u64 % ((max - min) as u64 + 1)) as u32
+    }
+}
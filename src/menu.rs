@@ -0,0 +1,416 @@
+use crate::config::GameConfig;
+
+// Widget layout in UI virtual pixels: a centered column of equally sized buttons/sliders
+pub const WIDGET_WIDTH: f32 = 200.0;
+pub const WIDGET_HEIGHT: f32 = 20.0;
+const WIDGET_GAP: f32 = 4.0;
+/// The Options screen has two narrower columns, with its Done button below
+const OPTION_WIDTH: f32 = 150.0;
+const OPTION_COLUMN_GAP: f32 = 10.0;
+const COLUMN_TOP: f32 = 96.0;
+/// Baseline of the screen title, above the widget column
+pub const TITLE_Y: f32 = COLUMN_TOP + 8.0;
+/// Widget rows the lines of the Statistics screen take, above its Done button
+pub const STATISTICS_ROWS: usize = 4;
+
+/// Default mouse sensitivity, shown as 100% on the sensitivity slider
+const SENSITIVITY_REFERENCE: f32 = 0.005;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuScreen {
+    /// Title screen
+    Main,
+    Pause,
+    Options,
+    Death,
+    Statistics,
+    /// In bed
+    Sleeping,
+}
+
+impl MenuScreen {
+    pub fn title(self) -> &'static str {
+        match self {
+            MenuScreen::Main => "Rustcraft",
+            MenuScreen::Pause => "Game Menu",
+            MenuScreen::Options => "Options",
+            MenuScreen::Death => "You Died!",
+            MenuScreen::Statistics => "Statistics",
+            MenuScreen::Sleeping => "Sleeping",
+        }
+    }
+
+    /// Buttons of the screen, top to bottom (the Options and Statistics
+    /// screens have their own widgets)
+    pub fn buttons(self) -> &'static [MenuButton] {
+        match self {
+            MenuScreen::Main => &[MenuButton::Play, MenuButton::Options, MenuButton::Quit],
+            MenuScreen::Pause => &[MenuButton::Resume, MenuButton::Options, MenuButton::Statistics, MenuButton::SaveAndQuit],
+            MenuScreen::Options | MenuScreen::Statistics => &[],
+            MenuScreen::Death => &[MenuButton::Respawn, MenuButton::SaveAndQuit],
+            MenuScreen::Sleeping => &[MenuButton::LeaveBed],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuButton {
+    Play,
+    Resume,
+    Options,
+    Statistics,
+    SaveAndQuit,
+    Respawn,
+    LeaveBed,
+    Quit,
+}
+
+impl MenuButton {
+    pub fn label(self) -> &'static str {
+        match self {
+            MenuButton::Play => "Play",
+            MenuButton::Resume => "Back to Game",
+            MenuButton::Options => "Options...",
+            MenuButton::Statistics => "Statistics...",
+            MenuButton::SaveAndQuit => "Save and Quit",
+            MenuButton::Respawn => "Respawn",
+            MenuButton::LeaveBed => "Leave Bed",
+            MenuButton::Quit => "Quit Game",
+        }
+    }
+}
+
+/// A setting exposed on the Options screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionId {
+    Fov,
+    Sensitivity,
+    ViewDistance,
+    Vsync,
+    Fog,
+    ShowDebug,
+    GuiScale,
+    Volume,
+    Graphics,
+    SmoothLighting,
+    Mipmapping,
+    Particles,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OptionKind {
+    Slider { min: f32, max: f32, step: f32 },
+    /// Switches to the next value when clicked
+    Toggle,
+}
+
+impl OptionId {
+    /// In the order of the left column, then the right one
+    pub const ALL: [OptionId; 12] = [
+        OptionId::Fov,
+        OptionId::Sensitivity,
+        OptionId::GuiScale,
+        OptionId::Volume,
+        OptionId::Vsync,
+        OptionId::ShowDebug,
+        OptionId::Graphics,
+        OptionId::ViewDistance,
+        OptionId::Fog,
+        OptionId::SmoothLighting,
+        OptionId::Mipmapping,
+        OptionId::Particles,
+    ];
+
+    pub fn kind(self) -> OptionKind {
+        match self {
+            OptionId::Fov => OptionKind::Slider { min: 30.0, max: 110.0, step: 1.0 },
+            OptionId::Sensitivity => OptionKind::Slider { min: 0.001, max: 0.02, step: 0.0005 },
+            OptionId::ViewDistance => OptionKind::Slider { min: 2.0, max: 16.0, step: 1.0 },
+            OptionId::GuiScale => OptionKind::Slider { min: 0.0, max: 6.0, step: 1.0 },
+            OptionId::Volume => OptionKind::Slider { min: 0.0, max: 1.0, step: 0.05 },
+            OptionId::Vsync
+            | OptionId::Fog
+            | OptionId::ShowDebug
+            | OptionId::Graphics
+            | OptionId::SmoothLighting
+            | OptionId::Mipmapping
+            | OptionId::Particles => OptionKind::Toggle,
+        }
+    }
+
+    /// Current value as a number (on/off toggles are 0 or 1, the others count
+    /// their values from 0)
+    pub fn value(self, config: &GameConfig) -> f32 {
+        match self {
+            OptionId::Fov => config.fov,
+            OptionId::Sensitivity => config.sensitivity,
+            OptionId::ViewDistance => config.view_distance as f32,
+            OptionId::GuiScale => config.gui_scale as f32,
+            OptionId::Volume => config.master_volume,
+            OptionId::Vsync => config.vsync as u8 as f32,
+            OptionId::Fog => config.fog as u8 as f32,
+            OptionId::ShowDebug => config.show_debug as u8 as f32,
+            OptionId::Graphics => config.graphics_preset() as u8 as f32,
+            OptionId::SmoothLighting => config.smooth_lighting as u8 as f32,
+            OptionId::Mipmapping => config.mipmapping as u8 as f32,
+            OptionId::Particles => config.particles as u8 as f32,
+        }
+    }
+
+    /// Set a slider value, clamped to its range and snapped to its step
+    pub fn set_value(self, config: &mut GameConfig, value: f32) {
+        let OptionKind::Slider { min, max, step } = self.kind() else {
+            return;
+        };
+        let value = (min + ((value - min) / step).round() * step).clamp(min, max);
+        match self {
+            OptionId::Fov => config.fov = value,
+            OptionId::Sensitivity => config.sensitivity = value,
+            OptionId::ViewDistance => config.view_distance = value as i32,
+            OptionId::GuiScale => config.gui_scale = value as u32,
+            OptionId::Volume => config.master_volume = value,
+            _ => {}
+        }
+    }
+
+    pub fn toggle(self, config: &mut GameConfig) {
+        match self {
+            OptionId::Vsync => config.vsync = !config.vsync,
+            OptionId::Fog => config.fog = !config.fog,
+            OptionId::ShowDebug => config.show_debug = !config.show_debug,
+            OptionId::Graphics => config.set_graphics_preset(config.graphics_preset().next()),
+            OptionId::SmoothLighting => config.smooth_lighting = !config.smooth_lighting,
+            OptionId::Mipmapping => config.mipmapping = !config.mipmapping,
+            OptionId::Particles => config.particles = config.particles.next(),
+            _ => {}
+        }
+    }
+
+    /// Slider fill fraction in [0, 1]
+    pub fn fraction(self, config: &GameConfig) -> f32 {
+        match self.kind() {
+            OptionKind::Slider { min, max, .. } => ((self.value(config) - min) / (max - min)).clamp(0.0, 1.0),
+            OptionKind::Toggle => self.value(config),
+        }
+    }
+
+    /// Widget label including the current value, e.g. "FOV: 70"
+    pub fn label(self, config: &GameConfig) -> String {
+        let on_off = |b: bool| if b { "ON" } else { "OFF" };
+        match self {
+            OptionId::Fov => format!("FOV: {:.0}", config.fov),
+            OptionId::Sensitivity => format!("Sensitivity: {:.0}%", config.sensitivity / SENSITIVITY_REFERENCE * 100.0),
+            OptionId::ViewDistance => format!("View Distance: {} chunks", config.view_distance),
+            OptionId::GuiScale if config.gui_scale == 0 => "GUI Scale: Auto".to_string(),
+            OptionId::GuiScale => format!("GUI Scale: {}", config.gui_scale),
+            OptionId::Volume if config.master_volume == 0.0 => "Volume: OFF".to_string(),
+            OptionId::Volume => format!("Volume: {:.0}%", config.master_volume * 100.0),
+            OptionId::Vsync => format!("VSync: {}", on_off(config.vsync)),
+            OptionId::Fog => format!("Fog: {}", on_off(config.fog)),
+            OptionId::ShowDebug => format!("Debug Info: {}", on_off(config.show_debug)),
+            OptionId::Graphics => format!("Graphics: {}", config.graphics_preset().name()),
+            OptionId::SmoothLighting => format!("Smooth Lighting: {}", on_off(config.smooth_lighting)),
+            OptionId::Mipmapping => format!("Mipmaps: {}", on_off(config.mipmapping)),
+            OptionId::Particles => format!("Particles: {}", config.particles.name()),
+        }
+    }
+}
+
+/// Something clickable on a menu screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuWidget {
+    Button(MenuButton),
+    Option(OptionId),
+    Done,
+}
+
+/// What the game should do after the menu handled input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuAction {
+    None,
+    /// Menu contents changed and need redrawing
+    Redraw,
+    Resume,
+    /// Start the game from the title screen
+    Play,
+    Respawn,
+    LeaveBed,
+    SaveAndQuit,
+    /// Quit from the title screen, before a world was entered
+    Quit,
+    /// A setting changed; `save` is set once the change is final (toggle or slider release)
+    ConfigChanged { save: bool },
+}
+
+/// Title, pause, death, sleeping and Options screen state
+pub struct Menu {
+    screen: Option<MenuScreen>,
+    /// Screen the Options screen was opened from, for Done / Escape
+    options_parent: MenuScreen,
+    /// Slider being dragged with the mouse
+    dragging: Option<OptionId>,
+}
+
+impl Menu {
+    pub fn new() -> Self {
+        Self {
+            screen: None,
+            options_parent: MenuScreen::Pause,
+            dragging: None,
+        }
+    }
+
+    pub fn screen(&self) -> Option<MenuScreen> {
+        self.screen
+    }
+
+    /// Screen that Done / Escape on the Options screen returns to
+    pub fn options_parent(&self) -> MenuScreen {
+        self.options_parent
+    }
+
+    pub fn open(&mut self, screen: MenuScreen) {
+        self.screen = Some(screen);
+        self.dragging = None;
+    }
+
+    pub fn close(&mut self) {
+        self.screen = None;
+        self.dragging = None;
+    }
+
+    /// Escape: Options goes back to the screen it came from, Statistics to
+    /// the pause screen, the pause screen resumes the game and the sleeping
+    /// screen leaves the bed; the title and death screens stay
+    pub fn back(&mut self) -> MenuAction {
+        self.dragging = None;
+        match self.screen {
+            Some(MenuScreen::Options) => {
+                self.screen = Some(self.options_parent);
+                MenuAction::Redraw
+            }
+            Some(MenuScreen::Statistics) => {
+                self.screen = Some(MenuScreen::Pause);
+                MenuAction::Redraw
+            }
+            Some(MenuScreen::Pause) => {
+                self.close();
+                MenuAction::Resume
+            }
+            Some(MenuScreen::Sleeping) => MenuAction::LeaveBed,
+            Some(MenuScreen::Main) | Some(MenuScreen::Death) | None => MenuAction::None,
+        }
+    }
+
+    /// Widgets on the current screen with their (x, y, width, height) rectangle,
+    /// (x, y) being the bottom-left corner
+    pub fn widgets(&self) -> Vec<(MenuWidget, (f32, f32, f32, f32))> {
+        // The lines of the Statistics screen go where its first rows would be
+        let first_row = if self.screen == Some(MenuScreen::Statistics) { STATISTICS_ROWS } else { 0 };
+        let row_y = |i: usize| COLUMN_TOP - (i + 1) as f32 * WIDGET_HEIGHT - i as f32 * WIDGET_GAP;
+        let widgets: Vec<MenuWidget> = match self.screen {
+            Some(MenuScreen::Options) => {
+                let rows = OptionId::ALL.len().div_ceil(2);
+                let mut widgets: Vec<_> = OptionId::ALL
+                    .iter()
+                    .enumerate()
+                    .map(|(i, option)| {
+                        let x = if i < rows { -OPTION_WIDTH - OPTION_COLUMN_GAP / 2.0 } else { OPTION_COLUMN_GAP / 2.0 };
+                        (MenuWidget::Option(*option), (x, row_y(i % rows), OPTION_WIDTH, WIDGET_HEIGHT))
+                    })
+                    .collect();
+                widgets.push((MenuWidget::Done, (-WIDGET_WIDTH / 2.0, row_y(rows), WIDGET_WIDTH, WIDGET_HEIGHT)));
+                return widgets;
+            }
+            Some(MenuScreen::Statistics) => vec![MenuWidget::Done],
+            Some(screen) => screen.buttons().iter().map(|b| MenuWidget::Button(*b)).collect(),
+            None => Vec::new(),
+        };
+
+        widgets
+            .into_iter()
+            .enumerate()
+            .map(|(i, widget)| (widget, (-WIDGET_WIDTH / 2.0, row_y(i + first_row), WIDGET_WIDTH, WIDGET_HEIGHT)))
+            .collect()
+    }
+
+    /// Widget under a point in UI virtual pixels
+    pub fn widget_at(&self, x: f32, y: f32) -> Option<MenuWidget> {
+        self.widgets()
+            .into_iter()
+            .find(|(_, (wx, wy, w, h))| x >= *wx && x < wx + w && y >= *wy && y < wy + h)
+            .map(|(widget, _)| widget)
+    }
+
+    /// Handle a mouse press at (x, y) in UI virtual pixels
+    pub fn press(&mut self, x: f32, y: f32, config: &mut GameConfig) -> MenuAction {
+        match self.widget_at(x, y) {
+            Some(MenuWidget::Button(MenuButton::Resume)) => {
+                self.close();
+                MenuAction::Resume
+            }
+            Some(MenuWidget::Button(MenuButton::Options)) => {
+                self.options_parent = self.screen.unwrap_or(MenuScreen::Pause);
+                self.screen = Some(MenuScreen::Options);
+                MenuAction::Redraw
+            }
+            Some(MenuWidget::Button(MenuButton::Statistics)) => {
+                self.screen = Some(MenuScreen::Statistics);
+                MenuAction::Redraw
+            }
+            Some(MenuWidget::Button(MenuButton::Play)) => MenuAction::Play,
+            Some(MenuWidget::Button(MenuButton::Respawn)) => MenuAction::Respawn,
+            Some(MenuWidget::Button(MenuButton::LeaveBed)) => MenuAction::LeaveBed,
+            Some(MenuWidget::Button(MenuButton::SaveAndQuit)) => MenuAction::SaveAndQuit,
+            Some(MenuWidget::Button(MenuButton::Quit)) => MenuAction::Quit,
+            Some(MenuWidget::Done) => self.back(),
+            Some(MenuWidget::Option(option)) => match option.kind() {
+                OptionKind::Toggle => {
+                    option.toggle(config);
+                    MenuAction::ConfigChanged { save: true }
+                }
+                OptionKind::Slider { .. } => {
+                    self.dragging = Some(option);
+                    self.drag(x, config)
+                }
+            },
+            None => MenuAction::None,
+        }
+    }
+
+    /// Cursor moved to virtual-pixel x: update the slider being dragged, if any
+    pub fn drag(&mut self, x: f32, config: &mut GameConfig) -> MenuAction {
+        let Some(option) = self.dragging else {
+            return MenuAction::None;
+        };
+        let OptionKind::Slider { min, max, .. } = option.kind() else {
+            return MenuAction::None;
+        };
+        let Some((_, (left, _, width, _))) = self.widgets().into_iter().find(|(widget, _)| *widget == MenuWidget::Option(option)) else {
+            return MenuAction::None;
+        };
+
+        let t = ((x - left) / width).clamp(0.0, 1.0);
+        let before = option.value(config);
+        option.set_value(config, min + t * (max - min));
+        if option.value(config) != before {
+            MenuAction::ConfigChanged { save: false }
+        } else {
+            MenuAction::None
+        }
+    }
+
+    /// Mouse released: finish dragging a slider
+    pub fn release(&mut self) -> MenuAction {
+        match self.dragging.take() {
+            Some(_) => MenuAction::ConfigChanged { save: true },
+            None => MenuAction::None,
+        }
+    }
+}
+
+impl Default for Menu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,79 +1,128 @@
-use crate::block::BlockType;
-use crate::chunk::{Chunk, CHUNK_HEIGHT, CHUNK_SIZE};
+use crate::block::{BlockType, MAX_LIGHT};
+use crate::chunk::{CHUNK_HEIGHT, CHUNK_SIZE};
+use crate::chunk_snapshot::ChunkSnapshot;
+use crate::resource_pack::AtlasLayout;
 use crate::vertex::Vertex;
-use crate::world::World;
+
+/// Triangle indices of a mesh: 16 bits wide while every vertex can be
+/// addressed that way, which halves their size, and 32 bits otherwise
+#[derive(Clone, Debug, PartialEq)]
+pub enum MeshIndices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl MeshIndices {
+    /// No indices yet, wide enough for a mesh of `vertex_count` vertices
+    pub fn for_vertices(vertex_count: usize) -> Self {
+        if fits_u16(vertex_count) {
+            Self::U16(Vec::new())
+        } else {
+            Self::U32(Vec::new())
+        }
+    }
+
+    /// The `indices` of a mesh of `vertex_count` vertices, narrowed if they fit
+    pub fn new(indices: Vec<u32>, vertex_count: usize) -> Self {
+        if fits_u16(vertex_count) {
+            Self::U16(indices.into_iter().map(|index| index as u16).collect())
+        } else {
+            Self::U32(indices)
+        }
+    }
+
+    pub fn push(&mut self, index: u32) {
+        match self {
+            Self::U16(indices) => indices.push(index as u16),
+            Self::U32(indices) => indices.push(index),
+        }
+    }
+
+    /// Append the indices of another mesh whose vertices start at `offset`
+    pub fn extend_offset(&mut self, other: &MeshIndices, offset: u32) {
+        match other {
+            Self::U16(indices) => indices.iter().for_each(|&index| self.push(index as u32 + offset)),
+            Self::U32(indices) => indices.iter().for_each(|&index| self.push(index + offset)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::U16(indices) => indices.len(),
+            Self::U32(indices) => indices.len(),
+        }
+    }
+
+    /// Pad 16-bit indices to a whole number of 4-byte words, as buffer
+    /// uploads need. The padding index is never drawn.
+    pub fn pad_to_words(&mut self) {
+        if let Self::U16(indices) = self {
+            if indices.len() % 2 == 1 {
+                indices.push(0);
+            }
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::U16(indices) => bytemuck::cast_slice(indices),
+            Self::U32(indices) => bytemuck::cast_slice(indices),
+        }
+    }
+
+}
+
+/// Whether 16-bit indices address all of `vertex_count` vertices
+fn fits_u16(vertex_count: usize) -> bool {
+    vertex_count <= u16::MAX as usize + 1
+}
 
 pub struct MeshBuilder {
     pub vertices: Vec<Vertex>,
     pub indices: Vec<u32>,
+    /// Blend light across faces; otherwise each face is lit evenly by the
+    /// cell in front of it
+    pub smooth_lighting: bool,
 }
 
-const ATLAS_COLS: u32 = 9;      // number of tiles horizontally in atlas — set to your atlas layout
-const ATLAS_ROWS: u32 = 1;      // number of tiles vertically in atlas
-const TILE_PX: f32 = 16.0;
-
 impl MeshBuilder {
     pub fn new() -> Self {
         Self {
             vertices: Vec::new(),
             indices: Vec::new(),
+            smooth_lighting: true,
         }
     }
 
+    #[allow(dead_code)]
     pub fn clear(&mut self) {
         self.vertices.clear();
         self.indices.clear();
     }
 
+    /// Block next to local cell (cx, cy, cz), offset by (dx, dy, dz)
     #[allow(clippy::too_many_arguments)]
-    fn get_block_at(&self, world: &World, chunk: &Chunk, cx: usize, cy: usize, cz: usize, dx: i32, dy: i32, dz: i32) -> BlockType {
-        let x = cx as i32 + dx;
-        let y = cy as i32 + dy;
-        let z = cz as i32 + dz;
-
-        // Check if still within current chunk
-        if x >= 0 && x < CHUNK_SIZE as i32 && y >= 0 && y < CHUNK_HEIGHT as i32 && z >= 0 && z < CHUNK_SIZE as i32 {
-            return chunk.get_block(x as usize, y as usize, z as usize);
-        }
-
-        // Check if out of world height bounds
-        if y < 0 || y >= CHUNK_HEIGHT as i32 {
-            return BlockType::Air;
-        }
-
-        // Calculate which chunk the neighbor is in
-        let world_x = chunk.x * CHUNK_SIZE as i32 + x;
-        let world_z = chunk.z * CHUNK_SIZE as i32 + z;
-        let neighbor_chunk_x = world_x.div_euclid(CHUNK_SIZE as i32);
-        let neighbor_chunk_z = world_z.div_euclid(CHUNK_SIZE as i32);
-        let local_x = world_x.rem_euclid(CHUNK_SIZE as i32) as usize;
-        let local_z = world_z.rem_euclid(CHUNK_SIZE as i32) as usize;
-
-        // Get block from neighbor chunk
-        if let Some(neighbor_chunk) = world.get_chunk(neighbor_chunk_x, neighbor_chunk_z) {
-            neighbor_chunk.get_block(local_x, y as usize, local_z)
-        } else {
-            BlockType::Air
-        }
+    fn get_block_at(&self, snapshot: &ChunkSnapshot, cx: usize, cy: usize, cz: usize, dx: i32, dy: i32, dz: i32) -> BlockType {
+        snapshot.get_block(cx as i32 + dx, cy as i32 + dy, cz as i32 + dz)
     }
 
-    pub fn build_chunk_mesh(&mut self, chunk: &Chunk, world: &World) {
+    pub fn build_chunk_mesh(&mut self, snapshot: &ChunkSnapshot, atlas: &AtlasLayout) {
         for x in 0..CHUNK_SIZE {
             for y in 0..CHUNK_HEIGHT {
                 for z in 0..CHUNK_SIZE {
-                    let block = chunk.get_block(x, y, z);
+                    let block = snapshot.get_block(x as i32, y as i32, z as i32);
                     if block.is_solid() {
-                        let world_x = (chunk.x * CHUNK_SIZE as i32 + x as i32) as f32;
+                        let world_x = (snapshot.x * CHUNK_SIZE as i32 + x as i32) as f32;
                         let world_y = y as f32;
-                        let world_z = (chunk.z * CHUNK_SIZE as i32 + z as i32) as f32;
+                        let world_z = (snapshot.z * CHUNK_SIZE as i32 + z as i32) as f32;
 
                         self.add_block_faces(
                             world_x,
                             world_y,
                             world_z,
                             block,
-                            chunk,
-                            world,
+                            atlas,
+                            snapshot,
                             x,
                             y,
                             z,
@@ -91,31 +140,59 @@ impl MeshBuilder {
         y: f32,
         z: f32,
         block: BlockType,
-        chunk: &Chunk,
-        world: &World,
+        atlas: &AtlasLayout,
+        snapshot: &ChunkSnapshot,
         cx: usize,
         cy: usize,
         cz: usize,
     ) {
         let color = block.get_color();
-        let tile = block.atlas_coords().unwrap_or((0, 0));
+        // Each corner of a face is lit by the light around it on the side the
+        // face looks at; light sources glow all over
+        let cell = [x as i32, y as i32, z as i32];
+        let glow = block.light_emission() as f32 / MAX_LIGHT as f32;
+        let smooth = self.smooth_lighting;
+        let light = |normal: [i32; 3]| {
+            move |corner: [f32; 3]| match (glow > 0.0, smooth) {
+                (true, _) => glow,
+                (false, true) => smooth_light(snapshot.lights(), cell, normal, corner),
+                (false, false) => block_light(snapshot.lights(), [cell[0] + normal[0], cell[1] + normal[1], cell[2] + normal[2]]),
+            }
+        };
+        if block.is_plant() {
+            self.add_plant_quads(x, y, z, block, atlas, light([0, 1, 0]));
+            return;
+        }
+        if block == BlockType::OpenDoor {
+            self.add_open_door(x, y, z, block, atlas, light([1, 0, 0]));
+            return;
+        }
+        // Layers and the water surface are lower than a full block: their
+        // sides are as high as the block and they always show their top. No
+        // faces show between two of the same transparent block, so water and
+        // glass are drawn without inner walls.
+        let above = self.get_block_at(snapshot, cx, cy, cz, 0, 1, 0);
+        let height = if block.is_liquid() && above == block { 1.0 } else { block.height() };
+        let up = [0.0, height, 0.0];
+        let shows = |neighbor: BlockType| neighbor.is_transparent() && neighbor != block;
 
         // Top face
-        if self.get_block_at(world, chunk, cx, cy, cz, 0, 1, 0).is_transparent() {
+        if height < 1.0 || shows(above) {
             self.add_face(
                 x,
-                y + 1.0,
+                y + height,
                 z,
                 [0.0, 0.0, 1.0],
                 [1.0, 0.0, 0.0],
                 color,
                 1.0,
-                tile,
+                light([0, 1, 0]),
+                self.face_uv(snapshot, (cx, cy, cz), block, atlas, [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]),
             );
         }
 
         // Bottom face
-        if self.get_block_at(world, chunk, cx, cy, cz, 0, -1, 0).is_transparent() {
+        if shows(self.get_block_at(snapshot, cx, cy, cz, 0, -1, 0)) {
             self.add_face(
                 x,
                 y,
@@ -124,67 +201,119 @@ impl MeshBuilder {
                 [0.0, 0.0, 1.0],
                 color,
                 0.5,
-                tile,
+                light([0, -1, 0]),
+                self.face_uv(snapshot, (cx, cy, cz), block, atlas, [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
             );
         }
 
         // Front face (+Z)
-        if self.get_block_at(world, chunk, cx, cy, cz, 0, 0, 1).is_transparent() {
+        if shows(self.get_block_at(snapshot, cx, cy, cz, 0, 0, 1)) {
             self.add_face(
                 x,
                 y,
                 z + 1.0,
                 [1.0, 0.0, 0.0],
-                [0.0, 1.0, 0.0],
+                up,
                 color,
                 0.8,
-                tile,
+                light([0, 0, 1]),
+                self.face_uv(snapshot, (cx, cy, cz), block, atlas, [1.0, 0.0, 0.0], up),
             );
         }
 
         // Back face (-Z)
-        if self.get_block_at(world, chunk, cx, cy, cz, 0, 0, -1).is_transparent() {
+        if shows(self.get_block_at(snapshot, cx, cy, cz, 0, 0, -1)) {
             self.add_face(
                 x,
                 y,
                 z,
-                [0.0, 1.0, 0.0],
+                up,
                 [1.0, 0.0, 0.0],
                 color,
                 0.8,
-                tile,
+                light([0, 0, -1]),
+                self.face_uv(snapshot, (cx, cy, cz), block, atlas, up, [1.0, 0.0, 0.0]),
             );
         }
 
         // Right face (+X)
-        if self.get_block_at(world, chunk, cx, cy, cz, 1, 0, 0).is_transparent() {
+        if shows(self.get_block_at(snapshot, cx, cy, cz, 1, 0, 0)) {
             self.add_face(
                 x + 1.0,
                 y,
                 z + 1.0,    
                 [0.0, 0.0, -1.0], // changed to point u so u x v = +X (outward)
-                [0.0, 1.0, 0.0],
+                up,
                 color,
                 0.7,
-                tile,
+                light([1, 0, 0]),
+                self.face_uv(snapshot, (cx, cy, cz), block, atlas, [0.0, 0.0, -1.0], up),
             );
         }
 
         // Left face (-X)
-        if self.get_block_at(world, chunk, cx, cy, cz, -1, 0, 0).is_transparent() {
+        if shows(self.get_block_at(snapshot, cx, cy, cz, -1, 0, 0)) {
             self.add_face(
                 x,
                 y,
                 z,
                 [0.0, 0.0, 1.0], // changed so u x v = -X (outward for left face)
-                [0.0, 1.0, 0.0],
+                up,
                 color,
                 0.7,
-                tile,
+                light([-1, 0, 0]),
+                self.face_uv(snapshot, (cx, cy, cz), block, atlas, [0.0, 0.0, 1.0], up),
             );
         }
     }
 
+    /// Two quads crossing diagonally through the cell, each drawn from both
+    /// sides, as high as the plant
+    fn add_plant_quads(&mut self, x: f32, y: f32, z: f32, block: BlockType, atlas: &AtlasLayout, light: impl Fn([f32; 3]) -> f32) {
+        let color = block.get_color();
+        let uv = atlas.tile_uv(block).unwrap_or_default();
+        let up = [0.0, block.height(), 0.0];
+        for (x, z, across) in [(x, z, [1.0, 0.0, 1.0]), (x + 1.0, z, [-1.0, 0.0, 1.0])] {
+            self.add_face(x, y, z, across, up, color, 0.9, &light, uv);
+            self.add_face(x, y, z, up, across, color, 0.9, &light, uv);
+        }
+    }
+
+    /// An open door swung against the side of its cell: a thin panel drawn
+    /// from both sides
+    fn add_open_door(&mut self, x: f32, y: f32, z: f32, block: BlockType, atlas: &AtlasLayout, light: impl Fn([f32; 3]) -> f32) {
+        let color = block.get_color();
+        let uv = atlas.tile_uv(block).unwrap_or_default();
+        let (along, up) = ([0.0, 0.0, 1.0], [0.0, 1.0, 0.0]);
+        self.add_face(x + 0.125, y, z, along, up, color, 0.7, &light, uv);
+        self.add_face(x + 0.125, y, z, up, along, color, 0.7, &light, uv);
+    }
+
+    /// UV rectangle of a face spanned by `u` and `v` of the block in cell
+    /// (cx, cy, cz): with a connected texture, the tile joining it to the
+    /// faces of the same block next to it
+    #[allow(clippy::too_many_arguments)]
+    fn face_uv(
+        &self,
+        snapshot: &ChunkSnapshot,
+        (cx, cy, cz): (usize, usize, usize),
+        block: BlockType,
+        atlas: &AtlasLayout,
+        u: [f32; 3],
+        v: [f32; 3],
+    ) -> [f32; 4] {
+        if !atlas.is_connected(block) {
+            return atlas.tile_uv(block).unwrap_or_default();
+        }
+        let direction = |axis: [f32; 3], sign: i32| axis.map(|c| sign * ((c > 0.0) as i32 - (c < 0.0) as i32));
+        // The top of the tile is along -v, its left side along -u
+        let edges = [(direction(v, -1), 1), (direction(u, 1), 2), (direction(v, 1), 4), (direction(u, -1), 8)]
+            .into_iter()
+            .filter(|&([dx, dy, dz], _)| self.get_block_at(snapshot, cx, cy, cz, dx, dy, dz) == block)
+            .fold(0, |edges, (_, edge)| edges | edge);
+        atlas.connected_tile_uv(block, edges).unwrap_or_default()
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn add_face(
         &mut self,
@@ -195,7 +324,8 @@ impl MeshBuilder {
         v: [f32; 3],
         base_color: [f32; 3],
         shade: f32,
-        tile: (u32, u32),
+        light: impl Fn([f32; 3]) -> f32,
+        uv: [f32; 4],
     ) {
         let color = [
             base_color[0] * shade,
@@ -203,18 +333,7 @@ impl MeshBuilder {
             base_color[2] * shade,
         ];
 
-        // compute UV rectangle for this tile
-        let tile_w = 1.0 / ATLAS_COLS as f32;
-        let tile_h = 1.0 / ATLAS_ROWS as f32;
-
-        // small inset in UV space to avoid bleeding from neighbors (0.5 texel)
-        let inset_u = 0.5 / (ATLAS_COLS as f32 * TILE_PX);
-        let inset_v = 0.5 / (ATLAS_ROWS as f32 * TILE_PX);
-
-        let u0 = tile.0 as f32 * tile_w + inset_u;
-        let v0 = tile.1 as f32 * tile_h + inset_v;
-        let u1 = u0 + tile_w - 2.0 * inset_u;
-        let v1 = v0 + tile_h - 2.0 * inset_v;
+        let [u0, v0, u1, v1] = uv;
 
         let base_idx = self.vertices.len() as u32;
 
@@ -223,21 +342,29 @@ impl MeshBuilder {
             position: [x, y, z],
             color,
             tex_coords: [u0, v0],
+            light: light([x, y, z]),
+            appeared: 0.0,
         });
         self.vertices.push(Vertex {
             position: [x + u[0], y + u[1], z + u[2]],
             color,
             tex_coords: [u1, v0],
+            light: light([x + u[0], y + u[1], z + u[2]]),
+            appeared: 0.0,
         });
         self.vertices.push(Vertex {
             position: [x + u[0] + v[0], y + u[1] + v[1], z + u[2] + v[2]],
             color,
             tex_coords: [u1, v1],
+            light: light([x + u[0] + v[0], y + u[1] + v[1], z + u[2] + v[2]]),
+            appeared: 0.0,
         });
         self.vertices.push(Vertex {
             position: [x + v[0], y + v[1], z + v[2]],
             color,
             tex_coords: [u0, v1],
+            light: light([x + v[0], y + v[1], z + v[2]]),
+            appeared: 0.0,
         });
 
         // Two triangles per face
@@ -249,3 +376,42 @@ impl MeshBuilder {
         self.indices.push(base_idx + 3);
     }
 }
+
+/// Block light in [0, 1] at a cell: the brightest of the `lights`, each
+/// losing a level per block of (Manhattan) distance. Light isn't blocked by
+/// what is in between.
+pub fn block_light(lights: &[([i32; 3], u8)], cell: [i32; 3]) -> f32 {
+    let level = lights
+        .iter()
+        .map(|(position, emission)| {
+            let distance: i32 = (0..3).map(|axis| (position[axis] - cell[axis]).abs()).sum();
+            *emission as i32 - distance
+        })
+        .max()
+        .unwrap_or(0);
+    level.max(0) as f32 / MAX_LIGHT as f32
+}
+
+/// Smooth light in [0, 1] at the `corner` of a face of the block at `cell`
+/// looking along `normal`: the average of the four cells in front of the face
+/// that touch the corner, so light blends across faces instead of changing
+/// from one block to the next
+pub fn smooth_light(lights: &[([i32; 3], u8)], cell: [i32; 3], normal: [i32; 3], corner: [f32; 3]) -> f32 {
+    if lights.is_empty() {
+        return 0.0;
+    }
+    let axis = (0..3).find(|&axis| normal[axis] != 0).unwrap_or(1);
+    let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+    let mut front = cell;
+    front[axis] += normal[axis];
+    let mut total = 0.0;
+    for da in [-1, 0] {
+        for db in [-1, 0] {
+            let mut sample = front;
+            sample[a] = corner[a].round() as i32 + da;
+            sample[b] = corner[b].round() as i32 + db;
+            total += block_light(lights, sample);
+        }
+    }
+    total / 4.0
+}
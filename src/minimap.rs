@@ -0,0 +1,134 @@
+use crate::chunk::{Chunk, CHUNK_HEIGHT, CHUNK_SIZE};
+use crate::world::World;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Blocks shown on each side of the player
+pub const MAP_RADIUS: i32 = 32;
+/// Chunks kept in the color cache around the player
+const CACHE_RADIUS: i32 = MAP_RADIUS / CHUNK_SIZE as i32 + 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MinimapMode {
+    Off,
+    /// North (-Z) is always up
+    #[default]
+    NorthUp,
+    /// The map turns so the view direction is up
+    Rotating,
+}
+
+impl MinimapMode {
+    /// Next mode for the toggle key: off -> north-up -> rotating -> off
+    pub fn next(self) -> Self {
+        match self {
+            MinimapMode::Off => MinimapMode::NorthUp,
+            MinimapMode::NorthUp => MinimapMode::Rotating,
+            MinimapMode::Rotating => MinimapMode::Off,
+        }
+    }
+}
+
+/// Top-down colors of one chunk, tagged with the chunk revision they were built from
+struct ChunkColors {
+    revision: u32,
+    colors: Vec<[f32; 3]>,
+}
+
+/// Per-chunk cache of top-down block colors for the minimap
+pub struct Minimap {
+    cache: HashMap<(i32, i32), ChunkColors>,
+    /// Player block position, heading and mode the map was last drawn for
+    last_view: Option<(i32, i32, i32, MinimapMode)>,
+}
+
+impl Minimap {
+    pub fn new() -> Self {
+        Self {
+            cache: HashMap::new(),
+            last_view: None,
+        }
+    }
+
+    /// Refresh stale chunk colors around the player. Returns true if the map
+    /// needs to be redrawn (new colors, player moved a block or turned in rotating mode).
+    pub fn update(&mut self, world: &World, player_pos: Vec3, yaw: f32, mode: MinimapMode) -> bool {
+        let center = (
+            (player_pos.x / CHUNK_SIZE as f32).floor() as i32,
+            (player_pos.z / CHUNK_SIZE as f32).floor() as i32,
+        );
+
+        self.cache.retain(|&(x, z), _| {
+            (x - center.0).abs() <= CACHE_RADIUS && (z - center.1).abs() <= CACHE_RADIUS
+        });
+
+        let mut changed = false;
+        for dx in -CACHE_RADIUS..=CACHE_RADIUS {
+            for dz in -CACHE_RADIUS..=CACHE_RADIUS {
+                let key = (center.0 + dx, center.1 + dz);
+                let Some(chunk) = world.get_chunk(key.0, key.1) else {
+                    continue;
+                };
+                if self.cache.get(&key).is_some_and(|c| c.revision == chunk.revision) {
+                    continue;
+                }
+                self.cache.insert(key, ChunkColors {
+                    revision: chunk.revision,
+                    colors: chunk_colors(&chunk),
+                });
+                changed = true;
+            }
+        }
+
+        // Heading only matters when the map rotates; quantize it to whole degrees
+        let heading = if mode == MinimapMode::Rotating { yaw.to_degrees().round() as i32 } else { 0 };
+        let view = (player_pos.x.floor() as i32, player_pos.z.floor() as i32, heading, mode);
+        if self.last_view != Some(view) {
+            self.last_view = Some(view);
+            changed = true;
+        }
+        changed
+    }
+
+    /// Force a redraw on the next update (e.g. after the window is resized)
+    pub fn invalidate(&mut self) {
+        self.last_view = None;
+    }
+
+    /// Cached color of the topmost block at a world column, if its chunk is loaded
+    pub fn color_at(&self, x: i32, z: i32) -> Option<[f32; 3]> {
+        let size = CHUNK_SIZE as i32;
+        let entry = self.cache.get(&(x.div_euclid(size), z.div_euclid(size)))?;
+        let (lx, lz) = (x.rem_euclid(size) as usize, z.rem_euclid(size) as usize);
+        Some(entry.colors[lx + lz * CHUNK_SIZE])
+    }
+}
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Top-down colors of every column in a chunk, indexed by x + z * CHUNK_SIZE
+fn chunk_colors(chunk: &Chunk) -> Vec<[f32; 3]> {
+    let mut colors = Vec::with_capacity(CHUNK_SIZE * CHUNK_SIZE);
+    for z in 0..CHUNK_SIZE {
+        for x in 0..CHUNK_SIZE {
+            colors.push(column_color(chunk, x, z));
+        }
+    }
+    colors
+}
+
+/// Color of the topmost non-air block in a column, brighter the higher it is
+pub fn column_color(chunk: &Chunk, x: usize, z: usize) -> [f32; 3] {
+    let Some(y) = chunk.height(x, z).checked_sub(1) else {
+        return [0.0, 0.0, 0.0];
+    };
+    let shade = 0.6 + 0.4 * y as f32 / CHUNK_HEIGHT as f32;
+    let color = chunk.get_block(x, y, z).get_color();
+    [color[0] * shade, color[1] * shade, color[2] * shade]
+}
@@ -0,0 +1,85 @@
+//! Breaking blocks by holding the mouse button. How long a block takes
+//! follows its hardness and the tool held (see `BlockType::hardness`,
+//! `preferred_tool` and `required_tier`): the right tool speeds it up by
+//! its tier and its efficiency enchantment, and blocks that need a tool
+//! take much longer, and drop nothing, without it.
+
+use crate::block::BlockType;
+use crate::enchanting::{self, Enchantment};
+use crate::inventory::ItemStack;
+use crate::item::Item;
+
+/// Break time per point of hardness when the block will drop
+const HARVEST_FACTOR: f32 = 1.5;
+/// Break time per point of hardness when it needs a better tool to drop
+const NO_HARVEST_FACTOR: f32 = 5.0;
+/// Seconds between breaking one block and starting on the next while the
+/// button stays held
+pub const BREAK_DELAY: f32 = 0.25;
+
+/// Whether breaking `block` with `held` in hand drops it
+pub fn can_harvest(block: BlockType, held: Option<Item>) -> bool {
+    let Some(required) = block.required_tier() else {
+        return true;
+    };
+    held.and_then(|item| item.tool())
+        .is_some_and(|(kind, tier)| Some(kind) == block.preferred_tool() && tier >= required)
+}
+
+/// Seconds it takes to break `block` with `held` in hand, enchanted with
+/// `efficiency`; see `enchanting::efficiency_bonus`
+pub fn break_time(block: BlockType, held: Option<Item>, efficiency: u8) -> f32 {
+    let factor = if can_harvest(block, held) { HARVEST_FACTOR } else { NO_HARVEST_FACTOR };
+    let speed = held
+        .and_then(|item| item.tool())
+        .filter(|(kind, _)| Some(*kind) == block.preferred_tool())
+        .map_or(1.0, |(_, tier)| tier.speed() + enchanting::efficiency_bonus(efficiency));
+    block.hardness() * factor / speed
+}
+
+/// Progress on the block being broken
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Mining {
+    /// The block being broken and the seconds it takes in all
+    target: Option<((i32, i32, i32), BlockType, f32)>,
+    /// Seconds spent on it so far
+    elapsed: f32,
+    /// Seconds left before the next block can be started on
+    delay: f32,
+}
+
+impl Mining {
+    /// Keep breaking `block` at `pos` for `delta_time` more seconds with
+    /// `held` in hand; moving to another block starts over. Returns true
+    /// once the block breaks.
+    pub fn advance(&mut self, pos: (i32, i32, i32), block: BlockType, held: Option<ItemStack>, delta_time: f32) -> bool {
+        if self.delay > 0.0 {
+            self.delay -= delta_time;
+            return false;
+        }
+        let efficiency = held.map_or(0, |stack| stack.enchantments.level(Enchantment::Efficiency));
+        let time = break_time(block, held.map(|stack| stack.item), efficiency);
+        if self.target.is_none_or(|(target, current, _)| target != pos || current != block) {
+            self.elapsed = 0.0;
+        }
+        self.target = Some((pos, block, time));
+        self.elapsed += delta_time;
+        if self.elapsed < time {
+            return false;
+        }
+        *self = Self { delay: BREAK_DELAY, ..Self::default() };
+        true
+    }
+
+    /// Stop breaking, losing the progress
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// How far the current block is from breaking, 0 to 1; None when no
+    /// block is being broken
+    pub fn progress(&self) -> Option<f32> {
+        let (_, _, time) = self.target?;
+        Some((self.elapsed / time).min(1.0))
+    }
+}
@@ -0,0 +1,163 @@
+//! TCP transport for LAN multiplayer. Every message is a packet: its length as
+//! a little-endian u32, then the bincode-encoded message. Each connection gets
+//! a reader and a writer thread that bridge the socket to the channels behind a
+//! `ClientConnection` or `ServerConnection`, so the game and the server handle
+//! network peers exactly like the in-process one.
+
+use crate::protocol::{ClientConnection, ClientMessage, LoginInfo, ServerConnection, ServerMessage, PROTOCOL_VERSION};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+pub const DEFAULT_PORT: u16 = 25570;
+/// Larger packets are treated as a corrupt stream; a chunk is well below this
+const MAX_PACKET_SIZE: usize = 4 * 1024 * 1024;
+/// How long either side waits for the other's login message
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn write_packet<T: Serialize>(writer: &mut impl Write, packet: &T) -> io::Result<()> {
+    let payload = bincode::serialize(packet).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    if payload.len() > MAX_PACKET_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "packet too large"));
+    }
+    writer.write_all(&(payload.len() as u32).to_le_bytes())?;
+    writer.write_all(&payload)
+}
+
+pub fn read_packet<T: DeserializeOwned>(reader: &mut impl Read) -> io::Result<T> {
+    let mut length = [0; 4];
+    reader.read_exact(&mut length)?;
+    let length = u32::from_le_bytes(length) as usize;
+    if length > MAX_PACKET_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "packet too large"));
+    }
+    let mut payload = vec![0; length];
+    reader.read_exact(&mut payload)?;
+    bincode::deserialize(&payload).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// `host` or `host:port`, with the default port if none is given
+pub fn parse_address(address: &str) -> String {
+    if address.rsplit_once(':').is_some_and(|(_, port)| port.parse::<u16>().is_ok()) {
+        address.to_string()
+    } else {
+        format!("{}:{}", address, DEFAULT_PORT)
+    }
+}
+
+/// Connect to a server and log in as `name`
+pub fn connect(address: &str, name: &str) -> Result<(ClientConnection, LoginInfo), String> {
+    let address = parse_address(address);
+    let socket_address = address
+        .to_socket_addrs()
+        .map_err(|e| format!("{}: {}", address, e))?
+        .next()
+        .ok_or_else(|| format!("{}: no address found", address))?;
+    let mut stream = TcpStream::connect_timeout(&socket_address, LOGIN_TIMEOUT)
+        .map_err(|e| format!("Could not connect to {}: {}", address, e))?;
+    stream.set_nodelay(true).ok();
+
+    let login = ClientMessage::Login { version: PROTOCOL_VERSION, name: name.to_string() };
+    write_packet(&mut stream, &login).map_err(|e| format!("Login failed: {}", e))?;
+    stream.set_read_timeout(Some(LOGIN_TIMEOUT)).ok();
+    let info = match read_packet(&mut stream).map_err(|e| format!("Login failed: {}", e))? {
+        ServerMessage::LoginAccepted(info) => info,
+        ServerMessage::Disconnect(reason) => return Err(format!("The server refused the login: {}", reason)),
+        other => return Err(format!("Login failed: unexpected {:?}", other)),
+    };
+    stream.set_read_timeout(None).ok();
+
+    let (to_server, from_server) = bridge(stream).map_err(|e| e.to_string())?;
+    Ok((ClientConnection::new(to_server, from_server), info))
+}
+
+/// A client that logged in with a matching protocol version
+pub struct NewClient {
+    pub name: String,
+    pub address: String,
+    pub connection: ServerConnection,
+}
+
+/// Accept LAN players on `port` (0 for any free one) in the background.
+/// Clients arrive on the returned channel once their login checked out; the
+/// server answers them. Also returns the port listened on.
+pub fn listen(port: u16) -> io::Result<(Receiver<NewClient>, u16)> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    let port = listener.local_addr()?.port();
+    let (new_clients, arrivals) = mpsc::channel();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().map_while(Result::ok) {
+            let new_clients = new_clients.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = accept(stream, &new_clients) {
+                    log::warn!("Rejected connection: {}", e);
+                }
+            });
+        }
+    });
+    Ok((arrivals, port))
+}
+
+/// Check a new connection's login and hand it to the server
+fn accept(mut stream: TcpStream, new_clients: &Sender<NewClient>) -> io::Result<()> {
+    let address = stream.peer_addr()?.to_string();
+    stream.set_nodelay(true).ok();
+    stream.set_read_timeout(Some(LOGIN_TIMEOUT))?;
+    let ClientMessage::Login { version, name } = read_packet(&mut stream)? else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} did not log in", address)));
+    };
+    if version != PROTOCOL_VERSION {
+        let reason = format!("Protocol version {} is required, the client has {}", PROTOCOL_VERSION, version);
+        write_packet(&mut stream, &ServerMessage::Disconnect(reason)).ok();
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} has protocol version {}", address, version)));
+    }
+    stream.set_read_timeout(None)?;
+
+    let (to_client, from_client) = bridge(stream)?;
+    let connection = ServerConnection::new(to_client, from_client);
+    new_clients.send(NewClient { name, address, connection }).ok();
+    Ok(())
+}
+
+/// Reader and writer threads between `stream` and a pair of channels. The
+/// incoming channel disconnects when the peer goes away; dropping the outgoing
+/// sender closes the socket.
+fn bridge<Out, In>(stream: TcpStream) -> io::Result<(Sender<Out>, Receiver<In>)>
+where
+    Out: Serialize + Send + 'static,
+    In: DeserializeOwned + Send + 'static,
+{
+    let (outgoing, to_write) = mpsc::channel::<Out>();
+    let (received, incoming) = mpsc::channel();
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    std::thread::spawn(move || {
+        while let Ok(message) = read_packet::<In>(&mut reader) {
+            if received.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    std::thread::spawn(move || {
+        let mut writer = BufWriter::new(&stream);
+        // Write whatever is queued, then flush once
+        'connection: while let Ok(message) = to_write.recv() {
+            for message in std::iter::once(message).chain(to_write.try_iter()) {
+                if write_packet(&mut writer, &message).is_err() {
+                    break 'connection;
+                }
+            }
+            if writer.flush().is_err() {
+                break;
+            }
+        }
+        drop(writer);
+        stream.shutdown(Shutdown::Both).ok();
+    });
+
+    Ok((outgoing, incoming))
+}
@@ -0,0 +1,82 @@
+//! Who may do what on the server. Operators (listed in `--ops`, one name per
+//! line) may run the commands that change the world for everyone; with
+//! `--whitelist` only the players listed there may join. Names compare
+//! case-insensitively, through `normalize_name`. The server console and the
+//! singleplayer host are always operators.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// Commands only operators may run
+pub const OP_COMMANDS: &[&str] = &["give", "xp", "tp", "time", "weather", "setworldspawn", "worldborder", "gamerule", "locate", "blockinfo", "gamemode", "set", "fill", "paste", "schem", "kick", "stop", "op", "deop"];
+
+pub struct Permissions {
+    ops: BTreeSet<String>,
+    ops_path: PathBuf,
+    /// Everyone may join without a whitelist
+    whitelist: Option<BTreeSet<String>>,
+}
+
+impl Permissions {
+    /// Read the operator list, and the whitelist if one is given. A missing
+    /// operator list means no operators yet; a missing whitelist lets nobody in.
+    pub fn load(ops_path: &Path, whitelist_path: Option<&Path>) -> Self {
+        let ops = read_names(ops_path).unwrap_or_default();
+        let whitelist = whitelist_path.map(|path| {
+            read_names(path).unwrap_or_else(|| {
+                log::warn!("No whitelist at {}, nobody can join", path.display());
+                BTreeSet::new()
+            })
+        });
+        Self { ops, ops_path: ops_path.to_path_buf(), whitelist }
+    }
+
+    pub fn is_op(&self, name: &str) -> bool {
+        self.ops.contains(&normalize_name(name))
+    }
+
+    pub fn may_join(&self, name: &str) -> bool {
+        self.whitelist.as_ref().is_none_or(|names| names.contains(&normalize_name(name)))
+    }
+
+    /// Make `name` an operator or take that away, saving the list. Returns
+    /// false if nothing changed. The list only changes once it is saved, so
+    /// a failed save leaves everyone's rights as they were.
+    pub fn set_op(&mut self, name: &str, op: bool) -> Result<bool, String> {
+        let name = normalize_name(name);
+        let mut ops = self.ops.clone();
+        let changed = if op { ops.insert(name) } else { ops.remove(&name) };
+        if changed {
+            let mut contents: String = ops.iter().map(|name| format!("{}\n", name)).collect();
+            contents.insert_str(0, "# Server operators, one player name per line\n");
+            std::fs::write(&self.ops_path, contents)
+                .map_err(|e| format!("Could not save {}: {}", self.ops_path.display(), e))?;
+            self.ops = ops;
+        }
+        Ok(changed)
+    }
+}
+
+/// The form names are stored and compared in: trimmed and lowercased, the
+/// same for every check so no name passes one and fails another
+pub fn normalize_name(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// Whether two player names are the same one
+pub fn same_name(a: &str, b: &str) -> bool {
+    normalize_name(a) == normalize_name(b)
+}
+
+/// Normalized names, one per line, skipping blank lines and `#` comments;
+/// None if the file can't be read
+fn read_names(path: &Path) -> Option<BTreeSet<String>> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    let names = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(normalize_name)
+        .collect();
+    Some(names)
+}
@@ -1,15 +1,71 @@
 use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use crate::block::BlockType;
+use crate::survival::SurvivalStats;
 use crate::world::World;
 
 // Small epsilon when converting AABB float bounds to integer block indices
 const RANGE_EPS: f32 = 1e-4;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Blocks are consumed when placed and collected when broken
+    Survival,
+    /// Unlimited blocks; breaking doesn't collect drops
+    Creative,
+}
+
+impl GameMode {
+    pub fn from_name(name: &str) -> Option<GameMode> {
+        match name.to_ascii_lowercase().as_str() {
+            "survival" | "s" | "0" => Some(GameMode::Survival),
+            "creative" | "c" | "1" => Some(GameMode::Creative),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Survival => "survival",
+            GameMode::Creative => "creative",
+        }
+    }
+}
+
+/// Movement constants used by `Player::apply_physics` and `Player::jump`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicsConstants {
+    pub gravity: f32,
+    pub terminal_velocity: f32,
+    pub jump_velocity: f32,
+    /// Max height the player can climb without jumping
+    pub step_height: f32,
+}
+
+impl Default for PhysicsConstants {
+    fn default() -> Self {
+        Self {
+            gravity: -25.0,
+            terminal_velocity: -50.0,
+            jump_velocity: 8.0,
+            step_height: 0.6,
+        }
+    }
+}
+
 // Aabb and Player struct remain unchanged
 pub struct Player {
     pub position: Vec3,
     pub velocity: Vec3,
     pub on_ground: bool,
     pub bounding_box: Aabb,
+    pub game_mode: GameMode,
+    pub stats: SurvivalStats,
+    pub physics: PhysicsConstants,
+    /// Shift is held: slower movement and muffled footsteps
+    pub sneaking: bool,
+    /// Ctrl is held while walking forward: faster movement and a wider FOV
+    pub sprinting: bool,
 }
 
 #[derive(Clone, Copy)]
@@ -38,6 +94,12 @@ impl Aabb {
         }
     }
 
+    /// The cell of the block at `pos`
+    pub fn block(pos: (i32, i32, i32)) -> Self {
+        let min = Vec3::new(pos.0 as f32, pos.1 as f32, pos.2 as f32);
+        Self { min, max: min + Vec3::ONE }
+    }
+
     pub fn intersects(&self, other: &Aabb) -> bool {
         self.min.x < other.max.x
             && self.max.x > other.min.x
@@ -52,8 +114,9 @@ impl Aabb {
 
 impl Player {
     // ⚠️ New, unified constant for actual collision size (0.3 for 0.6 total width)
-    const COLLISION_HALF_WIDTH: f32 = 0.3; 
+    pub const COLLISION_HALF_WIDTH: f32 = 0.3; 
     const PLAYER_HEIGHT: f32 = 1.8;
+    const EYE_HEIGHT: f32 = 1.6;
 
     pub fn new(position: Vec3) -> Self {
         Self {
@@ -62,24 +125,52 @@ impl Player {
             on_ground: false,
             // Use the unified constant
             bounding_box: Aabb::from_position(position, Self::COLLISION_HALF_WIDTH, Self::PLAYER_HEIGHT),
+            game_mode: GameMode::Survival,
+            stats: SurvivalStats::new(),
+            physics: PhysicsConstants::default(),
+            sneaking: false,
+            sprinting: false,
         }
     }
 
+    /// Camera position (eyes) for the current feet position
+    pub fn eye_position(&self) -> Vec3 {
+        self.position + Vec3::new(0.0, Self::EYE_HEIGHT, 0.0)
+    }
+
+    /// Whether the player's eyes are inside water
+    pub fn is_head_submerged(&self, world: &World) -> bool {
+        let eye = self.eye_position();
+        world.get_block_at(eye.x.floor() as i32, eye.y.floor() as i32, eye.z.floor() as i32) == Some(BlockType::Water)
+    }
+
+    /// Block the player is standing on, if any
+    pub fn block_underfoot(&self, world: &World) -> Option<BlockType> {
+        let (x, y, z) = (self.position.x.floor() as i32, (self.position.y - 0.1).floor() as i32, self.position.z.floor() as i32);
+        world.get_block_at(x, y, z).filter(|block| !matches!(block, BlockType::Air | BlockType::Water))
+    }
+
+    /// Move the player instantly, cancelling any motion
+    pub fn teleport(&mut self, position: Vec3) {
+        self.position = position;
+        self.velocity = Vec3::ZERO;
+        self.on_ground = false;
+        self.update_bounding_box();
+    }
+
     pub fn update_bounding_box(&mut self) {
         // Use the unified constant
         self.bounding_box = Aabb::from_position(self.position, Self::COLLISION_HALF_WIDTH, Self::PLAYER_HEIGHT);
     }
 
     pub fn apply_physics(&mut self, delta_time: f32, world: &World) {
-        const GRAVITY: f32 = -25.0;
-        const TERMINAL_VELOCITY: f32 = -50.0;
+        let PhysicsConstants { gravity, terminal_velocity, step_height, .. } = self.physics;
         const EPSILON: f32 = 0.001;
         // Small epsilon used when converting floating AABB bounds to integer block coordinates
         const RANGE_EPS: f32 = 1e-4;
         // Use the unified constant for horizontal calculations
         const HALF_WIDTH: f32 = Player::COLLISION_HALF_WIDTH; 
         const HEIGHT: f32 = Player::PLAYER_HEIGHT;
-        const STEP_HEIGHT: f32 = 0.6; // Max height the player can climb
 
         let prev_position = self.position;
         let prev_feet_y = prev_position.y;
@@ -114,7 +205,7 @@ impl Player {
                 for z in min_z..=max_z {
                     let check_for_support = |cy: i32| -> bool {
                         if let Some(block_type) = world.get_block_at(x, cy, z) {
-                            if block_type.is_solid() {
+                            if block_type.is_full_cube() {
                                 let block_top = cy as f32 + 1.0;
                                 // 1. Check if the block's top is at the right height (near feet_y)
                                 if (block_top - feet_y).abs() <= support_probe + EPSILON {
@@ -151,8 +242,8 @@ impl Player {
 
         // Apply gravity
         if !self.on_ground {
-            self.velocity.y += GRAVITY * delta_time;
-            self.velocity.y = self.velocity.y.max(TERMINAL_VELOCITY);
+            self.velocity.y += gravity * delta_time;
+            self.velocity.y = self.velocity.y.max(terminal_velocity);
         }
 
         let desired_position = self.position + self.velocity * delta_time;
@@ -184,7 +275,7 @@ impl Player {
                 'xz_loop: for x in swept_min_x..=swept_max_x {
                     for z in swept_min_z..=swept_max_z {
                         if let Some(block_type) = world.get_block_at(x, y, z) {
-                            if block_type.is_solid() {
+                            if block_type.is_full_cube() {
                                 // ⚠️ Ensure block AABB intersects the player AABB horizontally
                                 let block_aabb = Aabb::new(
                                     Vec3::new(x as f32, y as f32, z as f32),
@@ -251,7 +342,7 @@ impl Player {
                     for z in min_z..=max_z {
                         for y in min_y..=max_y {
                             if let Some(block_type) = world.get_block_at(x, y, z) {
-                                if block_type.is_solid()
+                                if block_type.is_full_cube()
                                     && lowest_block_y_above.is_none_or(|lb| y < lb) {
                                         lowest_block_y_above = Some(y);
                                     }
@@ -292,7 +383,7 @@ impl Player {
         if self.check_collision(world) {
             if can_step_up { // <-- Only try to step up if not moving upward
                 // Collision in X: Try to step up
-                self.position.y += STEP_HEIGHT;
+                self.position.y += step_height;
                 self.update_bounding_box();
 
                 if self.check_collision(world) {
@@ -320,7 +411,7 @@ impl Player {
         if self.check_collision(world) {
             if can_step_up { // <-- Only try to step up if not moving upward
                 // Collision in Z: Try to step up
-                self.position.y = old_y + STEP_HEIGHT; // Always step up from the original Y for Z-check
+                self.position.y = old_y + step_height; // Always step up from the original Y for Z-check
                 self.update_bounding_box();
 
                 if self.check_collision(world) {
@@ -335,24 +426,27 @@ impl Player {
                 self.position.z = old_pos.z;
                 self.position.y = old_y;
             }
-        } else if self.position.y == old_y + STEP_HEIGHT {
+        } else if self.position.y == old_y + step_height {
             // If the X axis already lifted us, and Z didn't collide, keep the elevated Y.
         } else {
             self.position.y = old_y; // If no step-up occurred in Z, ensure Y is back to old_y
         }
 
+        // --- 5. The world border pushes the player back inside ---
+        world.push_inside_border(&mut self.position, &mut self.velocity, HALF_WIDTH);
+
         self.update_bounding_box();
     }
 
     pub fn jump(&mut self) {
         if self.on_ground {
-            self.velocity.y = 8.0;
+            self.velocity.y = self.physics.jump_velocity;
             self.on_ground = false;
         }
     }
 
-    // check_collision remains unchanged as its logic is correct for an AABB check
-    fn check_collision(&self, world: &World) -> bool {
+    /// Whether the player's bounding box overlaps a solid block
+    pub fn check_collision(&self, world: &World) -> bool {
         let min_x = self.bounding_box.min.x.floor() as i32;
         let max_x = (self.bounding_box.max.x - RANGE_EPS).floor() as i32;
         let min_y = self.bounding_box.min.y.floor() as i32;
@@ -364,7 +458,7 @@ impl Player {
             for y in min_y..=max_y {
                 for z in min_z..=max_z {
                     if let Some(block_type) = world.get_block_at(x, y, z) {
-                        if block_type.is_solid() {
+                        if block_type.is_full_cube() {
                             let block_aabb = Aabb::new(
                                 Vec3::new(x as f32, y as f32, z as f32),
                                 Vec3::new((x + 1) as f32, (y + 1) as f32, (z + 1) as f32),
@@ -0,0 +1,80 @@
+//! Lifetime statistics of the player at this computer: play time, distance
+//! walked, jumps, deaths and the blocks mined and placed of each kind. They
+//! are kept in `stats.json` next to the config, across worlds and servers,
+//! and shown on the Statistics screen of the pause menu.
+
+use crate::block::BlockType;
+use crate::events::{EventListener, GameEvent};
+use crate::storage::Storage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// File name of the statistics, in the directory of the config file
+pub const STATS_FILE: &str = "stats.json";
+
+/// Kinds of block named on a line of the Statistics screen, the most first
+const TOP_BLOCKS: usize = 3;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerStats {
+    /// Seconds spent playing, menus and screens that pause the game left out
+    pub play_time: f64,
+    /// Blocks walked along the ground
+    pub distance_walked: f64,
+    pub jumps: u64,
+    pub deaths: u64,
+    pub blocks_mined: HashMap<BlockType, u64>,
+    pub blocks_placed: HashMap<BlockType, u64>,
+}
+
+impl PlayerStats {
+    /// The statistics saved at `path`; fresh ones if there are none yet or
+    /// the file can't be read
+    pub fn load(storage: &dyn Storage, path: &Path) -> Self {
+        storage.read_to_string(path).ok().and_then(|data| serde_json::from_str(&data).ok()).unwrap_or_default()
+    }
+
+    pub fn save(&self, storage: &dyn Storage, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let data = serde_json::to_string_pretty(self)?;
+        storage.write(path, data.as_bytes())?;
+        Ok(())
+    }
+
+    /// Lines of the Statistics screen
+    pub fn lines(&self) -> Vec<String> {
+        let seconds = self.play_time as u64;
+        vec![
+            format!("Play time: {}h {:02}m {:02}s", seconds / 3600, seconds / 60 % 60, seconds % 60),
+            format!("Distance walked: {:.0} blocks", self.distance_walked),
+            format!("Jumps: {}", self.jumps),
+            format!("Deaths: {}", self.deaths),
+            format!("Blocks mined: {}", summary(&self.blocks_mined)),
+            format!("Blocks placed: {}", summary(&self.blocks_placed)),
+        ]
+    }
+}
+
+impl EventListener for PlayerStats {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::BlockBroken { block, .. } => *self.blocks_mined.entry(*block).or_default() += 1,
+            GameEvent::BlockPlaced { block, .. } => *self.blocks_placed.entry(*block).or_default() += 1,
+            _ => {}
+        }
+    }
+}
+
+/// Total of the counts, with the kinds counted most
+fn summary(counts: &HashMap<BlockType, u64>) -> String {
+    let total: u64 = counts.values().sum();
+    let mut kinds: Vec<(BlockType, u64)> = counts.iter().map(|(block, count)| (*block, *count)).collect();
+    kinds.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.name().cmp(b.0.name())));
+    let top: Vec<String> = kinds.iter().take(TOP_BLOCKS).map(|(block, count)| format!("{} {}", block.name(), count)).collect();
+    if top.is_empty() {
+        total.to_string()
+    } else {
+        format!("{} ({})", total, top.join(", "))
+    }
+}
@@ -0,0 +1,178 @@
+//! Per-frame timing of the game loop phases, averaged over the last frames for
+//! the debug overlays, with an optional Chrome tracing capture
+//! (`--trace <path>`, open in chrome://tracing or Perfetto).
+
+use std::collections::VecDeque;
+use std::time::Duration;
+use web_time::Instant;
+
+/// Frames in the rolling average
+const HISTORY: usize = 60;
+/// Trace events kept before the capture stops, about 25 MB
+const MAX_TRACE_EVENTS: usize = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Window and mouse event handling, camera look
+    Input,
+    /// Player movement and the fixed world ticks
+    Physics,
+    /// Integrating chunks generated by the worker threads
+    ChunkGen,
+    /// Building chunk meshes
+    Meshing,
+    /// Vertex, index and uniform buffer uploads
+    Upload,
+    /// Recording and submitting the render passes
+    RenderPass,
+    /// Presenting the frame, including waiting for vsync
+    Present,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 7] = [
+        Phase::Input,
+        Phase::Physics,
+        Phase::ChunkGen,
+        Phase::Meshing,
+        Phase::Upload,
+        Phase::RenderPass,
+        Phase::Present,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Input => "Input",
+            Phase::Physics => "Physics",
+            Phase::ChunkGen => "Chunk gen",
+            Phase::Meshing => "Meshing",
+            Phase::Upload => "Upload",
+            Phase::RenderPass => "Render pass",
+            Phase::Present => "Present",
+        }
+    }
+}
+
+type PhaseTimes = [Duration; Phase::ALL.len()];
+
+/// One completed phase in a trace
+struct TraceEvent {
+    phase: Phase,
+    start: Duration,
+    duration: Duration,
+}
+
+pub struct Profiler {
+    /// Phase times of the frame in progress
+    current: PhaseTimes,
+    /// Phase times and total length of the last `HISTORY` frames
+    history: VecDeque<(PhaseTimes, Duration)>,
+    frame_start: Instant,
+    /// Trace start and the events recorded since, while capturing
+    trace: Option<(Instant, Vec<TraceEvent>)>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            current: PhaseTimes::default(),
+            history: VecDeque::with_capacity(HISTORY),
+            frame_start: Instant::now(),
+            trace: None,
+        }
+    }
+
+    /// Add the time from `start` until now to `phase`. Returns the end time,
+    /// so consecutive phases can be chained.
+    pub fn record(&mut self, phase: Phase, start: Instant) -> Instant {
+        let end = Instant::now();
+        let duration = end.duration_since(start);
+        self.current[phase as usize] += duration;
+        if let Some((trace_start, events)) = &mut self.trace {
+            if events.len() < MAX_TRACE_EVENTS {
+                events.push(TraceEvent {
+                    phase,
+                    start: start.saturating_duration_since(*trace_start),
+                    duration,
+                });
+            }
+        }
+        end
+    }
+
+    /// Close the current frame and start the next
+    pub fn end_frame(&mut self) {
+        let now = Instant::now();
+        if self.history.len() == HISTORY {
+            self.history.pop_front();
+        }
+        let frame = now.duration_since(self.frame_start);
+        self.history.push_back((std::mem::take(&mut self.current), frame));
+        self.frame_start = now;
+    }
+
+    /// Average time per frame of each phase over the recent frames
+    pub fn averages(&self) -> Vec<(Phase, Duration)> {
+        let frames = self.history.len().max(1) as u32;
+        Phase::ALL
+            .into_iter()
+            .map(|phase| {
+                let total: Duration = self.history.iter().map(|(times, _)| times[phase as usize]).sum();
+                (phase, total / frames)
+            })
+            .collect()
+    }
+
+    /// Average frame length over the recent frames
+    pub fn average_frame(&self) -> Duration {
+        let total: Duration = self.history.iter().map(|(_, frame)| *frame).sum();
+        total / self.history.len().max(1) as u32
+    }
+
+    /// Breakdown for the F3 debug output
+    pub fn format_display(&self) -> Vec<String> {
+        let frame = self.average_frame();
+        let mut lines = vec![format!("Frame: {:.2} ms (last {} frames)", ms(frame), self.history.len())];
+        let mut measured = Duration::ZERO;
+        for (phase, time) in self.averages() {
+            measured += time;
+            lines.push(format!("  {}: {:.2} ms", phase.name(), ms(time)));
+        }
+        lines.push(format!("  Other: {:.2} ms", ms(frame.saturating_sub(measured))));
+        lines
+    }
+
+    /// Start recording every phase for a Chrome trace
+    pub fn start_trace(&mut self) {
+        self.trace = Some((Instant::now(), Vec::new()));
+    }
+
+    /// Stop recording and return the trace in the Chrome trace event format
+    pub fn finish_trace(&mut self) -> Option<String> {
+        let (_, events) = self.trace.take()?;
+        let events: Vec<serde_json::Value> = events
+            .iter()
+            .map(|event| {
+                serde_json::json!({
+                    "name": event.phase.name(),
+                    "ph": "X",
+                    "ts": event.start.as_secs_f64() * 1e6,
+                    "dur": event.duration.as_secs_f64() * 1e6,
+                    "pid": 1,
+                    "tid": 1,
+                })
+            })
+            .collect();
+        Some(serde_json::json!({ "traceEvents": events }).to_string())
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn ms(duration: Duration) -> f32 {
+    duration.as_secs_f32() * 1000.0
+}
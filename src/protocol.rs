@@ -0,0 +1,264 @@
+//! Messages between the game client and the server that owns the world. In
+//! singleplayer both ends live in one process and talk over a local
+//! connection; LAN players connect over TCP (see `net`), which carries the same
+//! messages as bincode packets.
+
+use crate::block::BlockType;
+use crate::chunk::{CHUNK_HEIGHT, CHUNK_SIZE};
+use crate::events::GameEvent;
+use crate::gamerules::GameRules;
+use crate::item::Item;
+use crate::physics::GameMode;
+use crate::sleep::SleepStatus;
+use crate::time::Weather;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+
+/// Bumped on every incompatible change to the messages; a client must match
+/// the server's version to log in
+pub const PROTOCOL_VERSION: u32 = 10;
+/// Largest decompressed `ChunkData`: a run of 8 bytes for every block
+const MAX_RUNS_SIZE: usize = 8 + CHUNK_SIZE * CHUNK_HEIGHT * CHUNK_SIZE * 8;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ClientMessage {
+    /// First message of a network client
+    Login { version: u32, name: String },
+    /// The client's predicted player state, sent every frame. The server
+    /// accepts it or answers with a `Teleport` back to the last valid position.
+    Move {
+        position: [f32; 3],
+        velocity: [f32; 3],
+        on_ground: bool,
+        /// View direction, for the other players' view of this one
+        yaw: f32,
+    },
+    /// Chunks to keep loaded around the player
+    ViewDistance(i32),
+    /// A console command line, with or without the leading '/'
+    Command(String),
+    /// A chat line for every player
+    Chat(String),
+    /// A network client broke or placed a block in its copy of the world
+    SetBlock { pos: (i32, i32, i32), block: BlockType },
+    /// The player used (right-clicked) the block at `pos`
+    UseBlock { pos: (i32, i32, i32) },
+    /// The player broke, placed or used a block; the others see its arm swing
+    Swing,
+    /// PNG of the player's skin for the others to see, empty for the default
+    /// one; at most `skin::MAX_SKIN_FILE_SIZE` bytes
+    Skin(Vec<u8>),
+    /// The player hit the entity (another player) under its crosshair
+    Attack { target: u32 },
+    /// Back to the spawn point after dying
+    Respawn,
+    /// The player went to bed at `pos`
+    Sleep { pos: (i32, i32, i32) },
+    /// The player got out of bed
+    WakeUp,
+    Save,
+    /// Answer to the server's `KeepAlive`, echoing its id
+    KeepAlive(u64),
+}
+
+/// What a network client learns when it logs in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoginInfo {
+    pub player_id: u32,
+    pub seed: u32,
+    pub spawn: [f32; 3],
+    /// Game time in ticks, see `WorldTime::ticks`
+    pub time: u64,
+    pub weather: Weather,
+    /// See `World::border`
+    pub border: Option<i32>,
+    pub rules: GameRules,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ServerMessage {
+    LoginAccepted(LoginInfo),
+    /// The server refused the login or dropped the client
+    Disconnect(String),
+    /// Move the player: a rejected move, a respawn or `/tp`
+    Teleport { position: [f32; 3] },
+    GameMode(GameMode),
+    /// Set the player's health and hunger, as an emergency save had them
+    Vitals { health: u32, hunger: u32 },
+    /// `/give` for a network client, which keeps its own inventory
+    Give { item: Item, count: u32 },
+    /// `/xp` for a network client, which keeps its own experience: points,
+    /// or whole levels
+    Experience { amount: u32, levels: bool },
+    /// Feedback line of a `Command`, or its error
+    CommandOutput(Result<String, String>),
+    /// One world tick ran
+    Tick,
+    /// Sets the clock of a network client after `/time`, in game ticks
+    Time(u64),
+    /// The weather changed
+    Weather(Weather),
+    /// `/worldborder` moved or removed the world border
+    WorldBorder(Option<i32>),
+    /// `/gamerule` changed a rule
+    GameRules(GameRules),
+    /// Blocks changed or chunks were loaded; meshes need rebuilding
+    WorldChanged,
+    /// Chest or furnace contents changed on their own (smelting)
+    BlockEntitiesChanged,
+    /// Gameplay event for the client's sounds and screens
+    Event(GameEvent),
+    /// Outcome of a `Save`
+    Saved(Result<(), String>),
+    /// A whole chunk for a network client, compressed (see `compress_blocks`)
+    ChunkData { x: i32, z: i32, data: Vec<u8> },
+    /// The chunk left the client's view distance
+    ChunkUnload { x: i32, z: i32 },
+    /// One block changed in a chunk the client has
+    BlockChange { pos: (i32, i32, i32), block: BlockType },
+    /// Several blocks changed in one chunk during one update, by index into
+    /// the chunk's blocks
+    MultiBlockChange { x: i32, z: i32, changes: Vec<(u16, BlockType)> },
+    /// Another player joined or came into view
+    EntitySpawn { id: u32, name: String, position: [f32; 3], yaw: f32 },
+    EntityMove { id: u32, position: [f32; 3], yaw: f32 },
+    EntityDespawn { id: u32 },
+    /// Another player swung its arm
+    EntitySwing { id: u32 },
+    /// Another player's skin PNG, sent after its `EntitySpawn` and when it
+    /// changes; empty for the default skin
+    EntitySkin { id: u32, data: Vec<u8> },
+    /// Another player hit this one: lose `amount` health and get pushed by
+    /// `knockback`, added to the velocity
+    Hurt { amount: u32, knockback: [f32; 3] },
+    Chat { from: String, text: String },
+    /// Who is in bed changed
+    SleepStatus(SleepStatus),
+    /// Get out of bed: the night was skipped, it is day, or the server
+    /// didn't let the player sleep
+    WakeUp,
+    /// Sent every few seconds; a client that doesn't answer is dropped
+    KeepAlive(u64),
+}
+
+/// Chunk blocks for the network: (block, count) runs in storage order,
+/// bincode-encoded and LZ4-compressed. Terrain is mostly long runs of air and
+/// stone, and the runs of one layer repeat in the next, so a chunk shrinks
+/// from 64 KiB of blocks to a few hundred bytes.
+pub fn compress_blocks(blocks: &[BlockType]) -> Vec<u8> {
+    let mut runs: Vec<(BlockType, u16)> = Vec::new();
+    for &block in blocks {
+        match runs.last_mut() {
+            Some((last, count)) if *last == block && *count < u16::MAX => *count += 1,
+            _ => runs.push((block, 1)),
+        }
+    }
+    let encoded = bincode::serialize(&runs).expect("block runs always serialize");
+    lz4_flex::compress_prepend_size(&encoded)
+}
+
+/// Undo `compress_blocks`; fails on corrupt data and on runs adding up to
+/// more than `max_blocks`
+pub fn decompress_blocks(data: &[u8], max_blocks: usize) -> Result<Vec<BlockType>, String> {
+    // The size comes first; don't let a corrupt one allocate gigabytes
+    let size = data.get(..4).map(|size| u32::from_le_bytes(size.try_into().unwrap())).unwrap_or(0);
+    if size as usize > MAX_RUNS_SIZE {
+        return Err(format!("{} bytes of block runs is too many", size));
+    }
+    let encoded = lz4_flex::decompress_size_prepended(data).map_err(|e| e.to_string())?;
+    let runs: Vec<(BlockType, u16)> = bincode::deserialize(&encoded).map_err(|e| e.to_string())?;
+    // Nor a run count that stays within it but expands to gigabytes
+    let total = runs
+        .iter()
+        .try_fold(0usize, |total, &(_, count)| total.checked_add(count as usize).filter(|&total| total <= max_blocks))
+        .ok_or_else(|| format!("Block runs of more than {} blocks", max_blocks))?;
+    let mut blocks = Vec::with_capacity(total);
+    blocks.extend(runs.iter().flat_map(|&(block, count)| std::iter::repeat_n(block, count as usize)));
+    Ok(blocks)
+}
+
+/// Client end of a connection
+pub struct ClientConnection {
+    to_server: Sender<ClientMessage>,
+    from_server: Receiver<ServerMessage>,
+    /// Cleared once the server end is gone
+    open: Cell<bool>,
+}
+
+/// Server end of a connection
+pub struct ServerConnection {
+    to_client: Sender<ServerMessage>,
+    from_client: Receiver<ClientMessage>,
+    open: Cell<bool>,
+}
+
+/// A connected client and server end
+pub fn local_connection() -> (ClientConnection, ServerConnection) {
+    let (to_server, from_client) = mpsc::channel();
+    let (to_client, from_server) = mpsc::channel();
+    (
+        ClientConnection::new(to_server, from_server),
+        ServerConnection::new(to_client, from_client),
+    )
+}
+
+/// Everything queued on `receiver`, clearing `open` if the sender is gone
+fn drain<T>(receiver: &Receiver<T>, open: &Cell<bool>) -> Vec<T> {
+    let mut messages = Vec::new();
+    loop {
+        match receiver.try_recv() {
+            Ok(message) => messages.push(message),
+            Err(TryRecvError::Empty) => break,
+            Err(TryRecvError::Disconnected) => {
+                open.set(false);
+                break;
+            }
+        }
+    }
+    messages
+}
+
+impl ClientConnection {
+    /// Client end over channels bridged to some transport
+    pub fn new(to_server: Sender<ClientMessage>, from_server: Receiver<ServerMessage>) -> Self {
+        Self { to_server, from_server, open: Cell::new(true) }
+    }
+
+    /// Queue a message; one sent after the server is gone is dropped
+    pub fn send(&self, message: ClientMessage) {
+        if self.to_server.send(message).is_err() {
+            self.open.set(false);
+        }
+    }
+
+    /// Messages received since the last call, oldest first
+    pub fn receive(&self) -> Vec<ServerMessage> {
+        drain(&self.from_server, &self.open)
+    }
+
+    /// False once the server closed the connection
+    pub fn is_open(&self) -> bool {
+        self.open.get()
+    }
+}
+
+impl ServerConnection {
+    pub fn new(to_client: Sender<ServerMessage>, from_client: Receiver<ClientMessage>) -> Self {
+        Self { to_client, from_client, open: Cell::new(true) }
+    }
+
+    pub fn send(&self, message: ServerMessage) {
+        if self.to_client.send(message).is_err() {
+            self.open.set(false);
+        }
+    }
+
+    pub fn receive(&self) -> Vec<ClientMessage> {
+        drain(&self.from_client, &self.open)
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open.get()
+    }
+}
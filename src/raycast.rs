@@ -1,53 +1,171 @@
 use glam::Vec3;
+use crate::block::BlockType;
+use crate::physics::Aabb;
 use crate::world::World;
 
-pub struct RaycastResult {
-    pub hit: bool,
-    pub position: Option<(i32, i32, i32)>,
-    pub normal: Option<(i32, i32, i32)>,
+/// What a ray passes through and what stops it
+#[derive(Clone, Copy, Default)]
+pub struct RaycastFilter<'a> {
+    /// Pass through water, e.g. to place a block on the sea floor below it
+    pub ignore_liquids: bool,
+    /// Pass through blocks that don't fill their whole cell
+    pub ignore_partial_blocks: bool,
+    /// Entity boxes with their ids; the ray stops at the first it enters
+    pub entities: &'a [(u32, Aabb)],
 }
 
-pub fn raycast(origin: Vec3, direction: Vec3, max_distance: f32, world: &World) -> RaycastResult {
-    let step = 0.1;
-    let max_steps = (max_distance / step) as i32;
-
-    let mut current = origin;
-    let mut previous = origin;
-
-    for _ in 0..max_steps {
-        current += direction * step;
-
-        let x = current.x.floor() as i32;
-        let y = current.y.floor() as i32;
-        let z = current.z.floor() as i32;
-
-        if let Some(block) = world.get_block_at(x, y, z) {
-            if block.is_solid() {
-                // Calculate the normal based on which face was hit
-                let prev_x = previous.x.floor() as i32;
-                let prev_y = previous.y.floor() as i32;
-                let prev_z = previous.z.floor() as i32;
-
-                let normal = (
-                    prev_x - x,
-                    prev_y - y,
-                    prev_z - z,
-                );
-
-                return RaycastResult {
-                    hit: true,
-                    position: Some((x, y, z)),
-                    normal: Some(normal),
-                };
-            }
+impl RaycastFilter<'_> {
+    /// Every block, liquids included, and no entities
+    pub const ALL_BLOCKS: RaycastFilter<'static> = RaycastFilter {
+        ignore_liquids: false,
+        ignore_partial_blocks: false,
+        entities: &[],
+    };
+
+    /// Blocks the player can break, place against or use: liquids are looked through
+    pub const TARGET_BLOCKS: RaycastFilter<'static> = RaycastFilter {
+        ignore_liquids: true,
+        ..Self::ALL_BLOCKS
+    };
+
+    fn stops_at(&self, block: BlockType) -> bool {
+        block.is_solid()
+            && !(self.ignore_liquids && block.is_liquid())
+            && (!self.ignore_partial_blocks || block.is_full_cube())
+    }
+}
+
+/// What the ray hit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitTarget {
+    Block(BlockType),
+    /// Id of one of the filter's entities
+    Entity(u32),
+}
+
+/// Where a ray stopped
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaycastHit {
+    /// The block hit, or for an entity the block the ray was passing through
+    pub position: (i32, i32, i32),
+    /// Outward normal of the face the ray entered through; placing a block
+    /// puts it at `position + normal`
+    pub normal: (i32, i32, i32),
+    /// Distance from the origin to `point`
+    pub distance: f32,
+    /// Where the ray entered the block or entity box
+    pub point: Vec3,
+    pub target: HitTarget,
+}
+
+impl RaycastHit {
+    /// The block hit, unless the ray stopped at an entity
+    pub fn block(&self) -> Option<BlockType> {
+        match self.target {
+            HitTarget::Block(block) => Some(block),
+            HitTarget::Entity(_) => None,
         }
+    }
+}
 
-        previous = current;
+/// First block or entity along the ray within `max_distance` that `filter`
+/// stops at. Blocks are visited in the order the ray passes through them
+/// (Amanatides & Woo voxel traversal), so it can't slip past a corner. The
+/// block the ray starts in is never hit.
+pub fn raycast(origin: Vec3, direction: Vec3, max_distance: f32, world: &World, filter: RaycastFilter) -> Option<RaycastHit> {
+    let direction = direction.normalize_or_zero();
+    if direction == Vec3::ZERO || !origin.is_finite() {
+        return None;
     }
+    let origin_cell = origin.floor();
+    let mut cell = [origin_cell.x as i32, origin_cell.y as i32, origin_cell.z as i32];
+    let mut step = [0; 3];
+    // Distance along the ray to the next block boundary on each axis, and
+    // between two boundaries
+    let mut t_max = [f32::INFINITY; 3];
+    let mut t_delta = [f32::INFINITY; 3];
+    for axis in 0..3 {
+        let d = direction[axis];
+        if d > 0.0 {
+            step[axis] = 1;
+            t_max[axis] = (origin_cell[axis] + 1.0 - origin[axis]) / d;
+            t_delta[axis] = 1.0 / d;
+        } else if d < 0.0 {
+            step[axis] = -1;
+            t_max[axis] = (origin[axis] - origin_cell[axis]) / -d;
+            t_delta[axis] = -1.0 / d;
+        }
+    }
+
+    // The nearest entity in reach; blocks only count if they come first
+    let entity = filter
+        .entities
+        .iter()
+        .filter_map(|(id, aabb)| Some((*id, ray_box(origin, direction, aabb)?)))
+        .filter(|(_, (distance, _))| *distance <= max_distance)
+        .min_by(|(_, (a, _)), (_, (b, _))| a.total_cmp(b));
+
+    loop {
+        let axis = if t_max[0] <= t_max[1] && t_max[0] <= t_max[2] {
+            0
+        } else if t_max[1] <= t_max[2] {
+            1
+        } else {
+            2
+        };
+        let distance = t_max[axis];
+        if let Some((id, (entity_distance, normal))) = entity.filter(|(_, (d, _))| *d <= distance) {
+            return Some(RaycastHit {
+                position: (cell[0], cell[1], cell[2]),
+                normal,
+                distance: entity_distance,
+                point: origin + direction * entity_distance,
+                target: HitTarget::Entity(id),
+            });
+        }
+        if distance > max_distance {
+            return None;
+        }
+        cell[axis] += step[axis];
+        t_max[axis] += t_delta[axis];
+
+        let (x, y, z) = (cell[0], cell[1], cell[2]);
+        if let Some(block) = world.get_block_at(x, y, z).filter(|block| filter.stops_at(*block)) {
+            let mut normal = [0; 3];
+            normal[axis] = -step[axis];
+            return Some(RaycastHit {
+                position: (x, y, z),
+                normal: (normal[0], normal[1], normal[2]),
+                distance,
+                point: origin + direction * distance,
+                target: HitTarget::Block(block),
+            });
+        }
+    }
+}
 
-    RaycastResult {
-        hit: false,
-        position: None,
-        normal: None,
+/// Distance along the ray to where it enters `aabb`, and the normal of the
+/// face it enters through. A ray starting inside the box doesn't hit it.
+fn ray_box(origin: Vec3, direction: Vec3, aabb: &Aabb) -> Option<(f32, (i32, i32, i32))> {
+    let mut enter = f32::NEG_INFINITY;
+    let mut exit = f32::INFINITY;
+    let mut normal = [0; 3];
+    for axis in 0..3 {
+        let (o, d) = (origin[axis], direction[axis]);
+        if d == 0.0 {
+            if o < aabb.min[axis] || o > aabb.max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let (near, far) = if d > 0.0 { (aabb.min[axis], aabb.max[axis]) } else { (aabb.max[axis], aabb.min[axis]) };
+        let (t_near, t_far) = ((near - o) / d, (far - o) / d);
+        if t_near > enter {
+            enter = t_near;
+            normal = [0; 3];
+            normal[axis] = if d > 0.0 { -1 } else { 1 };
+        }
+        exit = exit.min(t_far);
     }
+    (enter >= 0.0 && enter <= exit).then_some((enter, (normal[0], normal[1], normal[2])))
 }
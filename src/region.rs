@@ -0,0 +1,73 @@
+//! Cuboid regions for the building commands: `//pos1` and `//pos2` pick two
+//! opposite corners, `//set` and `//fill` change the blocks between them and
+//! `//copy` and `//paste` move them elsewhere. Each player has a selection
+//! and a clipboard of their own.
+
+use crate::block::BlockType;
+use crate::world::World;
+
+/// Most blocks one command may change or copy, so a typo can't stall the server
+pub const MAX_REGION_BLOCKS: usize = 64 * 64 * 64;
+
+/// Lowest and highest corner of a cuboid
+pub type Bounds = ((i32, i32, i32), (i32, i32, i32));
+
+/// A player's two corners and copied blocks
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Selection {
+    pub pos1: Option<(i32, i32, i32)>,
+    pub pos2: Option<(i32, i32, i32)>,
+    pub clipboard: Option<Clipboard>,
+}
+
+impl Selection {
+    /// Lowest and highest corner of the selected cuboid, once both corners are set
+    pub fn bounds(&self) -> Option<Bounds> {
+        let (a, b) = (self.pos1?, self.pos2?);
+        Some(((a.0.min(b.0), a.1.min(b.1), a.2.min(b.2)), (a.0.max(b.0), a.1.max(b.1), a.2.max(b.2))))
+    }
+}
+
+/// Blocks copied with `//copy`, kept relative to where the player stood
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clipboard {
+    /// Lowest corner, from the block the player's feet were in
+    pub offset: (i32, i32, i32),
+    /// Blocks along each axis
+    pub size: (i32, i32, i32),
+    /// Blocks x fastest, then z, then y
+    pub blocks: Vec<BlockType>,
+}
+
+impl Clipboard {
+    /// Copy the blocks from `min` to `max` of `world`, relative to `origin`;
+    /// blocks in chunks that aren't loaded copy as air
+    pub fn copy(world: &World, min: (i32, i32, i32), max: (i32, i32, i32), origin: (i32, i32, i32)) -> Self {
+        let blocks = cuboid(min, max)
+            .map(|(x, y, z)| world.get_block_at(x, y, z).unwrap_or(BlockType::Air))
+            .collect();
+        Self {
+            offset: (min.0 - origin.0, min.1 - origin.1, min.2 - origin.2),
+            size: (max.0 - min.0 + 1, max.1 - min.1 + 1, max.2 - min.2 + 1),
+            blocks,
+        }
+    }
+
+    /// The blocks placed relative to `origin`, with their positions
+    pub fn placed_at(&self, origin: (i32, i32, i32)) -> impl Iterator<Item = ((i32, i32, i32), BlockType)> + '_ {
+        let min = (origin.0 + self.offset.0, origin.1 + self.offset.1, origin.2 + self.offset.2);
+        let max = (min.0 + self.size.0 - 1, min.1 + self.size.1 - 1, min.2 + self.size.2 - 1);
+        cuboid(min, max).zip(self.blocks.iter().copied())
+    }
+}
+
+/// Blocks in a cuboid, corners included
+pub fn volume(min: (i32, i32, i32), max: (i32, i32, i32)) -> usize {
+    let side = |low: i32, high: i32| (high as i64 - low as i64 + 1).max(0) as usize;
+    side(min.0, max.0).saturating_mul(side(min.1, max.1)).saturating_mul(side(min.2, max.2))
+}
+
+/// Every position of a cuboid, corners included, x fastest, then z, then y
+pub fn cuboid(min: (i32, i32, i32), max: (i32, i32, i32)) -> impl Iterator<Item = (i32, i32, i32)> {
+    (min.1..=max.1).flat_map(move |y| (min.2..=max.2).flat_map(move |z| (min.0..=max.0).map(move |x| (x, y, z))))
+}
@@ -0,0 +1,356 @@
+//! The other players as the client shows them. Their positions arrive a few
+//! times a second at best, so each is drawn gliding from where it was to the
+//! latest reported spot. They are boxy figures wearing their player's skin
+//! (see `skin`) that walk, face where their player looks and swing an arm when
+//! breaking or placing a block.
+
+use crate::debug_render::DebugVertex;
+use crate::labels::Label;
+use crate::physics::{Aabb, Player};
+use crate::skin::{self, Face, Skin};
+use glam::{Mat3, Vec2, Vec3};
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, PI};
+
+/// Seconds to glide from one reported position to the next
+const INTERPOLATION_TIME: f32 = 0.1;
+/// Seconds an arm swing lasts
+const SWING_TIME: f32 = 0.3;
+/// Leg and arm swing per block walked, in radians of the walk cycle
+const WALK_CYCLE_SPEED: f32 = 2.5;
+/// Largest angle legs and arms swing to while walking
+const WALK_SWING_ANGLE: f32 = 0.6;
+/// Height of the name tag above the player's feet
+const NAME_TAG_HEIGHT: f32 = 2.1;
+
+/// Size of a skin pixel on the figure: the 32 pixels from feet to the top
+/// of the head are the 1.8 blocks of the player hitbox
+const PIXEL: f32 = 1.8 / 32.0;
+/// How much larger than the first layer the second layer of a skin is drawn,
+/// in skin pixels, for the head and for the other parts
+const HAT_INFLATE: f32 = 0.5;
+const OUTER_INFLATE: f32 = 0.25;
+
+/// Shirt of the local player's own figure without a skin, seen from the
+/// spectator camera
+const OWN_SHIRT_COLOR: [f32; 3] = [0.2, 0.3, 0.75];
+/// Shirt colors of players without a skin, picked by player id so players
+/// tell apart
+const SHIRT_COLORS: [[f32; 3]; 6] = [
+    [0.15, 0.62, 0.66],
+    [0.75, 0.25, 0.2],
+    [0.3, 0.6, 0.25],
+    [0.85, 0.65, 0.15],
+    [0.55, 0.3, 0.7],
+    [0.9, 0.9, 0.9],
+];
+
+/// One other player
+pub struct RemotePlayer {
+    pub name: String,
+    /// Where the glide started and where it ends
+    from: Vec3,
+    to: Vec3,
+    from_yaw: f32,
+    to_yaw: f32,
+    /// Seconds since the last reported position
+    since_update: f32,
+    /// Drawn position and view direction
+    pub position: Vec3,
+    pub yaw: f32,
+    /// Walk cycle in radians, advanced by the distance walked
+    walk_phase: f32,
+    /// How far into walking the figure is, 0 standing to 1 walking
+    walk_amount: f32,
+    /// Seconds left of the current arm swing
+    swing: f32,
+    skin: Skin,
+}
+
+impl RemotePlayer {
+    fn new(name: String, position: Vec3, yaw: f32, skin: Skin) -> Self {
+        Self {
+            name,
+            from: position,
+            to: position,
+            from_yaw: yaw,
+            to_yaw: yaw,
+            since_update: INTERPOLATION_TIME,
+            position,
+            yaw,
+            walk_phase: 0.0,
+            walk_amount: 0.0,
+            swing: 0.0,
+            skin,
+        }
+    }
+
+    fn update(&mut self, delta_time: f32) {
+        self.since_update += delta_time;
+        let t = (self.since_update / INTERPOLATION_TIME).min(1.0);
+        let previous = self.position;
+        self.position = self.from.lerp(self.to, t);
+        self.yaw = lerp_angle(self.from_yaw, self.to_yaw, t);
+
+        let walked = Vec2::new(self.position.x - previous.x, self.position.z - previous.z).length();
+        self.walk_phase = (self.walk_phase + walked * WALK_CYCLE_SPEED) % (2.0 * PI);
+        let walking = if walked > 0.001 { 1.0 } else { 0.0 };
+        // Ease in and out of the walk over about a fifth of a second
+        self.walk_amount += (walking - self.walk_amount) * (delta_time * 5.0).min(1.0);
+        self.swing = (self.swing - delta_time).max(0.0);
+    }
+}
+
+/// Shortest way from angle `a` to `b`, `t` of the way
+fn lerp_angle(a: f32, b: f32, t: f32) -> f32 {
+    let difference = (b - a + PI).rem_euclid(2.0 * PI) - PI;
+    a + difference * t
+}
+
+pub struct RemotePlayers {
+    players: HashMap<u32, RemotePlayer>,
+    /// The local player's figure while the camera has left it
+    own: Option<RemotePlayer>,
+    own_skin: Skin,
+}
+
+impl Default for RemotePlayers {
+    fn default() -> Self {
+        Self { players: HashMap::new(), own: None, own_skin: Skin::default_with_shirt(OWN_SHIRT_COLOR) }
+    }
+}
+
+/// Skin of the player `id` before it sends its own
+fn default_skin(id: u32) -> Skin {
+    Skin::default_with_shirt(SHIRT_COLORS[id as usize % SHIRT_COLORS.len()])
+}
+
+impl RemotePlayers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.players.is_empty()
+    }
+
+    #[allow(dead_code)]
+    pub fn iter(&self) -> impl Iterator<Item = &RemotePlayer> {
+        self.players.values()
+    }
+
+    pub fn spawn(&mut self, id: u32, name: String, position: Vec3, yaw: f32) {
+        self.players.insert(id, RemotePlayer::new(name, position, yaw, default_skin(id)));
+    }
+
+    /// Dress the player in its skin, or back in the default one
+    pub fn set_skin(&mut self, id: u32, skin: Option<Skin>) {
+        if let Some(player) = self.players.get_mut(&id) {
+            player.skin = skin.unwrap_or_else(|| default_skin(id));
+        }
+    }
+
+    /// Skin of the local player's own figure, None for the default one
+    pub fn set_own_skin(&mut self, skin: Option<Skin>) {
+        self.own_skin = skin.unwrap_or_else(|| Skin::default_with_shirt(OWN_SHIRT_COLOR));
+        if let Some(own) = &mut self.own {
+            own.skin = self.own_skin.clone();
+        }
+    }
+
+    /// Draw the local player's figure at `body` (feet position and yaw)
+    /// from the next `update` on, or stop drawing it; for when the camera has
+    /// left the player
+    pub fn set_body(&mut self, body: Option<(Vec3, f32)>) {
+        match (&mut self.own, body) {
+            (Some(own), Some((position, yaw))) => {
+                // Straight there; `update` still sees how far it walked
+                (own.from, own.to, own.from_yaw, own.to_yaw) = (position, position, yaw, yaw);
+            }
+            (_, body) => {
+                self.own = body.map(|(position, yaw)| RemotePlayer::new(String::new(), position, yaw, self.own_skin.clone()));
+            }
+        }
+    }
+
+    /// Start gliding towards a newly reported position
+    pub fn move_to(&mut self, id: u32, position: Vec3, yaw: f32) {
+        if let Some(player) = self.players.get_mut(&id) {
+            player.from = player.position;
+            player.from_yaw = player.yaw;
+            player.to = position;
+            player.to_yaw = yaw;
+            player.since_update = 0.0;
+        }
+    }
+
+    /// Remove a player, returning its name
+    pub fn despawn(&mut self, id: u32) -> Option<String> {
+        self.players.remove(&id).map(|player| player.name)
+    }
+
+    /// The player broke, placed or used a block
+    pub fn swing(&mut self, id: u32) {
+        if let Some(player) = self.players.get_mut(&id) {
+            player.swing = SWING_TIME;
+        }
+    }
+
+    /// Each player's hitbox where it is drawn, with its id
+    pub fn hitboxes(&self) -> Vec<(u32, Aabb)> {
+        self.players.iter().map(|(&id, player)| (id, Player::new(player.position).bounding_box)).collect()
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        for player in self.players.values_mut().chain(&mut self.own) {
+            player.update(delta_time);
+        }
+    }
+
+    /// Triangles of every player's figure, plus the local player's while
+    /// `set_body` has placed it
+    pub fn build_mesh(&self) -> (Vec<DebugVertex>, Vec<u32>) {
+        let mut mesh = FigureMesh::default();
+        if let Some(own) = &self.own {
+            mesh.add_figure(own);
+        }
+        let mut ids: Vec<_> = self.players.keys().copied().collect();
+        ids.sort_unstable();
+        for id in ids {
+            mesh.add_figure(&self.players[&id]);
+        }
+        (mesh.vertices, mesh.indices)
+    }
+
+    /// Name tags above the players
+    pub fn labels(&self) -> Vec<Label> {
+        self.players
+            .values()
+            .map(|player| Label { text: player.name.clone(), position: player.position + Vec3::Y * NAME_TAG_HEIGHT })
+            .collect()
+    }
+}
+
+/// Solid triangles of the player figures
+#[derive(Default)]
+struct FigureMesh {
+    vertices: Vec<DebugVertex>,
+    indices: Vec<u32>,
+}
+
+impl FigureMesh {
+    /// Legs, body, arms and head in the proportions of the skin layout,
+    /// each a box of the skin's first layer inside one of its second
+    fn add_figure(&mut self, player: &RemotePlayer) {
+        // Model space faces +z; turn it to the player's yaw (0 looks along +x)
+        let facing = Mat3::from_rotation_y(FRAC_PI_2 - player.yaw);
+        let swing = (player.walk_phase.sin() * WALK_SWING_ANGLE) * player.walk_amount;
+        // The right arm chops forward and back once per swing
+        let action = (1.0 - player.swing / SWING_TIME) * PI;
+        let right_arm = if player.swing > 0.0 { -action.sin() * 1.4 } else { -swing };
+
+        let feet = player.position;
+        // Corner and size in skin pixels, the height of the hip or shoulder
+        // the part swings about and the angle, and the skin regions
+        let parts = [
+            ([-4.0, 0.0, -2.0], [4, 12, 4], 12.0, swing, skin::RIGHT_LEG),
+            ([0.0, 0.0, -2.0], [4, 12, 4], 12.0, -swing, skin::LEFT_LEG),
+            ([-4.0, 12.0, -2.0], [8, 12, 4], 0.0, 0.0, skin::BODY),
+            ([-8.0, 12.0, -2.0], [4, 12, 4], 22.0, right_arm, skin::RIGHT_ARM),
+            ([4.0, 12.0, -2.0], [4, 12, 4], 22.0, swing, skin::LEFT_ARM),
+            ([-4.0, 24.0, -4.0], [8, 8, 8], 0.0, 0.0, skin::HEAD),
+        ];
+        for (min, size, pivot_y, angle, [inner, outer]) in parts {
+            // Limbs swing about the x axis through their hip or shoulder
+            let pivot = Vec3::new(0.0, pivot_y, 0.0) * PIXEL;
+            let limb = Mat3::from_rotation_x(angle);
+            let transform = |p: Vec3| feet + facing * (pivot + limb * (p * PIXEL - pivot));
+            let inflate = if inner == skin::HEAD[0] { HAT_INFLATE } else { OUTER_INFLATE };
+            self.add_skin_box(&player.skin, inner, Vec3::from(min), size, 0.0, transform);
+            self.add_skin_box(&player.skin, outer, Vec3::from(min), size, inflate, transform);
+        }
+    }
+
+    /// The box at `min`, `size` skin pixels large and grown by `inflate` on
+    /// every side, with each face covered by its region of the skin at
+    /// `origin`: a quad for every run of same colored pixels in a row of the
+    /// region, leaving out transparent ones. Faces are shaded by direction
+    /// like `add_cuboid`.
+    fn add_skin_box(&mut self, skin: &Skin, origin: (u32, u32), min: Vec3, size: [u32; 3], inflate: f32, transform: impl Fn(Vec3) -> Vec3) {
+        let (lo, hi) = (min - inflate, min + Vec3::new(size[0] as f32, size[1] as f32, size[2] as f32) + inflate);
+        let extent = hi - lo;
+        let (x, y, z) = (Vec3::X * extent.x, Vec3::Y * extent.y, Vec3::Z * extent.z);
+        for face in Face::ALL {
+            // Corner of the face at the region's top left, and the edges along
+            // its columns and rows
+            let (corner, along, down, shade) = match face {
+                Face::Top => (Vec3::new(lo.x, hi.y, lo.z), x, z, 1.0),
+                Face::Bottom => (Vec3::new(lo.x, lo.y, hi.z), x, -z, 0.5),
+                Face::Right => (Vec3::new(lo.x, hi.y, lo.z), z, -y, 0.65),
+                Face::Front => (Vec3::new(lo.x, hi.y, hi.z), x, -y, 0.8),
+                Face::Left => (Vec3::new(hi.x, hi.y, hi.z), -z, -y, 0.65),
+                Face::Back => (Vec3::new(hi.x, hi.y, lo.z), -x, -y, 0.8),
+            };
+            let (columns, rows) = face.texels(size);
+            let point = |s: u32, t: u32| transform(corner + along * (s as f32 / columns as f32) + down * (t as f32 / rows as f32));
+            for t in 0..rows {
+                let texel = |s: u32| {
+                    let (u, v) = Skin::face_texel(origin, size, face, s, t);
+                    skin.color(u, v)
+                };
+                let mut s = 0;
+                while s < columns {
+                    let Some(color) = texel(s) else {
+                        s += 1;
+                        continue;
+                    };
+                    let end = (s + 1..columns).find(|&end| texel(end) != Some(color)).unwrap_or(columns);
+                    let base = self.vertices.len() as u32;
+                    let color = [color[0] * shade, color[1] * shade, color[2] * shade, 1.0];
+                    for (s, t) in [(s, t), (end, t), (end, t + 1), (s, t + 1)] {
+                        self.vertices.push(DebugVertex { position: point(s, t).to_array(), color });
+                    }
+                    self.indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+                    s = end;
+                }
+            }
+        }
+    }
+}
+
+/// Triangles of a box with its six faces shaded by direction, like the
+/// terrain; `transform` places its corners in the world
+pub fn add_cuboid(
+    vertices: &mut Vec<DebugVertex>,
+    indices: &mut Vec<u32>,
+    min: Vec3,
+    max: Vec3,
+    transform: impl Fn(Vec3) -> Vec3,
+    color: [f32; 3],
+) {
+    let corner = |x: bool, y: bool, z: bool| {
+        transform(Vec3::new(
+            if x { max.x } else { min.x },
+            if y { max.y } else { min.y },
+            if z { max.z } else { min.z },
+        ))
+    };
+    // Corners of each face (counter-clockwise from outside) and its brightness
+    let faces = [
+        ([(0, 1, 0), (0, 1, 1), (1, 1, 1), (1, 1, 0)], 1.0),
+        ([(0, 0, 0), (1, 0, 0), (1, 0, 1), (0, 0, 1)], 0.5),
+        ([(0, 0, 1), (1, 0, 1), (1, 1, 1), (0, 1, 1)], 0.8),
+        ([(1, 0, 0), (0, 0, 0), (0, 1, 0), (1, 1, 0)], 0.8),
+        ([(1, 0, 1), (1, 0, 0), (1, 1, 0), (1, 1, 1)], 0.65),
+        ([(0, 0, 0), (0, 0, 1), (0, 1, 1), (0, 1, 0)], 0.65),
+    ];
+    for (corners, shade) in faces {
+        let base = vertices.len() as u32;
+        let color = [color[0] * shade, color[1] * shade, color[2] * shade, 1.0];
+        for (x, y, z) in corners {
+            let position = corner(x == 1, y == 1, z == 1).to_array();
+            vertices.push(DebugVertex { position, color });
+        }
+        indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+}
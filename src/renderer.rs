@@ -1,46 +1,248 @@
+use crate::buffer_pool::{BufferPool, MeshMemory};
 use crate::camera::Camera;
-use crate::mesh::MeshBuilder;
-use crate::ui::{UiRenderer, UiVertex};
-use crate::vertex::{Uniforms, Vertex};
+use crate::chunk::{Chunk, CHUNK_SIZE};
+use crate::chunk_loader::{self, FrameBudget};
+use crate::chunk_snapshot::ChunkSnapshot;
+use crate::debug_render::{DebugMesh, DebugVertex};
+use crate::font;
+use crate::labels::LabelVertex;
+use crate::mesh::{MeshBuilder, MeshIndices};
+use crate::profiler::{Phase, Profiler};
+use crate::resource_pack::TextureAtlas;
+use crate::ui::{UiIconVertex, UiRenderer, UiScale, UiVertex};
+use crate::vertex::{ChunkOffset, Uniforms, Vertex};
 use crate::world::World;
+use crate::world_border;
+use serde::{Deserialize, Serialize};
 use wgpu::util::DeviceExt;
 use std::collections::HashMap;
+use std::sync::Arc;
+use web_time::Instant;
+use winit::window::Window;
+
+/// Chunks beyond the view distance whose meshes stay cached, so turning back
+/// doesn't rebuild them; see `Renderer::mesh_memory` for what this costs
+const MESH_CACHE_MARGIN: i32 = 2;
+
+/// Graphics API to render with. `auto` lets wgpu pick among the native APIs;
+/// whichever is chosen, the others are tried if it fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphicsBackend {
+    #[default]
+    Auto,
+    Vulkan,
+    Dx12,
+    Metal,
+    /// OpenGL / OpenGL ES, the last resort on old drivers
+    Gl,
+}
 
-fn load_texture_atlas(
-    device: &wgpu::Device,
-    queue: &wgpu::Queue,
-) -> Result<(wgpu::Texture, wgpu::TextureView, wgpu::Sampler), String> {
-    // Try to load texture atlas from textures directory
-    let texture_bytes = match std::fs::read("textures/atlas.png") {
-        Ok(bytes) => bytes,
-        Err(_) => {
-            // Try individual texture as fallback
-            match std::fs::read("textures/dirt.png") {
-                Ok(bytes) => bytes,
-                Err(_) => {
-                    // If texture loading fails, create a simple 16x16 white texture as fallback
-                    return create_fallback_texture(device, queue);
-                }
+impl GraphicsBackend {
+    fn backends(self) -> wgpu::Backends {
+        match self {
+            GraphicsBackend::Auto => wgpu::Backends::PRIMARY,
+            GraphicsBackend::Vulkan => wgpu::Backends::VULKAN,
+            GraphicsBackend::Dx12 => wgpu::Backends::DX12,
+            GraphicsBackend::Metal => wgpu::Backends::METAL,
+            GraphicsBackend::Gl => wgpu::Backends::GL,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GraphicsBackend::Auto => "auto",
+            GraphicsBackend::Vulkan => "Vulkan",
+            GraphicsBackend::Dx12 => "DirectX 12",
+            GraphicsBackend::Metal => "Metal",
+            GraphicsBackend::Gl => "OpenGL",
+        }
+    }
+
+    /// Backends to try in order: this one, then the native APIs, then OpenGL
+    pub fn fallback_order(self) -> Vec<GraphicsBackend> {
+        let mut order = vec![self];
+        for backend in [
+            GraphicsBackend::Vulkan,
+            GraphicsBackend::Dx12,
+            GraphicsBackend::Metal,
+            GraphicsBackend::Gl,
+        ] {
+            // Auto already covers the native APIs
+            let covered = self == GraphicsBackend::Auto && backend != GraphicsBackend::Gl;
+            if backend != self && !covered {
+                order.push(backend);
             }
         }
-    };
+        order
+    }
+}
+
+/// What drawing all chunks with one `multi_draw_indexed_indirect` takes; the
+/// device asks for these where the adapter has them
+fn multi_draw_features() -> wgpu::Features {
+    wgpu::Features::MULTI_DRAW_INDIRECT | wgpu::Features::INDIRECT_FIRST_INSTANCE
+}
+
+/// Find a backend and adapter that can present to the window. Every failed
+/// attempt is logged and listed in the error.
+async fn request_device(
+    window: Arc<Window>,
+    preference: GraphicsBackend,
+) -> Result<(wgpu::Surface<'static>, wgpu::Adapter, wgpu::Device, wgpu::Queue), String> {
+    let mut failures = Vec::new();
+    for backend in preference.fallback_order() {
+        match try_backend(window.clone(), backend).await {
+            Ok(result) => return Ok(result),
+            Err(e) => {
+                log::warn!("Graphics backend {} unavailable: {}", backend.name(), e);
+                failures.push(format!("{}: {}", backend.name(), e));
+            }
+        }
+    }
+    Err(format!(
+        "No usable graphics adapter was found. Updating the graphics drivers usually helps.\n\n{}",
+        failures.join("\n")
+    ))
+}
 
-    let img = match image::load_from_memory(&texture_bytes) {
-        Ok(img) => img.to_rgba8(),
-        Err(_) => return create_fallback_texture(device, queue),
+async fn try_backend(
+    window: Arc<Window>,
+    backend: GraphicsBackend,
+) -> Result<(wgpu::Surface<'static>, wgpu::Adapter, wgpu::Device, wgpu::Queue), String> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: backend.backends(),
+        ..Default::default()
+    });
+    let surface = instance.create_surface(window).map_err(|e| e.to_string())?;
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: Some(&surface),
+            force_fallback_adapter: false,
+        })
+        .await
+        .ok_or("no compatible adapter")?;
+    if surface.get_capabilities(&adapter).formats.is_empty() {
+        return Err("the adapter can't present to the window".to_string());
+    }
+
+    // Older GPUs and OpenGL may fall short of the default limits; the game
+    // gets by with the downlevel ones
+    let limits = if wgpu::Limits::default().check_limits(&adapter.limits()) {
+        wgpu::Limits::default()
+    } else {
+        wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits())
     };
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                required_features: adapter.features() & multi_draw_features(),
+                required_limits: limits,
+                label: None,
+            },
+            None,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok((surface, adapter, device, queue))
+}
+
+/// Write the graphics adapter and its limits to the log, for bug reports
+fn log_adapter(adapter: &wgpu::Adapter) {
+    let info = adapter.get_info();
+    log::info!(
+        "Adapter: {} ({:?}, {:?} backend), driver {} {}",
+        info.name,
+        info.device_type,
+        info.backend,
+        info.driver,
+        info.driver_info
+    );
+    let limits = adapter.limits();
+    log::info!(
+        "Limits: max texture {}, max buffer {} bytes, max bind groups {}, max vertex buffers {}",
+        limits.max_texture_dimension_2d,
+        limits.max_buffer_size,
+        limits.max_bind_groups,
+        limits.max_vertex_buffers
+    );
+}
 
-    let dimensions = img.dimensions();
+/// Unlit, alpha-blended pipeline for the debug overlays, depth tested against
+/// the world. Overlays don't write depth; solid geometry such as the other
+/// players' figures does.
+fn create_debug_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    topology: wgpu::PrimitiveTopology,
+    depth_write: bool,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Debug Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[DebugVertex::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: depth_write,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
+/// Upload a block texture atlas, with its mipmaps if `mipmapping`, and
+/// create its view and pixel-art sampler
+fn create_atlas_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    atlas: &TextureAtlas,
+    mipmapping: bool,
+) -> (wgpu::Texture, wgpu::TextureView, wgpu::Sampler) {
+    let image = &atlas.image;
+    let dimensions = image.dimensions();
     let size = wgpu::Extent3d {
         width: dimensions.0,
         height: dimensions.1,
         depth_or_array_layers: 1,
     };
+    let mipmaps = if mipmapping { atlas.mipmaps() } else { Vec::new() };
 
     let texture = device.create_texture(&wgpu::TextureDescriptor {
         label: Some("Block Texture Atlas"),
         size,
-        mip_level_count: 1,
+        mip_level_count: 1 + mipmaps.len() as u32,
         sample_count: 1,
         dimension: wgpu::TextureDimension::D2,
         format: wgpu::TextureFormat::Rgba8UnormSrgb,
@@ -55,7 +257,7 @@ fn load_texture_atlas(
             origin: wgpu::Origin3d::ZERO,
             aspect: wgpu::TextureAspect::All,
         },
-        &img,
+        image,
         wgpu::ImageDataLayout {
             offset: 0,
             bytes_per_row: Some(4 * dimensions.0),
@@ -63,6 +265,24 @@ fn load_texture_atlas(
         },
         size,
     );
+    for (level, mipmap) in mipmaps.iter().enumerate() {
+        let (width, height) = mipmap.dimensions();
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: level as u32 + 1,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            mipmap,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        );
+    }
 
     let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
     let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
@@ -71,40 +291,30 @@ fn load_texture_atlas(
         address_mode_w: wgpu::AddressMode::Repeat,
         mag_filter: wgpu::FilterMode::Nearest,
         min_filter: wgpu::FilterMode::Nearest,
-        mipmap_filter: wgpu::FilterMode::Nearest,
+        // Blend between mipmaps so their switch doesn't show as a line
+        mipmap_filter: wgpu::FilterMode::Linear,
         ..Default::default()
     });
 
-    Ok((texture, view, sampler))
+    (texture, view, sampler)
 }
 
-fn create_fallback_texture(
-    device: &wgpu::Device,
-    queue: &wgpu::Queue,
-) -> Result<(wgpu::Texture, wgpu::TextureView, wgpu::Sampler), String> {
-    // Create a simple 16x16 white texture
-    let size = wgpu::Extent3d {
-        width: 16,
-        height: 16,
-        depth_or_array_layers: 1,
-    };
-
+/// Upload the font atlas for the world-space labels and bind it with a
+/// pixel-art sampler
+fn create_font_bind_group(device: &wgpu::Device, queue: &wgpu::Queue, layout: &wgpu::BindGroupLayout) -> wgpu::BindGroup {
+    let atlas = font::atlas();
+    let (width, height) = atlas.dimensions();
+    let size = wgpu::Extent3d { width, height, depth_or_array_layers: 1 };
     let texture = device.create_texture(&wgpu::TextureDescriptor {
-        label: Some("Fallback Texture"),
+        label: Some("Font Atlas"),
         size,
         mip_level_count: 1,
         sample_count: 1,
         dimension: wgpu::TextureDimension::D2,
-        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        format: wgpu::TextureFormat::Rgba8Unorm,
         usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
         view_formats: &[],
     });
-
-    // Create white pixels
-    let pixels: Vec<u8> = (0..16 * 16)
-        .flat_map(|_| [255u8, 255u8, 255u8, 255u8])
-        .collect();
-
     queue.write_texture(
         wgpu::ImageCopyTexture {
             texture: &texture,
@@ -112,32 +322,185 @@ fn create_fallback_texture(
             origin: wgpu::Origin3d::ZERO,
             aspect: wgpu::TextureAspect::All,
         },
-        &pixels,
+        &atlas,
         wgpu::ImageDataLayout {
             offset: 0,
-            bytes_per_row: Some(4 * 16),
-            rows_per_image: Some(16),
+            bytes_per_row: Some(4 * width),
+            rows_per_image: Some(height),
         },
         size,
     );
-
     let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
     let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-        address_mode_u: wgpu::AddressMode::Repeat,
-        address_mode_v: wgpu::AddressMode::Repeat,
-        address_mode_w: wgpu::AddressMode::Repeat,
         mag_filter: wgpu::FilterMode::Nearest,
         min_filter: wgpu::FilterMode::Nearest,
-        mipmap_filter: wgpu::FilterMode::Nearest,
         ..Default::default()
     });
+    create_texture_bind_group(device, layout, &view, &sampler)
+}
+
+fn create_texture_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+        label: Some("texture_bind_group"),
+    })
+}
+
+/// Upload `data` to the terrain buffer `current`: it goes back to `pool`
+/// and a buffer large enough for the data comes out, reused if possible.
+/// No data leaves no buffer.
+fn upload_pooled(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    pool: &mut BufferPool<wgpu::Buffer>,
+    current: &mut Option<wgpu::Buffer>,
+    label: &str,
+    usage: wgpu::BufferUsages,
+    data: &[u8],
+) {
+    if let Some(buffer) = current.take() {
+        pool.give_back(buffer);
+    }
+    if data.is_empty() {
+        return;
+    }
+    let buffer = pool.take(data.len() as u64, |size| {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage: usage | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    });
+    queue.write_buffer(&buffer, 0, data);
+    *current = Some(buffer);
+}
 
-    Ok((texture, view, sampler))
+/// UI shader uniform: virtual-pixel to NDC scale, padded to 16 bytes
+fn ui_uniform_data(scale: &UiScale) -> [f32; 4] {
+    let [x, y] = scale.ndc_scale();
+    [x, y, 0.0, 0.0]
+}
+
+/// GPU buffers for one UI layer (crosshair, toolbar, ...)
+#[derive(Default)]
+struct UiLayer {
+    vertex_buffer: Option<wgpu::Buffer>,
+    index_buffer: Option<wgpu::Buffer>,
+    num_indices: u32,
+}
+
+impl UiLayer {
+    /// Replace the layer contents; an empty mesh clears the layer
+    fn upload<V: bytemuck::Pod>(&mut self, device: &wgpu::Device, label: &str, vertices: &[V], indices: &[u32]) {
+        if vertices.is_empty() {
+            self.vertex_buffer = None;
+            self.index_buffer = None;
+            self.num_indices = 0;
+            return;
+        }
+
+        self.vertex_buffer = Some(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Vertex Buffer", label)),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        }));
+        self.index_buffer = Some(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Index Buffer", label)),
+            contents: bytemuck::cast_slice(indices),
+            usage: wgpu::BufferUsages::INDEX,
+        }));
+        self.num_indices = indices.len() as u32;
+    }
+
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if let (Some(vertex_buffer), Some(index_buffer)) = (&self.vertex_buffer, &self.index_buffer) {
+            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+        }
+    }
 }
 
 pub struct ChunkMesh {
     pub vertices: Vec<Vertex>,
-    pub indices: Vec<u32>,
+    pub indices: MeshIndices,
+    /// When the chunk first showed, on the renderer's clock
+    pub appeared: f32,
+}
+
+/// One chunk's draw, laid out as `wgpu::util::DrawIndexedIndirectArgs` so a
+/// list of them goes to the GPU as it is
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ChunkDraw {
+    pub index_count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub base_vertex: i32,
+    /// The chunk's entry in the offsets
+    pub first_instance: u32,
+}
+
+/// The visible chunk meshes laid out for drawing. Every chunk takes a range of
+/// one vertex and one index buffer, as multi-draws need, and has a draw and
+/// an offset of its own.
+pub struct TerrainBatch {
+    pub vertices: Vec<Vertex>,
+    pub indices: MeshIndices,
+    pub draws: Vec<ChunkDraw>,
+    pub offsets: Vec<ChunkOffset>,
+}
+
+impl TerrainBatch {
+    /// Lay out the `meshes` of the chunks at their keys. With `base_vertex`
+    /// each chunk's indices count from its own first vertex, so they stay
+    /// 16-bit however many chunks there are; GPUs that can't add a base
+    /// vertex get them offset into the shared buffer instead.
+    pub fn build(meshes: &[((i32, i32), &ChunkMesh)], base_vertex: bool) -> Self {
+        let vertex_count = meshes.iter().map(|(_, mesh)| mesh.vertices.len()).sum();
+        let widest = match base_vertex {
+            true => meshes.iter().map(|(_, mesh)| mesh.vertices.len()).max().unwrap_or(0),
+            false => vertex_count,
+        };
+        let mut batch = Self {
+            vertices: Vec::with_capacity(vertex_count),
+            indices: MeshIndices::for_vertices(widest),
+            draws: Vec::with_capacity(meshes.len()),
+            offsets: Vec::with_capacity(meshes.len()),
+        };
+        for (i, &((x, z), mesh)) in meshes.iter().enumerate() {
+            let first_vertex = batch.vertices.len() as u32;
+            let first_index = batch.indices.len() as u32;
+            batch.vertices.extend_from_slice(&mesh.vertices);
+            batch.indices.extend_offset(&mesh.indices, if base_vertex { 0 } else { first_vertex });
+            batch.draws.push(ChunkDraw {
+                index_count: mesh.indices.len() as u32,
+                instance_count: 1,
+                first_index,
+                base_vertex: if base_vertex { first_vertex as i32 } else { 0 },
+                first_instance: i as u32,
+            });
+            let origin = [(x * CHUNK_SIZE as i32) as f32, 0.0, (z * CHUNK_SIZE as i32) as f32];
+            batch.offsets.push(ChunkOffset { origin });
+        }
+        batch
+    }
 }
 
 pub struct Renderer {
@@ -148,66 +511,115 @@ pub struct Renderer {
     pub size: winit::dpi::PhysicalSize<u32>,
     render_pipeline: wgpu::RenderPipeline,
     ui_pipeline: wgpu::RenderPipeline,
+    ui_icon_pipeline: wgpu::RenderPipeline,
+    debug_line_pipeline: wgpu::RenderPipeline,
+    debug_plane_pipeline: wgpu::RenderPipeline,
+    /// Translucent striped walls of the world border
+    border_pipeline: wgpu::RenderPipeline,
+    /// Solid, depth-writing variant of the debug pipelines for the player figures
+    entity_pipeline: wgpu::RenderPipeline,
+    /// Text in the world, such as the other players' names
+    label_pipeline: wgpu::RenderPipeline,
+    font_bind_group: wgpu::BindGroup,
     uniform_buffer: wgpu::Buffer,
     uniform_bind_group: wgpu::BindGroup,
     texture_bind_group: wgpu::BindGroup,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    /// The current block texture atlas, kept to upload again with or
+    /// without mipmaps
+    atlas: TextureAtlas,
+    mipmapping: bool,
+    /// Whether chunk meshes are built with smooth lighting
+    smooth_lighting: bool,
     uniforms: Uniforms,
+    /// Virtual-pixel to NDC scale for the UI shaders
+    ui_uniform_buffer: wgpu::Buffer,
+    ui_uniform_bind_group: wgpu::BindGroup,
     depth_texture: wgpu::Texture,
     depth_view: wgpu::TextureView,
+    /// Terrain of all visible chunks, each in a range of the same buffers
+    /// (see `TerrainBatch`)
     vertex_buffer: Option<wgpu::Buffer>,
     index_buffer: Option<wgpu::Buffer>,
-    num_indices: u32,
-    crosshair_vertex_buffer: Option<wgpu::Buffer>,
-    crosshair_index_buffer: Option<wgpu::Buffer>,
-    crosshair_num_indices: u32,
-    toolbar_vertex_buffer: Option<wgpu::Buffer>,
-    toolbar_index_buffer: Option<wgpu::Buffer>,
-    toolbar_num_indices: u32,
-    inventory_vertex_buffer: Option<wgpu::Buffer>,
-    inventory_index_buffer: Option<wgpu::Buffer>,
-    inventory_num_indices: u32,
+    /// 16-bit while every chunk, or without base vertices all of them
+    /// together, has few enough vertices
+    index_format: wgpu::IndexFormat,
+    /// Chunk origins, one instance per chunk draw
+    offset_buffer: Option<wgpu::Buffer>,
+    /// The chunk draws, for `multi_draw_indexed_indirect`; the draws are
+    /// kept on the CPU too, for GPUs without it
+    draw_buffer: Option<wgpu::Buffer>,
+    chunk_draws: Vec<ChunkDraw>,
+    /// The GPU draws all chunks from one indirect buffer
+    multi_draw: bool,
+    /// The GPU adds a base vertex to indices; otherwise chunks share them
+    base_vertex: bool,
+    /// Terrain buffers replaced by larger or smaller ones, kept for reuse
+    vertex_pool: BufferPool<wgpu::Buffer>,
+    index_pool: BufferPool<wgpu::Buffer>,
+    /// Bytes of mesh data in the terrain buffers
+    terrain_bytes: u64,
+    /// Chunk borders and hitboxes, see `DebugMesh`
+    debug_line_layer: UiLayer,
+    debug_plane_layer: UiLayer,
+    entity_layer: UiLayer,
+    weather_layer: UiLayer,
+    border_layer: UiLayer,
+    label_layer: UiLayer,
+    crosshair_layer: UiLayer,
+    toolbar_layer: UiLayer,
+    toolbar_icon_layer: UiLayer,
+    hud_layer: UiLayer,
+    action_bar_layer: UiLayer,
+    compass_layer: UiLayer,
+    minimap_layer: UiLayer,
+    inventory_layer: UiLayer,
+    inventory_icon_layer: UiLayer,
+    console_layer: UiLayer,
+    menu_layer: UiLayer,
+    loading_layer: UiLayer,
+    toast_layer: UiLayer,
+    sky_color: wgpu::Color,
+    /// Draw the world and the in-game HUD; off on the title screen
+    world_visible: bool,
     chunk_mesh_cache: HashMap<(i32, i32), ChunkMesh>,
+    /// Clock of animations in the shaders, such as chunks fading in
+    clock: Instant,
+    /// Name and backend of the graphics adapter
+    adapter: String,
 }
 
 impl Renderer {
-    pub async fn new(window: std::sync::Arc<winit::window::Window>) -> Self {
+    /// Set up rendering to `window`, trying the preferred backend first and
+    /// then the others. Fails with a readable message if none of them work.
+    pub async fn new(
+        window: Arc<Window>,
+        atlas: &TextureAtlas,
+        backend: GraphicsBackend,
+    ) -> Result<Self, String> {
         let size = window.inner_size();
 
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            ..Default::default()
-        });
-
-        let surface = instance.create_surface(window).unwrap();
-
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
-
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    required_features: wgpu::Features::empty(),
-                    required_limits: wgpu::Limits::default(),
-                    label: None,
-                },
-                None,
-            )
-            .await
-            .unwrap();
+        let (surface, adapter, device, queue) = request_device(window, backend).await?;
+        log_adapter(&adapter);
+        let info = adapter.get_info();
+        let adapter_name = format!("{} ({:?} backend)", info.name, info.backend);
+        let downlevel = adapter.get_downlevel_capabilities().flags;
+        let multi_draw = device.features().contains(multi_draw_features()) && downlevel.contains(wgpu::DownlevelFlags::INDIRECT_EXECUTION);
+        let base_vertex = downlevel.contains(wgpu::DownlevelFlags::BASE_VERTEX);
+        log::info!("Terrain drawn with {}", if multi_draw { "one indirect multi-draw" } else { "a draw per chunk" });
 
         let surface_caps = surface.get_capabilities(&adapter);
-        let surface_format = surface_caps
-            .formats
-            .iter()
-            .copied()
-            .find(|f| f.is_srgb())
-            .unwrap_or(surface_caps.formats[0]);
+        // The shaders output linear colors and rely on an sRGB target to encode them
+        let surface_format = match surface_caps.formats.iter().copied().find(|f| f.is_srgb()) {
+            Some(format) => format,
+            None => {
+                log::warn!(
+                    "No sRGB surface format, using {:?}; colors will look darker",
+                    surface_caps.formats[0]
+                );
+                surface_caps.formats[0]
+            }
+        };
 
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
@@ -220,6 +632,7 @@ impl Renderer {
             desired_maximum_frame_latency: 2,
         };
         surface.configure(&device, &config);
+        log::info!("Surface: {}x{} {:?}", size.width, size.height, surface_format);
 
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader"),
@@ -238,7 +651,7 @@ impl Renderer {
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -258,11 +671,7 @@ impl Renderer {
             label: Some("uniform_bind_group"),
         });
 
-        // Load texture
-        let (_texture, texture_view, texture_sampler) = 
-            load_texture_atlas(&device, &queue).unwrap_or_else(|_| {
-                create_fallback_texture(&device, &queue).unwrap()
-            });
+        let (_texture, texture_view, texture_sampler) = create_atlas_texture(&device, &queue, atlas, false);
 
         // Create texture bind group layout
         let texture_bind_group_layout = 
@@ -288,20 +697,8 @@ impl Renderer {
                 label: Some("texture_bind_group_layout"),
             });
 
-        let texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&texture_sampler),
-                },
-            ],
-            label: Some("texture_bind_group"),
-        });
+        let texture_bind_group =
+            create_texture_bind_group(&device, &texture_bind_group_layout, &texture_view, &texture_sampler);
 
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -333,7 +730,7 @@ impl Renderer {
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: "vs_main",
-                buffers: &[Vertex::desc()],
+                buffers: &[Vertex::desc(), ChunkOffset::desc()],
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
@@ -368,6 +765,37 @@ impl Renderer {
             multiview: None,
         });
 
+        // UI geometry is in virtual pixels; this uniform maps it to NDC
+        let ui_uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("UI Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[ui_uniform_data(&UiScale::new(config.width, config.height, 0))]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let ui_uniform_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("ui_uniform_bind_group_layout"),
+            });
+
+        let ui_uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &ui_uniform_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: ui_uniform_buffer.as_entire_binding(),
+            }],
+            label: Some("ui_uniform_bind_group"),
+        });
+
         // Create UI pipeline
         let ui_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("UI Shader"),
@@ -376,7 +804,7 @@ impl Renderer {
 
         let ui_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("UI Pipeline Layout"),
-            bind_group_layouts: &[],
+            bind_group_layouts: &[&ui_uniform_bind_group_layout],
             push_constant_ranges: &[],
         });
 
@@ -427,7 +855,155 @@ impl Renderer {
             multiview: None,
         });
 
-        Self {
+        // Textured UI pipeline for block thumbnails, sharing the world's atlas bind group
+        let ui_icon_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("UI Icon Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("ui_icon_shader.wgsl").into()),
+        });
+
+        let ui_icon_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("UI Icon Pipeline Layout"),
+            bind_group_layouts: &[&ui_uniform_bind_group_layout, &texture_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let ui_icon_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("UI Icon Pipeline"),
+            layout: Some(&ui_icon_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &ui_icon_shader,
+                entry_point: "vs_main",
+                buffers: &[UiIconVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &ui_icon_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            // Same depth setup as the UI pipeline: no depth test or writes
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        // Debug overlays draw over the world, depth tested against it but not writing depth
+        let debug_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Debug Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("debug_shader.wgsl").into()),
+        });
+        let debug_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Debug Pipeline Layout"),
+            bind_group_layouts: &[&uniform_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let debug_line_pipeline = create_debug_pipeline(
+            &device,
+            &debug_pipeline_layout,
+            &debug_shader,
+            config.format,
+            wgpu::PrimitiveTopology::LineList,
+            false,
+        );
+        let debug_plane_pipeline = create_debug_pipeline(
+            &device,
+            &debug_pipeline_layout,
+            &debug_shader,
+            config.format,
+            wgpu::PrimitiveTopology::TriangleList,
+            false,
+        );
+        let border_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Border Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("border_shader.wgsl").into()),
+        });
+        let border_pipeline = create_debug_pipeline(
+            &device,
+            &debug_pipeline_layout,
+            &border_shader,
+            config.format,
+            wgpu::PrimitiveTopology::TriangleList,
+            false,
+        );
+        let entity_pipeline = create_debug_pipeline(
+            &device,
+            &debug_pipeline_layout,
+            &debug_shader,
+            config.format,
+            wgpu::PrimitiveTopology::TriangleList,
+            true,
+        );
+
+        // Labels blend over the figures, depth tested but not writing depth
+        let label_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Label Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("label_shader.wgsl").into()),
+        });
+        let label_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Label Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &label_shader,
+                entry_point: "vs_main",
+                buffers: &[LabelVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &label_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+        let font_bind_group = create_font_bind_group(&device, &queue, &texture_bind_group_layout);
+
+        Ok(Self {
             surface,
             device,
             queue,
@@ -435,26 +1011,66 @@ impl Renderer {
             size,
             render_pipeline,
             ui_pipeline,
+            ui_icon_pipeline,
+            debug_line_pipeline,
+            debug_plane_pipeline,
+            border_pipeline,
+            entity_pipeline,
+            label_pipeline,
+            font_bind_group,
             uniform_buffer,
             uniform_bind_group,
             texture_bind_group,
+            texture_bind_group_layout,
+            atlas: TextureAtlas { image: atlas.image.clone(), layout: atlas.layout.clone() },
+            mipmapping: false,
+            smooth_lighting: true,
             uniforms,
+            ui_uniform_buffer,
+            ui_uniform_bind_group,
             depth_texture,
             depth_view,
             vertex_buffer: None,
             index_buffer: None,
-            num_indices: 0,
-            crosshair_vertex_buffer: None,
-            crosshair_index_buffer: None,
-            crosshair_num_indices: 0,
-            toolbar_vertex_buffer: None,
-            toolbar_index_buffer: None,
-            toolbar_num_indices: 0,
-            inventory_vertex_buffer: None,
-            inventory_index_buffer: None,
-            inventory_num_indices: 0,
+            index_format: wgpu::IndexFormat::Uint32,
+            offset_buffer: None,
+            draw_buffer: None,
+            chunk_draws: Vec::new(),
+            multi_draw,
+            base_vertex,
+            vertex_pool: BufferPool::new(),
+            index_pool: BufferPool::new(),
+            terrain_bytes: 0,
+            debug_line_layer: UiLayer::default(),
+            debug_plane_layer: UiLayer::default(),
+            entity_layer: UiLayer::default(),
+            weather_layer: UiLayer::default(),
+            border_layer: UiLayer::default(),
+            label_layer: UiLayer::default(),
+            crosshair_layer: UiLayer::default(),
+            toolbar_layer: UiLayer::default(),
+            toolbar_icon_layer: UiLayer::default(),
+            hud_layer: UiLayer::default(),
+            action_bar_layer: UiLayer::default(),
+            compass_layer: UiLayer::default(),
+            minimap_layer: UiLayer::default(),
+            inventory_layer: UiLayer::default(),
+            inventory_icon_layer: UiLayer::default(),
+            console_layer: UiLayer::default(),
+            menu_layer: UiLayer::default(),
+            loading_layer: UiLayer::default(),
+            toast_layer: UiLayer::default(),
+            sky_color: wgpu::Color {
+                r: 0.53,
+                g: 0.81,
+                b: 0.92,
+                a: 1.0,
+            },
+            world_visible: true,
             chunk_mesh_cache: HashMap::new(),
-        }
+            clock: Instant::now(),
+            adapter: adapter_name,
+        })
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
@@ -486,101 +1102,156 @@ impl Renderer {
         }
     }
 
-    pub fn update_mesh(&mut self, world: &mut World, camera: &Camera, view_distance: i32) {
+    /// Switch to another block texture atlas, e.g. of a new resource pack.
+    /// Chunk meshes are rebuilt with its tiles on the next mesh update.
+    pub fn set_texture_atlas(&mut self, atlas: &TextureAtlas) {
+        self.atlas = TextureAtlas { image: atlas.image.clone(), layout: atlas.layout.clone() };
+        self.upload_atlas();
+        self.chunk_mesh_cache.clear();
+    }
+
+    /// Draw distant blocks with smaller copies of their textures
+    pub fn set_mipmapping(&mut self, enabled: bool) {
+        if self.mipmapping != enabled {
+            self.mipmapping = enabled;
+            self.upload_atlas();
+        }
+    }
+
+    /// Build chunk meshes with smooth or flat lighting; the meshes built so
+    /// far are built again
+    pub fn set_smooth_lighting(&mut self, enabled: bool) {
+        if self.smooth_lighting != enabled {
+            self.smooth_lighting = enabled;
+            self.chunk_mesh_cache.clear();
+        }
+    }
+
+    fn upload_atlas(&mut self) {
+        let (_texture, view, sampler) = create_atlas_texture(&self.device, &self.queue, &self.atlas, self.mipmapping);
+        self.texture_bind_group =
+            create_texture_bind_group(&self.device, &self.texture_bind_group_layout, &view, &sampler);
+    }
+
+    /// Build the meshes of dirty and new chunks in view, at most `budget` of
+    /// them in rings outwards from the camera, and upload the visible ones.
+    /// Returns false if some are left for the next call.
+    pub fn update_mesh(&mut self, world: &mut World, camera: &Camera, view_distance: i32, budget: FrameBudget, profiler: &mut Profiler) -> bool {
+        let start = Instant::now();
         let cam_chunk_x = (camera.position.x / 16.0).floor() as i32;
         let cam_chunk_z = (camera.position.z / 16.0).floor() as i32;
 
         let render_distance = view_distance;
         
         // Evict chunks from cache that are too far away (beyond render distance + buffer)
-        let eviction_distance = render_distance + 2;
+        let eviction_distance = render_distance + MESH_CACHE_MARGIN;
         self.chunk_mesh_cache.retain(|&(chunk_x, chunk_z), _| {
             let dx = (chunk_x - cam_chunk_x).abs();
             let dz = (chunk_z - cam_chunk_z).abs();
             dx <= eviction_distance && dz <= eviction_distance
         });
         
-        // Build or update chunk meshes for dirty chunks
-        for dx in -render_distance..=render_distance {
-            for dz in -render_distance..=render_distance {
-                let chunk_x = cam_chunk_x + dx;
-                let chunk_z = cam_chunk_z + dz;
-                let chunk_key = (chunk_x, chunk_z);
-
-                if let Some(chunk) = world.get_chunk(chunk_x, chunk_z) {
-                    // Only rebuild mesh if chunk is dirty or not cached
-                    if chunk.dirty || !self.chunk_mesh_cache.contains_key(&chunk_key) {
-                        let mut mesh_builder = MeshBuilder::new();
-                        mesh_builder.build_chunk_mesh(chunk, world);
-                        
-                        self.chunk_mesh_cache.insert(chunk_key, ChunkMesh {
-                            vertices: mesh_builder.vertices,
-                            indices: mesh_builder.indices,
-                        });
-                    }
-                }
+        // Build or update chunk meshes for dirty chunks, nearest first
+        let mut built = 0;
+        let mut done = true;
+        for chunk_key in (0..=render_distance).flat_map(|distance| chunk_loader::ring((cam_chunk_x, cam_chunk_z), distance)) {
+            let Some(chunk) = world.get_chunk(chunk_key.0, chunk_key.1) else {
+                continue;
+            };
+            // Only rebuild mesh if chunk is dirty or not cached
+            if !chunk.dirty && self.chunk_mesh_cache.contains_key(&chunk_key) {
+                continue;
             }
-        }
-        
-        // Mark all visible chunks as clean
-        for dx in -render_distance..=render_distance {
-            for dz in -render_distance..=render_distance {
-                let chunk_x = cam_chunk_x + dx;
-                let chunk_z = cam_chunk_z + dz;
-                if let Some(chunk) = world.get_chunk_mut(chunk_x, chunk_z) {
-                    chunk.mark_clean();
-                }
+            if budget.spent(built, start) {
+                done = false;
+                break;
             }
-        }
-        
-        // Combine all visible chunk meshes into single buffers
-        let mut all_vertices = Vec::new();
-        let mut all_indices = Vec::new();
-        
-        for dx in -render_distance..=render_distance {
-            for dz in -render_distance..=render_distance {
-                let chunk_x = cam_chunk_x + dx;
-                let chunk_z = cam_chunk_z + dz;
-                let chunk_key = (chunk_x, chunk_z);
-                
-                if let Some(chunk_mesh) = self.chunk_mesh_cache.get(&chunk_key) {
-                    let vertex_offset = all_vertices.len() as u32;
-                    all_vertices.extend_from_slice(&chunk_mesh.vertices);
-                    
-                    // Offset indices by current vertex count
-                    for &index in &chunk_mesh.indices {
-                        all_indices.push(index + vertex_offset);
-                    }
-                }
+            let Some(snapshot) = ChunkSnapshot::capture(&world.chunks, chunk_key.0, chunk_key.1) else {
+                continue;
+            };
+            let mut mesh_builder = MeshBuilder::new();
+            mesh_builder.smooth_lighting = self.smooth_lighting;
+            mesh_builder.build_chunk_mesh(&snapshot, &self.atlas.layout);
+
+            // A rebuilt chunk stays as it is; one showing for the first time
+            // fades in. Vertices are kept relative to the chunk, whose draw
+            // adds its origin.
+            let appeared = self.chunk_mesh_cache.get(&chunk_key).map_or(self.clock.elapsed().as_secs_f32(), |mesh| mesh.appeared);
+            let origin = [(chunk_key.0 * CHUNK_SIZE as i32) as f32, (chunk_key.1 * CHUNK_SIZE as i32) as f32];
+            for vertex in &mut mesh_builder.vertices {
+                vertex.appeared = appeared;
+                vertex.position[0] -= origin[0];
+                vertex.position[2] -= origin[1];
             }
+            let indices = MeshIndices::new(mesh_builder.indices, mesh_builder.vertices.len());
+            self.chunk_mesh_cache.insert(chunk_key, ChunkMesh {
+                vertices: mesh_builder.vertices,
+                indices,
+                appeared,
+            });
+            world.update_chunk(chunk_key.0, chunk_key.1, Chunk::mark_clean);
+            built += 1;
         }
+        
+        // Lay the visible chunk meshes out in shared buffers, a draw each
+        let visible: Vec<((i32, i32), &ChunkMesh)> = chunk_loader::area((cam_chunk_x, cam_chunk_z), render_distance)
+            .filter_map(|chunk_key| Some((chunk_key, self.chunk_mesh_cache.get(&chunk_key)?)))
+            .collect();
+        let mut batch = TerrainBatch::build(&visible, self.base_vertex);
+        self.index_format = match batch.indices {
+            MeshIndices::U16(_) => wgpu::IndexFormat::Uint16,
+            MeshIndices::U32(_) => wgpu::IndexFormat::Uint32,
+        };
+        batch.indices.pad_to_words();
 
-        if !all_vertices.is_empty() {
-            self.vertex_buffer = Some(
-                self.device
-                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                        label: Some("Vertex Buffer"),
-                        contents: bytemuck::cast_slice(&all_vertices),
-                        usage: wgpu::BufferUsages::VERTEX,
-                    }),
-            );
-
-            self.index_buffer = Some(
-                self.device
-                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                        label: Some("Index Buffer"),
-                        contents: bytemuck::cast_slice(&all_indices),
-                        usage: wgpu::BufferUsages::INDEX,
-                    }),
-            );
+        let start = profiler.record(Phase::Meshing, start);
+
+        let vertex_data: &[u8] = bytemuck::cast_slice(&batch.vertices);
+        let index_data = batch.indices.as_bytes();
+        let (device, queue) = (&self.device, &self.queue);
+        upload_pooled(device, queue, &mut self.vertex_pool, &mut self.vertex_buffer, "Vertex Buffer", wgpu::BufferUsages::VERTEX, vertex_data);
+        upload_pooled(device, queue, &mut self.index_pool, &mut self.index_buffer, "Index Buffer", wgpu::BufferUsages::INDEX, index_data);
+        let small_buffer = |label: &str, data: &[u8], usage: wgpu::BufferUsages| {
+            (!data.is_empty()).then(|| device.create_buffer_init(&wgpu::util::BufferInitDescriptor { label: Some(label), contents: data, usage }))
+        };
+        self.offset_buffer = small_buffer("Chunk Offset Buffer", bytemuck::cast_slice(&batch.offsets), wgpu::BufferUsages::VERTEX);
+        self.draw_buffer = match self.multi_draw {
+            true => small_buffer("Chunk Draw Buffer", bytemuck::cast_slice(&batch.draws), wgpu::BufferUsages::INDIRECT),
+            false => None,
+        };
+        self.chunk_draws = batch.draws;
+        self.terrain_bytes = (vertex_data.len() + index_data.len()) as u64;
+        profiler.record(Phase::Upload, start);
+        done
+    }
 
-            self.num_indices = all_indices.len() as u32;
+    /// Memory taken by the terrain meshes on the CPU and the GPU
+    pub fn mesh_memory(&self) -> MeshMemory {
+        let buffer_size = |buffer: &Option<wgpu::Buffer>| buffer.as_ref().map_or(0, wgpu::Buffer::size);
+        let cached_bytes = self
+            .chunk_mesh_cache
+            .values()
+            .map(|mesh| std::mem::size_of_val(mesh.vertices.as_slice()) + mesh.indices.as_bytes().len())
+            .sum::<usize>();
+        MeshMemory {
+            cached_chunks: self.chunk_mesh_cache.len(),
+            cached_bytes: cached_bytes as u64,
+            gpu_bytes: buffer_size(&self.vertex_buffer)
+                + buffer_size(&self.index_buffer)
+                + buffer_size(&self.offset_buffer)
+                + buffer_size(&self.draw_buffer),
+            pooled_bytes: self.vertex_pool.free_bytes() + self.index_pool.free_bytes(),
+            used_bytes: self.terrain_bytes,
+            reused: self.vertex_pool.reused + self.index_pool.reused,
+            allocated: self.vertex_pool.allocated + self.index_pool.allocated,
         }
     }
 
     pub fn update_camera(&mut self, camera: &Camera) {
         self.uniforms
             .update_view_proj(camera.get_view_matrix(), camera.get_projection_matrix());
+        self.uniforms.update_camera_pos(camera.position);
+        self.uniforms.set_time(self.clock.elapsed().as_secs_f32());
         self.queue.write_buffer(
             &self.uniform_buffer,
             0,
@@ -589,80 +1260,150 @@ impl Renderer {
     }
 
     pub fn update_ui(&mut self, ui: &UiRenderer) {
-        // Update crosshair buffers
-        let (crosshair_verts, crosshair_inds) = ui.get_crosshair_buffers();
-        if !crosshair_verts.is_empty() {
-            self.crosshair_vertex_buffer = Some(
-                self.device
-                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                        label: Some("Crosshair Vertex Buffer"),
-                        contents: bytemuck::cast_slice(crosshair_verts),
-                        usage: wgpu::BufferUsages::VERTEX,
-                    }),
-            );
-            self.crosshair_index_buffer = Some(
-                self.device
-                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                        label: Some("Crosshair Index Buffer"),
-                        contents: bytemuck::cast_slice(crosshair_inds),
-                        usage: wgpu::BufferUsages::INDEX,
-                    }),
-            );
-            self.crosshair_num_indices = crosshair_inds.len() as u32;
-        }
+        let (verts, inds) = ui.get_crosshair_buffers();
+        self.crosshair_layer.upload(&self.device, "Crosshair", verts, inds);
 
-        // Update toolbar buffers
-        let (toolbar_verts, toolbar_inds) = ui.get_toolbar_buffers();
-        if !toolbar_verts.is_empty() {
-            self.toolbar_vertex_buffer = Some(
-                self.device
-                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                        label: Some("Toolbar Vertex Buffer"),
-                        contents: bytemuck::cast_slice(toolbar_verts),
-                        usage: wgpu::BufferUsages::VERTEX,
-                    }),
-            );
-            self.toolbar_index_buffer = Some(
-                self.device
-                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                        label: Some("Toolbar Index Buffer"),
-                        contents: bytemuck::cast_slice(toolbar_inds),
-                        usage: wgpu::BufferUsages::INDEX,
-                    }),
-            );
-            self.toolbar_num_indices = toolbar_inds.len() as u32;
+        let (verts, inds) = ui.get_toolbar_buffers();
+        self.toolbar_layer.upload(&self.device, "Toolbar", verts, inds);
+
+        let (verts, inds) = ui.get_toolbar_icon_buffers();
+        self.toolbar_icon_layer.upload(&self.device, "Toolbar Icons", verts, inds);
+
+        let (verts, inds) = ui.get_hud_buffers();
+        self.hud_layer.upload(&self.device, "HUD", verts, inds);
+
+        let (verts, inds) = ui.get_action_bar_buffers();
+        self.action_bar_layer.upload(&self.device, "Action Bar", verts, inds);
+
+        let (verts, inds) = ui.get_compass_buffers();
+        self.compass_layer.upload(&self.device, "Compass", verts, inds);
+
+        let (verts, inds) = ui.get_minimap_buffers();
+        self.minimap_layer.upload(&self.device, "Minimap", verts, inds);
+
+        // Inventory and console meshes are empty while closed, which clears their layers
+        let (verts, inds) = ui.get_inventory_buffers();
+        self.inventory_layer.upload(&self.device, "Inventory", verts, inds);
+
+        let (verts, inds) = ui.get_inventory_icon_buffers();
+        self.inventory_icon_layer.upload(&self.device, "Inventory Icons", verts, inds);
+
+        let (verts, inds) = ui.get_console_buffers();
+        self.console_layer.upload(&self.device, "Console", verts, inds);
+
+        let (verts, inds) = ui.get_menu_buffers();
+        self.menu_layer.upload(&self.device, "Menu", verts, inds);
+
+        let (verts, inds) = ui.get_loading_buffers();
+        self.loading_layer.upload(&self.device, "Loading Screen", verts, inds);
+
+        let (verts, inds) = ui.get_toast_buffers();
+        self.toast_layer.upload(&self.device, "Toasts", verts, inds);
+    }
+
+    /// Replace the debug overlay geometry; an empty mesh hides it
+    pub fn update_debug(&mut self, mesh: &DebugMesh) {
+        self.debug_line_layer.upload(&self.device, "Debug Lines", &mesh.line_vertices, &mesh.line_indices);
+        self.debug_plane_layer.upload(&self.device, "Debug Planes", &mesh.plane_vertices, &mesh.plane_indices);
+    }
+
+    /// Replace the other players' figures
+    pub fn update_entities(&mut self, vertices: &[DebugVertex], indices: &[u32]) {
+        self.entity_layer.upload(&self.device, "Players", vertices, indices);
+    }
+
+    /// Replace the falling rain and snow streaks
+    pub fn update_weather(&mut self, vertices: &[DebugVertex], indices: &[u32]) {
+        self.weather_layer.upload(&self.device, "Weather", vertices, indices);
+    }
+
+    /// Replace the world border walls; None removes them
+    pub fn update_border(&mut self, border: Option<i32>) {
+        let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+        if let Some(radius) = border {
+            world_border::build_wall_mesh(radius, &mut vertices, &mut indices);
         }
+        self.border_layer.upload(&self.device, "World Border", &vertices, &indices);
+    }
 
-        // Update inventory buffers
-        let (inventory_verts, inventory_inds) = ui.get_inventory_buffers();
-        if !inventory_verts.is_empty() {
-            self.inventory_vertex_buffer = Some(
-                self.device
-                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                        label: Some("Inventory Vertex Buffer"),
-                        contents: bytemuck::cast_slice(inventory_verts),
-                        usage: wgpu::BufferUsages::VERTEX,
-                    }),
-            );
-            self.inventory_index_buffer = Some(
-                self.device
-                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                        label: Some("Inventory Index Buffer"),
-                        contents: bytemuck::cast_slice(inventory_inds),
-                        usage: wgpu::BufferUsages::INDEX,
-                    }),
-            );
-            self.inventory_num_indices = inventory_inds.len() as u32;
+    /// Replace the labels in the world; they turn with the camera, so they
+    /// are built again every frame they show
+    pub fn update_labels(&mut self, vertices: &[LabelVertex], indices: &[u32]) {
+        self.label_layer.upload(&self.device, "Labels", vertices, indices);
+    }
+
+    /// Set the UI virtual screen size, e.g. after a resize or GUI scale change
+    pub fn set_ui_scale(&mut self, scale: &UiScale) {
+        self.queue.write_buffer(&self.ui_uniform_buffer, 0, bytemuck::cast_slice(&[ui_uniform_data(scale)]));
+    }
+
+    /// Switch between vsync'd (Fifo) and uncapped presentation
+    pub fn set_vsync(&mut self, enabled: bool) {
+        let present_mode = if enabled {
+            wgpu::PresentMode::Fifo
         } else {
-            // Clear inventory buffers when inventory is closed
-            self.inventory_vertex_buffer = None;
-            self.inventory_index_buffer = None;
-            self.inventory_num_indices = 0;
+            wgpu::PresentMode::AutoNoVsync
+        };
+        if self.config.present_mode != present_mode {
+            self.config.present_mode = present_mode;
+            self.surface.configure(&self.device, &self.config);
         }
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+    /// Configure distance fog; it fades out the last part of the loaded view distance
+    pub fn set_fog(&mut self, enabled: bool, view_distance: i32) {
+        let end = (view_distance.max(1) * 16) as f32;
+        self.uniforms.set_fog(enabled, end * 0.6, end);
+    }
+
+    /// Set the clear color used for the sky
+    pub fn set_sky_color(&mut self, color: [f32; 3]) {
+        self.uniforms.set_fog_color(color);
+        self.sky_color = wgpu::Color {
+            r: color[0] as f64,
+            g: color[1] as f64,
+            b: color[2] as f64,
+            a: 1.0,
+        };
+    }
+
+    /// Dim the world, e.g. under rain clouds; 1 is full brightness
+    pub fn set_light(&mut self, brightness: f32) {
+        self.uniforms.set_light(brightness);
+    }
+
+    /// Show or hide the world together with the toolbar, HUD, minimap and crosshair
+    pub fn set_world_visible(&mut self, visible: bool) {
+        self.world_visible = visible;
+    }
+
+    /// Draw a thumbnail layer with the textured pipeline, then switch back to the plain UI pipeline
+    fn draw_icon_layer<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, layer: &'a UiLayer) {
+        if layer.num_indices == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.ui_icon_pipeline);
+        render_pass.set_bind_group(1, &self.texture_bind_group, &[]);
+        layer.draw(render_pass);
+        render_pass.set_pipeline(&self.ui_pipeline);
+    }
+
+    #[cfg_attr(feature = "debug-ui", allow(dead_code))]
+    pub fn render(&mut self, profiler: &mut Profiler) -> Result<(), wgpu::SurfaceError> {
+        self.render_with(profiler, |_, _, _, _| {})
+    }
+
+    /// Render the frame, then let `overlay` record extra passes onto the same
+    /// surface texture before it is presented (used by the egui debug UI)
+    pub fn render_with(
+        &mut self,
+        profiler: &mut Profiler,
+        overlay: impl FnOnce(&wgpu::Device, &wgpu::Queue, &mut wgpu::CommandEncoder, &wgpu::TextureView),
+    ) -> Result<(), wgpu::SurfaceError> {
+        // Acquiring the next surface texture is where vsync usually blocks
+        let start = Instant::now();
         let output = self.surface.get_current_texture()?;
+        let start = profiler.record(Phase::Present, start);
         let view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
@@ -680,12 +1421,7 @@ impl Renderer {
                     view: &view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.53,
-                            g: 0.81,
-                            b: 0.92,
-                            a: 1.0,
-                        }),
+                        load: wgpu::LoadOp::Clear(self.sky_color),
                         store: wgpu::StoreOp::Store,
                     },
                 })],
@@ -706,48 +1442,106 @@ impl Renderer {
             render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
             render_pass.set_bind_group(1, &self.texture_bind_group, &[]);
 
-            if let (Some(vertex_buffer), Some(index_buffer)) =
-                (&self.vertex_buffer, &self.index_buffer)
+            if let (true, Some(vertex_buffer), Some(index_buffer), Some(offset_buffer)) =
+                (self.world_visible, &self.vertex_buffer, &self.index_buffer, &self.offset_buffer)
             {
                 render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-                render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-                render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+                render_pass.set_index_buffer(index_buffer.slice(..), self.index_format);
+                if let Some(draw_buffer) = &self.draw_buffer {
+                    render_pass.set_vertex_buffer(1, offset_buffer.slice(..));
+                    render_pass.multi_draw_indexed_indirect(draw_buffer, 0, self.chunk_draws.len() as u32);
+                } else {
+                    // A call per chunk, each with its offset as the only instance
+                    let stride = std::mem::size_of::<ChunkOffset>() as u64;
+                    for (i, draw) in self.chunk_draws.iter().enumerate() {
+                        let offset = i as u64 * stride;
+                        render_pass.set_vertex_buffer(1, offset_buffer.slice(offset..offset + stride));
+                        render_pass.draw_indexed(draw.first_index..draw.first_index + draw.index_count, draw.base_vertex, 0..1);
+                    }
+                }
+            }
+
+            if self.world_visible {
+                render_pass.set_pipeline(&self.entity_pipeline);
+                self.entity_layer.draw(&mut render_pass);
+                render_pass.set_pipeline(&self.debug_plane_pipeline);
+                self.debug_plane_layer.draw(&mut render_pass);
+                render_pass.set_pipeline(&self.border_pipeline);
+                self.border_layer.draw(&mut render_pass);
+                render_pass.set_pipeline(&self.debug_line_pipeline);
+                self.weather_layer.draw(&mut render_pass);
+                self.debug_line_layer.draw(&mut render_pass);
+                if self.label_layer.num_indices > 0 {
+                    render_pass.set_pipeline(&self.label_pipeline);
+                    render_pass.set_bind_group(1, &self.font_bind_group, &[]);
+                    self.label_layer.draw(&mut render_pass);
+                }
             }
 
             // Render UI elements
             render_pass.set_pipeline(&self.ui_pipeline);
-
-            // Render toolbar
-            if let (Some(vertex_buffer), Some(index_buffer)) =
-                (&self.toolbar_vertex_buffer, &self.toolbar_index_buffer)
-            {
-                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-                render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-                render_pass.draw_indexed(0..self.toolbar_num_indices, 0, 0..1);
+            render_pass.set_bind_group(0, &self.ui_uniform_bind_group, &[]);
+
+            // Render toolbar, HUD and minimap, then inventory, console and menu (if open), then crosshair on top.
+            // Block thumbnails use the textured icon pipeline on top of their panel.
+            if self.world_visible {
+                self.toolbar_layer.draw(&mut render_pass);
+                self.draw_icon_layer(&mut render_pass, &self.toolbar_icon_layer);
+                self.hud_layer.draw(&mut render_pass);
+                self.compass_layer.draw(&mut render_pass);
+                self.minimap_layer.draw(&mut render_pass);
             }
-
-            // Render inventory (if open)
-            if let (Some(vertex_buffer), Some(index_buffer)) =
-                (&self.inventory_vertex_buffer, &self.inventory_index_buffer)
-            {
-                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-                render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-                render_pass.draw_indexed(0..self.inventory_num_indices, 0, 0..1);
+            self.inventory_layer.draw(&mut render_pass);
+            self.draw_icon_layer(&mut render_pass, &self.inventory_icon_layer);
+            self.console_layer.draw(&mut render_pass);
+            self.menu_layer.draw(&mut render_pass);
+            if self.world_visible {
+                self.crosshair_layer.draw(&mut render_pass);
+                // Above the sleeping screen, which shows it
+                self.action_bar_layer.draw(&mut render_pass);
             }
+            self.toast_layer.draw(&mut render_pass);
 
-            // Render crosshair
-            if let (Some(vertex_buffer), Some(index_buffer)) =
-                (&self.crosshair_vertex_buffer, &self.crosshair_index_buffer)
-            {
-                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-                render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-                render_pass.draw_indexed(0..self.crosshair_num_indices, 0, 0..1);
-            }
+            // The loading screen covers everything while the spawn area generates
+            self.loading_layer.draw(&mut render_pass);
         }
 
+        overlay(&self.device, &self.queue, &mut encoder, &view);
+
         self.queue.submit(std::iter::once(encoder.finish()));
+        let start = profiler.record(Phase::RenderPass, start);
         output.present();
+        profiler.record(Phase::Present, start);
 
         Ok(())
     }
+
+    /// Name and backend of the graphics adapter
+    pub fn adapter(&self) -> &str {
+        &self.adapter
+    }
+}
+
+/// Accessors for the egui debug UI
+#[cfg(feature = "debug-ui")]
+impl Renderer {
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn surface_format(&self) -> wgpu::TextureFormat {
+        self.config.format
+    }
+
+    /// Cached chunk meshes and their total vertex and index counts
+    pub fn chunk_cache_stats(&self) -> (usize, usize, usize) {
+        let vertices = self.chunk_mesh_cache.values().map(|mesh| mesh.vertices.len()).sum();
+        let indices = self.chunk_mesh_cache.values().map(|mesh| mesh.indices.len()).sum();
+        (self.chunk_mesh_cache.len(), vertices, indices)
+    }
+
+    /// Drop every cached chunk mesh, e.g. after the terrain was regenerated
+    pub fn clear_chunk_cache(&mut self) {
+        self.chunk_mesh_cache.clear();
+    }
 }
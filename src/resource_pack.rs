@@ -0,0 +1,352 @@
+//! Resource packs replace the block textures. A pack is a folder or `.zip` in
+//! `resourcepacks/` laid out as
+//!
+//! ```text
+//! textures/<name>.png            one square texture, e.g. textures/stone.png
+//! textures/<name>_connected.png  optional connected texture, see below
+//! blocks.json                    optional block -> texture map, e.g. {"furnace": "cobblestone"}
+//! ```
+//!
+//! A block uses the texture named after it unless `blocks.json` maps it to
+//! another one. Anything a pack leaves out comes from the built-in textures.
+//!
+//! A connected texture is a square sheet of 4x4 tiles, one for each set of
+//! edges a face shares with faces of the same block next to it, so a wall of
+//! glass can be drawn as one pane. Tile `i` (left to right, then top to
+//! bottom) is for the edges in `i`: 1 top, 2 right, 4 bottom, 8 left.
+
+use crate::block::BlockType;
+use image::{imageops, Rgba, RgbaImage};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Component, Path};
+
+/// Folder scanned for resource packs
+pub const RESOURCE_PACKS_DIR: &str = "resourcepacks";
+/// Block definitions file at the root of a pack
+const BLOCKS_FILE: &str = "blocks.json";
+/// Built-in textures, laid out as given by `BlockType::atlas_coords`
+const BUILTIN_ATLAS: &str = "textures/atlas.png";
+const BUILTIN_ATLAS_COLS: u32 = 9;
+const BUILTIN_ATLAS_ROWS: u32 = 1;
+/// Edge length of an atlas tile in pixels; other texture sizes are rescaled
+pub const TILE_SIZE: u32 = 16;
+/// Suffix of the name of a connected texture sheet
+const CONNECTED_SUFFIX: &str = "_connected";
+/// Tiles in a connected texture sheet, one per set of connected edges
+pub const CONNECTED_TILES: u32 = 16;
+const CONNECTED_COLS: u32 = 4;
+
+pub struct ResourcePack {
+    /// Tiles by texture name (file name without `.png`)
+    textures: HashMap<String, RgbaImage>,
+    /// Texture names from `blocks.json`
+    blocks: HashMap<BlockType, String>,
+    /// Connected texture sheets split into their tiles, by the name of the
+    /// texture they connect
+    connected: HashMap<String, Vec<RgbaImage>>,
+}
+
+impl ResourcePack {
+    fn empty() -> Self {
+        Self {
+            textures: HashMap::new(),
+            blocks: HashMap::new(),
+            connected: HashMap::new(),
+        }
+    }
+
+    /// The textures shipped with the game. A missing atlas gives an empty pack,
+    /// which renders every block in its plain tint color.
+    pub fn builtin() -> Self {
+        let mut pack = Self::empty();
+        // The web build has no files next to it, so it carries the atlas inside
+        #[cfg(target_arch = "wasm32")]
+        let atlas = image::load_from_memory(include_bytes!("../textures/atlas.png"));
+        #[cfg(not(target_arch = "wasm32"))]
+        let atlas = image::open(BUILTIN_ATLAS);
+        let atlas = match atlas {
+            Ok(img) => img.to_rgba8(),
+            Err(e) => {
+                log::error!("Failed to load {}: {}", BUILTIN_ATLAS, e);
+                return pack;
+            }
+        };
+        let tile_w = atlas.width() / BUILTIN_ATLAS_COLS;
+        let tile_h = atlas.height() / BUILTIN_ATLAS_ROWS;
+        for block in BlockType::ALL {
+            if let Some((col, row)) = block.atlas_coords() {
+                let tile = imageops::crop_imm(&atlas, col * tile_w, row * tile_h, tile_w, tile_h).to_image();
+                pack.textures.insert(block.name().to_string(), fit_tile(tile));
+            }
+        }
+        pack
+    }
+
+    /// Load the pack folder or zip at `path`
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let mut pack = Self::empty();
+
+        if path.is_dir() {
+            let mut files = vec![path.join(BLOCKS_FILE)];
+            if let Ok(entries) = std::fs::read_dir(path.join("textures")) {
+                files.extend(entries.flatten().map(|entry| entry.path()));
+            }
+            for file in files.iter().filter(|file| file.is_file()) {
+                let bytes = std::fs::read(file).map_err(|e| format!("{}: {}", file.display(), e))?;
+                pack.add_file(file.strip_prefix(path).unwrap_or(file), &bytes)?;
+            }
+        } else {
+            let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+            let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+            for i in 0..archive.len() {
+                let mut entry = archive.by_index(i).map_err(|e| e.to_string())?;
+                let Some(entry_path) = entry.enclosed_name().map(Path::to_path_buf) else {
+                    continue;
+                };
+                if entry.is_dir() {
+                    continue;
+                }
+                let mut bytes = Vec::new();
+                entry.read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+                pack.add_file(&entry_path, &bytes)?;
+            }
+        }
+
+        if pack.textures.is_empty() && pack.blocks.is_empty() {
+            return Err(format!("no textures/ or {} in {}", BLOCKS_FILE, path.display()));
+        }
+        Ok(pack)
+    }
+
+    /// Take in one file of the pack; files other than textures and block
+    /// definitions are ignored. Zips often wrap the pack in a folder of its
+    /// own, so one leading folder is skipped.
+    fn add_file(&mut self, path: &Path, bytes: &[u8]) -> Result<(), String> {
+        let parts: Vec<&str> = path
+            .components()
+            .filter_map(|part| match part {
+                Component::Normal(part) => part.to_str(),
+                _ => None,
+            })
+            .collect();
+        let mut parts = parts.as_slice();
+        if matches!(parts, [_, BLOCKS_FILE] | [_, "textures", _]) {
+            parts = &parts[1..];
+        }
+
+        match parts {
+            [BLOCKS_FILE] => {
+                let definitions: HashMap<String, String> =
+                    serde_json::from_slice(bytes).map_err(|e| format!("{}: {}", BLOCKS_FILE, e))?;
+                for (block_name, texture) in definitions {
+                    let block = BlockType::from_name(&block_name)
+                        .ok_or_else(|| format!("{}: no block named '{}'", BLOCKS_FILE, block_name))?;
+                    self.blocks.insert(block, texture);
+                }
+            }
+            ["textures", file] => {
+                let Some(texture) = file.strip_suffix(".png") else {
+                    return Ok(());
+                };
+                let img = image::load_from_memory(bytes).map_err(|e| format!("textures/{}: {}", file, e))?;
+                if let Some(texture) = texture.strip_suffix(CONNECTED_SUFFIX) {
+                    self.connected.insert(texture.to_string(), split_connected(img.to_rgba8()));
+                } else {
+                    self.textures.insert(texture.to_string(), fit_tile(img.to_rgba8()));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn texture_name(&self, block: BlockType) -> Option<&str> {
+        self.blocks.get(&block).map(String::as_str)
+    }
+}
+
+/// Names of the packs in `dir`: its subfolders and zip files, sorted
+pub fn available(dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir() || path.extension().and_then(|ext| ext.to_str()) == Some("zip"))
+        .filter_map(|path| path.file_name().and_then(|name| name.to_str()).map(str::to_string))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Square tile of `TILE_SIZE` pixels. Tall textures (animation strips) keep
+/// their first frame.
+fn fit_tile(img: RgbaImage) -> RgbaImage {
+    let side = img.width().min(img.height());
+    let img = if img.height() > side {
+        imageops::crop_imm(&img, 0, 0, side, side).to_image()
+    } else {
+        img
+    };
+    if img.dimensions() == (TILE_SIZE, TILE_SIZE) {
+        img
+    } else {
+        imageops::resize(&img, TILE_SIZE, TILE_SIZE, imageops::FilterType::Nearest)
+    }
+}
+
+/// The tiles of a connected texture sheet, each of `TILE_SIZE` pixels
+fn split_connected(img: RgbaImage) -> Vec<RgbaImage> {
+    let size = CONNECTED_COLS * TILE_SIZE;
+    let img = imageops::resize(&img, size, size, imageops::FilterType::Nearest);
+    (0..CONNECTED_TILES)
+        .map(|i| {
+            let (x, y) = ((i % CONNECTED_COLS) * TILE_SIZE, (i / CONNECTED_COLS) * TILE_SIZE);
+            imageops::crop_imm(&img, x, y, TILE_SIZE, TILE_SIZE).to_image()
+        })
+        .collect()
+}
+
+/// Block textures packed into one image, and where each block's tile is
+pub struct TextureAtlas {
+    pub image: RgbaImage,
+    pub layout: AtlasLayout,
+}
+
+impl TextureAtlas {
+    /// Ever smaller copies of the image down to one pixel per tile, each half
+    /// the size of the one before. Pixels are averaged within a tile only, and
+    /// weighted by their alpha so transparent ones don't darken the edges.
+    pub fn mipmaps(&self) -> Vec<RgbaImage> {
+        let mut levels: Vec<RgbaImage> = Vec::new();
+        for _ in 0..TILE_SIZE.ilog2() {
+            let previous = levels.last().unwrap_or(&self.image);
+            let (width, height) = previous.dimensions();
+            if width % 2 != 0 || height % 2 != 0 {
+                break;
+            }
+            let level = RgbaImage::from_fn(width / 2, height / 2, |x, y| {
+                let texels = [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(dx, dy)| previous.get_pixel(2 * x + dx, 2 * y + dy).0);
+                let alpha: u32 = texels.iter().map(|texel| texel[3] as u32).sum();
+                if alpha == 0 {
+                    return image::Rgba([0, 0, 0, 0]);
+                }
+                let channel = |c: usize| (texels.iter().map(|texel| texel[c] as u32 * texel[3] as u32).sum::<u32>() / alpha) as u8;
+                image::Rgba([channel(0), channel(1), channel(2), (alpha / 4) as u8])
+            });
+            levels.push(level);
+        }
+        levels
+    }
+}
+
+/// Build the atlas from a stack of packs: each block takes its texture from
+/// the first pack defining it and falls back to plain white. The tiles of
+/// connected textures follow those of the plain ones.
+pub fn build_atlas(packs: &[&ResourcePack]) -> TextureAtlas {
+    let mut names: Vec<&str> = Vec::new();
+    let mut block_names = Vec::new();
+    for block in BlockType::ALL.into_iter().filter(|block| *block != BlockType::Air) {
+        let name = packs
+            .iter()
+            .find_map(|pack| pack.texture_name(block))
+            .unwrap_or(block.name());
+        if !names.contains(&name) {
+            names.push(name);
+        }
+        block_names.push((block, name));
+    }
+
+    let sheets: Vec<(&str, &Vec<RgbaImage>)> = names
+        .iter()
+        .filter_map(|name| packs.iter().find_map(|pack| pack.connected.get(*name)).map(|sheet| (*name, sheet)))
+        .collect();
+
+    let count = names.len() as u32 + sheets.len() as u32 * CONNECTED_TILES;
+    let cols = (count as f32).sqrt().ceil().max(1.0) as u32;
+    let rows = count.div_ceil(cols).max(1);
+    let position = |i: u32| (i % cols, i / cols);
+    let mut image = RgbaImage::from_pixel(cols * TILE_SIZE, rows * TILE_SIZE, Rgba([255, 255, 255, 255]));
+    let mut place = |i: u32, tile: &RgbaImage| {
+        let (col, row) = position(i);
+        imageops::replace(&mut image, tile, (col * TILE_SIZE) as i64, (row * TILE_SIZE) as i64);
+    };
+    for (i, name) in names.iter().enumerate() {
+        if let Some(tile) = packs.iter().find_map(|pack| pack.textures.get(*name)) {
+            place(i as u32, tile);
+        }
+    }
+    let mut sheet_tiles = HashMap::new();
+    for (s, (name, sheet)) in sheets.iter().enumerate() {
+        let first = names.len() as u32 + s as u32 * CONNECTED_TILES;
+        for (i, tile) in sheet.iter().enumerate() {
+            place(first + i as u32, tile);
+        }
+        sheet_tiles.insert(*name, (first..first + CONNECTED_TILES).map(position).collect::<Vec<_>>());
+    }
+
+    let tiles = block_names
+        .iter()
+        .map(|&(block, name)| {
+            let i = names.iter().position(|n| *n == name).unwrap_or(0) as u32;
+            (block, position(i))
+        })
+        .collect();
+    let connected = block_names
+        .iter()
+        .filter_map(|&(block, name)| sheet_tiles.get(name).map(|tiles| (block, tiles.clone())))
+        .collect();
+    TextureAtlas {
+        image,
+        layout: AtlasLayout { cols, rows, tiles, connected },
+    }
+}
+
+/// Tile positions of the blocks in the atlas texture
+#[derive(Clone, Default)]
+pub struct AtlasLayout {
+    cols: u32,
+    rows: u32,
+    tiles: HashMap<BlockType, (u32, u32)>,
+    /// Tile positions of the blocks with a connected texture, by connected edges
+    connected: HashMap<BlockType, Vec<(u32, u32)>>,
+}
+
+impl AtlasLayout {
+    /// Column and row of the block's tile; Air has none
+    pub fn tile(&self, block: BlockType) -> Option<(u32, u32)> {
+        self.tiles.get(&block).copied()
+    }
+
+    /// UV rectangle (u0, v0, u1, v1) of the block's tile, inset half a texel to
+    /// avoid bleeding from neighboring tiles
+    pub fn tile_uv(&self, block: BlockType) -> Option<[f32; 4]> {
+        Some(self.uv(self.tile(block)?))
+    }
+
+    /// Whether the block has a connected texture
+    pub fn is_connected(&self, block: BlockType) -> bool {
+        self.connected.contains_key(&block)
+    }
+
+    /// UV rectangle of the block's connected texture tile for a face sharing
+    /// `edges` (1 top, 2 right, 4 bottom, 8 left) with faces of the same block
+    pub fn connected_tile_uv(&self, block: BlockType, edges: u8) -> Option<[f32; 4]> {
+        let tiles = self.connected.get(&block)?;
+        Some(self.uv(tiles[edges as usize % tiles.len()]))
+    }
+
+    fn uv(&self, (col, row): (u32, u32)) -> [f32; 4] {
+        let tile_w = 1.0 / self.cols as f32;
+        let tile_h = 1.0 / self.rows as f32;
+
+        let inset_u = 0.5 / (self.cols * TILE_SIZE) as f32;
+        let inset_v = 0.5 / (self.rows * TILE_SIZE) as f32;
+
+        let u0 = col as f32 * tile_w + inset_u;
+        let v0 = row as f32 * tile_h + inset_v;
+        [u0, v0, u0 + tile_w - 2.0 * inset_u, v0 + tile_h - 2.0 * inset_v]
+    }
+}
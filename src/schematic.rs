@@ -0,0 +1,340 @@
+//! Schematic files, to carry builds between worlds: `//schem save <name>`
+//! writes the selected region to `schematics/<name>.rschem` and `//schem
+//! load <name>` reads one into the clipboard, to be placed with `//paste`.
+//!
+//! Our own files are a short header followed by the size and the blocks
+//! compressed like chunk data. Sponge schematics (`<name>.schem`, from
+//! WorldEdit and other editors) load too; their blocks are matched to ours
+//! by name, and those without a match become air.
+
+use crate::block::BlockType;
+use crate::protocol;
+use crate::region::{self, Clipboard, MAX_REGION_BLOCKS};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Folder schematics are saved to and loaded from
+pub const SCHEMATICS_DIR: &str = "schematics";
+/// Extension of our own schematics
+const EXTENSION: &str = "rschem";
+/// Extension of Sponge schematics
+const SPONGE_EXTENSION: &str = "schem";
+/// Start of our own schematics, with the format version
+const MAGIC: &[u8; 8] = b"RCSCHEM\x01";
+/// Deepest nesting of NBT tags read, so a crafted file can't overflow the stack
+const MAX_NBT_DEPTH: usize = 32;
+
+#[derive(Serialize, Deserialize)]
+struct SchematicFile {
+    size: (i32, i32, i32),
+    /// Blocks x fastest, then z, then y, as `protocol::compress_blocks` packs them
+    blocks: Vec<u8>,
+}
+
+/// A loaded schematic, and how many of its blocks this game has no match for
+pub struct Loaded {
+    pub clipboard: Clipboard,
+    pub unknown: usize,
+}
+
+/// Schematic names are file names without the extension: letters, digits,
+/// `-` and `_`
+fn check_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid schematic name: {} (letters, digits, - and _ only)", name))
+    }
+}
+
+/// Write the clipboard's blocks to `<dir>/<name>.rschem`, creating the folder
+/// if needed. Where it was copied from isn't kept; loading puts the lowest
+/// corner at the player's feet.
+pub fn save(dir: &Path, name: &str, clipboard: &Clipboard) -> Result<PathBuf, String> {
+    check_name(name)?;
+    let file = SchematicFile { size: clipboard.size, blocks: protocol::compress_blocks(&clipboard.blocks) };
+    let mut data = MAGIC.to_vec();
+    data.extend(bincode::serialize(&file).map_err(|e| e.to_string())?);
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let path = dir.join(format!("{}.{}", name, EXTENSION));
+    std::fs::write(&path, data).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Read `<dir>/<name>.rschem`, or failing that the Sponge schematic
+/// `<dir>/<name>.schem`, into a clipboard with its lowest corner at the
+/// player's feet
+pub fn load(dir: &Path, name: &str) -> Result<Loaded, String> {
+    check_name(name)?;
+    let own = dir.join(format!("{}.{}", name, EXTENSION));
+    let sponge = dir.join(format!("{}.{}", name, SPONGE_EXTENSION));
+    if own.exists() {
+        let data = std::fs::read(&own).map_err(|e| e.to_string())?;
+        let clipboard = from_bytes(&data)?;
+        Ok(Loaded { clipboard, unknown: 0 })
+    } else if sponge.exists() {
+        let data = std::fs::read(&sponge).map_err(|e| e.to_string())?;
+        from_sponge(&data)
+    } else {
+        Err(format!("No schematic named {} in {}/", name, dir.display()))
+    }
+}
+
+/// Names of the schematics in `dir`, sorted; none if it doesn't exist
+pub fn list(dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION || ext == SPONGE_EXTENSION))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Parse one of our own schematics
+fn from_bytes(data: &[u8]) -> Result<Clipboard, String> {
+    let body = data.strip_prefix(MAGIC.as_slice()).ok_or_else(|| "Not a schematic of this game".to_string())?;
+    let file: SchematicFile = bincode::deserialize(body).map_err(|e| e.to_string())?;
+    let blocks = protocol::decompress_blocks(&file.blocks, volume(file.size)?)?;
+    clipboard(file.size, blocks)
+}
+
+/// Blocks a schematic of `size` holds, if they aren't too many
+fn volume(size: (i32, i32, i32)) -> Result<usize, String> {
+    let volume = region::volume((0, 0, 0), (size.0.saturating_sub(1), size.1.saturating_sub(1), size.2.saturating_sub(1)));
+    if volume > MAX_REGION_BLOCKS {
+        return Err(format!("The schematic has {} blocks, more than the {} allowed", volume, MAX_REGION_BLOCKS));
+    }
+    Ok(volume)
+}
+
+/// A clipboard of `blocks`, checking they fill `size` and aren't too many
+fn clipboard(size: (i32, i32, i32), blocks: Vec<BlockType>) -> Result<Clipboard, String> {
+    let volume = volume(size)?;
+    if volume == 0 || blocks.len() != volume {
+        return Err(format!("The schematic has {} blocks for a size of {:?}", blocks.len(), size));
+    }
+    Ok(Clipboard { offset: (0, 0, 0), size, blocks })
+}
+
+/// Parse a gzipped Sponge schematic, version 1 to 3
+fn from_sponge(data: &[u8]) -> Result<Loaded, String> {
+    let mut bytes = Vec::new();
+    flate2::read::GzDecoder::new(data)
+        .take(64 * MAX_REGION_BLOCKS as u64)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Not a gzipped schematic: {}", e))?;
+    let root = Nbt::read_root(&bytes)?;
+    // Version 3 wraps everything in a "Schematic" compound
+    let schematic = root.get("Schematic").unwrap_or(&root);
+    let dimension = |key: &str| match schematic.get(key) {
+        Some(Nbt::Short(value)) => Ok(*value as u16 as i32),
+        _ => Err(format!("The schematic has no {}", key)),
+    };
+    let size = (dimension("Width")?, dimension("Height")?, dimension("Length")?);
+    let (palette, data) = match schematic.get("Blocks") {
+        Some(blocks) => (blocks.get("Palette"), blocks.get("Data")),
+        None => (schematic.get("Palette"), schematic.get("BlockData")),
+    };
+    let (Some(Nbt::Compound(palette)), Some(Nbt::ByteArray(data))) = (palette, data) else {
+        return Err("The schematic has no block palette or data".to_string());
+    };
+
+    let mut by_index = HashMap::new();
+    for (state, index) in palette {
+        let Nbt::Int(index) = index else {
+            return Err(format!("Bad palette entry for {}", state));
+        };
+        by_index.insert(*index, block_from_minecraft(state));
+    }
+    let mut blocks = Vec::new();
+    let mut unknown = 0;
+    let mut reader = data.iter().copied();
+    while let Some(index) = read_varint(&mut reader)? {
+        let block = *by_index.get(&index).ok_or_else(|| format!("Block {} is not in the palette", index))?;
+        unknown += usize::from(block.is_none());
+        blocks.push(block.unwrap_or(BlockType::Air));
+        if blocks.len() > MAX_REGION_BLOCKS {
+            break;
+        }
+    }
+    Ok(Loaded { clipboard: clipboard(size, blocks)?, unknown })
+}
+
+/// Next varint of the block data, None at its end
+fn read_varint(bytes: &mut impl Iterator<Item = u8>) -> Result<Option<i32>, String> {
+    let mut value = 0i32;
+    for shift in (0..35).step_by(7) {
+        let Some(byte) = bytes.next() else {
+            return if shift == 0 { Ok(None) } else { Err("Block data ends mid-number".to_string()) };
+        };
+        value |= ((byte & 0x7F) as i32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+    Err("Block data has a number that is too long".to_string())
+}
+
+/// Our block for a Minecraft block state such as `minecraft:oak_log[axis=y]`;
+/// None if there is nothing like it
+fn block_from_minecraft(state: &str) -> Option<BlockType> {
+    let name = state.split('[').next().unwrap_or_default();
+    let name = name.strip_prefix("minecraft:").unwrap_or(name);
+    let block = match name {
+        "air" | "cave_air" | "void_air" => BlockType::Air,
+        "dirt" | "coarse_dirt" | "rooted_dirt" | "farmland" | "dirt_path" => BlockType::Dirt,
+        "grass_block" | "podzol" | "mycelium" => BlockType::Grass,
+        "sand" | "red_sand" => BlockType::Sand,
+        "gravel" => BlockType::Gravel,
+        "clay" => BlockType::Clay,
+        "water" => BlockType::Water,
+        "snow" => BlockType::Snow,
+        "fire" => BlockType::Fire,
+        "chest" | "trapped_chest" | "barrel" => BlockType::Chest,
+        "furnace" | "blast_furnace" | "smoker" => BlockType::Furnace,
+        "enchanting_table" => BlockType::EnchantingTable,
+        _ if name.ends_with("_bed") => BlockType::Bed,
+        "brown_mushroom" | "red_mushroom" => BlockType::Mushroom,
+        "glow_lichen" => BlockType::GlowingFungus,
+        "stone" | "cobblestone" | "deepslate" | "granite" | "diorite" | "andesite" | "bedrock" => BlockType::Stone,
+        _ if name.ends_with("glass") || name.ends_with("glass_pane") => BlockType::Glass,
+        _ if name.ends_with("_log") || name.ends_with("_wood") || name.ends_with("_stem") => BlockType::Wood,
+        _ if name.ends_with("_planks") => BlockType::Planks,
+        _ if name.ends_with("_leaves") => BlockType::Leaves,
+        _ if name.ends_with("stone") || name.ends_with("stone_bricks") || name.ends_with("_ore") => BlockType::Stone,
+        _ => return None,
+    };
+    Some(block)
+}
+
+/// The NBT tags Sponge schematics are made of, as far as loading them needs
+enum Nbt {
+    Short(i16),
+    Int(i32),
+    ByteArray(Vec<u8>),
+    Compound(HashMap<String, Nbt>),
+    /// Any other tag, read past
+    Other,
+}
+
+impl Nbt {
+    /// The named compound a file consists of
+    fn read_root(bytes: &[u8]) -> Result<Nbt, String> {
+        let mut reader = NbtReader { bytes, pos: 0 };
+        if reader.u8()? != 10 {
+            return Err("The schematic doesn't start with a compound".to_string());
+        }
+        reader.string()?;
+        reader.payload(10, 0)
+    }
+
+    /// The tag named `key`, if this is a compound holding one
+    fn get(&self, key: &str) -> Option<&Nbt> {
+        match self {
+            Nbt::Compound(tags) => tags.get(key),
+            _ => None,
+        }
+    }
+}
+
+/// Big-endian reader over NBT data
+struct NbtReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl NbtReader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], String> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.bytes.len());
+        let end = end.ok_or_else(|| "The schematic ends early".to_string())?;
+        let taken = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    /// An array or list length, which can't be more than the bytes left
+    fn len(&mut self, element_size: usize) -> Result<usize, String> {
+        let len = i32::from_be_bytes(self.array()?).max(0) as usize;
+        if len.saturating_mul(element_size) > self.bytes.len() - self.pos {
+            return Err("The schematic ends early".to_string());
+        }
+        Ok(len)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = u16::from_be_bytes(self.array()?) as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    fn payload(&mut self, tag: u8, depth: usize) -> Result<Nbt, String> {
+        if depth > MAX_NBT_DEPTH {
+            return Err("The schematic nests too deep".to_string());
+        }
+        Ok(match tag {
+            2 => Nbt::Short(i16::from_be_bytes(self.array()?)),
+            3 => Nbt::Int(i32::from_be_bytes(self.array()?)),
+            7 => {
+                let len = self.len(1)?;
+                Nbt::ByteArray(self.take(len)?.to_vec())
+            }
+            // Byte, long, float, double and string
+            1 | 4 | 5 | 6 | 8 => {
+                match tag {
+                    1 => self.take(1)?,
+                    4 | 6 => self.take(8)?,
+                    5 => self.take(4)?,
+                    _ => {
+                        let len = u16::from_be_bytes(self.array()?) as usize;
+                        self.take(len)?
+                    }
+                };
+                Nbt::Other
+            }
+            9 => {
+                let element = self.u8()?;
+                let len = self.len(1)?;
+                for _ in 0..len {
+                    self.payload(element, depth + 1)?;
+                }
+                Nbt::Other
+            }
+            10 => {
+                let mut tags = HashMap::new();
+                loop {
+                    let tag = self.u8()?;
+                    if tag == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    tags.insert(name, self.payload(tag, depth + 1)?);
+                }
+                Nbt::Compound(tags)
+            }
+            // Int and long arrays
+            11 | 12 => {
+                let size = if tag == 11 { 4 } else { 8 };
+                let len = self.len(size)?;
+                self.take(len * size)?;
+                Nbt::Other
+            }
+            _ => return Err(format!("Unknown NBT tag {}", tag)),
+        })
+    }
+}
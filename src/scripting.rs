@@ -0,0 +1,162 @@
+//! Block behaviors written in Rhai. `scripts/<block>.rhai` (e.g. `scripts/sand.rhai`)
+//! may define any of
+//!
+//! ```text
+//! fn on_place(x, y, z) { ... }
+//! fn on_break(x, y, z) { ... }
+//! fn on_random_tick(x, y, z) { ... }
+//! fn on_interact(x, y, z) { ... }
+//! ```
+//!
+//! and change the world through `get_block(x, y, z)` and `set_block(x, y, z, name)`,
+//! which use the block names from `/give`, and `explode(x, y, z, power)`, which
+//! blows up the blocks around the middle of that block (see `explosion`).
+
+use crate::block::BlockType;
+use crate::explosion;
+use crate::world::World;
+use glam::Vec3;
+use rhai::{CallFnOptions, Dynamic, Engine, Scope, AST, INT};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+/// Folder scanned for block scripts at startup
+pub const SCRIPTS_DIR: &str = "scripts";
+
+/// Script callbacks a block can define
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockHook {
+    /// After the player placed the block
+    Place,
+    /// After the player broke the block; the position is already empty
+    Break,
+    /// The block was picked for a random tick; replaces the built-in behavior
+    RandomTick,
+    /// Right click on the block; replaces placing a block against it
+    Interact,
+}
+
+impl BlockHook {
+    fn function(self) -> &'static str {
+        match self {
+            BlockHook::Place => "on_place",
+            BlockHook::Break => "on_break",
+            BlockHook::RandomTick => "on_random_tick",
+            BlockHook::Interact => "on_interact",
+        }
+    }
+}
+
+pub struct BlockScripts {
+    engine: Engine,
+    scripts: HashMap<BlockType, AST>,
+    /// The world being scripted. It is swapped in for the duration of a
+    /// callback so the registered functions can reach it.
+    world: Rc<RefCell<World>>,
+}
+
+impl BlockScripts {
+    /// An engine with no scripts loaded
+    pub fn new() -> Self {
+        let world = Rc::new(RefCell::new(World::new(0)));
+        let mut engine = Engine::new();
+        // Scripts run inside the game tick; keep a runaway loop from freezing it
+        engine.set_max_operations(100_000);
+
+        let w = world.clone();
+        engine.register_fn("get_block", move |x: INT, y: INT, z: INT| -> String {
+            w.borrow()
+                .get_block_at(x as i32, y as i32, z as i32)
+                .map_or("", |block| block.name())
+                .to_string()
+        });
+        let w = world.clone();
+        engine.register_fn("set_block", move |x: INT, y: INT, z: INT, name: &str| -> bool {
+            BlockType::from_name(name).is_some_and(|block| w.borrow_mut().set_block_at(x as i32, y as i32, z as i32, block))
+        });
+        let w = world.clone();
+        engine.register_fn("explode", move |x: INT, y: INT, z: INT, power: INT| -> INT {
+            let center = Vec3::new(x as f32, y as f32, z as f32) + 0.5;
+            explosion::explode(&mut w.borrow_mut(), center, power as f32) as INT
+        });
+
+        Self {
+            engine,
+            scripts: HashMap::new(),
+            world,
+        }
+    }
+
+    /// Load every `<block name>.rhai` in `dir` (a missing folder means no
+    /// scripts). Returns the scripts and one message per file that failed.
+    pub fn load(dir: &Path) -> (Self, Vec<String>) {
+        let mut scripts = Self::new();
+        let mut errors = Vec::new();
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return (scripts, errors);
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().and_then(|ext| ext.to_str()) != Some("rhai") {
+                continue;
+            }
+            let name = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+            let result = match BlockType::from_name(name) {
+                Some(block) => std::fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|source| scripts.add(block, &source)),
+                None => Err(format!("no block named '{}'", name)),
+            };
+            if let Err(e) = result {
+                errors.push(format!("{}: {}", path.display(), e));
+            }
+        }
+        (scripts, errors)
+    }
+
+    /// Compile `source` as the script of `block`, replacing any previous one
+    pub fn add(&mut self, block: BlockType, source: &str) -> Result<(), String> {
+        let ast = self.engine.compile(source).map_err(|e| e.to_string())?;
+        self.scripts.insert(block, ast);
+        Ok(())
+    }
+
+    /// Whether `block` has a script defining the callback
+    pub fn handles(&self, block: BlockType, hook: BlockHook) -> bool {
+        self.scripts.get(&block).is_some_and(|ast| {
+            ast.iter_functions()
+                .any(|f| f.name == hook.function() && f.params.len() == 3)
+        })
+    }
+
+    /// Run the callback of `block` for the block at `pos`. Returns false if the
+    /// block has no such callback.
+    pub fn run(&mut self, hook: BlockHook, block: BlockType, world: &mut World, (x, y, z): (i32, i32, i32)) -> bool {
+        if !self.handles(block, hook) {
+            return false;
+        }
+        let ast = &self.scripts[&block];
+
+        std::mem::swap(world, &mut self.world.borrow_mut());
+        let result = self.engine.call_fn_with_options::<Dynamic>(
+            CallFnOptions::new().eval_ast(false),
+            &mut Scope::new(),
+            ast,
+            hook.function(),
+            (x as INT, y as INT, z as INT),
+        );
+        std::mem::swap(world, &mut self.world.borrow_mut());
+
+        if let Err(e) = result {
+            log::error!("Script error in {}.rhai {}: {}", block.name(), hook.function(), e);
+        }
+        true
+    }
+}
+
+impl Default for BlockScripts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,966 @@
+//! The authoritative side of the game: world generation, ticking, block
+//! scripts, persistence and validation of the players' movement. The
+//! dedicated server (`rustcraft-server`, or `rustcraft --headless`) runs it on
+//! its own and accepts LAN players; in singleplayer the client runs it
+//! in-process and talks to it through a local connection.
+//!
+//! In-process clients share the server's world. Network clients keep a copy
+//! that the server fills with `ChunkData` and keeps current with `BlockChange`
+//! and `MultiBlockChange`.
+
+use crate::block::BlockType;
+use crate::boat::Boat;
+use crate::chunk_loader::{self, ChunkLoader, FrameBudget};
+use crate::circuit;
+use crate::cli::Args;
+use crate::commands::{self, CommandContext, CommandResult};
+use crate::crash::{self, CrashGuard, PlayerState};
+use crate::events::GameEvent;
+use crate::input::REACH;
+use crate::item_entity::ItemEntity;
+use crate::loot::{self, LootTables};
+use crate::net::{self, NewClient};
+use crate::permissions::{self, Permissions, OP_COMMANDS};
+use crate::physics::{Aabb, Player};
+use crate::protocol::{self, ClientConnection, ClientMessage, LoginInfo, ServerConnection, ServerMessage};
+use crate::region::Selection;
+use crate::scripting::{self, BlockHook, BlockScripts};
+use crate::skin::MAX_SKIN_FILE_SIZE;
+use crate::sleep::{self, SleepStatus, SLEEP_TICKS};
+use crate::storage::Storage;
+use crate::tick::{TickClock, TICK_LENGTH};
+use crate::time::Weather;
+use crate::world::World;
+use crate::world_gen::{TerrainParams, WorldGenerator};
+use glam::{Vec2, Vec3};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+
+/// Fastest legitimate movement in blocks per second, falling at terminal
+/// velocity included
+const MAX_PLAYER_SPEED: f32 = 60.0;
+/// Slack on the speed check for uneven frame times, in blocks
+const MOVE_TOLERANCE: f32 = 1.0;
+/// Slack on the reach check of `SetBlock`, for a player moving while the
+/// message travels
+const REACH_TOLERANCE: f32 = 2.0;
+/// Chunks sent to one network client per update, nearest first, so a new
+/// player doesn't stall the server
+const CHUNKS_PER_UPDATE: usize = 8;
+/// Block changes to one chunk in one update above which the whole chunk is
+/// sent instead, being smaller compressed
+const MAX_BLOCK_CHANGES: usize = 256;
+/// Seconds between keep-alives to network clients
+const KEEP_ALIVE_INTERVAL: f32 = 5.0;
+/// A network client silent for this many seconds is dropped
+const CLIENT_TIMEOUT: f32 = 30.0;
+/// Longest player name, in characters; longer ones are cut
+const MAX_NAME_LENGTH: usize = 16;
+/// Longest chat line, in characters, as long as the console's input line
+const MAX_CHAT_LENGTH: usize = 100;
+/// Health a hit takes, one heart
+const ATTACK_DAMAGE: u32 = 2;
+/// Seconds a player has to wait between hits
+const ATTACK_COOLDOWN: f32 = 0.5;
+/// Horizontal and upward speed a hit gives the player hit, in blocks per second
+const KNOCKBACK_SPEED: f32 = 6.0;
+const KNOCKBACK_LIFT: f32 = 4.0;
+
+/// A connected player
+struct Client {
+    id: u32,
+    name: String,
+    connection: ServerConnection,
+    /// Network clients get chunk data and keep-alives; in-process ones share the world
+    remote: bool,
+    /// Authoritative player state; the client predicts its own copy
+    player: Player,
+    view_distance: i32,
+    /// Seconds since the last accepted move, for the speed check
+    since_move: f32,
+    /// View direction, passed on to the other clients and used to load and
+    /// send the chunks in front of the player first
+    yaw: f32,
+    /// Position and view direction the other clients last heard of
+    announced: (Vec3, f32),
+    /// Chunks a network client has a copy of
+    sent_chunks: HashSet<(i32, i32)>,
+    /// Seconds since the client last sent anything
+    silent_for: f32,
+    /// Why `/kick` removes the client at the end of the update
+    kicked: Option<String>,
+    /// Seconds since the player last hit someone
+    since_attack: f32,
+    /// Region and clipboard of the building commands
+    selection: Selection,
+    /// PNG of the player's skin, passed on to players joining later; empty
+    /// for the default skin
+    skin: Vec<u8>,
+    /// The bed the player sleeps in
+    bed: Option<(i32, i32, i32)>,
+}
+
+impl Client {
+    fn chunk(&self) -> (i32, i32) {
+        let position = self.player.position;
+        ((position.x / 16.0).floor() as i32, (position.z / 16.0).floor() as i32)
+    }
+
+    /// Position on the ground plane and view direction, for chunk ordering
+    fn view(&self) -> (Vec2, Vec2) {
+        (Vec2::new(self.player.position.x, self.player.position.z), Vec2::from_angle(self.yaw))
+    }
+}
+
+pub struct Server {
+    pub world: World,
+    world_path: String,
+    /// Where the world and its emergency save are kept
+    storage: Arc<dyn Storage>,
+    /// Replaced when terrain parameters are changed in the debug UI
+    pub generator: WorldGenerator,
+    chunk_loader: ChunkLoader,
+    pub scripts: BlockScripts,
+    tick_clock: TickClock,
+    /// View distance of new clients, and of the area kept loaded around spawn
+    view_distance: i32,
+    /// Chunks around each player, or around spawn while nobody is connected,
+    /// that the world simulates
+    simulation_distance: i32,
+    clients: Vec<Client>,
+    next_client_id: u32,
+    /// Network clients that logged in, once `listen` was called
+    arrivals: Option<Receiver<NewClient>>,
+    since_keep_alive: f32,
+    keep_alive_id: u64,
+    /// Stands in for the player when the console runs a command with nobody connected
+    console_player: Player,
+    /// The console's region for the building commands
+    console_selection: Selection,
+    permissions: Permissions,
+    /// Set by `/stop`; the dedicated server saves and exits
+    stopping: bool,
+    /// The player of an emergency save, for the first local client to join
+    recovered_player: Option<PlayerState>,
+    /// Who was in bed when the clients last heard of it
+    sleep_status: SleepStatus,
+    /// Ticks enough players have slept for
+    slept_ticks: u32,
+}
+
+impl Server {
+    /// Load or create the world given on the command line. Also returns one
+    /// message per block script or loot table that failed to load.
+    pub fn open(args: &Args, storage: Arc<dyn Storage>, view_distance: i32, simulation_distance: i32) -> (Self, Vec<String>) {
+        let mut world = args.open_world(&*storage);
+        // Chunks changed before the game last crashed, and the player then
+        let recovered = crash::recover(&*storage, &mut world, &crash::emergency_path(&args.world_path()));
+        if let Some(radius) = args.world_border {
+            world.border = Some(radius);
+        }
+        let (scripts, mut load_errors) = BlockScripts::load(Path::new(scripting::SCRIPTS_DIR));
+        for error in &load_errors {
+            log::error!("Failed to load block script {}", error);
+        }
+        let (loot, loot_errors) = LootTables::load(Path::new(loot::LOOT_TABLES_DIR));
+        for error in &loot_errors {
+            log::error!("Failed to load loot table {}", error);
+        }
+        load_errors.extend(loot_errors);
+        let generator = WorldGenerator::with_params(world.seed, TerrainParams { mode: world.terrain, ..TerrainParams::default() })
+            .with_loot(Arc::new(loot));
+
+        // A new world picks its spawn point once; it is saved with the world
+        let spawn = *world.spawn.get_or_insert_with(|| generator.find_spawn(world.border));
+        log::info!("World spawn at ({}, {}, {})", spawn.0, spawn.1, spawn.2);
+        let spawn_position = world.spawn_position();
+        let server = Self {
+            world,
+            world_path: args.world_path(),
+            storage,
+            chunk_loader: ChunkLoader::new(&generator),
+            generator,
+            scripts,
+            tick_clock: TickClock::new(),
+            view_distance,
+            simulation_distance,
+            clients: Vec::new(),
+            next_client_id: 1,
+            arrivals: None,
+            since_keep_alive: 0.0,
+            keep_alive_id: 0,
+            console_player: Player::new(spawn_position),
+            console_selection: Selection::default(),
+            permissions: Permissions::load(Path::new(&args.ops), args.whitelist.as_deref().map(Path::new)),
+            stopping: false,
+            recovered_player: recovered.and_then(|(_, player)| player),
+            sleep_status: SleepStatus::default(),
+            slept_ticks: 0,
+        };
+        (server, load_errors)
+    }
+
+    /// Accept LAN players on `port` from now on; 0 picks a free port.
+    /// Returns the port listened on.
+    pub fn listen(&mut self, port: u16) -> std::io::Result<u16> {
+        let (arrivals, port) = net::listen(port)?;
+        self.arrivals = Some(arrivals);
+        log::info!("Listening for players on port {}", port);
+        Ok(port)
+    }
+
+    /// Connect an in-process client playing as `name`
+    pub fn connect_local(&mut self, name: &str) -> ClientConnection {
+        let (client, server) = protocol::local_connection();
+        self.add_client(name.to_string(), server, false);
+        client
+    }
+
+    pub fn spawn_position(&self) -> Vec3 {
+        self.world.spawn_position()
+    }
+
+    /// True once an operator ran `/stop`
+    pub fn is_stopping(&self) -> bool {
+        self.stopping
+    }
+
+    fn spawn_chunk(&self) -> (i32, i32) {
+        let (x, _, z) = self.world.spawn.unwrap_or_default();
+        (x.div_euclid(16), z.div_euclid(16))
+    }
+
+    /// Generate the area around spawn right away, blocking until done
+    pub fn generate_spawn_area(&mut self) {
+        for (x, z) in chunk_loader::area(self.spawn_chunk(), self.view_distance) {
+            self.world.load_or_generate_chunk(x, z, &self.generator);
+        }
+    }
+
+    /// Request the missing chunks around every player, or around spawn while
+    /// nobody is connected, and add those generated since within `budget`
+    pub fn load_chunks(&mut self, budget: FrameBudget) {
+        if self.clients.is_empty() {
+            let (x, _, z) = self.world.spawn.unwrap_or_default();
+            let spawn = Vec2::new(x as f32 + 0.5, z as f32 + 0.5);
+            self.chunk_loader.request_area(&self.world, spawn, Vec2::ZERO, self.view_distance);
+        }
+        for client in &self.clients {
+            let (position, facing) = client.view();
+            self.chunk_loader.request_area(&self.world, position, facing, client.view_distance);
+        }
+        if self.chunk_loader.integrate(&mut self.world, &self.generator, budget) > 0 {
+            self.send_local(ServerMessage::WorldChanged);
+        }
+    }
+
+    /// Handle the clients' messages and, unless paused, run the world ticks
+    /// that are due. Returns the number of ticks run.
+    pub fn update(&mut self, delta_time: f32, simulate: bool) -> u32 {
+        self.accept_clients();
+        for index in 0..self.clients.len() {
+            let client = &mut self.clients[index];
+            client.since_move += delta_time;
+            client.since_attack += delta_time;
+            client.silent_for += delta_time;
+            let messages = client.connection.receive();
+            if !messages.is_empty() {
+                client.silent_for = 0.0;
+            }
+            for message in messages {
+                self.handle(index, message);
+            }
+        }
+        self.announce_moves();
+        self.keep_alive(delta_time);
+
+        let ticks = if simulate { self.tick_clock.advance(delta_time) } else { 0 };
+        if ticks > 0 {
+            self.world.simulated_chunks = Some(self.simulation_area());
+        }
+        for _ in 0..ticks {
+            self.tick();
+        }
+        self.dispatch_events();
+        self.sync_blocks();
+        self.stream_chunks();
+        ticks
+    }
+
+    /// Chunks within the simulation distance of a player, or of spawn while
+    /// nobody is connected
+    fn simulation_area(&self) -> HashSet<(i32, i32)> {
+        let centers: Vec<(i32, i32)> = if self.clients.is_empty() {
+            vec![self.spawn_chunk()]
+        } else {
+            self.clients.iter().map(Client::chunk).collect()
+        };
+        centers.into_iter().flat_map(|center| chunk_loader::area(center, self.simulation_distance)).collect()
+    }
+
+    /// Daylight, block updates and furnaces
+    fn tick(&mut self) {
+        self.world.time.tick(&self.world.rules);
+        if self.world.time.tick_weather() {
+            self.send_remote(ServerMessage::Weather(self.world.time.weather()));
+        }
+        let weathered = self.world.tick_precipitation(&self.generator);
+        // Whatever stands on a pressure plate presses it
+        let players = self.clients.iter().map(|client| client.player.bounding_box);
+        let items = self.world.items.iter().map(ItemEntity::bounding_box);
+        let boats = self.world.boats.iter().map(Boat::bounding_box);
+        self.world.bodies = players.chain(items).chain(boats).collect();
+        if self.world.tick_blocks(&mut self.scripts) || weathered {
+            self.send_local(ServerMessage::WorldChanged);
+        }
+        if self.world.update_block_entities(TICK_LENGTH) {
+            self.send_local(ServerMessage::BlockEntitiesChanged);
+        }
+        self.tick_sleep();
+        self.broadcast(ServerMessage::Tick);
+        // Network clients advance their clock by one tick on each Tick
+        if self.world.time.timelapse().is_some() {
+            self.send_remote(ServerMessage::Time(self.world.time.ticks()));
+        }
+    }
+
+    /// Tell the players who is in bed when that changes, and skip to the
+    /// morning once enough of them slept for `SLEEP_TICKS`. Sleepers get up
+    /// by themselves when the night or the storm ends.
+    fn tick_sleep(&mut self) {
+        if !sleep::can_sleep(&self.world.time) {
+            self.wake_all();
+        }
+        let sleeping = self.clients.iter().filter(|client| client.bed.is_some()).count() as u32;
+        let status = if sleeping == 0 {
+            SleepStatus::default()
+        } else {
+            SleepStatus {
+                sleeping,
+                needed: sleep::needed(self.clients.len() as u32, self.world.rules.players_sleeping_percentage()),
+                awake: self.clients.iter().filter(|client| client.bed.is_none()).map(|client| client.name.clone()).collect(),
+            }
+        };
+        self.slept_ticks = if status.is_enough() { self.slept_ticks + 1 } else { 0 };
+        if status != self.sleep_status {
+            self.broadcast(ServerMessage::SleepStatus(status.clone()));
+            self.sleep_status = status;
+        }
+        if self.slept_ticks < SLEEP_TICKS {
+            return;
+        }
+
+        log::info!("Skipping the night");
+        self.world.time.add_ticks(sleep::ticks_until_morning(self.world.time.time_of_day()));
+        self.send_remote(ServerMessage::Time(self.world.time.ticks()));
+        if self.world.time.weather() != Weather::Clear {
+            self.world.time.set_weather(Weather::Clear);
+            self.send_remote(ServerMessage::Weather(Weather::Clear));
+        }
+        self.wake_all();
+        self.slept_ticks = 0;
+        self.sleep_status = SleepStatus::default();
+        self.broadcast(ServerMessage::SleepStatus(SleepStatus::default()));
+    }
+
+    /// Get every sleeping player out of bed
+    fn wake_all(&mut self) {
+        for client in self.clients.iter_mut().filter(|client| client.bed.is_some()) {
+            client.bed = None;
+            client.connection.send(ServerMessage::WakeUp);
+        }
+    }
+
+    /// Put a player to bed if the bed is in reach and it is time to sleep;
+    /// otherwise send them back up
+    fn handle_sleep(&mut self, index: usize, pos: (i32, i32, i32)) {
+        let client = &mut self.clients[index];
+        let center = Vec3::new(pos.0 as f32, pos.1 as f32, pos.2 as f32) + Vec3::splat(0.5);
+        let in_reach = center.distance(client.player.eye_position()) <= REACH + REACH_TOLERANCE;
+        let bed = self.world.get_block_at(pos.0, pos.1, pos.2) == Some(BlockType::Bed);
+        if in_reach && bed && sleep::can_sleep(&self.world.time) {
+            client.bed = Some(pos);
+        } else {
+            log::warn!("Rejected sleep of {} at {:?}", client.name, pos);
+            client.bed = None;
+            client.connection.send(ServerMessage::WakeUp);
+        }
+    }
+
+    /// Run the block scripts for the events of this update and pass the
+    /// events on to the in-process clients
+    fn dispatch_events(&mut self) {
+        for event in self.world.events.drain() {
+            match event {
+                GameEvent::BlockBroken { pos, block } => {
+                    self.scripts.run(BlockHook::Break, block, &mut self.world, pos);
+                    self.send_local(ServerMessage::WorldChanged);
+                }
+                GameEvent::BlockPlaced { pos, block } => {
+                    self.scripts.run(BlockHook::Place, block, &mut self.world, pos);
+                    self.send_local(ServerMessage::WorldChanged);
+                }
+                _ => {}
+            }
+            self.send_local(ServerMessage::Event(event));
+        }
+    }
+
+    /// Pass the blocks set since the last update on to the network clients
+    /// that have their chunk: a single change on its own, a few together and
+    /// a chunk with many changes (an explosion, a script) as a whole
+    fn sync_blocks(&mut self) {
+        let changes = std::mem::take(&mut self.world.block_changes);
+        if changes.is_empty() {
+            return;
+        }
+        // Network players change the shared world behind the local client's back
+        self.send_local(ServerMessage::WorldChanged);
+        if !self.clients.iter().any(|client| client.remote) {
+            return;
+        }
+
+        let mut by_chunk: HashMap<(i32, i32), Vec<(u16, BlockType)>> = HashMap::new();
+        for ((x, y, z), block) in changes {
+            let chunk = (x.div_euclid(16), z.div_euclid(16));
+            let index = x.rem_euclid(16) + z.rem_euclid(16) * 16 + y * 16 * 16;
+            // Clients apply them in order, so a block changed twice ends up right
+            by_chunk.entry(chunk).or_default().push((index as u16, block));
+        }
+        for ((x, z), changes) in by_chunk {
+            let message = match changes.as_slice() {
+                &[(index, block)] => {
+                    let index = index as i32;
+                    let pos = (x * 16 + index % 16, index / (16 * 16), z * 16 + index / 16 % 16);
+                    ServerMessage::BlockChange { pos, block }
+                }
+                _ if changes.len() > MAX_BLOCK_CHANGES => match self.world.chunks.get(&(x, z)) {
+                    Some(chunk) => ServerMessage::ChunkData { x, z, data: protocol::compress_blocks(&chunk.blocks) },
+                    None => continue,
+                },
+                _ => ServerMessage::MultiBlockChange { x, z, changes },
+            };
+            for client in self.clients.iter().filter(|client| client.remote && client.sent_chunks.contains(&(x, z))) {
+                client.connection.send(message.clone());
+            }
+        }
+    }
+
+    /// Send network clients the loaded chunks in their view distance they
+    /// don't have yet, and tell them to drop those they left behind. Each
+    /// client gets the chunks nearest to it and in front of it first.
+    fn stream_chunks(&mut self) {
+        for client in self.clients.iter_mut().filter(|client| client.remote) {
+            let center = client.chunk();
+            let radius = client.view_distance;
+            let mut missing: Vec<(i32, i32)> = chunk_loader::area(center, radius)
+                .filter(|key| !client.sent_chunks.contains(key) && self.world.chunks.contains_key(key))
+                .collect();
+            let (position, facing) = client.view();
+            missing.sort_by(|&a, &b| {
+                chunk_loader::load_priority(a, position, facing).total_cmp(&chunk_loader::load_priority(b, position, facing))
+            });
+            for (x, z) in missing.into_iter().take(CHUNKS_PER_UPDATE) {
+                let Some(data) = self.world.chunks.with(&(x, z), |chunk| protocol::compress_blocks(&chunk.blocks)) else {
+                    continue;
+                };
+                client.connection.send(ServerMessage::ChunkData { x, z, data });
+                client.sent_chunks.insert((x, z));
+            }
+
+            // One chunk of slack so walking along a border doesn't resend chunks
+            let out_of_range = |&(x, z): &(i32, i32)| (x - center.0).abs().max((z - center.1).abs()) > radius + 1;
+            let left: Vec<(i32, i32)> = client.sent_chunks.iter().copied().filter(out_of_range).collect();
+            for (x, z) in left {
+                client.sent_chunks.remove(&(x, z));
+                client.connection.send(ServerMessage::ChunkUnload { x, z });
+            }
+        }
+    }
+
+    /// Add the clients that logged in over the network since the last update
+    fn accept_clients(&mut self) {
+        let arrivals: Vec<NewClient> = self.arrivals.as_ref().map(|a| a.try_iter().collect()).unwrap_or_default();
+        for new_client in arrivals {
+            log::info!("{} connected from {}", new_client.name, new_client.address);
+            if !self.permissions.may_join(&player_name(&new_client.name)) {
+                log::info!("{} is not on the whitelist", new_client.name);
+                let reason = "You are not whitelisted on this server".to_string();
+                new_client.connection.send(ServerMessage::Disconnect(reason));
+                continue;
+            }
+            // The login answer comes before anything `add_client` sends
+            new_client.connection.send(ServerMessage::LoginAccepted(LoginInfo {
+                player_id: self.next_client_id,
+                seed: self.world.seed,
+                spawn: self.spawn_position().to_array(),
+                time: self.world.time.ticks(),
+                weather: self.world.time.weather(),
+                border: self.world.border,
+                rules: self.world.rules.clone(),
+            }));
+            self.add_client(new_client.name, new_client.connection, true);
+        }
+    }
+
+    /// Register a client and introduce it and the other players to each other
+    fn add_client(&mut self, name: String, connection: ServerConnection, remote: bool) {
+        let name = player_name(&name);
+        let id = self.next_client_id;
+        self.next_client_id += 1;
+        let mut player = Player::new(self.spawn_position());
+        // The host carries on where the game crashed
+        if let Some(state) = self.recovered_player.take_if(|_| !remote) {
+            state.restore(&mut player);
+            connection.send(ServerMessage::Teleport { position: state.position });
+            connection.send(ServerMessage::GameMode(state.game_mode));
+            connection.send(ServerMessage::Vitals { health: state.health, hunger: state.hunger });
+        }
+        for other in &self.clients {
+            other.connection.send(ServerMessage::EntitySpawn {
+                id,
+                name: name.clone(),
+                position: player.position.to_array(),
+                yaw: 0.0,
+            });
+            connection.send(ServerMessage::EntitySpawn {
+                id: other.id,
+                name: other.name.clone(),
+                position: other.player.position.to_array(),
+                yaw: other.yaw,
+            });
+            if !other.skin.is_empty() {
+                connection.send(ServerMessage::EntitySkin { id: other.id, data: other.skin.clone() });
+            }
+        }
+        log::info!("{} joined the game", name);
+        self.clients.push(Client {
+            id,
+            name,
+            connection,
+            remote,
+            yaw: 0.0,
+            announced: (player.position, 0.0),
+            player,
+            view_distance: self.view_distance,
+            since_move: 0.0,
+            sent_chunks: HashSet::new(),
+            silent_for: 0.0,
+            kicked: None,
+            since_attack: ATTACK_COOLDOWN,
+            selection: Selection::default(),
+            skin: Vec::new(),
+            bed: None,
+        });
+    }
+
+    /// Remove a client, telling it why if it is still listening
+    fn drop_client(&mut self, index: usize, reason: &str) {
+        let client = self.clients.remove(index);
+        client.connection.send(ServerMessage::Disconnect(reason.to_string()));
+        log::info!("{} left the game ({})", client.name, reason);
+        self.broadcast(ServerMessage::EntityDespawn { id: client.id });
+    }
+
+    /// Tell every client where the players that moved or turned are now
+    fn announce_moves(&mut self) {
+        for index in 0..self.clients.len() {
+            let client = &self.clients[index];
+            let (position, yaw) = client.announced;
+            if client.player.position.distance_squared(position) < 1e-4 && (client.yaw - yaw).abs() < 0.01 {
+                continue;
+            }
+            let message = ServerMessage::EntityMove {
+                id: client.id,
+                position: client.player.position.to_array(),
+                yaw: client.yaw,
+            };
+            self.send_others(index, message);
+            let client = &mut self.clients[index];
+            client.announced = (client.player.position, client.yaw);
+        }
+    }
+
+    /// Ping the network clients now and then; drop those that went quiet,
+    /// closed their connection or were kicked
+    fn keep_alive(&mut self, delta_time: f32) {
+        self.since_keep_alive += delta_time;
+        if self.since_keep_alive >= KEEP_ALIVE_INTERVAL {
+            self.since_keep_alive = 0.0;
+            self.keep_alive_id += 1;
+            for client in self.clients.iter().filter(|client| client.remote) {
+                client.connection.send(ServerMessage::KeepAlive(self.keep_alive_id));
+            }
+        }
+
+        let mut index = 0;
+        while index < self.clients.len() {
+            let client = &self.clients[index];
+            if let Some(reason) = client.kicked.clone() {
+                self.drop_client(index, &reason);
+            } else if !client.connection.is_open() {
+                self.drop_client(index, "disconnected");
+            } else if client.remote && client.silent_for > CLIENT_TIMEOUT {
+                self.drop_client(index, "timed out");
+            } else {
+                index += 1;
+            }
+        }
+    }
+
+    fn handle(&mut self, index: usize, message: ClientMessage) {
+        match message {
+            ClientMessage::Move { position, velocity, on_ground, yaw } => {
+                self.clients[index].yaw = yaw;
+                self.handle_move(index, Vec3::from(position), Vec3::from(velocity), on_ground)
+            }
+            ClientMessage::ViewDistance(distance) => self.clients[index].view_distance = distance.clamp(2, 16),
+            ClientMessage::Command(line) => {
+                let output = self.run_command(Some(index), &line, self.is_op(index));
+                self.clients[index].connection.send(ServerMessage::CommandOutput(output));
+            }
+            ClientMessage::Chat(text) => {
+                let text: String = text.trim().chars().filter(|c| !c.is_control()).take(MAX_CHAT_LENGTH).collect();
+                if text.starts_with('/') {
+                    // A command typed into the chat runs like one sent as `Command`
+                    let output = self.run_command(Some(index), &text, self.is_op(index));
+                    self.clients[index].connection.send(ServerMessage::CommandOutput(output));
+                } else if !text.is_empty() {
+                    let from = self.clients[index].name.clone();
+                    log::info!("<{}> {}", from, text);
+                    self.broadcast(ServerMessage::Chat { from, text });
+                }
+            }
+            ClientMessage::SetBlock { pos, block } => self.handle_set_block(index, pos, block),
+            ClientMessage::UseBlock { pos } => self.handle_use_block(index, pos),
+            ClientMessage::Swing => {
+                let id = self.clients[index].id;
+                self.send_others(index, ServerMessage::EntitySwing { id });
+            }
+            ClientMessage::Skin(data) => {
+                if data.len() > MAX_SKIN_FILE_SIZE {
+                    log::warn!("Ignoring the {} byte skin of {}", data.len(), self.clients[index].name);
+                    return;
+                }
+                let client = &mut self.clients[index];
+                client.skin = data.clone();
+                let id = client.id;
+                self.send_others(index, ServerMessage::EntitySkin { id, data });
+            }
+            ClientMessage::Attack { target } => self.handle_attack(index, target),
+            ClientMessage::Sleep { pos } => self.handle_sleep(index, pos),
+            ClientMessage::WakeUp => self.clients[index].bed = None,
+            ClientMessage::Respawn => {
+                let spawn_position = self.world.spawn_position();
+                let client = &mut self.clients[index];
+                client.player.teleport(spawn_position);
+                client.since_move = 0.0;
+                client.connection.send(ServerMessage::Teleport { position: spawn_position.to_array() });
+            }
+            ClientMessage::Save => {
+                let result = self.save();
+                self.clients[index].connection.send(ServerMessage::Saved(result));
+            }
+            // Any message counts as a sign of life; logging in twice changes nothing
+            ClientMessage::KeepAlive(_) | ClientMessage::Login { .. } => {}
+        }
+    }
+
+    /// Accept the client's predicted position unless it got there impossibly
+    /// fast or ended up inside a block; then send the player back.
+    fn handle_move(&mut self, index: usize, position: Vec3, velocity: Vec3, on_ground: bool) {
+        let client = &mut self.clients[index];
+        let too_fast = position.distance(client.player.position) > MAX_PLAYER_SPEED * client.since_move + MOVE_TOLERANCE;
+        // A player already stuck in a block (sand fell on them) may move out
+        let blocked = Player::new(position).check_collision(&self.world) && !client.player.check_collision(&self.world);
+        let outside = position.distance(self.world.clamp_to_border(position, 0.0)) > MOVE_TOLERANCE;
+        if too_fast || blocked || outside {
+            log::warn!(
+                "Rejected move of {} from ({:.1}, {:.1}, {:.1}) to ({:.1}, {:.1}, {:.1})",
+                client.name,
+                client.player.position.x, client.player.position.y, client.player.position.z,
+                position.x, position.y, position.z
+            );
+            client.connection.send(ServerMessage::Teleport { position: client.player.position.to_array() });
+            return;
+        }
+        client.player.position = position;
+        client.player.velocity = velocity;
+        client.player.on_ground = on_ground;
+        client.player.update_bounding_box();
+        client.since_move = 0.0;
+    }
+
+    /// Apply a network client's block edit if the block is in reach and may go
+    /// there, like the in-process client's interaction; otherwise correct the
+    /// client's copy
+    fn handle_set_block(&mut self, index: usize, pos: (i32, i32, i32), block: BlockType) {
+        let client = &self.clients[index];
+        let center = Vec3::new(pos.0 as f32, pos.1 as f32, pos.2 as f32) + Vec3::splat(0.5);
+        let in_reach = center.distance(client.player.eye_position()) <= REACH + REACH_TOLERANCE;
+        // A solid block may not go where a player or another entity is, nor
+        // any block without the support it needs
+        let cell = Aabb::block(pos);
+        let players = self.clients.iter().any(|other| other.player.bounding_box.intersects(&cell));
+        let traps = block.is_full_cube() && (players || self.world.entity_in(&cell));
+        let unsupported = !self.world.can_place(pos, block);
+        let current = self.world.get_block_at(pos.0, pos.1, pos.2);
+        match current {
+            Some(current) if in_reach && !traps && !unsupported => {
+                if current != block && self.world.set_block_at(pos.0, pos.1, pos.2, block) {
+                    let event = if block == BlockType::Air {
+                        GameEvent::BlockBroken { pos, block: current }
+                    } else {
+                        GameEvent::BlockPlaced { pos, block }
+                    };
+                    self.world.events.emit(event);
+                }
+            }
+            _ => {
+                log::warn!("Rejected block change of {} at {:?}", client.name, pos);
+                let block = current.unwrap_or(BlockType::Air);
+                client.connection.send(ServerMessage::BlockChange { pos, block });
+            }
+        }
+    }
+
+    /// A player used a block, flipping a lever or a door. Uses out of reach
+    /// are ignored; the client predicted nothing, so there is nothing to undo.
+    fn handle_use_block(&mut self, index: usize, pos: (i32, i32, i32)) {
+        let client = &self.clients[index];
+        let center = Vec3::new(pos.0 as f32, pos.1 as f32, pos.2 as f32) + Vec3::splat(0.5);
+        if center.distance(client.player.eye_position()) > REACH + REACH_TOLERANCE {
+            log::warn!("Rejected use of the block at {:?} by {}", pos, client.name);
+            return;
+        }
+        if let Some(block) = self.world.get_block_at(pos.0, pos.1, pos.2) {
+            // A script replaces what levers and doors do
+            if !self.scripts.run(BlockHook::Interact, block, &mut self.world, pos) {
+                circuit::switch(&mut self.world, pos);
+            }
+            self.send_local(ServerMessage::WorldChanged);
+        }
+    }
+
+    /// A player hit another one. Hits out of reach or too soon after the last
+    /// one are ignored; the client predicted nothing, so there is nothing to undo.
+    fn handle_attack(&mut self, index: usize, target: u32) {
+        let attacker = &self.clients[index];
+        let Some(victim) = self.clients.iter().find(|client| client.id == target && client.id != attacker.id) else {
+            return;
+        };
+        let eye = attacker.player.eye_position();
+        let closest = eye.clamp(victim.player.bounding_box.min, victim.player.bounding_box.max);
+        if closest.distance(eye) > REACH + REACH_TOLERANCE || attacker.since_attack < ATTACK_COOLDOWN {
+            log::warn!("Rejected attack of {} on {}", attacker.name, victim.name);
+            return;
+        }
+        // Away from the attacker and a little up
+        let offset = victim.player.position - attacker.player.position;
+        let away = Vec3::new(offset.x, 0.0, offset.z).normalize_or_zero();
+        let knockback = away * KNOCKBACK_SPEED + Vec3::Y * KNOCKBACK_LIFT;
+        victim.connection.send(ServerMessage::Hurt { amount: ATTACK_DAMAGE, knockback: knockback.to_array() });
+        self.clients[index].since_attack = 0.0;
+        let id = self.clients[index].id;
+        self.send_others(index, ServerMessage::EntitySwing { id });
+    }
+
+    /// Run a console command typed on the server, as the first player if
+    /// anyone is connected. The console may run every command.
+    pub fn execute_command(&mut self, line: &str) -> CommandResult {
+        let target = if self.clients.is_empty() { None } else { Some(0) };
+        self.run_command(target, line, true)
+    }
+
+    /// In-process clients are the singleplayer host; network clients need to
+    /// be on the operator list
+    fn is_op(&self, index: usize) -> bool {
+        let client = &self.clients[index];
+        !client.remote || self.permissions.is_op(&client.name)
+    }
+
+    /// Run a console command as a client's player, telling the client about
+    /// its effects on the player. Only operators (`op`) may run `OP_COMMANDS`.
+    fn run_command(&mut self, index: Option<usize>, line: &str, op: bool) -> CommandResult {
+        let (name, args) = commands::parse(line)?;
+        if !op && OP_COMMANDS.contains(&name) {
+            return Err(format!("Only operators may use /{}", name));
+        }
+        let by = index.map_or("the server".to_string(), |index| self.clients[index].name.clone());
+        match (name, args.as_slice()) {
+            ("kick", [player, reason @ ..]) => return self.kick(player, reason, &by),
+            ("kick", []) => return Err("Usage: /kick <player> [reason]".to_string()),
+            ("op" | "deop", [player]) => {
+                let changed = self.permissions.set_op(player, name == "op")?;
+                return Ok(match (changed, name) {
+                    (false, "op") => format!("{} is already an operator", player),
+                    (false, _) => format!("{} is not an operator", player),
+                    (true, "op") => format!("Made {} an operator", player),
+                    (true, _) => format!("{} is no longer an operator", player),
+                });
+            }
+            ("op" | "deop", _) => return Err(format!("Usage: /{} <player>", name)),
+            ("stop", _) => {
+                log::info!("Stopped by {}", by);
+                self.stopping = true;
+                self.broadcast(ServerMessage::Disconnect("The server stopped".to_string()));
+                return Ok("Stopping the server".to_string());
+            }
+            _ => {}
+        }
+
+        // A network client keeps its own inventory and experience, and adds
+        // what the command gives itself
+        if let Some(client) = index.map(|index| &self.clients[index]).filter(|client| client.remote) {
+            match name {
+                "give" => {
+                    let (item, count) = commands::parse_give(&args)?;
+                    client.connection.send(ServerMessage::Give { item, count });
+                    return Ok(format!("Gave {} x {}", count, item.name()));
+                }
+                "xp" => {
+                    let (amount, levels) = commands::parse_xp(&args)?;
+                    client.connection.send(ServerMessage::Experience { amount, levels });
+                    return Ok(format!("Gave {} {}", amount, if levels { "levels" } else { "points" }));
+                }
+                _ => {}
+            }
+        }
+
+        let (time, weather, border) = (self.world.time.ticks(), self.world.time.weather(), self.world.border);
+        let rules = self.world.rules.clone();
+        let (player, selection) = match index {
+            Some(index) => {
+                let client = &mut self.clients[index];
+                (&mut client.player, &mut client.selection)
+            }
+            None => (&mut self.console_player, &mut self.console_selection),
+        };
+        let (position, game_mode) = (player.position, player.game_mode);
+        let mut ctx = CommandContext { world: &mut self.world, player, selection };
+        let result = commands::execute(line, &mut ctx);
+
+        if let Some(client) = index.map(|index| &mut self.clients[index]) {
+            if client.player.position != position {
+                client.since_move = 0.0;
+                client.connection.send(ServerMessage::Teleport { position: client.player.position.to_array() });
+            }
+            if client.player.game_mode != game_mode {
+                client.connection.send(ServerMessage::GameMode(client.player.game_mode));
+            }
+        }
+        // Network clients run their own clock between syncs
+        if self.world.time.ticks() != time {
+            self.send_remote(ServerMessage::Time(self.world.time.ticks()));
+        }
+        if self.world.time.weather() != weather {
+            self.send_remote(ServerMessage::Weather(self.world.time.weather()));
+        }
+        if self.world.border != border {
+            self.send_remote(ServerMessage::WorldBorder(self.world.border));
+            self.enforce_border();
+        }
+        if self.world.rules != rules {
+            self.send_remote(ServerMessage::GameRules(self.world.rules.clone()));
+        }
+        result
+    }
+
+    /// Move the players a shrunk world border left outside back in
+    fn enforce_border(&mut self) {
+        for client in &mut self.clients {
+            let inside = self.world.clamp_to_border(client.player.position, Player::COLLISION_HALF_WIDTH);
+            if inside != client.player.position {
+                client.player.teleport(inside);
+                client.since_move = 0.0;
+                client.connection.send(ServerMessage::Teleport { position: inside.to_array() });
+            }
+        }
+    }
+
+    /// Drop the player named `player` once the messages of this update are
+    /// handled, so the client indices stay valid until then
+    fn kick(&mut self, player: &str, reason: &[&str], by: &str) -> CommandResult {
+        let client = self
+            .clients
+            .iter_mut()
+            .find(|client| permissions::same_name(&client.name, player))
+            .ok_or_else(|| format!("No player named {}", player))?;
+        let reason = if reason.is_empty() { format!("Kicked by {}", by) } else { reason.join(" ") };
+        client.kicked = Some(reason);
+        Ok(format!("Kicked {}", client.name))
+    }
+
+    pub fn save(&mut self) -> Result<(), String> {
+        self.world.save(&*self.storage, &self.world_path).map_err(|e| e.to_string())?;
+        self.world.mark_saved();
+        // Everything an emergency save had is in the world file now
+        self.storage.remove(&crash::emergency_path(&self.world_path)).ok();
+        Ok(())
+    }
+
+    /// Have the crash hook save this world's unsaved chunks
+    pub fn watch_for_crash(&self, crash: &CrashGuard) {
+        crash.watch_world(&self.world, Arc::clone(&self.storage), crash::emergency_path(&self.world_path));
+    }
+
+    /// Start over with fresh terrain; chunks stream back in like on startup
+    #[cfg(feature = "debug-ui")]
+    pub fn regenerate(&mut self, generator: WorldGenerator) {
+        let generator = generator.with_loot(self.generator.loot.clone());
+        self.chunk_loader = ChunkLoader::new(&generator);
+        self.world.terrain = generator.params.mode;
+        self.generator = generator;
+        self.world.chunks.clear();
+        self.world.block_entities.clear();
+        self.world.structures.clear();
+        self.world.villagers.clear();
+        self.world.block_changes.clear();
+        for client in &mut self.clients {
+            client.sent_chunks.clear();
+        }
+    }
+
+    fn broadcast(&self, message: ServerMessage) {
+        for client in &self.clients {
+            client.connection.send(message.clone());
+        }
+    }
+
+    /// Send to every client but the one at `index`
+    fn send_others(&self, index: usize, message: ServerMessage) {
+        for (other_index, other) in self.clients.iter().enumerate() {
+            if other_index != index {
+                other.connection.send(message.clone());
+            }
+        }
+    }
+
+    /// Send to the in-process clients, which share the world
+    fn send_local(&self, message: ServerMessage) {
+        for client in self.clients.iter().filter(|client| !client.remote) {
+            client.connection.send(message.clone());
+        }
+    }
+
+    /// Send to the network clients, whose copy of the world needs it
+    fn send_remote(&self, message: ServerMessage) {
+        for client in self.clients.iter().filter(|client| client.remote) {
+            client.connection.send(message.clone());
+        }
+    }
+}
+
+/// A name that fits a name tag: printable ASCII, at most `MAX_NAME_LENGTH`
+/// characters, and never empty
+fn player_name(requested: &str) -> String {
+    let name: String = requested
+        .trim()
+        .chars()
+        .filter(|c| c.is_ascii_graphic() || *c == ' ')
+        .take(MAX_NAME_LENGTH)
+        .collect();
+    if name.is_empty() { "Player".to_string() } else { name }
+}
@@ -0,0 +1,245 @@
+//! The world as the game client sees it. In singleplayer that is the world of
+//! the in-process server; connected to a LAN server it is a copy the server
+//! fills with chunk data and keeps current with block changes.
+
+use crate::chunk::{Chunk, CHUNK_HEIGHT, CHUNK_SIZE};
+use crate::chunk_loader::FrameBudget;
+use crate::cli::Args;
+use crate::commands;
+use crate::events::GameEvent;
+use crate::net;
+use crate::protocol::{self, ClientConnection, ClientMessage, LoginInfo, ServerMessage};
+use crate::scripting::{self, BlockScripts};
+use crate::server::Server;
+use crate::storage::Storage;
+use crate::world::World;
+use crate::world_gen::WorldGenerator;
+use glam::Vec3;
+use std::sync::Arc;
+
+pub enum Session {
+    Local(Box<Server>),
+    Remote(Box<RemoteWorld>),
+}
+
+/// A network client's copy of the server's world
+pub struct RemoteWorld {
+    /// Its clock ticks along with the server's `Tick` messages between syncs
+    world: World,
+    /// Same seed as the server's, for the climate and the debug UI
+    generator: WorldGenerator,
+    /// The scripts run on the server; the client only checks which blocks
+    /// react to being used
+    scripts: BlockScripts,
+    spawn_position: Vec3,
+}
+
+impl Session {
+    /// Start the world of the command line on an in-process server. Also
+    /// returns one message per block script or loot table that failed to load.
+    pub fn open(
+        args: &Args,
+        storage: Arc<dyn Storage>,
+        view_distance: i32,
+        simulation_distance: i32,
+    ) -> (Self, ClientConnection, Vec<String>) {
+        let (mut server, load_errors) = Server::open(args, storage, view_distance, simulation_distance);
+        let connection = server.connect_local(&args.name);
+        (Self::Local(Box::new(server)), connection, load_errors)
+    }
+
+    /// Log in to a LAN server
+    pub fn connect(address: &str, name: &str) -> Result<(Self, ClientConnection, Vec<String>), String> {
+        let (connection, info) = net::connect(address, name)?;
+        log::info!("Connected to {} as {} (player {})", address, name, info.player_id);
+        let (session, script_errors) = Self::remote(info);
+        Ok((session, connection, script_errors))
+    }
+
+    /// A network client's copy of the world the server described at login.
+    /// Also returns one message per block script that failed to load.
+    pub fn remote(info: LoginInfo) -> (Self, Vec<String>) {
+        let (scripts, script_errors) = BlockScripts::load(std::path::Path::new(scripting::SCRIPTS_DIR));
+        let mut world = World::new(info.seed);
+        world.time.set_ticks(info.time);
+        world.time.set_weather(info.weather);
+        world.border = info.border;
+        world.rules = info.rules;
+        let remote = RemoteWorld {
+            world,
+            generator: WorldGenerator::new(info.seed),
+            scripts,
+            spawn_position: Vec3::from(info.spawn),
+        };
+        (Self::Remote(Box::new(remote)), script_errors)
+    }
+
+    pub fn is_remote(&self) -> bool {
+        matches!(self, Self::Remote(_))
+    }
+
+    pub fn world(&self) -> &World {
+        match self {
+            Self::Local(server) => &server.world,
+            Self::Remote(remote) => &remote.world,
+        }
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        match self {
+            Self::Local(server) => &mut server.world,
+            Self::Remote(remote) => &mut remote.world,
+        }
+    }
+
+    /// The world with the block scripts, for block interaction
+    pub fn world_and_scripts(&mut self) -> (&mut World, &BlockScripts) {
+        match self {
+            Self::Local(server) => (&mut server.world, &server.scripts),
+            Self::Remote(remote) => (&mut remote.world, &remote.scripts),
+        }
+    }
+
+    /// The terrain generator, which also knows where it is cold enough to snow
+    pub fn generator(&self) -> &WorldGenerator {
+        match self {
+            Self::Local(server) => &server.generator,
+            Self::Remote(remote) => &remote.generator,
+        }
+    }
+
+    /// World and terrain generator, for the debug UI
+    #[cfg(feature = "debug-ui")]
+    pub fn dev_parts(&mut self) -> (&mut World, &WorldGenerator) {
+        match self {
+            Self::Local(server) => (&mut server.world, &server.generator),
+            Self::Remote(remote) => (&mut remote.world, &remote.generator),
+        }
+    }
+
+    pub fn spawn_position(&self) -> Vec3 {
+        match self {
+            Self::Local(server) => server.spawn_position(),
+            Self::Remote(remote) => remote.spawn_position,
+        }
+    }
+
+    /// Generate the chunks around the player, adding them to the world within
+    /// `budget`; a LAN server sends them instead
+    pub fn load_chunks(&mut self, budget: FrameBudget) {
+        if let Self::Local(server) = self {
+            server.load_chunks(budget);
+        }
+    }
+
+    /// Run the in-process server for a frame. A network client sends the
+    /// blocks it changed in its copy of the world instead.
+    pub fn update(&mut self, connection: &ClientConnection, delta_time: f32, simulate: bool) {
+        match self {
+            Self::Local(server) => {
+                server.update(delta_time, simulate);
+            }
+            Self::Remote(remote) => {
+                for (pos, block) in remote.world.block_changes.drain(..) {
+                    connection.send(ClientMessage::SetBlock { pos, block });
+                }
+            }
+        }
+    }
+
+    /// Events of a network client's own edits. The in-process server passes
+    /// events on as messages, so a local session has none.
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        match self {
+            Self::Local(_) => Vec::new(),
+            Self::Remote(remote) => remote.world.events.drain(),
+        }
+    }
+
+    /// Apply world data for a network client's copy: chunks, block changes,
+    /// the clock, and what commands gave the player. Returns true if the
+    /// blocks changed.
+    pub fn apply(&mut self, message: &ServerMessage) -> bool {
+        let Self::Remote(remote) = self else {
+            return false;
+        };
+        let world = &mut remote.world;
+        match message {
+            ServerMessage::ChunkData { x, z, data } => {
+                let blocks = match protocol::decompress_blocks(data, CHUNK_SIZE * CHUNK_HEIGHT * CHUNK_SIZE) {
+                    Ok(blocks) => blocks,
+                    Err(e) => {
+                        log::warn!("Ignoring corrupt chunk ({}, {}): {}", x, z, e);
+                        return false;
+                    }
+                };
+                if blocks.len() != CHUNK_SIZE * CHUNK_HEIGHT * CHUNK_SIZE {
+                    log::warn!("Ignoring chunk ({}, {}) with {} blocks", x, z, blocks.len());
+                    return false;
+                }
+                world.chunks.insert((*x, *z), Chunk::with_blocks(*x, *z, blocks));
+                // Faces along the border with the new chunk may now be hidden
+                for (dx, dz) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                    world.update_chunk(x + dx, z + dz, Chunk::mark_dirty);
+                }
+                true
+            }
+            ServerMessage::ChunkUnload { x, z } => {
+                world.chunks.remove(&(*x, *z));
+                false
+            }
+            ServerMessage::BlockChange { pos, block } => {
+                // Not an edit of this client, so not one to send back
+                if world.set_block_at(pos.0, pos.1, pos.2, *block) {
+                    world.block_changes.pop();
+                    return true;
+                }
+                false
+            }
+            ServerMessage::MultiBlockChange { x, z, changes } => {
+                let mut changed = false;
+                for &(index, block) in changes {
+                    let index = index as i32;
+                    let (bx, y, bz) = (x * 16 + index % 16, index / (16 * 16), z * 16 + index / 16 % 16);
+                    if world.set_block_at(bx, y, bz, block) {
+                        world.block_changes.pop();
+                        changed = true;
+                    }
+                }
+                changed
+            }
+            ServerMessage::Tick => {
+                remote.world.time.tick(&remote.world.rules);
+                false
+            }
+            ServerMessage::Time(ticks) => {
+                remote.world.time.set_ticks(*ticks);
+                false
+            }
+            ServerMessage::Weather(weather) => {
+                remote.world.time.set_weather(*weather);
+                false
+            }
+            ServerMessage::WorldBorder(border) => {
+                remote.world.border = *border;
+                false
+            }
+            ServerMessage::GameRules(rules) => {
+                remote.world.rules = rules.clone();
+                false
+            }
+            ServerMessage::Give { item, count } => {
+                let left = remote.world.inventory.add_stack(*item, *count);
+                if left > 0 {
+                    log::warn!("Inventory full, {} x {} didn't fit", left, item.name());
+                }
+                false
+            }
+            ServerMessage::Experience { amount, levels } => {
+                commands::add_experience(&mut remote.world.experience, *amount, *levels);
+                false
+            }
+            _ => false,
+        }
+    }
+}
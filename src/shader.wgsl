@@ -1,7 +1,17 @@
 struct Uniforms {
     view_proj: mat4x4<f32>,
+    camera_pos: vec4<f32>,
+    fog_color: vec4<f32>,
+    // x = start distance, y = end distance, z = enabled (0 or 1)
+    fog_params: vec4<f32>,
+    // x = brightness of the world, dimmed by clouds, y = seconds on the
+    // renderer's clock
+    light: vec4<f32>,
 };
 
+// Seconds a newly shown chunk takes to fade in out of the fog
+const FADE_IN_TIME: f32 = 1.5;
+
 @group(0) @binding(0)
 var<uniform> uniforms: Uniforms;
 
@@ -14,26 +24,48 @@ struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) color: vec3<f32>,
     @location(2) tex_coords: vec2<f32>,
+    // Block light from fire and other light sources
+    @location(3) light: f32,
+    // When the chunk first showed, on the renderer's clock
+    @location(4) appeared: f32,
+    // Origin of the vertex's chunk, per draw
+    @location(5) chunk_offset: vec3<f32>,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec3<f32>,
     @location(1) tex_coords: vec2<f32>,
+    @location(2) world_position: vec3<f32>,
+    @location(3) light: f32,
+    @location(4) appeared: f32,
 };
 
 @vertex
 fn vs_main(input: VertexInput) -> VertexOutput {
     var out: VertexOutput;
-    out.clip_position = uniforms.view_proj * vec4<f32>(input.position, 1.0);
+    let world_position = input.position + input.chunk_offset;
+    out.clip_position = uniforms.view_proj * vec4<f32>(world_position, 1.0);
     out.color = input.color;
     out.tex_coords = input.tex_coords;
+    out.world_position = world_position;
+    out.light = input.light;
+    out.appeared = input.appeared;
     return out;
 }
 
 @fragment
 fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
     let tex_color = textureSample(t_diffuse, s_diffuse, input.tex_coords);
-    // Blend texture with color (for shading)
-    return vec4<f32>(tex_color.rgb * input.color, 1.0);
+    // Blend texture with color (for shading); block light brightens what the sky leaves dark
+    let shaded = tex_color.rgb * input.color * max(uniforms.light.x, input.light);
+
+    // Linear distance fog towards the sky color (horizontal distance, like chunk loading)
+    let offset = input.world_position.xz - uniforms.camera_pos.xz;
+    let fog_range = max(uniforms.fog_params.y - uniforms.fog_params.x, 0.001);
+    let distance_fog = clamp((length(offset) - uniforms.fog_params.x) / fog_range, 0.0, 1.0) * uniforms.fog_params.z;
+    // New chunks emerge from the fog instead of popping in
+    let fade_in = 1.0 - smoothstep(0.0, FADE_IN_TIME, uniforms.light.y - input.appeared);
+    let fog = max(distance_fog, fade_in);
+    return vec4<f32>(mix(shaded, uniforms.fog_color.rgb, fog), 1.0);
 }
@@ -0,0 +1,171 @@
+//! Player skins: the 64x64 PNG images that color the player figures, in the
+//! layout Minecraft uses, so skins made for it work as they are. Each part of
+//! the figure is a box whose six faces come from one region of the image, and
+//! a second layer a little larger than the first (a hat, jacket, sleeves and
+//! trouser legs) is drawn where its pixels aren't transparent. Old 64x32
+//! skins, which have no second layer besides the hat and no left limbs, are
+//! read too; their left limbs wear the right ones.
+//!
+//! The skin of the local player is the PNG at the `skin` path of the config;
+//! it is sent to the server, which passes it on to the other players. Players
+//! without one wear a default skin in the shirt color picked for them.
+
+use image::{imageops, Rgba, RgbaImage};
+
+/// Side of a skin, in pixels
+pub const SKIN_SIZE: u32 = 64;
+/// Largest skin PNG sent to the server; a 64x64 image fits many times over
+pub const MAX_SKIN_FILE_SIZE: usize = 32 * 1024;
+
+/// Texture origin of each part's box of the first layer in the skin, and of
+/// the second layer's box; see `Skin::face_texel`
+pub const HEAD: [(u32, u32); 2] = [(0, 0), (32, 0)];
+pub const BODY: [(u32, u32); 2] = [(16, 16), (16, 32)];
+pub const RIGHT_ARM: [(u32, u32); 2] = [(40, 16), (40, 32)];
+pub const LEFT_ARM: [(u32, u32); 2] = [(32, 48), (48, 48)];
+pub const RIGHT_LEG: [(u32, u32); 2] = [(0, 16), (0, 32)];
+pub const LEFT_LEG: [(u32, u32); 2] = [(16, 48), (0, 48)];
+
+/// Default skin colors, in linear RGB like the vertex colors
+const SKIN_COLOR: [f32; 3] = [0.86, 0.66, 0.52];
+const HAIR_COLOR: [f32; 3] = [0.35, 0.22, 0.12];
+const TROUSERS_COLOR: [f32; 3] = [0.22, 0.24, 0.55];
+const EYE_COLOR: [f32; 3] = [0.08, 0.1, 0.3];
+const EYE_WHITE: [f32; 3] = [0.95, 0.95, 0.95];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skin {
+    image: RgbaImage,
+}
+
+impl Skin {
+    /// Read a skin from PNG (or other image) data. Fails on anything but a
+    /// 64x64 or 64x32 image.
+    pub fn decode(data: &[u8]) -> Result<Self, String> {
+        let image = image::load_from_memory(data).map_err(|e| e.to_string())?.to_rgba8();
+        match image.dimensions() {
+            (SKIN_SIZE, SKIN_SIZE) => Ok(Self { image }),
+            (SKIN_SIZE, 32) => Ok(Self::from_legacy(&image)),
+            (width, height) => Err(format!("a skin is 64x64 or 64x32 pixels, not {}x{}", width, height)),
+        }
+    }
+
+    /// A 64x64 skin from an old 64x32 one: the left limbs copy the right
+    /// ones. A hat without any transparent pixel was left filled in by
+    /// painting programs rather than meant, so it is dropped.
+    fn from_legacy(legacy: &RgbaImage) -> Self {
+        let mut image = RgbaImage::new(SKIN_SIZE, SKIN_SIZE);
+        imageops::replace(&mut image, legacy, 0, 0);
+        for (right, left) in [(RIGHT_ARM[0], LEFT_ARM[0]), (RIGHT_LEG[0], LEFT_LEG[0])] {
+            let limb = imageops::crop_imm(legacy, right.0, right.1, 16, 16).to_image();
+            imageops::replace(&mut image, &limb, left.0 as i64, left.1 as i64);
+        }
+        let (u, v) = HEAD[1];
+        let hat = imageops::crop_imm(&image, u, v, 32, 16).to_image();
+        if hat.pixels().all(|pixel| pixel[3] == 255) {
+            imageops::replace(&mut image, &RgbaImage::new(32, 16), u as i64, v as i64);
+        }
+        Self { image }
+    }
+
+    /// The skin of players that have none of their own: skin colored head
+    /// and arms, brown hair, a `shirt` colored body and blue trousers
+    pub fn default_with_shirt(shirt: [f32; 3]) -> Self {
+        let mut image = RgbaImage::new(SKIN_SIZE, SKIN_SIZE);
+        let mut fill = |x: u32, y: u32, width: u32, height: u32, color: [f32; 3]| {
+            let pixel = to_pixel(color);
+            for y in y..y + height {
+                for x in x..x + width {
+                    image.put_pixel(x, y, pixel);
+                }
+            }
+        };
+        // The whole region of each box: its four sides with top and bottom above
+        fill(0, 0, 32, 16, SKIN_COLOR);
+        fill(16, 16, 24, 16, shirt);
+        for (u, v) in [RIGHT_ARM[0], LEFT_ARM[0]] {
+            fill(u, v, 16, 16, SKIN_COLOR);
+        }
+        for (u, v) in [RIGHT_LEG[0], LEFT_LEG[0]] {
+            fill(u, v, 16, 16, TROUSERS_COLOR);
+        }
+        // Hair on top, around the top of the head and down the back
+        fill(8, 0, 8, 8, HAIR_COLOR);
+        fill(0, 8, 32, 2, HAIR_COLOR);
+        fill(24, 8, 8, 8, HAIR_COLOR);
+        // Eyes on the front, whites to the outside
+        fill(9, 12, 1, 1, EYE_WHITE);
+        fill(10, 12, 1, 1, EYE_COLOR);
+        fill(13, 12, 1, 1, EYE_COLOR);
+        fill(14, 12, 1, 1, EYE_WHITE);
+        Self { image }
+    }
+
+    /// Linear color of the pixel at (x, y), or None if it is more than half
+    /// transparent
+    pub fn color(&self, x: u32, y: u32) -> Option<[f32; 3]> {
+        let Rgba([r, g, b, a]) = *self.image.get_pixel(x, y);
+        (a >= 128).then(|| [to_linear(r), to_linear(g), to_linear(b)])
+    }
+
+    /// The pixel of `face` at column `s` and row `t` of the box at texture
+    /// origin `origin`, `size` pixels wide, high and deep. The faces are laid
+    /// out as in Minecraft: top and bottom side by side in the first `depth`
+    /// rows, then the right, front, left and back sides.
+    pub fn face_texel(origin: (u32, u32), size: [u32; 3], face: Face, s: u32, t: u32) -> (u32, u32) {
+        let [width, _, depth] = size;
+        let (u, v) = match face {
+            Face::Top => (depth, 0),
+            Face::Bottom => (depth + width, 0),
+            Face::Right => (0, depth),
+            Face::Front => (depth, depth),
+            Face::Left => (depth + width, depth),
+            Face::Back => (2 * depth + width, depth),
+        };
+        (origin.0 + u + s, origin.1 + v + t)
+    }
+}
+
+/// A side of a box of the figure. Model space faces +z, so the right side of
+/// the figure is the one towards -x.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Face {
+    Top,
+    Bottom,
+    Right,
+    Front,
+    Left,
+    Back,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [Face::Top, Face::Bottom, Face::Right, Face::Front, Face::Left, Face::Back];
+
+    /// Columns and rows of the face's region, for a box `size` pixels wide,
+    /// high and deep
+    pub fn texels(self, size: [u32; 3]) -> (u32, u32) {
+        let [width, height, depth] = size;
+        match self {
+            Face::Top | Face::Bottom => (width, depth),
+            Face::Right | Face::Left => (depth, height),
+            Face::Front | Face::Back => (width, height),
+        }
+    }
+}
+
+fn to_linear(channel: u8) -> f32 {
+    let c = channel as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn to_pixel(color: [f32; 3]) -> Rgba<u8> {
+    let channel = |c: f32| {
+        let c = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+        (c.clamp(0.0, 1.0) * 255.0).round() as u8
+    };
+    Rgba([channel(color[0]), channel(color[1]), channel(color[2]), 255])
+}
@@ -0,0 +1,63 @@
+//! Sleeping through the night. A right click on a bed at night, or during a
+//! thunderstorm, puts the player to bed. The server skips the night once
+//! enough of the players online are in bed: all of them, or the share the
+//! `playersSleepingPercentage` game rule asks for. After a few seconds with
+//! enough sleepers the clock jumps to the next morning, the weather clears
+//! and everyone gets up. Until then every player's HUD shows how many sleep
+//! and who is still awake.
+
+use crate::time::{Weather, WorldTime, TICKS_PER_DAY, TIME_DAY, TIME_NIGHT};
+use serde::{Deserialize, Serialize};
+
+/// Ticks enough players have to sleep for before the night is skipped
+pub const SLEEP_TICKS: u32 = 100;
+/// Awake players the HUD names; the rest are only counted
+const NAMED_AWAKE: usize = 3;
+
+/// Whether players may go to bed: at night and during thunderstorms
+pub fn can_sleep(time: &WorldTime) -> bool {
+    time.time_of_day() >= TIME_NIGHT || time.weather() == Weather::Thunder
+}
+
+/// Ticks from `time_of_day` to the next morning, at `TIME_DAY`
+pub fn ticks_until_morning(time_of_day: u32) -> u64 {
+    ((TIME_DAY + TICKS_PER_DAY - time_of_day % TICKS_PER_DAY) % TICKS_PER_DAY) as u64
+}
+
+/// Sleepers it takes to skip the night with `players` online: `percentage`
+/// of them rounded up, and at least one
+pub fn needed(players: u32, percentage: u32) -> u32 {
+    (players * percentage.min(100)).div_ceil(100).max(1)
+}
+
+/// Who is in bed, as every player's HUD shows it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SleepStatus {
+    pub sleeping: u32,
+    /// Sleepers it takes to skip the night
+    pub needed: u32,
+    /// Names of the players still awake
+    pub awake: Vec<String>,
+}
+
+impl SleepStatus {
+    /// Whether enough players sleep to skip the night
+    pub fn is_enough(&self) -> bool {
+        self.sleeping > 0 && self.sleeping >= self.needed
+    }
+
+    /// The HUD line; None while nobody sleeps
+    pub fn message(&self) -> Option<String> {
+        if self.sleeping == 0 {
+            return None;
+        }
+        if self.is_enough() {
+            return Some("Sleeping through the night".to_string());
+        }
+        let mut names = self.awake.iter().take(NAMED_AWAKE).cloned().collect::<Vec<_>>().join(", ");
+        if self.awake.len() > NAMED_AWAKE {
+            names += &format!(" and {} more", self.awake.len() - NAMED_AWAKE);
+        }
+        Some(format!("{}/{} players sleeping, awake: {}", self.sleeping, self.needed, names))
+    }
+}
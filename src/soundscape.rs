@@ -0,0 +1,133 @@
+use crate::audio::Ambience;
+use crate::block::BlockType;
+use crate::chunk::CHUNK_HEIGHT;
+use crate::world::World;
+use crate::world_gen::WATER_LEVEL;
+use glam::Vec3;
+
+/// Opaque blocks overhead that shut out the daylight and make a place a cave
+const CAVE_COVER: usize = 3;
+/// Rain loudness under a roof
+const SHELTERED_RAIN: f32 = 0.4;
+/// Eye height from which open-sky places count as mountaintops
+const MOUNTAIN_HEIGHT: f32 = WATER_LEVEL as f32 + 14.0;
+/// Blocks around the player searched for ocean water
+const OCEAN_RADIUS: i32 = 8;
+/// Share of water columns around the player that makes it an ocean
+const OCEAN_FRACTION: f32 = 0.3;
+/// Seconds for a full cross-fade between ambiences
+const FADE_TIME: f32 = 3.0;
+/// Ambience level while a music track plays
+const MUSIC_DUCK: f32 = 0.5;
+/// Random pause between music tracks, in seconds
+const MUSIC_MIN_DELAY: f32 = 180.0;
+const MUSIC_MAX_DELAY: f32 = 480.0;
+
+/// Opaque blocks above the eye
+fn cover(world: &World, eye: Vec3) -> usize {
+    let (x, y, z) = (eye.x.floor() as i32, eye.y.floor() as i32, eye.z.floor() as i32);
+    (y + 1..CHUNK_HEIGHT as i32)
+        .filter(|&above| world.get_block_at(x, above, z).is_some_and(|block| !block.is_transparent()))
+        .count()
+}
+
+/// How loud the rain is at the eye in [0, 1]: full in the open, muffled
+/// under a roof and not heard in caves
+pub fn rain_exposure(world: &World, eye: Vec3) -> f32 {
+    match cover(world, eye) {
+        0 => 1.0,
+        cover if cover < CAVE_COVER => SHELTERED_RAIN,
+        _ => 0.0,
+    }
+}
+
+/// Ambience for the player's surroundings: caves win over wind and ocean.
+/// Rain is not picked here; it plays on top of these.
+pub fn detect(world: &World, eye: Vec3) -> Option<Ambience> {
+    let (x, z) = (eye.x.floor() as i32, eye.z.floor() as i32);
+
+    let cover = cover(world, eye);
+    if cover >= CAVE_COVER {
+        return Some(Ambience::Cave);
+    }
+    if cover == 0 && eye.y >= MOUNTAIN_HEIGHT {
+        return Some(Ambience::Wind);
+    }
+
+    // Sample every other column at the water line
+    let mut columns = 0;
+    let mut water = 0;
+    for dx in (-OCEAN_RADIUS..=OCEAN_RADIUS).step_by(2) {
+        for dz in (-OCEAN_RADIUS..=OCEAN_RADIUS).step_by(2) {
+            columns += 1;
+            if world.get_block_at(x + dx, WATER_LEVEL as i32 - 1, z + dz) == Some(BlockType::Water) {
+                water += 1;
+            }
+        }
+    }
+    if water as f32 >= columns as f32 * OCEAN_FRACTION {
+        return Some(Ambience::Ocean);
+    }
+    None
+}
+
+/// Cross-fades the ambience loops and schedules the occasional music track
+pub struct Soundscape {
+    levels: [f32; Ambience::ALL.len()],
+    /// Seconds until the next music track
+    music_delay: f32,
+    tracks_played: u32,
+    rng: u32,
+}
+
+impl Soundscape {
+    pub fn new(seed: u32) -> Self {
+        let mut soundscape = Self {
+            levels: [0.0; Ambience::ALL.len()],
+            music_delay: 0.0,
+            tracks_played: 0,
+            rng: seed | 1,
+        };
+        soundscape.music_delay = soundscape.next_music_delay();
+        soundscape
+    }
+
+    fn next_music_delay(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        let t = self.rng as f32 / u32::MAX as f32;
+        MUSIC_MIN_DELAY + t * (MUSIC_MAX_DELAY - MUSIC_MIN_DELAY)
+    }
+
+    /// Fade toward `target`, and the rain toward the `rain` level, for
+    /// `delta_time` seconds. Returns the music track to start, if one is due.
+    pub fn update(&mut self, delta_time: f32, target: Option<Ambience>, rain: f32, music_playing: bool) -> Option<u32> {
+        let full = if music_playing { MUSIC_DUCK } else { 1.0 };
+        let step = delta_time / FADE_TIME;
+        for (level, ambience) in self.levels.iter_mut().zip(Ambience::ALL) {
+            let goal = match ambience {
+                Ambience::Rain => rain * full,
+                _ if target == Some(ambience) => full,
+                _ => 0.0,
+            };
+            *level = if *level < goal { (*level + step).min(goal) } else { (*level - step).max(goal) };
+        }
+
+        if music_playing {
+            return None;
+        }
+        self.music_delay -= delta_time;
+        if self.music_delay > 0.0 {
+            return None;
+        }
+        self.music_delay = self.next_music_delay();
+        self.tracks_played += 1;
+        Some(self.tracks_played)
+    }
+
+    /// Current loudness of each ambience loop in [0, 1]
+    pub fn levels(&self) -> impl Iterator<Item = (Ambience, f32)> + '_ {
+        Ambience::ALL.into_iter().zip(self.levels.iter().copied())
+    }
+}
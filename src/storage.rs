@@ -0,0 +1,184 @@
+//! Where world saves, the config and the statistics are kept. The game
+//! reads and writes them through a `Storage`: files on the desktop and the
+//! dedicated server, the browser's local storage on the web, which has no
+//! file system, and memory in tests. Paths name the entries the same way on
+//! all of them.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+pub trait Storage: Send + Sync {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Replace the contents of `path` with `data`, creating it if needed
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()>;
+
+    /// Paths of the entries directly in `dir`, sorted
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>>;
+
+    fn remove(&self, path: &Path) -> io::Result<()>;
+
+    fn exists(&self, path: &Path) -> bool {
+        self.read(path).is_ok()
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// The storage of the platform the game runs on
+pub fn platform() -> Arc<dyn Storage> {
+    #[cfg(target_arch = "wasm32")]
+    return Arc::new(LocalStorage);
+    #[cfg(not(target_arch = "wasm32"))]
+    return Arc::new(FileStorage);
+}
+
+/// Files, relative to the working directory
+pub struct FileStorage;
+
+impl Storage for FileStorage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        fs::write(path, data)
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut paths = fs::read_dir(dir)?.map(|entry| entry.map(|entry| entry.path())).collect::<io::Result<Vec<_>>>()?;
+        paths.sort();
+        Ok(paths)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+}
+
+/// Entries kept in memory only, gone with the storage; for tests
+#[derive(Default)]
+pub struct MemoryStorage {
+    entries: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, BTreeMap<PathBuf, Vec<u8>>> {
+        self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.entries().get(path).cloned().ok_or_else(|| not_found(path))
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        self.entries().insert(path.to_path_buf(), data.to_vec());
+        Ok(())
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        Ok(self.entries().keys().filter(|path| path.parent() == Some(dir)).cloned().collect())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.entries().remove(path).map(|_| ()).ok_or_else(|| not_found(path))
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{} not found", path.display()))
+}
+
+/// The browser's local storage. It only holds text, and not much of it, so
+/// entries are compressed and kept as hex under `rustcraft:<path>`.
+#[cfg(target_arch = "wasm32")]
+pub struct LocalStorage;
+
+#[cfg(target_arch = "wasm32")]
+impl LocalStorage {
+    const PREFIX: &'static str = "rustcraft:";
+
+    fn storage() -> io::Result<web_sys::Storage> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "the browser has no local storage"))
+    }
+
+    fn key(path: &Path) -> String {
+        format!("{}{}", Self::PREFIX, path.to_string_lossy())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn js_error(error: wasm_bindgen::JsValue) -> io::Error {
+    io::Error::other(format!("{:?}", error))
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Storage for LocalStorage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        use flate2::read::DeflateDecoder;
+        use std::io::Read;
+
+        let text = Self::storage()?.get_item(&Self::key(path)).map_err(js_error)?.ok_or_else(|| not_found(path))?;
+        let compressed = (0..text.len())
+            .step_by(2)
+            .map(|i| text.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("{} is damaged", path.display())))?;
+        let mut data = Vec::new();
+        DeflateDecoder::new(compressed.as_slice()).read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> io::Result<()> {
+        use flate2::write::DeflateEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let text: String = encoder.finish()?.iter().map(|byte| format!("{:02x}", byte)).collect();
+        Self::storage()?.set_item(&Self::key(path), &text).map_err(js_error)
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let storage = Self::storage()?;
+        let mut paths = Vec::new();
+        for index in 0..storage.length().map_err(js_error)? {
+            let Some(key) = storage.key(index).map_err(js_error)? else {
+                continue;
+            };
+            if let Some(path) = key.strip_prefix(Self::PREFIX).map(PathBuf::from) {
+                if path.parent() == Some(dir) {
+                    paths.push(path);
+                }
+            }
+        }
+        paths.sort();
+        Ok(paths)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        Self::storage()?.remove_item(&Self::key(path)).map_err(js_error)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        Self::storage().is_ok_and(|storage| storage.get_item(&Self::key(path)).ok().flatten().is_some())
+    }
+}
@@ -0,0 +1,238 @@
+//! Structures the generator builds into the terrain: dungeons, small stone
+//! rooms hidden underground with a chest of loot (see `loot`), and villages,
+//! a row of wooden houses on flat grassland with a path along their doors
+//! and a villager outside each (see `villager`). Every structure placed is
+//! recorded with its bounds in the world, so `/locate` can point to the
+//! nearest one and F3+L can outline them.
+
+use crate::block::BlockType;
+use crate::block_entity::BlockEntity;
+use crate::chunk::CHUNK_SIZE;
+use crate::loot;
+use crate::physics::Aabb;
+use crate::region;
+use crate::villager::Villager;
+use crate::world::World;
+use crate::world_gen::{WorldGenerator, WATER_LEVEL};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+/// One in this many chunks has a dungeon
+const DUNGEON_CHANCE: u32 = 10;
+/// Outer size of a dungeon, walls included
+const DUNGEON_SIZE: (i32, i32, i32) = (7, 5, 7);
+/// Blocks of ground kept between a dungeon's ceiling and the surface above it
+const DUNGEON_COVER: i32 = 3;
+/// Dungeons are never built below this height
+const DUNGEON_MIN_Y: i32 = 4;
+/// One in this many chunks has a village, if the ground there is flat enough
+const VILLAGE_CHANCE: u32 = 12;
+/// Mixed into the seed so villages don't follow the dungeons
+const VILLAGE_SALT: u32 = 0x7111_A6E5;
+/// Houses of a village, standing in a row with this many blocks between them
+const VILLAGE_HOUSES: i32 = 2;
+const HOUSE_GAP: i32 = 3;
+/// Side of a house, walls included, and the height of its roof above the floor
+const HOUSE_SIZE: i32 = 5;
+const HOUSE_HEIGHT: i32 = 4;
+/// Most the ground under a village may rise and fall; it is leveled
+const VILLAGE_SLOPE: i32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StructureKind {
+    Dungeon,
+    Village,
+}
+
+impl StructureKind {
+    pub const ALL: [StructureKind; 2] = [StructureKind::Dungeon, StructureKind::Village];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StructureKind::Dungeon => "dungeon",
+            StructureKind::Village => "village",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<StructureKind> {
+        StructureKind::ALL.into_iter().find(|kind| kind.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// A structure the generator placed, and the blocks it takes up from `min`
+/// up to but not including `max`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Structure {
+    pub kind: StructureKind,
+    pub min: (i32, i32, i32),
+    pub max: (i32, i32, i32),
+}
+
+impl Structure {
+    pub fn bounds(&self) -> Aabb {
+        let corner = |(x, y, z): (i32, i32, i32)| Vec3::new(x as f32, y as f32, z as f32);
+        Aabb::new(corner(self.min), corner(self.max))
+    }
+
+    pub fn center(&self) -> Vec3 {
+        let bounds = self.bounds();
+        (bounds.min + bounds.max) / 2.0
+    }
+}
+
+impl World {
+    /// The recorded structure of this kind nearest to `position`
+    pub fn nearest_structure(&self, kind: StructureKind, position: Vec3) -> Option<&Structure> {
+        self.structures
+            .iter()
+            .filter(|structure| structure.kind == kind)
+            .min_by(|a, b| a.center().distance_squared(position).total_cmp(&b.center().distance_squared(position)))
+    }
+
+    /// Whether the column at (x, z) is within `margin` blocks of a village
+    pub fn near_village(&self, x: i32, z: i32, margin: i32) -> bool {
+        self.structures.iter().any(|structure| {
+            structure.kind == StructureKind::Village
+                && (structure.min.0 - margin..structure.max.0 + margin).contains(&x)
+                && (structure.min.2 - margin..structure.max.2 + margin).contains(&z)
+        })
+    }
+}
+
+impl WorldGenerator {
+    /// Build the structures of the newly generated chunk at (chunk_x,
+    /// chunk_z) and record them in the world. They fit inside the chunk.
+    pub fn place_structures(&self, world: &mut World, chunk_x: i32, chunk_z: i32) {
+        let bits = hash(world.seed, chunk_x, chunk_z);
+        if bits.is_multiple_of(DUNGEON_CHANCE) {
+            self.place_dungeon(world, chunk_x, chunk_z, bits);
+        }
+        let bits = hash(world.seed ^ VILLAGE_SALT, chunk_x, chunk_z);
+        if bits.is_multiple_of(VILLAGE_CHANCE) {
+            place_village(world, chunk_x, chunk_z, bits);
+        }
+    }
+
+    fn place_dungeon(&self, world: &mut World, chunk_x: i32, chunk_z: i32, bits: u32) {
+        let (width, height, depth) = DUNGEON_SIZE;
+        let room = CHUNK_SIZE as i32 - width + 1;
+        let x = chunk_x * CHUNK_SIZE as i32 + (bits >> 8) as i32 % room;
+        let z = chunk_z * CHUNK_SIZE as i32 + (bits >> 16) as i32 % room;
+
+        // Deep enough under the lowest ground above it to stay hidden
+        let ground = (x..x + width)
+            .flat_map(|x| (z..z + depth).map(move |z| (x, z)))
+            .filter_map(|(x, z)| world.highest_block(x, z).map(|(y, _)| y))
+            .min();
+        let Some(ground) = ground else {
+            return;
+        };
+        let y = ground - DUNGEON_COVER - height;
+        if y < DUNGEON_MIN_Y {
+            return;
+        }
+
+        // Stone walls and ceiling around a gravel floor, with a chest in a corner
+        let chest = (x + 1, y + 1, z + 1);
+        let room = region::cuboid((x, y, z), (x + width - 1, y + height - 1, z + depth - 1)).map(|pos| {
+            let (dx, dy, dz) = (pos.0 - x, pos.1 - y, pos.2 - z);
+            let wall = dx == 0 || dx == width - 1 || dz == 0 || dz == depth - 1 || dy == height - 1;
+            let block = match dy {
+                _ if pos == chest => BlockType::Chest,
+                0 => BlockType::Gravel,
+                _ if wall => BlockType::Stone,
+                _ => BlockType::Air,
+            };
+            (pos, block)
+        });
+        world.set_blocks(room);
+        if let (Some(BlockEntity::Chest(contents)), Some(table)) =
+            (world.block_entities.get_mut(&chest), self.loot.get(loot::DUNGEON_CHEST))
+        {
+            table.fill(&mut contents.slots, bits);
+        }
+
+        world.structures.push(Structure {
+            kind: StructureKind::Dungeon,
+            min: (x, y, z),
+            max: (x + width, y + height, z + depth),
+        });
+    }
+}
+
+/// Build a village in the chunk at (chunk_x, chunk_z), unless the ground
+/// there is under water, rises and falls too much or isn't all grass
+fn place_village(world: &mut World, chunk_x: i32, chunk_z: i32, bits: u32) {
+    // The houses, then a row of grass and the path in front of their doors
+    let width = VILLAGE_HOUSES * HOUSE_SIZE + (VILLAGE_HOUSES - 1) * HOUSE_GAP;
+    let depth = HOUSE_SIZE + 2;
+    let x = chunk_x * CHUNK_SIZE as i32 + (bits >> 8) as i32 % (CHUNK_SIZE as i32 - width + 1);
+    let z = chunk_z * CHUNK_SIZE as i32 + (bits >> 16) as i32 % (CHUNK_SIZE as i32 - depth + 1);
+
+    let columns: Vec<(i32, i32)> = (x..x + width).flat_map(|x| (z..z + depth).map(move |z| (x, z))).collect();
+    let mut ground = Vec::with_capacity(columns.len());
+    for &(x, z) in &columns {
+        match world.highest_block(x, z) {
+            Some((y, BlockType::Grass)) if y >= WATER_LEVEL as i32 => ground.push(y),
+            _ => return,
+        }
+    }
+    let (Some(&low), Some(&top)) = (ground.iter().min(), ground.iter().max()) else {
+        return;
+    };
+    if top - low > VILLAGE_SLOPE {
+        return;
+    }
+
+    // The ground filled up to the highest column, with room above it
+    let path_z = z + depth - 1;
+    let mut blocks = Vec::new();
+    for (&(x, z), &y) in columns.iter().zip(&ground) {
+        blocks.extend((y..top).map(|y| ((x, y, z), BlockType::Dirt)));
+        let surface = if z == path_z { BlockType::Gravel } else { BlockType::Grass };
+        blocks.push(((x, top, z), surface));
+        blocks.extend((top + 1..=top + HOUSE_HEIGHT + 2).map(|y| ((x, y, z), BlockType::Air)));
+    }
+    // Plank houses on wooden posts, a door towards the path and a window in
+    // every other wall
+    for i in 0..VILLAGE_HOUSES {
+        let house_x = x + i * (HOUSE_SIZE + HOUSE_GAP);
+        let (last, middle) = (HOUSE_SIZE - 1, HOUSE_SIZE / 2);
+        blocks.extend(region::cuboid((house_x, top, z), (house_x + last, top + HOUSE_HEIGHT, z + last)).map(|pos| {
+            let (dx, dy, dz) = (pos.0 - house_x, pos.1 - top, pos.2 - z);
+            let edge = |d: i32| d == 0 || d == last;
+            let block = match dy {
+                0 => BlockType::Planks,
+                _ if dy == HOUSE_HEIGHT => BlockType::Planks,
+                _ if edge(dx) && edge(dz) => BlockType::Wood,
+                1 | 2 if dx == middle && dz == last => BlockType::Door,
+                2 if (dx == middle && dz == 0) || (dz == middle && edge(dx)) => BlockType::Glass,
+                _ if edge(dx) || edge(dz) => BlockType::Planks,
+                _ => BlockType::Air,
+            };
+            (pos, block)
+        }));
+    }
+    world.set_blocks(blocks);
+
+    // A villager on the path outside every door
+    for i in 0..VILLAGE_HOUSES {
+        let door_x = x + i * (HOUSE_SIZE + HOUSE_GAP) + HOUSE_SIZE / 2;
+        let position = Vec3::new(door_x as f32 + 0.5, (top + 1) as f32, path_z as f32 + 0.5);
+        world.villagers.push(Villager::new(position, bits.rotate_left(i as u32 * 7) ^ i as u32));
+    }
+    world.structures.push(Structure {
+        kind: StructureKind::Village,
+        min: (x, top, z),
+        max: (x + width, top + HOUSE_HEIGHT + 1, z + depth),
+    });
+}
+
+/// Fixed pseudo-random bits for a chunk of the world with this seed
+fn hash(seed: u32, chunk_x: i32, chunk_z: i32) -> u32 {
+    let mut h = (chunk_x as u32).wrapping_mul(0x8DA6_B343) ^ (chunk_z as u32).wrapping_mul(0xD816_3841) ^ seed.wrapping_mul(0xCB1A_B31F);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2C1B_3C6D);
+    h ^= h >> 12;
+    h
+}
@@ -0,0 +1,122 @@
+// Player survival stats: health, hunger and air.
+// Values use Minecraft's scales: 20 health/hunger points (two per heart or
+// drumstick on the HUD) and 300 ticks of air (30 per bubble).
+
+pub const MAX_HEALTH: u32 = 20;
+pub const MAX_HUNGER: u32 = 20;
+pub const MAX_AIR: u32 = 300;
+
+/// Air ticks lost per second while the head is underwater
+const AIR_DRAIN_PER_SECOND: f32 = 20.0;
+/// Air ticks regained per second above water
+const AIR_REFILL_PER_SECOND: f32 = 80.0;
+/// Damage taken each second once out of air
+const DROWNING_DAMAGE: u32 = 2;
+
+/// Exhaustion that costs one hunger point
+const EXHAUSTION_PER_HUNGER: f32 = 4.0;
+/// Exhaustion per block travelled
+const EXHAUSTION_PER_BLOCK: f32 = 0.01;
+/// Exhaustion spent for each health point regenerated
+const EXHAUSTION_PER_HEAL: f32 = 6.0;
+/// Minimum hunger for natural regeneration
+const REGEN_HUNGER: u32 = 18;
+/// Seconds between regeneration / starvation ticks
+const HUNGER_INTERVAL: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurvivalStats {
+    pub health: u32,
+    pub hunger: u32,
+    pub air: u32,
+    exhaustion: f32,
+    /// Fractional air ticks carried between frames
+    air_accumulator: f32,
+    drowning_timer: f32,
+    hunger_timer: f32,
+}
+
+impl SurvivalStats {
+    pub fn new() -> Self {
+        Self {
+            health: MAX_HEALTH,
+            hunger: MAX_HUNGER,
+            air: MAX_AIR,
+            exhaustion: 0.0,
+            air_accumulator: 0.0,
+            drowning_timer: 0.0,
+            hunger_timer: 0.0,
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.health == 0
+    }
+
+    pub fn damage(&mut self, amount: u32) {
+        self.health = self.health.saturating_sub(amount);
+    }
+
+    pub fn heal(&mut self, amount: u32) {
+        self.health = (self.health + amount).min(MAX_HEALTH);
+    }
+
+    /// Add exhaustion from activity; every 4 points costs one hunger point
+    pub fn add_exhaustion(&mut self, amount: f32) {
+        self.exhaustion += amount;
+        while self.exhaustion >= EXHAUSTION_PER_HUNGER {
+            self.exhaustion -= EXHAUSTION_PER_HUNGER;
+            self.hunger = self.hunger.saturating_sub(1);
+        }
+    }
+
+    /// Advance the stats by `delta_time` seconds.
+    /// `distance` is how far the player moved this frame, `head_submerged` whether
+    /// the eyes are inside water.
+    pub fn update(&mut self, delta_time: f32, distance: f32, head_submerged: bool) {
+        self.add_exhaustion(distance * EXHAUSTION_PER_BLOCK);
+        self.update_air(delta_time, head_submerged);
+
+        // Regenerate when well fed, starve (down to half a heart) when empty
+        self.hunger_timer += delta_time;
+        if self.hunger_timer >= HUNGER_INTERVAL {
+            self.hunger_timer -= HUNGER_INTERVAL;
+            if self.hunger >= REGEN_HUNGER && self.health < MAX_HEALTH {
+                self.heal(1);
+                self.add_exhaustion(EXHAUSTION_PER_HEAL);
+            } else if self.hunger == 0 && self.health > 1 {
+                self.damage(1);
+            }
+        }
+    }
+
+    fn update_air(&mut self, delta_time: f32, head_submerged: bool) {
+        if !head_submerged {
+            self.drowning_timer = 0.0;
+            self.air_accumulator += delta_time * AIR_REFILL_PER_SECOND;
+            let ticks = self.air_accumulator.floor();
+            self.air_accumulator -= ticks;
+            self.air = (self.air + ticks as u32).min(MAX_AIR);
+            return;
+        }
+
+        if self.air > 0 {
+            self.air_accumulator += delta_time * AIR_DRAIN_PER_SECOND;
+            let ticks = self.air_accumulator.floor();
+            self.air_accumulator -= ticks;
+            self.air = self.air.saturating_sub(ticks as u32);
+        } else {
+            self.drowning_timer += delta_time;
+            if self.drowning_timer >= 1.0 {
+                self.drowning_timer -= 1.0;
+                self.damage(DROWNING_DAMAGE);
+            }
+        }
+    }
+}
+
+impl Default for SurvivalStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,13 +1,19 @@
 #[cfg(test)]
 mod tests {
     use crate::block::BlockType;
+    use crate::item::Item;
     use crate::chunk::Chunk;
+    use crate::chunk_map::ChunkMap;
+    use crate::chunk_snapshot::ChunkSnapshot;
     use crate::mesh::MeshBuilder;
+    use crate::resource_pack::AtlasLayout;
     use crate::world::World;
     use crate::world_gen::WorldGenerator;
     use crate::physics::{Player, Aabb};
-    use crate::raycast::raycast;
+    use crate::raycast::{raycast, RaycastFilter};
+    use crate::storage::{MemoryStorage, Storage};
     use glam::Vec3;
+    use std::sync::Arc;
 
     #[test]
     fn test_block_types() {
@@ -66,8 +72,481 @@ mod tests {
         assert!(has_solid_blocks, "Generated chunk should have solid blocks");
     }
 
+    #[test]
+    fn test_terrain_params() {
+        use crate::world_gen::{TerrainParams, WATER_LEVEL};
+
+        // Default parameters reproduce the stock terrain
+        let stock = WorldGenerator::new(7);
+        let tuned = WorldGenerator::with_params(7, TerrainParams::default());
+        assert_eq!(stock.get_height(123.0, -45.0), tuned.get_height(123.0, -45.0));
+
+        // Without height variation the terrain is flat at the base height
+        let flat = WorldGenerator::with_params(7, TerrainParams {
+            height_scale: 0.0,
+            base_height: 5.0,
+            ..TerrainParams::default()
+        });
+        assert_eq!(flat.get_height(0.0, 0.0), WATER_LEVEL + 5);
+        assert_eq!(flat.get_height(500.0, -300.0), WATER_LEVEL + 5);
+    }
+
+    #[test]
+    fn test_surface_materials() {
+        use crate::world_gen::{TerrainParams, WATER_LEVEL};
+        use std::collections::HashSet;
+
+        // Land is grass over dirt, whatever the noise
+        let generator = WorldGenerator::new(7);
+        assert_eq!(generator.surface_blocks(0, 0, WATER_LEVEL + 10), (BlockType::Grass, BlockType::Dirt));
+
+        // Tall terrain around the water line has beaches, rocky shores and
+        // patchy ocean floors
+        let coast = WorldGenerator::with_params(7, TerrainParams {
+            height_scale: 25.0,
+            base_height: 0.0,
+            ..TerrainParams::default()
+        });
+        let mut shores = HashSet::new();
+        let mut floors = HashSet::new();
+        for x in (-256..256).step_by(4) {
+            for z in (-256..256).step_by(4) {
+                let height = coast.get_height(x as f64, z as f64);
+                let (top, sub) = coast.surface_blocks(x, z, height);
+                if height > WATER_LEVEL + 2 {
+                    let bare = top == BlockType::Stone && coast.is_mountain(x, z);
+                    assert!(top == BlockType::Grass || bare);
+                } else if height + 2 >= WATER_LEVEL {
+                    assert_eq!(top, sub);
+                    shores.insert(top);
+                } else {
+                    assert_eq!(top, sub);
+                    floors.insert(top);
+                }
+            }
+        }
+        assert_eq!(shores, HashSet::from([BlockType::Sand, BlockType::Stone]));
+        assert_eq!(floors, HashSet::from([BlockType::Sand, BlockType::Gravel, BlockType::Clay]));
+
+        // Generated chunks use the same layers
+        let chunk = coast.generate_chunk(3, -2);
+        let height = coast.get_height(3.0 * 16.0, -2.0 * 16.0);
+        let (top, _) = coast.surface_blocks(48, -32, height);
+        assert_eq!(chunk.get_block(0, height - 1, 0), top);
+
+        assert!(BlockType::Gravel.has_gravity());
+        assert_eq!(BlockType::from_name("clay"), Some(BlockType::Clay));
+    }
+
+    #[test]
+    fn test_mountain_biome() {
+        use crate::chunk::{CHUNK_HEIGHT, CHUNK_SIZE};
+        use crate::world_gen::WATER_LEVEL;
+
+        let generator = WorldGenerator::new(7);
+        let (mut mountains, mut plains) = (Vec::new(), Vec::new());
+        for x in (-1024..1024).step_by(16) {
+            for z in (-1024..1024).step_by(16) {
+                let height = generator.get_height(x as f64, z as f64);
+                assert!(height <= CHUNK_HEIGHT - 5);
+                if generator.is_mountain(x, z) {
+                    mountains.push((x, z, height));
+                } else if generator.mountain_factor(x as f64, z as f64) == 0.0 {
+                    plains.push((x, z, height));
+                }
+            }
+        }
+        assert!(!mountains.is_empty() && !plains.is_empty());
+
+        // Mountains are higher, bare stone up high and snowy at the top
+        let average = |columns: &[(i32, i32, usize)]| columns.iter().map(|c| c.2).sum::<usize>() / columns.len();
+        assert!(average(&mountains) > average(&plains));
+        let &(x, z, height) = mountains.iter().max_by_key(|c| c.2).unwrap();
+        assert_eq!(generator.surface_blocks(x, z, height), (BlockType::Stone, BlockType::Stone));
+        assert!(generator.has_snow_cap(x, z, height));
+        assert!(!generator.should_generate_tree(x, z));
+        for &(x, z, height) in &plains {
+            assert!(!generator.has_snow_cap(x, z, height));
+            if height > WATER_LEVEL + 2 {
+                assert_eq!(generator.surface_blocks(x, z, height).0, BlockType::Grass);
+            }
+        }
+
+        // The peak's chunk has the snow layer on the stone
+        let size = CHUNK_SIZE as i32;
+        let chunk = generator.generate_chunk(x.div_euclid(size), z.div_euclid(size));
+        let (cx, cz) = (x.rem_euclid(size) as usize, z.rem_euclid(size) as usize);
+        assert_eq!(chunk.get_block(cx, height - 1, cz), BlockType::Stone);
+        assert_eq!(chunk.get_block(cx, height, cz), BlockType::Snow);
+        assert_eq!(chunk.get_block(cx, height + 1, cz), BlockType::Air);
+    }
+
+    #[test]
+    fn test_density_terrain() {
+        use crate::chunk::CHUNK_HEIGHT;
+        use crate::world_gen::{TerrainMode, TerrainParams};
+
+        // The heightmap fills each column up to its height
+        let heightmap = WorldGenerator::new(7);
+        let height = heightmap.get_height(10.0, 20.0);
+        assert!(heightmap.is_solid(10, height - 1, 20, height) && !heightmap.is_solid(10, height, 20, height));
+        assert_eq!(heightmap.surface_height(10, 20), height);
+
+        // Density terrain has solid blocks over air: overhangs, arches and caves
+        let density = WorldGenerator::with_params(7, TerrainParams {
+            mode: TerrainMode::Density,
+            ..TerrainParams::default()
+        });
+        let mut overhangs = 0;
+        for chunk_x in -2..2 {
+            for chunk_z in -2..2 {
+                let chunk = density.generate_chunk(chunk_x, chunk_z);
+                for x in 0..16 {
+                    for z in 0..16 {
+                        assert!(chunk.get_block(x, 0, z).is_full_cube(), "no holes into the void");
+                        overhangs += (1..CHUNK_HEIGHT)
+                            .filter(|&y| chunk.get_block(x, y, z).is_full_cube() && chunk.get_block(x, y - 1, z) == BlockType::Air)
+                            .count();
+                    }
+                }
+            }
+        }
+        assert!(overhangs > 0);
+
+        // The spawn is on top of the real surface, with nothing overhead
+        let (x, y, z) = density.find_spawn(None);
+        let height = density.get_height(x as f64, z as f64);
+        assert!(density.is_solid(x, y as usize - 1, z, height));
+        assert!((y as usize..CHUNK_HEIGHT).all(|y| !density.is_solid(x, y, z, height)));
+
+        // The mode is saved with the world
+        let storage = MemoryStorage::new();
+        let world = World { terrain: TerrainMode::Density, ..World::new(7) };
+        world.save(&storage, "density.dat").unwrap();
+        assert_eq!(World::load(&storage, "density.dat").unwrap().terrain, TerrainMode::Density);
+    }
+
+    #[test]
+    fn test_region_commands() {
+        use crate::commands::{execute, CommandContext};
+        use crate::region::{Selection, MAX_REGION_BLOCKS};
+
+        // Four chunks of stone floor at y 9, all drawn
+        let mut world = World::new(1);
+        for (cx, cz) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            world.chunks.insert((cx, cz), Chunk::new(cx, cz));
+        }
+        for x in 0..32 {
+            for z in 0..32 {
+                world.set_block_at(x, 9, z, BlockType::Stone);
+            }
+        }
+        world.set_block_at(3, 10, 3, BlockType::Glass);
+        let mut player = Player::new(Vec3::new(2.5, 10.0, 2.5));
+        let mut selection = Selection::default();
+        let mut ctx = CommandContext { world: &mut world, player: &mut player, selection: &mut selection };
+
+        // Nothing happens before both corners are picked
+        assert!(execute("//set planks", &mut ctx).is_err());
+        assert_eq!(execute("//pos1", &mut ctx), Ok("First position set to (2, 10, 2)".to_string()));
+        assert!(execute("//copy", &mut ctx).is_err());
+        assert_eq!(execute("//pos2 ~2 ~1 5", &mut ctx), Ok("Second position set to (4, 11, 5) (24 blocks)".to_string()));
+        assert!(execute("//pos2 1 2", &mut ctx).is_err());
+
+        // //fill only takes the air, //set everything
+        ctx.world.chunks.update_all(|chunk| chunk.dirty = false);
+        assert_eq!(execute("//fill sand", &mut ctx), Ok("Changed 23 blocks to sand".to_string()));
+        assert_eq!(ctx.world.get_block_at(3, 10, 3), Some(BlockType::Glass));
+        assert_eq!(ctx.world.get_block_at(4, 11, 5), Some(BlockType::Sand));
+        assert_eq!(execute("//set planks", &mut ctx), Ok("Changed 24 blocks to planks".to_string()));
+        assert_eq!(ctx.world.get_block_at(3, 10, 3), Some(BlockType::Planks));
+        assert_eq!(ctx.world.get_block_at(5, 10, 5), Some(BlockType::Air));
+        assert!(ctx.world.chunks.values().all(|chunk| chunk.dirty), "the chunk and the ones around it");
+        assert!(execute("//set unobtainium", &mut ctx).is_err());
+        assert!(execute("//set", &mut ctx).is_err());
+
+        // Copies paste relative to where the player stands
+        assert_eq!(execute("//copy", &mut ctx), Ok("Copied 24 blocks".to_string()));
+        ctx.player.position = Vec3::new(20.5, 10.0, 20.5);
+        assert_eq!(execute("//paste", &mut ctx), Ok("Pasted, changing 24 blocks".to_string()));
+        assert_eq!(ctx.world.get_block_at(20, 10, 20), Some(BlockType::Planks));
+        assert_eq!(ctx.world.get_block_at(22, 11, 23), Some(BlockType::Planks));
+        assert_eq!(ctx.world.get_block_at(23, 10, 20), Some(BlockType::Air));
+        assert_eq!(execute("//paste", &mut ctx), Ok("Pasted, changing 0 blocks".to_string()));
+
+        // Regions too big for one command are refused
+        assert!(execute("//pos2 ~100 ~50 ~100", &mut ctx).is_ok());
+        let error = execute("//set air", &mut ctx).unwrap_err();
+        assert!(error.contains(&MAX_REGION_BLOCKS.to_string()), "{}", error);
+
+        // Changing blocks is for operators
+        assert!(crate::permissions::OP_COMMANDS.contains(&"set"));
+        assert!(!crate::permissions::OP_COMMANDS.contains(&"pos1"));
+    }
+
+    #[test]
+    fn test_set_blocks() {
+        use crate::region::cuboid;
+
+        // A row of four chunks, the last one not loaded
+        let mut world = World::new(1);
+        for cx in 0..3 {
+            world.chunks.insert((cx, 0), Chunk::new(cx, 0));
+        }
+        world.set_block_at(20, 10, 4, BlockType::Sand);
+        world.set_block_at(21, 9, 4, BlockType::Stone);
+        world.set_block_at(21, 10, 4, BlockType::Mushroom);
+        world.chunks.update_all(|chunk| chunk.dirty = false);
+        world.block_changes.clear();
+
+        // Blocks already right and those out of the loaded chunks are skipped
+        let floor = cuboid((0, 9, 0), (3, 9, 3)).map(|pos| (pos, BlockType::Stone));
+        let more = [((20, 10, 4), BlockType::Sand), ((60, 10, 4), BlockType::Stone), ((2, 10, 2), BlockType::Chest)];
+        assert_eq!(world.set_blocks(floor.chain(more)), 17);
+        assert_eq!(world.block_changes.len(), 17);
+        assert_eq!(world.get_block_at(3, 9, 3), Some(BlockType::Stone));
+        assert!(world.block_entity((2, 10, 2)).is_some(), "containers still get their contents");
+
+        // Only the chunk changed and its neighbors are marked, once at the end
+        assert!(world.chunks.get(&(0, 0)).unwrap().dirty && world.chunks.get(&(1, 0)).unwrap().dirty);
+        assert!(!world.chunks.get(&(2, 0)).unwrap().dirty);
+
+        // The rest happens as with `set_block_at`, such as plants popping off
+        assert_eq!(world.fill_region((21, 9, 4), (21, 9, 4), BlockType::Air), 1);
+        assert_eq!(world.get_block_at(21, 10, 4), Some(BlockType::Air));
+    }
+
+    #[test]
+    fn test_schematics() {
+        use crate::region::Clipboard;
+        use crate::schematic;
+        use std::io::Write;
+
+        let dir = std::env::temp_dir().join(format!("rustcraft_schematics_{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        assert!(schematic::list(&dir).is_empty());
+
+        // Our own files keep every block
+        let blocks = vec![BlockType::Stone, BlockType::Air, BlockType::Glass, BlockType::Chest, BlockType::Planks, BlockType::Planks];
+        let clipboard = Clipboard { offset: (-4, 1, 2), size: (3, 2, 1), blocks };
+        let path = schematic::save(&dir, "house", &clipboard).unwrap();
+        assert!(path.ends_with("house.rschem"));
+        let loaded = schematic::load(&dir, "house").unwrap();
+        assert_eq!(loaded.unknown, 0);
+        assert_eq!(loaded.clipboard, Clipboard { offset: (0, 0, 0), ..clipboard });
+
+        // Names can't leave the folder, and files must be what they claim
+        assert!(schematic::save(&dir, "../escape", &loaded.clipboard).is_err());
+        assert!(schematic::load(&dir, "").is_err());
+        assert!(schematic::load(&dir, "missing").is_err());
+        std::fs::write(dir.join("junk.rschem"), b"not a schematic").unwrap();
+        assert!(schematic::load(&dir, "junk").is_err());
+
+        // A Sponge schematic (version 2): a 2x1x2 gzipped NBT compound with
+        // a palette by Minecraft names and varint block data
+        let mut nbt = vec![10, 0, 9];
+        nbt.extend(b"Schematic");
+        let short = |nbt: &mut Vec<u8>, name: &str, value: i16| {
+            nbt.extend([2, 0, name.len() as u8]);
+            nbt.extend(name.as_bytes());
+            nbt.extend(value.to_be_bytes());
+        };
+        short(&mut nbt, "Width", 2);
+        short(&mut nbt, "Height", 1);
+        short(&mut nbt, "Length", 2);
+        nbt.extend([3, 0, 7]);
+        nbt.extend(b"Version");
+        nbt.extend(2i32.to_be_bytes());
+        nbt.extend([11, 0, 6]);
+        nbt.extend(b"Offset");
+        nbt.extend(3i32.to_be_bytes());
+        nbt.extend([0; 12]);
+        nbt.extend([10, 0, 7]);
+        nbt.extend(b"Palette");
+        for (state, index) in [("minecraft:oak_log[axis=y]", 0i32), ("minecraft:air", 1), ("minecraft:beacon", 200)] {
+            nbt.extend([3, 0, state.len() as u8]);
+            nbt.extend(state.as_bytes());
+            nbt.extend(index.to_be_bytes());
+        }
+        nbt.push(0);
+        nbt.extend([7, 0, 9]);
+        nbt.extend(b"BlockData");
+        let data = [0u8, 1, 0xC8, 0x01, 0];
+        nbt.extend((data.len() as i32).to_be_bytes());
+        nbt.extend(data);
+        nbt.push(0);
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&nbt).unwrap();
+        std::fs::write(dir.join("tower.schem"), encoder.finish().unwrap()).unwrap();
+
+        let loaded = schematic::load(&dir, "tower").unwrap();
+        assert_eq!(loaded.clipboard.size, (2, 1, 2));
+        assert_eq!(loaded.clipboard.blocks, vec![BlockType::Wood, BlockType::Air, BlockType::Air, BlockType::Wood]);
+        assert_eq!(loaded.unknown, 1, "no beacons here");
+        assert_eq!(schematic::list(&dir), vec!["house", "junk", "tower"]);
+
+        // Cut short, it fails instead of panicking
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&nbt[..nbt.len() - 8]).unwrap();
+        std::fs::write(dir.join("broken.schem"), encoder.finish().unwrap()).unwrap();
+        assert!(schematic::load(&dir, "broken").is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_structures() {
+        use crate::block_entity::BlockEntity;
+        use crate::commands::{execute, CommandContext};
+        use crate::region::Selection;
+        use crate::debug_render::{DebugLayers, DebugMesh};
+        use crate::structures::StructureKind;
+
+        let mut world = World::new(12345);
+        let generator = WorldGenerator::new(12345);
+        let mut player = Player::new(Vec3::new(0.0, 50.0, 0.0));
+        {
+            let mut ctx = CommandContext { world: &mut world, player: &mut player, selection: &mut Selection::default() };
+            assert!(execute("/locate dungeon", &mut ctx).is_err(), "nothing generated yet");
+            assert!(execute("/locate castle", &mut ctx).is_err());
+        }
+        for x in -5..5 {
+            for z in -5..5 {
+                world.load_or_generate_chunk(x, z, &generator);
+            }
+        }
+        assert!(!world.structures.is_empty());
+
+        // A dungeon is a closed stone room underground with a chest of loot
+        for dungeon in world.structures.iter().filter(|s| s.kind == StructureKind::Dungeon) {
+            let (x, y, z) = dungeon.min;
+            let (max_x, max_y, max_z) = dungeon.max;
+            assert_eq!(world.get_block_at(x, y + 1, z), Some(BlockType::Stone));
+            assert!(!world.get_block_at(x + 2, y + 1, z + 2).unwrap().is_full_cube(), "hollow inside, maybe overgrown");
+            assert_eq!(world.get_block_at(x + 1, y + 1, z + 1), Some(BlockType::Chest));
+            match world.block_entities.get(&(x + 1, y + 1, z + 1)) {
+                Some(BlockEntity::Chest(chest)) => assert!(chest.slots.iter().any(|slot| slot.is_some())),
+                _ => panic!("dungeon chest without contents"),
+            }
+            assert!(world.highest_block(x, z).unwrap().0 >= max_y);
+            assert!(max_x - x == 7 && max_z - z == 7);
+        }
+
+        // /locate finds the nearest one
+        let nearest = *world.nearest_structure(StructureKind::Dungeon, player.position).unwrap();
+        let message = execute("/locate dungeon", &mut CommandContext { world: &mut world, player: &mut player, selection: &mut Selection::default() }).unwrap();
+        let center = nearest.center();
+        assert!(message.contains(&format!("({}, {}, {})", center.x.floor(), center.y.floor(), center.z.floor())), "{}", message);
+
+        // F3+L outlines it
+        player.position = center;
+        let layers = DebugLayers { structures: true, ..DebugLayers::default() };
+        let mesh = DebugMesh::build(layers, &world, &player, &crate::camera::Camera::new(1.0), &[]);
+        assert!(mesh.line_indices.len() >= 24);
+
+        // Structures are saved with the world
+        let storage = MemoryStorage::new();
+        world.save(&storage, "structures.dat").unwrap();
+        assert_eq!(World::load(&storage, "structures.dat").unwrap().structures, world.structures);
+    }
+
+    #[test]
+    fn test_loot_tables() {
+        use crate::loot::{LootTable, LootTables, DUNGEON_CHEST};
+
+        let table = LootTable::from_json(
+            r#"{"rolls": [3, 3], "entries": [{"item": "sand", "weight": 3, "count": [2, 5]}, {"item": "stone", "weight": 0}, {"item": "bucket", "count": [1, 99]}]}"#,
+        )
+        .unwrap();
+        assert_eq!(table.entries[1].count, (1, 1), "count defaults to one");
+
+        // Seeded rolls repeat; weightless entries are never drawn and counts
+        // stay in range and within a stack
+        for seed in 0..50 {
+            let stacks = table.roll(seed);
+            assert_eq!(stacks, table.roll(seed));
+            assert_eq!(stacks.len(), 3);
+            for stack in &stacks {
+                match stack.item {
+                    Item::Block(BlockType::Sand) => assert!((2..=5).contains(&stack.count)),
+                    Item::Bucket => assert!(stack.count <= Item::Bucket.max_stack_size()),
+                    other => panic!("drew {:?}", other),
+                }
+            }
+        }
+
+        // Filling spreads the stacks over the free slots
+        let mut slots = [None; 27];
+        slots[0] = Some(crate::inventory::ItemStack::new(BlockType::Dirt, 1));
+        table.fill(&mut slots, 7);
+        assert_eq!(slots[0].unwrap().item, Item::Block(BlockType::Dirt));
+        assert_eq!(slots.iter().flatten().count(), 4);
+
+        assert!(LootTable::from_json(r#"{"rolls": [1, 2], "entries": [{"item": "diamond"}]}"#).is_err());
+        assert!(LootTable::from_json(r#"{"rolls": [3, 1], "entries": []}"#).is_err());
+
+        // Files replace the built-in tables by name; broken ones are reported
+        let dir = std::env::temp_dir().join(format!("rustcraft_loot_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("dungeon_chest.json"), r#"{"rolls": [1, 1], "entries": [{"item": "glass", "count": [4, 4]}]}"#).unwrap();
+        std::fs::write(dir.join("broken.json"), "{").unwrap();
+        let (tables, errors) = LootTables::load(&dir);
+        assert_eq!(errors.len(), 1);
+        assert!(tables.get("broken").is_none());
+        let stacks = tables.get(DUNGEON_CHEST).unwrap().roll(1);
+        assert_eq!(stacks, vec![crate::inventory::ItemStack::new(BlockType::Glass, 4)]);
+        assert_ne!(tables, LootTables::builtin());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_dark_flora() {
+        assert!(BlockType::Mushroom.is_plant() && !BlockType::Mushroom.is_full_cube());
+        assert!(BlockType::GlowingFungus.light_emission() > 0);
+
+        // Generated plants grow on solid ground in the shade
+        let mut world = World::new(12345);
+        let generator = WorldGenerator::new(12345);
+        for x in -2..2 {
+            for z in -2..2 {
+                world.load_or_generate_chunk(x, z, &generator);
+            }
+        }
+        let mut plants = Vec::new();
+        for x in -32..32 {
+            for z in -32..32 {
+                for y in 1..crate::chunk::CHUNK_HEIGHT as i32 {
+                    if world.get_block_at(x, y, z).is_some_and(|block| block.is_plant()) {
+                        plants.push((x, y, z));
+                    }
+                }
+            }
+        }
+        assert!(!plants.is_empty());
+        for &(x, y, z) in &plants {
+            assert!(world.get_block_at(x, y - 1, z).unwrap().is_full_cube());
+            assert!(world.highest_block(x, z).unwrap().0 > y);
+        }
+
+        // Breaking the ground takes the plant with it
+        let (x, y, z) = plants[0];
+        world.set_block_at(x, y - 1, z, BlockType::Air);
+        assert_eq!(world.get_block_at(x, y, z), Some(BlockType::Air));
+
+        // A plant is two crossed quads seen from both sides, as high as the plant
+        let mut world = World::new(1);
+        let mut chunk = Chunk::new(0, 0);
+        chunk.set_block(5, 10, 5, BlockType::Mushroom);
+        world.chunks.insert((0, 0), chunk);
+        let mut mesh_builder = MeshBuilder::new();
+        mesh_builder.build_chunk_mesh(&ChunkSnapshot::capture(&world.chunks, 0, 0).unwrap(), &AtlasLayout::default());
+        assert_eq!(mesh_builder.vertices.len(), 4 * 4);
+        let top = mesh_builder.vertices.iter().map(|v| v.position[1]).fold(0.0, f32::max);
+        assert_eq!(top, 10.5);
+    }
+
     #[test]
     fn test_world_save_load() {
+        use crate::storage::FileStorage;
         use std::fs;
         // Use a platform-safe temporary path
         let test_path_buf = std::env::temp_dir().join("rustcraft_test_world.dat");
@@ -78,12 +557,12 @@ mod tests {
             let mut world = World::new(54321);
             let generator = WorldGenerator::new(54321);
             world.load_or_generate_chunk(0, 0, &generator);
-            world.save(test_path).expect("Failed to save world");
+            world.save(&FileStorage, test_path).expect("Failed to save world");
         }
 
         // Load the world
         {
-            let loaded_world = World::load(test_path).expect("Failed to load world");
+            let loaded_world = World::load(&FileStorage, test_path).expect("Failed to load world");
             assert_eq!(loaded_world.seed, 54321);
             assert!(loaded_world.get_chunk(0, 0).is_some());
         }
@@ -92,6 +571,95 @@ mod tests {
         fs::remove_file(test_path_buf).ok();
     }
 
+    #[test]
+    fn test_world_spawn() {
+        use crate::world_gen::WATER_LEVEL;
+
+        // Spawn is on dry, flat land, as close to the origin as there is some
+        for seed in [1, 2, 3, 54321] {
+            let generator = WorldGenerator::new(seed);
+            let (x, y, z) = generator.find_spawn(None);
+            assert_eq!(y, generator.get_height(x as f64, z as f64) as i32);
+            assert!(y > WATER_LEVEL as i32, "Seed {} spawns in the water at ({}, {}, {})", seed, x, y, z);
+            for (dx, dz) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                assert!((generator.get_height((x + dx) as f64, (z + dz) as f64) as i32 - y).abs() <= 1);
+            }
+        }
+
+        // The spawn point is saved with the world
+        let storage = MemoryStorage::new();
+        let mut world = World::new(5);
+        assert_eq!(world.spawn, None);
+        world.spawn = Some((12, 50, -7));
+        world.save(&storage, "spawn.dat").unwrap();
+        let loaded = World::load(&storage, "spawn.dat").unwrap();
+        assert_eq!(loaded.spawn, Some((12, 50, -7)));
+    }
+
+    #[test]
+    fn test_world_border() {
+        use crate::chunk_loader::area_progress;
+        use crate::world_border::{self, parse_radius};
+
+        let generator = WorldGenerator::new(7);
+        let mut world = World::new(7);
+        assert!(world.chunk_inside_border(1000, -1000));
+        world.border = Some(20);
+
+        // Chunks with any block inside the border load, the rest never do
+        assert!(world.chunk_inside_border(0, 0));
+        assert!(world.chunk_inside_border(1, -2));
+        assert!(!world.chunk_inside_border(2, 0));
+        assert!(!world.chunk_inside_border(0, -3));
+        world.load_or_generate_chunk(2, 0, &generator);
+        assert!(world.get_chunk(2, 0).is_none());
+        for x in -2..=1 {
+            for z in -2..=1 {
+                world.load_or_generate_chunk(x, z, &generator);
+            }
+        }
+        assert_eq!(area_progress(&world, (0, 0), 4), 1.0);
+
+        // A player walking into the border stops at it
+        let (x, z) = (18, 0);
+        let y = (0..64).rev().find(|&y| world.get_block_at(x, y, z).is_some_and(|b| b.is_full_cube())).unwrap() + 1;
+        let mut player = Player::new(Vec3::new(x as f32 + 0.5, y as f32 + 0.01, z as f32 + 0.5));
+        for _ in 0..60 {
+            player.velocity.x = 10.0;
+            player.apply_physics(0.05, &world);
+        }
+        assert!(player.position.x <= 20.0 - Player::COLLISION_HALF_WIDTH);
+        assert_eq!(world.clamp_to_border(Vec3::new(-50.0, 10.0, 5.0), 1.0), Vec3::new(-19.0, 10.0, 5.0));
+
+        // So does anything else thrown at it
+        let item = crate::inventory::ItemStack::new(BlockType::Dirt, 1);
+        world.items.push(crate::item_entity::ItemEntity::thrown(item, Vec3::new(18.5, y as f32 + 1.5, 0.5), Vec3::X * 10.0));
+        for _ in 0..60 {
+            crate::item_entity::update_items(&mut world, None, 0.05);
+        }
+        assert!(world.items[0].position.x < 20.0);
+
+        // Spawn is looked for inside the border only
+        let (x, _, z) = generator.find_spawn(Some(20));
+        assert!(x.abs() < 20 && z.abs() < 20);
+
+        // The walls enclose the square
+        let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+        world_border::build_wall_mesh(20, &mut vertices, &mut indices);
+        assert_eq!(indices.len(), 4 * 6);
+        assert!(vertices.iter().all(|v| v.position[0].abs() == 20.0 || v.position[2].abs() == 20.0));
+
+        assert_eq!(parse_radius("off"), Ok(None));
+        assert_eq!(parse_radius("300"), Ok(Some(300)));
+        assert!(parse_radius("3").is_err());
+        assert!(parse_radius("wide").is_err());
+
+        // The border is saved with the world
+        let storage = MemoryStorage::new();
+        world.save(&storage, "border.dat").unwrap();
+        assert_eq!(World::load(&storage, "border.dat").unwrap().border, Some(20));
+    }
+
     #[test]
     fn test_block_colors() {
         let dirt_color = BlockType::Dirt.get_color();
@@ -102,7 +670,7 @@ mod tests {
         
         // Verify colors are in valid range [0, 1]
         for component in dirt_color {
-            assert!(component >= 0.0 && component <= 1.0);
+            assert!((0.0..=1.0).contains(&component));
         }
     }
 
@@ -123,15 +691,15 @@ mod tests {
         mesh_builder.clear();
         
         // Build meshes for both chunks
-        if let Some(chunk) = world.get_chunk(0, 0) {
-            mesh_builder.build_chunk_mesh(chunk, &world);
+        if let Some(snapshot) = ChunkSnapshot::capture(&world.chunks, 0, 0) {
+            mesh_builder.build_chunk_mesh(&snapshot, &AtlasLayout::default());
         }
         
         let vertices_after_first = mesh_builder.vertices.len();
         assert!(vertices_after_first > 0, "First chunk should generate vertices");
         
-        if let Some(chunk) = world.get_chunk(1, 0) {
-            mesh_builder.build_chunk_mesh(chunk, &world);
+        if let Some(snapshot) = ChunkSnapshot::capture(&world.chunks, 1, 0) {
+            mesh_builder.build_chunk_mesh(&snapshot, &AtlasLayout::default());
         }
         
         let vertices_after_second = mesh_builder.vertices.len();
@@ -151,8 +719,8 @@ mod tests {
         let mut mesh_builder = MeshBuilder::new();
         mesh_builder.clear();
         
-        if let Some(chunk) = world.get_chunk(0, 0) {
-            mesh_builder.build_chunk_mesh(chunk, &world);
+        if let Some(snapshot) = ChunkSnapshot::capture(&world.chunks, 0, 0) {
+            mesh_builder.build_chunk_mesh(&snapshot, &AtlasLayout::default());
         }
         
         // An isolated block should have 6 faces, each with 4 vertices
@@ -186,6 +754,298 @@ mod tests {
         assert!(has_bottom_face, "Should have vertices at bottom face position (y=10)");
     }
 
+    #[test]
+    fn test_chunk_snapshot() {
+        let mut world = World::new(1);
+        let mut chunk = Chunk::new(0, 0);
+        chunk.set_block(15, 10, 3, BlockType::Stone);
+        chunk.set_block(2, 5, 2, BlockType::Fire);
+        world.chunks.insert((0, 0), chunk);
+        let mut neighbor = Chunk::new(1, 0);
+        neighbor.set_block(0, 10, 3, BlockType::Stone);
+        neighbor.set_block(1, 10, 3, BlockType::Dirt);
+        world.chunks.insert((1, 0), neighbor);
+        assert!(ChunkSnapshot::capture(&world.chunks, 5, 5).is_none());
+
+        // The border comes from the neighbor, air where none is loaded
+        let snapshot = ChunkSnapshot::capture(&world.chunks, 0, 0).unwrap();
+        assert_eq!(snapshot.get_block(15, 10, 3), BlockType::Stone);
+        assert_eq!(snapshot.get_block(16, 10, 3), BlockType::Stone);
+        assert_eq!(snapshot.get_block(17, 10, 3), BlockType::Air);
+        assert_eq!(snapshot.get_block(-1, 10, 3), BlockType::Air);
+        assert_eq!(snapshot.get_block(3, -1, 3), BlockType::Air);
+        assert_eq!(snapshot.lights(), &[([2, 5, 2], BlockType::Fire.light_emission())]);
+        let east = ChunkSnapshot::capture(&world.chunks, 1, 0).unwrap();
+        assert_eq!(east.get_block(-1, 10, 3), BlockType::Stone);
+        assert_eq!(east.lights().len(), 1);
+
+        // Later edits don't reach a snapshot, and one meshes the same on another thread
+        world.set_block_at(16, 10, 3, BlockType::Air);
+        assert_eq!(snapshot.get_block(16, 10, 3), BlockType::Stone);
+        let mut here = MeshBuilder::new();
+        here.build_chunk_mesh(&snapshot, &AtlasLayout::default());
+        let shared = snapshot.clone();
+        let there = std::thread::spawn(move || {
+            let mut mesh = MeshBuilder::new();
+            mesh.build_chunk_mesh(&shared, &AtlasLayout::default());
+            mesh
+        })
+        .join()
+        .unwrap();
+        let corners = |mesh: &MeshBuilder| mesh.vertices.iter().map(|v| (v.position, v.light)).collect::<Vec<_>>();
+        assert_eq!(corners(&here), corners(&there));
+        assert_eq!(here.indices, there.indices);
+    }
+
+    #[test]
+    fn test_chunk_map() {
+        let mut world = World::new(1);
+        world.chunks.insert((0, 0), Chunk::new(0, 0));
+        let reader = world.chunks.reader();
+        let held = reader.get(&(0, 0)).unwrap();
+
+        // Changes show through the reader, but not in a chunk it already holds
+        world.set_block_at(3, 10, 3, BlockType::Stone);
+        world.chunks.insert((1, 0), Chunk::new(1, 0));
+        assert_eq!(held.get_block(3, 10, 3), BlockType::Air);
+        assert_eq!(reader.with(&(0, 0), |chunk| chunk.get_block(3, 10, 3)), Some(BlockType::Stone));
+        let seen = std::thread::spawn(move || {
+            let snapshot = ChunkSnapshot::capture(&reader, 0, 0).unwrap();
+            (reader.len(), snapshot.get_block(3, 10, 3))
+        })
+        .join()
+        .unwrap();
+        assert_eq!(seen, (2, BlockType::Stone));
+
+        // Saved the same way as a plain map of chunks
+        let mut plain = std::collections::HashMap::new();
+        plain.insert((0, 0), Chunk::new(0, 0));
+        let map = ChunkMap::from(plain.clone());
+        assert_eq!(bincode::serialize(&map).unwrap(), bincode::serialize(&plain).unwrap());
+        let loaded: ChunkMap = bincode::deserialize(&bincode::serialize(&plain).unwrap()).unwrap();
+        assert!(loaded.contains_key(&(0, 0)) && loaded.len() == 1);
+    }
+
+    #[test]
+    fn test_storage() {
+        use crate::storage::FileStorage;
+        use std::path::Path;
+
+        // Files and memory behave the same
+        let dir = std::env::temp_dir().join(format!("rustcraft_storage_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let storages: [(&dyn Storage, &Path); 2] = [(&FileStorage, &dir), (&MemoryStorage::new(), Path::new("saves"))];
+        for (storage, dir) in storages {
+            let path = dir.join("world.dat");
+            assert!(!storage.exists(&path));
+            assert!(storage.read(&path).is_err());
+            assert!(storage.remove(&path).is_err());
+            storage.write(&path, &[1, 2, 3]).unwrap();
+            assert!(storage.exists(&path));
+            assert_eq!(storage.read(&path).unwrap(), vec![1, 2, 3]);
+            storage.write(&path, b"text").unwrap();
+            assert_eq!(storage.read_to_string(&path).unwrap(), "text");
+
+            // Only what is directly in the directory is listed, in order
+            storage.write(&dir.join("config.json"), b"{}").unwrap();
+            storage.write(Path::new("elsewhere.dat"), b"").unwrap();
+            assert_eq!(storage.list(dir).unwrap(), vec![dir.join("config.json"), path.clone()]);
+
+            storage.remove(&path).unwrap();
+            assert!(!storage.exists(&path));
+            storage.remove(Path::new("elsewhere.dat")).unwrap();
+        }
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_emergency_save() {
+        use crate::crash::{self, PlayerState};
+        use crate::inventory::Inventory;
+
+        let mut world = World::new(5);
+        world.chunks.insert((0, 0), Chunk::new(0, 0));
+        world.chunks.insert((1, 0), Chunk::new(1, 0));
+        world.set_block_at(3, 10, 3, BlockType::Planks);
+        world.inventory = Inventory::new();
+        world.inventory.add_item(Item::Block(BlockType::Glass), 7);
+        let mut player = Player::new(Vec3::new(3.5, 11.0, 3.5));
+        player.game_mode = crate::physics::GameMode::Creative;
+        player.stats.health = 7;
+        player.stats.hunger = 4;
+
+        // Only the chunk changed since the last save is written
+        let storage = MemoryStorage::new();
+        let path = crash::emergency_path("world.dat");
+        let state = PlayerState::new(&player, &world.inventory);
+        assert_eq!(world.chunks.try_values().len(), 2);
+        assert_eq!(crash::write_emergency_save(&storage, &world.chunks.reader(), 5, Some(state), &path), Ok(1));
+        world.mark_saved();
+        assert!(world.chunks.values().all(|chunk| !chunk.unsaved));
+
+        // The next start puts the chunk and the inventory back
+        let mut restarted = World::new(5);
+        restarted.chunks.insert((0, 0), Chunk::new(0, 0));
+        let (chunks, recovered) = crash::recover(&storage, &mut restarted, &path).unwrap();
+        assert_eq!(chunks, 1);
+        assert_eq!(restarted.get_block_at(3, 10, 3), Some(BlockType::Planks));
+        assert_eq!(restarted.inventory.count_item(BlockType::Glass), 7);
+        assert!(restarted.get_chunk(0, 0).unwrap().unsaved, "A second crash saves it again");
+        // And the player where and as it was
+        let mut restored = Player::new(Vec3::ZERO);
+        recovered.unwrap().restore(&mut restored);
+        assert_eq!(restored.position, player.position);
+        assert_eq!((restored.game_mode, restored.stats.health, restored.stats.hunger), (player.game_mode, player.stats.health, player.stats.hunger));
+
+        // Not into another world, and not without a save
+        let mut other = World::new(6);
+        assert!(crash::recover(&storage, &mut other, &path).is_none());
+        assert!(other.chunks.is_empty());
+        storage.remove(&path).unwrap();
+        assert!(crash::recover(&storage, &mut restarted, &path).is_none());
+
+        // Opening the world hands the player back to the host
+        use crate::cli::Args;
+        use crate::protocol::ServerMessage;
+        use crate::server::Server;
+        use clap::Parser;
+        let args = Args::parse_from(["rustcraft", "--seed", "5"]);
+        let storage = Arc::new(MemoryStorage::new());
+        let state = PlayerState::new(&player, &world.inventory);
+        let path = crash::emergency_path(&args.world_path());
+        crash::write_emergency_save(&*storage, &World::new(5).chunks.reader(), 5, Some(state), &path).unwrap();
+        let (mut server, _) = Server::open(&args, storage, 2, 2);
+        let host = server.connect_local("Host");
+        let messages = host.receive();
+        assert!(messages.contains(&ServerMessage::Teleport { position: [3.5, 11.0, 3.5] }));
+        assert!(messages.contains(&ServerMessage::GameMode(crate::physics::GameMode::Creative)));
+        assert!(messages.contains(&ServerMessage::Vitals { health: 7, hunger: 4 }));
+        assert_eq!(server.world.inventory.count_item(BlockType::Glass), 7);
+        // Only once
+        let messages = server.connect_local("Other").receive();
+        assert!(!messages.iter().any(|m| matches!(m, ServerMessage::Teleport { .. })));
+        assert_eq!(crash::emergency_path("worlds/home.dat"), std::path::PathBuf::from("worlds/home.dat.emergency"));
+    }
+
+    #[test]
+    fn test_heightmap() {
+        let mut chunk = Chunk::new(0, 0);
+        assert_eq!(chunk.height(3, 4), 0);
+        chunk.set_block(3, 10, 4, BlockType::Stone);
+        chunk.set_block(3, 5, 4, BlockType::Dirt);
+        assert_eq!(chunk.height(3, 4), 11);
+        assert_eq!(chunk.height(4, 3), 0);
+
+        // Taking away the top finds the block below it
+        chunk.set_block(3, 10, 4, BlockType::Air);
+        assert_eq!(chunk.height(3, 4), 6);
+        chunk.set_block(3, 5, 4, BlockType::Air);
+        assert_eq!(chunk.height(3, 4), 0);
+
+        // Loaded and received chunks work theirs out
+        chunk.set_block(3, 63, 4, BlockType::Glass);
+        let loaded: Chunk = bincode::deserialize(&bincode::serialize(&chunk).unwrap()).unwrap();
+        assert_eq!(loaded.height(3, 4), 64);
+        assert_eq!(Chunk::with_blocks(0, 0, chunk.blocks.clone()).height(3, 4), 64);
+
+        let mut world = World::new(1);
+        world.chunks.insert((0, 0), chunk);
+        assert_eq!(world.highest_block(3, 4), Some((63, BlockType::Glass)));
+        assert_eq!(world.highest_block(4, 4), None);
+        world.set_block_at(3, 63, 4, BlockType::Air);
+        assert_eq!(world.highest_block(3, 4), None);
+    }
+
+    #[test]
+    fn test_vertex_layout() {
+        use crate::vertex::Vertex;
+
+        // Every field of the vertex reaches the shader, the fade-in time last
+        let layout = Vertex::desc();
+        let last = layout.attributes.last().unwrap();
+        assert_eq!(layout.attributes.len(), 5);
+        assert_eq!(last.offset + 4, layout.array_stride);
+        assert_eq!(layout.array_stride as usize, std::mem::size_of::<Vertex>());
+    }
+
+    #[test]
+    fn test_mesh_indices() {
+        use crate::mesh::MeshIndices;
+
+        // A chunk mesh with few vertices gets 16-bit indices
+        let mut indices = MeshIndices::new(vec![0, 1, 2], 4);
+        assert_eq!(indices, MeshIndices::U16(vec![0, 1, 2]));
+        assert_eq!(MeshIndices::new(vec![0, 70_000], 70_001), MeshIndices::U32(vec![0, 70_000]));
+
+        // Combined meshes widen once their vertices don't fit
+        let mut combined = MeshIndices::for_vertices(65_536);
+        combined.extend_offset(&indices, 65_532);
+        assert_eq!(combined, MeshIndices::U16(vec![65_532, 65_533, 65_534]));
+        let mut wide = MeshIndices::for_vertices(65_537);
+        wide.extend_offset(&indices, 65_533);
+        assert_eq!(wide, MeshIndices::U32(vec![65_533, 65_534, 65_535]));
+
+        // Uploads take whole 4-byte words
+        assert_eq!(indices.len(), 3);
+        indices.pad_to_words();
+        assert_eq!(indices.as_bytes().len(), 8);
+        assert_eq!(wide.as_bytes().len(), 12);
+    }
+
+    #[test]
+    fn test_terrain_batch() {
+        use crate::mesh::MeshIndices;
+        use crate::renderer::{ChunkDraw, ChunkMesh, TerrainBatch};
+        use crate::vertex::{ChunkOffset, Vertex};
+
+        let vertex = Vertex { position: [1.0, 2.0, 3.0], color: [1.0; 3], tex_coords: [0.0; 2], light: 0.0, appeared: 0.0 };
+        let mesh = |vertices: usize| ChunkMesh {
+            vertices: vec![vertex; vertices],
+            indices: MeshIndices::new(vec![0, 1, 2], vertices),
+            appeared: 0.0,
+        };
+        let (small, large) = (mesh(4), mesh(40_000));
+        let meshes = [((0, 0), &small), ((-1, 2), &large), ((3, 0), &large)];
+
+        // Every chunk has a draw and an offset of its own; with base vertices
+        // its indices count from its first vertex and stay 16-bit
+        let batch = TerrainBatch::build(&meshes, true);
+        assert_eq!(batch.vertices.len(), 80_004);
+        assert_eq!(batch.indices, MeshIndices::U16(vec![0, 1, 2, 0, 1, 2, 0, 1, 2]));
+        let draw = |first_index, base_vertex, first_instance| ChunkDraw { index_count: 3, instance_count: 1, first_index, base_vertex, first_instance };
+        assert_eq!(batch.draws, vec![draw(0, 0, 0), draw(3, 4, 1), draw(6, 40_004, 2)]);
+        assert_eq!(batch.offsets[1], ChunkOffset { origin: [-16.0, 0.0, 32.0] });
+        // Laid out as the GPU reads indirect draws
+        assert_eq!(std::mem::size_of::<ChunkDraw>(), std::mem::size_of::<wgpu::util::DrawIndexedIndirectArgs>());
+
+        // Without them the indices are offset into the shared buffer, and widen
+        let batch = TerrainBatch::build(&meshes, false);
+        assert_eq!(batch.indices, MeshIndices::U32(vec![0, 1, 2, 4, 5, 6, 40_004, 40_005, 40_006]));
+        assert!(batch.draws.iter().all(|draw| draw.base_vertex == 0));
+    }
+
+    #[test]
+    fn test_water_mesh() {
+        let mut world = World::new(12345);
+        let mut chunk = Chunk::new(0, 0);
+
+        // Two water blocks side by side, and one under them: no inner faces
+        // and the surface below the top of the cell
+        chunk.set_block(5, 10, 5, BlockType::Water);
+        chunk.set_block(6, 10, 5, BlockType::Water);
+        chunk.set_block(5, 9, 5, BlockType::Water);
+        world.chunks.insert((0, 0), chunk);
+
+        let mut mesh_builder = MeshBuilder::new();
+        mesh_builder.build_chunk_mesh(&ChunkSnapshot::capture(&world.chunks, 0, 0).unwrap(), &AtlasLayout::default());
+        // 5 faces of each top block, the lower one shows neither its top nor the side under the other
+        assert_eq!(mesh_builder.vertices.len(), (5 + 5 + 4) * 4);
+        let top = mesh_builder.vertices.iter().map(|v| v.position[1]).fold(0.0, f32::max);
+        assert_eq!(top, 10.875);
+        // Under more water the lower block fills its cell
+        assert!(mesh_builder.vertices.iter().all(|v| v.position[1] != 9.875));
+    }
+
     #[test]
     fn test_player_creation() {
         let player = Player::new(Vec3::new(0.0, 10.0, 0.0));
@@ -201,6 +1061,12 @@ mod tests {
         player.jump();
         assert!(player.velocity.y > 0.0, "Jump should give upward velocity");
         assert!(!player.on_ground, "Player should not be on ground after jump");
+
+        // Jump strength comes from the player's physics constants
+        player.on_ground = true;
+        player.physics.jump_velocity = 12.0;
+        player.jump();
+        assert_eq!(player.velocity.y, 12.0);
     }
 
     #[test]
@@ -271,10 +1137,71 @@ mod tests {
         let origin = Vec3::new(5.5, 15.0, 5.5);
         let direction = Vec3::new(0.0, -1.0, 0.0);
         
-        let result = raycast(origin, direction, 10.0, &world);
-        
-        assert!(result.hit, "Ray should hit the block");
-        assert_eq!(result.position, Some((5, 10, 5)), "Should hit the correct block");
+        let hit = raycast(origin, direction, 10.0, &world, RaycastFilter::ALL_BLOCKS).expect("Ray should hit the block");
+        assert_eq!(hit.position, (5, 10, 5), "Should hit the correct block");
+        assert_eq!(hit.normal, (0, 1, 0), "Should enter through the top face");
+        assert!((hit.distance - 4.0).abs() < 1e-5);
+        assert!((hit.point - Vec3::new(5.5, 11.0, 5.5)).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_raycast_corners_and_faces() {
+        let mut world = World::new(12345);
+        let mut chunk = Chunk::new(0, 0);
+        chunk.set_block(3, 10, 3, BlockType::Stone);
+        world.chunks.insert((0, 0), chunk);
+
+        // A diagonal ray that only clips the block's corner still hits it
+        let origin = Vec3::new(2.45, 10.5, 1.5);
+        let hit = raycast(origin, Vec3::new(1.0, 0.0, 1.0), 10.0, &world, RaycastFilter::ALL_BLOCKS).expect("Corner should be hit");
+        assert_eq!(hit.position, (3, 10, 3));
+        assert_eq!(hit.normal, (0, 0, -1));
+        assert!((hit.point - Vec3::new(3.95, 10.5, 3.0)).length() < 1e-5);
+
+        // Each side reports its own face; the reach limit is exact
+        for (origin, normal) in [
+            (Vec3::new(0.5, 10.5, 3.5), (-1, 0, 0)),
+            (Vec3::new(6.5, 10.5, 3.5), (1, 0, 0)),
+            (Vec3::new(3.5, 7.5, 3.5), (0, -1, 0)),
+            (Vec3::new(3.5, 10.5, 6.5), (0, 0, 1)),
+        ] {
+            let direction = Vec3::new(3.5, 10.5, 3.5) - origin;
+            let hit = raycast(origin, direction, 2.5, &world, RaycastFilter::ALL_BLOCKS).expect("Side should be hit");
+            assert_eq!(hit.normal, normal);
+            assert!((hit.distance - 2.5).abs() < 1e-5);
+            assert!(raycast(origin, direction, 2.4, &world, RaycastFilter::ALL_BLOCKS).is_none());
+        }
+    }
+
+    #[test]
+    fn test_raycast_filters() {
+        use crate::physics::Aabb;
+        use crate::raycast::HitTarget;
+
+        let mut world = World::new(12345);
+        let mut chunk = Chunk::new(0, 0);
+        chunk.set_block(5, 10, 5, BlockType::Sand);
+        chunk.set_block(5, 11, 5, BlockType::Water);
+        chunk.set_block(5, 12, 5, BlockType::Water);
+        world.chunks.insert((0, 0), chunk);
+        let (origin, down) = (Vec3::new(5.5, 15.0, 5.5), Vec3::NEG_Y);
+
+        // Liquids stop the ray unless ignored, then the floor below is hit
+        let hit = raycast(origin, down, 10.0, &world, RaycastFilter::ALL_BLOCKS).unwrap();
+        assert_eq!((hit.position, hit.block()), ((5, 12, 5), Some(BlockType::Water)));
+        let hit = raycast(origin, down, 10.0, &world, RaycastFilter::TARGET_BLOCKS).unwrap();
+        assert_eq!((hit.position, hit.normal), ((5, 10, 5), (0, 1, 0)));
+
+        // An entity in front of the blocks stops the ray first; one behind doesn't
+        let entities = [(7, Aabb::new(Vec3::new(5.0, 13.0, 5.0), Vec3::new(6.0, 14.0, 6.0)))];
+        let filter = RaycastFilter { entities: &entities, ..RaycastFilter::TARGET_BLOCKS };
+        let hit = raycast(origin, down, 10.0, &world, filter).unwrap();
+        assert_eq!((hit.target, hit.normal), (HitTarget::Entity(7), (0, 1, 0)));
+        assert!((hit.distance - 1.0).abs() < 1e-5);
+        assert_eq!(hit.block(), None);
+        let entities = [(7, Aabb::new(Vec3::new(5.0, 8.0, 5.0), Vec3::new(6.0, 9.0, 6.0)))];
+        let filter = RaycastFilter { entities: &entities, ..RaycastFilter::TARGET_BLOCKS };
+        assert_eq!(raycast(origin, down, 10.0, &world, filter).unwrap().target, HitTarget::Block(BlockType::Sand));
     }
 
     #[test]
@@ -285,10 +1212,8 @@ mod tests {
         let origin = Vec3::new(5.5, 15.0, 5.5);
         let direction = Vec3::new(0.0, -1.0, 0.0);
         
-        let result = raycast(origin, direction, 3.0, &world);
-        
-        assert!(!result.hit, "Ray should not hit anything in empty world");
-        assert_eq!(result.position, None, "Should have no hit position");
+        let result = raycast(origin, direction, 3.0, &world, RaycastFilter::ALL_BLOCKS);
+        assert!(result.is_none(), "Ray should not hit anything in empty world");
     }
 
     #[test]
@@ -453,30 +1378,28 @@ mod tests {
     #[test]
     fn test_config_save_load() {
         use crate::config::GameConfig;
-        use std::fs;
-        
-        let test_path_buf = std::env::temp_dir().join("rustcraft_test_config.json");
-        let test_path = test_path_buf.to_str().unwrap();
+
+        let storage = MemoryStorage::new();
+        let test_path = "config.json";
 
         // Create and save a config
         {
-            let mut config = GameConfig::default();
-            config.sensitivity = 0.01;
-            config.walk_speed = 5.0;
-            config.view_distance = 10;
-            config.save(test_path).expect("Failed to save config");
+            let config = GameConfig {
+                sensitivity: 0.01,
+                walk_speed: 5.0,
+                view_distance: 10,
+                ..Default::default()
+            };
+            config.save(&storage, test_path).expect("Failed to save config");
         }
 
         // Load the config
         {
-            let loaded_config = GameConfig::load(test_path);
+            let loaded_config = GameConfig::load(&storage, test_path);
             assert_eq!(loaded_config.sensitivity, 0.01);
             assert_eq!(loaded_config.walk_speed, 5.0);
             assert_eq!(loaded_config.view_distance, 10);
         }
-
-        // Cleanup
-        fs::remove_file(test_path_buf).ok();
     }
 
     #[test]
@@ -497,6 +1420,45 @@ mod tests {
         assert_eq!(debug_info.chunk_z, 1);
     }
 
+    #[test]
+    fn test_buffer_pool() {
+        use crate::buffer_pool::{buffer_size, BufferPool, MeshMemory, PooledBuffer};
+
+        struct FakeBuffer(u64);
+        impl PooledBuffer for FakeBuffer {
+            fn size(&self) -> u64 {
+                self.0
+            }
+        }
+
+        // New buffers come in powers of two, at least 64 KiB
+        assert_eq!(buffer_size(10), 64 * 1024);
+        assert_eq!(buffer_size(100_000), 131_072);
+        let mut pool = BufferPool::new();
+        let buffer = pool.take(100_000, FakeBuffer);
+        assert_eq!((buffer.0, pool.allocated, pool.reused), (131_072, 1, 0));
+
+        // A freed buffer is reused for data that fits and isn't much smaller
+        pool.give_back(buffer);
+        assert_eq!(pool.free_bytes(), 131_072);
+        let buffer = pool.take(120_000, |_| panic!("should reuse"));
+        assert_eq!((buffer.0, pool.reused), (131_072, 1));
+        pool.give_back(buffer);
+        assert_eq!(pool.take(200_000, FakeBuffer).0, 262_144);
+        assert_eq!(pool.take(1_000, FakeBuffer).0, 131_072, "up to twice the new size is reused");
+        pool.give_back(FakeBuffer(1 << 30));
+        assert_eq!(pool.take(1_000, FakeBuffer).0, 64 * 1024, "a far too large buffer is not");
+
+        // The pool keeps a few buffers and lets go of the oldest
+        for size in 1..=6 {
+            pool.give_back(FakeBuffer(size << 20));
+        }
+        assert_eq!(pool.free_bytes(), (3 + 4 + 5 + 6) << 20);
+
+        let memory = MeshMemory { cached_chunks: 9, gpu_bytes: 3 << 20, ..Default::default() };
+        assert!(memory.format_display().contains("9 chunks") && memory.format_display().contains("GPU 3.0 MB"));
+    }
+
     #[test]
     fn test_inventory_creation() {
         use crate::inventory::Inventory;
@@ -517,7 +1479,7 @@ mod tests {
         assert!(inventory.add_item(BlockType::Dirt, 10));
         
         // Check that dirt was added
-        assert_eq!(inventory.toolbar[0].as_ref().unwrap().block_type, BlockType::Dirt);
+        assert_eq!(inventory.toolbar[0].as_ref().unwrap().item, Item::Block(BlockType::Dirt));
         assert_eq!(inventory.toolbar[0].as_ref().unwrap().count, 10);
         
         // Add more dirt (should stack)
@@ -590,40 +1552,3643 @@ mod tests {
         
         // Check starter items
         assert!(world.inventory.toolbar[0].is_some());
-        assert_eq!(world.inventory.toolbar[0].as_ref().unwrap().block_type, BlockType::Dirt);
+        assert_eq!(world.inventory.toolbar[0].as_ref().unwrap().item, Item::Block(BlockType::Dirt));
+    }
+
+    #[test]
+    fn test_save_versions() {
+        use crate::world::SAVE_VERSION;
+        use std::path::Path;
+
+        let storage = MemoryStorage::new();
+        let mut world = World::new(3);
+        world.chunks.insert((0, 0), Chunk::new(0, 0));
+        world.set_block_at(1, 10, 1, BlockType::Planks);
+        world.spawn = Some((1, 11, 1));
+
+        // Saves start with their format version
+        world.save(&storage, "world.dat").unwrap();
+        let data = storage.read(Path::new("world.dat")).unwrap();
+        assert_eq!(&data[..8], b"RCWORLD\0");
+        assert_eq!(data[8..12], SAVE_VERSION.to_le_bytes());
+
+        // Saves from before that still load, as whatever format they read as
+        storage.write(Path::new("unversioned.dat"), &data[12..]).unwrap();
+        let loaded = World::load(&storage, "unversioned.dat").unwrap();
+        assert_eq!(loaded.get_block_at(1, 10, 1), Some(BlockType::Planks));
+        assert_eq!(loaded.spawn, Some((1, 11, 1)));
+        let old = bincode::serialize(&(&world.chunks, world.seed, &world.inventory, &world.block_entities, &world.time)).unwrap();
+        storage.write(Path::new("unversioned.dat"), &old).unwrap();
+        let loaded = World::load(&storage, "unversioned.dat").unwrap();
+        assert_eq!(loaded.get_block_at(1, 10, 1), Some(BlockType::Planks));
+        assert_eq!(loaded.spawn, None, "Format 3 had no spawn yet");
+
+        // An older format is migrated, filling in what it lacked
+        let mut versioned = data[..8].to_vec();
+        versioned.extend(3u32.to_le_bytes());
+        versioned.extend(&old);
+        storage.write(Path::new("old.dat"), &versioned).unwrap();
+        let loaded = World::load(&storage, "old.dat").unwrap();
+        assert_eq!((loaded.seed, loaded.spawn, loaded.boats.len()), (3, None, 0));
+
+        // Newer formats, cut short saves and other files are refused
+        let mut newer = data.clone();
+        newer[8..12].copy_from_slice(&(SAVE_VERSION + 1).to_le_bytes());
+        storage.write(Path::new("newer.dat"), &newer).unwrap();
+        assert!(World::load(&storage, "newer.dat").is_err());
+        storage.write(Path::new("short.dat"), &data[..10]).unwrap();
+        assert!(World::load(&storage, "short.dat").is_err());
+        storage.write(Path::new("other.dat"), b"not a world").unwrap();
+        assert!(World::load(&storage, "other.dat").is_err());
     }
 
     #[test]
     fn test_inventory_serialization() {
-        use std::fs;
-        let test_path_buf = std::env::temp_dir().join("rustcraft_test_inventory.dat");
-        let test_path = test_path_buf.to_str().unwrap();
+        let storage = MemoryStorage::new();
+        let test_path = "inventory.dat";
 
         // Create world with inventory
         {
             let mut world = World::new(54321);
             world.inventory.add_item(BlockType::Stone, 32);
-            world.save(test_path).expect("Failed to save world");
+            world.save(&storage, test_path).expect("Failed to save world");
         }
 
         // Load the world and check inventory
         {
-            let loaded_world = World::load(test_path).expect("Failed to load world");
+            let loaded_world = World::load(&storage, test_path).expect("Failed to load world");
             
             // Check that inventory was saved/loaded correctly
             // Should have starter items plus the stone we added
             let stone_count: u32 = loaded_world.inventory.toolbar.iter()
                 .chain(loaded_world.inventory.storage.iter())
                 .filter_map(|slot| slot.as_ref())
-                .filter(|stack| stack.block_type == BlockType::Stone)
+                .filter(|stack| stack.item == Item::Block(BlockType::Stone))
                 .map(|stack| stack.count)
                 .sum();
             
             assert!(stone_count >= 32, "Stone count should be at least 32, got {}", stone_count);
         }
+    }
 
-        // Cleanup
-        fs::remove_file(test_path_buf).ok();
+    #[test]
+    fn test_chest_contents() {
+        use crate::block_entity::BlockEntity;
+        use crate::inventory::{Inventory, ItemStack, SlotClick, SlotId};
+
+        let mut world = World::new(0);
+        world.chunks.insert((0, 0), Chunk::new(0, 0));
+        world.set_block_at(1, 5, 1, BlockType::Chest);
+        assert!(matches!(world.block_entity((1, 5, 1)), Some(BlockEntity::Chest(_))));
+
+        // Shift-click moves a stack into the chest, a click on the chest slot picks it up again
+        let mut inv = Inventory::new();
+        inv.toolbar[0] = Some(ItemStack::new(BlockType::Stone, 10));
+        let chest = world.block_entities.get_mut(&(1, 5, 1)).unwrap();
+        chest.quick_move_from(&mut inv, SlotId::Toolbar(0));
+        assert!(inv.toolbar[0].is_none());
+        assert_eq!(chest.slots()[0], Some(ItemStack::new(BlockType::Stone, 10)));
+        chest.click_slot(0, SlotClick::Right, &mut inv);
+        assert_eq!(inv.held, Some(ItemStack::new(BlockType::Stone, 5)));
+        chest.click_slot(4, SlotClick::Left, &mut inv);
+        assert_eq!(chest.slots()[4], Some(ItemStack::new(BlockType::Stone, 5)));
+        chest.click_slot(4, SlotClick::ShiftLeft, &mut inv);
+        assert_eq!(inv.count_item(BlockType::Stone), 5);
+
+        // Contents are saved with the world
+        let storage = MemoryStorage::new();
+        world.save(&storage, "chest.dat").unwrap();
+        let loaded = World::load(&storage, "chest.dat").unwrap();
+        assert_eq!(loaded.block_entity((1, 5, 1)), world.block_entity((1, 5, 1)));
+
+        // Replacing the block removes its block entity
+        world.set_block_at(1, 5, 1, BlockType::Air);
+        assert!(world.block_entity((1, 5, 1)).is_none());
     }
-}
 
+    #[test]
+    fn test_furnace_smelting() {
+        use crate::block_entity::{BlockEntity, Furnace, FURNACE_FUEL, FURNACE_INPUT, FURNACE_OUTPUT};
+        use crate::inventory::{Inventory, ItemStack, SlotClick, SlotId};
+
+        let mut furnace = BlockEntity::Furnace(Furnace::new());
+        let mut inv = Inventory::new();
+
+        // The fuel slot refuses non-fuel items; shift-click sorts by recipe and fuel
+        inv.held = Some(ItemStack::new(BlockType::Dirt, 1));
+        furnace.click_slot(FURNACE_FUEL, SlotClick::Left, &mut inv);
+        assert!(furnace.slots()[FURNACE_FUEL].is_none());
+        inv.held = None;
+        inv.toolbar[0] = Some(ItemStack::new(BlockType::Sand, 2));
+        inv.toolbar[1] = Some(ItemStack::new(BlockType::Planks, 1));
+        furnace.quick_move_from(&mut inv, SlotId::Toolbar(0));
+        furnace.quick_move_from(&mut inv, SlotId::Toolbar(1));
+        assert_eq!(furnace.slots()[FURNACE_INPUT], Some(ItemStack::new(BlockType::Sand, 2)));
+        assert_eq!(furnace.slots()[FURNACE_FUEL], Some(ItemStack::new(BlockType::Planks, 1)));
+
+        // One plank burns for 15 seconds, enough for one 10 second smelt
+        for _ in 0..200 {
+            furnace.update(0.1);
+        }
+        let BlockEntity::Furnace(state) = &furnace else { unreachable!() };
+        assert_eq!(state.slots[FURNACE_OUTPUT], Some(ItemStack::new(BlockType::Glass, 1)));
+        assert_eq!(state.slots[FURNACE_INPUT], Some(ItemStack::new(BlockType::Sand, 1)));
+        assert!(!state.is_burning());
+        assert_eq!(state.cook_fraction(), 0.0);
+
+        // The output can only be taken out
+        furnace.click_slot(FURNACE_OUTPUT, SlotClick::Left, &mut inv);
+        assert_eq!(inv.held, Some(ItemStack::new(BlockType::Glass, 1)));
+        furnace.click_slot(FURNACE_OUTPUT, SlotClick::Left, &mut inv);
+        assert!(furnace.slots()[FURNACE_OUTPUT].is_none());
+    }
+
+    #[test]
+    fn test_sound_synthesis() {
+        use crate::audio::{synthesize, Sound, SoundMaterial, SAMPLE_RATE};
+
+        assert_eq!(SoundMaterial::of(BlockType::Planks), SoundMaterial::Wood);
+        assert_eq!(SoundMaterial::of(BlockType::Furnace), SoundMaterial::Stone);
+        assert_ne!(synthesize(Sound::block_break(BlockType::Stone)), synthesize(Sound::block_break(BlockType::Glass)));
+
+        for sound in [
+            Sound::block_break(BlockType::Dirt),
+            Sound::block_place(BlockType::Wood),
+            Sound::UiClick,
+            Sound::Pickup,
+            Sound::Jump,
+            Sound::Land,
+            Sound::Hurt,
+            Sound::Splash,
+        ] {
+            let samples = synthesize(sound);
+            assert!(!samples.is_empty() && samples.len() < SAMPLE_RATE as usize, "{:?}", sound);
+            assert!(samples.iter().all(|s| s.abs() <= 1.0), "{:?} clips", sound);
+            assert_eq!(samples, synthesize(sound), "{:?} is not deterministic", sound);
+        }
+
+        // Placing is a shorter sound than breaking the same block
+        let place = synthesize(Sound::block_place(BlockType::Sand));
+        assert!(place.len() < synthesize(Sound::block_break(BlockType::Sand)).len());
+    }
+
+    #[test]
+    fn test_footsteps() {
+        use crate::audio::{Footsteps, Sound, SoundMaterial};
+
+        // Steps follow distance walked, so running steps more often than walking
+        let count_steps = |speed: f32| {
+            let mut footsteps = Footsteps::new();
+            (0..60).filter(|_| footsteps.update(speed / 60.0, Some(BlockType::Grass), false).is_some()).count()
+        };
+        assert!(count_steps(4.3) > count_steps(1.3));
+        assert_eq!(count_steps(0.0), 0);
+
+        // The block underfoot picks the sound; sneaking muffles it; no steps in the air
+        let mut footsteps = Footsteps::new();
+        assert_eq!(footsteps.update(1.0, None, false), None);
+        let step = footsteps.update(5.0, Some(BlockType::Sand), true);
+        assert!(matches!(step, Some(Sound::Step { material: SoundMaterial::Sand, muffled: true, .. })));
+
+        // Standing on the ground, not in water
+        let mut world = World::new(1);
+        world.chunks.insert((0, 0), Chunk::new(0, 0));
+        world.set_block_at(2, 10, 2, BlockType::Stone);
+        let player = Player::new(Vec3::new(2.5, 11.0, 2.5));
+        assert_eq!(player.block_underfoot(&world), Some(BlockType::Stone));
+        world.set_block_at(2, 10, 2, BlockType::Water);
+        assert_eq!(player.block_underfoot(&world), None);
+    }
+
+    #[test]
+    fn test_soundscape() {
+        use crate::audio::{synthesize_ambience, synthesize_music, Ambience};
+        use crate::soundscape::{detect, rain_exposure, Soundscape};
+        use crate::world_gen::WATER_LEVEL;
+
+        let mut world = World::new(1);
+        world.chunks.insert((0, 0), Chunk::new(0, 0));
+        let eye = Vec3::new(8.5, 20.5, 8.5);
+        assert_eq!(detect(&world, eye), None);
+
+        // Water at the sea level all around
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_block_at(x, WATER_LEVEL as i32 - 1, z, BlockType::Water);
+            }
+        }
+        assert_eq!(detect(&world, eye), Some(Ambience::Ocean));
+
+        // Rock overhead makes it a cave; glass lets the daylight in
+        for y in 30..33 {
+            world.set_block_at(8, y, 8, BlockType::Stone);
+        }
+        assert_eq!(detect(&world, eye), Some(Ambience::Cave));
+        for y in 30..33 {
+            world.set_block_at(8, y, 8, BlockType::Glass);
+        }
+        assert_eq!(detect(&world, eye), Some(Ambience::Ocean));
+
+        // High up under the open sky the wind blows
+        assert_eq!(detect(&world, Vec3::new(8.5, 60.5, 2.5)), Some(Ambience::Wind));
+
+        // Levels cross-fade over a few seconds and duck under music
+        let mut soundscape = Soundscape::new(7);
+        let level = |s: &Soundscape, a: Ambience| s.levels().find(|(kind, _)| *kind == a).unwrap().1;
+        soundscape.update(1.0, Some(Ambience::Ocean), 0.0, false);
+        assert!(level(&soundscape, Ambience::Ocean) > 0.0 && level(&soundscape, Ambience::Ocean) < 1.0);
+        soundscape.update(10.0, Some(Ambience::Ocean), 0.0, false);
+        assert_eq!(level(&soundscape, Ambience::Ocean), 1.0);
+        soundscape.update(1.0, Some(Ambience::Cave), 0.0, true);
+        assert!(level(&soundscape, Ambience::Ocean) < 1.0 && level(&soundscape, Ambience::Cave) > 0.0);
+        soundscape.update(10.0, Some(Ambience::Cave), 0.0, true);
+        assert_eq!(level(&soundscape, Ambience::Ocean), 0.0);
+        assert_eq!(level(&soundscape, Ambience::Cave), 0.5);
+
+        // Rain plays on top, muffled under a roof and unheard in caves
+        soundscape.update(10.0, Some(Ambience::Cave), 1.0, false);
+        assert_eq!(level(&soundscape, Ambience::Rain), 1.0);
+        assert_eq!(level(&soundscape, Ambience::Cave), 1.0);
+        assert_eq!(rain_exposure(&world, Vec3::new(2.5, 20.5, 2.5)), 1.0);
+        world.set_block_at(8, 32, 8, BlockType::Stone);
+        assert!(rain_exposure(&world, eye) > 0.0 && rain_exposure(&world, eye) < 1.0);
+        world.set_block_at(8, 31, 8, BlockType::Stone);
+        world.set_block_at(8, 30, 8, BlockType::Stone);
+        assert_eq!(rain_exposure(&world, eye), 0.0);
+        soundscape.update(10.0, Some(Ambience::Cave), 0.0, false);
+
+        // Music comes after a pause of minutes, never while a track is playing
+        assert_eq!(soundscape.update(600.0, None, 0.0, true), None);
+        assert_eq!(soundscape.update(10.0, None, 0.0, false), None);
+        assert_eq!(soundscape.update(600.0, None, 0.0, false), Some(1));
+
+        for ambience in Ambience::ALL {
+            let samples = synthesize_ambience(ambience);
+            assert!(samples.iter().all(|s| s.abs() <= 1.0));
+            // The loop wraps around without a jump
+            assert!((samples[0] - samples[samples.len() - 1]).abs() < 0.05, "{:?}", ambience);
+        }
+        assert_ne!(synthesize_music(1), synthesize_music(2));
+        assert!(synthesize_music(1).iter().all(|s| s.abs() <= 1.0));
+    }
+
+    #[test]
+    fn test_toasts() {
+        use crate::toast::Toasts;
+        use crate::ui::UiRenderer;
+
+        let mut toasts = Toasts::new();
+        assert!(!toasts.update(0.1), "Nothing to show");
+        toasts.push("Inventory full");
+        toasts.push("Inventory full");
+        toasts.push("You died!");
+        assert!(toasts.update(0.1), "New toasts need drawing");
+        let visible: Vec<_> = toasts.visible().collect();
+        assert_eq!(visible, vec![("You died!", 1.0), ("Inventory full", 1.0)], "Repeats don't stack");
+
+        // Steady until the fade-out, then gone after three seconds
+        assert!(!toasts.update(1.0));
+        assert!(toasts.update(1.6));
+        assert!(toasts.visible().all(|(_, alpha)| alpha < 1.0));
+        let mut ui = UiRenderer::new();
+        ui.build_toasts(&toasts);
+        assert!(!ui.get_toast_buffers().0.is_empty());
+        assert!(toasts.update(0.5));
+        assert_eq!(toasts.visible().count(), 0);
+        ui.build_toasts(&toasts);
+        assert!(ui.get_toast_buffers().0.is_empty());
+    }
+
+    #[test]
+    fn test_screen_to_ndc() {
+        use crate::input::screen_to_ndc;
+        let size = winit::dpi::PhysicalSize::new(800, 600);
+
+        assert_eq!(screen_to_ndc((0.0, 0.0), size), (-1.0, 1.0), "Top-left maps to (-1, 1)");
+        assert_eq!(screen_to_ndc((800.0, 600.0), size), (1.0, -1.0), "Bottom-right maps to (1, -1)");
+        assert_eq!(screen_to_ndc((400.0, 300.0), size), (0.0, 0.0), "Center maps to origin");
+    }
+
+    #[test]
+    fn test_inventory_panel_hit_test() {
+        use crate::ui::UiRenderer;
+
+        let mut ui = UiRenderer::new();
+        assert!(!ui.inventory_panel_contains(0.0, 0.0), "Closed inventory has no hit area");
+
+        ui.toggle_inventory();
+        assert!(ui.inventory_panel_contains(0.0, 0.0), "Center of screen is on the panel");
+        assert!(!ui.inventory_panel_contains(150.0, 100.0), "Corner of screen is outside the panel");
+    }
+
+    #[test]
+    fn test_inventory_mouse_clicks() {
+        use crate::inventory::{Inventory, ItemStack, SlotId};
+
+        let mut inv = Inventory::new();
+        inv.toolbar[0] = Some(ItemStack::new(BlockType::Dirt, 10));
+        inv.toolbar[1] = Some(ItemStack::new(BlockType::Dirt, 60));
+        inv.storage[0] = Some(ItemStack::new(BlockType::Stone, 5));
+
+        // Pick up, then merge into a matching stack with the overflow staying held
+        inv.click_slot(SlotId::Toolbar(0));
+        assert_eq!(inv.held, Some(ItemStack::new(BlockType::Dirt, 10)));
+        assert!(inv.toolbar[0].is_none());
+        inv.click_slot(SlotId::Toolbar(1));
+        assert_eq!(inv.toolbar[1].unwrap().count, 64);
+        assert_eq!(inv.held.unwrap().count, 6);
+
+        // Clicking a different block type swaps
+        inv.click_slot(SlotId::Storage(0));
+        assert_eq!(inv.storage[0], Some(ItemStack::new(BlockType::Dirt, 6)));
+        assert_eq!(inv.held, Some(ItemStack::new(BlockType::Stone, 5)));
+
+        // Right click places one, then right click on a stack picks up half
+        inv.right_click_slot(SlotId::Storage(1));
+        assert_eq!(inv.storage[1], Some(ItemStack::new(BlockType::Stone, 1)));
+        assert_eq!(inv.held.unwrap().count, 4);
+        inv.click_slot(SlotId::Toolbar(0));
+        assert!(inv.held.is_none());
+        inv.right_click_slot(SlotId::Toolbar(1));
+        assert_eq!(inv.held.unwrap().count, 32);
+        assert_eq!(inv.toolbar[1].unwrap().count, 32);
+
+        // Closing the screen puts the held stack back
+        assert_eq!(inv.return_held(), None);
+        assert!(inv.held.is_none());
+        assert_eq!(inv.count_item(BlockType::Dirt), 70);
+
+        // What doesn't fit comes back, to be dropped rather than lost
+        let mut full = Inventory::new();
+        while full.add_item(BlockType::Stone, 64) {}
+        full.held = Some(ItemStack::new(BlockType::Dirt, 5));
+        assert_eq!(full.return_held(), Some(ItemStack::new(BlockType::Dirt, 5)));
+        assert!(full.held.is_none());
+    }
+
+    #[test]
+    fn test_inventory_quick_move() {
+        use crate::inventory::{Inventory, ItemStack, SlotId};
+
+        let mut inv = Inventory::new();
+        inv.toolbar[0] = Some(ItemStack::new(BlockType::Sand, 20));
+        inv.storage[3] = Some(ItemStack::new(BlockType::Sand, 50));
+
+        // Fills the matching storage stack first, remainder goes to the first empty slot
+        inv.quick_move(SlotId::Toolbar(0));
+        assert!(inv.toolbar[0].is_none());
+        assert_eq!(inv.storage[3].unwrap().count, 64);
+        assert_eq!(inv.storage[0], Some(ItemStack::new(BlockType::Sand, 6)));
+
+        inv.quick_move(SlotId::Storage(0));
+        assert_eq!(inv.toolbar[0], Some(ItemStack::new(BlockType::Sand, 6)));
+        assert!(inv.storage[0].is_none());
+    }
+
+    #[test]
+    fn test_inventory_move_item() {
+        use crate::inventory::{Inventory, ItemStack, SlotId};
+
+        // Every pair of the player's slots, including a slot with itself, swaps
+        let ids: Vec<SlotId> = (0..9)
+            .map(SlotId::Toolbar)
+            .chain((0..27).map(SlotId::Storage))
+            .chain([SlotId::Offhand])
+            .collect();
+        for &from in &ids {
+            for &to in &ids {
+                let mut inv = Inventory::new();
+                inv.put_slot(from, Some(ItemStack::new(BlockType::Sand, 3)));
+                if from != to {
+                    inv.put_slot(to, Some(ItemStack::new(BlockType::Stone, 7)));
+                }
+                assert!(inv.move_item(from, to), "{:?} -> {:?}", from, to);
+                assert_eq!(inv.slot(to), Some(&Some(ItemStack::new(BlockType::Sand, 3))), "{:?} -> {:?}", from, to);
+                if from != to {
+                    assert_eq!(inv.slot(from), Some(&Some(ItemStack::new(BlockType::Stone, 7))), "{:?} -> {:?}", from, to);
+                }
+                assert_eq!(inv.count_item(BlockType::Sand), 3);
+            }
+        }
+
+        // Slots out of range and container slots aren't the inventory's; nothing moves
+        let mut inv = Inventory::new();
+        inv.toolbar[0] = Some(ItemStack::new(BlockType::Dirt, 1));
+        for bad in [SlotId::Toolbar(9), SlotId::Storage(27), SlotId::Container(0)] {
+            assert_eq!(inv.slot(bad), None);
+            assert!(!inv.move_item(SlotId::Toolbar(0), bad));
+            assert!(!inv.move_item(bad, SlotId::Toolbar(0)));
+            assert_eq!(inv.take_slot(bad), None);
+        }
+        assert_eq!(inv.toolbar[0], Some(ItemStack::new(BlockType::Dirt, 1)));
+
+        // Shift-click from the off hand goes to the toolbar
+        inv.offhand = Some(ItemStack::new(BlockType::Dirt, 5));
+        inv.quick_move(SlotId::Offhand);
+        assert!(inv.offhand.is_none());
+        assert_eq!(inv.toolbar[0], Some(ItemStack::new(BlockType::Dirt, 6)));
+    }
+
+    #[test]
+    fn test_inventory_compact() {
+        use crate::inventory::{Inventory, ItemStack};
+        use crate::item::Item;
+
+        let mut inv = Inventory::new();
+        inv.toolbar[0] = Some(ItemStack::new(BlockType::Sand, 5));
+        inv.storage[3] = Some(ItemStack::new(BlockType::Sand, 40));
+        inv.storage[7] = Some(ItemStack::new(Item::Apple, 2));
+        inv.storage[10] = Some(ItemStack::new(BlockType::Dirt, 10));
+        inv.storage[12] = Some(ItemStack::new(BlockType::Sand, 40));
+        inv.storage[20] = Some(ItemStack::new(Item::WoodenAxe, 1));
+        inv.storage[26] = Some(ItemStack::new(Item::WoodenAxe, 1));
+        inv.compact();
+
+        // Partial stacks merge, ordered by id with the fuller stack first
+        assert_eq!(inv.storage[0], Some(ItemStack::new(BlockType::Dirt, 10)));
+        assert_eq!(inv.storage[1], Some(ItemStack::new(BlockType::Sand, 64)));
+        assert_eq!(inv.storage[2], Some(ItemStack::new(BlockType::Sand, 16)));
+        // Tools don't stack
+        assert_eq!(inv.storage[3], Some(ItemStack::new(Item::WoodenAxe, 1)));
+        assert_eq!(inv.storage[4], Some(ItemStack::new(Item::WoodenAxe, 1)));
+        assert_eq!(inv.storage[5], Some(ItemStack::new(Item::Apple, 2)));
+        assert!(inv.storage[6..].iter().all(Option::is_none));
+        // The toolbar stays as it was
+        assert_eq!(inv.toolbar[0], Some(ItemStack::new(BlockType::Sand, 5)));
+
+        // Nothing is lost, and sorting again changes nothing
+        let sorted = inv.storage;
+        inv.compact();
+        assert_eq!(inv.storage, sorted);
+        assert_eq!(inv.count_item(BlockType::Sand), 85);
+    }
+
+    #[test]
+    fn test_offhand() {
+        use crate::inventory::{Hand, Inventory, InventoryWithoutOffhand, ItemStack};
+        use crate::item::Item;
+
+        let mut inv = Inventory::new();
+        inv.toolbar[0] = Some(ItemStack::new(BlockType::GlowingFungus, 3));
+        assert_eq!(inv.active_hand(), Hand::Main);
+        inv.swap_offhand();
+        assert!(inv.toolbar[0].is_none());
+        assert_eq!(inv.offhand, Some(ItemStack::new(BlockType::GlowingFungus, 3)));
+
+        // An empty main hand uses the off hand
+        assert_eq!(inv.active_hand(), Hand::Off);
+        assert_eq!(inv.hand_item(Hand::Off), Some(Item::Block(BlockType::GlowingFungus)));
+        assert!(inv.remove_hand_item(Hand::Off, 2));
+        assert_eq!(inv.count_item(BlockType::GlowingFungus), 1);
+        inv.toolbar[0] = Some(ItemStack::new(BlockType::Stone, 1));
+        assert_eq!(inv.active_hand(), Hand::Main);
+
+        // Trading the last item keeps it in the off hand
+        inv.offhand = Some(ItemStack::new(Item::Bucket, 1));
+        assert!(inv.replace_hand_item(Hand::Off, Item::WaterBucket));
+        assert_eq!(inv.offhand, Some(ItemStack::new(Item::WaterBucket, 1)));
+        assert!(inv.remove_hand_item(Hand::Off, 1));
+        assert!(inv.offhand.is_none());
+
+        // Inventories saved before the off hand still load
+        let toolbar = inv.toolbar;
+        let data = bincode::serialize(&(toolbar, [None::<ItemStack>; 27], 4usize)).unwrap();
+        let old: Inventory = bincode::deserialize::<InventoryWithoutOffhand>(&data).unwrap().into();
+        assert_eq!(old.toolbar, toolbar);
+        assert_eq!(old.selected_slot, 4);
+        assert!(old.offhand.is_none());
+    }
+
+    #[test]
+    fn test_inventory_slot_at() {
+        use crate::block_entity::ContainerKind;
+        use crate::ui::{screen_slot_at, ScreenSection};
+
+        assert_eq!(screen_slot_at(None, 150.0, 100.0), None);
+        // Every slot is reachable and distinct, with and without a container or the catalog
+        // above, and the off hand beside the panel
+        let sections = [
+            (None, 37),
+            (Some(ScreenSection::Container(ContainerKind::Chest)), 64),
+            (Some(ScreenSection::Container(ContainerKind::Furnace)), 40),
+            (Some(ScreenSection::Container(ContainerKind::EnchantingTable)), 40),
+            (Some(ScreenSection::Catalog), 64),
+            (Some(ScreenSection::Trades(3)), 40),
+        ];
+        for (section, expected) in sections {
+            let mut seen = std::collections::HashSet::new();
+            for y in (-90..90).map(|i| i as f32) {
+                for x in (-110..90).map(|i| i as f32) {
+                    if let Some(slot) = screen_slot_at(section, x, y) {
+                        seen.insert(slot);
+                    }
+                }
+            }
+            assert_eq!(seen.len(), expected);
+        }
+    }
+
+    #[test]
+    fn test_creative_catalog() {
+        use crate::catalog::{catalog_items, click_catalog, Catalog, CATALOG_COLUMNS, CATALOG_ROWS};
+        use crate::inventory::{Inventory, ItemStack, SlotClick};
+        use crate::item::Item;
+
+        // Everything placeable and every item, without air or liquids
+        let all: Vec<Item> = catalog_items().collect();
+        assert!(all.contains(&Item::Block(BlockType::Stone)) && all.contains(&Item::Apple));
+        assert!(!all.contains(&Item::Block(BlockType::Air)) && !all.contains(&Item::Block(BlockType::Water)));
+
+        let mut catalog = Catalog::default();
+        assert_eq!(catalog.results(), all);
+        catalog.scroll_by(100);
+        assert_eq!(catalog.scroll(), catalog.rows() - CATALOG_ROWS);
+        assert_eq!(catalog.item_at(0), all.get(catalog.scroll() * CATALOG_COLUMNS).copied());
+        catalog.scroll_by(-100);
+        assert_eq!(catalog.scroll(), 0);
+
+        // Searching ignores case, matches spaces to underscores and goes back to the top
+        catalog.scroll_by(1);
+        catalog.type_text("Wooden P");
+        assert_eq!(catalog.scroll(), 0);
+        assert_eq!(catalog.results(), vec![Item::WoodenPickaxe]);
+        catalog.scroll_by(1);
+        assert_eq!(catalog.scroll(), 0);
+        catalog.backspace();
+        catalog.backspace();
+        assert_eq!(catalog.search(), "Wooden");
+        assert_eq!(catalog.results(), vec![Item::WoodenPickaxe, Item::WoodenShovel, Item::WoodenAxe]);
+        catalog.type_text("zzz");
+        assert_eq!(catalog.item_at(0), None);
+
+        // Left click takes a full stack, right click one, shift-click fills the inventory
+        let mut inv = Inventory::new();
+        click_catalog(Some(Item::Block(BlockType::Sand)), SlotClick::Left, &mut inv);
+        assert_eq!(inv.held, Some(ItemStack::new(BlockType::Sand, 64)));
+        // With a stack held, the click throws it away
+        click_catalog(Some(Item::Apple), SlotClick::Left, &mut inv);
+        assert!(inv.held.is_none());
+        click_catalog(Some(Item::WoodenAxe), SlotClick::Right, &mut inv);
+        assert_eq!(inv.held, Some(ItemStack::new(Item::WoodenAxe, 1)));
+        inv.held = None;
+        let before = inv.count_item(BlockType::Glass);
+        click_catalog(Some(Item::Block(BlockType::Glass)), SlotClick::ShiftLeft, &mut inv);
+        assert_eq!(inv.count_item(BlockType::Glass), before + 64);
+        assert!(inv.held.is_none());
+    }
+
+    #[test]
+    fn test_options_menu() {
+        use crate::config::GameConfig;
+        use crate::menu::{Menu, MenuAction, MenuScreen, MenuButton, MenuWidget, OptionId};
+
+        let mut config = GameConfig::default();
+        let mut menu = Menu::new();
+        menu.open(MenuScreen::Pause);
+
+        let center = |menu: &Menu, target: MenuWidget| {
+            let (_, (x, y, w, h)) = menu.widgets().into_iter().find(|(w, _)| *w == target).unwrap();
+            (x + w / 2.0, y + h / 2.0)
+        };
+
+        // Pause menu -> Options
+        let (x, y) = center(&menu, MenuWidget::Button(MenuButton::Options));
+        assert_eq!(menu.press(x, y, &mut config), MenuAction::Redraw);
+        assert_eq!(menu.screen(), Some(MenuScreen::Options));
+
+        // Toggles flip immediately and ask for a save
+        let (x, y) = center(&menu, MenuWidget::Option(OptionId::Fog));
+        assert_eq!(menu.press(x, y, &mut config), MenuAction::ConfigChanged { save: true });
+        assert!(!config.fog);
+
+        // Dragging a slider updates live, snapping to whole chunks; release saves
+        let (_, (left_edge, y, width, _)) =
+            menu.widgets().into_iter().find(|(w, _)| *w == MenuWidget::Option(OptionId::ViewDistance)).unwrap();
+        assert_eq!(menu.press(left_edge, y + 1.0, &mut config), MenuAction::ConfigChanged { save: false });
+        assert_eq!(config.view_distance, 2);
+        menu.drag(left_edge + width, &mut config);
+        assert_eq!(config.view_distance, 16);
+        assert_eq!(menu.release(), MenuAction::ConfigChanged { save: true });
+        assert_eq!(menu.drag(0.0, &mut config), MenuAction::None);
+
+        OptionId::Fov.set_value(&mut config, 500.0);
+        assert_eq!(config.fov, 110.0);
+        OptionId::Volume.set_value(&mut config, 0.42);
+        assert_eq!(OptionId::Volume.label(&config), "Volume: 40%");
+
+        // Escape walks back out
+        assert_eq!(menu.back(), MenuAction::Redraw);
+        assert_eq!(menu.back(), MenuAction::Resume);
+        assert_eq!(menu.screen(), None);
+
+        // Options opened from the title screen returns there; the title screen stays
+        menu.open(MenuScreen::Main);
+        let (x, y) = center(&menu, MenuWidget::Button(MenuButton::Options));
+        menu.press(x, y, &mut config);
+        assert_eq!(menu.back(), MenuAction::Redraw);
+        assert_eq!(menu.screen(), Some(MenuScreen::Main));
+        assert_eq!(menu.back(), MenuAction::None);
+        let (x, y) = center(&menu, MenuWidget::Button(MenuButton::Play));
+        assert_eq!(menu.press(x, y, &mut config), MenuAction::Play);
+
+        menu.open(MenuScreen::Death);
+        let (x, y) = center(&menu, MenuWidget::Button(MenuButton::Respawn));
+        assert_eq!(menu.press(x, y, &mut config), MenuAction::Respawn);
+        assert_eq!(menu.back(), MenuAction::None);
+
+        // Escape leaves the bed too
+        menu.open(MenuScreen::Sleeping);
+        let (x, y) = center(&menu, MenuWidget::Button(MenuButton::LeaveBed));
+        assert_eq!(menu.press(x, y, &mut config), MenuAction::LeaveBed);
+        assert_eq!(menu.back(), MenuAction::LeaveBed);
+    }
+
+    #[test]
+    fn test_player_stats() {
+        use crate::config::GameConfig;
+        use crate::events::{EventListener, GameEvent};
+        use crate::menu::{Menu, MenuAction, MenuButton, MenuScreen, MenuWidget};
+        use crate::player_stats::PlayerStats;
+
+        let mut stats = PlayerStats::default();
+        for block in [BlockType::Stone, BlockType::Stone, BlockType::Dirt] {
+            stats.on_event(&GameEvent::BlockBroken { pos: (0, 0, 0), block });
+        }
+        stats.on_event(&GameEvent::BlockPlaced { pos: (0, 0, 0), block: BlockType::Planks });
+        stats.on_event(&GameEvent::ChunkLoaded { x: 0, z: 0 });
+        stats.play_time = 3725.5;
+        stats.jumps = 2;
+        let lines = stats.lines();
+        assert_eq!(lines[0], "Play time: 1h 02m 05s");
+        assert_eq!(lines[4], "Blocks mined: 3 (stone 2, dirt 1)");
+        assert_eq!(lines[5], "Blocks placed: 1 (planks 1)");
+
+        // Kept across runs; a missing or broken file starts afresh
+        let storage = MemoryStorage::new();
+        let path = std::path::Path::new(crate::player_stats::STATS_FILE);
+        stats.save(&storage, path).unwrap();
+        assert_eq!(PlayerStats::load(&storage, path), stats);
+        storage.write(path, b"not json").unwrap();
+        assert_eq!(PlayerStats::load(&storage, path), PlayerStats::default());
+
+        // The pause menu opens the Statistics screen; Done and Escape go back
+        let mut config = GameConfig::default();
+        let mut menu = Menu::new();
+        menu.open(MenuScreen::Pause);
+        let center = |menu: &Menu, target: MenuWidget| {
+            let (_, (x, y, w, h)) = menu.widgets().into_iter().find(|(w, _)| *w == target).unwrap();
+            (x + w / 2.0, y + h / 2.0)
+        };
+        let (x, y) = center(&menu, MenuWidget::Button(MenuButton::Statistics));
+        assert_eq!(menu.press(x, y, &mut config), MenuAction::Redraw);
+        assert_eq!(menu.screen(), Some(MenuScreen::Statistics));
+        let (x, y) = center(&menu, MenuWidget::Done);
+        assert_eq!(menu.press(x, y, &mut config), MenuAction::Redraw);
+        assert_eq!(menu.screen(), Some(MenuScreen::Pause));
+        menu.open(MenuScreen::Statistics);
+        assert_eq!(menu.back(), MenuAction::Redraw);
+        assert_eq!(menu.screen(), Some(MenuScreen::Pause));
+    }
+
+    #[test]
+    fn test_display_settings() {
+        use crate::config::GameConfig;
+        use crate::display::{pick_mode, pick_monitor};
+
+        let names = [Some("DP-1".to_string()), None, Some("HDMI-1".to_string())];
+        assert_eq!(pick_monitor(&names, "HDMI-1"), Some(2));
+        assert_eq!(pick_monitor(&names, "VGA-1"), None);
+
+        // The fastest mode of the asked size wins
+        let modes = [([1920, 1080], 60_000), ([1280, 720], 60_000), ([1920, 1080], 144_000), ([1920, 1080], 75_000)];
+        assert_eq!(pick_mode(&modes, [1920, 1080]), Some(2));
+        assert_eq!(pick_mode(&modes, [1280, 720]), Some(1));
+        assert_eq!(pick_mode(&modes, [2560, 1440]), None);
+
+        let config: GameConfig =
+            serde_json::from_str(r#"{"fullscreen": true, "monitor": "HDMI-1", "resolution": [1920, 1080]}"#).unwrap();
+        assert!(config.fullscreen);
+        assert_eq!(config.monitor.as_deref(), Some("HDMI-1"));
+        assert_eq!(config.resolution, Some([1920, 1080]));
+        let config: GameConfig = serde_json::from_str("{}").unwrap();
+        assert!(!config.fullscreen);
+        assert_eq!((config.monitor, config.resolution), (None, None));
+    }
+
+    #[test]
+    fn test_game_state_stack() {
+        use crate::game_state::{GameState, StateStack};
+
+        let mut states = StateStack::new(GameState::MainMenu);
+        assert!(!GameState::MainMenu.shows_world());
+        assert_eq!(states.replace(GameState::Loading), GameState::MainMenu);
+        states.replace(GameState::Playing);
+        assert!(!states.current().gui_mode());
+
+        // Overlays return to the state they were opened from
+        states.push(GameState::Inventory);
+        assert!(states.current().simulates_player());
+        states.push(GameState::Paused);
+        assert!(!states.current().simulates_world());
+        assert_eq!(states.pop(), Some(GameState::Paused));
+        assert_eq!(states.pop(), Some(GameState::Inventory));
+        assert_eq!(states.current(), GameState::Playing);
+
+        // The world keeps running on the death screen, the player doesn't
+        states.push(GameState::Dead);
+        assert!(GameState::Dead.simulates_world() && !GameState::Dead.simulates_player());
+        states.pop();
+
+        // The bottom state is never popped
+        assert_eq!(states.pop(), None);
+        assert_eq!(states.current(), GameState::Playing);
+    }
+
+    #[test]
+    fn test_survival_stats() {
+        use crate::survival::{SurvivalStats, MAX_AIR, MAX_HEALTH, MAX_HUNGER};
+
+        let mut stats = SurvivalStats::new();
+
+        // 15 seconds underwater uses up all the air, then drowning hurts every second
+        for _ in 0..150 {
+            stats.update(0.1, 0.0, true);
+        }
+        assert_eq!(stats.air, 0);
+        assert_eq!(stats.health, MAX_HEALTH);
+        for _ in 0..10 {
+            stats.update(0.1, 0.0, true);
+        }
+        assert_eq!(stats.health, MAX_HEALTH - 2);
+
+        // Surfacing refills air and a full hunger bar regenerates health
+        for _ in 0..80 {
+            stats.update(0.1, 0.0, false);
+        }
+        assert_eq!(stats.air, MAX_AIR);
+        assert!(stats.health > MAX_HEALTH - 2);
+
+        // Walking 400 blocks costs one hunger point
+        let mut stats = SurvivalStats::new();
+        stats.update(0.0, 400.0, false);
+        assert_eq!(stats.hunger, MAX_HUNGER - 1);
+    }
+
+    #[test]
+    fn test_hud_hidden_in_creative() {
+        use crate::experience::Experience;
+        use crate::physics::GameMode;
+        use crate::survival::SurvivalStats;
+        use crate::ui::UiRenderer;
+
+        let mut ui = UiRenderer::new();
+        let mut stats = SurvivalStats::new();
+        let experience = Experience::new();
+        ui.build_hud(&stats, &experience, GameMode::Survival);
+        let full = ui.get_hud_buffers().0.len();
+        assert!(full > 0);
+
+        // Air bubbles appear while underwater
+        stats.air = 150;
+        ui.build_hud(&stats, &experience, GameMode::Survival);
+        let underwater = ui.get_hud_buffers().0.len();
+        assert!(underwater > full);
+
+        // The experience level shows beside the toolbar once there is one
+        ui.build_hud(&stats, &Experience { level: 2, points: 0 }, GameMode::Survival);
+        assert!(ui.get_hud_buffers().0.len() > underwater);
+
+        ui.build_hud(&stats, &experience, GameMode::Creative);
+        assert!(ui.get_hud_buffers().0.is_empty());
+    }
+
+    #[test]
+    fn test_block_thumbnails() {
+        use crate::inventory::Inventory;
+        use crate::resource_pack::{build_atlas, ResourcePack};
+        use crate::ui::{UiIconMesh, UiRenderer};
+
+        let atlas = build_atlas(&[&ResourcePack::builtin()]).layout;
+        let mut icons = UiIconMesh::default();
+        icons.add_block(&atlas, BlockType::Air, 0.0, 0.0, 0.1, 0.1);
+        assert!(icons.vertices.is_empty(), "Air has no thumbnail");
+
+        // Three visible faces, all sampling the block's atlas tile
+        icons.add_block(&atlas, BlockType::Stone, 0.0, 0.0, 0.1, 0.1);
+        assert_eq!(icons.vertices.len(), 12);
+        assert_eq!(icons.indices.len(), 18);
+        let [u0, v0, u1, v1] = atlas.tile_uv(BlockType::Stone).unwrap();
+        for v in &icons.vertices {
+            assert!((u0..=u1).contains(&v.tex_coords[0]) && (v0..=v1).contains(&v.tex_coords[1]));
+            assert!((0.0..=0.1).contains(&v.position[0]) && (0.0..=0.1).contains(&v.position[1]));
+        }
+
+        // One cube per block in the toolbar; the pickaxe is a flat icon
+        let mut ui = UiRenderer::new();
+        ui.set_atlas(atlas);
+        ui.build_toolbar(&Inventory::with_starter_items());
+        assert_eq!(ui.get_toolbar_icon_buffers().0.len(), 8 * 12);
+    }
+
+    #[test]
+    fn test_item_ids() {
+        use crate::inventory::ItemStack;
+
+        // Stacks saved when items were block types load as the same items
+        let old = |id: u32, count: u32| [id.to_le_bytes(), count.to_le_bytes()].concat();
+        let stack: ItemStack = bincode::deserialize(&old(14, 1)).unwrap();
+        assert_eq!(stack, ItemStack::new(Item::Compass, 1));
+        let stack: ItemStack = bincode::deserialize(&old(18, 1)).unwrap();
+        assert_eq!(stack, ItemStack::new(Item::FlintAndSteel, 1));
+        let stack: ItemStack = bincode::deserialize(&old(9, 64)).unwrap();
+        assert_eq!(stack, ItemStack::new(BlockType::Stone, 64));
+        assert!(bincode::deserialize::<ItemStack>(&old(1000, 1)).is_err());
+
+        // Blocks after the old items keep their numbers in chunks
+        assert_eq!(bincode::serialize(&BlockType::Fire).unwrap(), 17u32.to_le_bytes());
+        assert_eq!(bincode::deserialize::<BlockType>(&22u32.to_le_bytes()).unwrap(), BlockType::GlowingFungus);
+        assert!(bincode::deserialize::<BlockType>(&14u32.to_le_bytes()).is_err());
+
+        // Every item and block round-trips, by name too
+        let items = BlockType::ALL.into_iter().map(Item::Block).chain(Item::ITEMS);
+        for item in items {
+            let bytes = bincode::serialize(&ItemStack::new(item, 1)).unwrap();
+            assert_eq!(bincode::deserialize::<ItemStack>(&bytes).unwrap().item, item);
+            assert_eq!(Item::from_name(item.name()), Some(item));
+        }
+        assert_eq!(Item::from_name("STICK"), Some(Item::Stick));
+        assert_eq!(Item::Stick.max_stack_size(), 64);
+        assert_eq!(crate::block_entity::fuel_time(Item::Stick), Some(5.0));
+    }
+
+    #[test]
+    fn test_chunk_loader_generates_area() {
+        use crate::chunk_loader::{area, area_progress, chunk_at, load_priority, ring, ChunkLoader, FrameBudget};
+        use glam::Vec2;
+        use std::time::{Duration, Instant};
+
+        let generator = WorldGenerator::new(42);
+        let mut world = World::new(42);
+        world.load_or_generate_chunk(0, 0, &generator);
+        assert!(area_progress(&world, (0, 0), 1) > 0.0);
+
+        // A frame stops at the chunk count or, past the first chunk, at the time limit
+        let start = Instant::now();
+        let timed = FrameBudget { chunks: 4, time: Duration::ZERO };
+        assert!(!timed.spent(0, start) && timed.spent(1, start));
+        let counted = FrameBudget { chunks: 2, time: Duration::MAX };
+        assert!(!counted.spent(1, start) && counted.spent(2, start));
+
+        let mut loader = ChunkLoader::new(&generator);
+        loader.request_area(&world, Vec2::ZERO, Vec2::X, 1);
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while area_progress(&world, (0, 0), 1) < 1.0 {
+            assert!(Instant::now() < deadline, "chunk generation timed out");
+            assert!(loader.integrate(&mut world, &generator, FrameBudget { chunks: 1, time: Duration::MAX }) <= 1);
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(world.chunks.len(), 9);
+
+        // Background terrain matches synchronous generation
+        let mut reference = World::new(42);
+        reference.load_or_generate_chunk(1, -1, &generator);
+        let loaded = world.get_chunk(1, -1).unwrap();
+        assert_eq!(loaded.get_block(3, 10, 3), reference.get_chunk(1, -1).unwrap().get_block(3, 10, 3));
+
+        // Chunks in view come before those behind, but not before much nearer ones
+        let position = Vec2::new(8.0, 8.0);
+        assert_eq!(chunk_at(position), (0, 0));
+        assert_eq!(chunk_at(Vec2::new(-0.5, 16.0)), (-1, 1));
+        let ahead = load_priority((2, 0), position, Vec2::X);
+        let behind = load_priority((-2, 0), position, Vec2::X);
+        assert!(ahead < behind);
+        assert!(load_priority((-1, 0), position, Vec2::X) < load_priority((4, 0), position, Vec2::X));
+        assert_eq!(load_priority((2, 0), position, Vec2::ZERO), load_priority((-2, 0), position, Vec2::ZERO));
+
+        // Rings outwards from the center cover the area once each
+        assert_eq!(ring((3, 4), 0).collect::<Vec<_>>(), vec![(3, 4)]);
+        assert_eq!(ring((3, 4), 2).count(), 16);
+        let mut rings: Vec<(i32, i32)> = (0..=2).flat_map(|distance| ring((3, 4), distance)).collect();
+        let mut square: Vec<(i32, i32)> = area((3, 4), 2).collect();
+        rings.sort();
+        square.sort();
+        assert_eq!(rings, square);
+    }
+
+    #[test]
+    fn test_loading_screen() {
+        use crate::ui::UiRenderer;
+
+        let mut ui = UiRenderer::new();
+        ui.build_loading_screen(Some(0.5));
+        assert!(!ui.get_loading_buffers().0.is_empty());
+        ui.build_loading_screen(None);
+        assert!(ui.get_loading_buffers().0.is_empty());
+    }
+
+    #[test]
+    fn test_compass() {
+        use crate::compass::{cardinal, heading, needle_angle};
+        use crate::debug::DebugInfo;
+        use crate::inventory::Inventory;
+        use crate::ui::UiRenderer;
+        use std::f32::consts::{FRAC_PI_2, PI};
+
+        // Yaw 0 looks along +X (east), -90 degrees along -Z (north)
+        assert!((heading(0.0) - 90.0).abs() < 1e-3);
+        assert!(heading(-FRAC_PI_2) < 1e-3 || heading(-FRAC_PI_2) > 360.0 - 1e-3);
+        assert_eq!(cardinal(heading(-FRAC_PI_2)), ("north", "-Z"));
+        assert_eq!(cardinal(heading(FRAC_PI_2)), ("south", "+Z"));
+        assert_eq!(cardinal(heading(PI + 0.3)), ("west", "-X"));
+        assert_eq!(cardinal(heading(10.0 * PI)), ("east", "+X"));
+
+        // The needle points ahead, to the right or behind, relative to the view
+        let target = Vec3::new(10.0, 70.0, 0.0);
+        assert!(needle_angle(Vec3::ZERO, 0.0, target).abs() < 1e-4);
+        assert!((needle_angle(Vec3::ZERO, -FRAC_PI_2, target) - FRAC_PI_2).abs() < 1e-4, "East is right when facing north");
+        assert!((needle_angle(Vec3::ZERO, PI, target).abs() - PI).abs() < 1e-4);
+        assert_eq!(needle_angle(target, 1.0, target), 0.0);
+
+        // The F3 overlay names the direction
+        let mut info = DebugInfo::new();
+        info.yaw = -FRAC_PI_2;
+        assert!(info.format_display().iter().any(|line| line.starts_with("Facing: north (towards -Z)")));
+
+        // A compass is held, not placed, and draws a dial while held
+        assert!(Item::Compass.block().is_none() && Item::Block(BlockType::Stone).block().is_some());
+        assert_eq!(Inventory::with_starter_items().count_item(Item::Compass), 1);
+        let mut ui = UiRenderer::new();
+        ui.build_compass(Some(0.5));
+        assert!(!ui.get_compass_buffers().0.is_empty());
+        ui.build_compass(None);
+        assert!(ui.get_compass_buffers().0.is_empty());
+    }
+
+    #[test]
+    fn test_minimap_cache() {
+        use crate::minimap::{column_color, Minimap, MinimapMode};
+        use crate::ui::UiRenderer;
+
+        let mut world = World::new(0);
+        let mut chunk = Chunk::new(0, 0);
+        chunk.set_block(2, 5, 3, BlockType::Stone);
+        chunk.set_block(2, 10, 3, BlockType::Sand);
+        let sand = column_color(&chunk, 2, 3);
+        assert!(sand[0] > sand[2], "Topmost block (sand) decides the color");
+        assert_eq!(column_color(&chunk, 0, 0), [0.0, 0.0, 0.0]);
+        world.chunks.insert((0, 0), chunk);
+
+        let mut minimap = Minimap::new();
+        let pos = Vec3::new(8.0, 20.0, 8.0);
+        assert!(minimap.update(&world, pos, 0.0, MinimapMode::NorthUp));
+        assert!(!minimap.update(&world, pos, 0.0, MinimapMode::NorthUp), "Nothing changed");
+        assert_eq!(minimap.color_at(2, 3), Some(sand));
+        assert_eq!(minimap.color_at(40, 40), None, "Unloaded chunks have no color");
+
+        // Editing a block refreshes the cached colors
+        world.set_block_at(2, 20, 3, BlockType::Water);
+        assert!(minimap.update(&world, pos, 0.0, MinimapMode::NorthUp));
+        assert_ne!(minimap.color_at(2, 3), Some(sand));
+
+        // Turning only matters in rotating mode
+        assert!(!minimap.update(&world, pos, 1.0, MinimapMode::NorthUp));
+        assert!(minimap.update(&world, pos, 1.0, MinimapMode::Rotating));
+
+        let mut ui = UiRenderer::new();
+        ui.build_minimap(&minimap, MinimapMode::Rotating, pos, 1.0);
+        assert!(!ui.get_minimap_buffers().0.is_empty());
+        ui.build_minimap(&minimap, MinimapMode::Off, pos, 1.0);
+        assert!(ui.get_minimap_buffers().0.is_empty());
+        assert_eq!(MinimapMode::Rotating.next(), MinimapMode::Off);
+    }
+
+    #[test]
+    fn test_ui_scale() {
+        use crate::ui::{UiRenderer, UiScale};
+
+        // Auto picks the largest integer scale that keeps 320x240 virtual pixels
+        assert_eq!(UiScale::auto_scale(1280, 720), 3);
+        assert_eq!(UiScale::auto_scale(1920, 1080), 4);
+        assert_eq!(UiScale::auto_scale(200, 100), 1);
+
+        let scale = UiScale::new(1280, 720, 2);
+        assert_eq!(scale.scale, 2.0);
+        assert_eq!((scale.half_width, scale.half_height), (320.0, 180.0));
+        assert_eq!(UiScale::new(1280, 720, 10).scale, 3.0, "Scale is capped so the UI fits");
+        assert_eq!(scale.ndc_to_ui((1.0, -0.5)), (320.0, -90.0));
+        assert_eq!(scale.ndc_scale(), [1.0 / 320.0, 1.0 / 180.0]);
+
+        // The toolbar keeps its size and hugs the bottom edge at any window shape
+        let toolbar_bounds = |scale: UiScale| {
+            let mut ui = UiRenderer::new();
+            ui.set_scale(scale);
+            ui.build_toolbar(&crate::inventory::Inventory::new());
+            let verts = ui.get_toolbar_buffers().0;
+            let min_x = verts.iter().map(|v| v.position[0]).fold(f32::MAX, f32::min);
+            let max_x = verts.iter().map(|v| v.position[0]).fold(f32::MIN, f32::max);
+            let min_y = verts.iter().map(|v| v.position[1]).fold(f32::MAX, f32::min);
+            (max_x - min_x, min_y + scale.half_height)
+        };
+        let wide = toolbar_bounds(UiScale::new(2560, 720, 2));
+        let tall = toolbar_bounds(UiScale::new(800, 1200, 2));
+        assert_eq!(wide, tall);
+    }
+
+    #[test]
+    fn test_console_submit_and_history() {
+        use crate::console::Console;
+
+        let mut console = Console::new();
+        console.open("/");
+        console.push_str("seed");
+        assert_eq!(console.input(), "/seed");
+
+        assert_eq!(console.submit(), Some("/seed".to_string()));
+        assert!(!console.is_open(), "Submitting closes the console");
+
+        // Empty input submits nothing
+        console.open("");
+        assert_eq!(console.submit(), None);
+
+        // Up arrow recalls the last command
+        console.open("");
+        console.history_prev();
+        assert_eq!(console.input(), "/seed");
+        console.history_next();
+        assert_eq!(console.input(), "");
+    }
+
+    #[test]
+    fn test_console_scrollback_limit() {
+        use crate::console::{Console, VISIBLE_LINES};
+
+        let mut console = Console::new();
+        for i in 0..200 {
+            console.print(format!("line {}", i));
+        }
+        let visible: Vec<&String> = console.visible_lines().collect();
+        assert_eq!(visible.len(), VISIBLE_LINES);
+        assert_eq!(visible.last().unwrap().as_str(), "line 199", "Newest line is shown last");
+    }
+
+    #[test]
+    fn test_chat_log_fades() {
+        use crate::console::{Console, CHAT_LOG_LINES};
+
+        let mut console = Console::new();
+        for i in 0..8 {
+            console.print(format!("line {}", i));
+        }
+        assert!(console.update(0.0), "New lines need the log rebuilt");
+        let log: Vec<(&str, f32)> = console.chat_log().collect();
+        assert_eq!(log.len(), CHAT_LOG_LINES);
+        assert_eq!(log.last().unwrap(), &("line 7", 1.0), "Newest line is shown last");
+
+        // Lines stay a while, then fade out and disappear
+        assert!(!console.update(5.0));
+        assert!(console.update(4.5));
+        assert!(console.chat_log().all(|(_, alpha)| alpha > 0.0 && alpha < 1.0));
+        assert!(console.update(1.0));
+        assert_eq!(console.chat_log().count(), 0);
+        assert!(!console.update(1.0));
+
+        // The scrollback keeps them for the open console
+        assert_eq!(console.visible_lines().count(), 8);
+    }
+
+    #[test]
+    fn test_resource_packs() {
+        use crate::resource_pack::{available, build_atlas, ResourcePack, TILE_SIZE};
+        use image::{Rgba, RgbaImage};
+        use std::io::Write;
+
+        let dir = std::env::temp_dir().join(format!("rustcraft_test_resourcepacks_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let png = |color: [u8; 4], size: u32| {
+            let mut bytes = std::io::Cursor::new(Vec::new());
+            RgbaImage::from_pixel(size, size, Rgba(color))
+                .write_to(&mut bytes, image::ImageOutputFormat::Png)
+                .unwrap();
+            bytes.into_inner()
+        };
+
+        // Folder pack: a 32px stone texture that the furnace reuses
+        let folder = dir.join("red");
+        std::fs::create_dir_all(folder.join("textures")).unwrap();
+        std::fs::write(folder.join("textures/stone.png"), png([255, 0, 0, 255], 32)).unwrap();
+        std::fs::write(folder.join("blocks.json"), r#"{"furnace": "stone"}"#).unwrap();
+
+        // Zip pack wrapped in a folder of its own
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(dir.join("blue.zip")).unwrap());
+        zip.start_file("blue/textures/dirt.png", zip::write::FileOptions::default()).unwrap();
+        zip.write_all(&png([0, 0, 255, 255], 16)).unwrap();
+        zip.finish().unwrap();
+
+        assert_eq!(available(&dir), vec!["blue.zip".to_string(), "red".to_string()]);
+        let builtin = ResourcePack::builtin();
+        let red = ResourcePack::load(&folder).expect("folder pack loads");
+        let blue = ResourcePack::load(&dir.join("blue.zip")).expect("zip pack loads");
+
+        let atlas = build_atlas(&[&red, &blue, &builtin]);
+        assert_eq!(atlas.image.width() % TILE_SIZE, 0);
+        let layout = &atlas.layout;
+        assert_eq!(layout.tile(BlockType::Air), None);
+        assert!(BlockType::ALL.iter().skip(1).all(|block| layout.tile(*block).is_some()));
+        assert_eq!(layout.tile(BlockType::Furnace), layout.tile(BlockType::Stone));
+        assert_ne!(layout.tile(BlockType::Dirt), layout.tile(BlockType::Stone));
+
+        let pixel = |block: BlockType| {
+            let (col, row) = layout.tile(block).unwrap();
+            atlas.image.get_pixel(col * TILE_SIZE + TILE_SIZE - 1, row * TILE_SIZE + TILE_SIZE - 1).0
+        };
+        assert_eq!(pixel(BlockType::Stone), [255, 0, 0, 255], "32px texture scaled into its tile");
+        assert_eq!(pixel(BlockType::Dirt), [0, 0, 255, 255]);
+
+        // A connected glass sheet: tile i is shaded i, and a face of a pane
+        // with glass to its right (+X on a +Z face) takes tile 2
+        let mut sheet = RgbaImage::new(4 * TILE_SIZE, 4 * TILE_SIZE);
+        for (x, y, pixel) in sheet.enumerate_pixels_mut() {
+            *pixel = Rgba([((y / TILE_SIZE) * 4 + x / TILE_SIZE) as u8, 0, 0, 255]);
+        }
+        sheet.save(folder.join("textures/glass_connected.png")).unwrap();
+        let red = ResourcePack::load(&folder).expect("folder pack loads");
+        let atlas = build_atlas(&[&red, &builtin]);
+        let layout = &atlas.layout;
+        assert!(layout.is_connected(BlockType::Glass) && !layout.is_connected(BlockType::Stone));
+        let [u0, v0, ..] = layout.connected_tile_uv(BlockType::Glass, 5).unwrap();
+        let (w, h) = atlas.image.dimensions();
+        assert_eq!(atlas.image.get_pixel((u0 * w as f32) as u32, (v0 * h as f32) as u32).0, [5, 0, 0, 255]);
+
+        let mut world = World::new(1);
+        let mut chunk = Chunk::new(0, 0);
+        chunk.set_block(4, 10, 4, BlockType::Glass);
+        chunk.set_block(5, 10, 4, BlockType::Glass);
+        world.chunks.insert((0, 0), chunk);
+        let mut mesh = MeshBuilder::new();
+        mesh.build_chunk_mesh(&ChunkSnapshot::capture(&world.chunks, 0, 0).unwrap(), layout);
+        let front = mesh.vertices.chunks(4).find(|face| face[0].position == [4.0, 10.0, 5.0]).unwrap();
+        let [u0, v0, ..] = layout.connected_tile_uv(BlockType::Glass, 2).unwrap();
+        assert_eq!(front[0].tex_coords, [u0, v0]);
+
+        // Unknown blocks in the definitions are an error, not silently dropped
+        std::fs::write(folder.join("blocks.json"), r#"{"unobtainium": "stone"}"#).unwrap();
+        assert!(ResourcePack::load(&folder).is_err());
+        assert!(ResourcePack::load(&dir.join("missing")).is_err());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_cli_args() {
+        use crate::cli::Args;
+        use clap::Parser;
+
+        let args = Args::try_parse_from(["rustcraft"]).unwrap();
+        assert_eq!(args.world_path(), "world.dat");
+        assert_eq!(args.config, "config.json");
+        assert!(!args.fullscreen && !args.headless);
+        assert_eq!(args.view_distance, None);
+
+        let storage = MemoryStorage::new();
+        let args = Args::try_parse_from([
+            "rustcraft", "--world", "cli", "--seed", "99", "--view-distance", "3", "--config", "cli.json", "--headless",
+            "--terrain", "density",
+        ])
+        .unwrap();
+        assert!(args.headless);
+        assert_eq!(args.load_config(&storage).view_distance, 3, "command line overrides config.json");
+        assert!(storage.exists(std::path::Path::new("cli.json")), "The defaults are written");
+        assert_eq!(args.open_world(&storage).seed, 99);
+        assert_eq!(args.open_world(&storage).terrain, crate::world_gen::TerrainMode::Density);
+
+        assert!(Args::try_parse_from(["rustcraft", "--view-distance", "100"]).is_err());
+        assert!(Args::try_parse_from(["rustcraft", "--seed", "abc"]).is_err());
+        assert!(Args::try_parse_from(["rustcraft", "--terrain", "flat"]).is_err());
+    }
+
+    #[test]
+    fn test_frame_profiler() {
+        use crate::profiler::{Phase, Profiler};
+        use std::time::{Duration, Instant};
+
+        let mut profiler = Profiler::new();
+        profiler.start_trace();
+        for _ in 0..3 {
+            let start = Instant::now() - Duration::from_millis(4);
+            let start = profiler.record(Phase::Meshing, start);
+            profiler.record(Phase::Present, start);
+            profiler.end_frame();
+        }
+
+        let averages = profiler.averages();
+        assert_eq!(averages.len(), Phase::ALL.len());
+        let meshing = averages.iter().find(|(phase, _)| *phase == Phase::Meshing).unwrap().1;
+        assert!(meshing >= Duration::from_millis(4));
+        let input = averages.iter().find(|(phase, _)| *phase == Phase::Input).unwrap().1;
+        assert_eq!(input, Duration::ZERO);
+        assert!(profiler.format_display().iter().any(|line| line.starts_with("  Meshing: ")));
+
+        // One complete ("X") event per recorded phase
+        let trace: serde_json::Value = serde_json::from_str(&profiler.finish_trace().unwrap()).unwrap();
+        let events = trace["traceEvents"].as_array().unwrap();
+        assert_eq!(events.len(), 6);
+        assert_eq!(events[0]["name"], "Meshing");
+        assert_eq!(events[0]["ph"], "X");
+        assert!(events[0]["dur"].as_f64().unwrap() >= 4000.0);
+        assert!(profiler.finish_trace().is_none(), "the capture ends with the export");
+    }
+
+    #[test]
+    fn test_debug_geometry() {
+        use crate::camera::Camera;
+        use crate::debug_render::{DebugLayers, DebugMesh};
+
+        let world = World::new(777);
+        let player = Player::new(Vec3::new(20.0, 50.0, -5.0));
+        let mut camera = Camera::new(1.0);
+        camera.position = player.eye_position();
+
+        let layers = DebugLayers::default();
+        assert!(!layers.any());
+        let mesh = DebugMesh::build(layers, &world, &player, &camera, &[]);
+        assert!(mesh.line_indices.is_empty() && mesh.plane_indices.is_empty());
+
+        // A box is its twelve edges
+        let mut mesh = DebugMesh::default();
+        mesh.add_box(&Aabb::new(Vec3::ZERO, Vec3::ONE), [1.0; 4]);
+        assert_eq!(mesh.line_indices.len(), 24);
+        let length: f32 = mesh
+            .line_indices
+            .chunks(2)
+            .map(|line| {
+                let a = Vec3::from(mesh.line_vertices[line[0] as usize].position);
+                let b = Vec3::from(mesh.line_vertices[line[1] as usize].position);
+                a.distance(b)
+            })
+            .sum();
+        assert!((length - 12.0).abs() < 1e-5);
+
+        // Four border planes around the chunk the camera is in (x 16..32, z -16..0)
+        let layers = DebugLayers { chunk_borders: true, hitboxes: false, structures: false };
+        let mesh = DebugMesh::build(layers, &world, &player, &camera, &[]);
+        assert_eq!(mesh.plane_indices.len(), 4 * 6);
+        for vertex in &mesh.plane_vertices {
+            let [x, _, z] = vertex.position;
+            assert!(x == 16.0 || x == 32.0, "x = {}", x);
+            assert!(z == -16.0 || z == 0.0, "z = {}", z);
+        }
+
+        // The player's box plus the targeting ray
+        let layers = DebugLayers { chunk_borders: false, hitboxes: true, structures: false };
+        let mesh = DebugMesh::build(layers, &world, &player, &camera, &[]);
+        assert!(mesh.plane_indices.is_empty());
+        assert!(mesh.line_indices.len() >= 24 + 2);
+        assert!(layers.format_display().contains("Hitboxes (F3+B): on"));
+    }
+
+    #[test]
+    fn test_log_rotation() {
+        use crate::logging::rotate;
+        use std::fs;
+
+        let dir = std::env::temp_dir().join(format!("rustcraft_logs_{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        let read = |name: &str| fs::read_to_string(dir.join(name)).ok();
+
+        for session in 0..4 {
+            rotate(&dir, 2);
+            fs::write(dir.join("latest.log"), format!("session {}", session)).unwrap();
+        }
+        assert_eq!(read("latest.log").as_deref(), Some("session 3"));
+        assert_eq!(read("latest.1.log").as_deref(), Some("session 2"));
+        assert_eq!(read("latest.2.log").as_deref(), Some("session 1"));
+        assert_eq!(read("latest.3.log"), None, "only two old logs are kept");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_graphics_backend_fallback() {
+        use crate::config::GameConfig;
+        use crate::renderer::GraphicsBackend;
+
+        // Auto covers the native APIs in one go, so only OpenGL is left
+        assert_eq!(GraphicsBackend::Auto.fallback_order(), vec![GraphicsBackend::Auto, GraphicsBackend::Gl]);
+        assert_eq!(
+            GraphicsBackend::Gl.fallback_order(),
+            vec![
+                GraphicsBackend::Gl,
+                GraphicsBackend::Vulkan,
+                GraphicsBackend::Dx12,
+                GraphicsBackend::Metal,
+            ]
+        );
+        assert_eq!(GraphicsBackend::Dx12.fallback_order()[..2], [GraphicsBackend::Dx12, GraphicsBackend::Vulkan]);
+
+        let config: GameConfig = serde_json::from_str(r#"{"graphics_backend": "vulkan"}"#).unwrap();
+        assert_eq!(config.graphics_backend, GraphicsBackend::Vulkan);
+        let config: GameConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(config.graphics_backend, GraphicsBackend::Auto);
+    }
+
+    #[test]
+    fn test_commands() {
+        use crate::commands::{execute, execute_client, ClientContext, CommandContext};
+        use crate::region::Selection;
+        use crate::free_cam::{FreeCam, DEFAULT_SPEED, MAX_SPEED, MIN_SPEED};
+        use crate::physics::GameMode;
+        use crate::time::{Weather, TIME_NIGHT};
+
+        let mut world = World::new(777);
+        let mut player = Player::new(Vec3::new(0.0, 50.0, 0.0));
+        let mut ctx = CommandContext { world: &mut world, player: &mut player, selection: &mut Selection::default() };
+
+        assert_eq!(execute("/seed", &mut ctx), Ok("Seed: 777".to_string()));
+
+        assert!(execute("/tp 10 ~5 -3", &mut ctx).is_ok());
+        assert_eq!(ctx.player.position, Vec3::new(10.0, 55.0, -3.0));
+        assert!(execute("/tp 1 2", &mut ctx).is_err(), "tp needs three coordinates");
+        // Not past the world border
+        ctx.world.border = Some(20);
+        assert!(execute("/tp 100 ~ -100", &mut ctx).is_ok());
+        assert_eq!(ctx.player.position, Vec3::new(19.7, 55.0, -19.7));
+        ctx.world.border = None;
+        assert!(execute("/tp 10 55 -3", &mut ctx).is_ok());
+
+        let before = ctx.world.inventory.count_item(BlockType::Stone);
+        assert!(execute("/give stone 10", &mut ctx).is_ok());
+        assert_eq!(ctx.world.inventory.count_item(BlockType::Stone), before + 10);
+        assert!(execute("/give unobtainium", &mut ctx).is_err());
+
+        assert!(execute("/xp 3L", &mut ctx).is_ok());
+        assert!(execute("/xp 14", &mut ctx).is_ok());
+        assert_eq!((ctx.world.experience.level, ctx.world.experience.points), (4, 1));
+        assert!(execute("/xp lots", &mut ctx).is_err());
+
+        assert!(execute("/time set night", &mut ctx).is_ok());
+        assert_eq!(ctx.world.time.time_of_day(), TIME_NIGHT);
+        assert_eq!(execute("/time query", &mut ctx), Ok(format!("Time: {} (day 0)", TIME_NIGHT)));
+        assert!(execute("/time add 24000", &mut ctx).is_ok());
+        assert_eq!((ctx.world.time.time_of_day(), ctx.world.time.day()), (TIME_NIGHT, 1));
+        assert!(execute("/time add soon", &mut ctx).is_err());
+        assert!(execute("/time add 18446744073709551615", &mut ctx).is_err());
+        assert_eq!(ctx.world.time.day(), 1);
+        // The clock stops at the end of time instead of overflowing
+        let mut end = crate::time::WorldTime::new();
+        end.add_ticks(u64::MAX);
+        end.add_ticks(u64::MAX);
+        end.tick(&crate::gamerules::GameRules::new());
+        assert_eq!(end.ticks(), u64::MAX);
+
+        assert_eq!(execute("/time timelapse", &mut ctx), Ok("Timelapse is off".to_string()));
+        assert!(execute("/time timelapse 1", &mut ctx).is_err());
+        assert!(execute("/time timelapse 100", &mut ctx).is_ok());
+        let before = ctx.world.time.ticks();
+        ctx.world.time.tick(&ctx.world.rules);
+        assert_eq!(ctx.world.time.ticks(), before + 100);
+        assert!(execute("/time timelapse off", &mut ctx).is_ok());
+        ctx.world.time.tick(&ctx.world.rules);
+        assert_eq!(ctx.world.time.ticks(), before + 101);
+
+        assert!(execute("/weather thunder", &mut ctx).is_ok());
+        assert_eq!(ctx.world.time.weather(), Weather::Thunder);
+        assert_eq!(execute("/weather", &mut ctx), Ok("Weather: thunder".to_string()));
+        assert!(execute("/weather snow", &mut ctx).is_err());
+
+        assert_eq!(execute("/setworldspawn", &mut ctx), Ok("Set the world spawn to (10, 55, -3)".to_string()));
+        assert!(execute("/setworldspawn 4 60 ~2", &mut ctx).is_ok());
+        assert_eq!(ctx.world.spawn, Some((4, 60, -1)));
+        assert_eq!(ctx.world.spawn_position(), Vec3::new(4.5, 60.0, -0.5));
+        assert!(execute("/setworldspawn 4 60", &mut ctx).is_err());
+
+        assert_eq!(execute("/worldborder", &mut ctx), Ok("There is no world border".to_string()));
+        assert!(execute("/worldborder 500", &mut ctx).is_ok());
+        assert_eq!(ctx.world.border, Some(500));
+        assert!(execute("/worldborder 5", &mut ctx).is_err());
+        assert_eq!(execute("/worldborder off", &mut ctx), Ok("Removed the world border".to_string()));
+        assert_eq!(ctx.world.border, None);
+
+        assert!(execute("/gamemode creative", &mut ctx).is_ok());
+        assert_eq!(ctx.player.game_mode, GameMode::Creative);
+
+        assert!(execute("/nonsense", &mut ctx).is_err());
+
+        // Resource packs are switched by the client without asking the server
+        assert!(execute("/resourcepack default", &mut ctx).is_err());
+        let mut resource_pack = Some("old".to_string());
+        let mut free_cam = None;
+        let eye = Vec3::new(0.0, 70.0, 0.0);
+        let mut client = ClientContext { resource_pack: &mut resource_pack, free_cam: &mut free_cam, eye, yaw: 1.0 };
+        assert!(execute_client("/resourcepack no_such_pack", &mut client).unwrap().is_err());
+        assert!(execute_client("/resourcepack default", &mut client).unwrap().is_ok());
+        assert_eq!(*client.resource_pack, None);
+        assert!(execute_client("/seed", &mut client).is_none());
+
+        // So is the spectator camera: /spectate toggles it, a speed starts it or changes its speed
+        assert!(execute_client("/spectate", &mut client).unwrap().is_ok());
+        assert_eq!(client.free_cam.as_ref().map(|cam| (cam.position, cam.body_yaw)), Some((eye, 1.0)));
+        assert!(execute_client("/spectate", &mut client).unwrap().is_ok());
+        assert!(client.free_cam.is_none());
+        assert!(execute_client("/spectate 1000", &mut client).unwrap().is_ok());
+        assert_eq!(client.free_cam.as_ref().map(|cam| cam.speed()), Some(MAX_SPEED));
+        assert!(execute_client("/spectate fast", &mut client).unwrap().is_err());
+        assert!(execute("/spectate", &mut ctx).is_err(), "The server has no camera");
+
+        // It flies through blocks along the view and speeds up with the mouse wheel
+        let mut cam = FreeCam::new(eye, 0.0);
+        cam.fly(Vec3::new(3.0, 0.0, 4.0), 1.0);
+        assert!((cam.position - (eye + Vec3::new(6.0, 0.0, 8.0))).length() < 1e-4);
+        cam.scroll_speed(1.0);
+        assert!(cam.speed() > DEFAULT_SPEED);
+        cam.scroll_speed(-100.0);
+        assert_eq!(cam.speed(), MIN_SPEED);
+    }
+
+    #[test]
+    fn test_stats_and_blockinfo() {
+        use crate::commands::{execute, with_target, CommandContext};
+        use crate::region::Selection;
+
+        let mut world = World::new(5);
+        world.chunks.insert((0, 0), Chunk::new(0, 0));
+        world.set_block_at(2, 9, 2, BlockType::Stone);
+        world.set_block_at(2, 10, 2, BlockType::Chest);
+        world.set_block_at(4, 12, 2, BlockType::Fire);
+        let mut player = Player::new(Vec3::new(2.5, 10.0, 2.5));
+        let mut ctx = CommandContext { world: &mut world, player: &mut player, selection: &mut Selection::default() };
+
+        let stats = execute("/stats", &mut ctx).unwrap();
+        assert!(stats.starts_with("Loaded chunks: 1 (1 to redraw), generated: 0"), "{}", stats);
+        assert!(stats.contains("block entities: 1; blocks changed: 3"), "{}", stats);
+        assert!(ctx.world.memory_estimate() > 16 * 16 * 64);
+
+        // Without coordinates, the block under the feet
+        let info = execute("/blockinfo", &mut ctx).unwrap();
+        assert!(info.starts_with("Block at (2, 9, 2): stone, full block; block light 9/14, under cover"), "{}", info);
+        let info = execute("/blockinfo 2 10 2", &mut ctx).unwrap();
+        assert!(info.contains("holding 0 items") && info.contains("open to the sky") && info.contains("biome "), "{}", info);
+        assert!(execute("/blockinfo 100 10 100", &mut ctx).is_err());
+        assert!(execute("/blockinfo 1 2", &mut ctx).is_err());
+
+        // The client fills in the block looked at
+        assert_eq!(with_target("/blockinfo".to_string(), Some((1, 2, 3))), "/blockinfo 1 2 3");
+        assert_eq!(with_target("/blockinfo".to_string(), None), "/blockinfo");
+        assert_eq!(with_target("/blockinfo 4 5 6".to_string(), Some((1, 2, 3))), "/blockinfo 4 5 6");
+        assert_eq!(with_target("/seed".to_string(), Some((1, 2, 3))), "/seed");
+    }
+
+    #[test]
+    fn test_local_server() {
+        use crate::cli::Args;
+        use crate::physics::GameMode;
+        use crate::protocol::{ClientMessage, ServerMessage};
+        use crate::server::Server;
+        use clap::Parser;
+
+        let args = Args::parse_from(["rustcraft", "--seed", "99"]);
+        let (mut server, _) = Server::open(&args, Arc::new(MemoryStorage::new()), 2, 2);
+        server.generate_spawn_area();
+        let client = server.connect_local("Player");
+        let spawn = server.spawn_position();
+
+        // Commands run on the server and report their effects back
+        client.send(ClientMessage::Command("/gamemode creative".to_string()));
+        client.send(ClientMessage::Command("/tp ~ 200 ~".to_string()));
+        server.update(0.0, false);
+        let messages = client.receive();
+        assert!(messages.contains(&ServerMessage::GameMode(GameMode::Creative)));
+        assert!(messages.contains(&ServerMessage::Teleport { position: [spawn.x, 200.0, spawn.z] }));
+        assert!(messages.iter().any(|m| matches!(m, ServerMessage::CommandOutput(Ok(_)))));
+
+        // Chat comes back with the sender's name; lines starting with '/' are commands
+        client.send(ClientMessage::Chat("  hello\u{7}  ".to_string()));
+        client.send(ClientMessage::Chat("/seed".to_string()));
+        server.update(0.0, false);
+        let messages = client.receive();
+        assert!(messages.contains(&ServerMessage::Chat { from: "Player".to_string(), text: "hello".to_string() }));
+        assert!(messages.iter().any(|m| matches!(m, ServerMessage::CommandOutput(Ok(output)) if output.contains("99"))));
+        assert!(!messages.iter().any(|m| matches!(m, ServerMessage::Chat { text, .. } if text.starts_with('/'))));
+
+        // A plausible move is accepted; jumping across the map is not
+        let step = [spawn.x + 0.5, 200.0, spawn.z];
+        client.send(ClientMessage::Move { position: step, velocity: [0.0; 3], on_ground: false, yaw: 0.0 });
+        server.update(0.1, false);
+        assert!(client.receive().is_empty());
+        client.send(ClientMessage::Move { position: [500.0, 200.0, 0.0], velocity: [0.0; 3], on_ground: false, yaw: 0.0 });
+        server.update(0.1, false);
+        assert_eq!(client.receive(), vec![ServerMessage::Teleport { position: step }]);
+
+        // Paused servers don't tick
+        assert_eq!(server.update(1.0, false), 0);
+        assert!(server.update(0.1, true) > 0);
+        assert!(client.receive().contains(&ServerMessage::Tick));
+
+        client.send(ClientMessage::Save);
+        server.update(0.0, false);
+        assert_eq!(client.receive(), vec![ServerMessage::Saved(Ok(()))]);
+
+        // A player next to another can hit it, but only every so often
+        let other = server.connect_local("Sam");
+        server.update(0.0, false);
+        client.receive();
+        other.receive();
+        client.send(ClientMessage::Respawn);
+        client.send(ClientMessage::Attack { target: 2 });
+        client.send(ClientMessage::Attack { target: 2 });
+        server.update(0.0, false);
+        let hurt: Vec<_> = other.receive().into_iter().filter(|m| matches!(m, ServerMessage::Hurt { .. })).collect();
+        assert_eq!(hurt.len(), 1, "The second hit came too soon");
+        let ServerMessage::Hurt { amount, knockback } = hurt[0] else { unreachable!() };
+        assert!(amount > 0 && knockback[1] > 0.0);
+        assert!(client.receive().iter().all(|m| !matches!(m, ServerMessage::Hurt { .. })));
+
+        // The singleplayer host is an operator and may stop the server
+        client.send(ClientMessage::Command("/stop".to_string()));
+        server.update(0.0, false);
+        assert!(server.is_stopping());
+        assert!(client.receive().iter().any(|m| matches!(m, ServerMessage::Disconnect(_))));
+    }
+
+    #[test]
+    fn test_lan_multiplayer() {
+        use crate::block::BlockType;
+        use crate::chunk::{CHUNK_HEIGHT, CHUNK_SIZE};
+        use crate::cli::Args;
+        use crate::net;
+        use crate::protocol::{self, ClientMessage, ServerMessage, PROTOCOL_VERSION};
+        use crate::server::Server;
+        use crate::session::Session;
+        use clap::Parser;
+        use std::net::TcpStream;
+
+        // Packets survive the framing, and a corrupt length is refused
+        let mut buffer = Vec::new();
+        let message = ServerMessage::Chat { from: "Alex".to_string(), text: "hi".to_string() };
+        net::write_packet(&mut buffer, &message).unwrap();
+        assert_eq!(net::read_packet::<ServerMessage>(&mut buffer.as_slice()).unwrap(), message);
+        assert!(net::read_packet::<ServerMessage>(&mut [0xff; 8].as_slice()).is_err());
+        assert_eq!(net::parse_address("lan-host"), format!("lan-host:{}", net::DEFAULT_PORT));
+        assert_eq!(net::parse_address("10.0.0.2:4000"), "10.0.0.2:4000");
+
+        // Chunks compress far below their raw size and come back unchanged
+        let blocks = [vec![BlockType::Stone; 300], vec![BlockType::Air; 70000]].concat();
+        let data = protocol::compress_blocks(&blocks);
+        assert!(data.len() < 64, "{} bytes compressed", data.len());
+        assert_eq!(protocol::decompress_blocks(&data, blocks.len()).unwrap(), blocks);
+        assert!(protocol::decompress_blocks(&data[..data.len() / 2], blocks.len()).is_err());
+        assert!(protocol::decompress_blocks(&[0xff; 16], blocks.len()).is_err());
+        // Runs a few bytes long mustn't expand past the blocks expected
+        assert!(protocol::decompress_blocks(&data, blocks.len() - 1).is_err());
+        let runs = vec![(BlockType::Stone, u16::MAX); 20_000];
+        let bomb = lz4_flex::compress_prepend_size(&bincode::serialize(&runs).unwrap());
+        assert!(protocol::decompress_blocks(&bomb, CHUNK_SIZE * CHUNK_HEIGHT * CHUNK_SIZE).is_err());
+
+        let ops = std::env::temp_dir().join(format!("rustcraft_lan_ops_{}.txt", std::process::id()));
+        let args = Args::parse_from(["rustcraft", "--seed", "7", "--ops", ops.to_str().unwrap()]);
+        let (mut server, _) = Server::open(&args, Arc::new(MemoryStorage::new()), 2, 2);
+        server.generate_spawn_area();
+        let host = server.connect_local("Host");
+        let port = server.listen(0).unwrap();
+
+        // The login completes once the server gets to the new client
+        let joining = std::thread::spawn(move || net::connect(&format!("127.0.0.1:{}", port), "Alex"));
+        while !joining.is_finished() {
+            server.update(0.0, false);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        let (remote, info) = joining.join().unwrap().unwrap();
+        assert_eq!(info.seed, 7);
+        assert!(host.receive().iter().any(|m| matches!(m, ServerMessage::EntitySpawn { name, .. } if name == "Alex")));
+
+        // Receive from the remote client until `done` is satisfied
+        let await_messages = |server: &mut Server, done: &dyn Fn(&[ServerMessage]) -> bool| {
+            let mut received = Vec::new();
+            for _ in 0..400 {
+                server.update(0.0, false);
+                received.extend(remote.receive());
+                if done(&received) {
+                    return received;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(5));
+            }
+            panic!("Timed out waiting for the server, got {:?}", received);
+        };
+
+        // The remote client gets the spawn area, then the edits in it
+        let chunks = |messages: &[ServerMessage]| messages.iter().filter(|m| matches!(m, ServerMessage::ChunkData { .. })).count();
+        await_messages(&mut server, &|messages| chunks(messages) == 25);
+        let spawn = server.spawn_position();
+        let pos = (spawn.x as i32, spawn.y as i32 + 2, spawn.z as i32);
+        server.world.set_block_at(pos.0, pos.1, pos.2, BlockType::Planks);
+        let change = ServerMessage::BlockChange { pos, block: BlockType::Planks };
+        await_messages(&mut server, &|messages| messages.contains(&change));
+
+        // Several edits in one chunk travel together, by index into the chunk
+        let corner = (pos.0.div_euclid(16) * 16, pos.1, pos.2.div_euclid(16) * 16);
+        for dx in 0..3 {
+            server.world.set_block_at(corner.0 + dx, corner.1, corner.2, BlockType::Glass);
+        }
+        let received = await_messages(&mut server, &|messages| {
+            messages.iter().any(|m| matches!(m, ServerMessage::MultiBlockChange { .. }))
+        });
+        let changes = received.iter().find_map(|m| match m {
+            ServerMessage::MultiBlockChange { changes, .. } => Some(changes),
+            _ => None,
+        });
+        let first = (corner.1 * 16 * 16) as u16;
+        assert_eq!(changes.unwrap(), &[0, 1, 2].map(|dx| (first + dx, BlockType::Glass)));
+
+        // Its edits and chat reach the server and the other players
+        remote.send(ClientMessage::SetBlock { pos, block: BlockType::Air });
+        remote.send(ClientMessage::Chat("hello".to_string()));
+        let chat = ServerMessage::Chat { from: "Alex".to_string(), text: "hello".to_string() };
+        await_messages(&mut server, &|messages| messages.contains(&chat));
+        assert_eq!(server.world.get_block_at(pos.0, pos.1, pos.2), Some(BlockType::Air));
+        assert!(host.receive().contains(&chat));
+
+        // Out of reach edits are undone
+        let far = (pos.0 + 20, pos.1, pos.2);
+        let block = server.world.get_block_at(far.0, far.1, far.2).unwrap();
+        remote.send(ClientMessage::SetBlock { pos: far, block: BlockType::Planks });
+        let undo = ServerMessage::BlockChange { pos: far, block };
+        await_messages(&mut server, &|messages| messages.contains(&undo));
+
+        // Only levers within reach can be flipped
+        server.world.set_block_at(far.0, far.1, far.2, BlockType::Lever);
+        server.world.set_block_at(pos.0, pos.1, pos.2, BlockType::Lever);
+        remote.send(ClientMessage::UseBlock { pos: far });
+        remote.send(ClientMessage::UseBlock { pos });
+        let flipped = (0..400).any(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            server.update(0.0, false);
+            server.world.get_block_at(pos.0, pos.1, pos.2) == Some(BlockType::LeverOn)
+        });
+        assert!(flipped);
+        assert_eq!(server.world.get_block_at(far.0, far.1, far.2), Some(BlockType::Lever));
+        server.world.set_block_at(pos.0, pos.1, pos.2, BlockType::Air);
+
+        // So are solid blocks placed where a player stands
+        let feet = server.spawn_position().floor().as_ivec3();
+        let feet = (feet.x, feet.y, feet.z);
+        let block = server.world.get_block_at(feet.0, feet.1, feet.2).unwrap();
+        remote.send(ClientMessage::SetBlock { pos: feet, block: BlockType::Stone });
+        let undo = ServerMessage::BlockChange { pos: feet, block };
+        await_messages(&mut server, &|messages| messages.contains(&undo));
+        assert_eq!(server.world.get_block_at(feet.0, feet.1, feet.2), Some(block));
+
+        // Only operators may change the time or teleport; the console makes Alex one
+        let is_output = |ok: bool| move |m: &ServerMessage| matches!(m, ServerMessage::CommandOutput(output) if output.is_ok() == ok);
+        remote.send(ClientMessage::Command("/time set noon".to_string()));
+        await_messages(&mut server, &|messages| messages.iter().any(is_output(false)));
+        remote.send(ClientMessage::Command("/tp ~ 200 ~".to_string()));
+        let messages = await_messages(&mut server, &|messages| messages.iter().any(is_output(false)));
+        assert!(!messages.iter().any(|m| matches!(m, ServerMessage::Teleport { .. })));
+        assert!(server.execute_command("/op alex").is_ok());
+        assert!(std::fs::read_to_string(&ops).unwrap().contains("alex"));
+        remote.send(ClientMessage::Command("/time set noon".to_string()));
+        await_messages(&mut server, &|messages| messages.iter().any(is_output(true)));
+
+        // What a network player gives itself goes to its own copy of the
+        // world, which applies it, and not to the host's inventory
+        let (stone, level) = (server.world.inventory.count_item(BlockType::Stone), server.world.experience.level);
+        let joining = std::thread::spawn(move || net::connect(&format!("127.0.0.1:{}", port), "Sam"));
+        while !joining.is_finished() {
+            server.update(0.0, false);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        let (sam, info) = joining.join().unwrap().unwrap();
+        let (mut session, _) = Session::remote(info);
+        let own_stone = session.world().inventory.count_item(BlockType::Stone);
+        assert!(server.execute_command("/op sam").is_ok());
+        sam.send(ClientMessage::Command("/give stone 5".to_string()));
+        sam.send(ClientMessage::Command("/xp 3L".to_string()));
+        let given = (0..400).any(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            server.update(0.0, false);
+            for message in sam.receive() {
+                session.apply(&message);
+            }
+            session.world().experience.level == 3
+        });
+        assert!(given);
+        assert_eq!(session.world().inventory.count_item(BlockType::Stone), own_stone + 5);
+        assert_eq!((server.world.inventory.count_item(BlockType::Stone), server.world.experience.level), (stone, level));
+        drop(sam);
+
+        // Kicking disconnects the player and despawns it for the others
+        assert!(server.execute_command("/kick Alex Too loud").is_ok());
+        let kicked = ServerMessage::Disconnect("Too loud".to_string());
+        await_messages(&mut server, &|messages| messages.contains(&kicked));
+        drop(remote);
+        let despawned = (0..400).any(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            server.update(0.0, false);
+            host.receive().iter().any(|m| matches!(m, ServerMessage::EntityDespawn { .. }))
+        });
+        assert!(despawned);
+
+        // A client of another protocol version is turned away
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let login = ClientMessage::Login { version: PROTOCOL_VERSION + 1, name: "Old".to_string() };
+        net::write_packet(&mut stream, &login).unwrap();
+        assert!(matches!(net::read_packet(&mut stream).unwrap(), ServerMessage::Disconnect(_)));
+        std::fs::remove_file(ops).ok();
+    }
+
+    #[test]
+    fn test_permissions() {
+        use crate::permissions::{Permissions, OP_COMMANDS};
+
+        // Handing out items and experience, and teleporting, is for operators
+        assert!(OP_COMMANDS.contains(&"give") && OP_COMMANDS.contains(&"xp") && OP_COMMANDS.contains(&"tp"));
+
+        let dir = std::env::temp_dir().join(format!("rustcraft_permissions_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (ops, whitelist) = (dir.join("ops.txt"), dir.join("whitelist.txt"));
+        std::fs::write(&ops, "# operators\nAlex\n\n").unwrap();
+        std::fs::write(&whitelist, "alex\nSam\n").unwrap();
+
+        // Names compare case-insensitively; comments and blank lines are skipped
+        let mut permissions = Permissions::load(&ops, Some(&whitelist));
+        assert!(permissions.is_op("ALEX"));
+        assert!(!permissions.is_op("# operators"));
+        assert!(permissions.may_join("sam") && !permissions.may_join("Robin"));
+        // Beyond ASCII too, the same way for every list
+        assert_eq!(permissions.set_op("Émile", true), Ok(true));
+        assert!(permissions.is_op("ÉMILE") && permissions.is_op(" émile "));
+        assert_eq!(permissions.set_op("éMILE", false), Ok(true));
+        assert!(crate::permissions::same_name("ÉMILE", "émile"));
+
+        // Op changes are saved; without a whitelist everyone may join
+        assert_eq!(permissions.set_op("Sam", true), Ok(true));
+        assert_eq!(permissions.set_op("alex", false), Ok(true));
+        assert_eq!(permissions.set_op("alex", false), Ok(false));
+        let permissions = Permissions::load(&ops, None);
+        assert!(permissions.is_op("sam") && !permissions.is_op("alex"));
+        assert!(permissions.may_join("Robin"));
+
+        // A list that can't be saved doesn't change
+        let mut permissions = Permissions::load(&dir.join("missing").join("ops.txt"), None);
+        assert!(permissions.set_op("Robin", true).is_err());
+        assert!(!permissions.is_op("Robin"));
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_remote_players() {
+        use crate::camera::Camera;
+        use crate::font;
+        use crate::labels;
+        use crate::remote_players::RemotePlayers;
+        use glam::Vec3;
+
+        let mut players = RemotePlayers::new();
+        players.spawn(1, "Alex".to_string(), Vec3::ZERO, 3.0);
+        players.move_to(1, Vec3::new(2.0, 0.0, 0.0), -3.0);
+
+        // Half way there after half the glide; turning takes the short way round
+        players.update(0.05);
+        let alex = players.iter().next().unwrap();
+        assert!((alex.position.x - 1.0).abs() < 1e-4);
+        assert!(alex.yaw > 3.0, "Turned past pi instead of back through zero: {}", alex.yaw);
+        players.update(1.0);
+        assert_eq!(players.iter().next().unwrap().position, Vec3::new(2.0, 0.0, 0.0));
+
+        // Quads of skin pixels, from the feet to the top of the hat layer
+        let (vertices, indices) = players.build_mesh();
+        assert!(!vertices.is_empty());
+        assert_eq!(indices.len(), vertices.len() / 4 * 6);
+        assert!(vertices.iter().all(|v| (-0.05..=1.83).contains(&v.position[1])));
+        // The spectator camera also sees the local player's body, in the
+        // default skin with another shirt
+        players.set_body(Some((Vec3::new(5.0, 0.0, 0.0), 0.0)));
+        assert_eq!(players.build_mesh().0.len(), 2 * vertices.len());
+        players.set_body(None);
+        assert_eq!(players.build_mesh().0.len(), vertices.len());
+
+        // The name tag floats above the player, facing a camera in front of
+        // it; it isn't built behind the camera or out of range
+        let mut camera = Camera::new(1.0);
+        camera.position = Vec3::new(-5.0, 1.6, 0.0);
+        camera.yaw = 0.0;
+        let tags = players.labels();
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].text, "Alex");
+        let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+        labels::build_mesh(&tags, &camera, &mut vertices, &mut indices);
+        // A background and a quad per letter
+        assert_eq!(vertices.len(), 5 * 4);
+        assert_eq!(indices.len(), 5 * 6);
+        assert!(vertices.iter().all(|v| (v.position[0] - 2.0).abs() < 1e-4 && v.position[1] > 1.8));
+        vertices.clear();
+        camera.yaw = std::f32::consts::PI;
+        labels::build_mesh(&tags, &camera, &mut vertices, &mut indices);
+        assert!(vertices.is_empty());
+        camera.yaw = 0.0;
+        camera.position.x = -labels::LABEL_RANGE;
+        labels::build_mesh(&tags, &camera, &mut vertices, &mut indices);
+        assert!(vertices.is_empty());
+        // Labels fade out towards the end of their range
+        assert_eq!(labels::fade(1.0), 1.0);
+        assert!(labels::fade(40.0) > 0.0 && labels::fade(40.0) < 1.0);
+        assert_eq!(labels::fade(labels::LABEL_RANGE), 0.0);
+
+        // The font atlas has every glyph, '?' for the rest, and a solid cell
+        let atlas = font::atlas();
+        let pixel = |(x, y): (u32, u32), col: u32, row: u32| atlas.get_pixel(x + col, y + row)[3] > 0;
+        assert_eq!(font::atlas_cell('é'), font::atlas_cell('?'));
+        assert!((0..5).all(|col| (0..7).all(|row| pixel(font::solid_cell(), col, row))));
+        assert!((0..5).all(|col| (0..7).all(|row| pixel(font::atlas_cell('A'), col, row) == font::glyph_pixel('A', col as usize, row as usize))));
+
+        assert_eq!(players.despawn(1), Some("Alex".to_string()));
+        assert!(players.is_empty());
+    }
+
+    #[test]
+    fn test_skins() {
+        use crate::cli::Args;
+        use crate::protocol::{ClientMessage, ServerMessage};
+        use crate::remote_players::RemotePlayers;
+        use crate::server::Server;
+        use crate::skin::{self, Face, Skin, MAX_SKIN_FILE_SIZE};
+        use clap::Parser;
+        use glam::Vec3;
+        use image::{Rgba, RgbaImage};
+
+        let png = |image: &RgbaImage| {
+            let mut data = std::io::Cursor::new(Vec::new());
+            image.write_to(&mut data, image::ImageOutputFormat::Png).unwrap();
+            data.into_inner()
+        };
+
+        // Faces are laid out as in Minecraft skins
+        assert_eq!(Skin::face_texel(skin::HEAD[0], [8, 8, 8], Face::Front, 0, 0), (8, 8));
+        assert_eq!(Skin::face_texel(skin::HEAD[0], [8, 8, 8], Face::Top, 7, 7), (15, 7));
+        assert_eq!(Skin::face_texel(skin::RIGHT_ARM[0], [4, 12, 4], Face::Back, 0, 0), (52, 20));
+        assert_eq!(Face::Right.texels([8, 12, 4]), (4, 12));
+
+        // The default skin fills the first layer and leaves the hat out
+        let default = Skin::default_with_shirt([0.5, 0.0, 0.0]);
+        assert!(default.color(8, 12).is_some());
+        let shirt = default.color(20, 20).unwrap();
+        assert!((shirt[0] - 0.5).abs() < 0.01 && shirt[1] < 0.01);
+        assert_eq!(default.color(40, 8), None);
+
+        // Old 64x32 skins get left limbs like the right ones; their hat is
+        // dropped when it has no transparent pixel
+        let mut legacy = RgbaImage::from_pixel(64, 32, Rgba([0, 0, 0, 255]));
+        legacy.put_pixel(44, 20, Rgba([255, 0, 0, 255]));
+        let skin = Skin::decode(&png(&legacy)).unwrap();
+        assert_eq!(skin.color(36, 52), Some([1.0, 0.0, 0.0]));
+        assert_eq!(skin.color(40, 8), None);
+        assert!(Skin::decode(&png(&RgbaImage::new(16, 16))).unwrap_err().contains("64x64"));
+        assert!(Skin::decode(b"not a png").is_err());
+
+        // A quad for each run of same colored pixels in a row; transparent
+        // pixels are left out
+        let mut image = RgbaImage::new(64, 64);
+        let mut players = RemotePlayers::new();
+        players.spawn(1, "Alex".to_string(), Vec3::ZERO, 0.0);
+        let mesh_of = |image: &RgbaImage, players: &mut RemotePlayers| {
+            players.set_skin(1, Some(Skin::decode(&png(image)).unwrap()));
+            players.build_mesh().0.len() / 4
+        };
+        assert_eq!(mesh_of(&image, &mut players), 0);
+        image.put_pixel(8, 8, Rgba([255, 255, 255, 255]));
+        image.put_pixel(9, 8, Rgba([255, 255, 255, 255]));
+        assert_eq!(mesh_of(&image, &mut players), 1);
+        image.put_pixel(10, 8, Rgba([0, 0, 255, 255]));
+        image.put_pixel(12, 8, Rgba([0, 0, 255, 100]));
+        assert_eq!(mesh_of(&image, &mut players), 2);
+        // The top left pixel of the face is at the figure's top right (-x)
+        players.set_skin(1, Some(Skin::decode(&png(&image)).unwrap()));
+        let (vertices, _) = players.build_mesh();
+        assert!(vertices.iter().all(|v| v.position[1] > 1.7));
+        players.set_skin(1, None);
+        assert!(players.build_mesh().0.len() > 100);
+
+        // The server passes skins on to the others and to players joining later
+        let args = Args::parse_from(["rustcraft", "--seed", "99"]);
+        let (mut server, _) = Server::open(&args, Arc::new(MemoryStorage::new()), 2, 2);
+        let host = server.connect_local("Host");
+        let other = server.connect_local("Sam");
+        server.update(0.0, false);
+        other.receive();
+        let data = png(&image);
+        host.send(ClientMessage::Skin(data.clone()));
+        host.send(ClientMessage::Skin(vec![0; MAX_SKIN_FILE_SIZE + 1]));
+        server.update(0.0, false);
+        let skins: Vec<_> = other.receive().into_iter().filter(|m| matches!(m, ServerMessage::EntitySkin { .. })).collect();
+        assert_eq!(skins, vec![ServerMessage::EntitySkin { id: 1, data: data.clone() }]);
+        let late = server.connect_local("Robin");
+        let messages = late.receive();
+        let spawn = messages.iter().position(|m| matches!(m, ServerMessage::EntitySpawn { id: 1, .. })).unwrap();
+        assert_eq!(messages[spawn + 1], ServerMessage::EntitySkin { id: 1, data });
+        assert!(!messages.iter().any(|m| matches!(m, ServerMessage::EntitySkin { id: 2, .. })));
+    }
+
+    #[test]
+    fn test_world_time_cycle() {
+        use crate::time::{Weather, WorldTime, TICKS_PER_DAY, TIME_MIDNIGHT, TIME_NOON};
+        use crate::gamerules::GameRules;
+
+        let mut time = WorldTime::new();
+        time.set_time_of_day(TIME_NOON);
+        assert!(time.daylight() > 0.99, "Noon is full daylight");
+        time.set_time_of_day(TIME_MIDNIGHT);
+        assert!(time.daylight() < 0.01, "Midnight is dark");
+
+        // Ticks wrap around into the next day
+        time.set_time_of_day(TICKS_PER_DAY - 10);
+        for _ in 0..20 {
+            time.tick(&GameRules::new());
+        }
+        assert_eq!(time.time_of_day(), 10);
+        assert_eq!(time.day(), 1);
+        time.set_time_of_day(TIME_NOON);
+        assert_eq!(time.day(), 1, "Setting the time stays on the same day");
+
+        // Clouds dim the daylight; the weather changes now and then
+        let clear = time.sky_light();
+        time.set_weather(Weather::Rain);
+        assert!(time.sky_light() < clear);
+        assert!(time.daylight() > 0.99, "The sun is still up behind the clouds");
+        let changes = (0..TICKS_PER_DAY * 10).filter(|_| time.tick_weather()).count();
+        assert!(changes >= 2, "The weather changed {} times in ten days", changes);
+
+        // Time and weather are saved with the world
+        let mut world = World::new(1);
+        world.time.set_ticks(3 * TICKS_PER_DAY as u64 + 500);
+        world.time.set_weather(Weather::Thunder);
+        let storage = MemoryStorage::new();
+        world.save(&storage, "time.dat").unwrap();
+        let loaded = World::load(&storage, "time.dat").unwrap();
+        assert_eq!((loaded.time.day(), loaded.time.time_of_day()), (3, 500));
+        assert_eq!(loaded.time.weather(), Weather::Thunder);
+    }
+
+    #[test]
+    fn test_gamerules() {
+        use crate::commands::{execute, CommandContext};
+        use crate::gamerules::{GameRules, RuleValue};
+        use crate::inventory::ItemStack;
+        use crate::item_entity::{update_items, ItemEntity};
+        use crate::region::Selection;
+        use crate::time::TIME_NOON;
+
+        let mut world = World::new(5);
+        let mut player = Player::new(Vec3::new(0.0, 50.0, 0.0));
+        let mut ctx = CommandContext { world: &mut world, player: &mut player, selection: &mut Selection::default() };
+        assert_eq!(
+            execute("/gamerule", &mut ctx),
+            Ok("Game rules: doDaytimeCycle = true, dayLengthSeconds = 1200, keepInventory = false, doMobSpawning = true, playersSleepingPercentage = 100".to_string())
+        );
+        assert_eq!(execute("/gamerule keepinventory true", &mut ctx), Ok("Set keepInventory to true".to_string()));
+        assert_eq!(execute("/gamerule keepInventory", &mut ctx), Ok("keepInventory = true".to_string()));
+        assert!(execute("/gamerule keepInventory maybe", &mut ctx).is_err());
+        assert!(execute("/gamerule dayLengthSeconds 0", &mut ctx).is_err());
+        assert!(execute("/gamerule playersSleepingPercentage 101", &mut ctx).is_err());
+        assert!(execute("/gamerule flyingPigs true", &mut ctx).is_err());
+
+        // Days of 600 seconds run the clock twice as fast; a stopped cycle
+        // holds it wherever it is
+        ctx.world.time.set_time_of_day(TIME_NOON);
+        assert!(execute("/gamerule dayLengthSeconds 600", &mut ctx).is_ok());
+        for _ in 0..10 {
+            ctx.world.time.tick(&ctx.world.rules);
+        }
+        assert_eq!(ctx.world.time.time_of_day(), TIME_NOON + 20);
+        assert!(execute("/gamerule dayLengthSeconds 4800", &mut ctx).is_ok());
+        for _ in 0..10 {
+            ctx.world.time.tick(&ctx.world.rules);
+        }
+        assert_eq!(ctx.world.time.time_of_day(), TIME_NOON + 22);
+        assert!(execute("/gamerule doDaytimeCycle false", &mut ctx).is_ok());
+        for _ in 0..100 {
+            ctx.world.time.tick(&ctx.world.rules);
+        }
+        assert_eq!(ctx.world.time.time_of_day(), TIME_NOON + 22);
+
+        // The rules are saved with the world
+        let storage = MemoryStorage::new();
+        world.save(&storage, "rules.dat").unwrap();
+        let loaded = World::load(&storage, "rules.dat").unwrap();
+        assert!(loaded.rules.keep_inventory() && !loaded.rules.do_daytime_cycle());
+        assert_eq!(loaded.rules.day_length_seconds(), 4800);
+        let mut rules = GameRules::new();
+        assert_eq!(rules.set("dayLengthSeconds", "1200"), Ok(("dayLengthSeconds", RuleValue::Int(1200))));
+        assert_eq!(rules, GameRules::new(), "Setting a rule to its default forgets it");
+
+        // Dying without keepInventory scatters the inventory, which the dead
+        // player can't pick up again
+        let mut inventory = crate::inventory::Inventory::with_starter_items();
+        let stacks = inventory.take_all();
+        assert!(stacks.len() > 10 && inventory.take_all().is_empty());
+        world.chunks.insert((0, 0), Chunk::new(0, 0));
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_block_at(x, 9, z, BlockType::Stone);
+            }
+        }
+        world.items.push(ItemEntity::thrown(ItemStack::new(BlockType::Dirt, 3), Vec3::new(4.0, 12.0, 4.0), Vec3::X));
+        for _ in 0..200 {
+            assert!(!update_items(&mut world, None, 1.0 / 60.0));
+        }
+        assert_eq!(world.items.len(), 1);
+    }
+
+    #[test]
+    fn test_sleep() {
+        use crate::cli::Args;
+        use crate::protocol::{ClientMessage, ServerMessage};
+        use crate::server::Server;
+        use crate::sleep::{self, SleepStatus, SLEEP_TICKS};
+        use crate::tick::TICK_LENGTH;
+        use crate::time::{Weather, WorldTime, TIME_DAY, TIME_MIDNIGHT, TIME_NIGHT, TIME_NOON};
+        use clap::Parser;
+
+        assert_eq!(BlockType::from_name("bed"), Some(BlockType::Bed));
+        assert_eq!((sleep::needed(1, 100), sleep::needed(3, 50), sleep::needed(4, 50), sleep::needed(5, 0)), (1, 2, 2, 1));
+        assert_eq!(sleep::ticks_until_morning(TIME_MIDNIGHT), 7000);
+        assert_eq!(sleep::ticks_until_morning(TIME_DAY), 0);
+        let mut time = WorldTime::new();
+        time.set_time_of_day(TIME_NOON);
+        assert!(!sleep::can_sleep(&time));
+        time.set_weather(Weather::Thunder);
+        assert!(sleep::can_sleep(&time), "Thunderstorms can be slept through");
+        time.set_weather(Weather::Clear);
+        time.set_time_of_day(TIME_NIGHT);
+        assert!(sleep::can_sleep(&time));
+
+        // The HUD names a few of the players still awake
+        let awake = ["Ann", "Bo", "Cy", "Di", "Ed"].map(String::from).to_vec();
+        let status = SleepStatus { sleeping: 1, needed: 3, awake };
+        assert_eq!(status.message().unwrap(), "1/3 players sleeping, awake: Ann, Bo, Cy and 2 more");
+        assert_eq!(SleepStatus::default().message(), None);
+
+        let args = Args::parse_from(["rustcraft", "--seed", "99"]);
+        let (mut server, _) = Server::open(&args, Arc::new(MemoryStorage::new()), 2, 2);
+        server.generate_spawn_area();
+        let host = server.connect_local("Host");
+        let other = server.connect_local("Sam");
+        let feet = server.spawn_position().floor().as_ivec3();
+        let bed = (feet.x + 1, feet.y, feet.z);
+        assert!(server.world.set_block_at(bed.0, bed.1, bed.2, BlockType::Bed));
+        let run = |server: &mut Server, ticks: u32| {
+            let mut done = 0;
+            while done < ticks {
+                done += server.update(TICK_LENGTH, true);
+            }
+        };
+
+        // Beds are for the night
+        server.world.time.set_time_of_day(TIME_NOON);
+        host.send(ClientMessage::Sleep { pos: bed });
+        server.update(0.0, false);
+        assert!(host.receive().contains(&ServerMessage::WakeUp));
+
+        // One of two asleep isn't enough by default; everyone sees who is awake
+        server.world.time.set_time_of_day(TIME_MIDNIGHT);
+        host.send(ClientMessage::Sleep { pos: bed });
+        run(&mut server, 1);
+        let status = SleepStatus { sleeping: 1, needed: 2, awake: vec!["Sam".to_string()] };
+        assert!(other.receive().contains(&ServerMessage::SleepStatus(status)));
+        run(&mut server, SLEEP_TICKS + 10);
+        assert!(server.world.time.time_of_day() >= TIME_MIDNIGHT);
+        assert!(!host.receive().contains(&ServerMessage::WakeUp));
+
+        // Half of the players are enough at 50%: a while later it is morning,
+        // the storm is over and everyone is up
+        assert!(server.execute_command("/gamerule playersSleepingPercentage 50").is_ok());
+        server.world.time.set_weather(Weather::Thunder);
+        run(&mut server, SLEEP_TICKS + 1);
+        assert!(server.world.time.time_of_day() < TIME_DAY + 10);
+        assert_eq!(server.world.time.weather(), Weather::Clear);
+        let messages = host.receive();
+        assert!(messages.contains(&ServerMessage::WakeUp));
+        assert!(messages.contains(&ServerMessage::SleepStatus(SleepStatus::default())));
+    }
+
+    #[test]
+    fn test_rain_and_snow() {
+        use crate::time::Weather;
+        use crate::weather::Precipitation;
+        use crate::world_gen::{WorldGenerator, WATER_LEVEL};
+
+        // Snow and puddles are thin layers the player walks through
+        assert!(BlockType::Snow.is_layer() && !BlockType::Snow.is_full_cube());
+        assert!(BlockType::Puddle.is_liquid() && BlockType::Puddle.height() < BlockType::Snow.height());
+        assert_eq!(BlockType::from_name("snow"), Some(BlockType::Snow));
+        let mut world = World::new(1);
+        world.chunks.insert((0, 0), Chunk::new(0, 0));
+        world.set_block_at(8, 9, 8, BlockType::Stone);
+        world.set_block_at(8, 10, 8, BlockType::Snow);
+        let mut player = Player::new(Vec3::new(8.5, 12.0, 8.5));
+        for _ in 0..200 {
+            player.apply_physics(0.016, &world);
+        }
+        assert!(player.on_ground);
+        assert!((player.position.y - 10.0).abs() < 0.01, "Stands on the stone at {}", player.position.y);
+
+        // Colder up high; some places are cold enough to snow and some aren't
+        let generator = WorldGenerator::new(1);
+        let ground_y = WATER_LEVEL as i32 + 10;
+        assert!(generator.temperature(0, ground_y + 10, 0) < generator.temperature(0, ground_y, 0));
+        let places: Vec<i32> = (0..200).map(|i| i * 64).collect();
+        let cold_x = *places.iter().find(|&&x| generator.is_cold(x, ground_y + 1, 0)).unwrap();
+        let warm_x = *places.iter().find(|&&x| !generator.is_cold(x, ground_y + 1, 0)).unwrap();
+
+        // While it rains snow settles up high, and puddles form on warm ground
+        let mut world = World::new(1);
+        let (cold_chunk, warm_chunk) = ((cold_x.div_euclid(16), 0), (warm_x.div_euclid(16), 0));
+        for (cx, cz) in [cold_chunk, warm_chunk] {
+            world.chunks.insert((cx, cz), Chunk::new(cx, cz));
+        }
+        let ground = |chunk: (i32, i32), y: i32, world: &mut World| {
+            for x in 0..16 {
+                for z in 0..16 {
+                    world.set_block_at(chunk.0 * 16 + x, y, chunk.1 * 16 + z, BlockType::Grass);
+                }
+            }
+        };
+        ground(cold_chunk, ground_y, &mut world);
+        ground(warm_chunk, ground_y, &mut world);
+        let count = |world: &World, block: BlockType| {
+            world.chunks.values().map(|chunk| chunk.blocks.iter().filter(|b| **b == block).count()).sum::<usize>()
+        };
+        assert!(!world.tick_precipitation(&generator), "Nothing happens in clear weather");
+
+        world.time.set_weather(Weather::Rain);
+        for _ in 0..2000 {
+            world.tick_precipitation(&generator);
+        }
+        let (snow, puddles) = (count(&world, BlockType::Snow), count(&world, BlockType::Puddle));
+        assert!(snow > 100, "{} snow layers", snow);
+        assert!(puddles > 10 && puddles < 200, "{} puddles", puddles);
+        assert_eq!(world.highest_block(cold_x, 0), Some((ground_y + 1, BlockType::Snow)));
+        assert_eq!(world.highest_block(cold_x, -1), None, "Chunk not loaded");
+
+        // Once the sky clears the puddles dry up; the snow stays where it's cold
+        world.time.set_weather(Weather::Clear);
+        for _ in 0..20_000 {
+            world.tick_precipitation(&generator);
+        }
+        assert_eq!(count(&world, BlockType::Puddle), 0);
+        assert_eq!(count(&world, BlockType::Snow), snow);
+
+        // The client fades the rain in, and drops stop at the top of each column
+        let mut precipitation = Precipitation::new();
+        let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+        let eye = Vec3::new(warm_x as f32 + 8.5, ground_y as f32 + 2.6, 8.5);
+        precipitation.build_mesh(&world, &generator, eye, &mut vertices, &mut indices);
+        assert!(indices.is_empty(), "Nothing falls from a clear sky");
+        for _ in 0..100 {
+            precipitation.update(Weather::Thunder, 0.1);
+        }
+        assert_eq!(precipitation.strength(), 1.0);
+        assert!(precipitation.light() < 1.0, "Clouds dim the light");
+        precipitation.build_mesh(&world, &generator, eye, &mut vertices, &mut indices);
+        assert!(!indices.is_empty() && indices.len() % 2 == 0, "Rain falls as line streaks");
+        assert!(vertices.iter().all(|v| v.position[1] >= ground_y as f32 + 1.0));
+    }
+
+    #[test]
+    fn test_game_tick() {
+        use crate::tick::TickClock;
+
+        // 20 ticks per second regardless of frame rate; long stalls are capped
+        let mut clock = TickClock::new();
+        let ticks: u32 = (0..144).map(|_| clock.advance(1.0 / 144.0)).sum();
+        assert!((19..=20).contains(&ticks), "{} ticks in a second", ticks);
+        let ticks: u32 = (0..30).map(|_| clock.advance(1.0 / 30.0)).sum();
+        assert!((19..=21).contains(&ticks), "{} ticks in a second", ticks);
+        assert_eq!(clock.advance(5.0), 10);
+
+        let mut scripts = crate::scripting::BlockScripts::new();
+        let mut world = World::new(0);
+        world.chunks.insert((0, 0), Chunk::new(0, 0));
+        world.set_block_at(4, 0, 4, BlockType::Stone);
+        world.set_block_at(4, 1, 4, BlockType::Water);
+
+        // Sand falls through air and sinks through water, the sand on top follows
+        world.set_block_at(4, 5, 4, BlockType::Sand);
+        world.set_block_at(4, 6, 4, BlockType::Sand);
+        for _ in 0..20 {
+            world.tick_blocks(&mut scripts);
+        }
+        assert_eq!(world.get_block_at(4, 1, 4), Some(BlockType::Sand));
+        assert_eq!(world.get_block_at(4, 2, 4), Some(BlockType::Sand));
+        assert_eq!(world.get_block_at(4, 3, 4), Some(BlockType::Water));
+        assert_eq!(world.get_block_at(4, 6, 4), Some(BlockType::Air));
+
+        // Covered grass turns to dirt, open dirt next to grass grows over
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_block_at(x, 10, z, if x < 8 { BlockType::Grass } else { BlockType::Dirt });
+                if x < 4 {
+                    world.set_block_at(x, 11, z, BlockType::Stone);
+                }
+            }
+        }
+        for _ in 0..20 * 600 {
+            world.tick_blocks(&mut scripts);
+        }
+        assert_eq!(world.get_block_at(0, 10, 0), Some(BlockType::Dirt));
+        assert_eq!(world.get_block_at(8, 10, 8), Some(BlockType::Grass));
+
+        // Outside the simulation distance sand hangs in the air until a
+        // player comes near
+        world.chunks.insert((5, 0), Chunk::new(5, 0));
+        world.simulated_chunks = Some([(0, 0)].into_iter().collect());
+        world.set_block_at(84, 5, 4, BlockType::Sand);
+        for _ in 0..40 {
+            world.tick_blocks(&mut scripts);
+        }
+        assert_eq!(world.get_block_at(84, 5, 4), Some(BlockType::Sand));
+        assert!(world.simulates_block(15, 15) && !world.simulates_block(84, 4));
+        world.simulated_chunks = None;
+        for _ in 0..40 {
+            world.tick_blocks(&mut scripts);
+        }
+        assert_eq!(world.get_block_at(84, 0, 4), Some(BlockType::Sand));
+    }
+
+    #[test]
+    fn test_buckets() {
+        use crate::camera::Camera;
+        use crate::events::GameEvent;
+        use crate::input::InputHandler;
+        use crate::inventory::Inventory;
+        use crate::physics::GameMode;
+        use crate::scripting::BlockScripts;
+        use crate::ui::UiRenderer;
+
+        // Stone floor at y 9 with a water source on it, looked at from above
+        let mut world = World::new(1);
+        world.chunks.insert((0, 0), Chunk::new(0, 0));
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_block_at(x, 9, z, BlockType::Stone);
+            }
+        }
+        world.set_block_at(4, 10, 4, BlockType::Water);
+        let mut camera = Camera::new(1.0);
+        camera.position = Vec3::new(4.5, 13.0, 4.5);
+        camera.pitch = -std::f32::consts::FRAC_PI_2 + 0.01;
+        let mut player = Player::new(Vec3::new(8.5, 10.0, 8.5));
+        player.game_mode = GameMode::Survival;
+        world.inventory = Inventory::new();
+        world.inventory.add_item(Item::Bucket, 2);
+        let mut input = InputHandler::new();
+        let mut right_click = |world: &mut World| {
+            world.events.drain();
+            input.right_mouse_pressed = true;
+            input.handle_block_interaction(&camera, world, &UiRenderer::new(), &player, &BlockScripts::new(), &[], 0.0)
+        };
+
+        // An empty bucket scoops up the source, the full one goes to a free slot
+        assert!(right_click(&mut world).changed_block);
+        assert_eq!(world.get_block_at(4, 10, 4), Some(BlockType::Air));
+        assert_eq!(world.events.drain(), vec![GameEvent::BlockBroken { pos: (4, 10, 4), block: BlockType::Water }]);
+        assert_eq!(world.inventory.count_item(Item::Bucket), 1);
+        assert_eq!(world.inventory.count_item(Item::WaterBucket), 1);
+
+        // Without water in sight the bucket does nothing
+        assert!(!right_click(&mut world).changed_block);
+
+        // The last bucket is filled in place, and full buckets don't stack
+        world.set_block_at(4, 10, 4, BlockType::Water);
+        assert!(right_click(&mut world).changed_block);
+        assert_eq!(world.inventory.selected_item(), Some(Item::WaterBucket));
+        assert_eq!(world.inventory.count_item(Item::WaterBucket), 2);
+        assert_eq!(world.inventory.toolbar.iter().flatten().count(), 2);
+
+        // A full bucket pours a source onto the face looked at and empties
+        assert!(right_click(&mut world).changed_block);
+        assert_eq!(world.get_block_at(4, 10, 4), Some(BlockType::Water));
+        assert_eq!(world.events.drain(), vec![GameEvent::BlockPlaced { pos: (4, 10, 4), block: BlockType::Water }]);
+        assert_eq!(world.inventory.selected_item(), Some(Item::Bucket));
+
+        // Buckets are never placed as blocks
+        assert!(Item::Bucket.block().is_none() && Item::WaterBucket.block().is_none());
+        assert_eq!(Item::from_name("water_bucket"), Some(Item::WaterBucket));
+    }
+
+    #[test]
+    fn test_place_block_in_player() {
+        use crate::camera::Camera;
+        use crate::input::InputHandler;
+        use crate::inventory::Inventory;
+        use crate::physics::{Aabb, GameMode};
+        use crate::scripting::BlockScripts;
+        use crate::ui::UiRenderer;
+
+        // Stone floor at y 9, looked straight down at from above the player's head
+        let mut world = World::new(1);
+        world.chunks.insert((0, 0), Chunk::new(0, 0));
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_block_at(x, 9, z, BlockType::Stone);
+            }
+        }
+        let mut camera = Camera::new(1.0);
+        camera.position = Vec3::new(4.5, 12.5, 4.5);
+        camera.pitch = -std::f32::consts::FRAC_PI_2 + 0.01;
+        world.inventory = Inventory::new();
+        world.inventory.add_item(Item::Block(BlockType::Dirt), 10);
+        world.inventory.add_item(Item::Block(BlockType::Mushroom), 10);
+        let mut input = InputHandler::new();
+        let mut place = |world: &mut World, player: &Player, entities: &[(u32, Aabb)]| {
+            input.right_mouse_pressed = true;
+            input.handle_block_interaction(&camera, world, &UiRenderer::new(), player, &BlockScripts::new(), entities, 0.0)
+        };
+
+        // A block in the player's feet would trap them, and is not placed
+        let mut player = Player::new(Vec3::new(4.5, 10.0, 4.5));
+        player.game_mode = GameMode::Survival;
+        assert!(!place(&mut world, &player, &[]).changed_block);
+        assert_eq!(world.get_block_at(4, 10, 4), Some(BlockType::Air));
+        assert_eq!(world.inventory.count_item(BlockType::Dirt), 10);
+
+        // Even when only the edge of the body reaches into the cell
+        player = Player::new(Vec3::new(5.2, 10.0, 4.5));
+        player.game_mode = GameMode::Survival;
+        assert!(!place(&mut world, &player, &[]).changed_block);
+
+        // Nor where another player stands
+        let away = Player::new(Vec3::new(8.5, 10.0, 8.5));
+        let other = Player::new(Vec3::new(4.5, 10.0, 4.5)).bounding_box;
+        assert!(!place(&mut world, &away, &[(3, other)]).changed_block);
+        assert_eq!(world.get_block_at(4, 10, 4), Some(BlockType::Air));
+
+        // Nor where a boat, a villager or a dropped item is
+        world.boats.push(crate::boat::Boat::placed(Vec3::new(4.5, 10.0, 4.5), 0.0));
+        assert!(!place(&mut world, &away, &[]).changed_block);
+        world.boats.clear();
+        world.villagers.push(crate::villager::Villager::new(Vec3::new(4.5, 10.0, 4.5), 1));
+        assert!(!place(&mut world, &away, &[]).changed_block);
+        world.villagers.clear();
+        let stack = crate::inventory::ItemStack::new(BlockType::Sand, 1);
+        world.items.push(crate::item_entity::ItemEntity::thrown(stack, Vec3::new(4.5, 10.5, 4.5), Vec3::ZERO));
+        assert!(!place(&mut world, &away, &[]).changed_block);
+        world.items.clear();
+        assert_eq!(world.get_block_at(4, 10, 4), Some(BlockType::Air));
+
+        // Clear of everyone it is placed
+        assert!(place(&mut world, &away, &[]).changed_block);
+        assert_eq!(world.get_block_at(4, 10, 4), Some(BlockType::Dirt));
+        assert_eq!(world.inventory.count_item(BlockType::Dirt), 9);
+
+        // Plants don't collide, so they go down at the player's feet
+        world.set_block_at(4, 10, 4, BlockType::Air);
+        world.inventory.selected_slot = 1;
+        player = Player::new(Vec3::new(4.5, 10.0, 4.5));
+        assert!(place(&mut world, &player, &[]).changed_block);
+        assert_eq!(world.get_block_at(4, 10, 4), Some(BlockType::Mushroom));
+
+        // But not on top of another plant
+        assert!(!place(&mut world, &player, &[]).changed_block);
+        assert_eq!(world.get_block_at(4, 11, 4), Some(BlockType::Air));
+    }
+
+    #[test]
+    fn test_edit_history() {
+        use crate::edit_history::{BlockEdit, EditHistory, MAX_EDITS};
+
+        // Stone floor at y 9 with a mushroom on it
+        let mut world = World::new(1);
+        world.chunks.insert((0, 0), Chunk::new(0, 0));
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_block_at(x, 9, z, BlockType::Stone);
+            }
+        }
+        world.set_block_at(2, 10, 2, BlockType::Mushroom);
+        let mut history = EditHistory::new();
+        let step = |world: &mut World, history: &mut EditHistory, edit: &dyn Fn(&mut World)| {
+            world.recorded_edits = Some(Vec::new());
+            edit(world);
+            history.record(world.recorded_edits.take().unwrap());
+        };
+
+        // Breaking the floor under the mushroom takes it along, in one step
+        step(&mut world, &mut history, &|world| {
+            world.set_block_at(2, 9, 2, BlockType::Air);
+        });
+        step(&mut world, &mut history, &|world| {
+            world.set_block_at(5, 10, 5, BlockType::Planks);
+        });
+        assert_eq!(world.get_block_at(2, 10, 2), Some(BlockType::Air));
+
+        // Undo goes back step by step, redo forward again
+        assert!(history.undo(&mut world));
+        assert_eq!(world.get_block_at(5, 10, 5), Some(BlockType::Air));
+        assert!(history.undo(&mut world));
+        assert_eq!(world.get_block_at(2, 9, 2), Some(BlockType::Stone));
+        assert_eq!(world.get_block_at(2, 10, 2), Some(BlockType::Mushroom));
+        assert!(!history.undo(&mut world));
+        assert!(history.redo(&mut world));
+        assert_eq!(world.get_block_at(2, 9, 2), Some(BlockType::Air));
+        assert_eq!(world.get_block_at(2, 10, 2), Some(BlockType::Air));
+
+        // Undone edits go to the network clients like any other
+        world.block_changes.clear();
+        assert!(history.undo(&mut world));
+        assert!(world.block_changes.contains(&((2, 9, 2), BlockType::Stone)));
+
+        // A new step drops what was left to redo
+        step(&mut world, &mut history, &|world| {
+            world.set_block_at(7, 10, 7, BlockType::Glass);
+        });
+        assert!(!history.redo(&mut world));
+
+        // Blocks changed since are left as they are
+        world.set_block_at(7, 10, 7, BlockType::Dirt);
+        assert!(history.undo(&mut world));
+        assert_eq!(world.get_block_at(7, 10, 7), Some(BlockType::Dirt));
+
+        // Nothing is recorded unless asked for
+        world.set_block_at(8, 10, 8, BlockType::Dirt);
+        assert!(world.recorded_edits.is_none());
+
+        // Past the cap the oldest steps are forgotten
+        let mut history = EditHistory::new();
+        let edit = BlockEdit { pos: (3, 10, 3), old: BlockType::Air, new: BlockType::Sand };
+        history.record(vec![edit]);
+        history.record(vec![BlockEdit { pos: (4, 10, 4), ..edit }; MAX_EDITS]);
+        world.set_block_at(3, 10, 3, BlockType::Sand);
+        assert!(history.undo(&mut world));
+        assert!(!history.undo(&mut world));
+        assert_eq!(world.get_block_at(3, 10, 3), Some(BlockType::Sand));
+    }
+
+    #[test]
+    fn test_placement_support() {
+        // Stone floor at y 9 with glass, water, a plant and snow on it
+        let mut world = World::new(1);
+        world.chunks.insert((0, 0), Chunk::new(0, 0));
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_block_at(x, 9, z, BlockType::Stone);
+            }
+        }
+        world.set_block_at(2, 10, 2, BlockType::Glass);
+        world.set_block_at(4, 10, 4, BlockType::Water);
+        world.set_block_at(6, 10, 6, BlockType::Mushroom);
+        world.set_block_at(8, 10, 8, BlockType::Snow);
+
+        // Plants, layers, levers and doors need a full block under them
+        for block in [BlockType::Mushroom, BlockType::GlowingFungus, BlockType::Snow, BlockType::Puddle, BlockType::Lever, BlockType::Door, BlockType::OpenDoor] {
+            assert!(block.needs_floor(), "{:?}", block);
+            assert!(world.can_place((1, 10, 1), block), "{:?}", block);
+            assert!(world.can_place((2, 11, 2), block), "{:?} on glass", block);
+            assert!(!world.can_place((1, 11, 1), block), "{:?} in the air", block);
+            assert!(!world.can_place((6, 11, 6), block), "{:?} on a plant", block);
+            assert!(!world.can_place((8, 11, 8), block), "{:?} on snow", block);
+            assert!(!world.can_place((4, 10, 4), block), "{:?} in water", block);
+        }
+
+        // Other blocks go anywhere, into water too
+        for block in [BlockType::Dirt, BlockType::Sand, BlockType::Chest, BlockType::Water, BlockType::Fire] {
+            assert!(!block.needs_floor(), "{:?}", block);
+            assert!(world.can_place((1, 11, 1), block), "{:?}", block);
+            assert!(world.can_place((4, 10, 4), block), "{:?}", block);
+        }
+    }
+
+    #[test]
+    fn test_fire() {
+        use crate::camera::Camera;
+        use crate::input::InputHandler;
+        use crate::mesh::block_light;
+        use crate::scripting::BlockScripts;
+        use crate::time::Weather;
+        use crate::ui::UiRenderer;
+
+        // Stone floor at y 9 with a wooden pillar on it
+        let mut world = World::new(1);
+        world.chunks.insert((0, 0), Chunk::new(0, 0));
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_block_at(x, 9, z, BlockType::Stone);
+            }
+        }
+        for y in 10..14 {
+            world.set_block_at(4, y, 4, BlockType::Wood);
+        }
+        let count = |world: &World, block: BlockType| world.chunks.get(&(0, 0)).unwrap().blocks.iter().filter(|b| **b == block).count();
+
+        // Flint and steel sets fire to the air in front of the pillar
+        let mut camera = Camera::new(1.0);
+        camera.position = Vec3::new(6.5, 11.5, 4.5);
+        camera.yaw = std::f32::consts::PI;
+        world.inventory.toolbar[0] = Some(crate::inventory::ItemStack::new(Item::FlintAndSteel, 1));
+        world.inventory.selected_slot = 0;
+        let mut input = InputHandler::new();
+        input.right_mouse_pressed = true;
+        let player = Player::new(Vec3::new(8.5, 10.0, 8.5));
+        let interaction = input.handle_block_interaction(&camera, &mut world, &UiRenderer::new(), &player, &BlockScripts::new(), &[], 0.0);
+        assert!(interaction.changed_block);
+        assert_eq!(world.get_block_at(5, 11, 4), Some(BlockType::Fire));
+        assert_eq!(world.inventory.selected_item(), Some(Item::FlintAndSteel));
+
+        // Fire glows, but nothing collides with it and breaking it gives nothing
+        assert!(!BlockType::Fire.is_full_cube() && BlockType::Fire.drop().is_none());
+        let lights = [([5, 11, 4], BlockType::Fire.light_emission())];
+        assert_eq!(block_light(&lights, [5, 11, 4]), 1.0);
+        assert!(block_light(&lights, [5, 12, 4]) > block_light(&lights, [5, 14, 4]));
+        assert_eq!(block_light(&lights, [5, 11, 30]), 0.0);
+        let mut mesh = MeshBuilder::new();
+        mesh.build_chunk_mesh(&ChunkSnapshot::capture(&world.chunks, 0, 0).unwrap(), &AtlasLayout::default());
+        assert!(mesh.vertices.iter().any(|v| v.light == 1.0));
+        assert!(mesh.vertices.iter().any(|v| v.light == 0.0));
+
+        // The pillar burns away and the fires die out
+        let mut scripts = BlockScripts::new();
+        for _ in 0..4000 {
+            world.tick_blocks(&mut scripts);
+        }
+        assert_eq!(count(&world, BlockType::Wood), 0);
+        assert_eq!(count(&world, BlockType::Fire), 0);
+        assert_eq!(count(&world, BlockType::Stone), 256, "Stone doesn't burn");
+
+        // Rain puts out a fire under the open sky, but not one under a roof
+        world.time.set_weather(Weather::Rain);
+        world.set_block_at(1, 10, 1, BlockType::Fire);
+        world.set_block_at(8, 10, 8, BlockType::Planks);
+        world.set_block_at(8, 11, 8, BlockType::Fire);
+        world.set_block_at(8, 12, 8, BlockType::Stone);
+        for _ in 0..50 {
+            world.tick_blocks(&mut scripts);
+        }
+        assert_eq!(world.get_block_at(1, 10, 1), Some(BlockType::Air));
+        assert_eq!(world.get_block_at(8, 11, 8), Some(BlockType::Fire));
+    }
+
+    #[test]
+    fn test_smooth_lighting() {
+        use crate::mesh::smooth_light;
+
+        // A corner averages the four cells touching it in front of the face,
+        // so it dims smoothly away from the light
+        let lights = [([0, 1, 0], 14)];
+        let up = [0, 1, 0];
+        let near = smooth_light(&lights, [0, 0, 0], up, [0.0, 1.0, 0.0]);
+        let far = smooth_light(&lights, [1, 0, 1], up, [2.0, 1.0, 2.0]);
+        assert_eq!(near, (14.0 + 13.0 + 13.0 + 12.0) / 4.0 / 14.0);
+        assert_eq!(far, (12.0 + 11.0 + 11.0 + 10.0) / 4.0 / 14.0);
+
+        // Faces meeting at a corner agree on its light, so there are no seams
+        assert_eq!(
+            smooth_light(&lights, [0, 0, 0], up, [1.0, 1.0, 1.0]),
+            smooth_light(&lights, [1, 0, 1], up, [1.0, 1.0, 1.0])
+        );
+        assert_eq!(smooth_light(&[], [0, 0, 0], up, [0.0, 1.0, 0.0]), 0.0);
+
+        // A lit face is brighter at some corners than at others
+        let mut world = World::new(1);
+        let mut chunk = Chunk::new(0, 0);
+        for x in 0..16 {
+            for z in 0..16 {
+                chunk.set_block(x, 10, z, BlockType::Planks);
+            }
+        }
+        chunk.set_block(8, 11, 8, BlockType::Fire);
+        world.chunks.insert((0, 0), chunk);
+        let mut mesh = MeshBuilder::new();
+        mesh.build_chunk_mesh(&ChunkSnapshot::capture(&world.chunks, 0, 0).unwrap(), &AtlasLayout::default());
+        assert!(mesh.vertices.chunks(4).any(|face| face.iter().any(|v| v.light != face[0].light)));
+
+        // Flat lighting lights each face evenly, still brighter near the fire
+        let mut flat = MeshBuilder::new();
+        flat.smooth_lighting = false;
+        flat.build_chunk_mesh(&ChunkSnapshot::capture(&world.chunks, 0, 0).unwrap(), &AtlasLayout::default());
+        assert!(flat.vertices.chunks(4).all(|face| face.iter().all(|v| v.light == face[0].light)));
+        assert!(flat.vertices.iter().any(|v| v.light > 0.0 && v.light < 1.0));
+    }
+
+    #[test]
+    fn test_graphics_presets() {
+        use crate::config::{GameConfig, GraphicsPreset};
+        use crate::menu::{Menu, MenuAction, MenuScreen, MenuWidget, OptionId};
+        use crate::resource_pack::TextureAtlas;
+        use crate::weather::Particles;
+
+        // A new config is Medium; the Graphics button steps through the presets
+        let mut config = GameConfig::default();
+        assert_eq!(config.graphics_preset(), GraphicsPreset::Medium);
+        OptionId::Graphics.toggle(&mut config);
+        assert_eq!(config.graphics_preset(), GraphicsPreset::High);
+        assert!(config.mipmapping && config.view_distance == 10);
+        OptionId::Graphics.toggle(&mut config);
+        OptionId::Graphics.toggle(&mut config);
+        assert_eq!(config.graphics_preset(), GraphicsPreset::Low);
+        assert_eq!((config.fog, config.smooth_lighting, config.particles), (false, false, Particles::Minimal));
+        assert_eq!(OptionId::Graphics.label(&config), "Graphics: Low");
+
+        // Changing any setting of a preset makes it Custom, and the next click starts over at Low
+        OptionId::Particles.toggle(&mut config);
+        assert_eq!(config.particles, Particles::All);
+        assert_eq!(OptionId::Graphics.label(&config), "Graphics: Custom");
+        config.set_graphics_preset(GraphicsPreset::Ultra);
+        OptionId::ViewDistance.set_value(&mut config, 12.0);
+        assert_eq!(config.graphics_preset(), GraphicsPreset::Custom);
+        OptionId::Graphics.toggle(&mut config);
+        assert_eq!(config.graphics_preset(), GraphicsPreset::Low);
+
+        // All options fit on the smallest UI, and a click on one changes it
+        let mut menu = Menu::new();
+        menu.open(MenuScreen::Options);
+        let widgets = menu.widgets();
+        assert_eq!(widgets.len(), OptionId::ALL.len() + 1);
+        for (widget, (x, y, w, h)) in &widgets {
+            assert!(*x >= -160.0 && x + w <= 160.0 && *y >= -120.0 && y + h <= crate::menu::TITLE_Y, "{:?} is off screen", widget);
+        }
+        let (_, (x, y, _, _)) = widgets.iter().find(|(w, _)| *w == MenuWidget::Option(OptionId::Mipmapping)).unwrap();
+        assert_eq!(menu.press(x + 1.0, y + 1.0, &mut config), MenuAction::ConfigChanged { save: true });
+        assert!(config.mipmapping);
+
+        // Mipmaps halve down to a pixel per tile, leaving transparent texels out of the average
+        let mut image = image::RgbaImage::from_pixel(32, 16, image::Rgba([200, 100, 0, 255]));
+        for x in 0..16 {
+            image.put_pixel(x, 0, image::Rgba([0, 0, 0, 0]));
+        }
+        let atlas = TextureAtlas { image, layout: AtlasLayout::default() };
+        let mipmaps = atlas.mipmaps();
+        let sizes: Vec<_> = mipmaps.iter().map(|level| level.dimensions()).collect();
+        assert_eq!(sizes, vec![(16, 8), (8, 4), (4, 2), (2, 1)]);
+        assert_eq!(mipmaps[0].get_pixel(0, 0).0, [200, 100, 0, 127]);
+        assert_eq!(mipmaps[3].get_pixel(1, 0).0, [200, 100, 0, 255]);
+    }
+
+    #[test]
+    fn test_gameplay_events() {
+        use crate::camera::Camera;
+        use crate::events::GameEvent;
+        use crate::input::InputHandler;
+        use crate::physics::{GameMode, Player};
+        use crate::scripting::BlockScripts;
+        use crate::ui::UiRenderer;
+
+        let generator = WorldGenerator::new(1);
+        let mut world = World::new(1);
+        world.load_or_generate_chunk(0, 0, &generator);
+        assert!(world.events.drain().contains(&GameEvent::ChunkLoaded { x: 0, z: 0 }));
+        assert!(world.events.drain().is_empty(), "Draining empties the queue");
+
+        // Breaking a block with a pickaxe announces the block and the pickup
+        for x in 0..16 {
+            for z in 0..16 {
+                for y in 0..crate::chunk::CHUNK_HEIGHT as i32 {
+                    world.set_block_at(x, y, z, if y < 10 { BlockType::Stone } else { BlockType::Air });
+                }
+            }
+        }
+        let mut camera = Camera::new(1.0);
+        camera.position = glam::Vec3::new(4.5, 12.0, 4.5);
+        camera.pitch = -std::f32::consts::FRAC_PI_2 + 0.01;
+        let mut player = Player::new(glam::Vec3::new(8.5, 10.0, 8.5));
+        player.game_mode = GameMode::Survival;
+        let mut input = InputHandler::new();
+        world.inventory.selected_slot = 8;
+        assert_eq!(world.inventory.selected_item(), Some(Item::WoodenPickaxe));
+        input.left_mouse_pressed = true;
+        world.events.drain();
+        input.handle_block_interaction(&camera, &mut world, &UiRenderer::new(), &player, &BlockScripts::new(), &[], 10.0);
+        assert_eq!(
+            world.events.drain(),
+            vec![
+                GameEvent::ItemPickedUp { item: Item::Block(BlockType::Stone), count: 1 },
+                GameEvent::BlockBroken { pos: (4, 9, 4), block: BlockType::Stone },
+            ]
+        );
+
+        // A player in the way is hit instead of the block behind it
+        let other = Player::new(glam::Vec3::new(4.5, 8.0, 4.5)).bounding_box;
+        input.left_mouse_pressed = true;
+        let interaction =
+            input.handle_block_interaction(&camera, &mut world, &UiRenderer::new(), &player, &BlockScripts::new(), &[(3, other)], 0.0);
+        assert_eq!(interaction.attacked, Some(3));
+        assert!(!interaction.changed_block && world.events.drain().is_empty());
+    }
+
+    #[test]
+    fn test_drop_items() {
+        use crate::inventory::ItemStack;
+        use crate::item_entity::{update_items, ItemEntity, PICKUP_DELAY};
+
+        let mut world = World::new(1);
+        let mut chunk = Chunk::new(0, 0);
+        for x in 0..16 {
+            for z in 0..16 {
+                chunk.set_block(x, 9, z, BlockType::Stone);
+            }
+        }
+        world.chunks.insert((0, 0), chunk);
+        world.inventory = crate::inventory::Inventory::new();
+        world.inventory.toolbar[0] = Some(ItemStack::new(BlockType::Sand, 10));
+
+        // Q takes one, Ctrl+Q the rest of the stack
+        assert_eq!(world.inventory.take_selected(false), Some(ItemStack::new(BlockType::Sand, 1)));
+        assert_eq!(world.inventory.take_selected(true), Some(ItemStack::new(BlockType::Sand, 9)));
+        assert!(world.inventory.toolbar[0].is_none());
+        assert_eq!(world.inventory.take_selected(true), None);
+
+        // Thrown along +x, the stack lands on the floor further on
+        let eye = Vec3::new(3.5, 11.6, 8.5);
+        world.items.push(ItemEntity::thrown(ItemStack::new(BlockType::Sand, 9), eye, Vec3::X));
+        let far_away = Aabb::from_position(Vec3::new(100.0, 100.0, 100.0), 0.3, 1.8);
+        for _ in 0..120 {
+            assert!(!update_items(&mut world, Some(&far_away), 1.0 / 60.0));
+        }
+        let item = world.items[0];
+        assert!((item.position.y - 10.0).abs() < 1e-3, "rests on the floor, at {}", item.position.y);
+        assert!(item.position.x > eye.x + 1.0);
+
+        // Not picked up right after being thrown, even standing on it
+        let on_top = Aabb::from_position(item.position, 0.3, 1.8);
+        world.items[0].age = 0.0;
+        assert!(!update_items(&mut world, Some(&on_top), 0.1));
+        assert!(world.items[0].position.distance(item.position) < 0.01);
+
+        // Once the delay is over it drifts to a player a block away and goes into the inventory
+        let near = Aabb::from_position(item.position + Vec3::X * 1.2, 0.3, 1.8);
+        world.items[0].age = PICKUP_DELAY;
+        let mut frames = 0;
+        while !update_items(&mut world, Some(&near), 1.0 / 60.0) {
+            assert!(world.items[0].position.x > item.position.x);
+            frames += 1;
+            assert!(frames < 60, "not picked up");
+        }
+        assert!(world.items.is_empty());
+        assert_eq!(world.inventory.count_item(BlockType::Sand), 9);
+    }
+
+    #[test]
+    fn test_boats() {
+        use crate::boat::Boat;
+        use crate::item::Item;
+
+        // A pond three blocks deep, up to y = 10, with a bank as high from x = 10
+        let mut world = World::new(1);
+        let mut chunk = Chunk::new(0, 0);
+        for x in 0..16 {
+            for z in 0..16 {
+                chunk.set_block(x, 5, z, BlockType::Stone);
+                for y in 6..10 {
+                    chunk.set_block(x, y, z, if x < 10 { BlockType::Water } else { BlockType::Stone });
+                }
+            }
+        }
+        world.chunks.insert((0, 0), chunk);
+        let step = 1.0 / 60.0;
+
+        // Put down on the water, it settles floating
+        let mut boat = Boat::placed(Vec3::new(3.5, 10.0, 8.5), 0.0);
+        for _ in 0..180 {
+            boat.update(&world, step);
+        }
+        assert!(boat.position.y > 9.6 && boat.position.y < 9.9, "floats at {}", boat.position.y);
+        assert!(boat.in_water(&world));
+
+        // Paddling picks up speed along the heading, and the boat glides on once it stops
+        for _ in 0..30 {
+            boat.paddle(&world, 1.0, 0.0, step);
+            boat.update(&world, step);
+        }
+        assert!(boat.velocity.x > 2.0 && boat.velocity.z.abs() < 1e-3);
+        for _ in 0..30 {
+            boat.update(&world, step);
+        }
+        assert!(boat.velocity.x > 1.0, "keeps its momentum");
+
+        // Turning changes the heading, not the position
+        let mut turned = boat;
+        turned.paddle(&world, 0.0, 1.0, 0.5);
+        assert!(turned.yaw > 0.5 && turned.velocity == boat.velocity);
+
+        // Rowed into the bank it runs aground, and then stops dead
+        while boat.position.x < 11.0 {
+            boat.paddle(&world, 1.0, 0.0, step);
+            boat.update(&world, step);
+            assert!(boat.position.x < 20.0);
+        }
+        for _ in 0..60 {
+            boat.update(&world, step);
+        }
+        assert!((boat.position.y - 10.0).abs() < 0.02, "rests on the bank, at {}", boat.position.y);
+        assert!(boat.velocity.x.abs() < 0.01 && !boat.in_water(&world));
+
+        // The rider sits in it and stands on it when getting off
+        assert_eq!(boat.seat(), boat.position);
+        assert!(boat.dismount_position().y > boat.position.y);
+
+        // Boats are saved with the world, the one being ridden too, which
+        // loads empty; and worlds from before them still load
+        world.boats.push(boat);
+        world.boats.push(Boat { ridden: true, ..Boat::placed(Vec3::new(3.5, 12.0, 3.5), 0.0) });
+        assert_eq!(crate::boat::ridden(&world), Some(1));
+        // Left to its rider, it doesn't fall on its own
+        crate::boat::update_boats(&mut world, step);
+        assert_eq!(world.boats[1].position.y, 12.0);
+        let storage = MemoryStorage::new();
+        world.save(&storage, "boats.dat").unwrap();
+        let loaded = World::load(&storage, "boats.dat").unwrap();
+        assert_eq!(loaded.boats, vec![world.boats[0], Boat { ridden: false, ..world.boats[1] }]);
+        assert_eq!(crate::boat::ridden(&loaded), None);
+        // Save format 8 ended after the structures
+        let old = (&world.chunks, world.seed, &world.inventory, &world.block_entities, &world.time, world.spawn, world.border, world.terrain, &world.structures);
+        let mut data = b"RCWORLD\0".to_vec();
+        data.extend(8u32.to_le_bytes());
+        data.extend(bincode::serialize(&old).unwrap());
+        storage.write(std::path::Path::new("old.dat"), &data).unwrap();
+        let old = World::load(&storage, "old.dat").unwrap();
+        assert!(old.boats.is_empty() && old.seed == 1 && old.chunks.contains_key(&(0, 0)));
+
+        assert_eq!(Item::from_name("boat"), Some(Item::Boat));
+        assert_eq!(Item::Boat.max_stack_size(), 1);
+        assert_eq!(Item::try_from(u32::from(Item::Boat)), Ok(Item::Boat));
+    }
+
+    #[test]
+    fn test_fishing() {
+        use crate::fishing::{Bobber, BITE_TIME};
+        use crate::loot::{LootTables, FISHING};
+
+        // A pond up to y = 10, with a stone bank from x = 10
+        let mut world = World::new(1);
+        let mut chunk = Chunk::new(0, 0);
+        for x in 0..16 {
+            for z in 0..16 {
+                chunk.set_block(x, 5, z, BlockType::Stone);
+                for y in 6..10 {
+                    chunk.set_block(x, y, z, if x < 10 { BlockType::Water } else { BlockType::Stone });
+                }
+            }
+        }
+        world.chunks.insert((0, 0), chunk);
+        let step = 1.0 / 60.0;
+
+        // Cast from the bank, the bobber lands on the water and floats
+        let eye = Vec3::new(12.5, 11.6, 8.5);
+        let mut bobber = Bobber::cast(eye, Vec3::new(-1.0, 0.3, 0.0), 7);
+        let mut seconds = 0.0;
+        while !bobber.update(&world, step) {
+            seconds += step;
+            assert!(seconds < 40.0, "nothing bit");
+            if seconds > 3.0 && seconds < 4.0 {
+                assert!(bobber.in_water(&world) && !bobber.is_biting());
+                assert!(bobber.position.y > 9.6 && bobber.position.y < 10.1, "floats at {}", bobber.position.y);
+                assert!(bobber.position.x < 10.0 && bobber.position.x > 0.0);
+            }
+        }
+        // Something bites after a wait, pulling the bobber under for a moment
+        assert!(seconds > 5.0, "bit after {} seconds", seconds);
+        assert!(bobber.is_biting());
+        let surface = bobber.position.y;
+        for _ in 0..10 {
+            bobber.update(&world, step);
+        }
+        assert!(bobber.position.y < surface);
+
+        // Reeling in now catches a roll of the fishing table, flung towards the player
+        let loot = LootTables::builtin();
+        let table = loot.get(FISHING).unwrap();
+        let catch = bobber.reel_in(table, 3, eye);
+        assert_eq!(catch.len(), 1);
+        assert!(table.entries.iter().any(|entry| entry.item == catch[0].stack.item));
+        assert!(catch[0].velocity.x > 0.0 && catch[0].velocity.y > 0.0);
+        assert!(catch.iter().all(|item| item.age >= crate::item_entity::PICKUP_DELAY));
+        // Most catches are fish
+        let fish = (0..100).filter(|&seed| table.roll(seed)[0].item == Item::Fish).count();
+        assert!(fish > 50, "{} fish in 100", fish);
+
+        // A missed bite is over after a second, and reeling in then catches nothing
+        for _ in 0..(BITE_TIME / step) as usize + 1 {
+            bobber.update(&world, step);
+        }
+        assert!(!bobber.is_biting());
+        assert!(bobber.reel_in(table, 3, eye).is_empty());
+
+        // On land, nothing bites
+        let mut landed = Bobber::cast(Vec3::new(13.5, 11.6, 8.5), Vec3::X, 7);
+        for _ in 0..(40.0 / step) as usize {
+            assert!(!landed.update(&world, step));
+        }
+
+        assert_eq!(Item::from_name("fishing_rod"), Some(Item::FishingRod));
+        assert_eq!(Item::FishingRod.max_stack_size(), 1);
+        assert_eq!(Item::try_from(u32::from(Item::Fish)), Ok(Item::Fish));
+    }
+
+    #[test]
+    fn test_villagers() {
+        use crate::inventory::{Inventory, ItemStack};
+        use crate::structures::StructureKind;
+        use crate::villager::{self, Trade, Villager};
+
+        // A trade takes the whole price and gives the result, or does nothing
+        let trade = Trade { cost: ItemStack::new(Item::Emerald, 2), result: ItemStack::new(Item::Compass, 1) };
+        let mut inventory = Inventory::new();
+        inventory.add_item(Item::Emerald, 3);
+        assert!(trade.affordable(&inventory));
+        assert!(trade.make(&mut inventory));
+        assert_eq!(inventory.count_item(Item::Emerald), 1);
+        assert_eq!(inventory.count_item(Item::Compass), 1);
+        assert!(!trade.affordable(&inventory));
+        assert!(!trade.make(&mut inventory));
+        assert_eq!(inventory.count_item(Item::Emerald), 1);
+        // Nor when there is no room for the result
+        let mut full = Inventory::new();
+        while full.add_item(BlockType::Stone, 64) {}
+        full.remove_item(BlockType::Stone, 64);
+        full.add_item(Item::Emerald, 3);
+        assert!(!trade.make(&mut full));
+        assert_eq!(full.count_item(Item::Emerald), 3);
+
+        // A villager buys one thing for an emerald and sells two others
+        for seed in 0..20 {
+            let offers = villager::offers(seed);
+            assert_eq!(offers.len(), 3);
+            assert_eq!(offers[0].result.item, Item::Emerald);
+            assert!(offers[1..].iter().all(|offer| offer.cost.item == Item::Emerald));
+            assert_ne!(offers[1], offers[2]);
+            assert_eq!(offers, villager::offers(seed));
+        }
+
+        // On a stone floor a villager strolls around its home...
+        let mut world = World::new(1);
+        let mut chunk = Chunk::new(0, 0);
+        for x in 0..16 {
+            for z in 0..16 {
+                chunk.set_block(x, 9, z, BlockType::Stone);
+            }
+        }
+        world.chunks.insert((0, 0), chunk);
+        let home = Vec3::new(8.5, 10.0, 8.5);
+        world.villagers.push(Villager::new(home, 5));
+        let step = 1.0 / 30.0;
+        let mut moved = false;
+        for _ in 0..(60.0 / step) as usize {
+            villager::update_villagers(&mut world, &[], step);
+            let position = world.villagers[0].position;
+            assert!((position.y - 10.0).abs() < 0.01, "stands on the floor, at {}", position.y);
+            assert!(position.distance(home) < 4.5, "strayed to {}", position);
+            moved |= position.distance(home) > 0.5;
+        }
+        assert!(moved);
+
+        // ...and stops to look at a player who comes close
+        let eye = world.villagers[0].position + Vec3::new(2.0, 1.6, 0.0);
+        let position = world.villagers[0].position;
+        for _ in 0..30 {
+            villager::update_villagers(&mut world, &[eye], step);
+        }
+        assert_eq!(world.villagers[0].position.x, position.x);
+        assert!(world.villagers[0].yaw.abs() < 0.01, "looks along +x, not {}", world.villagers[0].yaw);
+
+        // Away from loaded chunks it waits rather than falling
+        world.villagers.push(Villager::new(Vec3::new(40.5, 10.0, 8.5), 6));
+        villager::update_villagers(&mut world, &[], 1.0);
+        assert_eq!(world.villagers[1].position.y, 10.0);
+
+        // Some chunks get a village: houses on a path with a villager at each door
+        let seed = 7;
+        let mut world = World::new(seed);
+        let generator = WorldGenerator::new(seed);
+        let mut x = 0;
+        while !world.structures.iter().any(|s| s.kind == StructureKind::Village) {
+            assert!(x < 40, "no village in {} rows of chunks", x);
+            for z in -8..8 {
+                world.load_or_generate_chunk(x, z, &generator);
+            }
+            x += 1;
+        }
+        let village = *world.structures.iter().find(|s| s.kind == StructureKind::Village).unwrap();
+        let (min_x, top, min_z) = village.min;
+        let villages = world.structures.iter().filter(|s| s.kind == StructureKind::Village).count();
+        assert_eq!(world.villagers.len(), 2 * villages);
+        for villager in &world.villagers[..2] {
+            let feet = villager.position.floor().as_ivec3();
+            assert_eq!(feet.y, top + 1);
+            assert_eq!(world.get_block_at(feet.x, top, feet.z), Some(BlockType::Gravel));
+            assert_eq!(world.get_block_at(feet.x, top + 1, feet.z - 2), Some(BlockType::Door));
+        }
+        assert_eq!(world.get_block_at(min_x, top + 1, min_z), Some(BlockType::Wood));
+        assert!(world.near_village(min_x, min_z, 0));
+        assert!(!world.near_village(min_x - 3, min_z, 2));
+
+        // Villagers are saved with the world, trades and all
+        let storage = MemoryStorage::new();
+        world.save(&storage, "villagers.dat").unwrap();
+        assert_eq!(World::load(&storage, "villagers.dat").unwrap().villagers, world.villagers);
+
+        assert_eq!(Item::from_name("emerald"), Some(Item::Emerald));
+        assert_eq!(Item::try_from(u32::from(Item::Emerald)), Ok(Item::Emerald));
+    }
+
+    #[test]
+    fn test_circuits() {
+        use crate::circuit;
+        use crate::scripting::BlockScripts;
+
+        // Stone floor at y 9 across two chunks, a lever at x 0 and a line of
+        // wire from x 1 to x 20 with a lamp at each end of the lever's reach
+        let mut world = World::new(1);
+        world.chunks.insert((0, 0), Chunk::new(0, 0));
+        world.chunks.insert((1, 0), Chunk::new(1, 0));
+        for x in 0..32 {
+            for z in 0..16 {
+                world.set_block_at(x, 9, z, BlockType::Stone);
+            }
+        }
+        world.set_block_at(0, 10, 4, BlockType::Lever);
+        for x in 1..=20 {
+            world.set_block_at(x, 10, 4, BlockType::Wire);
+        }
+        world.set_block_at(15, 10, 5, BlockType::Lamp);
+        world.set_block_at(16, 10, 5, BlockType::Lamp);
+        // Two doors stacked next to the wire
+        world.set_block_at(3, 10, 3, BlockType::Door);
+        world.set_block_at(3, 11, 3, BlockType::Door);
+        let mut scripts = BlockScripts::new();
+        let settle = |world: &mut World, scripts: &mut BlockScripts| {
+            for _ in 0..5 {
+                world.tick_blocks(scripts);
+            }
+        };
+        settle(&mut world, &mut scripts);
+        assert_eq!(world.get_block_at(1, 10, 4), Some(BlockType::Wire));
+        assert_eq!(world.get_block_at(15, 10, 5), Some(BlockType::Lamp));
+
+        // Flipping the lever powers 15 blocks of wire, lighting the lamp in
+        // reach and opening both doors
+        assert!(circuit::switch(&mut world, (0, 10, 4)));
+        settle(&mut world, &mut scripts);
+        assert_eq!(circuit::wire_levels(&world, (1, 10, 4))[&(1, 10, 4)], circuit::MAX_POWER);
+        assert_eq!(world.get_block_at(15, 10, 4), Some(BlockType::PoweredWire));
+        assert_eq!(world.get_block_at(16, 10, 4), Some(BlockType::Wire));
+        assert_eq!(world.get_block_at(15, 10, 5), Some(BlockType::LitLamp));
+        assert_eq!(world.get_block_at(16, 10, 5), Some(BlockType::Lamp));
+        assert_eq!(BlockType::LitLamp.light_emission(), crate::block::MAX_LIGHT);
+        assert_eq!(world.get_block_at(3, 10, 3), Some(BlockType::OpenDoor));
+        assert_eq!(world.get_block_at(3, 11, 3), Some(BlockType::OpenDoor));
+        assert!(!BlockType::OpenDoor.is_full_cube() && BlockType::Door.is_full_cube());
+
+        // Cutting the wire cuts the power beyond the gap
+        world.set_block_at(10, 10, 4, BlockType::Air);
+        settle(&mut world, &mut scripts);
+        assert_eq!(world.get_block_at(9, 10, 4), Some(BlockType::PoweredWire));
+        assert_eq!(world.get_block_at(11, 10, 4), Some(BlockType::Wire));
+        assert_eq!(world.get_block_at(15, 10, 5), Some(BlockType::Lamp));
+
+        // A door can still be shut by hand, and switching the lever off puts
+        // everything back; powered blocks break into the plain ones
+        assert!(circuit::switch(&mut world, (3, 11, 3)));
+        assert_eq!(world.get_block_at(3, 10, 3), Some(BlockType::Door));
+        assert!(circuit::switch(&mut world, (0, 10, 4)));
+        settle(&mut world, &mut scripts);
+        assert_eq!(world.get_block_at(1, 10, 4), Some(BlockType::Wire));
+        assert_eq!(world.get_block_at(3, 11, 3), Some(BlockType::Door));
+        assert_eq!(BlockType::PoweredWire.drop(), Some(BlockType::Wire));
+        assert!(!circuit::switch(&mut world, (0, 9, 4)));
+    }
+
+    #[test]
+    fn test_plates_and_buttons() {
+        use crate::circuit;
+        use crate::physics::Aabb;
+        use crate::scripting::BlockScripts;
+
+        // A pressure plate next to a lamp and a button next to a door, on a
+        // stone floor at y 9
+        let mut world = World::new(1);
+        world.chunks.insert((0, 0), Chunk::new(0, 0));
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_block_at(x, 9, z, BlockType::Stone);
+            }
+        }
+        world.set_block_at(2, 10, 2, BlockType::PressurePlate);
+        world.set_block_at(3, 10, 2, BlockType::Lamp);
+        world.set_block_at(8, 10, 8, BlockType::Button);
+        world.set_block_at(8, 10, 9, BlockType::Door);
+        let mut scripts = BlockScripts::new();
+        let run = |world: &mut World, scripts: &mut BlockScripts, ticks: u32| {
+            for _ in 0..ticks {
+                world.tick_blocks(scripts);
+            }
+        };
+        run(&mut world, &mut scripts, 5);
+        assert_eq!(world.get_block_at(3, 10, 2), Some(BlockType::Lamp));
+
+        // Standing on the plate presses it and lights the lamp for as long as
+        // the player stays; a body next to it doesn't count
+        world.bodies = vec![Player::new(Vec3::new(4.5, 10.0, 2.5)).bounding_box];
+        run(&mut world, &mut scripts, 5);
+        assert_eq!(world.get_block_at(2, 10, 2), Some(BlockType::PressurePlate));
+        world.bodies = vec![Player::new(Vec3::new(2.5, 10.0, 2.5)).bounding_box];
+        run(&mut world, &mut scripts, 40);
+        assert_eq!(world.get_block_at(2, 10, 2), Some(BlockType::PressedPlate));
+        assert_eq!(world.get_block_at(3, 10, 2), Some(BlockType::LitLamp));
+        assert!(!BlockType::PressedPlate.is_full_cube(), "Walked over, not into");
+
+        // Stepping off lets it up again; an item on it presses it too
+        world.bodies.clear();
+        run(&mut world, &mut scripts, 15);
+        assert_eq!(world.get_block_at(2, 10, 2), Some(BlockType::PressurePlate));
+        assert_eq!(world.get_block_at(3, 10, 2), Some(BlockType::Lamp));
+        world.bodies = vec![Aabb::from_position(Vec3::new(2.2, 10.0, 2.8), 0.125, 0.25)];
+        run(&mut world, &mut scripts, 3);
+        assert_eq!(world.get_block_at(3, 10, 2), Some(BlockType::LitLamp));
+
+        // A button opens the door for a second; pressing it again holds it
+        assert!(BlockType::Button.is_switch());
+        assert!(circuit::switch(&mut world, (8, 10, 8)));
+        run(&mut world, &mut scripts, 2);
+        assert_eq!(world.get_block_at(8, 10, 9), Some(BlockType::OpenDoor));
+        run(&mut world, &mut scripts, 10);
+        circuit::switch(&mut world, (8, 10, 8));
+        run(&mut world, &mut scripts, 15);
+        assert_eq!(world.get_block_at(8, 10, 8), Some(BlockType::PressedButton));
+        assert_eq!(world.get_block_at(8, 10, 9), Some(BlockType::OpenDoor));
+        run(&mut world, &mut scripts, 10);
+        assert_eq!(world.get_block_at(8, 10, 8), Some(BlockType::Button));
+        assert_eq!(world.get_block_at(8, 10, 9), Some(BlockType::Door));
+        assert_eq!(BlockType::PressedButton.drop(), Some(BlockType::Button));
+    }
+
+    #[test]
+    fn test_explosions() {
+        use crate::scripting::{BlockHook, BlockScripts};
+
+        // Stone up to y 9, an obsidian wall at x 6 with glass behind it and a
+        // row of glass on the other side
+        let mut world = World::new(0);
+        world.chunks.insert((0, 0), Chunk::new(0, 0));
+        for x in 0..16 {
+            for z in 0..16 {
+                for y in 0..10 {
+                    world.set_block_at(x, y, z, BlockType::Stone);
+                }
+                for y in 10..14 {
+                    world.set_block_at(6, y, z, BlockType::Obsidian);
+                }
+            }
+        }
+        world.set_block_at(5, 10, 8, BlockType::Glass);
+        for x in 9..16 {
+            world.set_block_at(x, 10, 8, BlockType::Glass);
+        }
+        assert!(BlockType::Obsidian.blast_resistance() > BlockType::Stone.blast_resistance());
+        assert!(BlockType::Stone.blast_resistance() > BlockType::Glass.blast_resistance());
+
+        // A scripted TNT block blows itself up with the power of 4
+        let mut scripts = BlockScripts::new();
+        scripts.add(BlockType::Sand, r#"fn on_interact(x, y, z) { set_block(x, y, z, "air"); explode(x, y, z, 4); }"#).unwrap();
+        world.set_block_at(8, 10, 8, BlockType::Sand);
+        assert!(scripts.run(BlockHook::Interact, BlockType::Sand, &mut world, (8, 10, 8)));
+
+        // Glass nearby and the stone right under it go, the obsidian holds
+        // and shields the glass behind it, and the far glass is out of reach
+        assert_eq!(world.get_block_at(11, 10, 8), Some(BlockType::Air));
+        assert_eq!(world.get_block_at(8, 9, 8), Some(BlockType::Air));
+        assert_eq!(world.get_block_at(8, 7, 8), Some(BlockType::Stone));
+        assert_eq!(world.get_block_at(6, 10, 8), Some(BlockType::Obsidian));
+        assert_eq!(world.get_block_at(5, 10, 8), Some(BlockType::Glass));
+        assert_eq!(world.get_block_at(15, 10, 8), Some(BlockType::Glass));
+        assert_eq!(crate::explosion::explode(&mut world, Vec3::new(8.5, 30.5, 8.5), 4.0), 0, "Nothing in reach");
+    }
+
+    #[test]
+    fn test_mining() {
+        use crate::camera::Camera;
+        use crate::input::InputHandler;
+        use crate::mining::{break_time, can_harvest, BREAK_DELAY};
+        use crate::physics::GameMode;
+        use crate::scripting::BlockScripts;
+        use crate::ui::UiRenderer;
+
+        // Stone takes longer than dirt, and a pickaxe to drop
+        let (hand, pickaxe, shovel) = (None, Some(Item::WoodenPickaxe), Some(Item::WoodenShovel));
+        assert!(break_time(BlockType::Stone, hand, 0) > break_time(BlockType::Dirt, hand, 0));
+        assert!(break_time(BlockType::Stone, pickaxe, 0) < break_time(BlockType::Stone, shovel, 0));
+        assert!(break_time(BlockType::Stone, Some(Item::StonePickaxe), 0) < break_time(BlockType::Stone, pickaxe, 0));
+        assert!(break_time(BlockType::Dirt, shovel, 0) < break_time(BlockType::Dirt, pickaxe, 0));
+        assert_eq!(break_time(BlockType::Mushroom, hand, 0), 0.0);
+        assert!(!can_harvest(BlockType::Stone, hand) && !can_harvest(BlockType::Stone, shovel));
+        assert!(can_harvest(BlockType::Stone, pickaxe) && can_harvest(BlockType::Furnace, Some(Item::StonePickaxe)));
+        assert!(can_harvest(BlockType::Dirt, hand) && can_harvest(BlockType::Wood, pickaxe));
+        for tool in [Item::WoodenPickaxe, Item::StonePickaxe, Item::WoodenShovel, Item::WoodenAxe] {
+            assert!(tool.block().is_none() && tool.max_stack_size() == 1);
+            assert_eq!(Item::from_name(tool.name()), Some(tool));
+        }
+
+        let mut world = World::new(12345);
+        world.chunks.insert((0, 0), Chunk::new(0, 0));
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_block_at(x, 9, z, BlockType::Stone);
+            }
+        }
+        world.set_block_at(4, 9, 4, BlockType::Dirt);
+        world.set_block_at(4, 8, 4, BlockType::Stone);
+        let mut camera = Camera::new(1.0);
+        camera.position = Vec3::new(4.5, 12.0, 4.5);
+        camera.pitch = -std::f32::consts::FRAC_PI_2 + 0.01;
+        let mut player = Player::new(Vec3::new(8.5, 10.0, 8.5));
+        player.game_mode = GameMode::Survival;
+        world.inventory = crate::inventory::Inventory::new();
+        let mut input = InputHandler::new();
+        let mut hold = |world: &mut World, delta_time: f32| {
+            input.left_mouse_held = true;
+            input.handle_block_interaction(&camera, world, &UiRenderer::new(), &player, &BlockScripts::new(), &[], delta_time);
+            input.mining.progress()
+        };
+
+        // Holding the button fills up the progress until the dirt breaks and drops
+        let dirt = break_time(BlockType::Dirt, None, 0);
+        assert_eq!(hold(&mut world, dirt / 2.0), Some(0.5));
+        assert_eq!(world.get_block_at(4, 9, 4), Some(BlockType::Dirt));
+        assert_eq!(hold(&mut world, dirt / 2.0), None);
+        assert_eq!(world.get_block_at(4, 9, 4), Some(BlockType::Air));
+        assert_eq!(world.inventory.count_item(BlockType::Dirt), 1);
+
+        // The stone under it waits out the delay, then breaks by hand but drops nothing
+        assert_eq!(hold(&mut world, BREAK_DELAY), None);
+        assert!(hold(&mut world, break_time(BlockType::Stone, None, 0) - 0.01).is_some_and(|progress| progress > 0.99));
+        assert_eq!(world.get_block_at(4, 8, 4), Some(BlockType::Stone));
+        hold(&mut world, 0.02);
+        assert_eq!(world.get_block_at(4, 8, 4), Some(BlockType::Air));
+        assert_eq!(world.inventory.count_item(BlockType::Stone), 0);
+    }
+
+    #[test]
+    fn test_enchanting() {
+        use crate::block_entity::BlockEntity;
+        use crate::enchanting::{self, Enchantment, Enchantments, Offer};
+        use crate::experience::Experience;
+        use crate::inventory::{Inventory, ItemStack, SlotId};
+        use crate::mining::break_time;
+
+        // Points add up to levels, each a little further than the last
+        let mut experience = Experience::new();
+        assert_eq!(experience.add(6), 0);
+        assert!((experience.progress() - 6.0 / 7.0).abs() < 1e-6);
+        assert_eq!(experience.add(1 + 9 + 11 + 3), 3);
+        assert_eq!(experience, Experience { level: 3, points: 3 });
+        assert!(!experience.spend(4));
+        assert!(experience.spend(2));
+        assert_eq!(experience, Experience { level: 1, points: 3 });
+
+        // Tools are offered the next level of each enchantment, dearer the higher it is
+        let mut pickaxe = ItemStack::new(Item::WoodenPickaxe, 1);
+        assert!(enchanting::offers(&ItemStack::new(BlockType::Stone, 1)).is_empty());
+        let offers = enchanting::offers(&pickaxe);
+        assert_eq!(offers.len(), 2);
+        assert_eq!(offers[0], Offer { enchantment: Enchantment::Efficiency, level: 1, cost: 1 });
+        assert_eq!(offers[0].title(), "Efficiency I");
+        let mut experience = Experience { level: 4, points: 0 };
+        assert!(enchanting::enchant(&mut pickaxe, offers[0], &mut experience));
+        assert_eq!(experience.level, 3);
+        assert_eq!(pickaxe.enchantments.level(Enchantment::Efficiency), 1);
+        // An offer that is gone or too dear changes nothing
+        assert!(!enchanting::enchant(&mut pickaxe, offers[0], &mut experience));
+        let second = enchanting::offers(&pickaxe)[0];
+        assert_eq!((second.level, second.cost), (2, 3));
+        let mut poor = Experience { level: 2, points: 5 };
+        assert!(!enchanting::enchant(&mut pickaxe, second, &mut poor));
+        assert_eq!(poor.level, 2);
+        assert!(enchanting::enchant(&mut pickaxe, second, &mut experience));
+        assert_eq!(experience, Experience::new());
+        pickaxe.enchantments.set(Enchantment::Efficiency, 9);
+        assert_eq!(pickaxe.enchantments.level(Enchantment::Efficiency), 3);
+        assert_eq!(enchanting::offers(&pickaxe).len(), 1);
+
+        // Efficiency speeds up the blocks the tool is made for, and only those
+        let tool = Some(Item::WoodenPickaxe);
+        assert!(break_time(BlockType::Stone, tool, 1) < break_time(BlockType::Stone, tool, 0));
+        assert!(break_time(BlockType::Stone, tool, 3) < break_time(BlockType::Stone, tool, 1));
+        assert_eq!(break_time(BlockType::Dirt, tool, 3), break_time(BlockType::Dirt, tool, 0));
+
+        // Every use wears a tool until it is worn out; unbreaking spares some
+        let mut shovel = ItemStack::new(Item::WoodenShovel, 1);
+        let durability = Item::WoodenShovel.durability().unwrap();
+        for roll in 0..durability - 1 {
+            assert!(!shovel.wear(roll));
+        }
+        assert!(shovel.durability_left().unwrap() < 0.05);
+        assert!(shovel.wear(0));
+        assert_eq!(ItemStack::new(BlockType::Stone, 1).durability_left(), None);
+        let mut unbreaking = ItemStack::new(Item::WoodenShovel, 1);
+        unbreaking.enchantments.set(Enchantment::Unbreaking, 3);
+        for roll in 0..400 {
+            unbreaking.wear(roll);
+        }
+        assert_eq!(unbreaking.wear, 100);
+        // A worn out tool in hand is gone
+        let mut inventory = Inventory::new();
+        inventory.insert(shovel.with_count(1));
+        inventory.toolbar[0].as_mut().unwrap().wear = durability - 1;
+        assert!(inventory.wear_selected(0));
+        assert!(inventory.get_selected_item().is_none());
+
+        // Wear and enchantments stay with the stack, saved and moved
+        let data = bincode::serialize(&pickaxe).unwrap();
+        assert_eq!(data.len(), 8, "Saved in the same space as before");
+        assert_eq!(bincode::deserialize::<ItemStack>(&data).unwrap(), pickaxe);
+        let mut inventory = Inventory::new();
+        assert_eq!(inventory.insert(unbreaking), 0);
+        assert_eq!(inventory.toolbar[0], Some(unbreaking));
+        assert_eq!(Enchantments::from_bits(pickaxe.enchantments.to_bits()), pickaxe.enchantments);
+
+        // The table only takes tools
+        let mut table = BlockEntity::for_block(BlockType::EnchantingTable).unwrap();
+        inventory.add_item(BlockType::Stone, 5);
+        inventory.selected_slot = 1;
+        table.quick_move_from(&mut inventory, SlotId::Toolbar(1));
+        assert_eq!(inventory.count_item(BlockType::Stone), 5);
+        table.quick_move_from(&mut inventory, SlotId::Toolbar(0));
+        assert_eq!(table.slots()[0], Some(unbreaking));
+        assert_eq!(BlockType::from_name("enchanting_table"), Some(BlockType::EnchantingTable));
+
+        // Experience is saved with the world
+        let mut world = World::new(3);
+        world.experience = Experience { level: 5, points: 2 };
+        let storage = MemoryStorage::new();
+        world.save(&storage, "experience.dat").unwrap();
+        assert_eq!(World::load(&storage, "experience.dat").unwrap().experience, world.experience);
+    }
+
+    #[test]
+    fn test_block_scripts() {
+        use crate::scripting::{BlockHook, BlockScripts};
+
+        let mut world = World::new(0);
+        world.chunks.insert((0, 0), Chunk::new(0, 0));
+        let mut scripts = BlockScripts::new();
+        assert!(scripts.add(BlockType::Glass, "fn on_place(x, y, z) {").is_err());
+
+        scripts
+            .add(
+                BlockType::Glass,
+                r#"
+                fn on_place(x, y, z) {
+                    if get_block(x, y - 1, z) == "sand" { set_block(x, y + 1, z, "glass"); }
+                }
+                fn on_break(x, y, z) { loop {} }
+                "#,
+            )
+            .unwrap();
+        assert!(scripts.handles(BlockType::Glass, BlockHook::Place));
+        assert!(!scripts.handles(BlockType::Glass, BlockHook::Interact));
+        assert!(!scripts.handles(BlockType::Stone, BlockHook::Place));
+
+        world.set_block_at(2, 0, 2, BlockType::Sand);
+        world.set_block_at(2, 1, 2, BlockType::Glass);
+        assert!(scripts.run(BlockHook::Place, BlockType::Glass, &mut world, (2, 1, 2)));
+        assert_eq!(world.get_block_at(2, 2, 2), Some(BlockType::Glass));
+        // A runaway script is stopped instead of hanging the game
+        assert!(scripts.run(BlockHook::Break, BlockType::Glass, &mut world, (2, 1, 2)));
+        assert!(!scripts.run(BlockHook::Interact, BlockType::Glass, &mut world, (2, 1, 2)));
+        assert_eq!(world.get_block_at(2, 0, 2), Some(BlockType::Sand), "The world survives the call");
+
+        // A random tick script replaces the built-in grass behavior
+        scripts.add(BlockType::Grass, "fn on_random_tick(x, y, z) {}").unwrap();
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_block_at(x, 10, z, BlockType::Grass);
+                world.set_block_at(x, 11, z, BlockType::Stone);
+            }
+        }
+        for _ in 0..20 * 600 {
+            world.tick_blocks(&mut scripts);
+        }
+        assert_eq!(world.get_block_at(0, 10, 0), Some(BlockType::Grass));
+    }
+
+    #[test]
+    fn test_font_glyphs() {
+        use crate::font::glyph_pixel;
+
+        // 'I' has a full vertical bar in its middle column
+        assert!((0..7).all(|row| glyph_pixel('I', 2, row)));
+        // Space is empty
+        assert!((0..5).all(|col| (0..7).all(|row| !glyph_pixel(' ', col, row))));
+        // Unsupported characters fall back to '?'
+        assert_eq!(glyph_pixel('\u{2603}', 0, 1), glyph_pixel('?', 0, 1));
+    }
+
+    #[test]
+    fn test_camera_zoom() {
+        use crate::camera::{Camera, SPRINT_FOV_FACTOR, ZOOM_FOV_DEGREES};
+
+        let mut camera = Camera::new(1.0);
+        camera.set_base_fov(90.0);
+        assert_eq!(camera.look_sensitivity_scale(), 1.0);
+
+        // Zooming eases towards the zoom FOV instead of snapping
+        camera.update_fov(true, false, 0.016, true);
+        assert!(camera.fov < 90.0_f32.to_radians() && camera.fov > ZOOM_FOV_DEGREES.to_radians());
+
+        for _ in 0..120 {
+            camera.update_fov(true, false, 0.016, true);
+        }
+        assert!((camera.fov - ZOOM_FOV_DEGREES.to_radians()).abs() < 0.01);
+        assert!(camera.look_sensitivity_scale() < 0.3, "Sensitivity is scaled down while zoomed");
+
+        // Releasing returns to the base FOV
+        for _ in 0..120 {
+            camera.update_fov(false, false, 0.016, true);
+        }
+        assert!((camera.fov - 90.0_f32.to_radians()).abs() < 0.01);
+
+        // Sprinting widens the FOV without speeding up mouse look; zoom wins
+        for _ in 0..120 {
+            camera.update_fov(false, true, 0.016, true);
+        }
+        assert!((camera.fov - 90.0_f32.to_radians() * SPRINT_FOV_FACTOR).abs() < 0.01);
+        assert_eq!(camera.look_sensitivity_scale(), 1.0);
+        camera.update_fov(true, true, 0.016, false);
+        assert_eq!(camera.fov, ZOOM_FOV_DEGREES.to_radians(), "Without smoothing the FOV snaps");
+    }
+
+    #[test]
+    fn test_camera_smoothing() {
+        use crate::camera::Camera;
+        use crate::camera_smoothing::{CameraSmoothing, PHYSICS_STEP};
+
+        let mut camera = Camera::new(1.0);
+        let mut smoothing = CameraSmoothing::new(&camera);
+
+        // Without smoothing the camera follows the input exactly
+        smoothing.look(&mut camera, 0.0, 0.016, |camera| camera.yaw += 1.0);
+        assert_eq!(camera.yaw, 1.0);
+
+        // With smoothing it eases towards where the mouse points
+        smoothing.look(&mut camera, 0.1, 0.016, |camera| camera.yaw += 1.0);
+        assert!(camera.yaw > 1.0 && camera.yaw < 2.0);
+        for _ in 0..120 {
+            smoothing.look(&mut camera, 0.1, 0.016, |_| {});
+        }
+        assert!((camera.yaw - 2.0).abs() < 0.001);
+
+        // Physics runs in whole steps; the rest carries over to the next frame
+        assert_eq!(smoothing.advance(PHYSICS_STEP * 2.5), 2);
+        assert_eq!(smoothing.advance(PHYSICS_STEP * 0.25), 0);
+        assert_eq!(smoothing.advance(100.0), 10, "A long stall doesn't run every missed step");
+
+        // The eye is drawn between the last two steps, by how far into the next step the frame is
+        smoothing.advance(PHYSICS_STEP * 1.5);
+        let (before, after) = (Vec3::new(0.0, 10.0, 0.0), Vec3::new(1.0, 10.0, 0.0));
+        smoothing.record_step(before, after);
+        assert!((smoothing.eye_position(after, true) - Vec3::new(0.5, 10.0, 0.0)).length() < 0.01);
+        assert_eq!(smoothing.eye_position(after, false), after);
+        // A teleport between steps isn't interpolated
+        let teleported = Vec3::new(100.0, 50.0, 0.0);
+        assert_eq!(smoothing.eye_position(teleported, true), teleported);
+    }
+
+    #[test]
+    fn test_view_bob() {
+        use crate::audio::Footsteps;
+        use crate::camera_smoothing::ViewBob;
+
+        // The walk cycle restarts every two footsteps, each falling on a half
+        let mut footsteps = Footsteps::new();
+        assert!(footsteps.update(0.85, Some(BlockType::Grass), false).is_none());
+        assert!((footsteps.walk_cycle() - 0.25).abs() < 1e-4);
+        assert!(footsteps.update(0.85, Some(BlockType::Grass), false).is_some());
+        assert!((footsteps.walk_cycle() - 0.5).abs() < 1e-4);
+
+        // Standing still the eye doesn't move; walking it rises between footsteps
+        let mut bob = ViewBob::new();
+        bob.update(0.016);
+        assert_eq!(bob.offset(Vec3::X), Vec3::ZERO);
+        for _ in 0..60 {
+            bob.step(Some(0.25));
+            bob.update(0.016);
+        }
+        assert!(bob.offset(Vec3::X).y > 0.04);
+        bob.step(Some(0.5));
+        assert!(bob.offset(Vec3::X).y.abs() < 1e-4, "Lowest at a footstep");
+        assert!(bob.offset(Vec3::X).x < 0.0, "Swaying towards the stepping foot");
+        // It fades out once the player stops
+        for _ in 0..60 {
+            bob.step(None);
+            bob.update(0.016);
+        }
+        assert!(bob.offset(Vec3::X).length() < 0.001);
+
+        // Landing dips the eye, more after a longer fall, and it comes back up
+        bob.land(8.0);
+        let jump = bob.offset(Vec3::X).y;
+        let mut fall = ViewBob::new();
+        fall.land(20.0);
+        assert!(jump < 0.0 && fall.offset(Vec3::X).y < jump);
+        for _ in 0..60 {
+            bob.update(0.016);
+        }
+        assert!(bob.offset(Vec3::X).y > -0.001);
+    }
+
+    #[test]
+    fn test_mouse_look_settings() {
+        use crate::camera::Camera;
+        use crate::config::GameConfig;
+        use crate::input::InputHandler;
+
+        let config = GameConfig {
+            sensitivity: 0.01,
+            sensitivity_x_scale: 2.0,
+            sensitivity_y_scale: 0.5,
+            invert_y: true,
+            ..Default::default()
+        };
+        let mut input = InputHandler::new();
+        input.apply_config(&config);
+
+        let mut camera = Camera::new(1.0);
+        input.process_mouse_motion((10.0, 10.0));
+        input.update_camera(&mut camera);
+
+        assert!((camera.yaw - 0.2).abs() < 1e-5, "Horizontal uses the X scale");
+        assert!((camera.pitch - 0.05).abs() < 1e-5, "Inverted Y pitches up when moving the mouse down");
+
+        // Motion while another window has focus doesn't turn the camera, and
+        // neither does what piled up before focus was lost
+        input.process_mouse_motion((10.0, 0.0));
+        input.set_focused(false);
+        input.process_mouse_motion((500.0, 500.0));
+        input.set_focused(true);
+        input.update_camera(&mut camera);
+        assert!((camera.yaw - 0.2).abs() < 1e-5);
+        input.process_mouse_motion((10.0, 0.0));
+        input.update_camera(&mut camera);
+        assert!((camera.yaw - 0.4).abs() < 1e-5, "Focus back turns again");
+    }
+
+    #[test]
+    fn test_mouse_acceleration_curve() {
+        use crate::input::acceleration_factor;
+
+        assert_eq!(acceleration_factor(50.0, 0.0), 1.0, "Zero acceleration is linear");
+        assert!(acceleration_factor(5.0, 1.0) < acceleration_factor(50.0, 1.0), "Fast moves are amplified more");
+        assert!(acceleration_factor(10_000.0, 1.0) <= 4.0, "Factor is capped");
+    }
+
+    #[test]
+    fn test_config_loads_older_files() {
+        use crate::config::GameConfig;
+
+        // A config written before the mouse settings existed
+        let storage = MemoryStorage::new();
+        let old = r#"{"sensitivity": 0.02, "walk_speed": 4.3, "view_distance": 4, "fov": 80.0, "show_debug": false}"#;
+        storage.write(std::path::Path::new("config.json"), old.as_bytes()).unwrap();
+        let config = GameConfig::load(&storage, "config.json");
+        assert_eq!(config.sensitivity, 0.02, "Existing values are kept");
+        assert_eq!(config.view_distance, 4);
+        assert!(config.raw_input, "New fields use their defaults");
+        assert!(!config.invert_y);
+    }
+
+    #[test]
+    fn test_config_watcher() {
+        use crate::config::{ConfigWatcher, GameConfig};
+        use crate::storage::FileStorage;
+        use std::time::{Duration, Instant};
+
+        let dir = std::env::temp_dir().join(format!("rustcraft_config_watch_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+        let path = path.to_str().unwrap();
+        GameConfig::default().save(&FileStorage, path).unwrap();
+        let watcher = ConfigWatcher::new(path).unwrap();
+        assert!(!watcher.changed());
+        let changed_within = |watcher: &ConfigWatcher, wait: Duration| {
+            let started = Instant::now();
+            while started.elapsed() < wait {
+                if watcher.changed() {
+                    return true;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            false
+        };
+
+        // Other files in the directory don't count; an edit is noticed and reads back
+        std::fs::write(dir.join("stats.json"), "{}").unwrap();
+        assert!(!changed_within(&watcher, Duration::from_millis(200)));
+        let edited = GameConfig { fov: 90.0, fog: false, ..GameConfig::default() };
+        edited.save(&FileStorage, path).unwrap();
+        assert!(changed_within(&watcher, Duration::from_secs(5)));
+        assert!(GameConfig::try_load(&FileStorage, path).unwrap() == edited);
+
+        // A half-written file is an error rather than the defaults
+        std::fs::write(path, r#"{"fov": 9"#).unwrap();
+        assert!(GameConfig::try_load(&FileStorage, path).is_err());
+        assert!(GameConfig::load(&FileStorage, path) == GameConfig::default());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,210 @@
+//! Fixed-rate game simulation, independent of the frame rate. Daylight,
+//! furnaces, survival stats and block updates advance in whole ticks.
+
+use crate::block::BlockType;
+use crate::chunk::{CHUNK_HEIGHT, CHUNK_SIZE};
+use crate::circuit;
+use crate::fire;
+use crate::scripting::{BlockHook, BlockScripts};
+use crate::time::TICKS_PER_SECOND;
+use crate::world::World;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Seconds of game time per tick
+pub const TICK_LENGTH: f32 = 1.0 / TICKS_PER_SECOND;
+/// Most ticks run in one frame; after a long stall the game falls behind
+/// instead of freezing while it catches up
+const MAX_TICKS_PER_FRAME: u32 = 10;
+/// Random block ticks per loaded chunk and game tick
+const RANDOM_TICKS_PER_CHUNK: u32 = 12;
+/// Ticks a falling block waits before dropping by one block
+pub const FALL_DELAY: u64 = 2;
+/// Ticks a block update due outside the simulation distance waits before it
+/// checks again whether a player has come near
+const FROZEN_DELAY: u64 = 20;
+
+/// Turns real frame times into a whole number of game ticks
+pub struct TickClock {
+    /// Seconds not yet consumed by a tick
+    accumulator: f32,
+}
+
+impl TickClock {
+    pub fn new() -> Self {
+        Self { accumulator: 0.0 }
+    }
+
+    /// Add `delta_time` seconds. Returns the number of ticks to run now.
+    pub fn advance(&mut self, delta_time: f32) -> u32 {
+        self.accumulator += delta_time;
+        let ticks = (self.accumulator / TICK_LENGTH).floor() as u32;
+        if ticks > MAX_TICKS_PER_FRAME {
+            self.accumulator = 0.0;
+            return MAX_TICKS_PER_FRAME;
+        }
+        self.accumulator -= ticks as f32 * TICK_LENGTH;
+        ticks
+    }
+}
+
+impl Default for TickClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+type ScheduledTick = (u64, (i32, i32, i32));
+
+/// Block tick state of a world: the tick counter, block updates scheduled for
+/// a later tick, and the random number stream for random ticks
+pub struct BlockTicks {
+    now: u64,
+    /// (due tick, block position), earliest first
+    scheduled: BinaryHeap<Reverse<ScheduledTick>>,
+    rng: u32,
+}
+
+impl BlockTicks {
+    pub fn new() -> Self {
+        Self {
+            now: 0,
+            scheduled: BinaryHeap::new(),
+            rng: 0x9E37_79B9,
+        }
+    }
+
+    /// Update the block at `pos` `delay` ticks from now
+    pub fn schedule(&mut self, pos: (i32, i32, i32), delay: u64) {
+        self.scheduled.push(Reverse((self.now + delay.max(1), pos)));
+    }
+
+    /// Whether an update of the block at `pos` is still to come
+    pub fn is_scheduled(&self, pos: (i32, i32, i32)) -> bool {
+        self.scheduled.iter().any(|Reverse((_, scheduled))| *scheduled == pos)
+    }
+
+    /// Advance the counter and take the updates due this tick, oldest first
+    fn start_tick(&mut self) -> Vec<(i32, i32, i32)> {
+        self.now += 1;
+        let mut due = Vec::new();
+        while let Some(Reverse((tick, pos))) = self.scheduled.peek().copied() {
+            if tick > self.now {
+                break;
+            }
+            self.scheduled.pop();
+            due.push(pos);
+        }
+        due
+    }
+
+    pub fn next_random(&mut self) -> u32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        self.rng
+    }
+}
+
+impl Default for BlockTicks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl World {
+    /// Run one tick of block updates: pressure plates being stood on, due
+    /// scheduled updates, then random ticks in every simulated chunk. Updates
+    /// due outside the simulation distance wait. Returns true if any block
+    /// changed.
+    pub fn tick_blocks(&mut self, scripts: &mut BlockScripts) -> bool {
+        let mut changed = circuit::press_plates(self);
+        for pos in self.block_ticks.start_tick() {
+            if self.simulates_block(pos.0, pos.2) {
+                changed |= scheduled_tick(self, pos);
+            } else {
+                self.block_ticks.schedule(pos, FROZEN_DELAY);
+            }
+        }
+
+        // Visit chunks in a fixed order so the same world and ticks give the same result
+        let mut chunks: Vec<(i32, i32)> = self.chunks.keys().filter(|&(x, z)| self.simulates_chunk(x, z)).collect();
+        chunks.sort_unstable();
+        for (chunk_x, chunk_z) in chunks {
+            for _ in 0..RANDOM_TICKS_PER_CHUNK {
+                let r = self.block_ticks.next_random();
+                let x = chunk_x * CHUNK_SIZE as i32 + (r % CHUNK_SIZE as u32) as i32;
+                let z = chunk_z * CHUNK_SIZE as i32 + ((r >> 8) % CHUNK_SIZE as u32) as i32;
+                let y = ((r >> 16) % CHUNK_HEIGHT as u32) as i32;
+                let pos = (x, y, z);
+                // A block script replaces the built-in behavior
+                let scripted = self
+                    .get_block_at(x, y, z)
+                    .is_some_and(|block| scripts.run(BlockHook::RandomTick, block, self, pos));
+                if scripted {
+                    changed = true;
+                } else {
+                    changed |= random_tick(self, pos);
+                }
+            }
+        }
+        changed
+    }
+}
+
+/// Delayed update of a block, e.g. sand dropping into the gap below it, a
+/// fire spreading or a circuit following a lever
+fn scheduled_tick(world: &mut World, pos: (i32, i32, i32)) -> bool {
+    // The block may be gone, cut out of a circuit
+    circuit::update(world, pos) | block_tick(world, pos)
+}
+
+fn block_tick(world: &mut World, (x, y, z): (i32, i32, i32)) -> bool {
+    let Some(block) = world.get_block_at(x, y, z) else {
+        return false;
+    };
+    if block == BlockType::Fire {
+        return fire::fire_tick(world, (x, y, z));
+    }
+    if matches!(block, BlockType::PressedPlate | BlockType::PressedButton) {
+        return circuit::release(world, (x, y, z));
+    }
+    if !block.has_gravity() || y == 0 {
+        return false;
+    }
+    // Fall through air and sink through water, swapping places with it
+    match world.get_block_at(x, y - 1, z) {
+        Some(below @ (BlockType::Air | BlockType::Water)) => {
+            world.set_block_at(x, y, z, below);
+            world.set_block_at(x, y - 1, z, block);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Slow ambient change of a randomly picked block: grass dies under cover and
+/// spreads onto nearby dirt in the open. Fires of a loaded world, which have
+/// no updates scheduled, start burning again, and its pressed plates and
+/// buttons pop back up.
+fn random_tick(world: &mut World, (x, y, z): (i32, i32, i32)) -> bool {
+    let above = world.get_block_at(x, y + 1, z).unwrap_or(BlockType::Air);
+    match world.get_block_at(x, y, z) {
+        Some(BlockType::Grass) if !above.is_transparent() => world.set_block_at(x, y, z, BlockType::Dirt),
+        Some(BlockType::Dirt) if above == BlockType::Air => {
+            let near_grass = (-1..=1).any(|dx| {
+                (-1..=1).any(|dy| (-1..=1).any(|dz| world.get_block_at(x + dx, y + dy, z + dz) == Some(BlockType::Grass)))
+            });
+            near_grass && world.set_block_at(x, y, z, BlockType::Grass)
+        }
+        Some(BlockType::Fire) => {
+            world.block_ticks.schedule((x, y, z), fire::FIRE_DELAY);
+            false
+        }
+        Some(BlockType::PressedPlate | BlockType::PressedButton) => {
+            world.block_ticks.schedule((x, y, z), circuit::CIRCUIT_DELAY);
+            false
+        }
+        _ => false,
+    }
+}
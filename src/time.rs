@@ -0,0 +1,234 @@
+use crate::gamerules::{GameRules, DEFAULT_DAY_LENGTH};
+use serde::{Deserialize, Serialize};
+
+/// Length of a full day/night cycle in game ticks
+pub const TICKS_PER_DAY: u32 = 24000;
+/// Game ticks per real-time second
+pub const TICKS_PER_SECOND: f32 = 20.0;
+
+// Named times of day (0 = sunrise, like Minecraft)
+pub const TIME_DAY: u32 = 1000;
+pub const TIME_NOON: u32 = 6000;
+pub const TIME_NIGHT: u32 = 13000;
+pub const TIME_MIDNIGHT: u32 = 18000;
+
+const DAY_SKY: [f32; 3] = [0.53, 0.81, 0.92];
+const NIGHT_SKY: [f32; 3] = [0.02, 0.02, 0.08];
+
+/// Shortest and longest spell of clear weather, in ticks
+const CLEAR_TICKS: (u32, u32) = (TICKS_PER_DAY / 2, TICKS_PER_DAY * 2);
+/// Shortest and longest rain or thunderstorm, in ticks
+const RAIN_TICKS: (u32, u32) = (TICKS_PER_DAY / 8, TICKS_PER_DAY / 2);
+/// One in this many rain spells is a thunderstorm
+const THUNDER_CHANCE: u32 = 3;
+/// Fastest timelapse; a whole day then passes in 1.2 seconds
+pub const MAX_TIMELAPSE: u32 = 1000;
+/// Most ticks `/time add` moves the clock at once, a million days
+pub const MAX_ADD_TICKS: u64 = 1_000_000 * TICKS_PER_DAY as u64;
+
+/// What the sky is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Weather {
+    Clear,
+    Rain,
+    Thunder,
+}
+
+impl Weather {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "clear" => Some(Self::Clear),
+            "rain" => Some(Self::Rain),
+            "thunder" => Some(Self::Thunder),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Clear => "clear",
+            Self::Rain => "rain",
+            Self::Thunder => "thunder",
+        }
+    }
+
+    /// How much of the daylight gets through the clouds
+    pub fn light_factor(self) -> f32 {
+        match self {
+            Self::Clear => 1.0,
+            Self::Rain => 0.7,
+            Self::Thunder => 0.45,
+        }
+    }
+}
+
+/// Game time since the world was created and the weather. Saved with the
+/// world; the server advances both every tick, network clients only the clock.
+#[derive(Serialize, Deserialize)]
+pub struct WorldTime {
+    /// Ticks since the world was created, starting on the morning of day 0
+    ticks: u64,
+    weather: Weather,
+    /// Ticks until the weather changes
+    weather_ticks: u32,
+    rng: u32,
+    /// How many times faster than normal the clock and the weather run, for
+    /// timelapse captures; not saved, a loaded world runs at normal speed
+    #[serde(skip)]
+    timelapse: Option<u32>,
+    /// Part of a game tick the clock has run on past `ticks`, when days are
+    /// longer than normal
+    #[serde(skip)]
+    progress: f32,
+}
+
+impl WorldTime {
+    pub fn new() -> Self {
+        Self {
+            ticks: TIME_DAY as u64,
+            weather: Weather::Clear,
+            weather_ticks: CLEAR_TICKS.0,
+            rng: 0x2545_F491,
+            timelapse: None,
+            progress: 0.0,
+        }
+    }
+
+    /// Advance the clock by one game tick, or by the timelapse factor, sped
+    /// up or slowed down to the day length of the game rules. The clock
+    /// stands still while the rules stop the daytime cycle.
+    pub fn tick(&mut self, rules: &GameRules) {
+        if !rules.do_daytime_cycle() {
+            return;
+        }
+        self.progress += self.speed() as f32 * DEFAULT_DAY_LENGTH as f32 / rules.day_length_seconds() as f32;
+        let whole = self.progress.floor();
+        self.ticks = self.ticks.saturating_add(whole as u64);
+        self.progress -= whole;
+    }
+
+    /// Count down to the next change of weather, by one tick or by the
+    /// timelapse factor. Only the server runs this. Returns true if the
+    /// weather changed.
+    pub fn tick_weather(&mut self) -> bool {
+        self.weather_ticks = self.weather_ticks.saturating_sub(self.speed());
+        if self.weather_ticks > 0 {
+            return false;
+        }
+        let thunder = self.next_random().is_multiple_of(THUNDER_CHANCE);
+        let next = match self.weather {
+            Weather::Clear if thunder => Weather::Thunder,
+            Weather::Clear => Weather::Rain,
+            Weather::Rain | Weather::Thunder => Weather::Clear,
+        };
+        self.set_weather(next);
+        true
+    }
+
+    /// Game time in ticks since the world was created
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Set the game time, e.g. to the server's on a network client
+    pub fn set_ticks(&mut self, ticks: u64) {
+        self.ticks = ticks;
+    }
+
+    /// Move the clock forward by `ticks`, into the following days if need
+    /// be; it stops at the end of time rather than wrapping around
+    pub fn add_ticks(&mut self, ticks: u64) {
+        self.ticks = self.ticks.saturating_add(ticks);
+    }
+
+    /// The timelapse factor, None at normal speed
+    pub fn timelapse(&self) -> Option<u32> {
+        self.timelapse
+    }
+
+    /// Run time `factor` times faster, up to `MAX_TIMELAPSE`; None or 1 for
+    /// normal speed
+    pub fn set_timelapse(&mut self, factor: Option<u32>) {
+        self.timelapse = factor.filter(|&factor| factor > 1).map(|factor| factor.min(MAX_TIMELAPSE));
+    }
+
+    /// Game ticks that pass each tick
+    fn speed(&self) -> u32 {
+        self.timelapse.unwrap_or(1)
+    }
+
+    /// Days since the world was created
+    pub fn day(&self) -> u64 {
+        self.ticks / TICKS_PER_DAY as u64
+    }
+
+    pub fn time_of_day(&self) -> u32 {
+        (self.ticks % TICKS_PER_DAY as u64) as u32
+    }
+
+    /// Set the time within the current day
+    pub fn set_time_of_day(&mut self, ticks: u32) {
+        self.ticks = self.day() * TICKS_PER_DAY as u64 + (ticks % TICKS_PER_DAY) as u64;
+    }
+
+    pub fn weather(&self) -> Weather {
+        self.weather
+    }
+
+    /// Change the weather, lasting a random while
+    pub fn set_weather(&mut self, weather: Weather) {
+        let (min, max) = if weather == Weather::Clear { CLEAR_TICKS } else { RAIN_TICKS };
+        self.weather = weather;
+        self.weather_ticks = min + self.next_random() % (max - min);
+    }
+
+    /// Sun height factor in [0, 1]: 1 at noon, 0 at midnight
+    pub fn daylight(&self) -> f32 {
+        let angle = (self.time_of_day() as f32 - TIME_NOON as f32) / TICKS_PER_DAY as f32 * std::f32::consts::TAU;
+        (angle.cos() * 0.5 + 0.5).clamp(0.0, 1.0)
+    }
+
+    /// Light reaching the ground from the sky in [0, 1]: daylight dimmed by
+    /// clouds, for lighting and whatever spawns in the dark
+    pub fn sky_light(&self) -> f32 {
+        self.daylight() * self.weather.light_factor()
+    }
+
+    /// Sky clear color for the current time of day and weather
+    pub fn sky_color(&self) -> [f32; 3] {
+        sky_color(self.sky_light())
+    }
+
+    fn next_random(&mut self) -> u32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        self.rng
+    }
+}
+
+impl Default for WorldTime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sky clear color for a sky light level in [0, 1], from night to day
+pub fn sky_color(sky_light: f32) -> [f32; 3] {
+    [
+        NIGHT_SKY[0] + (DAY_SKY[0] - NIGHT_SKY[0]) * sky_light,
+        NIGHT_SKY[1] + (DAY_SKY[1] - NIGHT_SKY[1]) * sky_light,
+        NIGHT_SKY[2] + (DAY_SKY[2] - NIGHT_SKY[2]) * sky_light,
+    ]
+}
+
+/// Parse a time argument: a named time (day, noon, night, midnight) or a tick count
+pub fn parse_time(arg: &str) -> Option<u32> {
+    match arg {
+        "day" => Some(TIME_DAY),
+        "noon" => Some(TIME_NOON),
+        "night" => Some(TIME_NIGHT),
+        "midnight" => Some(TIME_MIDNIGHT),
+        _ => arg.parse::<u32>().ok().map(|t| t % TICKS_PER_DAY),
+    }
+}
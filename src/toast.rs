@@ -0,0 +1,71 @@
+use std::collections::VecDeque;
+
+/// Seconds a toast stays on screen, including the fade-out
+const TOAST_DURATION: f32 = 3.0;
+/// Seconds spent fading out at the end
+const FADE_DURATION: f32 = 0.5;
+/// Older toasts are dropped beyond this many
+const MAX_TOASTS: usize = 4;
+
+struct Toast {
+    text: String,
+    age: f32,
+}
+
+/// Queue of short on-screen notifications ("Inventory full", ...), newest first
+pub struct Toasts {
+    toasts: VecDeque<Toast>,
+    /// A toast was pushed since the last update
+    pushed: bool,
+}
+
+impl Toasts {
+    pub fn new() -> Self {
+        Self {
+            toasts: VecDeque::new(),
+            pushed: false,
+        }
+    }
+
+    /// Show a message. Repeating the newest message restarts it instead of stacking.
+    pub fn push(&mut self, text: impl Into<String>) {
+        let text = text.into();
+        self.pushed = true;
+        if let Some(newest) = self.toasts.front_mut() {
+            if newest.text == text {
+                newest.age = 0.0;
+                return;
+            }
+        }
+        self.toasts.push_front(Toast { text, age: 0.0 });
+        self.toasts.truncate(MAX_TOASTS);
+    }
+
+    /// Age the toasts by `delta_time` seconds. Returns true if the display changed
+    /// (new toast, one fading or expired) and the UI needs rebuilding.
+    pub fn update(&mut self, delta_time: f32) -> bool {
+        let pushed = std::mem::take(&mut self.pushed);
+        for toast in &mut self.toasts {
+            toast.age += delta_time;
+        }
+        let before = self.toasts.len();
+        self.toasts.retain(|toast| toast.age < TOAST_DURATION);
+        pushed
+            || self.toasts.len() != before
+            || self.toasts.iter().any(|toast| toast.age > TOAST_DURATION - FADE_DURATION)
+    }
+
+    /// Visible toasts, newest first, with their opacity in [0, 1]
+    pub fn visible(&self) -> impl Iterator<Item = (&str, f32)> {
+        self.toasts.iter().map(|toast| {
+            let alpha = ((TOAST_DURATION - toast.age) / FADE_DURATION).clamp(0.0, 1.0);
+            (toast.text.as_str(), alpha)
+        })
+    }
+}
+
+impl Default for Toasts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,6 +1,23 @@
 use bytemuck::{Pod, Zeroable};
 use crate::block::BlockType;
-use crate::inventory::Inventory;
+use crate::catalog::{Catalog, CATALOG_COLUMNS, CATALOG_ROWS};
+use crate::block_entity::{BlockEntity, ContainerKind, CHEST_SLOTS, FURNACE_FUEL, FURNACE_INPUT};
+use crate::config::GameConfig;
+use crate::console::Console;
+use crate::enchanting::{self, Enchantment};
+use crate::experience::Experience;
+use crate::font;
+use crate::icons::{self, Icon, IconPixel};
+use crate::inventory::{Inventory, ItemStack, SlotId};
+use crate::item::Item;
+use crate::menu::{Menu, MenuScreen, MenuWidget, OptionKind};
+use crate::minimap::{Minimap, MinimapMode, MAP_RADIUS};
+use crate::physics::GameMode;
+use crate::player_stats::PlayerStats;
+use crate::resource_pack::AtlasLayout;
+use crate::survival::{SurvivalStats, MAX_AIR};
+use crate::toast::Toasts;
+use crate::villager::Trade;
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
@@ -30,77 +47,620 @@ impl UiVertex {
     }
 }
 
+/// Smallest virtual screen the automatic GUI scale keeps (like Minecraft's 320x240)
+const MIN_VIRTUAL_WIDTH: u32 = 320;
+const MIN_VIRTUAL_HEIGHT: u32 = 240;
+
+/// Maps the UI's virtual pixels to the window.
+///
+/// UI geometry is built in virtual pixels with the origin at the screen center and
+/// y pointing up; one virtual pixel covers `scale` physical pixels, so the UI keeps
+/// its proportions at any resolution or aspect ratio. The UI shaders convert to NDC.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UiScale {
+    pub scale: f32,
+    /// Half the screen size in virtual pixels
+    pub half_width: f32,
+    pub half_height: f32,
+}
+
+impl UiScale {
+    /// `gui_scale` 0 means automatic; explicit scales are capped at the automatic one
+    pub fn new(width: u32, height: u32, gui_scale: u32) -> Self {
+        let auto = Self::auto_scale(width, height);
+        let scale = if gui_scale == 0 || gui_scale > auto { auto } else { gui_scale } as f32;
+        Self {
+            scale,
+            half_width: width.max(1) as f32 / scale / 2.0,
+            half_height: height.max(1) as f32 / scale / 2.0,
+        }
+    }
+
+    /// Largest integer scale that keeps at least 320x240 virtual pixels
+    pub fn auto_scale(width: u32, height: u32) -> u32 {
+        (width / MIN_VIRTUAL_WIDTH).min(height / MIN_VIRTUAL_HEIGHT).max(1)
+    }
+
+    /// Multiplier from virtual pixels to NDC, as passed to the UI shaders
+    pub fn ndc_scale(&self) -> [f32; 2] {
+        [1.0 / self.half_width, 1.0 / self.half_height]
+    }
+
+    /// Convert a point in NDC (e.g. the cursor) to virtual pixels
+    pub fn ndc_to_ui(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (x * self.half_width, y * self.half_height)
+    }
+}
+
+impl Default for UiScale {
+    fn default() -> Self {
+        Self::new(1280, 720, 0)
+    }
+}
+
+/// UI vertex sampling the block texture atlas, used for item thumbnails
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct UiIconVertex {
+    pub position: [f32; 2],
+    pub tex_coords: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl UiIconVertex {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<UiIconVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: (std::mem::size_of::<[f32; 2]>() * 2) as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// Accumulates textured isometric block thumbnails for one layer
+#[derive(Default)]
+pub struct UiIconMesh {
+    pub vertices: Vec<UiIconVertex>,
+    pub indices: Vec<u32>,
+}
+
+impl UiIconMesh {
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+    }
+
+    pub fn buffers(&self) -> (&[UiIconVertex], &[u32]) {
+        (&self.vertices, &self.indices)
+    }
+
+    /// Draw an isometric cube of `block` filling the rectangle at (x, y) (bottom-left)
+    pub fn add_block(&mut self, atlas: &AtlasLayout, block: BlockType, x: f32, y: f32, width: f32, height: f32) {
+        let Some(uv) = atlas.tile_uv(block) else {
+            return;
+        };
+        let color = block.get_color();
+
+        // Hexagon outline of the cube: top, upper/lower right, bottom, lower/upper left, plus the near corner
+        let cx = x + width / 2.0;
+        let (x0, x1) = (x, x + width);
+        let (y0, y1) = (y, y + height);
+        let quarter = height / 4.0;
+        let top = [cx, y1];
+        let upper_right = [x1, y1 - quarter];
+        let lower_right = [x1, y0 + quarter];
+        let bottom = [cx, y0];
+        let lower_left = [x0, y0 + quarter];
+        let upper_left = [x0, y1 - quarter];
+        let center = [cx, y1 - 2.0 * quarter];
+
+        // Same shading as the world mesh: lit top, darker sides
+        self.add_face([upper_left, center, upper_right, top], uv, color, 1.0);
+        self.add_face([lower_left, bottom, center, upper_left], uv, color, 0.8);
+        self.add_face([bottom, lower_right, upper_right, center], uv, color, 0.6);
+    }
+
+    /// Quad with corners in (bottom-left, bottom-right, top-right, top-left) texture order
+    fn add_face(&mut self, corners: [[f32; 2]; 4], uv: [f32; 4], base_color: [f32; 3], shade: f32) {
+        let color = [base_color[0] * shade, base_color[1] * shade, base_color[2] * shade, 1.0];
+        let [u0, v0, u1, v1] = uv;
+        let tex_coords = [[u0, v0], [u1, v0], [u1, v1], [u0, v1]];
+
+        let base_idx = self.vertices.len() as u32;
+        for (position, tex_coords) in corners.into_iter().zip(tex_coords) {
+            self.vertices.push(UiIconVertex {
+                position,
+                tex_coords,
+                color,
+            });
+        }
+        self.indices.extend_from_slice(&[
+            base_idx, base_idx + 1, base_idx + 2,
+            base_idx, base_idx + 2, base_idx + 3,
+        ]);
+    }
+}
+
+/// Accumulates colored UI quads for one layer (crosshair, toolbar, ...)
+#[derive(Default)]
+pub struct UiMesh {
+    pub vertices: Vec<UiVertex>,
+    pub indices: Vec<u32>,
+}
+
+impl UiMesh {
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+    }
+
+    pub fn buffers(&self) -> (&[UiVertex], &[u32]) {
+        (&self.vertices, &self.indices)
+    }
+
+    pub fn add_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, thickness: f32, color: [f32; 4]) {
+        let dx = x2 - x1;
+        let dy = y2 - y1;
+        let len = (dx * dx + dy * dy).sqrt();
+        
+        if len < 0.0001 {
+            return;
+        }
+
+        // Perpendicular vector
+        let px = -dy / len * thickness;
+        let py = dx / len * thickness;
+
+        self.add_quad(
+            [[x1 - px, y1 - py], [x1 + px, y1 + py], [x2 + px, y2 + py], [x2 - px, y2 - py]],
+            color,
+        );
+    }
+
+    pub fn add_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) {
+        self.add_quad([[x, y], [x + width, y], [x + width, y + height], [x, y + height]], color);
+    }
+
+    /// Arbitrary quad, corners in winding order (triangulated as a fan from the first)
+    pub fn add_quad(&mut self, corners: [[f32; 2]; 4], color: [f32; 4]) {
+        let base_idx = self.vertices.len() as u32;
+
+        for position in corners {
+            self.vertices.push(UiVertex { position, color });
+        }
+
+        self.indices.extend_from_slice(&[
+            base_idx, base_idx + 1, base_idx + 2,
+            base_idx, base_idx + 2, base_idx + 3,
+        ]);
+    }
+
+    pub fn add_rect_outline(&mut self, x: f32, y: f32, width: f32, height: f32, thickness: f32, color: [f32; 4]) {
+        // Top
+        self.add_rect(x, y + height - thickness, width, thickness, color);
+        // Bottom
+        self.add_rect(x, y, width, thickness, color);
+        // Left
+        self.add_rect(x, y, thickness, height, color);
+        // Right
+        self.add_rect(x + width - thickness, y, thickness, height, color);
+    }
+
+    /// Draw a HUD icon with (x, y) as its bottom-left corner. Fill pixels left of
+    /// `fill_fraction` of the width use `fill`, the rest are drawn as an empty slot.
+    pub fn add_icon(&mut self, icon: &Icon, x: f32, y: f32, pixel: f32, fill: [f32; 4], fill_fraction: f32) {
+        let outline = [0.08, 0.08, 0.08, 1.0];
+        let empty = [0.2, 0.2, 0.2, 0.8];
+        let highlight = [
+            (fill[0] + 0.4).min(1.0),
+            (fill[1] + 0.4).min(1.0),
+            (fill[2] + 0.4).min(1.0),
+            fill[3],
+        ];
+        let filled_cols = (fill_fraction * icons::ICON_SIZE as f32).round() as usize;
+
+        for row in 0..icons::ICON_SIZE {
+            for col in 0..icons::ICON_SIZE {
+                let color = match icons::icon_pixel(icon, col, row) {
+                    Some(IconPixel::Outline) => outline,
+                    Some(_) if col >= filled_cols => empty,
+                    Some(IconPixel::Fill) => fill,
+                    Some(IconPixel::Highlight) => highlight,
+                    None => continue,
+                };
+                let px = x + col as f32 * pixel;
+                let py = y + (icons::ICON_SIZE - 1 - row) as f32 * pixel;
+                self.add_rect(px, py, pixel, pixel, color);
+            }
+        }
+    }
+
+    /// Draw text with the built-in bitmap font. (x, y) is the bottom-left corner
+    /// and `pixel` the size of one font pixel in virtual pixels.
+    pub fn add_text(&mut self, text: &str, x: f32, y: f32, pixel: f32, color: [f32; 4]) {
+        for (i, c) in text.chars().enumerate() {
+            let char_x = x + (i * font::GLYPH_ADVANCE) as f32 * pixel;
+            for col in 0..font::GLYPH_WIDTH {
+                for row in 0..font::GLYPH_HEIGHT {
+                    if font::glyph_pixel(c, col, row) {
+                        let px = char_x + col as f32 * pixel;
+                        let py = y + (font::GLYPH_HEIGHT - 1 - row) as f32 * pixel;
+                        self.add_rect(px, py, pixel, pixel, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Toolbar layout in virtual pixels, anchored to the bottom center of the screen
+const TOOLBAR_SLOT_SIZE: f32 = 20.0;
+/// Space between the toolbar and the off-hand slot
+const OFFHAND_GAP: f32 = 6.0;
+/// Seconds a toolbar slot lights up after an item went into it
+const SLOT_FLASH_TIME: f32 = 0.3;
+const TOOLBAR_MARGIN: f32 = 2.0;
+
+// Inventory panel layout in virtual pixels, centered on screen
+const INVENTORY_PANEL_WIDTH: f32 = 176.0;
+const INVENTORY_PANEL_HEIGHT: f32 = 108.0;
+const INVENTORY_TITLE_HEIGHT: f32 = 14.0;
+const INVENTORY_SLOT_SIZE: f32 = 16.0;
+const INVENTORY_SLOT_GAP: f32 = 2.0;
+const INVENTORY_MARGIN: f32 = 8.0;
+/// Extra space between the storage rows and the toolbar row
+const INVENTORY_TOOLBAR_GAP: f32 = 10.0;
+/// Container and catalog screens add three slot rows plus a label line above the player's inventory
+const CONTAINER_LABEL_HEIGHT: f32 = 12.0;
+const CONTAINER_SECTION_HEIGHT: f32 = 3.0 * (INVENTORY_SLOT_SIZE + INVENTORY_SLOT_GAP) + CONTAINER_LABEL_HEIGHT;
+// Furnace slot columns, relative to the panel center
+const FURNACE_INPUT_X: f32 = -48.0;
+const FURNACE_OUTPUT_X: f32 = 24.0;
+// Trade rows: the price left of an arrow pointing at the result, relative to the panel center
+const TRADE_COST_X: f32 = -48.0;
+const TRADE_RESULT_X: f32 = 24.0;
+// Enchanting table: the tool slot, and right of it a button for each
+// enchantment, relative to the panel center
+const ENCHANTING_TOOL_X: f32 = -80.0;
+const ENCHANTING_OFFER_X: f32 = -52.0;
+const ENCHANTING_OFFER_WIDTH: f32 = 132.0;
+/// Height of the experience bar above the toolbar
+const EXPERIENCE_BAR_HEIGHT: f32 = 3.0;
+
+// Creative tabs and search box in the title bar, relative to the panel's left edge
+const CATALOG_TAB_X: f32 = 4.0;
+const INVENTORY_TAB_X: f32 = 52.0;
+const SEARCH_BOX_X: f32 = 116.0;
+const SEARCH_BOX_WIDTH: f32 = 56.0;
+/// Sort button at the right end of the title bar, where the search box would be
+const SORT_BUTTON_WIDTH: f32 = 28.0;
+
+/// A slot on the inventory screen: the player's own or one of the open
+/// container's, one of the catalog slots on screen, the result of one of
+/// a villager's offers, or the button of one of the enchanting table's
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScreenSlot {
+    Slot(SlotId),
+    Catalog(usize),
+    Trade(usize),
+    /// Index into `enchanting::offers` for the tool on the table
+    Enchantment(usize),
+}
+
+/// What the inventory screen shows above the player's own slots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenSection {
+    Container(ContainerKind),
+    /// The creative catalog tab
+    Catalog,
+    /// A villager's offers, this many
+    Trades(usize),
+}
+
+/// Tabs of the inventory screen in creative mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InventoryTab {
+    Catalog,
+    Inventory,
+}
+
+fn panel_height(section: Option<ScreenSection>) -> f32 {
+    match section {
+        Some(_) => INVENTORY_PANEL_HEIGHT + CONTAINER_SECTION_HEIGHT,
+        None => INVENTORY_PANEL_HEIGHT,
+    }
+}
+
+/// Every slot on the screen, container or catalog slots first and the off
+/// hand last
+fn screen_slots(section: Option<ScreenSection>) -> impl Iterator<Item = ScreenSlot> {
+    let container = |slots: usize| (0..slots).map(|i| ScreenSlot::Slot(SlotId::Container(i))).collect();
+    let upper: Vec<ScreenSlot> = match section {
+        Some(ScreenSection::Container(ContainerKind::Chest)) => container(CHEST_SLOTS),
+        Some(ScreenSection::Container(ContainerKind::Furnace)) => container(3),
+        Some(ScreenSection::Container(ContainerKind::EnchantingTable)) => {
+            let mut slots = container(1);
+            slots.extend((0..Enchantment::ALL.len()).map(ScreenSlot::Enchantment));
+            slots
+        }
+        Some(ScreenSection::Catalog) => (0..CATALOG_ROWS * CATALOG_COLUMNS).map(ScreenSlot::Catalog).collect(),
+        Some(ScreenSection::Trades(count)) => (0..count.min(3)).map(ScreenSlot::Trade).collect(),
+        None => Vec::new(),
+    };
+    upper
+        .into_iter()
+        .chain((0..27).map(|i| ScreenSlot::Slot(SlotId::Storage(i))))
+        .chain((0..9).map(|i| ScreenSlot::Slot(SlotId::Toolbar(i))))
+        .chain([ScreenSlot::Slot(SlotId::Offhand)])
+}
+
+/// Bottom edge of the lowest container row, just above the player's inventory label
+fn container_base_y(section: Option<ScreenSection>) -> f32 {
+    let step = INVENTORY_SLOT_SIZE + INVENTORY_SLOT_GAP;
+    let toolbar_y = -panel_height(section) / 2.0 + INVENTORY_MARGIN;
+    toolbar_y + step + INVENTORY_TOOLBAR_GAP + 3.0 * step + CONTAINER_LABEL_HEIGHT
+}
+
+/// Bottom-left corner of an inventory screen slot.
+/// Storage is 3 rows of 9 with the toolbar row at the bottom; an open container's
+/// or the catalog's slots sit above them.
+fn screen_slot_origin(section: Option<ScreenSection>, slot: ScreenSlot) -> (f32, f32) {
+    let panel_x = -INVENTORY_PANEL_WIDTH / 2.0;
+    let panel_y = -panel_height(section) / 2.0;
+    let start_x = panel_x + INVENTORY_MARGIN;
+    let step = INVENTORY_SLOT_SIZE + INVENTORY_SLOT_GAP;
+    let toolbar_y = panel_y + INVENTORY_MARGIN;
+
+    match slot {
+        ScreenSlot::Slot(SlotId::Toolbar(idx)) => (start_x + idx as f32 * step, toolbar_y),
+        // Outside the panel, left of the toolbar row, as on the HUD
+        ScreenSlot::Slot(SlotId::Offhand) => (panel_x - step, toolbar_y),
+        ScreenSlot::Slot(SlotId::Storage(idx)) => {
+            let (row, col) = (idx / 9, idx % 9);
+            let top_row_y = toolbar_y + step + INVENTORY_TOOLBAR_GAP + 2.0 * step;
+            (start_x + col as f32 * step, top_row_y - row as f32 * step)
+        }
+        ScreenSlot::Trade(idx) => (TRADE_RESULT_X, container_base_y(section) + (2 - idx as i32) as f32 * step),
+        ScreenSlot::Enchantment(idx) => (ENCHANTING_OFFER_X, container_base_y(section) + (2 - idx as i32) as f32 * step),
+        ScreenSlot::Slot(SlotId::Container(idx)) | ScreenSlot::Catalog(idx) => {
+            let base_y = container_base_y(section);
+            match section {
+                Some(ScreenSection::Container(ContainerKind::Furnace)) => match idx {
+                    FURNACE_INPUT => (FURNACE_INPUT_X, base_y + 2.0 * step),
+                    FURNACE_FUEL => (FURNACE_INPUT_X, base_y),
+                    _ => (FURNACE_OUTPUT_X, base_y + step),
+                },
+                Some(ScreenSection::Container(ContainerKind::EnchantingTable)) => (ENCHANTING_TOOL_X, base_y + step),
+                _ => {
+                    let (row, col) = (idx / 9, idx % 9);
+                    (start_x + col as f32 * step, base_y + (2 - row) as f32 * step)
+                }
+            }
+        }
+    }
+}
+
+/// Width of an inventory screen slot; the enchanting buttons are wide
+/// enough for their text
+fn screen_slot_width(slot: ScreenSlot) -> f32 {
+    match slot {
+        ScreenSlot::Enchantment(_) => ENCHANTING_OFFER_WIDTH,
+        _ => INVENTORY_SLOT_SIZE,
+    }
+}
+
+/// Find the inventory screen slot under a point (virtual pixels)
+pub fn screen_slot_at(section: Option<ScreenSection>, x: f32, y: f32) -> Option<ScreenSlot> {
+    screen_slots(section).find(|slot| {
+        let (sx, sy) = screen_slot_origin(section, *slot);
+        x >= sx && x < sx + screen_slot_width(*slot) && y >= sy && y < sy + INVENTORY_SLOT_SIZE
+    })
+}
+
+/// A worn tool's durability bar along the bottom of its slot, from green to
+/// red as it wears, and a purple frame around enchanted stacks
+fn add_stack_marks(mesh: &mut UiMesh, stack: &ItemStack, x: f32, y: f32, slot_size: f32) {
+    if !stack.enchantments.is_empty() {
+        let inset = slot_size * 0.08;
+        mesh.add_rect_outline(x + inset, y + inset, slot_size - 2.0 * inset, slot_size - 2.0 * inset, 1.0, [0.7, 0.35, 1.0, 0.9]);
+    }
+    if let Some(left) = stack.durability_left().filter(|_| stack.wear > 0) {
+        let (bar_x, width) = (x + slot_size * 0.15, slot_size * 0.7);
+        mesh.add_rect(bar_x, y + 1.0, width, 1.5, [0.0, 0.0, 0.0, 1.0]);
+        mesh.add_rect(bar_x, y + 1.0, width * left, 1.5, [1.0 - left, left, 0.0, 1.0]);
+    }
+}
+
+/// Textured cube for a block; other items are a flat square of their color
+#[allow(clippy::too_many_arguments)]
+fn add_thumbnail(mesh: &mut UiMesh, icons: &mut UiIconMesh, atlas: &AtlasLayout, item: Item, x: f32, y: f32, width: f32, height: f32) {
+    match item.block() {
+        Some(block) => icons.add_block(atlas, block, x, y, width, height),
+        None => {
+            let [r, g, b] = item.get_color();
+            let inset = width * 0.15;
+            mesh.add_rect(x + inset, y + inset, width - 2.0 * inset, height - 2.0 * inset, [r, g, b, 1.0]);
+        }
+    }
+}
+
+/// Draw an item stack's thumbnail and count indicator inside a slot
+fn add_item_icon(
+    mesh: &mut UiMesh,
+    icons: &mut UiIconMesh,
+    atlas: &AtlasLayout,
+    stack: &ItemStack,
+    x: f32,
+    y: f32,
+    slot_size: f32,
+) {
+    let padding = slot_size * 0.15;
+    let item_size = slot_size - 2.0 * padding;
+    add_thumbnail(mesh, icons, atlas, stack.item, x + padding, y + padding, item_size, item_size);
+
+    // Draw count indicator if > 1, in the slot corner left free by the cube's hexagon
+    if stack.count > 1 {
+        let count_size = slot_size * 0.15;
+        let margin = slot_size * 0.05;
+        let count_color = [1.0, 1.0, 1.0, 0.9];
+        mesh.add_rect(x + slot_size - margin - count_size, y + margin, count_size, count_size, count_color);
+    }
+    add_stack_marks(mesh, stack, x, y, slot_size);
+}
+
 pub struct UiRenderer {
     pub selected_block: BlockType,
-    crosshair_vertices: Vec<UiVertex>,
-    crosshair_indices: Vec<u32>,
-    toolbar_vertices: Vec<UiVertex>,
-    toolbar_indices: Vec<u32>,
+    crosshair: UiMesh,
+    toolbar: UiMesh,
+    toolbar_icons: UiIconMesh,
+    /// Seconds left of each toolbar slot's flash after an item went into it
+    slot_flashes: [f32; 9],
+    hud: UiMesh,
+    /// Line of text above the HUD, such as who is still awake while others sleep
+    action_bar: UiMesh,
+    /// Dial next to the toolbar while a compass is held
+    compass: UiMesh,
+    minimap: UiMesh,
     inventory_open: bool,
-    inventory_vertices: Vec<UiVertex>,
-    inventory_indices: Vec<u32>,
+    /// Position and kind of the container shown above the inventory, if any
+    container: Option<((i32, i32, i32), ContainerKind)>,
+    /// Offers of the villager traded with, shown above the inventory
+    trades: Option<Vec<Trade>>,
+    /// Search and scroll of the creative catalog, while the screen has the creative tabs
+    catalog: Option<Catalog>,
+    /// The catalog tab is the one shown
+    catalog_tab: bool,
+    inventory: UiMesh,
+    inventory_icons: UiIconMesh,
+    console: UiMesh,
+    menu: UiMesh,
+    loading: UiMesh,
+    toasts: UiMesh,
+    /// Cursor position in virtual pixels
+    cursor: (f32, f32),
+    scale: UiScale,
+    /// Tiles of the block textures, for item thumbnails
+    atlas: AtlasLayout,
 }
 
 impl UiRenderer {
     pub fn new() -> Self {
         let mut ui = Self {
             selected_block: BlockType::Dirt,
-            crosshair_vertices: Vec::new(),
-            crosshair_indices: Vec::new(),
-            toolbar_vertices: Vec::new(),
-            toolbar_indices: Vec::new(),
+            crosshair: UiMesh::default(),
+            toolbar: UiMesh::default(),
+            toolbar_icons: UiIconMesh::default(),
+            slot_flashes: [0.0; 9],
+            hud: UiMesh::default(),
+            action_bar: UiMesh::default(),
+            compass: UiMesh::default(),
+            minimap: UiMesh::default(),
             inventory_open: false,
-            inventory_vertices: Vec::new(),
-            inventory_indices: Vec::new(),
+            container: None,
+            trades: None,
+            catalog: None,
+            catalog_tab: false,
+            inventory: UiMesh::default(),
+            inventory_icons: UiIconMesh::default(),
+            console: UiMesh::default(),
+            menu: UiMesh::default(),
+            loading: UiMesh::default(),
+            toasts: UiMesh::default(),
+            cursor: (0.0, 0.0),
+            scale: UiScale::default(),
+            atlas: AtlasLayout::default(),
         };
         ui.build_crosshair();
         ui
     }
 
+    pub fn scale(&self) -> UiScale {
+        self.scale
+    }
+
+    /// Set the virtual screen size; callers rebuild the screen-anchored meshes afterwards
+    pub fn set_scale(&mut self, scale: UiScale) {
+        self.scale = scale;
+    }
+
+    /// Use the tiles of a new texture atlas; callers rebuild the toolbar and
+    /// inventory afterwards
+    pub fn set_atlas(&mut self, atlas: AtlasLayout) {
+        self.atlas = atlas;
+    }
+
     fn build_crosshair(&mut self) {
-        self.crosshair_vertices.clear();
-        self.crosshair_indices.clear();
+        self.crosshair.clear();
 
-        let size = 0.015; // Size of crosshair in NDC
-        let thickness = 0.003;
-        let gap = 0.01;
+        let size = 5.0; // Arm length in virtual pixels
+        let thickness = 0.5; // Half the line width
+        let gap = 2.0;
         let white = [1.0, 1.0, 1.0, 1.0];
 
         // Horizontal line (left part)
         let left_start = -(gap + size);
         let left_end = -gap;
-        self.add_line(left_start, 0.0, left_end, 0.0, thickness, white);
+        self.crosshair.add_line(left_start, 0.0, left_end, 0.0, thickness, white);
 
         // Horizontal line (right part)
         let right_start = gap;
         let right_end = gap + size;
-        self.add_line(right_start, 0.0, right_end, 0.0, thickness, white);
+        self.crosshair.add_line(right_start, 0.0, right_end, 0.0, thickness, white);
 
         // Vertical line (top part)
         let top_start = gap;
         let top_end = gap + size;
-        self.add_line(0.0, top_start, 0.0, top_end, thickness, white);
+        self.crosshair.add_line(0.0, top_start, 0.0, top_end, thickness, white);
 
         // Vertical line (bottom part)
         let bottom_start = -(gap + size);
         let bottom_end = -gap;
-        self.add_line(0.0, bottom_start, 0.0, bottom_end, thickness, white);
+        self.crosshair.add_line(0.0, bottom_start, 0.0, bottom_end, thickness, white);
+    }
+
+    /// Crosshair with a bar under it filling up as the block under it is
+    /// broken; None shows the crosshair alone
+    pub fn build_mining_progress(&mut self, progress: Option<f32>) {
+        self.build_crosshair();
+        let Some(progress) = progress else {
+            return;
+        };
+        let (width, height) = (16.0, 2.0);
+        let (x, y) = (-width / 2.0, -12.0);
+        self.crosshair.add_rect(x, y, width, height, [0.0, 0.0, 0.0, 0.6]);
+        self.crosshair.add_rect(x, y, width * progress, height, [1.0, 1.0, 1.0, 0.9]);
     }
 
     pub fn build_toolbar(&mut self, inventory: &Inventory) {
-        self.toolbar_vertices.clear();
-        self.toolbar_indices.clear();
+        self.toolbar.clear();
+        self.toolbar_icons.clear();
 
-        let toolbar_width = 0.6;
-        let toolbar_height = 0.08;
-        let y_pos = -0.9; // Bottom of screen
         let num_slots = 9;
-        let slot_size = toolbar_width / num_slots as f32;
-        let border_thickness = 0.004;
+        let slot_size = TOOLBAR_SLOT_SIZE;
+        let toolbar_width = slot_size * num_slots as f32;
+        let toolbar_height = TOOLBAR_SLOT_SIZE;
+        let y_pos = self.toolbar_y(); // Bottom of screen
+        let border_thickness = 1.0;
 
         // Draw toolbar background
         let bg_color = [0.0, 0.0, 0.0, 0.5];
-        self.add_rect(
+        self.toolbar.add_rect(
             -toolbar_width / 2.0,
             y_pos,
             toolbar_width,
@@ -111,134 +671,302 @@ impl UiRenderer {
         // Draw slot borders and contents
         for i in 0..num_slots {
             let x = -toolbar_width / 2.0 + i as f32 * slot_size;
-            let border_color = [0.8, 0.8, 0.8, 0.8];
-            
-            // Draw border as outline
-            self.add_rect_outline(x, y_pos, slot_size, toolbar_height, border_thickness, border_color);
-
-            // Draw item in slot if present
-            if let Some(stack) = &inventory.toolbar[i] {
-                let padding = slot_size * 0.2;
-                let block_size = slot_size - 2.0 * padding;
-                let color = stack.block_type.get_color();
-                let block_color = [color[0], color[1], color[2], 1.0];
-                
-                self.add_rect(
-                    x + padding,
-                    y_pos + padding,
-                    block_size,
-                    toolbar_height - 2.0 * padding,
-                    block_color,
-                );
+            self.add_toolbar_slot(x, y_pos, inventory.toolbar[i].as_ref());
+        }
 
-                // Draw item count if > 1
-                if stack.count > 1 {
-                    // We'll draw a small indicator for count
-                    // For now, just make a small white rectangle to indicate multiple items
-                    let count_indicator_size = slot_size * 0.15;
-                    let count_color = [1.0, 1.0, 1.0, 0.8];
-                    self.add_rect(
-                        x + slot_size - padding - count_indicator_size,
-                        y_pos + padding,
-                        count_indicator_size,
-                        count_indicator_size,
-                        count_color,
-                    );
-                }
-            }
+        // Slots items just went into light up and fade
+        for (i, flash) in self.slot_flashes.iter().enumerate().filter(|(_, flash)| **flash > 0.0) {
+            let x = -toolbar_width / 2.0 + i as f32 * slot_size;
+            let alpha = 0.5 * flash / SLOT_FLASH_TIME;
+            self.toolbar.add_rect(x, y_pos, slot_size, toolbar_height, [1.0, 1.0, 1.0, alpha]);
+        }
+
+        // The off-hand slot, a little left of the toolbar, while it holds something
+        if inventory.offhand.is_some() {
+            let x = -toolbar_width / 2.0 - OFFHAND_GAP - slot_size;
+            self.toolbar.add_rect(x, y_pos, slot_size, toolbar_height, bg_color);
+            self.add_toolbar_slot(x, y_pos, inventory.offhand.as_ref());
         }
 
         // Highlight selected slot
         let x = -toolbar_width / 2.0 + inventory.selected_slot as f32 * slot_size;
         let highlight_color = [1.0, 1.0, 1.0, 1.0];
-        self.add_rect_outline(x, y_pos, slot_size, toolbar_height, border_thickness * 2.0, highlight_color);
+        self.toolbar.add_rect_outline(x, y_pos, slot_size, toolbar_height, border_thickness * 2.0, highlight_color);
     }
 
-    fn add_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, thickness: f32, color: [f32; 4]) {
-        let dx = x2 - x1;
-        let dy = y2 - y1;
-        let len = (dx * dx + dy * dy).sqrt();
-        
-        if len < 0.0001 {
+    /// Light up a toolbar slot an item just went into
+    pub fn flash_slot(&mut self, slot: usize) {
+        if let Some(flash) = self.slot_flashes.get_mut(slot) {
+            *flash = SLOT_FLASH_TIME;
+        }
+    }
+
+    /// Fade the slot flashes by `delta_time` seconds. Returns true while the
+    /// toolbar needs rebuilding for them.
+    pub fn update_slot_flashes(&mut self, delta_time: f32) -> bool {
+        let mut changed = false;
+        for flash in self.slot_flashes.iter_mut().filter(|flash| **flash > 0.0) {
+            *flash = (*flash - delta_time).max(0.0);
+            changed = true;
+        }
+        changed
+    }
+
+    /// Border of a toolbar slot at (x, y) and the stack in it
+    fn add_toolbar_slot(&mut self, x: f32, y_pos: f32, stack: Option<&ItemStack>) {
+        let slot_size = TOOLBAR_SLOT_SIZE;
+        let toolbar_height = TOOLBAR_SLOT_SIZE;
+        let border_thickness = 1.0;
+        let border_color = [0.8, 0.8, 0.8, 0.8];
+
+        // Draw border as outline
+        self.toolbar.add_rect_outline(x, y_pos, slot_size, toolbar_height, border_thickness, border_color);
+
+        // Draw item in slot if present
+        if let Some(stack) = stack {
+            let padding = slot_size * 0.2;
+            let block_size = slot_size - 2.0 * padding;
+
+            add_thumbnail(
+                &mut self.toolbar,
+                &mut self.toolbar_icons,
+                &self.atlas,
+                stack.item,
+                x + padding,
+                y_pos + padding,
+                block_size,
+                toolbar_height - 2.0 * padding,
+            );
+
+            // Draw item count if > 1
+            if stack.count > 1 {
+                // We'll draw a small indicator for count
+                // For now, just make a small white rectangle to indicate multiple items,
+                // tucked into the corner beside the block thumbnail
+                let count_indicator_size = slot_size * 0.15;
+                let margin = slot_size * 0.08;
+                let count_color = [1.0, 1.0, 1.0, 0.8];
+                self.toolbar.add_rect(
+                    x + slot_size - margin - count_indicator_size,
+                    y_pos + margin,
+                    count_indicator_size,
+                    count_indicator_size,
+                    count_color,
+                );
+            }
+            add_stack_marks(&mut self.toolbar, stack, x, y_pos, slot_size);
+        }
+    }
+
+    /// The experience bar with the level beside it, and the health and
+    /// hunger rows above it, plus air bubbles while underwater. Hidden in
+    /// creative mode.
+    pub fn build_hud(&mut self, stats: &SurvivalStats, experience: &Experience, game_mode: GameMode) {
+        self.hud.clear();
+
+        if game_mode == GameMode::Creative {
             return;
         }
 
-        // Perpendicular vector
-        let px = -dy / len * thickness;
-        let py = dx / len * thickness;
+        let pixel = 1.0; // one icon pixel per virtual pixel
+        let icon_step = icons::ICON_SIZE as f32 * pixel;
+        let toolbar_half_width = TOOLBAR_SLOT_SIZE * 9.0 / 2.0;
 
-        let base_idx = self.crosshair_vertices.len() as u32;
+        // Experience bar just above the toolbar, filling towards the next level
+        let bar_y = self.toolbar_y() + TOOLBAR_SLOT_SIZE + 1.0;
+        let bar_width = 2.0 * toolbar_half_width;
+        let experience_color = [0.5, 1.0, 0.2, 1.0];
+        self.hud.add_rect(-toolbar_half_width, bar_y, bar_width, EXPERIENCE_BAR_HEIGHT, [0.0, 0.0, 0.0, 0.6]);
+        self.hud.add_rect(-toolbar_half_width, bar_y, bar_width * experience.progress(), EXPERIENCE_BAR_HEIGHT, experience_color);
+        if experience.level > 0 {
+            self.hud.add_text(&experience.level.to_string(), toolbar_half_width + 3.0, bar_y - 2.0, pixel, experience_color);
+        }
 
-        self.crosshair_vertices.push(UiVertex {
-            position: [x1 - px, y1 - py],
-            color,
-        });
-        self.crosshair_vertices.push(UiVertex {
-            position: [x1 + px, y1 + py],
-            color,
-        });
-        self.crosshair_vertices.push(UiVertex {
-            position: [x2 + px, y2 + py],
-            color,
-        });
-        self.crosshair_vertices.push(UiVertex {
-            position: [x2 - px, y2 - py],
-            color,
-        });
+        let row_y = bar_y + EXPERIENCE_BAR_HEIGHT + 1.0;
+        let heart_color = [0.85, 0.1, 0.1, 1.0];
+        let hunger_color = [0.75, 0.45, 0.2, 1.0];
+        let bubble_color = [0.6, 0.8, 1.0, 1.0];
 
-        self.crosshair_indices.extend_from_slice(&[
-            base_idx, base_idx + 1, base_idx + 2,
-            base_idx, base_idx + 2, base_idx + 3,
-        ]);
+        // Each icon stands for two points; an odd value shows a half icon
+        let fraction = |value: u32, i: u32| match value.saturating_sub(i * 2) {
+            0 => 0.0,
+            1 => 0.5,
+            _ => 1.0,
+        };
+
+        // Hearts fill left to right from the toolbar's left edge
+        for i in 0..10 {
+            let x = -toolbar_half_width + i as f32 * icon_step;
+            self.hud.add_icon(&icons::HEART, x, row_y, pixel, heart_color, fraction(stats.health, i));
+        }
+
+        // Hunger fills right to left from the toolbar's right edge
+        for i in 0..10 {
+            let x = toolbar_half_width - (i + 1) as f32 * icon_step;
+            self.hud.add_icon(&icons::HUNGER, x, row_y, pixel, hunger_color, fraction(stats.hunger, i));
+        }
+
+        // Air bubbles above the hunger row, only while some air is missing
+        if stats.air < MAX_AIR {
+            let bubbles = stats.air.div_ceil(MAX_AIR / 10);
+            for i in 0..bubbles {
+                let x = toolbar_half_width - (i + 1) as f32 * icon_step;
+                self.hud.add_icon(&icons::BUBBLE, x, row_y + icon_step + pixel, pixel, bubble_color, 1.0);
+            }
+        }
+    }
+
+    /// A line of text centered above the hearts and air bubbles, shown in
+    /// every game mode; None hides it
+    pub fn build_action_bar(&mut self, text: Option<&str>) {
+        self.action_bar.clear();
+        let Some(text) = text else {
+            return;
+        };
+
+        let pixel = 1.0;
+        let padding = 2.0;
+        // Above the toolbar and the HUD rows, where the console's input line opens
+        let y = self.toolbar_y() + TOOLBAR_SLOT_SIZE + 28.0;
+        let width = (text.len() * font::GLYPH_ADVANCE - 1) as f32 * pixel + 2.0 * padding;
+        let height = font::GLYPH_HEIGHT as f32 * pixel + 2.0 * padding;
+        self.action_bar.add_rect(-width / 2.0, y, width, height, [0.0, 0.0, 0.0, 0.4]);
+        self.action_bar.add_text(text, -width / 2.0 + padding, y + padding, pixel, [1.0, 1.0, 1.0, 1.0]);
     }
 
-    fn add_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) {
-        let base_idx = self.toolbar_vertices.len() as u32;
+    /// Compass dial right of the toolbar with its needle at `needle` radians
+    /// clockwise from straight up; None hides it
+    pub fn build_compass(&mut self, needle: Option<f32>) {
+        self.compass.clear();
+        let Some(needle) = needle else {
+            return;
+        };
 
-        self.toolbar_vertices.push(UiVertex {
-            position: [x, y],
-            color,
-        });
-        self.toolbar_vertices.push(UiVertex {
-            position: [x + width, y],
-            color,
-        });
-        self.toolbar_vertices.push(UiVertex {
-            position: [x + width, y + height],
-            color,
-        });
-        self.toolbar_vertices.push(UiVertex {
-            position: [x, y + height],
-            color,
-        });
+        let radius = TOOLBAR_SLOT_SIZE / 2.0;
+        let cx = TOOLBAR_SLOT_SIZE * 9.0 / 2.0 + 4.0 + radius;
+        let cy = self.toolbar_y() + radius;
+        self.compass.add_rect(cx - radius, cy - radius, radius * 2.0, radius * 2.0, [0.0, 0.0, 0.0, 0.6]);
+        self.compass.add_rect_outline(cx - radius, cy - radius, radius * 2.0, radius * 2.0, 1.0, [0.8, 0.8, 0.8, 1.0]);
 
-        self.toolbar_indices.extend_from_slice(&[
-            base_idx, base_idx + 1, base_idx + 2,
-            base_idx, base_idx + 2, base_idx + 3,
-        ]);
+        // Red tip towards the target, white tail opposite
+        let (sin, cos) = needle.sin_cos();
+        let length = radius - 3.0;
+        let width = 2.0;
+        let point = |along: f32, side: f32| [cx + sin * along + cos * side, cy + cos * along - sin * side];
+        self.compass.add_quad([point(length, 0.0), point(0.0, -width), point(0.0, 0.0), point(0.0, width)], [0.9, 0.15, 0.1, 1.0]);
+        self.compass.add_quad([point(-length, 0.0), point(0.0, width), point(0.0, 0.0), point(0.0, -width)], [0.95, 0.95, 0.95, 1.0]);
     }
 
-    fn add_rect_outline(&mut self, x: f32, y: f32, width: f32, height: f32, thickness: f32, color: [f32; 4]) {
-        // Top
-        self.add_rect(x, y + height - thickness, width, thickness, color);
-        // Bottom
-        self.add_rect(x, y, width, thickness, color);
-        // Left
-        self.add_rect(x, y, thickness, height, color);
-        // Right
-        self.add_rect(x + width - thickness, y, thickness, height, color);
+    /// Top-down map in the top-right corner, one cell per block column around the player.
+    pub fn build_minimap(&mut self, minimap: &Minimap, mode: MinimapMode, player_pos: glam::Vec3, yaw: f32) {
+        self.minimap.clear();
+
+        if mode == MinimapMode::Off {
+            return;
+        }
+
+        let size = 96.0;
+        let (width, height) = (size, size);
+        let margin = 4.0;
+        let cx = self.scale.half_width - margin - width / 2.0;
+        let cy = self.scale.half_height - margin - height / 2.0;
+        // Virtual pixels per block
+        let (scale_x, scale_y) = (width / (2 * MAP_RADIUS) as f32, height / (2 * MAP_RADIUS) as f32);
+
+        // Screen "up" is north (-Z); in rotating mode turn the map so the view direction is up
+        let angle = if mode == MinimapMode::Rotating { std::f32::consts::FRAC_PI_2 + yaw } else { 0.0 };
+        let (sin, cos) = angle.sin_cos();
+        let rotate = |x: f32, y: f32| (x * cos - y * sin, x * sin + y * cos);
+        let to_ndc = |(x, y): (f32, f32)| [cx + x * scale_x, cy + y * scale_y];
+
+        self.minimap.add_rect(cx - width / 2.0, cy - height / 2.0, width, height, [0.0, 0.0, 0.0, 0.6]);
+
+        let (px, pz) = (player_pos.x.floor() as i32, player_pos.z.floor() as i32);
+        let limit = MAP_RADIUS as f32;
+        for dz in -MAP_RADIUS..=MAP_RADIUS {
+            for dx in -MAP_RADIUS..=MAP_RADIUS {
+                let (bx, bz) = (px + dx, pz + dz);
+                let Some(color) = minimap.color_at(bx, bz) else {
+                    continue;
+                };
+
+                // Block center relative to the player, in map space (x east, y north)
+                let (ox, oy) = rotate(bx as f32 + 0.5 - player_pos.x, -(bz as f32 + 0.5 - player_pos.z));
+                if ox.abs() > limit - 0.5 || oy.abs() > limit - 0.5 {
+                    continue;
+                }
+
+                let corners = [(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (-0.5, 0.5)].map(|(x, y)| {
+                    let (rx, ry) = rotate(x, y);
+                    to_ndc((ox + rx, oy + ry))
+                });
+                self.minimap.add_quad(corners, [color[0], color[1], color[2], 1.0]);
+            }
+        }
+
+        self.minimap.add_rect_outline(cx - width / 2.0, cy - height / 2.0, width, height, 1.0, [0.8, 0.8, 0.8, 1.0]);
+
+        // Player arrow (chevron) pointing along the view direction
+        let (fx, fy) = rotate(yaw.cos(), -yaw.sin());
+        let (sx, sy) = (fy, -fx); // to the arrow's right
+        let arrow = 2.5; // blocks
+        let point = |along: f32, side: f32| to_ndc((fx * along + sx * side, fy * along + sy * side));
+        self.minimap.add_quad(
+            [point(arrow, 0.0), point(-arrow * 0.7, arrow * 0.7), point(-arrow * 0.3, 0.0), point(-arrow * 0.7, -arrow * 0.7)],
+            [1.0, 1.0, 1.0, 1.0],
+        );
     }
 
     pub fn get_crosshair_buffers(&self) -> (&[UiVertex], &[u32]) {
-        (&self.crosshair_vertices, &self.crosshair_indices)
+        self.crosshair.buffers()
     }
 
     pub fn get_toolbar_buffers(&self) -> (&[UiVertex], &[u32]) {
-        (&self.toolbar_vertices, &self.toolbar_indices)
+        self.toolbar.buffers()
+    }
+
+    pub fn get_toolbar_icon_buffers(&self) -> (&[UiIconVertex], &[u32]) {
+        self.toolbar_icons.buffers()
+    }
+
+    pub fn get_inventory_icon_buffers(&self) -> (&[UiIconVertex], &[u32]) {
+        self.inventory_icons.buffers()
+    }
+
+    pub fn get_hud_buffers(&self) -> (&[UiVertex], &[u32]) {
+        self.hud.buffers()
+    }
+
+    pub fn get_action_bar_buffers(&self) -> (&[UiVertex], &[u32]) {
+        self.action_bar.buffers()
+    }
+
+    pub fn get_compass_buffers(&self) -> (&[UiVertex], &[u32]) {
+        self.compass.buffers()
+    }
+
+    pub fn get_minimap_buffers(&self) -> (&[UiVertex], &[u32]) {
+        self.minimap.buffers()
     }
 
     pub fn get_inventory_buffers(&self) -> (&[UiVertex], &[u32]) {
-        (&self.inventory_vertices, &self.inventory_indices)
+        self.inventory.buffers()
+    }
+
+    pub fn get_console_buffers(&self) -> (&[UiVertex], &[u32]) {
+        self.console.buffers()
+    }
+
+    pub fn get_menu_buffers(&self) -> (&[UiVertex], &[u32]) {
+        self.menu.buffers()
+    }
+
+    pub fn get_loading_buffers(&self) -> (&[UiVertex], &[u32]) {
+        self.loading.buffers()
+    }
+
+    pub fn get_toast_buffers(&self) -> (&[UiVertex], &[u32]) {
+        self.toasts.buffers()
     }
 
     pub fn is_inventory_open(&self) -> bool {
@@ -247,22 +975,131 @@ impl UiRenderer {
 
     pub fn toggle_inventory(&mut self) {
         self.inventory_open = !self.inventory_open;
+        self.container = None;
+        self.trades = None;
+        self.catalog = None;
     }
 
-    pub fn build_inventory(&mut self, inventory: &Inventory) {
-        self.inventory_vertices.clear();
-        self.inventory_indices.clear();
+    /// Open the inventory screen with the creative tabs, on the catalog
+    pub fn open_creative_inventory(&mut self) {
+        self.inventory_open = true;
+        self.container = None;
+        self.trades = None;
+        self.catalog = Some(Catalog::default());
+        self.catalog_tab = true;
+    }
+
+    /// The catalog, while its tab is shown
+    pub fn catalog(&self) -> Option<&Catalog> {
+        self.catalog.as_ref().filter(|_| self.catalog_tab)
+    }
+
+    pub fn catalog_mut(&mut self) -> Option<&mut Catalog> {
+        self.catalog.as_mut().filter(|_| self.catalog_tab)
+    }
+
+    pub fn set_tab(&mut self, tab: InventoryTab) {
+        self.catalog_tab = tab == InventoryTab::Catalog;
+    }
+
+    /// Creative tab under a point (virtual pixels)
+    pub fn tab_at(&self, x: f32, y: f32) -> Option<InventoryTab> {
+        self.catalog.as_ref()?;
+        let (panel_x, title_y) = self.title_bar_origin();
+        if !(title_y..title_y + INVENTORY_TITLE_HEIGHT).contains(&y) {
+            return None;
+        }
+        let tab_width = |title: &str| (title.len() * font::GLYPH_ADVANCE) as f32 + 4.0;
+        [(InventoryTab::Catalog, CATALOG_TAB_X, "Catalog"), (InventoryTab::Inventory, INVENTORY_TAB_X, "Inventory")]
+            .into_iter()
+            .find(|(_, tab_x, title)| (panel_x + tab_x..panel_x + tab_x + tab_width(title)).contains(&x))
+            .map(|(tab, _, _)| tab)
+    }
+
+    /// Whether a point (virtual pixels) lies on the catalog's search box
+    pub fn search_box_contains(&self, x: f32, y: f32) -> bool {
+        let (panel_x, title_y) = self.title_bar_origin();
+        self.catalog().is_some()
+            && (panel_x + SEARCH_BOX_X..panel_x + SEARCH_BOX_X + SEARCH_BOX_WIDTH).contains(&x)
+            && (title_y..title_y + INVENTORY_TITLE_HEIGHT).contains(&y)
+    }
+
+    /// Whether a point (virtual pixels) lies on the sort button, shown
+    /// unless the catalog's search box takes its place
+    pub fn sort_button_contains(&self, x: f32, y: f32) -> bool {
+        let (panel_x, title_y) = self.title_bar_origin();
+        let button_x = panel_x + INVENTORY_PANEL_WIDTH - SORT_BUTTON_WIDTH - 2.0;
+        self.catalog().is_none()
+            && (button_x..button_x + SORT_BUTTON_WIDTH).contains(&x)
+            && (title_y..title_y + INVENTORY_TITLE_HEIGHT).contains(&y)
+    }
+
+    /// Left edge and bottom of the inventory screen's title bar
+    fn title_bar_origin(&self) -> (f32, f32) {
+        let panel_height = panel_height(self.section());
+        (-INVENTORY_PANEL_WIDTH / 2.0, panel_height / 2.0 - INVENTORY_TITLE_HEIGHT)
+    }
+
+    /// Open the inventory screen with a chest or furnace at `pos` shown above it
+    pub fn open_container(&mut self, pos: (i32, i32, i32), kind: ContainerKind) {
+        self.inventory_open = true;
+        self.container = Some((pos, kind));
+        self.trades = None;
+    }
+
+    /// Open the inventory screen with a villager's offers above it
+    pub fn open_trades(&mut self, trades: Vec<Trade>) {
+        self.inventory_open = true;
+        self.container = None;
+        self.trades = Some(trades);
+    }
+
+    /// The offer in row `idx` of the open trade screen
+    pub fn trade(&self, idx: usize) -> Option<&Trade> {
+        self.trades.as_ref()?.get(idx)
+    }
+
+    /// Block position of the open container screen
+    pub fn container_pos(&self) -> Option<(i32, i32, i32)> {
+        self.container.map(|(pos, _)| pos)
+    }
+
+    fn section(&self) -> Option<ScreenSection> {
+        match (self.container, &self.trades) {
+            (Some((_, kind)), _) => Some(ScreenSection::Container(kind)),
+            (None, Some(trades)) => Some(ScreenSection::Trades(trades.len())),
+            (None, None) => self.catalog().map(|_| ScreenSection::Catalog),
+        }
+    }
+
+    /// Check whether a point (virtual pixels) lies on the open inventory
+    /// panel, or the off-hand slot beside it
+    pub fn inventory_panel_contains(&self, x: f32, y: f32) -> bool {
+        let on_panel = x.abs() <= INVENTORY_PANEL_WIDTH / 2.0 && y.abs() <= panel_height(self.section()) / 2.0;
+        self.inventory_open && (on_panel || self.slot_at(x, y).is_some())
+    }
+
+    /// Slot of the open inventory screen under a point (virtual pixels)
+    pub fn slot_at(&self, x: f32, y: f32) -> Option<ScreenSlot> {
+        screen_slot_at(self.section(), x, y)
+    }
+
+    /// Build the inventory screen; `container` holds the contents of the open
+    /// chest, furnace or enchanting table, and `experience` is what the
+    /// table's offers are paid out of
+    pub fn build_inventory(&mut self, inventory: &Inventory, container: Option<&BlockEntity>, experience: &Experience) {
+        self.inventory.clear();
+        self.inventory_icons.clear();
 
         if !self.inventory_open {
             return;
         }
 
-        // Inventory panel dimensions
-        let panel_width = 0.8;
-        let panel_height = 0.6;
-        let slot_size = 0.07;
-        let slot_gap = 0.005;
-        let border_thickness = 0.003;
+        let section = self.section();
+        let panel_width = INVENTORY_PANEL_WIDTH;
+        let panel_height = panel_height(section);
+        let slot_size = INVENTORY_SLOT_SIZE;
+        let border_thickness = 1.0;
 
         // Center the panel
         let panel_x = -panel_width / 2.0;
@@ -270,150 +1107,389 @@ impl UiRenderer {
 
         // Draw semi-transparent background
         let bg_color = [0.0, 0.0, 0.0, 0.8];
-        self.add_inventory_rect(panel_x, panel_y, panel_width, panel_height, bg_color);
+        self.inventory.add_rect(panel_x, panel_y, panel_width, panel_height, bg_color);
 
         // Draw title area
-        let title_height = 0.08;
         let title_color = [0.2, 0.2, 0.2, 0.9];
-        self.add_inventory_rect(panel_x, panel_y + panel_height - title_height, panel_width, title_height, title_color);
-
-        // Draw storage slots (3 rows of 9)
-        let start_x = panel_x + 0.1;
-        let start_y = panel_y + panel_height - title_height - 0.15;
-
-        for row in 0..3 {
-            for col in 0..9 {
-                let slot_idx = row * 9 + col;
-                let x = start_x + col as f32 * (slot_size + slot_gap);
-                let y = start_y - row as f32 * (slot_size + slot_gap);
-
-                // Draw slot background
-                let slot_bg = [0.3, 0.3, 0.3, 0.9];
-                self.add_inventory_rect(x, y, slot_size, slot_size, slot_bg);
-
-                // Draw slot border
-                let border_color = [0.5, 0.5, 0.5, 1.0];
-                self.add_inventory_rect_outline(x, y, slot_size, slot_size, border_thickness, border_color);
-
-                // Draw item if present
-                if let Some(stack) = &inventory.storage[slot_idx] {
-                    let padding = slot_size * 0.15;
-                    let item_size = slot_size - 2.0 * padding;
-                    let color = stack.block_type.get_color();
-                    let item_color = [color[0], color[1], color[2], 1.0];
-                    
-                    self.add_inventory_rect(
-                        x + padding,
-                        y + padding,
-                        item_size,
-                        item_size,
-                        item_color,
-                    );
-
-                    // Draw count indicator if > 1
-                    if stack.count > 1 {
-                        let count_size = slot_size * 0.15;
-                        let count_color = [1.0, 1.0, 1.0, 0.9];
-                        self.add_inventory_rect(
-                            x + slot_size - padding - count_size,
-                            y + padding,
-                            count_size,
-                            count_size,
-                            count_color,
-                        );
-                    }
-                }
-            }
+        let title_y = panel_y + panel_height - INVENTORY_TITLE_HEIGHT;
+        self.inventory.add_rect(panel_x, title_y, panel_width, INVENTORY_TITLE_HEIGHT, title_color);
+        let text_y = title_y + (INVENTORY_TITLE_HEIGHT - font::GLYPH_HEIGHT as f32) / 2.0;
+        let white = [1.0, 1.0, 1.0, 1.0];
+        if self.catalog.is_some() {
+            self.add_creative_tabs(panel_x, title_y, text_y);
+        } else {
+            let title = match section {
+                Some(ScreenSection::Container(ContainerKind::Chest)) => "Chest",
+                Some(ScreenSection::Container(ContainerKind::Furnace)) => "Furnace",
+                Some(ScreenSection::Container(ContainerKind::EnchantingTable)) => "Enchanting Table",
+                Some(ScreenSection::Trades(_)) => "Villager",
+                _ => "Inventory",
+            };
+            self.inventory.add_text(title, panel_x + INVENTORY_MARGIN, text_y, 1.0, white);
+        }
+        if self.catalog().is_none() {
+            let button_x = panel_x + panel_width - SORT_BUTTON_WIDTH - 2.0;
+            let hovered = self.sort_button_contains(self.cursor.0, self.cursor.1);
+            let bg = if hovered { [0.5, 0.5, 0.5, 0.9] } else { [0.35, 0.35, 0.35, 0.9] };
+            self.inventory.add_rect(button_x, title_y + 2.0, SORT_BUTTON_WIDTH, INVENTORY_TITLE_HEIGHT - 4.0, bg);
+            self.inventory.add_text("Sort", button_x + 3.0, text_y, 1.0, white);
+        }
+
+        if section.is_some() {
+            // Label the player's half of the screen
+            let label_y = container_base_y(section) - CONTAINER_LABEL_HEIGHT + 3.0;
+            self.inventory.add_text("Inventory", panel_x + INVENTORY_MARGIN, label_y, 1.0, white);
+        }
+        if let Some(BlockEntity::Furnace(furnace)) = container {
+            self.add_furnace_progress(furnace.burn_fraction(), furnace.cook_fraction());
         }
+        self.add_trade_prices(inventory);
 
-        // Draw toolbar slots at bottom (same as in build_toolbar but in panel)
-        let toolbar_y = panel_y + 0.05;
-        for i in 0..9 {
-            let x = start_x + i as f32 * (slot_size + slot_gap);
+        let hovered = self.slot_at(self.cursor.0, self.cursor.1);
 
-            // Draw slot background
-            let slot_bg = [0.3, 0.3, 0.3, 0.9];
-            self.add_inventory_rect(x, toolbar_y, slot_size, slot_size, slot_bg);
+        // Container slots, then storage slots (3 rows of 9) and the toolbar row at the bottom
+        for slot in screen_slots(section) {
+            let (x, y) = screen_slot_origin(section, slot);
+            let width = screen_slot_width(slot);
 
-            // Draw slot border
-            let border_color = if i == inventory.selected_slot {
-                [1.0, 1.0, 1.0, 1.0] // Highlight selected slot
+            // Draw slot background, lighter when hovered
+            let slot_bg = if hovered == Some(slot) {
+                [0.45, 0.45, 0.45, 0.9]
+            } else {
+                [0.3, 0.3, 0.3, 0.9]
+            };
+            self.inventory.add_rect(x, y, width, slot_size, slot_bg);
+
+            // Draw slot border, highlighting the selected toolbar slot
+            let selected = slot == ScreenSlot::Slot(SlotId::Toolbar(inventory.selected_slot));
+            let border_color = if selected {
+                [1.0, 1.0, 1.0, 1.0]
             } else {
                 [0.5, 0.5, 0.5, 1.0]
             };
-            let thickness = if i == inventory.selected_slot {
+            let thickness = if selected {
                 border_thickness * 2.0
             } else {
                 border_thickness
             };
-            self.add_inventory_rect_outline(x, toolbar_y, slot_size, slot_size, thickness, border_color);
-
-            // Draw item if present
-            if let Some(stack) = &inventory.toolbar[i] {
-                let padding = slot_size * 0.15;
-                let item_size = slot_size - 2.0 * padding;
-                let color = stack.block_type.get_color();
-                let item_color = [color[0], color[1], color[2], 1.0];
-                
-                self.add_inventory_rect(
-                    x + padding,
-                    toolbar_y + padding,
-                    item_size,
-                    item_size,
-                    item_color,
-                );
+            self.inventory.add_rect_outline(x, y, width, slot_size, thickness, border_color);
 
-                // Draw count indicator if > 1
-                if stack.count > 1 {
-                    let count_size = slot_size * 0.15;
-                    let count_color = [1.0, 1.0, 1.0, 0.9];
-                    self.add_inventory_rect(
-                        x + slot_size - padding - count_size,
-                        toolbar_y + padding,
-                        count_size,
-                        count_size,
-                        count_color,
-                    );
-                }
+            let stack = match slot {
+                ScreenSlot::Slot(SlotId::Container(idx)) => container.and_then(|c| c.slots().get(idx).copied().flatten()),
+                ScreenSlot::Slot(id) => inventory.slot(id).copied().flatten(),
+                ScreenSlot::Catalog(idx) => self.catalog().and_then(|catalog| catalog.item_at(idx)).map(|item| ItemStack::new(item, 1)),
+                ScreenSlot::Trade(idx) => self.trade(idx).map(|trade| trade.result),
+                ScreenSlot::Enchantment(_) => None,
+            };
+            if let Some(stack) = &stack {
+                add_item_icon(&mut self.inventory, &mut self.inventory_icons, &self.atlas, stack, x, y, slot_size);
             }
         }
+
+        if let Some(BlockEntity::EnchantingTable(table)) = container {
+            self.add_enchanting_offers(table.slots[0].as_ref(), experience);
+        }
+
+        // Draw the stack held by the mouse on top, centered on the cursor
+        if let Some(stack) = &inventory.held {
+            let x = self.cursor.0 - slot_size / 2.0;
+            let y = self.cursor.1 - slot_size / 2.0;
+            add_item_icon(&mut self.inventory, &mut self.inventory_icons, &self.atlas, stack, x, y, slot_size);
+        }
     }
 
-    fn add_inventory_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) {
-        let base_idx = self.inventory_vertices.len() as u32;
+    /// Catalog and Inventory tabs, the search box and, beside the catalog
+    /// rows, a scroll bar
+    fn add_creative_tabs(&mut self, panel_x: f32, title_y: f32, text_y: f32) {
+        let white = [1.0, 1.0, 1.0, 1.0];
+        for (catalog_tab, tab_x, title) in [(true, CATALOG_TAB_X, "Catalog"), (false, INVENTORY_TAB_X, "Inventory")] {
+            let width = (title.len() * font::GLYPH_ADVANCE) as f32 + 4.0;
+            if catalog_tab == self.catalog_tab {
+                self.inventory.add_rect(panel_x + tab_x, title_y, width, INVENTORY_TITLE_HEIGHT, [0.4, 0.4, 0.4, 0.9]);
+            }
+            self.inventory.add_text(title, panel_x + tab_x + 2.0, text_y, 1.0, white);
+        }
 
-        self.inventory_vertices.push(UiVertex {
-            position: [x, y],
-            color,
-        });
-        self.inventory_vertices.push(UiVertex {
-            position: [x + width, y],
-            color,
-        });
-        self.inventory_vertices.push(UiVertex {
-            position: [x + width, y + height],
-            color,
-        });
-        self.inventory_vertices.push(UiVertex {
-            position: [x, y + height],
-            color,
-        });
+        let Some(catalog) = self.catalog() else {
+            return;
+        };
+        let (search, focused, rows, scroll) = (catalog.search().to_string(), catalog.search_focused, catalog.rows(), catalog.scroll());
+        let box_x = panel_x + SEARCH_BOX_X;
+        let border = if focused { white } else { [0.5, 0.5, 0.5, 1.0] };
+        self.inventory.add_rect(box_x, title_y + 2.0, SEARCH_BOX_WIDTH, INVENTORY_TITLE_HEIGHT - 4.0, [0.0, 0.0, 0.0, 0.9]);
+        self.inventory.add_rect_outline(box_x, title_y + 2.0, SEARCH_BOX_WIDTH, INVENTORY_TITLE_HEIGHT - 4.0, 1.0, border);
+        // The end of a long search, with a cursor while typing
+        let fits = (SEARCH_BOX_WIDTH as usize - 4) / font::GLYPH_ADVANCE - 1;
+        let shown: String = search.chars().skip(search.chars().count().saturating_sub(fits)).collect();
+        let text = match (focused, shown.is_empty()) {
+            (true, _) => format!("{}_", shown),
+            (false, true) => "Search".to_string(),
+            (false, false) => shown,
+        };
+        let color = if focused || !search.is_empty() { white } else { [0.6, 0.6, 0.6, 1.0] };
+        self.inventory.add_text(&text, box_x + 2.0, text_y, 1.0, color);
 
-        self.inventory_indices.extend_from_slice(&[
-            base_idx, base_idx + 1, base_idx + 2,
-            base_idx, base_idx + 2, base_idx + 3,
-        ]);
+        // Scroll bar right of the catalog rows, its thumb as tall as the share on screen
+        if rows > CATALOG_ROWS {
+            let step = INVENTORY_SLOT_SIZE + INVENTORY_SLOT_GAP;
+            let base_y = container_base_y(Some(ScreenSection::Catalog));
+            let height = CATALOG_ROWS as f32 * step - INVENTORY_SLOT_GAP;
+            let x = panel_x + INVENTORY_MARGIN + CATALOG_COLUMNS as f32 * step;
+            let thumb = height * CATALOG_ROWS as f32 / rows as f32;
+            let top = base_y + height - (height - thumb) * scroll as f32 / (rows - CATALOG_ROWS) as f32;
+            self.inventory.add_rect(x, base_y, 3.0, height, [0.2, 0.2, 0.2, 1.0]);
+            self.inventory.add_rect(x, top - thumb, 3.0, thumb, [0.8, 0.8, 0.8, 1.0]);
+        }
     }
 
-    fn add_inventory_rect_outline(&mut self, x: f32, y: f32, width: f32, height: f32, thickness: f32, color: [f32; 4]) {
-        // Top
-        self.add_inventory_rect(x, y + height - thickness, width, thickness, color);
-        // Bottom
-        self.add_inventory_rect(x, y, width, thickness, color);
-        // Left
-        self.add_inventory_rect(x, y, thickness, height, color);
-        // Right
-        self.add_inventory_rect(x + width - thickness, y, thickness, height, color);
+    /// The price of each offer of the open trade screen, and an arrow to its
+    /// result slot, lit if the inventory holds the price
+    fn add_trade_prices(&mut self, inventory: &Inventory) {
+        let Some(trades) = self.trades.clone() else {
+            return;
+        };
+        let step = INVENTORY_SLOT_SIZE + INVENTORY_SLOT_GAP;
+        let section = self.section();
+        for (row, trade) in trades.iter().take(3).enumerate() {
+            let y = container_base_y(section) + (2 - row) as f32 * step;
+            self.inventory.add_rect(TRADE_COST_X, y, INVENTORY_SLOT_SIZE, INVENTORY_SLOT_SIZE, [0.2, 0.2, 0.2, 0.9]);
+            add_item_icon(&mut self.inventory, &mut self.inventory_icons, &self.atlas, &trade.cost, TRADE_COST_X, y, INVENTORY_SLOT_SIZE);
+            let count = trade.cost.count.to_string();
+            let count_x = TRADE_COST_X + INVENTORY_SLOT_SIZE + 3.0;
+            let text_y = y + (INVENTORY_SLOT_SIZE - font::GLYPH_HEIGHT as f32) / 2.0;
+            self.inventory.add_text(&count, count_x, text_y, 1.0, [1.0, 1.0, 1.0, 1.0]);
+
+            // Arrow from after the count to the result slot
+            let color = if trade.affordable(inventory) { [1.0, 1.0, 1.0, 1.0] } else { [0.35, 0.35, 0.35, 1.0] };
+            let (start, tip) = (count_x + (count.len() * font::GLYPH_ADVANCE) as f32 + 4.0, TRADE_RESULT_X - 4.0);
+            let center_y = y + INVENTORY_SLOT_SIZE / 2.0;
+            self.inventory.add_rect(start, center_y - 2.0, tip - 6.0 - start, 4.0, color);
+            self.inventory.add_quad([[tip - 6.0, center_y - 5.0], [tip, center_y], [tip, center_y], [tip - 6.0, center_y + 5.0]], color);
+            if trade.result.count > 1 {
+                let result_x = TRADE_RESULT_X + INVENTORY_SLOT_SIZE + 3.0;
+                self.inventory.add_text(&trade.result.count.to_string(), result_x, text_y, 1.0, [1.0, 1.0, 1.0, 1.0]);
+            }
+        }
+    }
+
+    /// On each enchanting button, the next level of its enchantment the tool
+    /// on the table can take and what it costs, lit if `experience` has the
+    /// levels
+    fn add_enchanting_offers(&mut self, tool: Option<&ItemStack>, experience: &Experience) {
+        let offers = tool.map(enchanting::offers).unwrap_or_default();
+        let step = INVENTORY_SLOT_SIZE + INVENTORY_SLOT_GAP;
+        let section = self.section();
+        for (row, offer) in offers.iter().enumerate() {
+            let y = container_base_y(section) + (2 - row) as f32 * step;
+            let text_y = y + (INVENTORY_SLOT_SIZE - font::GLYPH_HEIGHT as f32) / 2.0;
+            let affordable = experience.level >= offer.cost;
+            let color = if affordable { [1.0, 1.0, 1.0, 1.0] } else { [0.45, 0.45, 0.45, 1.0] };
+            self.inventory.add_text(&offer.title(), ENCHANTING_OFFER_X + 4.0, text_y, 1.0, color);
+            let cost = format!("{} lvl", offer.cost);
+            let cost_x = ENCHANTING_OFFER_X + ENCHANTING_OFFER_WIDTH - 4.0 - (cost.len() * font::GLYPH_ADVANCE) as f32;
+            let cost_color = if affordable { [0.5, 1.0, 0.2, 1.0] } else { [0.45, 0.45, 0.45, 1.0] };
+            self.inventory.add_text(&cost, cost_x, text_y, 1.0, cost_color);
+        }
+    }
+
+    /// Furnace flame (remaining fuel) under the input slot and the smelting arrow
+    /// pointing at the output slot
+    fn add_furnace_progress(&mut self, burn: f32, cook: f32) {
+        let section = Some(ScreenSection::Container(ContainerKind::Furnace));
+        let step = INVENTORY_SLOT_SIZE + INVENTORY_SLOT_GAP;
+        let middle_y = container_base_y(section) + step;
+        let track = [0.25, 0.25, 0.25, 1.0];
+
+        // Flame: fills from the bottom while fuel is burning
+        let flame_x = FURNACE_INPUT_X + 3.0;
+        let flame_size = INVENTORY_SLOT_SIZE - 6.0;
+        self.inventory.add_rect(flame_x, middle_y + 3.0, flame_size, flame_size, track);
+        self.inventory.add_rect(flame_x, middle_y + 3.0, flame_size, flame_size * burn, [1.0, 0.55, 0.1, 1.0]);
+
+        // Arrow: shaft fills left to right with the cooking progress, head at the output
+        let arrow_x = FURNACE_INPUT_X + INVENTORY_SLOT_SIZE + 6.0;
+        let head = 6.0;
+        let shaft = FURNACE_OUTPUT_X - 4.0 - head - arrow_x;
+        let center_y = middle_y + INVENTORY_SLOT_SIZE / 2.0;
+        let fill_color = [1.0, 1.0, 1.0, 1.0];
+        self.inventory.add_rect(arrow_x, center_y - 2.0, shaft, 4.0, track);
+        self.inventory.add_rect(arrow_x, center_y - 2.0, shaft * cook, 4.0, fill_color);
+        let tip = arrow_x + shaft + head;
+        self.inventory.add_quad(
+            [
+                [arrow_x + shaft, center_y - 5.0],
+                [tip, center_y],
+                [tip, center_y],
+                [arrow_x + shaft, center_y + 5.0],
+            ],
+            track,
+        );
+    }
+
+    /// Remember the cursor position (virtual pixels) for hover highlights, the held stack and menu buttons
+    pub fn set_cursor(&mut self, x: f32, y: f32) {
+        self.cursor = (x, y);
+    }
+
+    /// Bottom edge of the toolbar
+    fn toolbar_y(&self) -> f32 {
+        -self.scale.half_height + TOOLBAR_MARGIN
+    }
+
+    /// The open console, or the fading chat log while it is closed
+    pub fn build_console(&mut self, console: &Console) {
+        self.console.clear();
+
+        let pixel = 1.0; // virtual pixels per font pixel
+        let line_height = (font::GLYPH_HEIGHT + 3) as f32 * pixel;
+        let padding = 2.0;
+        let panel_x = -self.scale.half_width + 2.0;
+        let panel_width = (2.0 * self.scale.half_width - 4.0).min(320.0);
+        // Above the toolbar and the HUD rows
+        let input_y = self.toolbar_y() + TOOLBAR_SLOT_SIZE + 28.0;
+        let text_color = [1.0, 1.0, 1.0, 1.0];
+
+        if !console.is_open() {
+            // Recent lines where the scrollback would be, newest at the bottom
+            let lines: Vec<(&str, f32)> = console.chat_log().collect();
+            let log_y = input_y + line_height + padding * 2.0;
+            for (i, (line, alpha)) in lines.iter().rev().enumerate() {
+                let y = log_y + i as f32 * line_height;
+                self.console.add_rect(panel_x, y, panel_width, line_height, [0.0, 0.0, 0.0, 0.4 * alpha]);
+                self.console.add_text(line, panel_x + padding, y + padding, pixel, [1.0, 1.0, 1.0, *alpha]);
+            }
+            return;
+        }
+
+        // Input line background
+        self.console.add_rect(panel_x, input_y, panel_width, line_height + padding, [0.0, 0.0, 0.0, 0.7]);
+        let prompt = format!("> {}_", console.input());
+        self.console.add_text(&prompt, panel_x + padding, input_y + padding, pixel, text_color);
+
+        // Scrollback above the input line
+        let lines: Vec<&String> = console.visible_lines().collect();
+        let history_y = input_y + line_height + padding * 2.0;
+        let history_height = lines.len() as f32 * line_height + padding;
+        if !lines.is_empty() {
+            self.console.add_rect(panel_x, history_y, panel_width, history_height, [0.0, 0.0, 0.0, 0.5]);
+        }
+        for (i, line) in lines.iter().rev().enumerate() {
+            let y = history_y + padding + i as f32 * line_height;
+            self.console.add_text(line, panel_x + padding, y, pixel, text_color);
+        }
+    }
+
+    pub fn build_menu(&mut self, menu: &Menu, config: &GameConfig, stats: &PlayerStats) {
+        self.menu.clear();
+
+        let Some(screen) = menu.screen() else {
+            return;
+        };
+
+        let pixel = 1.0; // virtual pixels per font pixel
+        let text_height = font::GLYPH_HEIGHT as f32 * pixel;
+        let text_width = |text: &str| (text.len() * font::GLYPH_ADVANCE - 1) as f32 * pixel;
+        let white = [1.0, 1.0, 1.0, 1.0];
+        let (half_w, half_h) = (self.scale.half_width, self.scale.half_height);
+
+        // Dim the world behind the menu; red on the death screen, dark in bed,
+        // and the title screen has no world behind it
+        let backdrop = match screen {
+            MenuScreen::Main => [0.24, 0.17, 0.11, 1.0],
+            MenuScreen::Options if menu.options_parent() == MenuScreen::Main => [0.24, 0.17, 0.11, 1.0],
+            MenuScreen::Death => [0.5, 0.0, 0.0, 0.5],
+            MenuScreen::Sleeping => [0.0, 0.0, 0.05, 0.75],
+            _ => [0.0, 0.0, 0.0, 0.5],
+        };
+        self.menu.add_rect(-half_w, -half_h, 2.0 * half_w, 2.0 * half_h, backdrop);
+
+        let title = screen.title();
+        self.menu.add_text(title, -text_width(title), crate::menu::TITLE_Y, pixel * 2.0, white);
+
+        if screen == MenuScreen::Statistics {
+            let line_height = text_height + 5.0;
+            for (i, line) in stats.lines().iter().enumerate() {
+                let y = crate::menu::TITLE_Y - 12.0 - (i + 1) as f32 * line_height;
+                self.menu.add_text(line, -text_width(line) / 2.0, y, pixel, white);
+            }
+        }
+
+        let hovered = menu.widget_at(self.cursor.0, self.cursor.1);
+        for (widget, (x, y, w, h)) in menu.widgets() {
+            let bg = if hovered == Some(widget) {
+                [0.45, 0.45, 0.5, 0.9]
+            } else {
+                [0.25, 0.25, 0.25, 0.9]
+            };
+            self.menu.add_rect(x, y, w, h, bg);
+
+            let label = match widget {
+                MenuWidget::Button(button) => button.label().to_string(),
+                MenuWidget::Option(option) => {
+                    if let OptionKind::Slider { .. } = option.kind() {
+                        // Filled track plus a knob at the current value
+                        let fill = option.fraction(config) * w;
+                        self.menu.add_rect(x, y, fill, h, [0.35, 0.45, 0.65, 0.9]);
+                        self.menu.add_rect(x + fill - 2.0, y, 4.0, h, white);
+                    }
+                    option.label(config)
+                }
+                MenuWidget::Done => "Done".to_string(),
+            };
+
+            self.menu.add_rect_outline(x, y, w, h, 1.0, [0.7, 0.7, 0.7, 1.0]);
+            self.menu.add_text(&label, x + (w - text_width(&label)) / 2.0, y + (h - text_height) / 2.0, pixel, white);
+        }
+    }
+
+    /// Notification toasts stacked at the top center of the screen, newest on top
+    pub fn build_toasts(&mut self, toasts: &Toasts) {
+        self.toasts.clear();
+
+        let pixel = 1.0;
+        let padding = 3.0;
+        let height = font::GLYPH_HEIGHT as f32 * pixel + 2.0 * padding;
+        let mut y = self.scale.half_height - 4.0 - height;
+
+        for (text, alpha) in toasts.visible() {
+            let width = (text.len() * font::GLYPH_ADVANCE - 1) as f32 * pixel + 2.0 * padding;
+            let x = -width / 2.0;
+            self.toasts.add_rect(x, y, width, height, [0.0, 0.0, 0.0, 0.6 * alpha]);
+            self.toasts.add_text(text, x + padding, y + padding, pixel, [1.0, 1.0, 0.6, alpha]);
+            y -= height + 2.0;
+        }
+    }
+
+    /// Full-screen loading screen with a progress bar; `None` hides it
+    pub fn build_loading_screen(&mut self, progress: Option<f32>) {
+        self.loading.clear();
+
+        let Some(progress) = progress else {
+            return;
+        };
+        let progress = progress.clamp(0.0, 1.0);
+
+        let pixel = 1.0;
+        let text_width = |text: &str| (text.len() * font::GLYPH_ADVANCE - 1) as f32 * pixel;
+        let white = [1.0, 1.0, 1.0, 1.0];
+        let (half_w, half_h) = (self.scale.half_width, self.scale.half_height);
+
+        // Opaque dirt-brown backdrop
+        self.loading.add_rect(-half_w, -half_h, 2.0 * half_w, 2.0 * half_h, [0.24, 0.17, 0.11, 1.0]);
+
+        let title = "Generating world";
+        self.loading.add_text(title, -text_width(title) / 2.0, 12.0, pixel, white);
+
+        let bar_width = 200.0;
+        let bar_height = 10.0;
+        let bar_x = -bar_width / 2.0;
+        let bar_y = -5.0;
+        self.loading.add_rect(bar_x, bar_y, bar_width, bar_height, [0.1, 0.1, 0.1, 1.0]);
+        self.loading.add_rect(bar_x, bar_y, bar_width * progress, bar_height, [0.3, 0.75, 0.3, 1.0]);
+        self.loading.add_rect_outline(bar_x, bar_y, bar_width, bar_height, 1.0, [0.7, 0.7, 0.7, 1.0]);
+
+        let percent = format!("{:.0}%", progress * 100.0);
+        self.loading.add_text(&percent, -text_width(&percent) / 2.0, bar_y - 16.0, pixel, white);
     }
 
     #[allow(dead_code)]
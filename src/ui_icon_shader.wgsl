@@ -0,0 +1,43 @@
+// UI Shader for item thumbnails: samples the block texture atlas
+
+struct UiUniforms {
+    // Virtual pixels to NDC (xy; zw unused)
+    ndc_scale: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> ui: UiUniforms;
+
+@group(1) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(1) @binding(1)
+var s_diffuse: sampler;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    // Position is in UI virtual pixels around the screen center
+    out.clip_position = vec4<f32>(input.position * ui.ndc_scale.xy, 0.0, 1.0);
+    out.tex_coords = input.tex_coords;
+    out.color = input.color;
+    return out;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let tex_color = textureSample(t_diffuse, s_diffuse, input.tex_coords);
+    // Tint like the world shader so thumbnails match placed blocks
+    return vec4<f32>(tex_color.rgb * input.color.rgb, tex_color.a * input.color.a);
+}
@@ -1,5 +1,13 @@
 // UI Shader for 2D elements (crosshair, toolbar)
 
+struct UiUniforms {
+    // Virtual pixels to NDC (xy; zw unused)
+    ndc_scale: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> ui: UiUniforms;
+
 struct VertexInput {
     @location(0) position: vec2<f32>,
     @location(1) color: vec4<f32>,
@@ -13,8 +21,8 @@ struct VertexOutput {
 @vertex
 fn vs_main(input: VertexInput) -> VertexOutput {
     var out: VertexOutput;
-    // Position is already in normalized device coordinates (-1 to 1)
-    out.clip_position = vec4<f32>(input.position, 0.0, 1.0);
+    // Position is in UI virtual pixels around the screen center
+    out.clip_position = vec4<f32>(input.position * ui.ndc_scale.xy, 0.0, 1.0);
     out.color = input.color;
     return out;
 }
@@ -6,6 +6,12 @@ pub struct Vertex {
     pub position: [f32; 3],
     pub color: [f32; 3],
     pub tex_coords: [f32; 2],
+    /// Block light reaching the face in [0, 1]; the world is drawn at least
+    /// this bright however dark the sky gets
+    pub light: f32,
+    /// When the chunk first showed, in seconds on the renderer's clock; it
+    /// fades in out of the fog from then on
+    pub appeared: f32,
 }
 
 impl Vertex {
@@ -29,25 +35,91 @@ impl Vertex {
                     shader_location: 2,
                     format: wgpu::VertexFormat::Float32x2,
                 },
+                wgpu::VertexAttribute {
+                    offset: (std::mem::size_of::<[f32; 3]>() * 2 + std::mem::size_of::<[f32; 2]>()) as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                wgpu::VertexAttribute {
+                    offset: (std::mem::size_of::<[f32; 3]>() * 2 + std::mem::size_of::<[f32; 2]>() + std::mem::size_of::<f32>())
+                        as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32,
+                },
             ],
         }
     }
 }
 
+/// Where a chunk's mesh sits in the world. Terrain vertices are relative to
+/// their chunk; each chunk's draw takes its offset as an instance attribute.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+pub struct ChunkOffset {
+    pub origin: [f32; 3],
+}
+
+impl ChunkOffset {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<ChunkOffset>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[wgpu::VertexAttribute {
+                offset: 0,
+                shader_location: 5,
+                format: wgpu::VertexFormat::Float32x3,
+            }],
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 pub struct Uniforms {
     pub view_proj: [[f32; 4]; 4],
+    /// Camera world position (w unused)
+    pub camera_pos: [f32; 4],
+    /// Fog color, normally the sky color (a unused)
+    pub fog_color: [f32; 4],
+    /// Fog start distance, end distance, enabled flag (0/1), unused
+    pub fog_params: [f32; 4],
+    /// Brightness of the world, dimmed by clouds; seconds on the renderer's
+    /// clock, for chunks fading in; zw unused
+    pub light: [f32; 4],
 }
 
 impl Uniforms {
     pub fn new() -> Self {
         Self {
             view_proj: glam::Mat4::IDENTITY.to_cols_array_2d(),
+            camera_pos: [0.0; 4],
+            fog_color: [0.53, 0.81, 0.92, 1.0],
+            fog_params: [0.0, 1.0, 0.0, 0.0],
+            light: [1.0, 0.0, 0.0, 0.0],
         }
     }
 
     pub fn update_view_proj(&mut self, view: glam::Mat4, proj: glam::Mat4) {
         self.view_proj = (proj * view).to_cols_array_2d();
     }
+
+    pub fn update_camera_pos(&mut self, pos: glam::Vec3) {
+        self.camera_pos = [pos.x, pos.y, pos.z, 1.0];
+    }
+
+    pub fn set_fog_color(&mut self, color: [f32; 3]) {
+        self.fog_color = [color[0], color[1], color[2], 1.0];
+    }
+
+    pub fn set_fog(&mut self, enabled: bool, start: f32, end: f32) {
+        self.fog_params = [start, end, if enabled { 1.0 } else { 0.0 }, 0.0];
+    }
+
+    pub fn set_light(&mut self, brightness: f32) {
+        self.light[0] = brightness;
+    }
+
+    pub fn set_time(&mut self, seconds: f32) {
+        self.light[1] = seconds;
+    }
 }
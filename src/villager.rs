@@ -0,0 +1,261 @@
+//! Villagers, who live in the villages the generator builds (see
+//! `structures`). They stroll around the spot they were generated at and stop
+//! to look at a player who comes close. A right click on one opens its trade
+//! screen: each villager has a few fixed offers, either buying goods for
+//! emeralds or selling things for them, and every click on an offer's result
+//! makes the exchange once if the inventory has the price and room for what
+//! it buys. Villagers are saved with the world.
+
+use crate::block::BlockType;
+use crate::debug_render::DebugVertex;
+use crate::enchanting::Enchantments;
+use crate::inventory::{Inventory, ItemStack};
+use crate::item::Item;
+use crate::physics::Aabb;
+use crate::remote_players::add_cuboid;
+use crate::world::World;
+use glam::{Mat3, Vec3};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::FRAC_PI_2;
+
+/// Half the side of the square a villager collides as
+const HALF_WIDTH: f32 = 0.3;
+/// Height of a villager, a little more than a player for the tall head
+pub const HEIGHT: f32 = 1.9;
+/// Walking speed, in blocks per second
+const WALK_SPEED: f32 = 1.2;
+/// Farthest a villager strolls from its home, in blocks
+const WANDER_RADIUS: f32 = 4.0;
+/// Shortest and longest rest between two strolls, in seconds
+const REST_TIME: (f32, f32) = (2.0, 8.0);
+/// Players closer than this make a villager stop and look at them
+const LOOK_RANGE: f32 = 5.0;
+const GRAVITY: f32 = -20.0;
+
+/// Offers a villager makes, one of which buys goods
+const TRADES_PER_VILLAGER: usize = 3;
+
+/// One exchange a villager offers: `cost` from the player's inventory for `result`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trade {
+    pub cost: ItemStack,
+    pub result: ItemStack,
+}
+
+impl Trade {
+    const fn new(cost: Item, cost_count: u32, result: Item, result_count: u32) -> Self {
+        Self {
+            cost: ItemStack { item: cost, count: cost_count, wear: 0, enchantments: Enchantments::NONE },
+            result: ItemStack { item: result, count: result_count, wear: 0, enchantments: Enchantments::NONE },
+        }
+    }
+
+    /// Whether the inventory holds the price
+    pub fn affordable(&self, inventory: &Inventory) -> bool {
+        inventory.count_item(self.cost.item) >= self.cost.count
+    }
+
+    /// Pay the price out of the inventory and put the result into it.
+    /// Returns false, changing nothing, if the price isn't there or the
+    /// result doesn't fit.
+    pub fn make(&self, inventory: &mut Inventory) -> bool {
+        let mut after = inventory.clone();
+        if !after.remove_item(self.cost.item, self.cost.count) || !after.add_item(self.result.item, self.result.count) {
+            return false;
+        }
+        *inventory = after;
+        true
+    }
+}
+
+/// Goods villagers buy for emeralds
+const PURCHASES: [Trade; 4] = [
+    Trade::new(Item::Seeds, 20, Item::Emerald, 1),
+    Trade::new(Item::Fish, 6, Item::Emerald, 1),
+    Trade::new(Item::Block(BlockType::Wood), 16, Item::Emerald, 1),
+    Trade::new(Item::Apple, 8, Item::Emerald, 1),
+];
+
+/// What villagers sell for emeralds
+const SALES: [Trade; 6] = [
+    Trade::new(Item::Emerald, 1, Item::Block(BlockType::Glass), 8),
+    Trade::new(Item::Emerald, 1, Item::Apple, 4),
+    Trade::new(Item::Emerald, 2, Item::Compass, 1),
+    Trade::new(Item::Emerald, 3, Item::FishingRod, 1),
+    Trade::new(Item::Emerald, 4, Item::StonePickaxe, 1),
+    Trade::new(Item::Emerald, 2, Item::Block(BlockType::Lamp), 4),
+];
+
+/// The offers of a villager generated with `seed`: one purchase, then
+/// different sales
+pub fn offers(seed: u32) -> Vec<Trade> {
+    let mut rng = seed.wrapping_mul(0x9E37_79B9) | 1;
+    let mut sales = SALES.to_vec();
+    let mut trades = vec![PURCHASES[next(&mut rng) as usize % PURCHASES.len()]];
+    while trades.len() < TRADES_PER_VILLAGER && !sales.is_empty() {
+        trades.push(sales.remove(next(&mut rng) as usize % sales.len()));
+    }
+    trades
+}
+
+/// Step of the xorshift generator behind the offers and strolls
+fn next(state: &mut u32) -> u32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    *state
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Villager {
+    /// Middle of the feet
+    pub position: Vec3,
+    /// View direction, in the camera's yaw convention
+    pub yaw: f32,
+    /// Where it was generated, and strolls around
+    pub home: Vec3,
+    pub trades: Vec<Trade>,
+    /// Xorshift state drawing the strolls
+    rng: u32,
+    #[serde(skip)]
+    velocity: Vec3,
+    /// Where the current stroll goes
+    #[serde(skip)]
+    target: Option<Vec3>,
+    /// Seconds left of the rest before the next stroll
+    #[serde(skip)]
+    rest: f32,
+}
+
+impl Villager {
+    /// A villager at home at `position`, with the offers of `seed`
+    pub fn new(position: Vec3, seed: u32) -> Self {
+        Self {
+            position,
+            yaw: 0.0,
+            home: position,
+            trades: offers(seed),
+            rng: seed.wrapping_mul(0x85EB_CA6B) | 1,
+            velocity: Vec3::ZERO,
+            target: None,
+            rest: 0.0,
+        }
+    }
+
+    pub fn bounding_box(&self) -> Aabb {
+        Aabb::from_position(self.position, HALF_WIDTH, HEIGHT)
+    }
+
+    /// Uniform in [0, 1)
+    fn random(&mut self) -> f32 {
+        next(&mut self.rng) as f32 / (u32::MAX as f32 + 1.0)
+    }
+
+    /// Stroll, rest or look at the nearest of the players with their eyes at
+    /// `players` for `delta_time` seconds, falling and stopping against solid
+    /// blocks one axis at a time
+    pub fn update(&mut self, world: &World, players: &[Vec3], delta_time: f32) {
+        let eyes = self.position + Vec3::Y * (HEIGHT - 0.3);
+        let watched = players
+            .iter()
+            .filter(|player| player.distance(eyes) < LOOK_RANGE)
+            .min_by(|a, b| a.distance_squared(eyes).total_cmp(&b.distance_squared(eyes)));
+        let mut walk = Vec3::ZERO;
+        if let Some(player) = watched {
+            let to = *player - self.position;
+            self.yaw = to.z.atan2(to.x);
+        } else if self.rest > 0.0 {
+            self.rest -= delta_time;
+        } else if let Some(target) = self.target {
+            let to = Vec3::new(target.x - self.position.x, 0.0, target.z - self.position.z);
+            if to.length() < 0.2 {
+                self.stop();
+            } else {
+                walk = to.normalize() * WALK_SPEED;
+                self.yaw = to.z.atan2(to.x);
+            }
+        } else {
+            let (angle, distance) = (self.random() * std::f32::consts::TAU, self.random() * WANDER_RADIUS);
+            self.target = Some(self.home + Vec3::new(angle.cos(), 0.0, angle.sin()) * distance);
+        }
+
+        self.velocity = Vec3::new(walk.x, self.velocity.y + GRAVITY * delta_time, walk.z);
+        for axis in 0..3 {
+            let mut moved = self.bounding_box();
+            moved.min[axis] += self.velocity[axis] * delta_time;
+            moved.max[axis] += self.velocity[axis] * delta_time;
+            if collides(world, &moved) {
+                // Walking into a wall ends the stroll there
+                if axis != 1 && self.target.is_some() {
+                    self.stop();
+                }
+                self.velocity[axis] = 0.0;
+            } else {
+                self.position[axis] += self.velocity[axis] * delta_time;
+            }
+        }
+        // The border ends a stroll like a wall
+        if world.push_inside_border(&mut self.position, &mut self.velocity, HALF_WIDTH) && self.target.is_some() {
+            self.stop();
+        }
+    }
+
+    /// End the stroll and rest a while
+    fn stop(&mut self) {
+        self.target = None;
+        self.rest = REST_TIME.0 + (REST_TIME.1 - REST_TIME.0) * self.random();
+    }
+}
+
+/// Whether the box overlaps a full block other than water
+fn collides(world: &World, aabb: &Aabb) -> bool {
+    let (min, max) = (aabb.min.floor().as_ivec3(), (aabb.max - 1e-4).floor().as_ivec3());
+    let blocks = |x, y, z| world.get_block_at(x, y, z).is_some_and(|block: BlockType| block.is_full_cube() && !block.is_liquid());
+    (min.x..=max.x).any(|x| (min.y..=max.y).any(|y| (min.z..=max.z).any(|z| blocks(x, y, z))))
+}
+
+/// Let the villagers of the world stroll for `delta_time` seconds, watching
+/// the players with their eyes at `players`. Those in chunks that aren't
+/// loaded or simulated stand still, rather than falling through the missing
+/// ground.
+pub fn update_villagers(world: &mut World, players: &[Vec3], delta_time: f32) {
+    let mut villagers = std::mem::take(&mut world.villagers);
+    for villager in &mut villagers {
+        let p = villager.position.floor().as_ivec3();
+        if world.simulates_block(p.x, p.z) && world.get_block_at(p.x, p.y, p.z).is_some() {
+            villager.update(world, players, delta_time);
+        }
+    }
+    villagers.retain(|villager| villager.position.y > -64.0);
+    world.villagers = villagers;
+}
+
+/// Triangles of the villagers: a robed figure with its arms folded in front
+/// and a big nose
+pub fn build_mesh(villagers: &[Villager], vertices: &mut Vec<DebugVertex>, indices: &mut Vec<u32>) {
+    // Size of a model pixel, as on the player figures
+    const PIXEL: f32 = 1.8 / 32.0;
+    const ROBE: [f32; 3] = [0.42, 0.28, 0.16];
+    const SLEEVES: [f32; 3] = [0.36, 0.24, 0.14];
+    const LEGS: [f32; 3] = [0.2, 0.2, 0.22];
+    const SKIN: [f32; 3] = [0.7, 0.5, 0.38];
+    const NOSE: [f32; 3] = [0.62, 0.42, 0.32];
+    const EYES: [f32; 3] = [0.1, 0.35, 0.12];
+    // Corners in model pixels, facing +z
+    let parts = [
+        ([-4.0, 0.0, -2.0], [4.0, 6.0, 2.0], LEGS),
+        ([-4.0, 6.0, -3.0], [4.0, 24.0, 3.0], ROBE),
+        ([-6.0, 15.0, 3.0], [6.0, 19.0, 7.0], SLEEVES),
+        ([-4.0, 24.0, -4.0], [4.0, 34.0, 4.0], SKIN),
+        ([-1.0, 26.0, 4.0], [1.0, 30.0, 6.0], NOSE),
+        ([-3.0, 30.0, 4.0], [-1.0, 31.0, 4.2], EYES),
+        ([1.0, 30.0, 4.0], [3.0, 31.0, 4.2], EYES),
+    ];
+    for villager in villagers {
+        let facing = Mat3::from_rotation_y(FRAC_PI_2 - villager.yaw);
+        let transform = |p: Vec3| villager.position + facing * (p * PIXEL);
+        for (min, max, color) in parts {
+            add_cuboid(vertices, indices, Vec3::from(min), Vec3::from(max), transform, color);
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! Rain and snow. While it rains the server lets puddles form on exposed
+//! ground, and snow settle instead where it is cold; once the sky clears the
+//! puddles dry up, and snow melts wherever it isn't cold. The client draws
+//! the falling drops and flakes around the camera and dims the light.
+
+use crate::block::BlockType;
+use crate::chunk::{CHUNK_HEIGHT, CHUNK_SIZE};
+use crate::debug_render::DebugVertex;
+use crate::time::Weather;
+use crate::world::World;
+use crate::world_gen::WorldGenerator;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+/// One in this many ticks a loaded chunk has one of its columns weathered
+const COLUMN_CHANCE: u32 = 4;
+/// One in this many rained-on columns of bare ground gets a puddle
+const PUDDLE_CHANCE: u32 = 4;
+/// Seconds for precipitation to start or stop entirely
+const FADE_TIME: f32 = 4.0;
+/// Columns around the camera with falling drops, in blocks
+const FALL_RADIUS: i32 = 10;
+/// Drops fall from this far above the eye, and down to this far below it
+const FALL_SPAN: f32 = 16.0;
+/// Drops per column
+const DROPS_PER_COLUMN: u32 = 2;
+/// Falling speed in blocks per second and streak length in blocks
+const RAIN_SPEED: f32 = 14.0;
+const RAIN_LENGTH: f32 = 0.6;
+const SNOW_SPEED: f32 = 2.0;
+const SNOW_LENGTH: f32 = 0.1;
+/// How far snowflakes drift sideways while falling, in blocks
+const SNOW_DRIFT: f32 = 0.3;
+const RAIN_COLOR: [f32; 4] = [0.6, 0.65, 0.8, 0.5];
+const SNOW_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.85];
+/// The animation clock wraps after this many seconds to keep its precision
+const CLOCK_WRAP: f32 = 3600.0;
+
+impl World {
+    /// Weather one random column in some of the simulated chunks: snow or
+    /// puddles collect on its top block while it rains, and melt or dry up
+    /// afterwards. Returns true if any block changed.
+    pub fn tick_precipitation(&mut self, generator: &WorldGenerator) -> bool {
+        let raining = self.time.weather() != Weather::Clear;
+        let mut changed = false;
+        // Same fixed order as the random block ticks
+        let mut chunks: Vec<(i32, i32)> = self.chunks.keys().filter(|&(x, z)| self.simulates_chunk(x, z)).collect();
+        chunks.sort_unstable();
+        for (chunk_x, chunk_z) in chunks {
+            let r = self.block_ticks.next_random();
+            if !r.is_multiple_of(COLUMN_CHANCE) {
+                continue;
+            }
+            let x = chunk_x * CHUNK_SIZE as i32 + ((r >> 8) % CHUNK_SIZE as u32) as i32;
+            let z = chunk_z * CHUNK_SIZE as i32 + ((r >> 16) % CHUNK_SIZE as u32) as i32;
+            changed |= weather_column(self, generator, (x, z), raining, r >> 24);
+        }
+        changed
+    }
+}
+
+/// Settle, melt or dry up what is on top of the column at (x, z)
+fn weather_column(world: &mut World, generator: &WorldGenerator, (x, z): (i32, i32), raining: bool, roll: u32) -> bool {
+    let Some((y, top)) = world.highest_block(x, z) else {
+        return false;
+    };
+    let cold = generator.is_cold(x, y + 1, z);
+    match top {
+        BlockType::Snow if !cold => world.set_block_at(x, y, z, BlockType::Air),
+        BlockType::Puddle if !raining || cold => world.set_block_at(x, y, z, BlockType::Air),
+        _ if !raining || y + 1 >= CHUNK_HEIGHT as i32 => false,
+        block if cold && block.is_full_cube() && !block.is_liquid() => world.set_block_at(x, y + 1, z, BlockType::Snow),
+        BlockType::Grass | BlockType::Dirt | BlockType::Stone if !cold && roll.is_multiple_of(PUDDLE_CHANCE) => {
+            world.set_block_at(x, y + 1, z, BlockType::Puddle)
+        }
+        _ => false,
+    }
+}
+
+/// How many of the falling drops and flakes are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Particles {
+    Minimal,
+    Decreased,
+    #[default]
+    All,
+}
+
+impl Particles {
+    /// Next setting for the Options button: all -> decreased -> minimal -> all
+    pub fn next(self) -> Self {
+        match self {
+            Particles::All => Particles::Decreased,
+            Particles::Decreased => Particles::Minimal,
+            Particles::Minimal => Particles::All,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Particles::Minimal => "Minimal",
+            Particles::Decreased => "Decreased",
+            Particles::All => "All",
+        }
+    }
+
+    /// Share of the columns with falling drops
+    fn fraction(self) -> f32 {
+        match self {
+            Particles::Minimal => 0.25,
+            Particles::Decreased => 0.5,
+            Particles::All => 1.0,
+        }
+    }
+}
+
+/// Falling rain and snow as the client sees it, faded in and out with the weather
+pub struct Precipitation {
+    /// How heavily it rains or snows in [0, 1]
+    strength: f32,
+    /// Share of the daylight getting through the clouds
+    light: f32,
+    /// Seconds of animation
+    clock: f32,
+    particles: Particles,
+}
+
+impl Precipitation {
+    pub fn new() -> Self {
+        Self { strength: 0.0, light: 1.0, clock: 0.0, particles: Particles::All }
+    }
+
+    pub fn set_particles(&mut self, particles: Particles) {
+        self.particles = particles;
+    }
+
+    /// Fade towards the current `weather` for `delta_time` seconds
+    pub fn update(&mut self, weather: Weather, delta_time: f32) {
+        let step = delta_time / FADE_TIME;
+        let approach = |value: f32, goal: f32| if value < goal { (value + step).min(goal) } else { (value - step).max(goal) };
+        self.strength = approach(self.strength, if weather == Weather::Clear { 0.0 } else { 1.0 });
+        self.light = approach(self.light, weather.light_factor());
+        self.clock = (self.clock + delta_time) % CLOCK_WRAP;
+    }
+
+    pub fn strength(&self) -> f32 {
+        self.strength
+    }
+
+    pub fn light(&self) -> f32 {
+        self.light
+    }
+
+    /// Streaks of the drops and flakes falling around `eye` as a line list,
+    /// replacing the contents of `vertices` and `indices`. Drops stop at the
+    /// top of each column, so none fall under a roof; while the weather fades,
+    /// and with fewer particles, fewer columns have any.
+    pub fn build_mesh(&self, world: &World, generator: &WorldGenerator, eye: Vec3, vertices: &mut Vec<DebugVertex>, indices: &mut Vec<u32>) {
+        vertices.clear();
+        indices.clear();
+        if self.strength <= 0.0 {
+            return;
+        }
+        let (eye_x, eye_z) = (eye.x.floor() as i32, eye.z.floor() as i32);
+        for x in eye_x - FALL_RADIUS..=eye_x + FALL_RADIUS {
+            for z in eye_z - FALL_RADIUS..=eye_z + FALL_RADIUS {
+                let (dx, dz) = (x - eye_x, z - eye_z);
+                if dx * dx + dz * dz > FALL_RADIUS * FALL_RADIUS || unit(hash(x, z, 0)) >= self.strength * self.particles.fraction() {
+                    continue;
+                }
+                let Some((ground_y, ground)) = world.highest_block(x, z) else {
+                    continue;
+                };
+                let bottom = (ground_y as f32 + ground.height()).max(eye.y - FALL_SPAN);
+                let top = eye.y + FALL_SPAN;
+                if bottom >= top {
+                    continue;
+                }
+                let snow = generator.is_cold(x, ground_y + 1, z);
+                let (speed, length, color) =
+                    if snow { (SNOW_SPEED, SNOW_LENGTH, SNOW_COLOR) } else { (RAIN_SPEED, RAIN_LENGTH, RAIN_COLOR) };
+                let color = [color[0], color[1], color[2], color[3] * self.strength];
+
+                for drop in 1..=DROPS_PER_COLUMN {
+                    let h = hash(x, z, drop);
+                    let phase = unit(h) * (top - bottom);
+                    let y = top - (self.clock * speed + phase) % (top - bottom);
+                    let drift = if snow { (self.clock + phase).sin() * SNOW_DRIFT } else { 0.0 };
+                    let position = Vec3::new(x as f32 + unit(h >> 8) + drift, y, z as f32 + unit(h >> 16));
+
+                    let base = vertices.len() as u32;
+                    vertices.push(DebugVertex { position: position.to_array(), color });
+                    vertices.push(DebugVertex { position: (position + Vec3::Y * length).to_array(), color });
+                    indices.extend_from_slice(&[base, base + 1]);
+                }
+            }
+        }
+    }
+}
+
+impl Default for Precipitation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Fixed pseudo-random bits for a column and drop, so drops keep their place
+fn hash(x: i32, z: i32, drop: u32) -> u32 {
+    let mut h = (x as u32).wrapping_mul(0x8DA6_B343) ^ (z as u32).wrapping_mul(0xD816_3841) ^ drop.wrapping_mul(0xCB1A_B31F);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2C1B_3C6D);
+    h ^= h >> 12;
+    h
+}
+
+/// The low 8 bits of `bits` as a fraction in [0, 1)
+fn unit(bits: u32) -> f32 {
+    (bits & 0xFF) as f32 / 256.0
+}
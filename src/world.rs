@@ -1,77 +1,344 @@
 use crate::block::BlockType;
+use crate::boat::Boat;
+use crate::block_entity::BlockEntity;
 use crate::chunk::{Chunk, CHUNK_SIZE, CHUNK_HEIGHT};
-use crate::inventory::Inventory;
-use crate::world_gen::WorldGenerator;
+use crate::chunk_map::ChunkMap;
+use crate::circuit;
+use crate::edit_history::BlockEdit;
+use crate::events::{EventBus, GameEvent};
+use crate::experience::Experience;
+use crate::fire;
+use crate::gamerules::GameRules;
+use crate::inventory::{Inventory, InventoryWithoutOffhand};
+use crate::item_entity::ItemEntity;
+use crate::physics::Aabb;
+use crate::region;
+use crate::storage::Storage;
+use crate::tick::{self, BlockTicks};
+use crate::structures::Structure;
+use crate::time::WorldTime;
+use crate::villager::Villager;
+use crate::world_gen::{TerrainMode, WorldGenerator};
+use glam::Vec3;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
+use std::sync::Arc;
+
+/// Start of a world save, before its format version. Saves from before
+/// versions start with the number of chunks instead, as a `u64`, which this
+/// would be far too many of.
+const SAVE_MAGIC: &[u8; 8] = b"RCWORLD\0";
+/// Format of the world saves written now. Bump it whenever the saved fields
+/// of `World` change, keeping the old layout as a struct that
+/// `World::migrate` reads.
+pub const SAVE_VERSION: u32 = 12;
 
 #[derive(Serialize, Deserialize)]
 pub struct World {
-    pub chunks: HashMap<(i32, i32), Chunk>,
+    /// Loaded chunks, shared with threads that read them; see `ChunkMap`
+    pub chunks: ChunkMap,
     pub seed: u32,
     pub inventory: Inventory,
+    /// Chest and furnace contents, keyed by block position
+    pub block_entities: HashMap<(i32, i32, i32), BlockEntity>,
+    /// Game time and weather
+    pub time: WorldTime,
+    /// Block new players' feet start in; None until the server picks one
+    pub spawn: Option<(i32, i32, i32)>,
+    /// Half the side of the square the world is limited to, around the
+    /// origin, in blocks; None for no border. See `world_border`.
+    pub border: Option<i32>,
+    /// Whether the terrain is a heightmap or has overhangs; fixed when the
+    /// world is created
+    pub terrain: TerrainMode,
+    /// Structures generated so far, such as dungeons
+    pub structures: Vec<Structure>,
+    /// Boats lying around, and the one being ridden
+    pub boats: Vec<Boat>,
+    /// See `gamerules`
+    pub rules: GameRules,
+    /// Villagers of the generated villages
+    pub villagers: Vec<Villager>,
+    /// The player's experience, which goes with the inventory
+    pub experience: Experience,
+    /// Items thrown out of the inventory; not saved
+    #[serde(skip)]
+    pub items: Vec<ItemEntity>,
+    /// Bounding boxes of the players, items and boats as of this tick, which
+    /// press pressure plates; set by the server, not saved
+    #[serde(skip)]
+    pub bodies: Vec<Aabb>,
+    /// Pending block updates; not saved, so a block mid-fall stops until touched again
+    #[serde(skip)]
+    pub block_ticks: BlockTicks,
+    /// Gameplay events waiting to be dispatched
+    #[serde(skip)]
+    pub events: EventBus,
+    /// Blocks set since the server last passed them on to network clients
+    #[serde(skip)]
+    pub block_changes: Vec<((i32, i32, i32), BlockType)>,
+    /// Blocks set with what they replaced, while something records them for
+    /// undo; see `EditHistory`
+    #[serde(skip)]
+    pub recorded_edits: Option<Vec<BlockEdit>>,
+    /// Chunks within the simulation distance of a player, the only ones
+    /// blocks, weather and furnaces update in; chunks farther out are only
+    /// drawn. None simulates every loaded chunk.
+    #[serde(skip)]
+    pub simulated_chunks: Option<HashSet<(i32, i32)>>,
+    /// What happened to the world since it was loaded; not saved
+    #[serde(skip)]
+    pub stats: WorldStats,
+}
+
+/// Counters of what happened to a world since it was loaded, for `/stats`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WorldStats {
+    /// Blocks set, by players and the game alike
+    pub blocks_changed: u64,
+    /// Chunks generated, rather than loaded from the save
+    pub chunks_generated: u64,
+}
+
+/// Save format 11, from before experience
+#[derive(Deserialize)]
+struct WorldWithoutExperience {
+    chunks: ChunkMap,
+    seed: u32,
+    inventory: Inventory,
+    block_entities: HashMap<(i32, i32, i32), BlockEntity>,
+    time: WorldTime,
+    spawn: Option<(i32, i32, i32)>,
+    border: Option<i32>,
+    terrain: TerrainMode,
+    structures: Vec<Structure>,
+    boats: Vec<Boat>,
+    rules: GameRules,
+    villagers: Vec<Villager>,
+}
+
+/// Save format 10, from before villagers
+#[derive(Deserialize)]
+struct WorldWithoutVillagers {
+    chunks: ChunkMap,
+    seed: u32,
+    inventory: Inventory,
+    block_entities: HashMap<(i32, i32, i32), BlockEntity>,
+    time: WorldTime,
+    spawn: Option<(i32, i32, i32)>,
+    border: Option<i32>,
+    terrain: TerrainMode,
+    structures: Vec<Structure>,
+    boats: Vec<Boat>,
+    rules: GameRules,
+}
+
+/// Save format 9, from before game rules
+#[derive(Deserialize)]
+struct WorldWithoutRules {
+    chunks: ChunkMap,
+    seed: u32,
+    inventory: Inventory,
+    block_entities: HashMap<(i32, i32, i32), BlockEntity>,
+    time: WorldTime,
+    spawn: Option<(i32, i32, i32)>,
+    border: Option<i32>,
+    terrain: TerrainMode,
+    structures: Vec<Structure>,
+    boats: Vec<Boat>,
+}
+
+/// Save format 8, from before boats
+#[derive(Deserialize)]
+struct WorldWithoutBoats {
+    chunks: ChunkMap,
+    seed: u32,
+    inventory: Inventory,
+    block_entities: HashMap<(i32, i32, i32), BlockEntity>,
+    time: WorldTime,
+    spawn: Option<(i32, i32, i32)>,
+    border: Option<i32>,
+    terrain: TerrainMode,
+    structures: Vec<Structure>,
+}
+
+/// Save format 7, from before the off-hand slot
+#[derive(Deserialize)]
+struct WorldWithoutOffhand {
+    chunks: HashMap<(i32, i32), Chunk>,
+    seed: u32,
+    inventory: InventoryWithoutOffhand,
+    block_entities: HashMap<(i32, i32, i32), BlockEntity>,
+    time: WorldTime,
+    spawn: Option<(i32, i32, i32)>,
+    border: Option<i32>,
+    terrain: TerrainMode,
+    structures: Vec<Structure>,
+}
+
+/// Save format 1, from before block entities
+#[derive(Deserialize)]
+struct WorldWithoutBlockEntities {
+    chunks: HashMap<(i32, i32), Chunk>,
+    seed: u32,
+    inventory: InventoryWithoutOffhand,
+}
+
+/// Save format 6, from before structures were recorded
+#[derive(Deserialize)]
+struct WorldWithoutStructures {
+    chunks: HashMap<(i32, i32), Chunk>,
+    seed: u32,
+    inventory: InventoryWithoutOffhand,
+    block_entities: HashMap<(i32, i32, i32), BlockEntity>,
+    time: WorldTime,
+    spawn: Option<(i32, i32, i32)>,
+    border: Option<i32>,
+    terrain: TerrainMode,
+}
+
+/// Save format 5, from before the terrain mode
+#[derive(Deserialize)]
+struct WorldWithoutTerrain {
+    chunks: HashMap<(i32, i32), Chunk>,
+    seed: u32,
+    inventory: InventoryWithoutOffhand,
+    block_entities: HashMap<(i32, i32, i32), BlockEntity>,
+    time: WorldTime,
+    spawn: Option<(i32, i32, i32)>,
+    border: Option<i32>,
+}
+
+/// Save format 4, from before the world border
+#[derive(Deserialize)]
+struct WorldWithoutBorder {
+    chunks: HashMap<(i32, i32), Chunk>,
+    seed: u32,
+    inventory: InventoryWithoutOffhand,
+    block_entities: HashMap<(i32, i32, i32), BlockEntity>,
+    time: WorldTime,
+    spawn: Option<(i32, i32, i32)>,
+}
+
+/// Save format 3, from before the spawn point was saved
+#[derive(Deserialize)]
+struct WorldWithoutSpawn {
+    chunks: HashMap<(i32, i32), Chunk>,
+    seed: u32,
+    inventory: InventoryWithoutOffhand,
+    block_entities: HashMap<(i32, i32, i32), BlockEntity>,
+    time: WorldTime,
+}
+
+/// Save format 2, from before the game time was saved
+#[derive(Deserialize)]
+struct WorldWithoutTime {
+    chunks: HashMap<(i32, i32), Chunk>,
+    seed: u32,
+    inventory: InventoryWithoutOffhand,
+    block_entities: HashMap<(i32, i32, i32), BlockEntity>,
 }
 
 impl World {
     pub fn new(seed: u32) -> Self {
         Self {
-            chunks: HashMap::new(),
+            chunks: ChunkMap::new(),
             seed,
             inventory: Inventory::with_starter_items(),
+            block_entities: HashMap::new(),
+            time: WorldTime::new(),
+            spawn: None,
+            border: None,
+            terrain: TerrainMode::Heightmap,
+            structures: Vec::new(),
+            boats: Vec::new(),
+            rules: GameRules::new(),
+            villagers: Vec::new(),
+            experience: Experience::new(),
+            items: Vec::new(),
+            bodies: Vec::new(),
+            block_ticks: BlockTicks::new(),
+            events: EventBus::new(),
+            block_changes: Vec::new(),
+            recorded_edits: None,
+            simulated_chunks: None,
+            stats: WorldStats::default(),
         }
     }
 
+    /// Rough bytes held by the chunks, block entities and items
+    pub fn memory_estimate(&self) -> usize {
+        let chunk = CHUNK_SIZE * CHUNK_HEIGHT * CHUNK_SIZE * std::mem::size_of::<BlockType>() + CHUNK_SIZE * CHUNK_SIZE;
+        self.chunks.len() * (chunk + std::mem::size_of::<Chunk>())
+            + self.block_entities.len() * std::mem::size_of::<((i32, i32, i32), BlockEntity)>()
+            + self.items.len() * std::mem::size_of::<ItemEntity>()
+    }
+
+    /// Whether the chunk at (chunk_x, chunk_z) is within the simulation distance
+    pub fn simulates_chunk(&self, chunk_x: i32, chunk_z: i32) -> bool {
+        self.simulated_chunks.as_ref().is_none_or(|chunks| chunks.contains(&(chunk_x, chunk_z)))
+    }
+
+    /// Whether the block at (x, _, z) is within the simulation distance
+    pub fn simulates_block(&self, x: i32, z: i32) -> bool {
+        self.simulates_chunk(x.div_euclid(CHUNK_SIZE as i32), z.div_euclid(CHUNK_SIZE as i32))
+    }
+
+    /// Where new and respawning players start: standing in the middle of the spawn block
+    pub fn spawn_position(&self) -> Vec3 {
+        let (x, y, z) = self.spawn.unwrap_or_default();
+        Vec3::new(x as f32 + 0.5, y as f32, z as f32 + 0.5)
+    }
+
+    /// Generate a chunk synchronously if it isn't loaded yet (the game itself
+    /// streams chunks through `ChunkLoader`; headless mode generates up front)
     pub fn load_or_generate_chunk(&mut self, x: i32, z: i32, generator: &WorldGenerator) {
-        use std::collections::hash_map::Entry;
-
-        let is_newly_generated = match self.chunks.entry((x, z)) {
-            Entry::Occupied(_) => {
-                // Der Chunk existiert bereits, nichts zu tun.
-                false
-            }, 
-            Entry::Vacant(entry) => {
-                // 1. Chunk generieren und Terrain/Blöcke füllen (OHNE Bäume!)
-                let new_chunk = generator.generate_chunk(x, z);
-                
-                // Chunk in die HashMap einfügen
-                entry.insert(new_chunk);
-
-                // Chunk wurde neu generiert
-                true 
-            }
-        };
+        // Existiert der Chunk bereits, ist nichts zu tun.
+        if self.chunks.contains_key(&(x, z)) || !self.chunk_inside_border(x, z) {
+            return;
+        }
+
+        // Chunk generieren und Terrain/Blöcke füllen (OHNE Bäume!)
+        let new_chunk = generator.generate_chunk(x, z);
+        self.insert_generated_chunk(new_chunk, generator);
+    }
+
+    /// Insert a freshly generated terrain chunk and run the global feature pass
+    /// (structures, trees, then the flora growing in the dark), which may
+    /// write into neighboring chunks.
+    pub fn insert_generated_chunk(&mut self, chunk: Chunk, generator: &WorldGenerator) {
+        let (x, z) = (chunk.x, chunk.z);
+        self.chunks.insert((x, z), chunk);
+        self.stats.chunks_generated += 1;
 
         // --- GLOBALER FEATURE-PLATZIERUNGS-SCHRITT ---
-        // Dies muss außerhalb des 'match' erfolgen, damit wir das gesamte World-Objekt 
-        // als mutable Referenz (self) verwenden können, um Blöcke global zu setzen.
-        if is_newly_generated {
-            // Bäume global platzieren, was die set_block_at Methode der World verwendet
-            // Die Bäume werden nun über Chunk-Grenzen hinweg in benachbarten Chunks gesetzt.
-            generator.place_trees(self, x, z);
-            
-            // --- Logik: Nachbarn als Dirty markieren ---
-            // Markiere alle 9 Chunks (den aktuellen und 8 Nachbarn) als 'dirty', da Bäume 
-            // sowohl in den aktuellen Chunk als auch in die Nachbarn hineinragen können.
-            for dx in -1..=1 {
-                for dz in -1..=1 {
-                    if let Some(neighbor_chunk) = self.chunks.get_mut(&(x + dx, z + dz)) {
-                        // Den Nachbarn markieren, um sein Mesh zu aktualisieren.
-                        neighbor_chunk.mark_dirty(); 
-                    }
-                }
+        // Bäume global platzieren, was die set_block_at Methode der World verwendet
+        // Die Bäume werden nun über Chunk-Grenzen hinweg in benachbarten Chunks gesetzt.
+        generator.place_structures(self, x, z);
+        generator.place_trees(self, x, z);
+        generator.place_flora(self, x, z);
+
+        // --- Logik: Nachbarn als Dirty markieren ---
+        // Markiere alle 9 Chunks (den aktuellen und 8 Nachbarn) als 'dirty', da Bäume
+        // sowohl in den aktuellen Chunk als auch in die Nachbarn hineinragen können.
+        for dx in -1..=1 {
+            for dz in -1..=1 {
+                // Den Nachbarn markieren, um sein Mesh zu aktualisieren.
+                self.chunks.update(&(x + dx, z + dz), Chunk::mark_dirty);
             }
         }
+        self.events.emit(GameEvent::ChunkLoaded { x, z });
     }
 
 
-    pub fn get_chunk(&self, x: i32, z: i32) -> Option<&Chunk> {
+    pub fn get_chunk(&self, x: i32, z: i32) -> Option<Arc<Chunk>> {
         self.chunks.get(&(x, z))
     }
 
-    pub fn get_chunk_mut(&mut self, x: i32, z: i32) -> Option<&mut Chunk> {
-        self.chunks.get_mut(&(x, z))
+    /// Change the chunk at (x, z); None if it isn't loaded
+    pub fn update_chunk<R>(&mut self, x: i32, z: i32, f: impl FnOnce(&mut Chunk) -> R) -> Option<R> {
+        self.chunks.update(&(x, z), f)
     }
 
     pub fn get_block_at(&self, x: i32, y: i32, z: i32) -> Option<BlockType> {
@@ -89,68 +356,393 @@ impl World {
         let local_z = z.rem_euclid(CHUNK_SIZE as i32) as usize;
 
         // Get the chunk and the block
-        self.get_chunk(chunk_x, chunk_z)
-            .map(|chunk| chunk.get_block(local_x, y as usize, local_z))
+        self.chunks.with(&(chunk_x, chunk_z), |chunk| chunk.get_block(local_x, y as usize, local_z))
+    }
+
+    /// Topmost non-air block of the column at (x, z) and its height; None if
+    /// the chunk isn't loaded or the column is empty
+    pub fn highest_block(&self, x: i32, z: i32) -> Option<(i32, BlockType)> {
+        let (local_x, local_z) = (x.rem_euclid(CHUNK_SIZE as i32) as usize, z.rem_euclid(CHUNK_SIZE as i32) as usize);
+        self.chunks
+            .with(&(x.div_euclid(CHUNK_SIZE as i32), z.div_euclid(CHUNK_SIZE as i32)), |chunk| {
+                let top = chunk.height(local_x, local_z).checked_sub(1)?;
+                Some((top as i32, chunk.get_block(local_x, top, local_z)))
+            })
+            .flatten()
     }
 
     pub fn set_block_at(&mut self, x: i32, y: i32, z: i32, block: BlockType) -> bool {
-        // Check if y is within valid bounds
-        if y < 0 || y >= CHUNK_HEIGHT as i32 {
+        let Some(previous) = self.replace_block(x, y, z, block) else {
             return false;
+        };
+
+        let (chunk_x, chunk_z) = (x.div_euclid(CHUNK_SIZE as i32), z.div_euclid(CHUNK_SIZE as i32));
+        let (local_x, local_z) = (x.rem_euclid(CHUNK_SIZE as i32) as usize, z.rem_euclid(CHUNK_SIZE as i32) as usize);
+        // Block light reaches into the chunks around; other changes only
+        // show in a neighbor if the block is on its edge
+        if previous.light_emission() > 0 || block.light_emission() > 0 {
+            self.mark_around_dirty(chunk_x, chunk_z);
+        } else if local_x == 0 {
+            self.update_chunk(chunk_x - 1, chunk_z, Chunk::mark_dirty);
+        } else if local_x == CHUNK_SIZE - 1 {
+            self.update_chunk(chunk_x + 1, chunk_z, Chunk::mark_dirty);
         }
 
-        // Calculate which chunk this block belongs to
-        let chunk_x = x.div_euclid(CHUNK_SIZE as i32);
-        let chunk_z = z.div_euclid(CHUNK_SIZE as i32);
-        
-        // Calculate local coordinates within the chunk
-        let local_x = x.rem_euclid(CHUNK_SIZE as i32) as usize;
-        let local_z = z.rem_euclid(CHUNK_SIZE as i32) as usize;
+        if local_z == 0 {
+            self.update_chunk(chunk_x, chunk_z - 1, Chunk::mark_dirty);
+        } else if local_z == CHUNK_SIZE - 1 {
+            self.update_chunk(chunk_x, chunk_z + 1, Chunk::mark_dirty);
+        }
+
+        true
+    }
+
+    /// Set every block from `min` to `max`, corners included, to `block`.
+    /// Returns how many blocks changed; see `set_blocks`.
+    pub fn fill_region(&mut self, min: (i32, i32, i32), max: (i32, i32, i32), block: BlockType) -> usize {
+        self.set_blocks(region::cuboid(min, max).map(|pos| (pos, block)))
+    }
+
+    /// Set many blocks at once, like `set_block_at` each, but marking the
+    /// chunks changed and those around them dirty only once at the end.
+    /// Blocks already right and those in chunks that aren't loaded are
+    /// skipped. Returns how many blocks changed.
+    pub fn set_blocks(&mut self, blocks: impl IntoIterator<Item = ((i32, i32, i32), BlockType)>) -> usize {
+        let mut changed_chunks = HashSet::new();
+        let mut count = 0;
+        for ((x, y, z), block) in blocks {
+            if self.get_block_at(x, y, z).is_none_or(|current| current == block) {
+                continue;
+            }
+            if self.replace_block(x, y, z, block).is_some() {
+                changed_chunks.insert((x.div_euclid(CHUNK_SIZE as i32), z.div_euclid(CHUNK_SIZE as i32)));
+                count += 1;
+            }
+        }
+        for (chunk_x, chunk_z) in changed_chunks {
+            self.mark_around_dirty(chunk_x, chunk_z);
+        }
+        count
+    }
 
-        // Set the block
-        if let Some(chunk) = self.get_chunk_mut(chunk_x, chunk_z) {
+    /// Put `block` into its chunk with everything that goes with it: the
+    /// block entity, falling blocks, fire, circuits, plants popping off and
+    /// the records of the change. Only the chunk itself is marked dirty. Returns
+    /// the block replaced, None if the chunk isn't loaded.
+    fn replace_block(&mut self, x: i32, y: i32, z: i32, block: BlockType) -> Option<BlockType> {
+        // Check if y is within valid bounds
+        if y < 0 || y >= CHUNK_HEIGHT as i32 {
+            return None;
+        }
+        let (local_x, local_z) = (x.rem_euclid(CHUNK_SIZE as i32) as usize, z.rem_euclid(CHUNK_SIZE as i32) as usize);
+        let previous = self.update_chunk(x.div_euclid(CHUNK_SIZE as i32), z.div_euclid(CHUNK_SIZE as i32), |chunk| {
+            let previous = chunk.get_block(local_x, y as usize, local_z);
             chunk.set_block(local_x, y as usize, local_z, block);
-            
-            // Mark neighboring chunks as dirty if block is on chunk edge
-            if local_x == 0 {
-                if let Some(neighbor) = self.get_chunk_mut(chunk_x - 1, chunk_z) {
-                    neighbor.mark_dirty();
-                }
-            } else if local_x == CHUNK_SIZE - 1 {
-                if let Some(neighbor) = self.get_chunk_mut(chunk_x + 1, chunk_z) {
-                    neighbor.mark_dirty();
-                }
+            previous
+        })?;
+        self.block_changes.push(((x, y, z), block));
+        self.stats.blocks_changed += 1;
+        if let Some(edits) = &mut self.recorded_edits {
+            edits.push(BlockEdit { pos: (x, y, z), old: previous, new: block });
+        }
+
+        // Replacing a block discards its block entity; containers get a fresh one
+        self.block_entities.remove(&(x, y, z));
+        if let Some(entity) = BlockEntity::for_block(block) {
+            self.block_entities.insert((x, y, z), entity);
+        }
+
+        // Falling blocks react to the change: the block itself and one resting on it
+        for above in [y, y + 1] {
+            if self.get_block_at(x, above, z).is_some_and(|b| b.has_gravity()) {
+                self.block_ticks.schedule((x, above, z), tick::FALL_DELAY);
             }
-            
-            if local_z == 0 {
-                if let Some(neighbor) = self.get_chunk_mut(chunk_x, chunk_z - 1) {
-                    neighbor.mark_dirty();
-                }
-            } else if local_z == CHUNK_SIZE - 1 {
-                if let Some(neighbor) = self.get_chunk_mut(chunk_x, chunk_z + 1) {
-                    neighbor.mark_dirty();
-                }
+        }
+        if block == BlockType::Fire {
+            self.block_ticks.schedule((x, y, z), fire::FIRE_DELAY);
+        }
+        if circuit::changes_circuit(previous, block) {
+            self.block_ticks.schedule((x, y, z), circuit::CIRCUIT_DELAY);
+        }
+        // Plants pop off when the ground under them goes
+        if !block.is_full_cube() && self.get_block_at(x, y + 1, z).is_some_and(|b| b.is_plant()) {
+            self.set_block_at(x, y + 1, z, BlockType::Air);
+        }
+        Some(previous)
+    }
+
+    /// Mark the chunk and the eight around it dirty
+    fn mark_around_dirty(&mut self, chunk_x: i32, chunk_z: i32) {
+        for dx in -1..=1 {
+            for dz in -1..=1 {
+                self.update_chunk(chunk_x + dx, chunk_z + dz, Chunk::mark_dirty);
+            }
+        }
+    }
+
+    /// Whether `block` can be placed at `pos` as far as the blocks around are
+    /// concerned: those that need a floor get a full block under them and
+    /// stay out of water
+    pub fn can_place(&self, pos: (i32, i32, i32), block: BlockType) -> bool {
+        if !block.needs_floor() {
+            return true;
+        }
+        let (x, y, z) = pos;
+        let floor = self.get_block_at(x, y - 1, z).is_some_and(|below| below.is_full_cube());
+        let dry = !self.get_block_at(x, y, z).is_some_and(|current| current.is_liquid());
+        floor && dry
+    }
+
+    /// Whether a dropped item, a boat or a villager reaches into `cell`, where
+    /// a solid block would trap it
+    pub fn entity_in(&self, cell: &Aabb) -> bool {
+        self.items.iter().any(|item| item.bounding_box().intersects(cell))
+            || self.boats.iter().any(|boat| boat.bounding_box().intersects(cell))
+            || self.villagers.iter().any(|villager| villager.bounding_box().intersects(cell))
+    }
+
+    pub fn block_entity(&self, pos: (i32, i32, i32)) -> Option<&BlockEntity> {
+        self.block_entities.get(&pos)
+    }
+
+    /// Advance the furnaces within the simulation distance by `delta_time`
+    /// seconds. Returns true if any of them changed.
+    pub fn update_block_entities(&mut self, delta_time: f32) -> bool {
+        let mut changed = false;
+        let simulated = self.simulated_chunks.as_ref();
+        for (&(x, _, z), entity) in self.block_entities.iter_mut() {
+            let chunk = (x.div_euclid(CHUNK_SIZE as i32), z.div_euclid(CHUNK_SIZE as i32));
+            if simulated.is_none_or(|chunks| chunks.contains(&chunk)) {
+                changed |= entity.update(delta_time);
             }
-            
-            true
-        } else {
-            false
         }
+        changed
     }
 
-    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let encoded = bincode::serialize(self)?;
-        fs::write(path, encoded)?;
+    /// Write the world to `path`: `SAVE_MAGIC`, the format version, then
+    /// the world
+    pub fn save(&self, storage: &dyn Storage, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut data = SAVE_MAGIC.to_vec();
+        data.extend(SAVE_VERSION.to_le_bytes());
+        bincode::serialize_into(&mut data, self)?;
+        storage.write(Path::new(path), &data)?;
         Ok(())
     }
 
-    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        if Path::new(path).exists() {
-            let data = fs::read(path)?;
-            let world = bincode::deserialize(&data)?;
-            Ok(world)
-        } else {
-            Ok(World::new(12345))
+    /// Note that the chunks are in the world file now, after `save`
+    pub fn mark_saved(&mut self) {
+        let unsaved: Vec<_> = self.chunks.keys().filter(|key| self.chunks.with(key, |chunk| chunk.unsaved) == Some(true)).collect();
+        for key in unsaved {
+            self.chunks.update(&key, |chunk| chunk.unsaved = false);
+        }
+    }
+
+    /// Read the world saved at `path`, migrating saves of older formats; a
+    /// new world if there is none
+    pub fn load(storage: &dyn Storage, path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = Path::new(path);
+        if !storage.exists(path) {
+            return Ok(World::new(12345));
         }
+        let data = storage.read(path)?;
+        let (version, world) = match data.strip_prefix(SAVE_MAGIC) {
+            Some(rest) if rest.len() >= 4 => (u32::from_le_bytes(rest[..4].try_into().unwrap()), &rest[4..]),
+            Some(_) => return Err("The world save is cut short".into()),
+            None => (unversioned_format(&data).ok_or("Not a world save")?, &data[..]),
+        };
+        match version {
+            SAVE_VERSION => Ok(bincode::deserialize(world)?),
+            version if version > SAVE_VERSION => {
+                Err(format!("The world was saved in format {}, by a newer version of the game", version).into())
+            }
+            version => {
+                log::info!("Migrating the world from save format {} to {}", version, SAVE_VERSION);
+                Self::migrate(version, world)
+            }
+        }
+    }
+
+    /// Read a world saved in the older format `version`, filling in what that
+    /// format didn't have yet
+    fn migrate(version: u32, data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let world = match version {
+            11 => {
+                let old: WorldWithoutExperience = bincode::deserialize(data)?;
+                Self {
+                    chunks: old.chunks,
+                    seed: old.seed,
+                    inventory: old.inventory,
+                    block_entities: old.block_entities,
+                    time: old.time,
+                    spawn: old.spawn,
+                    border: old.border,
+                    terrain: old.terrain,
+                    structures: old.structures,
+                    boats: old.boats,
+                    rules: old.rules,
+                    villagers: old.villagers,
+                    ..Self::new(old.seed)
+                }
+            }
+            10 => {
+                let old: WorldWithoutVillagers = bincode::deserialize(data)?;
+                Self {
+                    chunks: old.chunks,
+                    seed: old.seed,
+                    inventory: old.inventory,
+                    block_entities: old.block_entities,
+                    time: old.time,
+                    spawn: old.spawn,
+                    border: old.border,
+                    terrain: old.terrain,
+                    structures: old.structures,
+                    boats: old.boats,
+                    rules: old.rules,
+                    ..Self::new(old.seed)
+                }
+            }
+            9 => {
+                let old: WorldWithoutRules = bincode::deserialize(data)?;
+                Self {
+                    chunks: old.chunks,
+                    seed: old.seed,
+                    inventory: old.inventory,
+                    block_entities: old.block_entities,
+                    time: old.time,
+                    spawn: old.spawn,
+                    border: old.border,
+                    terrain: old.terrain,
+                    structures: old.structures,
+                    boats: old.boats,
+                    ..Self::new(old.seed)
+                }
+            }
+            8 => {
+                let old: WorldWithoutBoats = bincode::deserialize(data)?;
+                Self {
+                    chunks: old.chunks,
+                    seed: old.seed,
+                    inventory: old.inventory,
+                    block_entities: old.block_entities,
+                    time: old.time,
+                    spawn: old.spawn,
+                    border: old.border,
+                    terrain: old.terrain,
+                    structures: old.structures,
+                    ..Self::new(old.seed)
+                }
+            }
+            7 => {
+                let old: WorldWithoutOffhand = bincode::deserialize(data)?;
+                Self {
+                    chunks: old.chunks.into(),
+                    seed: old.seed,
+                    inventory: old.inventory.into(),
+                    block_entities: old.block_entities,
+                    time: old.time,
+                    spawn: old.spawn,
+                    border: old.border,
+                    terrain: old.terrain,
+                    structures: old.structures,
+                    ..Self::new(old.seed)
+                }
+            }
+            6 => {
+                let old: WorldWithoutStructures = bincode::deserialize(data)?;
+                Self {
+                    chunks: old.chunks.into(),
+                    seed: old.seed,
+                    inventory: old.inventory.into(),
+                    block_entities: old.block_entities,
+                    time: old.time,
+                    spawn: old.spawn,
+                    border: old.border,
+                    terrain: old.terrain,
+                    ..Self::new(old.seed)
+                }
+            }
+            5 => {
+                let old: WorldWithoutTerrain = bincode::deserialize(data)?;
+                Self {
+                    chunks: old.chunks.into(),
+                    seed: old.seed,
+                    inventory: old.inventory.into(),
+                    block_entities: old.block_entities,
+                    time: old.time,
+                    spawn: old.spawn,
+                    border: old.border,
+                    ..Self::new(old.seed)
+                }
+            }
+            4 => {
+                let old: WorldWithoutBorder = bincode::deserialize(data)?;
+                Self {
+                    chunks: old.chunks.into(),
+                    seed: old.seed,
+                    inventory: old.inventory.into(),
+                    block_entities: old.block_entities,
+                    time: old.time,
+                    spawn: old.spawn,
+                    ..Self::new(old.seed)
+                }
+            }
+            3 => {
+                let old: WorldWithoutSpawn = bincode::deserialize(data)?;
+                Self {
+                    chunks: old.chunks.into(),
+                    seed: old.seed,
+                    inventory: old.inventory.into(),
+                    block_entities: old.block_entities,
+                    time: old.time,
+                    ..Self::new(old.seed)
+                }
+            }
+            2 => {
+                let old: WorldWithoutTime = bincode::deserialize(data)?;
+                Self {
+                    chunks: old.chunks.into(),
+                    seed: old.seed,
+                    inventory: old.inventory.into(),
+                    block_entities: old.block_entities,
+                    ..Self::new(old.seed)
+                }
+            }
+            1 => {
+                let old: WorldWithoutBlockEntities = bincode::deserialize(data)?;
+                Self {
+                    chunks: old.chunks.into(),
+                    seed: old.seed,
+                    inventory: old.inventory.into(),
+                    ..Self::new(old.seed)
+                }
+            }
+            _ => return Err(format!("Unknown world save format {}", version).into()),
+        };
+        Ok(world)
+    }
+}
+
+/// Format of a save written before saves started with their version: the
+/// newest layout it reads as, as bincode leaves the fields a layout doesn't
+/// have unread. None if it is no world save.
+fn unversioned_format(data: &[u8]) -> Option<u32> {
+    fn reads_as<T: serde::de::DeserializeOwned>(data: &[u8]) -> bool {
+        bincode::deserialize::<T>(data).is_ok()
     }
+    let reads = |version| match version {
+        SAVE_VERSION => reads_as::<World>(data),
+        11 => reads_as::<WorldWithoutExperience>(data),
+        10 => reads_as::<WorldWithoutVillagers>(data),
+        9 => reads_as::<WorldWithoutRules>(data),
+        8 => reads_as::<WorldWithoutBoats>(data),
+        7 => reads_as::<WorldWithoutOffhand>(data),
+        6 => reads_as::<WorldWithoutStructures>(data),
+        5 => reads_as::<WorldWithoutTerrain>(data),
+        4 => reads_as::<WorldWithoutBorder>(data),
+        3 => reads_as::<WorldWithoutSpawn>(data),
+        2 => reads_as::<WorldWithoutTime>(data),
+        1 => reads_as::<WorldWithoutBlockEntities>(data),
+        _ => false,
+    };
+    (1..=SAVE_VERSION).rev().find(|&version| reads(version))
 }
\ No newline at end of file
@@ -0,0 +1,82 @@
+//! The optional world border: a square `World::border` blocks either side of
+//! the origin. Chunks wholly outside it are never generated, players, items,
+//! boats, villagers and fishing bobbers are pushed back inside, and the client draws it as a translucent striped wall
+//! that fades in as the camera comes near (see `border_shader.wgsl`).
+
+use crate::chunk::{CHUNK_HEIGHT, CHUNK_SIZE};
+use crate::debug_render::DebugVertex;
+use crate::world::World;
+use glam::Vec3;
+
+/// Smallest and largest border, in blocks from the origin
+pub const MIN_BORDER: i32 = 16;
+pub const MAX_BORDER: i32 = 100_000;
+const WALL_COLOR: [f32; 4] = [0.25, 0.55, 1.0, 0.6];
+
+impl World {
+    /// Whether any block of the chunk at (chunk_x, chunk_z) is inside the border
+    pub fn chunk_inside_border(&self, chunk_x: i32, chunk_z: i32) -> bool {
+        let Some(radius) = self.border else {
+            return true;
+        };
+        let size = CHUNK_SIZE as i32;
+        let overlaps = |chunk: i32| chunk * size < radius && (chunk + 1) * size > -radius;
+        overlaps(chunk_x) && overlaps(chunk_z)
+    }
+
+    /// `position` moved back inside the border, at least `margin` blocks away
+    /// from it; unchanged if it already is
+    pub fn clamp_to_border(&self, position: Vec3, margin: f32) -> Vec3 {
+        let Some(radius) = self.border else {
+            return position;
+        };
+        let limit = (radius as f32 - margin).max(0.0);
+        Vec3::new(position.x.clamp(-limit, limit), position.y, position.z.clamp(-limit, limit))
+    }
+
+    /// Push something at `position` moving at `velocity` back inside the
+    /// border, `margin` blocks from it, stopping it along the axes it was
+    /// pushed on. Returns whether it was outside.
+    pub fn push_inside_border(&self, position: &mut Vec3, velocity: &mut Vec3, margin: f32) -> bool {
+        let inside = self.clamp_to_border(*position, margin);
+        if inside.x != position.x {
+            velocity.x = 0.0;
+        }
+        if inside.z != position.z {
+            velocity.z = 0.0;
+        }
+        let outside = inside != *position;
+        *position = inside;
+        outside
+    }
+}
+
+/// A border radius argument: blocks from the origin, or `off`
+pub fn parse_radius(arg: &str) -> Result<Option<i32>, String> {
+    if arg == "off" {
+        return Ok(None);
+    }
+    match arg.parse::<i32>() {
+        Ok(radius) if (MIN_BORDER..=MAX_BORDER).contains(&radius) => Ok(Some(radius)),
+        _ => Err(format!("Invalid border: {} (use {} to {} blocks, or off)", arg, MIN_BORDER, MAX_BORDER)),
+    }
+}
+
+/// The four walls of a border `radius` blocks from the origin, from bedrock
+/// to the build limit, as translucent quads for the border pipeline,
+/// replacing the contents of `vertices` and `indices`
+pub fn build_wall_mesh(radius: i32, vertices: &mut Vec<DebugVertex>, indices: &mut Vec<u32>) {
+    vertices.clear();
+    indices.clear();
+    let (r, height) = (radius as f32, CHUNK_HEIGHT as f32);
+    let corners = [(-r, -r), (r, -r), (r, r), (-r, r)];
+    for i in 0..4 {
+        let (ax, az) = corners[i];
+        let (bx, bz) = corners[(i + 1) % 4];
+        let base = vertices.len() as u32;
+        for (x, y, z) in [(ax, 0.0, az), (bx, 0.0, bz), (bx, height, bz), (ax, height, az)] {
+            vertices.push(DebugVertex { position: [x, y, z], color: WALL_COLOR });
+        }
+        indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+}
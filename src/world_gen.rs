@@ -1,7 +1,10 @@
 use crate::block::BlockType;
 use crate::chunk::{Chunk, CHUNK_HEIGHT, CHUNK_SIZE};
+use crate::loot::LootTables;
 use crate::world::World;
 use noise::{NoiseFn, Perlin};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 // --- Neue Konstanten für erweiterte Weltgenerierung (FBM und Wasserlinie) ---
 
@@ -15,18 +18,144 @@ const MIN_TREE_DISTANCE: i32 = 6;
 // Allgemeine Parameter
 pub const WATER_LEVEL: usize = 40; // Die Höhe der Meeresoberfläche
 
+/// Scale of the climate noise; cold and warm regions span a few hundred blocks
+const CLIMATE_FREQUENCY: f64 = 0.003;
+/// Temperature drop per block above the water line
+const TEMPERATURE_LAPSE: f64 = 1.0 / 40.0;
+/// Places colder than this get snow instead of rain
+const COLD_TEMPERATURE: f64 = -0.35;
+/// Mixed into the seed so the climate doesn't follow the terrain noise
+const CLIMATE_SEED: u32 = 0x5EA5_0115;
+/// Scale of the noise laying out the mountain biome
+const MOUNTAIN_FREQUENCY: f64 = 0.004;
+/// Mixed into the seed for the mountain noise
+const MOUNTAIN_SEED: u32 = 0x3A0_7A15;
+/// Mountain noise where mountains start rising, and where they are at full height
+const MOUNTAIN_START: f64 = 0.1;
+const MOUNTAIN_FULL: f64 = 0.4;
+/// Height variation in mountains, relative to the rest of the terrain, and
+/// how far mountains lift the terrain on top of that
+const MOUNTAIN_AMPLIFY: f64 = 1.8;
+const MOUNTAIN_LIFT: f64 = 3.0;
+/// Heights above which terrain is squeezed under the build limit instead of
+/// being cut off flat
+const SOFT_CEILING: f64 = 50.0;
+/// Mountain columns at least this high are bare stone, and from the snow
+/// line up they are capped with snow
+const STONE_LINE: usize = WATER_LEVEL + 14;
+const SNOW_LINE: usize = WATER_LEVEL + 17;
+/// Scale of the noise laying out gravel and clay patches and rocky shores
+const SURFACE_FREQUENCY: f64 = 0.05;
+/// Mixed into the seed for the surface noise
+const SURFACE_SEED: u32 = 0x6A7E_1C1A;
+/// Blocks below the water line that beach sand reaches
+const SHORE_DEPTH: usize = 2;
+/// Surface noise above which ocean floors are gravel, and below which clay
+const PATCH_THRESHOLD: f64 = 0.3;
+/// Shores are rocky where the land rises this many blocks within
+/// `SLOPE_DISTANCE`, give or take the surface noise; only the slopes of
+/// mountains are this steep
+const STEEP_SHORE: f64 = 4.0;
+const SLOPE_DISTANCE: i32 = 8;
+/// Scale of the noise scattering mushrooms and glowing fungus; high enough
+/// that neighboring columns are unrelated
+const FLORA_FREQUENCY: f64 = 0.91;
+/// Flora noise above which a dark spot grows a mushroom, and above which
+/// glowing fungus instead
+const MUSHROOM_THRESHOLD: f64 = 0.3;
+const FUNGUS_THRESHOLD: f64 = 0.7;
+/// Scale of the 3D noise shaping cliffs and overhangs in density terrain;
+/// vertical features are a little tighter than horizontal ones
+const DENSITY_FREQUENCY: f64 = 0.04;
+const DENSITY_VERTICAL_FREQUENCY: f64 = 0.1;
+/// Mixed into the seed for the density noise
+const DENSITY_SEED: u32 = 0x0DE2_5171;
+/// Blocks above and below the heightmap surface over which the density noise
+/// can add overhangs or carve into the ground
+const DENSITY_FALLOFF: f64 = 16.0;
+/// Bottom layers kept solid in density terrain, so nothing opens into the void
+const SOLID_FLOOR: usize = 4;
+/// How far from the origin to look for dry, flat land to spawn on, and the
+/// spacing of the columns tried
+const SPAWN_SEARCH_RADIUS: i32 = 512;
+const SPAWN_SEARCH_STEP: i32 = 4;
+
+/// How the generator decides which blocks are solid; saved with the world
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+pub enum TerrainMode {
+    /// Every column is solid up to the height of the terrain
+    #[default]
+    Heightmap,
+    /// The heightmap blended with 3D noise near the surface, which forms
+    /// cliffs, overhangs, arches and caves open to the sky
+    Density,
+}
+
+/// Tunable terrain shape; the defaults are the constants above
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TerrainParams {
+    pub mode: TerrainMode,
+    pub octaves: u32,
+    pub base_frequency: f64,
+    pub persistence: f64,
+    pub lacunarity: f64,
+    /// Height variation in blocks above and below the base height
+    pub height_scale: f64,
+    /// Average terrain height above the water line
+    pub base_height: f64,
+    /// Tree noise threshold; higher means fewer trees
+    pub tree_threshold: f64,
+}
+
+impl Default for TerrainParams {
+    fn default() -> Self {
+        Self {
+            mode: TerrainMode::Heightmap,
+            octaves: NUM_OCTAVES,
+            base_frequency: BASE_FREQUENCY,
+            persistence: PERSISTENCE,
+            lacunarity: LACUNARITY,
+            height_scale: 15.0,
+            base_height: 15.0,
+            tree_threshold: 0.6,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct WorldGenerator {
     noise: Perlin,
+    climate: Perlin,
+    surface: Perlin,
+    mountains: Perlin,
+    density: Perlin,
+    pub params: TerrainParams,
+    /// What the chests of structures are filled with
+    pub loot: Arc<LootTables>,
 }
 
 impl WorldGenerator {
     pub fn new(seed: u32) -> Self {
+        Self::with_params(seed, TerrainParams::default())
+    }
+
+    pub fn with_params(seed: u32, params: TerrainParams) -> Self {
         Self {
             noise: Perlin::new(seed),
+            climate: Perlin::new(seed ^ CLIMATE_SEED),
+            surface: Perlin::new(seed ^ SURFACE_SEED),
+            mountains: Perlin::new(seed ^ MOUNTAIN_SEED),
+            density: Perlin::new(seed ^ DENSITY_SEED),
+            params,
+            loot: Arc::new(LootTables::builtin()),
         }
     }
 
-    
+    /// The generator filling chests from `loot` instead of the built-in tables
+    pub fn with_loot(self, loot: Arc<LootTables>) -> Self {
+        Self { loot, ..self }
+    }
+
 
     pub fn should_generate_tree(&self, world_x: i32, world_z: i32) -> bool {
         if world_x % MIN_TREE_DISTANCE != 0 || world_z % MIN_TREE_DISTANCE != 0 {
@@ -34,10 +163,11 @@ impl WorldGenerator {
         }
 
         let height = self.get_height(world_x as f64, world_z as f64);
-        let top_block_is_grass = height > WATER_LEVEL + 2; 
+        let top_block_is_grass = self.surface_blocks(world_x, world_z, height).0 == BlockType::Grass
+            && !self.has_snow_cap(world_x, world_z, height);
         let tree_noise = self.noise.get([world_x as f64 * 0.05, world_z as f64 * 0.05]);
         
-        if top_block_is_grass && tree_noise > 0.6 {
+        if top_block_is_grass && tree_noise > self.params.tree_threshold {
             return true;
         }
         false
@@ -47,18 +177,18 @@ impl WorldGenerator {
     // Diese Funktion wird jetzt auch im main-Block verwendet, um die Spawn-Höhe zu bestimmen.
     pub fn get_height(&self, x: f64, z: f64) -> usize {
         let mut amplitude = 1.0;
-        let mut frequency = BASE_FREQUENCY;
+        let mut frequency = self.params.base_frequency;
         let mut total_noise = 0.0;
         let mut total_amplitude = 0.0;
         
         // FBM implementieren, um das Rauschen über mehrere Oktaven zu mischen
-        for _ in 0..NUM_OCTAVES {
+        for _ in 0..self.params.octaves.max(1) {
             let noise_value = self.noise.get([x * frequency, z * frequency]);
             total_noise += noise_value * amplitude;
             total_amplitude += amplitude;
             
-            amplitude *= PERSISTENCE;
-            frequency *= LACUNARITY;
+            amplitude *= self.params.persistence;
+            frequency *= self.params.lacunarity;
         }
 
         // Normalisieren (resultierendes Rauschen ist jetzt im Bereich [-1.0, 1.0])
@@ -66,10 +196,77 @@ impl WorldGenerator {
         
         // Skalieren und Verschieben zur gewünschten Höhe. 
         // Basis ist WATER_LEVEL + 15. Amplitude von 15.0 ergibt Höhen von ca. 40 bis 70.
-        let height = (normalized_noise * 15.0 + (WATER_LEVEL as f64 + 15.0)) as usize; 
+        // Im Gebirge wird die Amplitude verstärkt und das Gelände angehoben.
+        let mountain = self.mountain_factor(x, z);
+        let amplify = 1.0 + mountain * (MOUNTAIN_AMPLIFY - 1.0);
+        let mut height = normalized_noise * self.params.height_scale * amplify
+            + (WATER_LEVEL as f64 + self.params.base_height)
+            + mountain * MOUNTAIN_LIFT;
+
+        // Gipfel über der weichen Obergrenze werden gestaucht statt abgeschnitten
+        let room = (CHUNK_HEIGHT - 5) as f64 - SOFT_CEILING;
+        if height > SOFT_CEILING {
+            height = SOFT_CEILING + room * (1.0 - (-(height - SOFT_CEILING) / room).exp());
+        }
         
         // Sicherstellen, dass die Höhe innerhalb der Grenzen liegt
-        height.min(CHUNK_HEIGHT - 5).max(1) 
+        (height as usize).clamp(1, CHUNK_HEIGHT - 5)
+    }
+
+    /// How much the column lies in the mountain biome, from 0 (not at all)
+    /// to 1, easing in at the edges so mountains rise out of the plains
+    pub fn mountain_factor(&self, x: f64, z: f64) -> f64 {
+        let noise = self.mountains.get([x * MOUNTAIN_FREQUENCY, z * MOUNTAIN_FREQUENCY]);
+        let t = ((noise - MOUNTAIN_START) / (MOUNTAIN_FULL - MOUNTAIN_START)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    /// Columns more in the mountains than out of them
+    pub fn is_mountain(&self, x: i32, z: i32) -> bool {
+        self.mountain_factor(x as f64, z as f64) > 0.5
+    }
+
+    /// Mountain peaks `height` blocks high get a layer of snow on top
+    pub fn has_snow_cap(&self, x: i32, z: i32, height: usize) -> bool {
+        height >= SNOW_LINE && self.is_mountain(x, z)
+    }
+
+    /// Where new players appear: the column nearest the origin that is above
+    /// the water, level with its neighbors and clear of trees, and inside the
+    /// world `border` if there is one. Returns the block the feet are in; the
+    /// origin if no such column is in range.
+    pub fn find_spawn(&self, border: Option<i32>) -> (i32, i32, i32) {
+        let height = |x: i32, z: i32| self.surface_height(x, z) as i32;
+        let suitable = |x: i32, z: i32| {
+            let h = height(x, z);
+            h > WATER_LEVEL as i32
+                && [(1, 0), (-1, 0), (0, 1), (0, -1)].iter().all(|(dx, dz)| (height(x + dx, z + dz) - h).abs() <= 1)
+                && (-2..=2).all(|dx| (-2..=2).all(|dz| !self.should_generate_tree(x + dx, z + dz)))
+        };
+        // Rings of growing radius around the origin
+        let search_radius = border.map_or(SPAWN_SEARCH_RADIUS, |border| SPAWN_SEARCH_RADIUS.min(border - 1));
+        for radius in (0..=search_radius).step_by(SPAWN_SEARCH_STEP as usize) {
+            for i in (-radius..=radius).step_by(SPAWN_SEARCH_STEP as usize) {
+                for (x, z) in [(i, -radius), (i, radius), (-radius, i), (radius, i)] {
+                    if suitable(x, z) {
+                        return (x, height(x, z), z);
+                    }
+                }
+            }
+        }
+        (0, height(0, 0), 0)
+    }
+
+    /// Temperature at a block, about -1 (cold) to 1 (warm): a slowly changing
+    /// climate that gets colder with altitude
+    pub fn temperature(&self, x: i32, y: i32, z: i32) -> f64 {
+        let climate = self.climate.get([x as f64 * CLIMATE_FREQUENCY, z as f64 * CLIMATE_FREQUENCY]);
+        climate - (y - WATER_LEVEL as i32).max(0) as f64 * TEMPERATURE_LAPSE
+    }
+
+    /// Precipitation at this block falls as snow
+    pub fn is_cold(&self, x: i32, y: i32, z: i32) -> bool {
+        self.temperature(x, y, z) < COLD_TEMPERATURE
     }
 
     pub fn generate_chunk(&self, chunk_x: i32, chunk_z: i32) -> Chunk {
@@ -81,48 +278,124 @@ impl WorldGenerator {
                 let world_z = (chunk_z * CHUNK_SIZE as i32 + z as i32) as f64;
 
                 let height = self.get_height(world_x, world_z);
+                let (world_x, world_z) = (world_x as i32, world_z as i32);
 
-
-                // --- Verbesserte Biome- und Schichtlogik ---
-                
-                // Bestimme die oberste feste Schicht (Strand vs. Grasland)
-                let top_block = if height <= WATER_LEVEL + 2 {
-                    BlockType::Sand // Niedriges Land wird Sand (Strand)
-                } else {
-                    BlockType::Grass // Höheres Land wird Grasland
-                };
-                
-                // Bestimme die Subschicht (unter der obersten Schicht)
-                let sub_block = if top_block == BlockType::Sand {
-                    BlockType::Sand // Sand unter Sand (tieferer Sandstrand)
-                } else {
-                    BlockType::Dirt // Dirt unter Gras
-                };
-
-                for y in 0..CHUNK_HEIGHT {
-                    let block = if y < height {
-                        if y < height - 8 {
+                // Von oben nach unten: jede freiliegende Oberfläche bekommt
+                // ihre Schichten, gemessen ab dem Block darüber
+                let mut surface = None;
+                let mut topmost = true;
+                for y in (0..CHUNK_HEIGHT).rev() {
+                    let block = if self.is_solid(world_x, y, world_z, height) {
+                        let (top, top_block, sub_block) = *surface.get_or_insert_with(|| {
+                            let (top_block, sub_block) = self.surface_blocks(world_x, world_z, y + 1);
+                            (y + 1, top_block, sub_block)
+                        });
+                        if y + 8 < top {
                             BlockType::Stone // Tiefste Schicht: Stein für Felsen
-                        } else if y < height - 3 {
+                        } else if y + 3 < top {
                             sub_block // Mittlere Schicht: Dirt oder Sand
                         } else {
                             top_block // Oberste Schicht: Gras oder Sand
                         }
-                    } else if y < WATER_LEVEL {
-                        BlockType::Water // Wasser bis zur Wasserlinie
                     } else {
-                        BlockType::Air
+                        // Schneedecke auf den Gipfeln, nur auf der obersten Oberfläche
+                        let snow_cap = topmost
+                            && y > 0
+                            && self.is_solid(world_x, y - 1, world_z, height)
+                            && self.has_snow_cap(world_x, world_z, y);
+                        if surface.take().is_some() {
+                            topmost = false;
+                        }
+                        if snow_cap {
+                            BlockType::Snow
+                        } else if y < WATER_LEVEL {
+                            BlockType::Water // Wasser bis zur Wasserlinie
+                        } else {
+                            BlockType::Air
+                        }
                     };
 
                     chunk.set_block(x, y, z, block);
                 }
-            
             }
         }
 
         chunk
     }
 
+    /// Whether the block at (x, y, z) is solid ground, `height` being the
+    /// column's heightmap height. Density terrain adds the 3D noise within
+    /// `DENSITY_FALLOFF` of the heightmap surface.
+    pub fn is_solid(&self, x: i32, y: usize, z: i32, height: usize) -> bool {
+        match self.params.mode {
+            TerrainMode::Heightmap => y < height,
+            TerrainMode::Density => {
+                if y < SOLID_FLOOR {
+                    return true;
+                }
+                if y >= CHUNK_HEIGHT - 5 {
+                    return false;
+                }
+                let depth = (height as f64 - y as f64 - 0.5) / DENSITY_FALLOFF;
+                if depth.abs() >= 1.0 {
+                    return depth > 0.0;
+                }
+                let point = [
+                    x as f64 * DENSITY_FREQUENCY,
+                    y as f64 * DENSITY_VERTICAL_FREQUENCY,
+                    z as f64 * DENSITY_FREQUENCY,
+                ];
+                depth + self.density.get(point) > 0.0
+            }
+        }
+    }
+
+    /// Height of the topmost solid block of the column plus one, where
+    /// things stand on it; the heightmap height unless the terrain has
+    /// overhangs
+    pub fn surface_height(&self, x: i32, z: i32) -> usize {
+        let height = self.get_height(x as f64, z as f64);
+        if self.params.mode == TerrainMode::Heightmap {
+            return height;
+        }
+        (0..CHUNK_HEIGHT).rev().find(|&y| self.is_solid(x, y, z, height)).map_or(0, |y| y + 1)
+    }
+
+    /// Top layer and the layer under it for a column `height` blocks high:
+    /// grass and dirt on land, bare stone high in the mountains, sand along
+    /// the shores down to a couple of blocks under water, stone where
+    /// mountains rise steeply out of the water, and sand patched with gravel
+    /// and clay on the ocean floor
+    pub fn surface_blocks(&self, x: i32, z: i32, height: usize) -> (BlockType, BlockType) {
+        if height >= STONE_LINE && self.is_mountain(x, z) {
+            return (BlockType::Stone, BlockType::Stone);
+        }
+        if height > WATER_LEVEL + 2 {
+            return (BlockType::Grass, BlockType::Dirt);
+        }
+        let patch = self.surface.get([x as f64 * SURFACE_FREQUENCY, z as f64 * SURFACE_FREQUENCY]);
+        if height + SHORE_DEPTH >= WATER_LEVEL {
+            let d = SLOPE_DISTANCE;
+            let rise = [(d, 0), (-d, 0), (0, d), (0, -d)]
+                .iter()
+                .map(|(dx, dz)| self.get_height((x + dx) as f64, (z + dz) as f64).saturating_sub(height))
+                .max()
+                .unwrap_or(0);
+            return if rise as f64 + 2.0 * patch >= STEEP_SHORE {
+                (BlockType::Stone, BlockType::Stone)
+            } else {
+                (BlockType::Sand, BlockType::Sand)
+            };
+        }
+        if patch > PATCH_THRESHOLD {
+            (BlockType::Gravel, BlockType::Gravel)
+        } else if patch < -PATCH_THRESHOLD {
+            (BlockType::Clay, BlockType::Clay)
+        } else {
+            (BlockType::Sand, BlockType::Sand)
+        }
+    }
+
     pub fn place_trees(&self, world: &mut World, chunk_x: i32, chunk_z: i32) {
         // Wir iterieren über alle Blöcke DIESES Chunks, um mögliche Baumzentren zu finden.
         for x in 0..CHUNK_SIZE {
@@ -132,18 +405,20 @@ impl WorldGenerator {
                 let world_z = chunk_z * CHUNK_SIZE as i32 + z as i32;
                 
                 // 1. DETERMINISTISCHE PRÜFUNG: Soll hier ein Baum wachsen?
-                if self.should_generate_tree(world_x, world_z) {
+                // Villages keep their ground clear of trees, leaves included
+                if self.should_generate_tree(world_x, world_z) && !world.near_village(world_x, world_z, 2) {
                     
                     // --- KORREKTUR: Finde die tatsächliche Oberflächenhöhe im Chunk ---
                     let mut tree_height_y: usize = 0;
                     let mut found_surface = false;
                     
                     // Wir suchen von oben nach unten, um die y-Koordinate des Grasblocks zu finden.
-                    // Wir verwenden world.get_chunk, um den gerade generierten Chunk abzufragen.
-                    for y in (4..CHUNK_HEIGHT).rev() { 
+                    // Die Heightmap des Chunks sagt, wo die Säule endet; darüber ist nur Luft.
+                    let column_top = world.chunks.with(&(chunk_x, chunk_z), |c| c.height(x, z)).unwrap_or(0);
+                    for y in (4..column_top).rev() {
                         // Wir fragen den Block in dem Chunk ab, für den wir die Features generieren.
-                        let block = world.get_chunk(chunk_x, chunk_z)
-                                         .map_or(BlockType::Air, |c| c.get_block(x, y, z));
+                        let block = world.chunks.with(&(chunk_x, chunk_z), |c| c.get_block(x, y, z))
+                                         .unwrap_or(BlockType::Air);
                         
                         if block != BlockType::Air && block != BlockType::Water {
                             tree_height_y = y + 1; // Baum startet über diesem Block
@@ -162,10 +437,12 @@ impl WorldGenerator {
                     let top_y_i32 = top_y as i32;
                     let tree_height_y_i32 = tree_height_y as i32;
 
+                    // The tree's blocks are set together once collected
+                    let mut tree = Vec::new();
+
                     // 2. STAMM PLATZIEREN (Verwendet globale Koordinaten)
                     for y in tree_height_y_i32..top_y_i32 {
-                        // set_block_at kümmert sich um die Chunk-Auflösung
-                        world.set_block_at(world_x, y, world_z, BlockType::Wood);
+                        tree.push(((world_x, y, world_z), BlockType::Wood));
                     }
 
                     // 3. BLÄTTER PLATZIEREN (Verwendet globale Koordinaten)
@@ -195,14 +472,54 @@ impl WorldGenerator {
                                 // Nur setzen, wenn es Air, Leaves oder Water ist (oder ein anderer nicht-Stamm-Block)
                                 if let Some(current_block) = world.get_block_at(leaf_x, leaf_y, leaf_z) {
                                     if current_block != BlockType::Wood {
-                                        world.set_block_at(leaf_x, leaf_y, leaf_z, BlockType::Leaves);
+                                        tree.push(((leaf_x, leaf_y, leaf_z), BlockType::Leaves));
                                     }
                                 }
                             }
                         }
                     }
+                    world.set_blocks(tree);
                 }
             }
         }
     }
-}
\ No newline at end of file
+
+    /// Grow mushrooms, and now and then glowing fungus, on dark ground in the
+    /// chunk: on solid ground with something overhead, such as the floor
+    /// under a tree's canopy or a hollow in the rock
+    pub fn place_flora(&self, world: &mut World, chunk_x: i32, chunk_z: i32) {
+        let mut plants = Vec::new();
+        for x in 0..CHUNK_SIZE as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                let world_x = chunk_x * CHUNK_SIZE as i32 + x;
+                let world_z = chunk_z * CHUNK_SIZE as i32 + z;
+                let chance = self.surface.get([
+                    world_x as f64 * FLORA_FREQUENCY + 0.5,
+                    world_z as f64 * FLORA_FREQUENCY + 0.5,
+                ]);
+                if chance < MUSHROOM_THRESHOLD {
+                    continue;
+                }
+                let Some(y) = dark_ground(world, world_x, world_z) else {
+                    continue;
+                };
+                let plant = if chance > FUNGUS_THRESHOLD { BlockType::GlowingFungus } else { BlockType::Mushroom };
+                plants.push(((world_x, y, world_z), plant));
+            }
+        }
+        world.set_blocks(plants);
+    }
+}
+
+/// Highest air block of the column that has ground to grow on below it and
+/// blocks above it shading it from the sky
+fn dark_ground(world: &World, x: i32, z: i32) -> Option<i32> {
+    let (top, _) = world.highest_block(x, z)?;
+    (1..top).rev().find(|&y| {
+        let ground = world.get_block_at(x, y - 1, z).unwrap_or(BlockType::Air);
+        world.get_block_at(x, y, z) == Some(BlockType::Air)
+            && ground.is_full_cube()
+            && !ground.is_liquid()
+            && !matches!(ground, BlockType::Leaves | BlockType::Wood)
+    })
+}